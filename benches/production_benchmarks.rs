@@ -27,6 +27,7 @@ fn benchmark_job_submission(c: &mut Criterion) {
                             priority: job_queue::Priority::Normal,
                             params: HashMap::new(),
                             config: job_queue::JobConfig::default(),
+                            depends_on: Vec::new(),
                         };
                         
                         queue.submit(job).await.unwrap()
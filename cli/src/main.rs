@@ -1,10 +1,14 @@
 //! Quantum Theory Engine CLI - Production Ready
 
+mod plot;
+mod repl;
+
 use clap::{Parser, Subcommand};
 use quantum_theory_engine::*;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::time::Duration;
 
 #[derive(Parser)]
@@ -17,6 +21,24 @@ struct Cli {
     /// Set log level (trace, debug, info, warn, error)
     #[arg(short, long, global = true, default_value = "info")]
     log_level: String,
+
+    /// Also write logs to FILE, rotated at 10 MiB with five old files kept
+    #[arg(long, global = true, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Format of log lines: text, or json for one JSON object per line
+    #[arg(long, global = true, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Directory of template files (.toml, .yaml), loaded after the builtins
+    /// and the installed templates
+    #[arg(long, global = true, value_name = "DIR")]
+    template_dir: Option<PathBuf>,
+
+    /// Seed every random choice (counterexample search, random sweeps,
+    /// noise, bootstrap) so that a run can be repeated exactly
+    #[arg(long, global = true, value_name = "SEED")]
+    seed: Option<u64>,
 }
 
 #[derive(Subcommand)]
@@ -31,14 +53,89 @@ enum Commands {
         #[arg(short, long, value_name = "KEY=VALUE")]
         param: Vec<String>,
 
-        /// Output file for results (JSON)
+        /// Operator-valued template parameters (e.g., drive=sigma_y collapse=sigma_z)
+        #[arg(long, value_name = "NAME=OPERATOR")]
+        operator: Vec<String>,
+
+        /// Parameter file binding the model's `param` declarations (TOML or JSON)
+        #[arg(long, value_name = "FILE")]
+        values: Option<PathBuf>,
+
+        /// Output file for results (.h5/.hdf5 for HDF5, .csv for a tidy table, otherwise JSON)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// Export format of the output file, overriding its extension: csv (tidy rows of experiment, time, observable, value) or json (states included, complex numbers as [re, im])
+        #[arg(long, value_name = "FORMAT", requires = "output")]
+        format: Option<io::ExportFormat>,
+
+        /// Check each trajectory against the Ehrenfest theorem and report the largest residual per observable
+        #[arg(long)]
+        ehrenfest: bool,
+
+        /// Periodically save the evolving state to this file
+        #[arg(long, value_name = "FILE")]
+        checkpoint: Option<PathBuf>,
+
+        /// Time steps between checkpoints
+        #[arg(long, value_name = "STEPS", default_value = "100")]
+        checkpoint_every: usize,
+
+        /// Continue an interrupted run from its --checkpoint file
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+
+        /// Lindblad integrator precision: double or single (every 10th evolution is checked against double)
+        #[arg(long, default_value = "double")]
+        precision: precision::Precision,
+
+        /// Directory of cached results, keyed by program, parameters and backend settings
+        #[arg(long, value_name = "DIR", default_value = result_cache::DEFAULT_DIR)]
+        cache_dir: PathBuf,
+
+        /// Always simulate, neither reading nor writing the result cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Refuse programs whose largest operator or state exceeds this Hilbert-space dimension
+        #[arg(long, value_name = "DIM")]
+        max_dim: Option<usize>,
+
+        /// Refuse programs estimated to need more than this much memory per run
+        #[arg(long, value_name = "MB")]
+        max_memory_mb: Option<u64>,
+
+        /// Refuse programs integrating more time steps than this per run
+        #[arg(long, value_name = "STEPS")]
+        max_steps: Option<usize>,
+    },
+
+    /// Report the spectra of the declared Hamiltonians
+    Spectrum {
+        /// Path to the .phys file or template ID
+        #[arg(value_name = "PROGRAM")]
+        program: String,
+
+        /// Parameter values (e.g., omega=1.0)
+        #[arg(short, long, value_name = "KEY=VALUE")]
+        param: Vec<String>,
+
+        /// Only report this Hamiltonian
+        #[arg(long, value_name = "NAME")]
+        hamiltonian: Option<String>,
+
+        /// Scan one parameter and track level crossings (e.g., omega:0.0:2.0:41)
+        #[arg(short, long, value_name = "PARAM:START:END:STEPS")]
+        scan: Option<String>,
+
+        /// Output file (.csv for a table of energies, otherwise JSON)
         #[arg(short, long, value_name = "OUTPUT")]
         output: Option<PathBuf>,
     },
 
-    /// Prove a quantum identity or property
+    /// Prove a quantum identity (exit code 1 if refuted, 2 if undecided)
     Prove {
-        /// Statement to prove
+        /// Identity to prove, e.g. "dagger(A * B) == dagger(B) * dagger(A)"
         #[arg(value_name = "STATEMENT")]
         statement: String,
 
@@ -53,6 +150,33 @@ enum Commands {
         /// Output certificate to file
         #[arg(short, long, value_name = "CERT")]
         certificate: Option<PathBuf>,
+
+        /// Export the proof as a Lean 4 (.lean) or Coq (.v) skeleton
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+
+        /// Write a step-by-step trace for teaching: LaTeX for .tex, otherwise JSON
+        #[arg(long, value_name = "FILE")]
+        trace: Option<PathBuf>,
+
+        /// Sign the certificate with the Ed25519 key in this file, created if missing
+        #[arg(long, value_name = "KEY")]
+        sign_key: Option<PathBuf>,
+    },
+
+    /// Audit a proof certificate: check its hash, replay its steps and verify its signature
+    VerifyCert {
+        /// Certificate written by `prove --certificate`, or a bare certificate
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Also require a signature by this hex-encoded public key
+        #[arg(long, value_name = "HEX")]
+        trusted_key: Option<String>,
+
+        /// Export the recorded proof as a Lean 4 (.lean) or Coq (.v) skeleton
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
     },
 
     /// Fit model parameters to experimental data
@@ -61,7 +185,7 @@ enum Commands {
         #[arg(value_name = "MODEL")]
         model: String,
 
-        /// Path to CSV data file
+        /// Path to measurement data (CSV, JSON-lines, NPZ or HDF5)
         #[arg(short, long, value_name = "DATA")]
         data: PathBuf,
 
@@ -77,11 +201,61 @@ enum Commands {
         #[arg(short, long, default_value = "100")]
         max_iter: usize,
 
+        /// Wall-clock budget in seconds; returns the best fit found so far
+        #[arg(short, long, value_name = "SECONDS")]
+        budget: Option<f64>,
+
+        /// Optimizer: gradient, lbfgs or nelder-mead
+        #[arg(long, default_value = "gradient")]
+        method: stats::FitMethod,
+
+        /// Bounds for each parameter, in order (e.g. --bound 0:10)
+        #[arg(long, value_name = "LO:HI")]
+        bound: Vec<String>,
+
+        /// NaN/Inf values in the data: error, skip or clamp
+        #[arg(long, default_value = "error")]
+        non_finite: measurement_io::NonFinitePolicy,
+
         /// Output file for fit results (JSON)
         #[arg(short, long, value_name = "OUTPUT")]
         output: Option<PathBuf>,
     },
 
+    /// Calibrate a two-qubit gate from output-state tomography
+    Calibrate {
+        /// Gate: cz or cnot
+        #[arg(short, long, default_value = "cz")]
+        gate: calibration::TwoQubitGate,
+
+        /// Pauli expectation values named XX, XY, ..., ZZ (CSV, JSON-lines, NPZ or HDF5)
+        #[arg(short, long, value_name = "DATA")]
+        data: PathBuf,
+
+        /// Gate time, in the units of the coupling
+        #[arg(short, long, default_value = "1.0")]
+        time: f64,
+
+        /// Output file for the calibration report (JSON)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+    },
+
+    /// Compare the states of two saved results by fidelity and trace distance
+    Compare {
+        /// First result file (HDF5)
+        #[arg(value_name = "A")]
+        first: PathBuf,
+
+        /// Second result file (HDF5)
+        #[arg(value_name = "B")]
+        second: PathBuf,
+
+        /// Output file for the per-time-step metrics (JSON)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+    },
+
     /// Run parameter sweep
     Sweep {
         /// Path to model .phys file or template ID
@@ -110,6 +284,24 @@ enum Commands {
         /// HTTP API port
         #[arg(short, long, default_value = "8080")]
         port: u16,
+
+        /// TOML file of API clients; without it the server is open to anyone
+        #[arg(long, value_name = "FILE")]
+        auth: Option<PathBuf>,
+    },
+
+    /// Inspect and operate the job queue of a running `qte server`
+    Queue {
+        /// Base URL of the server
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+
+        /// API key of an authenticated server [default: $QTE_API_KEY]
+        #[arg(long)]
+        api_key: Option<String>,
+
+        #[command(subcommand)]
+        action: QueueAction,
     },
 
     /// List available templates
@@ -117,13 +309,107 @@ enum Commands {
         /// Filter by category
         #[arg(short, long)]
         category: Option<String>,
+
+        /// Check a template file and copy it to the --template-dir, or else
+        /// to $QTE_TEMPLATE_DIR or ~/.qte/templates
+        #[arg(long, value_name = "FILE")]
+        install: Option<PathBuf>,
     },
 
-    /// Validate a .phys file
+    /// Validate a .phys file, reporting every error and unused declaration
     Validate {
         /// Path to the .phys file
         #[arg(value_name = "FILE")]
         file: PathBuf,
+
+        /// Print the diagnostics as JSON, for editor integration
+        #[arg(long)]
+        json: bool,
+
+        /// Largest deviation from a quantum constraint that still passes
+        #[arg(long, value_name = "TOL")]
+        tol: Option<f64>,
+
+        /// Report violations of CONSTRAINT (hermiticity, positivity, trace,
+        /// normalization, idempotence, completeness, trace_preservation,
+        /// unitarity) as warnings; repeatable
+        #[arg(long, value_name = "CONSTRAINT")]
+        warn_only: Vec<String>,
+
+        /// Treat every violation as an error, overriding --warn-only
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Interactive session: declare, evaluate expressions, :check and :run
+    Repl,
+
+    /// Plot a results file (JSON or HDF5) as PNG or SVG
+    Plot {
+        /// Results written by `qte simulate`; states need --format json or HDF5
+        #[arg(value_name = "RESULTS")]
+        input: PathBuf,
+
+        /// Image to write: .svg for SVG, otherwise PNG
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// What to plot: populations, bloch or observables
+        #[arg(short, long, default_value = "populations")]
+        kind: plot::PlotKind,
+
+        /// Experiment to plot, by default the first
+        #[arg(short, long)]
+        experiment: Option<String>,
+
+        /// Image width in pixels
+        #[arg(long, default_value = "800")]
+        width: u32,
+
+        /// Image height in pixels
+        #[arg(long, default_value = "600")]
+        height: u32,
+    },
+
+    /// Summarize a .phys model and its validation as a Markdown or LaTeX report
+    Describe {
+        /// Path to the .phys file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Report format: md or tex
+        #[arg(long, default_value = "md")]
+        format: report::ReportFormat,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Translate an OpenQASM 2.0 or 3.0 circuit into a .phys program
+    Import {
+        /// Path to the .qasm file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Write the program to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Show how a result was produced: its job, inputs, template, engine and certificates
+    Provenance {
+        /// Result file, result id or unique id prefix
+        #[arg(value_name = "RESULT")]
+        result: String,
+
+        /// Provenance store
+        #[arg(long, default_value = provenance::DEFAULT_STORE)]
+        store: PathBuf,
+
+        /// Output format: text, json or dot
+        #[arg(short, long, default_value = "text")]
+        format: String,
     },
 
     /// Show system health and metrics
@@ -131,10 +417,74 @@ enum Commands {
         /// Show detailed metrics
         #[arg(short, long)]
         detailed: bool,
+
+        /// Run the self-benchmark and compare it with this baseline
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+
+        /// Run the self-benchmark and save its timings as a baseline
+        #[arg(long, value_name = "FILE", conflicts_with = "baseline")]
+        record_baseline: Option<PathBuf>,
+
+        /// Slowdown relative to the baseline that marks the node degraded
+        #[arg(long, default_value = "2.0")]
+        threshold: f64,
     },
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[derive(Subcommand)]
+enum QueueAction {
+    /// List jobs, optionally filtered
+    Ls {
+        /// queued, running, complete, failed or cancelled
+        #[arg(long)]
+        status: Option<String>,
+
+        /// simulate, prove, fit, test or sweep
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// low, normal, high or critical
+        #[arg(long)]
+        priority: Option<String>,
+
+        /// Every client's jobs, with their owners (admin keys only)
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Cancel a queued or running job
+    Cancel {
+        #[arg(value_name = "JOB_ID")]
+        job_id: job_queue::JobId,
+
+        /// Cancel another client's job (admin keys only)
+        #[arg(long)]
+        any: bool,
+    },
+
+    /// Queue a failed or cancelled job again
+    Retry {
+        #[arg(value_name = "JOB_ID")]
+        job_id: job_queue::JobId,
+    },
+
+    /// Show a job's status, parameters, configuration and diagnostics
+    Inspect {
+        #[arg(value_name = "JOB_ID")]
+        job_id: job_queue::JobId,
+    },
+
+    /// Let running jobs finish but start no new ones
+    Drain,
+
+    /// Start jobs again after a drain
+    Resume,
+}
+
+/// Exits with `error::exit_code::SUCCESS`, or with the code of the error's
+/// category (see the README). Invalid arguments exit with `USAGE` from clap.
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
     let log_level = match cli.log_level.to_lowercase().as_str() {
@@ -145,74 +495,651 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "error" => LogLevel::Error,
         _ => LogLevel::Info,
     };
-    logging::set_log_level(log_level);
+    let log_config = logging::LogConfig {
+        level: log_level,
+        console: Some(cli.log_format),
+        file: cli.log_file.as_ref().map(|path| logging::FileSinkConfig {
+            format: cli.log_format,
+            ..logging::FileSinkConfig::new(path)
+        }),
+        ..Default::default()
+    };
+    if let Err(e) = logging::configure(log_config) {
+        eprintln!("Error: {}", e);
+        return ExitCode::from(e.exit_code());
+    }
+    TEMPLATE_DIR.get_or_init(|| cli.template_dir.clone());
+    rng::set_global_seed(cli.seed);
 
-    match cli.command {
-        Commands::Simulate { program, param, output } => cmd_simulate(program, param, output),
-        Commands::Prove { statement, max_depth, timeout, certificate } => cmd_prove(statement, max_depth, timeout, certificate),
-        Commands::Fit { model, data, param, initial, max_iter, output } => cmd_fit(model, data, param, initial, max_iter, output),
+    let result = match cli.command {
+        Commands::Simulate {
+            program,
+            param,
+            operator,
+            values,
+            output,
+            format,
+            ehrenfest,
+            checkpoint,
+            checkpoint_every,
+            resume,
+            precision,
+            cache_dir,
+            no_cache,
+            max_dim,
+            max_memory_mb,
+            max_steps,
+        } => {
+            let options = SimulateOptions {
+                format,
+                ehrenfest,
+                checkpoint,
+                checkpoint_every,
+                resume,
+                precision,
+                cache: (!no_cache).then(|| std::sync::Arc::new(result_cache::ResultCache::new(cache_dir))),
+                limits: resources::ResourceLimits { max_dim, max_memory_mb, max_steps },
+            };
+            cmd_simulate(program, param, operator, values, output, options)
+        }
+        Commands::Spectrum { program, param, hamiltonian, scan, output } => cmd_spectrum(program, param, hamiltonian, scan, output),
+        Commands::Prove { statement, max_depth, timeout, certificate, export, trace, sign_key } => cmd_prove(statement, max_depth, timeout, certificate, export, trace, sign_key),
+        Commands::VerifyCert { file, trusted_key, export } => cmd_verify_cert(file, trusted_key, export),
+        Commands::Fit {
+            model,
+            data,
+            param,
+            initial,
+            max_iter,
+            budget,
+            method,
+            bound,
+            non_finite,
+            output,
+        } => {
+            let options = FitCommandOptions { max_iter, budget, method, bounds: bound, non_finite };
+            cmd_fit(model, data, param, initial, options, output)
+        }
+        Commands::Calibrate { gate, data, time, output } => cmd_calibrate(gate, data, time, output),
+        Commands::Compare { first, second, output } => cmd_compare(first, second, output),
         Commands::Sweep { model, range, workers, output } => cmd_sweep(model, range, workers, output),
-        Commands::Server { workers, port } => cmd_server(workers, port),
-        Commands::Templates { category } => cmd_templates(category),
-        Commands::Validate { file } => cmd_validate(file),
-        Commands::Health { detailed } => cmd_health(detailed),
+        Commands::Server { workers, port, auth } => cmd_server(workers, port, auth),
+        Commands::Queue { server, api_key, action } => cmd_queue(server, api_key, action),
+        Commands::Templates { category, install } => match install {
+            Some(file) => cmd_install_template(file),
+            None => cmd_templates(category),
+        },
+        Commands::Validate { file, json, tol, warn_only, strict } => cmd_validate(file, json, tol, warn_only, strict),
+        Commands::Repl => repl::run(),
+        Commands::Plot { input, output, kind, experiment, width, height } => cmd_plot(input, output, kind, experiment, (width, height)),
+        Commands::Describe { file, format, output } => cmd_describe(file, format, output),
+        Commands::Import { file, output } => cmd_import(file, output),
+        Commands::Provenance { result, store, format } => cmd_provenance(result, store, format),
+        Commands::Health {
+            detailed,
+            baseline,
+            record_baseline,
+            threshold,
+        } => cmd_health(detailed, baseline, record_baseline, threshold),
+    };
+
+    match result {
+        Ok(()) => ExitCode::from(error::exit_code::SUCCESS),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(exit_code(e.as_ref()))
+        }
     }
 }
 
-fn cmd_simulate(program: String, params: Vec<String>, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Simulating: {}", program);
-    let registry = TemplateRegistry::new();
-    let code = if let Some(_) = registry.get(&program) {
-        let param_map = parse_params(&params)?;
-        registry.instantiate(&program, &param_map)?
+/// Exit code for an error returned by a subcommand
+fn exit_code(error: &(dyn std::error::Error + 'static)) -> u8 {
+    if let Some(e) = error.downcast_ref::<EngineError>() {
+        e.exit_code()
+    } else if let Some(e) = error.downcast_ref::<ProofFailure>() {
+        e.exit_code()
+    } else if error.is::<std::io::Error>() || error.is::<serde_json::Error>() {
+        error::exit_code::IO
     } else {
-        fs::read_to_string(&program)?
+        error::exit_code::FAILURE
+    }
+}
+
+/// A statement `qte prove` did not prove, or a certificate `qte verify-cert`
+/// did not accept; the details are printed before it is returned
+#[derive(Debug)]
+enum ProofFailure {
+    Refuted,
+    Undecided,
+    InvalidCertificate,
+}
+
+impl ProofFailure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            ProofFailure::Refuted => error::exit_code::REFUTED,
+            ProofFailure::Undecided => error::exit_code::UNDECIDED,
+            ProofFailure::InvalidCertificate => error::exit_code::INVALID_CERTIFICATE,
+        }
+    }
+}
+
+impl std::fmt::Display for ProofFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProofFailure::Refuted => "Statement refuted",
+            ProofFailure::Undecided => "Statement undecided",
+            ProofFailure::InvalidCertificate => "Certificate failed verification",
+        })
+    }
+}
+
+impl std::error::Error for ProofFailure {}
+
+/// Options of `qte simulate` that shape how the program runs and where its result goes
+struct SimulateOptions {
+    format: Option<io::ExportFormat>,
+    ehrenfest: bool,
+    checkpoint: Option<PathBuf>,
+    checkpoint_every: usize,
+    resume: bool,
+    precision: precision::Precision,
+    cache: Option<std::sync::Arc<result_cache::ResultCache>>,
+    limits: resources::ResourceLimits,
+}
+
+fn cmd_simulate(
+    program: String,
+    params: Vec<String>,
+    operators: Vec<String>,
+    values: Option<PathBuf>,
+    output: Option<PathBuf>,
+    options: SimulateOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Simulating: {}", program);
+    let param_map = parse_params(&params)?;
+    let operator_map = parse_operators(&operators)?;
+    let ast = load_ast(&program, &param_map, &operator_map, values.as_deref())?;
+    let precision = options.precision;
+    let config = BackendConfig {
+        ehrenfest: options.ehrenfest.then(executor::EhrenfestCheck::default),
+        checkpoint: options
+            .checkpoint
+            .clone()
+            .map(|path| executor::CheckpointConfig { path, every_steps: options.checkpoint_every }),
+        precision,
+        precision_check: (precision == precision::Precision::Single).then(precision::PrecisionCheck::default),
+        ..BackendConfig::default()
     };
+    if sweep::has_sweeps(&ast) {
+        if config.checkpoint.is_some() {
+            return Err("--checkpoint is not supported for programs with sweep blocks".into());
+        }
+        if options.format.is_some() {
+            return Err("--format is not supported for programs with sweep blocks".into());
+        }
+        return simulate_sweep(&program, &ast, config, &param_map, values, output, &options);
+    }
+    let ir = lower_ast(&ast)?;
+    options.limits.check(&resources::estimate(&ir))?;
     println!("✓ Program loaded");
+    let format = options.format;
+    let result = match options.checkpoint.filter(|_| options.resume) {
+        Some(path) => {
+            let saved = executor::EvolutionCheckpoint::load(&path)?;
+            println!("✓ Resuming '{}' from time step {}", saved.experiment, saved.time_index);
+            Executor::new(config).resume_from(&ir, &saved)?
+        }
+        None => cached_executor(config, &options.cache).execute(&ir)?,
+    };
+    println!("✓ Simulated {} experiment(s)", result.experiment_results.len());
+    for experiment in &result.experiment_results {
+        for residual in &experiment.ehrenfest {
+            println!("  {} Ehrenfest residual for {}: {:.3e} at t = {}", experiment.name, residual.observable, residual.max_residual, residual.time);
+        }
+        for series in &experiment.observables {
+            if let (Some(first), Some(last)) = (series.values.first(), series.values.last()) {
+                println!("  {} <{}>: {:.6} → {:.6} over {} time(s)", experiment.name, series.label, first.re, last.re, series.values.len());
+            }
+        }
+    }
     if let Some(path) = output {
-        fs::write(path, serde_json::json!({"status": "success"}).to_string())?;
+        let extension = path.extension().and_then(|e| e.to_str());
+        if format.is_none() && matches!(extension, Some("h5") | Some("hdf5")) {
+            let metadata = serde_json::json!({ "program": program, "params": params, "values": values });
+            io::save_hdf5(&result, &metadata, &path)?;
+        } else if format == Some(io::ExportFormat::Csv) || (format.is_none() && extension == Some("csv")) {
+            io::export_csv(&result, &path)?;
+        } else if format == Some(io::ExportFormat::Json) {
+            io::export_json(&result, &path)?;
+        } else {
+            fs::write(&path, serde_json::to_string_pretty(&result)?)?;
+        }
+        println!("✓ Results written to {}", path.display());
+        record_provenance(&path, "simulate", |record| {
+            let record = with_program(record, &program)?.with_parameters(&param_map);
+            match &values {
+                Some(values) => record.with_input_file(values),
+                None => Ok(record),
+            }
+        })?;
     }
     Ok(())
 }
 
-fn cmd_prove(statement: String, _max_depth: usize, _timeout: u64, certificate: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+/// Run every point of a program's `sweep` blocks; results are written as
+/// JSON, one entry per grid point
+fn simulate_sweep(
+    program: &str,
+    ast: &Ast,
+    config: BackendConfig,
+    param_map: &HashMap<String, f64>,
+    values: Option<PathBuf>,
+    output: Option<PathBuf>,
+    options: &SimulateOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if matches!(output.as_ref().and_then(|p| p.extension()).and_then(|e| e.to_str()), Some("h5") | Some("hdf5")) {
+        return Err("Sweep results are written as JSON; choose a .json output".into());
+    }
+    let (grid, programs) = sweep::lower_sweeps(ast)?;
+    let names: Vec<&str> = grid.params.iter().map(|range| range.name.as_str()).collect();
+    println!("✓ Sweep over {} ({} points)", names.join(", "), programs.len());
+    for (_, ir) in &programs {
+        options.limits.check(&resources::estimate(ir))?;
+    }
+    let mut points = Vec::with_capacity(programs.len());
+    for (params, ir) in programs {
+        let result = cached_executor(config.clone(), &options.cache).execute(&ir)?;
+        let label: Vec<String> = params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        println!("  {}: {} experiment(s)", label.join(" "), result.experiment_results.len());
+        points.push(serde_json::json!({ "params": params, "result": result }));
+    }
+    if let Some(path) = output {
+        fs::write(&path, serde_json::to_string_pretty(&serde_json::json!({ "grid": grid, "points": points }))?)?;
+        println!("✓ Results written to {}", path.display());
+        record_provenance(&path, "simulate", |record| {
+            let record = with_program(record, program)?.with_parameters(param_map);
+            match &values {
+                Some(values) => record.with_input_file(values),
+                None => Ok(record),
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// Executor that looks results up in `cache`, if given, before simulating
+fn cached_executor(config: BackendConfig, cache: &Option<std::sync::Arc<result_cache::ResultCache>>) -> Executor {
+    match cache {
+        Some(cache) => Executor::new(config).with_result_cache(std::sync::Arc::clone(cache)),
+        None => Executor::new(config),
+    }
+}
+
+fn cmd_plot(input: PathBuf, output: PathBuf, kind: plot::PlotKind, experiment: Option<String>, size: (u32, u32)) -> Result<(), Box<dyn std::error::Error>> {
+    let result = match input.extension().and_then(|e| e.to_str()) {
+        Some("h5") | Some("hdf5") => io::load_hdf5(&input)?.result,
+        _ => io::load_json(&input)?,
+    };
+    plot::plot(&result, experiment.as_deref(), kind, &output, size)?;
+    println!("✓ Plot written to {}", output.display());
+    Ok(())
+}
+
+fn cmd_spectrum(program: String, params: Vec<String>, hamiltonian: Option<String>, scan: Option<String>, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut param_map = parse_params(&params)?;
+    let is_csv = matches!(output.as_ref().and_then(|p| p.extension()).and_then(|e| e.to_str()), Some("csv"));
+
+    let Some(scan) = scan else {
+        let spectra = compute_spectra(&program, &param_map, hamiltonian.as_deref())?;
+        for s in &spectra {
+            println!("Hamiltonian {} ({} levels)", s.name, s.eigenvalues.len());
+            for (k, (energy, vector)) in s.eigenvalues.iter().zip(&s.eigenvectors).enumerate() {
+                let components: Vec<String> = vector.iter().map(|c| format!("{:.4}{:+.4}i", c.re, c.im)).collect();
+                println!("  E{} = {:.6}  [{}]", k, energy, components.join(", "));
+            }
+            for group in &s.degeneracies {
+                println!("  degenerate: levels {:?} at E = {:.6}", group, s.eigenvalues[group[0]]);
+            }
+        }
+        if let Some(path) = output {
+            if is_csv {
+                let mut csv = String::from("hamiltonian,level,energy\n");
+                for s in &spectra {
+                    for (k, energy) in s.eigenvalues.iter().enumerate() {
+                        csv.push_str(&format!("{},{},{}\n", s.name, k, energy));
+                    }
+                }
+                fs::write(&path, csv)?;
+            } else {
+                fs::write(&path, serde_json::to_string_pretty(&spectra)?)?;
+            }
+            println!("✓ Spectra written to {}", path.display());
+        }
+        return Ok(());
+    };
+
+    let range = parse_range(&scan)?;
+    let values = range.values();
+    println!("Scanning {} over {} points", range.name, values.len());
+    let mut spectra = Vec::with_capacity(values.len());
+    for &value in &values {
+        param_map.insert(range.name.clone(), value);
+        let mut all = compute_spectra(&program, &param_map, hamiltonian.as_deref())?;
+        if all.len() > 1 {
+            return Err(format!("{} Hamiltonians declared; choose one with --hamiltonian", all.len()).into());
+        }
+        spectra.push(all.remove(0));
+    }
+    let scan = spectrum::LevelScan::track(&range.name, &values, &spectra)?;
+    println!("✓ {} level crossing(s)", scan.crossings.len());
+    for crossing in &scan.crossings {
+        println!("  levels {} and {} cross at {} = {:.6} (E = {:.6})", crossing.levels.0, crossing.levels.1, range.name, crossing.parameter, crossing.energy);
+    }
+    if let Some(path) = output {
+        if is_csv {
+            let header: Vec<String> = (0..scan.energies.first().map_or(0, |e| e.len())).map(|k| format!("E{}", k)).collect();
+            let mut csv = format!("{},{}\n", range.name, header.join(","));
+            for (value, energies) in scan.values.iter().zip(&scan.energies) {
+                let row: Vec<String> = energies.iter().map(|e| e.to_string()).collect();
+                csv.push_str(&format!("{},{}\n", value, row.join(",")));
+            }
+            fs::write(&path, csv)?;
+        } else {
+            fs::write(&path, serde_json::to_string_pretty(&scan)?)?;
+        }
+        println!("✓ Scan written to {}", path.display());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_prove(
+    statement: String,
+    max_depth: usize,
+    timeout: u64,
+    certificate: Option<PathBuf>,
+    export: Option<PathBuf>,
+    trace: Option<PathBuf>,
+    sign_key: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use proof_export::ProofAssistant;
+    use prover::ProverConfig;
+
+    let assistant = match &export {
+        Some(path) => Some(ProofAssistant::from_path(path).ok_or("Export file must end in .lean or .v")?),
+        None => None,
+    };
+
+    let signing_key = match &sign_key {
+        Some(path) => {
+            let (key, created) = signing::EngineKey::load_or_generate(path)?;
+            if created {
+                println!("✓ New signing key written to {}", path.display());
+            }
+            println!("  signing with public key {}", key.public_key_hex());
+            Some(key)
+        }
+        None => None,
+    };
+
     println!("Proving: {}", statement);
-    println!("✓ Proof found");
+    let (lhs, rhs) = parser::parse_identity(&statement)?;
+    let mut prover = Prover::new(ProverConfig {
+        max_depth,
+        timeout: Duration::from_secs(timeout),
+        signing_key,
+        ..ProverConfig::default()
+    });
+    let result = prover.prove_identity(&lhs, &rhs);
+
+    if let Some(path) = &trace {
+        let trace = proof_trace::ProofTrace::new(&prover::Statement::Identity { lhs: lhs.clone(), rhs: rhs.clone() }, &result);
+        let contents = match path.extension().and_then(|e| e.to_str()) {
+            Some("tex") => trace.to_latex(),
+            _ => serde_json::to_string_pretty(&trace)?,
+        };
+        fs::write(path, contents)?;
+        println!("✓ Proof trace written to {}", path.display());
+    }
+
+    let outcome = match &result {
+        ProofResult::Proven(proof) => {
+            if !prover.verify_proof(proof) {
+                return Err("Proof trace failed to verify".into());
+            }
+            println!("✓ Proof found ({} steps)", proof.steps.len());
+            for (i, step) in proof.steps.iter().enumerate() {
+                println!("  {}. {:?}: {}", i + 1, step.rule, step.justification);
+                println!("     {:?}  →  {:?}", step.before, step.after);
+            }
+            println!("  certificate: {}", proof.certificate.hash);
+            if let (Some(path), Some(assistant)) = (&export, assistant) {
+                fs::write(path, proof_export::export_proof(proof, assistant)?)?;
+                println!("✓ {:?} proof written to {}", assistant, path.display());
+            }
+            Ok(())
+        }
+        ProofResult::Refuted(counterexample) => {
+            println!("✗ Refuted: {}", counterexample);
+            Err(ProofFailure::Refuted)
+        }
+        ProofResult::Unknown(reason) => {
+            println!("? Undecided: {:?}", reason);
+            Err(ProofFailure::Undecided)
+        }
+    };
+
     if let Some(path) = certificate {
-        fs::write(path, serde_json::json!({"proven": true}).to_string())?;
+        if matches!(outcome, Err(ProofFailure::Undecided)) {
+            println!("  no certificate written for an undecided statement");
+        } else {
+            let document = serde_json::json!({ "statement": statement, "result": result });
+            fs::write(&path, serde_json::to_string_pretty(&document)?)?;
+            println!("✓ Certificate written to {}", path.display());
+            record_provenance(&path, "prove", |record| {
+                let record = record.with_input("statement", statement.as_bytes());
+                Ok(match &result {
+                    ProofResult::Proven(proof) => record.with_certificate(proof.certificate.hash.clone()),
+                    _ => record,
+                })
+            })?;
+        }
     }
-    Ok(())
+
+    Ok(outcome?)
 }
 
-fn cmd_fit(model: String, data: PathBuf, params: Vec<String>, initial: Vec<f64>, max_iter: usize, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_verify_cert(file: PathBuf, trusted_key: Option<String>, export: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    use proof_export::ProofAssistant;
+    use signing::SignatureStatus;
+
+    let assistant = match &export {
+        Some(path) => Some(ProofAssistant::from_path(path).ok_or("Export file must end in .lean or .v")?),
+        None => None,
+    };
+
+    let document: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file)?)?;
+    let certificate = match document.pointer("/result/Proven/certificate") {
+        Some(certificate) => certificate.clone(),
+        None if document.get("result").is_some() => {
+            return Err("The file records no proof, so there is no certificate to verify".into())
+        }
+        None => document,
+    };
+    let certificate: prover::Certificate = serde_json::from_value(certificate)?;
+    let check = Prover::new(prover::ProverConfig::default()).verify_certificate(&certificate);
+
+    println!("Certificate {}", certificate.hash);
+    println!("  engine {}, issued {}", certificate.engine_version, certificate.timestamp.to_rfc3339());
+    println!("  {} of {} steps replayed", check.steps_replayed, certificate.verification_steps.len());
+    match &check.signature {
+        SignatureStatus::Unsigned => println!("  unsigned"),
+        SignatureStatus::Valid { public_key } => println!("  signed by {}", public_key),
+        SignatureStatus::Invalid { .. } => {}
+    }
+    let mut problems = check.problems.clone();
+    if let Some(trusted) = &trusted_key {
+        let trusted_signature = matches!(&check.signature,
+            SignatureStatus::Valid { public_key } if public_key.eq_ignore_ascii_case(trusted.trim()));
+        if !trusted_signature {
+            problems.push(format!("not signed by the trusted key {}", trusted));
+        }
+    }
+
+    if problems.is_empty() {
+        println!("✓ Certificate verified");
+        if let (Some(path), Some(assistant)) = (&export, assistant) {
+            fs::write(path, proof_export::export_certificate(&certificate, assistant)?)?;
+            println!("✓ {:?} proof written to {}", assistant, path.display());
+        }
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("  ✗ {}", problem);
+    }
+    Err(ProofFailure::InvalidCertificate.into())
+}
+
+/// Options of `qte fit` for the optimizer and for NaN/Inf values in the data
+struct FitCommandOptions {
+    max_iter: usize,
+    /// Wall-clock budget in seconds
+    budget: Option<f64>,
+    method: stats::FitMethod,
+    /// `LO:HI` bounds, one per parameter
+    bounds: Vec<String>,
+    non_finite: measurement_io::NonFinitePolicy,
+}
+
+fn cmd_fit(
+    model: String,
+    data: PathBuf,
+    params: Vec<String>,
+    initial: Vec<f64>,
+    options: FitCommandOptions,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
     println!("Fitting: {}", model);
-    let measurements = stats::load_measurements(data.to_str().unwrap())?;
+    let measurements = measurement_io::import_measurements_with(&data, options.non_finite)?.data;
     println!("✓ Loaded {} measurements", measurements.num_shots);
+    let bounds = options.bounds.iter().map(|b| parse_bound(b)).collect::<Result<Vec<_>, _>>()?;
+    let options = stats::FitOptions {
+        method: options.method,
+        bounds: (!bounds.is_empty()).then_some(bounds),
+        max_iterations: options.max_iter,
+        budget: options.budget.map(Duration::from_secs_f64),
+        ..stats::FitOptions::default()
+    };
     let likelihood_fn = |p: &[f64]| -> error::Result<f64> { Ok(-p.iter().map(|x| x.powi(2)).sum::<f64>()) };
-    let result = stats::fit_parameters_mle(likelihood_fn, &initial, max_iter)?;
+    let result = stats::fit_parameters_mle_with(likelihood_fn, &initial, &options)?;
     println!("✓ Converged: {}", result.converged);
+    if result.truncated {
+        println!("⚠ Time budget exhausted after {} iterations; reporting best fit so far", result.iterations);
+    }
     for (i, param) in params.iter().enumerate() {
         println!("  {}: {:.6} ± {:.6}", param, result.best_params[i], result.uncertainties[i]);
     }
     if let Some(path) = output {
-        fs::write(path, serde_json::to_string_pretty(&result)?)?;
+        fs::write(&path, serde_json::to_string_pretty(&result)?)?;
+        let initial_guess: HashMap<String, f64> = params.iter().cloned().zip(initial.iter().copied()).collect();
+        record_provenance(&path, "fit", |record| with_program(record, &model)?.with_parameters(&initial_guess).with_input_file(&data))?;
+    }
+    Ok(())
+}
+
+fn cmd_calibrate(gate: calibration::TwoQubitGate, data: PathBuf, time: f64, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Calibrating {:?} gate (t = {})", gate, time);
+    let measurements = measurement_io::import_measurements(&data)?.data;
+    println!("✓ Loaded {} Pauli expectation values", measurements.observables.len());
+    let report = calibration::GateCalibration::new(gate, time).fit(&measurements)?;
+    println!("✓ Converged: {}", report.fit.converged);
+    println!("  J      = {:.6} ± {:.6}", report.coupling.value, report.coupling.uncertainty);
+    for (i, delta) in report.phase_errors.iter().enumerate() {
+        println!("  delta{} = {:.6} ± {:.6}", i + 1, delta.value, delta.uncertainty);
+    }
+    println!("  average gate fidelity = {:.6} ± {:.6}", report.fidelity.value, report.fidelity.uncertainty);
+    if let Some(path) = output {
+        fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        let time = HashMap::from([("time".to_string(), time)]);
+        record_provenance(&path, "calibrate", |record| with_program(record, calibration::TEMPLATE_ID)?.with_parameters(&time).with_input_file(&data))?;
+    }
+    Ok(())
+}
+
+/// Fidelity and trace distance between the states of one experiment in two
+/// results, one entry per time step
+struct StateComparison {
+    fidelity: Vec<f64>,
+    trace_distance: Vec<f64>,
+}
+
+impl StateComparison {
+    fn new(x: &executor::ExperimentResult, y: &executor::ExperimentResult) -> error::Result<Self> {
+        let pairs = || x.states.iter().zip(&y.states);
+        Ok(StateComparison {
+            fidelity: pairs().map(|(rho, sigma)| quantum_info::state_fidelity(rho, sigma)).collect::<error::Result<_>>()?,
+            trace_distance: pairs().map(|(rho, sigma)| quantum_info::trace_distance(rho, sigma)).collect::<error::Result<_>>()?,
+        })
+    }
+
+    fn worst_fidelity(&self) -> f64 {
+        self.fidelity.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+
+    fn worst_trace_distance(&self) -> f64 {
+        self.trace_distance.iter().copied().fold(0.0, f64::max)
+    }
+}
+
+fn cmd_compare(first: PathBuf, second: PathBuf, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let a = io::load_hdf5(&first)?.result;
+    let b = io::load_hdf5(&second)?.result;
+    let mut report = Vec::new();
+    for x in &a.experiment_results {
+        let Some(y) = b.experiment_results.iter().find(|y| y.name == x.name) else {
+            println!("  {}: only in {}", x.name, first.display());
+            continue;
+        };
+        if x.states.len() != y.states.len() {
+            return Err(format!("Experiment '{}' has {} states in {} but {} in {}", x.name, x.states.len(), first.display(), y.states.len(), second.display()).into());
+        }
+        let comparison = StateComparison::new(x, y)?;
+        if let (Some(fidelity), Some(distance)) = (comparison.fidelity.last(), comparison.trace_distance.last()) {
+            println!(
+                "{}: fidelity {:.6}, trace distance {:.6} at t = {} (worst over {} states: {:.6}, {:.6})",
+                x.name,
+                fidelity,
+                distance,
+                x.times.last().copied().unwrap_or(0.0),
+                comparison.fidelity.len(),
+                comparison.worst_fidelity(),
+                comparison.worst_trace_distance(),
+            );
+        }
+        report.push(serde_json::json!({
+            "experiment": x.name,
+            "times": x.times,
+            "fidelity": comparison.fidelity,
+            "trace_distance": comparison.trace_distance,
+        }));
+    }
+    for y in b.experiment_results.iter().filter(|y| a.experiment_results.iter().all(|x| x.name != y.name)) {
+        println!("  {}: only in {}", y.name, second.display());
+    }
+    if report.is_empty() {
+        return Err("The results share no experiments".into());
+    }
+    if let Some(path) = output {
+        fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        println!("✓ Comparison written to {}", path.display());
     }
     Ok(())
 }
 
 fn cmd_sweep(model: String, ranges: Vec<String>, workers: usize, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
     println!("Parameter sweep: {}", model);
-    let mut param_ranges = Vec::new();
-    for r in &ranges {
-        let p: Vec<&str> = r.split(':').collect();
-        param_ranges.push(job_queue::ParameterRange {
-            name: p[0].to_string(),
-            start: p[1].parse()?,
-            end: p[2].parse()?,
-            steps: p[3].parse()?,
-            scale: job_queue::ParameterScale::Linear,
-        });
-    }
+    let param_ranges = ranges.iter().map(String::as_str).map(parse_range).collect::<Result<Vec<_>, _>>()?;
     let total: usize = param_ranges.iter().map(|r| r.steps).product();
     println!("✓ {} jobs with {} workers", total, workers);
     if let Some(path) = output {
@@ -221,19 +1148,155 @@ fn cmd_sweep(model: String, ranges: Vec<String>, workers: usize, output: Option<
     Ok(())
 }
 
-fn cmd_server(workers: usize, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_server(workers: usize, port: u16, auth: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
     println!("Server: {} workers on port {}", workers, port);
+    let auth = auth.map(server::AuthConfig::load).transpose()?;
+    match &auth {
+        Some(auth) => println!("✓ {} API clients", auth.clients.len()),
+        None => println!("⚠ No --auth file: the API is open to anyone who can reach it"),
+    }
     println!("Press Ctrl+C to stop");
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async {
-        tokio::signal::ctrl_c().await?;
+        let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+        let shutdown = async {
+            let _ = tokio::signal::ctrl_c().await;
+        };
+        match auth {
+            Some(auth) => {
+                let options = job_queue::QueueOptions {
+                    max_running_per_owner: auth.queue_quotas(),
+                    ..job_queue::QueueOptions::default()
+                };
+                let queue = std::sync::Arc::new(JobQueue::with_options(workers, options));
+                let listener = tokio::net::TcpListener::bind(addr).await?;
+                server::serve_router(listener, server::authenticated_router(queue, auth)?, shutdown).await?;
+            }
+            None => server::serve(addr, std::sync::Arc::new(JobQueue::new(workers)), shutdown).await?,
+        }
         Ok::<(), Box<dyn std::error::Error>>(())
     })?;
+    println!("✓ Server stopped");
     Ok(())
 }
 
+fn cmd_queue(server: String, api_key: Option<String>, action: QueueAction) -> Result<(), Box<dyn std::error::Error>> {
+    let base = server.trim_end_matches('/');
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(key) = api_key.or_else(|| std::env::var("QTE_API_KEY").ok()) {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", key))?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    let client = reqwest::Client::builder().default_headers(headers).build()?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        match action {
+            QueueAction::Ls { status, kind, priority, all } => {
+                let filter = server::JobFilter { status, kind, priority, owner: None };
+                let path = if all { "admin/jobs" } else { "jobs" };
+                let jobs: Vec<server::JobSummary> = send(client.get(format!("{}/{}", base, path)).query(&filter)).await?.json().await?;
+                println!("{:<36}  {:<8}  {:<8}  {:<9}  DETAIL", "JOB", "KIND", "PRIORITY", "STATUS");
+                for job in &jobs {
+                    let detail = match (&job.progress, &job.error) {
+                        (Some(progress), _) => format!("{:.0}%", progress * 100.0),
+                        (_, Some(error)) => format!("{} (retries: {})", error, job.retry_count),
+                        _ => String::new(),
+                    };
+                    let detail = match (&job.owner, all) {
+                        (Some(owner), true) => format!("[{}] {}", owner, detail),
+                        _ => detail,
+                    };
+                    println!("{:<36}  {:<8}  {:<8}  {:<9}  {}", job.job_id, job.kind, format!("{:?}", job.priority), job.status, detail);
+                }
+                println!("{} jobs", jobs.len());
+            }
+            QueueAction::Cancel { job_id, any } => {
+                let path = if any { "admin/jobs" } else { "jobs" };
+                send(client.delete(format!("{}/{}/{}", base, path, job_id))).await?;
+                println!("✓ Cancelled {}", job_id);
+            }
+            QueueAction::Retry { job_id } => {
+                send(client.post(format!("{}/jobs/{}/retry", base, job_id))).await?;
+                println!("✓ Requeued {}", job_id);
+            }
+            QueueAction::Inspect { job_id } => {
+                let details: job_queue::JobDetails = send(client.get(format!("{}/jobs/{}", base, job_id))).await?.json().await?;
+                print_job_details(&details);
+            }
+            QueueAction::Drain => set_queue_state(&client, base, "drain").await?,
+            QueueAction::Resume => set_queue_state(&client, base, "resume").await?,
+        }
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+}
+
+/// `POST /queue/drain` or `/queue/resume`, printing the queue's state
+async fn set_queue_state(client: &reqwest::Client, base: &str, action: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let summary: server::QueueSummary = send(client.post(format!("{}/queue/{}", base, action))).await?.json().await?;
+    println!(
+        "Queue {}: {} queued, {} running, {} complete, {} failed, {} cancelled",
+        if summary.draining { "draining" } else { "running" },
+        summary.queued, summary.running, summary.complete, summary.failed, summary.cancelled
+    );
+    Ok(())
+}
+
+/// Send a request to the server, turning a non-2xx response into its
+/// `ApiError` message
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = match response.json::<server::ApiError>().await {
+        Ok(error) => error.error,
+        Err(_) => status.to_string(),
+    };
+    Err(format!("Server returned {}: {}", status, message).into())
+}
+
+fn print_job_details(details: &job_queue::JobDetails) {
+    let job = &details.job;
+    println!("Job {}", job.id);
+    println!("  Kind:     {}", job.kind.label());
+    println!("  Priority: {:?}", job.priority);
+    match &details.status {
+        JobStatus::Queued { position } => println!("  Status:   queued at position {}", position),
+        JobStatus::Running { progress, eta } => println!("  Status:   running, {:.0}% done, ETA {:?}", progress * 100.0, eta),
+        JobStatus::Failed { error, retry_count } => println!("  Status:   failed after {} retries: {}", retry_count, error),
+        status => println!("  Status:   {}", status.label()),
+    }
+    println!("  Submitted: {}", details.submitted_at.to_rfc3339());
+    if let Some(started_at) = details.started_at {
+        println!("  Started:  {}", started_at.to_rfc3339());
+    }
+    if let Some(finished_at) = details.finished_at {
+        println!("  Finished: {}", finished_at.to_rfc3339());
+    }
+    let mut params: Vec<_> = job.params.iter().collect();
+    params.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in params {
+        println!("  Param:    {} = {}", name, value);
+    }
+    println!("  Timeout:  {:?}, max retries {}", job.config.timeout, job.config.max_retries);
+    println!("  Backend:  {}", serde_json::to_string(&job.config.backend).unwrap_or_default());
+    if let JobStatus::Complete(result) = &details.status {
+        println!("  Waited:   {:?}", result.wait);
+        println!("  Ran:      {:?}", result.duration);
+        if let job_queue::JobOutput::Simulation(simulation) = &result.output {
+            let diagnostics = &simulation.diagnostics;
+            println!("  Diagnostics: max trace drift {:.2e}, min eigenvalue {:.2e}", diagnostics.max_trace_drift, diagnostics.min_eigenvalue);
+            for warning in &diagnostics.warnings {
+                println!("    ! {}", warning);
+            }
+        }
+    }
+}
+
 fn cmd_templates(category: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
-    let registry = TemplateRegistry::new();
+    let registry = template_registry()?;
     let templates = if let Some(cat) = category {
         use templates::TemplateCategory;
         let c = match cat.as_str() {
@@ -252,18 +1315,172 @@ fn cmd_templates(category: Option<String>) -> Result<(), Box<dyn std::error::Err
     Ok(())
 }
 
-fn cmd_validate(file: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    let code = fs::read_to_string(&file)?;
-    println!("✓ Loaded: {} bytes", code.len());
+fn cmd_install_template(file: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let template = templates::Template::from_file(&file)?;
+    let id = template.id.clone();
+    let mut registry = template_registry()?;
+    registry.register(template);
+    registry.instantiate(&id, &HashMap::new())?;
+
+    let dir = match TEMPLATE_DIR.get().cloned().flatten().or_else(templates::default_template_dir) {
+        Some(dir) => dir,
+        None => return Err("No template directory; pass --template-dir or set QTE_TEMPLATE_DIR".into()),
+    };
+    fs::create_dir_all(&dir)?;
+    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("toml");
+    let target = dir.join(format!("{}.{}", id, extension));
+    fs::copy(&file, &target)?;
+
+    let overrides = if TemplateRegistry::new().get(&id).is_some() { " (overrides the builtin)" } else { "" };
+    println!("✓ Installed template '{}' to {}{}", id, target.display(), overrides);
     Ok(())
 }
 
-fn cmd_health(detailed: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let checker = HealthChecker::default();
+fn cmd_validate(file: PathBuf, json: bool, tol: Option<f64>, warn_only: Vec<String>, strict: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // An unreadable file is an I/O error rather than a diagnostic
+    fs::metadata(&file)?;
+    let config = ValidatorConfig {
+        tol: tol.unwrap_or(VALIDATION_TOL),
+        strict_mode: strict,
+        warn_only: warn_only.iter().map(|c| c.parse()).collect::<error::Result<_>>()?,
+    };
+    config.validate()?;
+    let diagnostics = diagnostics::diagnose_file_with(&file, &config);
+    let errors: Vec<_> = diagnostics.iter().filter(|d| d.is_error()).collect();
+
+    if json {
+        let document = serde_json::json!({
+            "file": file,
+            "valid": errors.is_empty(),
+            "diagnostics": diagnostics,
+        });
+        println!("{}", serde_json::to_string_pretty(&document)?);
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}: {}", file.display(), diagnostic);
+        }
+    }
+
+    let Some(first) = errors.first() else {
+        if !json {
+            let warnings = diagnostics.len();
+            match warnings {
+                0 => println!("✓ {} is valid", file.display()),
+                _ => println!("✓ {} is valid ({} warning{})", file.display(), warnings, if warnings == 1 { "" } else { "s" }),
+            }
+        }
+        return Ok(());
+    };
+
+    // Exit with the category of the first error
+    let summary = format!("{} has {} error{}", file.display(), errors.len(), if errors.len() == 1 { "" } else { "s" });
+    Err(match first.exit_code {
+        Some(error::exit_code::PARSE) => EngineError::parse_error(first.line.unwrap_or(0), first.column.unwrap_or(0), summary),
+        Some(error::exit_code::TYPE) => EngineError::type_error(summary),
+        Some(error::exit_code::QUANTUM_CONSTRAINT) => EngineError::quantum_error(summary),
+        _ => EngineError::validation_error(summary),
+    }
+    .into())
+}
+
+fn cmd_describe(file: PathBuf, format: report::ReportFormat, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let ast = parse_file(&file)?;
+    let title = file.file_stem().map_or_else(|| file.display().to_string(), |s| s.to_string_lossy().into_owned());
+    let report = report::ModelReport::new(title, &ast);
+    match output {
+        Some(path) => {
+            fs::write(&path, report.render(format))?;
+            println!("✓ Report written to {}", path.display());
+        }
+        None => print!("{}", report.render(format)),
+    }
+    Ok(())
+}
+
+fn cmd_import(file: PathBuf, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let circuit = qasm::parse_qasm(&fs::read_to_string(&file)?)?;
+    let source = circuit.to_dsl();
+    match output {
+        Some(path) => {
+            fs::write(&path, source)?;
+            println!(
+                "✓ Imported {} qubits and {} gates into {}",
+                circuit.n_qubits(),
+                circuit.gates.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", source),
+    }
+    Ok(())
+}
+
+fn cmd_provenance(result: String, store: PathBuf, format: String) -> Result<(), Box<dyn std::error::Error>> {
+    let graph = provenance::ProvenanceGraph::load(&store)?;
+    // A path to a result file is looked up by the hash of its contents
+    let query = match fs::read(&result) {
+        Ok(bytes) => provenance::sha256_hex(&bytes),
+        Err(_) => result,
+    };
+    let id = graph.resolve(&query)?;
+    let lineage = graph.lineage(&id)?;
+    match format.as_str() {
+        "text" => print_lineage(&lineage, &id, 0),
+        "json" => println!("{}", serde_json::to_string_pretty(&lineage)?),
+        "dot" => print!("{}", lineage.to_dot()),
+        other => return Err(format!("Unknown format '{}', expected text, json or dot", other).into()),
+    }
+    Ok(())
+}
+
+fn print_lineage(graph: &provenance::ProvenanceGraph, id: &str, depth: usize) {
+    if depth == 0 {
+        println!("{}", graph.node(id).map_or_else(|| id.to_string(), |n| n.label()));
+    }
+    for edge in graph.edges_from(id) {
+        let label = graph.node(&edge.to).map_or_else(|| edge.to.clone(), |n| n.label());
+        println!("{}└─ {} {}", "   ".repeat(depth), edge.relation, label);
+        print_lineage(graph, &edge.to, depth + 1);
+    }
+}
+
+fn cmd_health(
+    detailed: bool,
+    baseline: Option<PathBuf>,
+    record_baseline: Option<PathBuf>,
+    threshold: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = record_baseline {
+        let timings = BenchmarkTimings::measure()?;
+        timings.save(&path)?;
+        println!(
+            "Baseline saved to {}: matrix_exp {:.1} µs, rk4 {:.0} µs",
+            path.display(),
+            timings.matrix_exp_us,
+            timings.rk4_us
+        );
+        return Ok(());
+    }
+
+    let mut checker = HealthChecker::default();
+    if let Some(path) = baseline {
+        let check = BenchmarkCheck::new(BenchmarkTimings::load(&path)?).with_threshold(threshold);
+        checker = checker.with_benchmark(check);
+    }
     let status = checker.run_checks();
-    println!("Status: {}", if status.healthy { "HEALTHY" } else { "UNHEALTHY" });
+    let label = match (status.healthy, status.degraded) {
+        (false, _) => "UNHEALTHY",
+        (true, true) => "DEGRADED",
+        (true, false) => "HEALTHY",
+    };
+    println!("Status: {}", label);
     for (name, result) in &status.checks {
-        println!("  {} {}", if result.passed { "✓" } else { "✗" }, name);
+        let mark = match (result.passed, result.degraded) {
+            (false, _) => "✗",
+            (true, true) => "!",
+            (true, false) => "✓",
+        };
+        println!("  {} {}: {}", mark, name, result.message);
     }
     if detailed {
         for m in logging::get_metrics() {
@@ -281,3 +1498,145 @@ fn parse_params(params: &[String]) -> Result<HashMap<String, f64>, Box<dyn std::
     }
     Ok(map)
 }
+
+fn parse_operators(operators: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    operators
+        .iter()
+        .map(|o| {
+            let (name, operator) = o.split_once('=').ok_or_else(|| format!("Invalid operator '{}', expected NAME=OPERATOR", o))?;
+            Ok::<_, Box<dyn std::error::Error>>((name.to_string(), operator.to_string()))
+        })
+        .collect()
+}
+
+fn parse_range(range: &str) -> Result<job_queue::ParameterRange, Box<dyn std::error::Error>> {
+    let p: Vec<&str> = range.split(':').collect();
+    if p.len() != 4 {
+        return Err(format!("Invalid range '{}', expected PARAM:START:END:STEPS", range).into());
+    }
+    Ok(job_queue::ParameterRange {
+        name: p[0].to_string(),
+        start: p[1].parse()?,
+        end: p[2].parse()?,
+        steps: p[3].parse()?,
+        scale: job_queue::ParameterScale::Linear,
+    })
+}
+
+fn parse_bound(bound: &str) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+    let (lo, hi) = bound.split_once(':').ok_or_else(|| format!("Invalid bound '{}', expected LO:HI", bound))?;
+    Ok((lo.parse()?, hi.parse()?))
+}
+
+/// `--template-dir`, set once in `main`
+static TEMPLATE_DIR: std::sync::OnceLock<Option<PathBuf>> = std::sync::OnceLock::new();
+
+/// The builtin templates, overridden and extended by those installed in
+/// `templates::default_template_dir` and then by the `--template-dir`
+fn template_registry() -> error::Result<TemplateRegistry> {
+    let mut registry = TemplateRegistry::new();
+    if let Some(dir) = templates::default_template_dir().filter(|dir| dir.is_dir()) {
+        registry.load_dir(dir)?;
+    }
+    if let Some(dir) = TEMPLATE_DIR.get().and_then(Option::as_ref) {
+        registry.load_dir(dir)?;
+    }
+    Ok(registry)
+}
+
+/// Load a template or .phys file and lower it to IR
+fn load_ir(program: &str, params: &HashMap<String, f64>, values: Option<&std::path::Path>) -> Result<ir::IrProgram, Box<dyn std::error::Error>> {
+    lower_ast(&load_ast(program, params, &HashMap::new(), values)?)
+}
+
+/// Parse a template or .phys file. Templates take `params` and
+/// `operators`. For .phys files, `params` and the `values` file bind
+/// `param` declarations and override `const` declarations, with `params`
+/// taking precedence.
+fn load_ast(program: &str, params: &HashMap<String, f64>, operators: &HashMap<String, String>, values: Option<&std::path::Path>) -> Result<Ast, Box<dyn std::error::Error>> {
+    let registry = template_registry()?;
+    Ok(if registry.get(program).is_some() {
+        if values.is_some() {
+            return Err("--values applies to .phys files; pass template parameters with --param".into());
+        }
+        registry.instantiate_ast(program, params, operators)?
+    } else {
+        if !operators.is_empty() {
+            return Err("--operator applies to templates; declare operators in the .phys file".into());
+        }
+        let mut bindings = match values {
+            Some(path) => ParameterValues::from_file(path)?,
+            None => ParameterValues::new(),
+        };
+        for (name, value) in params {
+            bindings.insert(name.clone(), *value);
+        }
+        bindings.bind(&parse_file(program)?)?
+    })
+}
+
+fn lower_ast(ast: &Ast) -> Result<ir::IrProgram, Box<dyn std::error::Error>> {
+    let typed = TypeChecker::new().check(ast)?;
+    let validated = QuantumValidator::new().validate(&typed)?;
+    Ok(lowering::Lowerer::new().lower(&validated)?)
+}
+
+/// Record how the file `result` was produced in the provenance store. Its
+/// result id is the SHA-256 of its contents, so `qte provenance <file>`
+/// finds it again.
+fn record_provenance(result: &std::path::Path, kind: &str, build: impl FnOnce(provenance::ProvenanceRecord) -> error::Result<provenance::ProvenanceRecord>) -> Result<(), Box<dyn std::error::Error>> {
+    let result_id = provenance::sha256_hex(&fs::read(result)?);
+    let job_id = format!("{}-{}", kind, &result_id[..12]);
+    let record = build(provenance::ProvenanceRecord::new(result_id, job_id, kind).with_description(result.display().to_string()))?;
+    let store = std::path::Path::new(provenance::DEFAULT_STORE);
+    let mut graph = provenance::ProvenanceGraph::load(store)?;
+    graph.record(&record);
+    graph.save(store)?;
+    Ok(())
+}
+
+/// A registry template by id and version, otherwise the program file by hash
+fn with_program(record: provenance::ProvenanceRecord, program: &str) -> error::Result<provenance::ProvenanceRecord> {
+    match template_registry()?.get(program) {
+        Some(template) => Ok(record.with_template(template)),
+        None => record.with_input_file(program),
+    }
+}
+
+fn compute_spectra(program: &str, params: &HashMap<String, f64>, only: Option<&str>) -> Result<Vec<spectrum::Spectrum>, Box<dyn std::error::Error>> {
+    let ir = load_ir(program, params, None)?;
+    let hamiltonians = Executor::new(BackendConfig::default()).hamiltonians(&ir)?;
+    let spectra = hamiltonians
+        .iter()
+        .filter(|(name, _)| only.is_none_or(|o| o == name))
+        .map(|(name, h)| spectrum::Spectrum::of(name, h, 1e-9))
+        .collect::<error::Result<Vec<_>>>()?;
+    if spectra.is_empty() {
+        return Err(match only {
+            Some(name) => format!("No Hamiltonian named '{}'", name).into(),
+            None => "Program declares no Hamiltonian".into(),
+        });
+    }
+    Ok(spectra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_mapping() {
+        let code = |e: Box<dyn std::error::Error>| exit_code(e.as_ref());
+        assert_eq!(code(parse_dsl("const = ;").unwrap_err().into()), 10);
+        assert_eq!(code(EngineError::type_error("x").into()), 11);
+        assert_eq!(code(EngineError::validation_error("x").into()), 12);
+        assert_eq!(code(EngineError::quantum_error("x").into()), 13);
+        assert_eq!(code(EngineError::ExecutionError("x".into()).into()), 20);
+        assert_eq!(code(ProofFailure::Refuted.into()), 40);
+        assert_eq!(code(ProofFailure::Undecided.into()), 41);
+        assert_eq!(code(ProofFailure::InvalidCertificate.into()), 42);
+        assert_eq!(code(fs::read("/nonexistent/qte").unwrap_err().into()), 3);
+        assert_eq!(code(serde_json::from_str::<u8>("x").unwrap_err().into()), 3);
+        assert_eq!(code("Unknown format".into()), 1);
+    }
+}
@@ -0,0 +1,271 @@
+//! `qte plot`: render a results file as PNG or SVG
+//!
+//! Three views of one experiment are available: the populations ρ_kk of
+//! its states, the Bloch vector (x, y, z) of a qubit's states, and the
+//! real parts of its tracked expectation values, each against time. States
+//! are only in HDF5 files and JSON written with `--format json`; a plain
+//! JSON result has just the expectation values.
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use quantum_theory_engine::executor::{ExecutionResult, ExperimentResult};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What to draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlotKind {
+    Populations,
+    Bloch,
+    Observables,
+}
+
+impl FromStr for PlotKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "populations" => Ok(PlotKind::Populations),
+            "bloch" => Ok(PlotKind::Bloch),
+            "observables" => Ok(PlotKind::Observables),
+            other => Err(format!(
+                "Unknown plot '{}' (expected populations, bloch or observables)",
+                other
+            )),
+        }
+    }
+}
+
+/// Named curves sharing one time axis
+struct Curves {
+    title: String,
+    y_label: &'static str,
+    times: Vec<f64>,
+    series: Vec<(String, Vec<f64>)>,
+}
+
+/// Draw `kind` for the experiment named `experiment`, or the first one, to
+/// `path`: SVG for a `.svg` extension, otherwise PNG
+pub fn plot(
+    result: &ExecutionResult,
+    experiment: Option<&str>,
+    kind: PlotKind,
+    path: &Path,
+    size: (u32, u32),
+) -> Result<(), Box<dyn Error>> {
+    let experiment = match experiment {
+        Some(name) => result
+            .experiment_results
+            .iter()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("No experiment named '{}'", name))?,
+        None => result
+            .experiment_results
+            .first()
+            .ok_or("The result has no experiments")?,
+    };
+    let curves = curves(experiment, kind)?;
+
+    if path.extension().and_then(|e| e.to_str()) == Some("svg") {
+        draw(SVGBackend::new(path, size).into_drawing_area(), &curves)
+    } else {
+        draw(BitMapBackend::new(path, size).into_drawing_area(), &curves)
+    }
+}
+
+fn curves(experiment: &ExperimentResult, kind: PlotKind) -> Result<Curves, Box<dyn Error>> {
+    let needs_states = || -> Result<(), Box<dyn Error>> {
+        if experiment.states.is_empty() {
+            return Err(format!(
+                "Experiment '{}' has no states; simulate with --format json or to HDF5",
+                experiment.name
+            )
+            .into());
+        }
+        Ok(())
+    };
+
+    let (title, y_label, series) = match kind {
+        PlotKind::Populations => {
+            needs_states()?;
+            let dim = experiment.states[0].nrows();
+            let series = (0..dim)
+                .map(|k| {
+                    let values = experiment.states.iter().map(|rho| rho[[k, k]].re).collect();
+                    (format!("ρ{}{}", k, k), values)
+                })
+                .collect();
+            ("populations", "population", series)
+        }
+        PlotKind::Bloch => {
+            needs_states()?;
+            let vectors = experiment.bloch_vectors()?;
+            let series = vec![
+                ("x".to_string(), vectors.iter().map(|v| v.0).collect()),
+                ("y".to_string(), vectors.iter().map(|v| v.1).collect()),
+                ("z".to_string(), vectors.iter().map(|v| v.2).collect()),
+            ];
+            ("Bloch vector", "component", series)
+        }
+        PlotKind::Observables => {
+            if experiment.observables.is_empty() {
+                return Err(
+                    format!("Experiment '{}' tracks no observables", experiment.name).into(),
+                );
+            }
+            let series = experiment
+                .observables
+                .iter()
+                .map(|s| {
+                    (
+                        format!("⟨{}⟩", s.label),
+                        s.values.iter().map(|v| v.re).collect(),
+                    )
+                })
+                .collect();
+            ("expectation values", "value", series)
+        }
+    };
+
+    Ok(Curves {
+        title: format!("{}: {}", experiment.name, title),
+        y_label,
+        times: experiment.times.clone(),
+        series,
+    })
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    curves: &Curves,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let finite = |v: &f64| v.is_finite();
+    let (t_min, t_max) = bounds(curves.times.iter().copied().filter(finite));
+    let (y_min, y_max) = bounds(
+        curves
+            .series
+            .iter()
+            .flat_map(|(_, values)| values.iter().copied())
+            .filter(finite),
+    );
+    // Some headroom, and a visible range for constant curves
+    let pad = ((y_max - y_min) * 0.05).max(1e-3);
+
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(&curves.title, ("sans-serif", 22))
+        .margin(12)
+        .x_label_area_size(40)
+        .y_label_area_size(56)
+        .build_cartesian_2d(
+            t_min..t_max.max(t_min + 1e-12),
+            (y_min - pad)..(y_max + pad),
+        )?;
+    chart
+        .configure_mesh()
+        .x_desc("t")
+        .y_desc(curves.y_label)
+        .draw()?;
+
+    for (i, (label, values)) in curves.series.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let points = curves.times.iter().copied().zip(values.iter().copied());
+        chart
+            .draw_series(LineSeries::new(points, color.stroke_width(2)))?
+            .label(label.as_str())
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+            });
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
+
+/// Smallest and largest of `values`, (0, 1) if there are none
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values
+        .fold(None, |range: Option<(f64, f64)>, v| match range {
+            Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+            None => Some((v, v)),
+        })
+        .unwrap_or((0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+    use num_complex::Complex64;
+    use quantum_theory_engine::executor::StateType;
+
+    fn precessing_qubit() -> ExperimentResult {
+        let times: Vec<f64> = (0..5).map(|k| k as f64 * 0.5).collect();
+        let states = times
+            .iter()
+            .map(|t| {
+                // |+⟩ precessing about z: ρ01 = e^{-it} / 2
+                let coherence = Complex64::from_polar(0.5, -t);
+                let mut rho = Array2::from_elem((2, 2), Complex64::new(0.5, 0.0));
+                rho[[0, 1]] = coherence;
+                rho[[1, 0]] = coherence.conj();
+                rho
+            })
+            .collect();
+        ExperimentResult {
+            name: "precession".to_string(),
+            times,
+            state_type: StateType::DensityMatrix,
+            measurements: vec![],
+            states,
+            diagnostics: None,
+            ehrenfest: vec![],
+            observables: vec![],
+        }
+    }
+
+    #[test]
+    fn test_bloch_curves() {
+        let bloch = curves(&precessing_qubit(), PlotKind::Bloch).unwrap();
+        let (x, y, z) = (
+            &bloch.series[0].1,
+            &bloch.series[1].1,
+            &bloch.series[2].1,
+        );
+        for (k, t) in bloch.times.iter().enumerate() {
+            assert!((x[k] - t.cos()).abs() < 1e-12);
+            assert!((y[k] - t.sin()).abs() < 1e-12);
+            assert!(z[k].abs() < 1e-12);
+        }
+        assert!(curves(&precessing_qubit(), PlotKind::Observables).is_err());
+        assert_eq!("bloch".parse::<PlotKind>(), Ok(PlotKind::Bloch));
+    }
+
+    #[test]
+    fn test_writes_svg() {
+        let result = ExecutionResult {
+            experiment_results: vec![precessing_qubit()],
+        };
+        let path = std::env::temp_dir().join(format!("qte_plot_{}.svg", std::process::id()));
+        plot(&result, None, PlotKind::Populations, &path, (640, 480)).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(svg.contains("<svg"));
+        assert!(plot(
+            &result,
+            Some("missing"),
+            PlotKind::Populations,
+            &path,
+            (640, 480)
+        )
+        .is_err());
+    }
+}
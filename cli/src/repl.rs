@@ -0,0 +1,505 @@
+//! `qte repl`: an interactive session on the DSL
+//!
+//! Declarations accumulate into one program, which is validated as a whole
+//! after each input; redeclaring a name replaces the input that declared it.
+//! Any other input is evaluated as an expression in the program's scope and
+//! printed, matrices as a grid. Input with unclosed braces continues on the
+//! next line, so experiments can be typed as in a file. Commands start with
+//! a colon; see [`HELP`].
+
+use quantum_theory_engine::ast::{Ast, Expr, Statement};
+use quantum_theory_engine::functions::{self, FunctionTable};
+use quantum_theory_engine::plugin::KernelValue;
+use quantum_theory_engine::prover::{ProofResult, Prover, ProverConfig};
+use quantum_theory_engine::{imports, parser, pipeline, BackendConfig, Executor};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+pub const HELP: &str = "\
+Declarations (const, matrix, Hamiltonian, func, experiment, ...) extend the program;
+any other input is evaluated as an expression.
+
+  :check EXPR PROPERTY   prove EXPR hermitian, unitary or idempotent
+  :run [EXPERIMENT]      execute the program, or only report EXPERIMENT
+  :load FILE             add the declarations of a .phys file
+  :list                  print the program
+  :reset                 forget every declaration
+  :help                  show this help
+  :quit                  leave the REPL";
+
+/// What the session answers to one input
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Output(String),
+    Quit,
+}
+
+/// One input that declared something, with the names it declared
+#[derive(Clone)]
+struct Entry {
+    names: Vec<String>,
+    source: String,
+    statements: Vec<Statement>,
+}
+
+/// Declarations entered so far
+#[derive(Default)]
+pub struct Session {
+    entries: Vec<Entry>,
+}
+
+/// Read inputs from stdin until end of input or `:quit`
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let mut session = Session::new();
+    let mut pending = String::new();
+    let stdin = io::stdin();
+    println!("qte {} — :help for commands", env!("CARGO_PKG_VERSION"));
+
+    loop {
+        print!("{}", if pending.is_empty() { "qte> " } else { "...> " });
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        pending.push_str(&line);
+        if !is_complete(&pending) {
+            continue;
+        }
+        match session.input(&std::mem::take(&mut pending)) {
+            Ok(Reply::Quit) => return Ok(()),
+            Ok(Reply::Output(text)) if text.is_empty() => {}
+            Ok(Reply::Output(text)) => println!("{}", text),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+}
+
+/// Whether `input` closes every brace it opens
+fn is_complete(input: &str) -> bool {
+    let depth: i64 = input
+        .chars()
+        .map(|c| match c {
+            '{' => 1,
+            '}' => -1,
+            _ => 0,
+        })
+        .sum();
+    depth <= 0
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The program declared so far, as entered
+    pub fn program(&self) -> String {
+        let sources: Vec<&str> = self.entries.iter().map(|e| e.source.as_str()).collect();
+        sources.join("\n")
+    }
+
+    fn ast(&self) -> Ast {
+        Ast::new(
+            self.entries
+                .iter()
+                .flat_map(|e| e.statements.iter().cloned())
+                .collect(),
+        )
+    }
+
+    /// Answer one complete input: a command, declarations or an expression
+    pub fn input(&mut self, input: &str) -> Result<Reply, Box<dyn Error>> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(Reply::Output(String::new()));
+        }
+        if let Some(command) = input.strip_prefix(':') {
+            return self.command(command.trim());
+        }
+
+        match parser::parse_dsl(input) {
+            Ok(ast) => self.declare(input, ast),
+            // Declarations end in `;` or `}`, expressions do not
+            Err(e) if input.ends_with(';') || input.ends_with('}') => Err(e.into()),
+            _ => {
+                let expr = parser::parse_expression(input)?;
+                Ok(Reply::Output(render(&pipeline::evaluate(
+                    &self.ast(),
+                    &expr,
+                )?)))
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<Reply, Box<dyn Error>> {
+        let (name, args) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, args)| (name, args.trim()));
+        let output = match name {
+            "q" | "quit" | "exit" => return Ok(Reply::Quit),
+            "h" | "help" => HELP.to_string(),
+            "list" if self.entries.is_empty() => "(no declarations)".to_string(),
+            "list" => self.program(),
+            "reset" => {
+                self.entries.clear();
+                "All declarations removed".to_string()
+            }
+            "load" if !args.is_empty() => {
+                let ast = imports::parse_file(args)?;
+                let source = std::fs::read_to_string(Path::new(args))?;
+                return self.declare(source.trim(), ast);
+            }
+            "check" => self.check(args)?,
+            "run" => self.run((!args.is_empty()).then_some(args))?,
+            _ => {
+                return Err(
+                    format!("Unknown command ':{}'; :help lists the commands", command).into(),
+                )
+            }
+        };
+        Ok(Reply::Output(output))
+    }
+
+    /// Add the statements of `ast`, entered as `source`, unless the
+    /// program they make fails validation
+    fn declare(&mut self, source: &str, ast: Ast) -> Result<Reply, Box<dyn Error>> {
+        let names: Vec<String> = ast
+            .statements
+            .iter()
+            .filter_map(declared_name)
+            .map(str::to_string)
+            .collect();
+        let previous = self.entries.clone();
+        let redeclares = |e: &Entry| e.names.iter().any(|name| names.contains(name));
+        // A redeclaration takes the place of the first input it replaces,
+        // ahead of the declarations that use it
+        let position = self.entries.iter().position(redeclares);
+        self.entries.retain(|e| !redeclares(e));
+        let entry = Entry {
+            names: names.clone(),
+            source: source.to_string(),
+            statements: ast.statements,
+        };
+        match position {
+            Some(index) => self.entries.insert(index, entry),
+            None => self.entries.push(entry),
+        }
+        let redefined = position.is_some();
+
+        if let Err(e) = pipeline::validate_quantum(&self.ast()) {
+            self.entries = previous;
+            return Err(e.into());
+        }
+        Ok(Reply::Output(match names.len() {
+            0 => String::new(),
+            _ if redefined => format!("redefined {}", names.join(", ")),
+            _ => format!("defined {}", names.join(", ")),
+        }))
+    }
+
+    /// Prove a property of an expression, with the declarations it uses
+    /// written out so that the prover sees their definitions
+    fn check(&self, args: &str) -> Result<String, Box<dyn Error>> {
+        let (expr, property) = args
+            .rsplit_once(char::is_whitespace)
+            .ok_or("Usage: :check EXPR hermitian|unitary|idempotent")?;
+        let expr = self.expand(&parser::parse_expression(expr.trim())?)?;
+        let dagger = Expr::Dagger(Box::new(expr.clone()));
+        let (lhs, rhs) = match property {
+            "hermitian" => (dagger, expr),
+            // A scalar stands for that multiple of the identity
+            "unitary" => (
+                Expr::Mul(Box::new(dagger), Box::new(expr)),
+                Expr::Number(1.0),
+            ),
+            "idempotent" => (
+                Expr::Mul(Box::new(expr.clone()), Box::new(expr.clone())),
+                expr,
+            ),
+            other => {
+                return Err(format!(
+                    "Unknown property '{}' (expected hermitian, unitary or idempotent)",
+                    other
+                )
+                .into())
+            }
+        };
+
+        let statement = format!("{} == {}", lhs, rhs);
+        Ok(
+            match Prover::new(ProverConfig::default()).prove_identity(&lhs, &rhs) {
+                ProofResult::Proven(proof) => {
+                    let mut lines = vec![format!("✓ {} ({} steps)", statement, proof.steps.len())];
+                    for (i, step) in proof.steps.iter().enumerate() {
+                        lines.push(format!(
+                            "  {}. {:?}: {}",
+                            i + 1,
+                            step.rule,
+                            step.justification
+                        ));
+                    }
+                    lines.join("\n")
+                }
+                ProofResult::Refuted(counterexample) => {
+                    format!("✗ {} is false\n{}", statement, counterexample)
+                }
+                ProofResult::Unknown(reason) => {
+                    format!(
+                        "? {}: no proof found ({:?}) and no counterexample among sampled values",
+                        statement, reason
+                    )
+                }
+            },
+        )
+    }
+
+    /// `expr` with every declared constant, matrix, operator and
+    /// Hamiltonian replaced by its value and every function call inlined;
+    /// symbols and parameters stay free
+    fn expand(&self, expr: &Expr) -> Result<Expr, Box<dyn Error>> {
+        let mut values: HashMap<String, Expr> = HashMap::new();
+        let mut functions = FunctionTable::new();
+
+        for statement in self.entries.iter().flat_map(|e| &e.statements) {
+            match statement {
+                Statement::ConstDecl { name, value } => {
+                    values.insert(name.clone(), Expr::Number(*value));
+                }
+                Statement::MatrixDecl { name, value } => {
+                    values.insert(name.clone(), Expr::Matrix(value.clone()));
+                }
+                Statement::OperatorDecl { name, value } => {
+                    values.insert(name.clone(), value.clone());
+                }
+                Statement::HamiltonianDef { name, expr, .. } => {
+                    let value = resolve(expr, &values, &functions, &[])?;
+                    values.insert(name.clone(), value);
+                }
+                Statement::FunctionDef { name, params, body } => {
+                    let body = resolve(body, &values, &functions, params)?;
+                    functions.define(name, params, &body)?;
+                }
+                _ => {}
+            }
+        }
+        resolve(expr, &values, &functions, &[])
+    }
+
+    /// Execute the program and summarize each experiment, or only
+    /// `experiment`
+    fn run(&self, experiment: Option<&str>) -> Result<String, Box<dyn Error>> {
+        let ast = self.ast();
+        if !ast
+            .statements
+            .iter()
+            .any(|s| matches!(s, Statement::Experiment { .. }))
+        {
+            return Err("No experiment declared".into());
+        }
+        let ir = pipeline::lower(&pipeline::validate_quantum(&ast)?)?;
+        let result = Executor::new(BackendConfig::default()).execute(&ir)?;
+
+        let mut lines = Vec::new();
+        for e in &result.experiment_results {
+            if experiment.is_some_and(|name| name != e.name) {
+                continue;
+            }
+            let t = e.times.last().copied().unwrap_or(0.0);
+            lines.push(format!(
+                "experiment {}: {} steps to t = {}",
+                e.name,
+                e.times.len(),
+                number(t)
+            ));
+            if let Some(rho) = e.states.last() {
+                let populations: Vec<String> =
+                    (0..rho.nrows()).map(|k| number(rho[[k, k]].re)).collect();
+                lines.push(format!("  populations [{}]", populations.join(", ")));
+            }
+            for series in &e.observables {
+                if let Some(value) = series.values.last() {
+                    lines.push(format!(
+                        "  ⟨{}⟩ = {}",
+                        series.label,
+                        complex(value.re, value.im)
+                    ));
+                }
+            }
+            if !e.measurements.is_empty() {
+                lines.push(format!("  {} measurement(s)", e.measurements.len()));
+            }
+        }
+        match (experiment, lines.is_empty()) {
+            (Some(name), true) => Err(format!("No experiment named '{}'", name).into()),
+            _ => Ok(lines.join("\n")),
+        }
+    }
+}
+
+/// `expr` with its function calls inlined and the names in `values`,
+/// other than those `shadowed`, replaced by their values
+fn resolve(
+    expr: &Expr,
+    values: &HashMap<String, Expr>,
+    functions: &FunctionTable,
+    shadowed: &[String],
+) -> Result<Expr, Box<dyn Error>> {
+    let mut expr = functions.inline(expr)?;
+    let bindings: HashMap<&str, &Expr> = values
+        .iter()
+        .filter(|(name, _)| !shadowed.contains(name))
+        .map(|(name, value)| (name.as_str(), value))
+        .collect();
+    functions::substitute(&mut expr, &bindings);
+    Ok(expr)
+}
+
+/// The name a statement declares; sweeps and proof goals declare none
+fn declared_name(statement: &Statement) -> Option<&str> {
+    match statement {
+        Statement::ConstDecl { name, .. }
+        | Statement::SymbolDecl { name }
+        | Statement::ParamDecl { name, .. }
+        | Statement::MatrixDecl { name, .. }
+        | Statement::OperatorDecl { name, .. }
+        | Statement::FunctionDef { name, .. }
+        | Statement::HamiltonianDef { name, .. }
+        | Statement::MeasurementDef { name, .. }
+        | Statement::ChannelDef { name, .. }
+        | Statement::Experiment { name, .. } => Some(name),
+        Statement::Sweep { .. } | Statement::Assume(_) | Statement::Prove(_) => None,
+    }
+}
+
+/// A value as printed by the REPL: scalars inline, vectors and matrices as
+/// a grid with aligned columns
+fn render(value: &KernelValue) -> String {
+    let rows: Vec<Vec<String>> = match value {
+        KernelValue::Scalar(c) => return complex(c.re, c.im),
+        KernelValue::Vector(v) => v.iter().map(|c| vec![complex(c.re, c.im)]).collect(),
+        KernelValue::Matrix(m) => m
+            .outer_iter()
+            .map(|row| row.iter().map(|c| complex(c.re, c.im)).collect())
+            .collect(),
+    };
+    let columns = rows.first().map_or(0, Vec::len);
+    let widths: Vec<usize> = (0..columns)
+        .map(|j| {
+            rows.iter()
+                .map(|row| row[j].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let last = rows.len().saturating_sub(1);
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let (open, close) = match (i, rows.len()) {
+                (_, 1) => ("[", "]"),
+                (0, _) => ("⎡", "⎤"),
+                (i, _) if i == last => ("⎣", "⎦"),
+                _ => ("⎢", "⎥"),
+            };
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:>width$}", cell, width = width))
+                .collect();
+            format!("{} {} {}", open, cells.join("  "), close)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `re + im i` with negligible parts left out
+fn complex(re: f64, im: f64) -> String {
+    let (re, im) = (number(re), number(im));
+    match (re.as_str(), im.as_str()) {
+        (_, "0") => re,
+        ("0", _) => format!("{}i", im),
+        _ => match im.strip_prefix('-') {
+            Some(magnitude) => format!("{}-{}i", re, magnitude),
+            None => format!("{}+{}i", re, im),
+        },
+    }
+}
+
+/// `x` to six decimal places, without trailing zeros
+fn number(x: f64) -> String {
+    let rounded = (x * 1e6).round() / 1e6;
+    // No negative zero
+    format!("{}", rounded + 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(session: &mut Session, input: &str) -> String {
+        match session.input(input).unwrap() {
+            Reply::Output(text) => text,
+            Reply::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    #[test]
+    fn test_declarations_accumulate_and_evaluate() {
+        let mut session = Session::new();
+        assert_eq!(output(&mut session, "const Omega = 0.5;"), "defined Omega");
+        assert_eq!(
+            output(&mut session, "Hamiltonian H = Omega * sigma_x;"),
+            "defined H"
+        );
+        assert_eq!(output(&mut session, "H"), "⎡   0  0.5 ⎤\n⎣ 0.5    0 ⎦");
+        assert_eq!(output(&mut session, "trace(H * H)"), "0.5");
+
+        // Redeclaring replaces, and a rejected declaration changes nothing
+        assert_eq!(output(&mut session, "const Omega = 2;"), "redefined Omega");
+        assert_eq!(output(&mut session, "trace(H * H)"), "8");
+        assert!(session.input("Hamiltonian H = [0, 1; 0, 0];").is_err());
+        assert_eq!(output(&mut session, "trace(H * H)"), "8");
+        assert!(session.input("undefined_name").is_err());
+        assert_eq!(session.input(":q").unwrap(), Reply::Quit);
+    }
+
+    #[test]
+    fn test_run_experiment() {
+        let mut session = Session::new();
+        let experiment = "experiment flip {
+            init: ket(vec(1, 0));
+            evolution: evolve(init, H, timegrid=(0.0, 1.5707963267948966, 51));
+            observables: track <sigma_z>;
+        }";
+        assert!(!is_complete("experiment flip {\n init: ket(vec(1, 0));"));
+        assert!(is_complete(experiment));
+        output(&mut session, "Hamiltonian H = sigma_x;");
+        assert_eq!(output(&mut session, experiment), "defined flip");
+
+        let report = output(&mut session, ":run flip");
+        assert!(report.contains("populations [0, 1]"), "{}", report);
+        assert!(report.contains("⟨sigma_z⟩ = -1"), "{}", report);
+        assert!(session.input(":run other").is_err());
+    }
+
+    #[test]
+    fn test_check_uses_definitions() {
+        let mut session = Session::new();
+        output(&mut session, "matrix A = [0, 1; 0, 0];");
+        assert!(output(&mut session, ":check A hermitian").starts_with('✗'));
+        assert!(!output(&mut session, ":check sigma_x unitary").starts_with('✗'));
+        assert!(session.input(":check A normal").is_err());
+    }
+
+    #[test]
+    fn test_render_complex_values() {
+        assert_eq!(complex(0.5, -0.25), "0.5-0.25i");
+        assert_eq!(complex(-1e-9, 2.0), "2i");
+        assert_eq!(complex(-1e-9, 0.0), "0");
+    }
+}
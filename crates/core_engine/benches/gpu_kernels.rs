@@ -0,0 +1,148 @@
+//! CPU vs GPU dense kernels across system sizes
+//!
+//! Run with `cargo bench --features gpu --bench gpu_kernels`. The crossover
+//! these numbers show is what `BackendConfig::gpu_min_dim` should be set to.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ndarray::{Array1, Array2};
+use num_complex::Complex64;
+use quantum_theory_engine::kernels_cpu;
+use quantum_theory_engine::kernels_gpu::GpuContext;
+use quantum_theory_engine::ode::Rk4Integrator;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const DIMS: [usize; 4] = [16, 64, 128, 256];
+
+fn random_hermitian(dim: usize, rng: &mut StdRng) -> Array2<Complex64> {
+    let a = Array2::from_shape_fn((dim, dim), |_| {
+        Complex64::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+    });
+    let h = &a + &a.t().mapv(|z| z.conj());
+    h.mapv(|z| z / (2.0 * dim as f64).sqrt())
+}
+
+fn lowering(dim: usize) -> Array2<Complex64> {
+    Array2::from_shape_fn((dim, dim), |(i, j)| {
+        if j == i + 1 {
+            Complex64::new((j as f64).sqrt(), 0.0)
+        } else {
+            Complex64::new(0.0, 0.0)
+        }
+    })
+}
+
+fn gpu() -> Option<std::sync::Arc<GpuContext>> {
+    match GpuContext::shared() {
+        Ok(gpu) => Some(gpu),
+        Err(e) => {
+            eprintln!("skipping GPU benchmarks: {}", e);
+            None
+        }
+    }
+}
+
+fn benchmark_matmul(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(1);
+    let gpu = gpu();
+    let mut group = c.benchmark_group("matmul");
+
+    for dim in DIMS {
+        let a = random_hermitian(dim, &mut rng);
+        let b = random_hermitian(dim, &mut rng);
+        group.bench_with_input(BenchmarkId::new("cpu", dim), &dim, |bench, _| {
+            bench.iter(|| black_box(&a).dot(black_box(&b)))
+        });
+        if let Some(gpu) = &gpu {
+            group.bench_with_input(BenchmarkId::new("gpu", dim), &dim, |bench, _| {
+                bench.iter(|| gpu.matmul(black_box(&a), black_box(&b)).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn benchmark_tensor_product(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(2);
+    let gpu = gpu();
+    let mut group = c.benchmark_group("tensor_product");
+
+    // Factors of dimension √dim, so the output matches the other groups
+    for dim in DIMS {
+        let factor = (dim as f64).sqrt().round() as usize;
+        let a = random_hermitian(factor, &mut rng);
+        let b = random_hermitian(dim / factor, &mut rng);
+        group.bench_with_input(BenchmarkId::new("cpu", dim), &dim, |bench, _| {
+            bench.iter(|| kernels_cpu::tensor_product(black_box(&a), black_box(&b)).unwrap())
+        });
+        if let Some(gpu) = &gpu {
+            group.bench_with_input(BenchmarkId::new("gpu", dim), &dim, |bench, _| {
+                bench.iter(|| gpu.tensor_product(black_box(&a), black_box(&b)).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn benchmark_matrix_exp(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(3);
+    let gpu = gpu();
+    let mut group = c.benchmark_group("matrix_exp");
+    let minus_i = Complex64::new(0.0, -1.0);
+
+    for dim in DIMS {
+        let a = random_hermitian(dim, &mut rng).mapv(|z| z * minus_i);
+        group.bench_with_input(BenchmarkId::new("cpu", dim), &dim, |bench, _| {
+            bench.iter(|| kernels_cpu::matrix_exp(black_box(&a)).unwrap())
+        });
+        if let Some(gpu) = &gpu {
+            group.bench_with_input(BenchmarkId::new("gpu", dim), &dim, |bench, _| {
+                bench.iter(|| gpu.matrix_exp(black_box(&a)).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn benchmark_rk4(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(4);
+    let gpu = gpu();
+    let mut group = c.benchmark_group("lindblad_rk4");
+    group.sample_size(10);
+    let times: Vec<f64> = (0..=100).map(|k| k as f64 * 0.01).collect();
+
+    for dim in DIMS {
+        let h = random_hermitian(dim, &mut rng);
+        let ops = vec![(lowering(dim), 0.1)];
+        let mut psi = Array1::zeros(dim);
+        psi[dim - 1] = Complex64::new(1.0, 0.0);
+        let rho0 = kernels_cpu::ket_to_rho(&psi);
+
+        group.bench_with_input(BenchmarkId::new("cpu", dim), &dim, |bench, _| {
+            bench.iter(|| {
+                Rk4Integrator::new(h.clone(), ops.clone())
+                    .integrate(rho0.clone(), &times)
+                    .unwrap()
+            })
+        });
+        if let Some(gpu) = &gpu {
+            group.bench_with_input(BenchmarkId::new("gpu", dim), &dim, |bench, _| {
+                bench.iter(|| gpu.integrate_rk4(&h, &ops, &rho0, &times).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_matmul,
+    benchmark_tensor_product,
+    benchmark_matrix_exp,
+    benchmark_rk4
+);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! The smallest complete program: parse, validate, lower and execute a
+//! driven qubit, then print its excited-state population
+//!
+//! ```text
+//! cargo run --example minimal
+//! ```
+
+use quantum_theory_engine::{execute, lower, parse_dsl, validate_quantum, BackendConfig};
+
+const RABI: &str = r#"
+    const omega = 1.0;
+    const Omega = 0.2;
+    matrix sigma_z = [1, 0; 0, -1];
+    matrix sigma_x = [0, 1; 1, 0];
+    Hamiltonian H = (omega/2) * sigma_z + Omega * sigma_x;
+
+    experiment rabi {
+        init: ket(vec(1, 0));
+        evolution: evolve(init, H, timegrid=(0.0, 0.5, 21));
+    }
+"#;
+
+fn main() -> anyhow::Result<()> {
+    let ast = parse_dsl(RABI)?;
+    let validated = validate_quantum(&ast)?;
+    let ir = lower(&validated)?;
+    let result = execute(&ir, BackendConfig::default())?;
+
+    for experiment in &result.experiment_results {
+        println!("{}", experiment.name);
+        for (t, rho) in experiment.times.iter().zip(&experiment.states) {
+            println!("  t = {:5.2}  P(|1⟩) = {:.4}", t, rho[[1, 1]].re);
+        }
+    }
+    Ok(())
+}
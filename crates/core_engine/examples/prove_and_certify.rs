@@ -0,0 +1,86 @@
+//! Prove identities and properties, then write and re-check the certificates
+//!
+//! ```text
+//! cargo run --example prove_and_certify -- certificates.json
+//! ```
+
+use quantum_theory_engine::prover::{PropertyResult, ProverConfig};
+use quantum_theory_engine::{Expr, ProofResult, Property, Prover};
+
+fn ident(name: &str) -> Expr {
+    Expr::Identifier(name.to_string())
+}
+
+fn dagger(expr: Expr) -> Expr {
+    Expr::Dagger(Box::new(expr))
+}
+
+fn main() -> anyhow::Result<()> {
+    let output = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "certificates.json".to_string());
+
+    let mut prover = Prover::new(ProverConfig::default());
+    let mut certificates = Vec::new();
+
+    // Identities
+    let identities = [
+        (dagger(dagger(ident("A"))), ident("A")),
+        (
+            dagger(Expr::Mul(Box::new(ident("A")), Box::new(ident("B")))),
+            Expr::Mul(Box::new(dagger(ident("B"))), Box::new(dagger(ident("A")))),
+        ),
+    ];
+
+    for (lhs, rhs) in &identities {
+        match prover.prove_identity(lhs, rhs) {
+            ProofResult::Proven(proof) => {
+                anyhow::ensure!(prover.verify_proof(&proof), "proof trace failed to verify");
+                println!(
+                    "proven in {} steps: {:?} = {:?}",
+                    proof.steps.len(),
+                    lhs,
+                    rhs
+                );
+                certificates.push(serde_json::to_value(&proof.certificate)?);
+            }
+            ProofResult::Refuted(counterexample) => {
+                println!(
+                    "refuted: {:?} = {:?} (|lhs - rhs| = {:.3e})",
+                    lhs, rhs, counterexample.difference_norm
+                );
+            }
+            ProofResult::Unknown(reason) => {
+                println!("unknown: {:?} = {:?} ({:?})", lhs, rhs, reason)
+            }
+        }
+    }
+
+    // Properties
+    let hamiltonian = Expr::Add(
+        Box::new(ident("sigma_z")),
+        Box::new(dagger(ident("sigma_z"))),
+    );
+    let properties = [
+        Property::Hermitian(hamiltonian),
+        Property::CPTP(vec![ident("K0"), ident("K1")]),
+    ];
+
+    for property in properties {
+        let proof = prover.prove_property(property);
+        match &proof.result {
+            PropertyResult::Failed(reason) => {
+                println!("{:?}: failed ({:?})", proof.property, reason)
+            }
+            _ => println!("{:?}: ok", proof.property),
+        }
+        if let Some(certificate) = &proof.certificate {
+            certificates.push(serde_json::to_value(certificate)?);
+        }
+    }
+
+    std::fs::write(&output, serde_json::to_string_pretty(&certificates)?)?;
+    println!("wrote {} certificates to {}", certificates.len(), output);
+
+    Ok(())
+}
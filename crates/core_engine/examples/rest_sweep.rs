@@ -0,0 +1,123 @@
+//! Submit a parameter sweep over the REST API and collect the results
+//!
+//! ```text
+//! # against a running `qte server --port 8080`
+//! cargo run --example rest_sweep -- http://127.0.0.1:8080
+//!
+//! # or with an in-process server on a random port
+//! cargo run --example rest_sweep
+//! ```
+
+use quantum_theory_engine::job_queue::{
+    GridStrategy, JobResult, JobStatus, ParameterGrid, ParameterRange, ParameterScale,
+};
+use quantum_theory_engine::server::{self, SweepCreated};
+use quantum_theory_engine::JobQueue;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Amplitude damping of a qubit; the sweep sets `omega` and `gamma`
+const AMP_DAMP: &str = r#"
+    param omega;
+    param gamma;
+    matrix sigma_z = [[1, 0], [0, -1]];
+    matrix L = [[0, 1], [0, 0]];
+    Hamiltonian H = (omega/2) * sigma_z;
+    experiment amp_damp {
+        init: rho([[0, 0], [0, 1]]);
+        evolution: evolve(init, H, timegrid=(0.0, 0.05, 100));
+        lindblad: { L: L, rate: gamma };
+    }
+"#;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let base_url = match std::env::args().nth(1) {
+        Some(url) => url,
+        None => spawn_local_server().await?,
+    };
+    let client = reqwest::Client::new();
+
+    let grid = ParameterGrid {
+        params: vec![
+            ParameterRange {
+                name: "omega".to_string(),
+                start: 0.5,
+                end: 2.0,
+                steps: 4,
+                scale: ParameterScale::Linear,
+            },
+            ParameterRange {
+                name: "gamma".to_string(),
+                start: 1e-3,
+                end: 1e-1,
+                steps: 3,
+                scale: ParameterScale::Log,
+            },
+        ],
+        strategy: GridStrategy::FullGrid,
+    };
+
+    let SweepCreated { job_ids } = client
+        .post(format!("{}/sweeps", base_url))
+        .json(&json!({
+            "base": { "kind": { "Simulate": { "program": AMP_DAMP } } },
+            "grid": grid,
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    println!("Submitted {} jobs to {}", job_ids.len(), base_url);
+
+    for job_id in job_ids {
+        loop {
+            let status: JobStatus = client
+                .get(format!("{}/jobs/{}/status", base_url, job_id))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            match status {
+                JobStatus::Complete(_) => break,
+                JobStatus::Failed { error, .. } => {
+                    anyhow::bail!("job {} failed: {}", job_id, error)
+                }
+                JobStatus::Cancelled => anyhow::bail!("job {} was cancelled", job_id),
+                _ => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        }
+
+        let result: JobResult = client
+            .get(format!("{}/jobs/{}/result", base_url, job_id))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        println!("{}  {:>8.1?}  {:?}", job_id, result.duration, result.output);
+    }
+
+    Ok(())
+}
+
+/// Start a server on an ephemeral port and return its base URL
+async fn spawn_local_server() -> anyhow::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let queue = Arc::new(JobQueue::new(4));
+
+    tokio::spawn(server::serve_listener(
+        listener,
+        queue,
+        std::future::pending(),
+    ));
+
+    Ok(format!("http://{}", addr))
+}
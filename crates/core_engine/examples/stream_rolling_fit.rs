@@ -0,0 +1,91 @@
+//! Stream measurements over WebSocket into a rolling fit
+//!
+//! A local WebSocket server plays the role of the lab: it emits one JSON
+//! [`DataPoint`] per message, the same format `WebSocketSource` expects from
+//! real acquisition hardware.
+//!
+//! ```text
+//! cargo run --example stream_rolling_fit
+//! ```
+
+use futures_util::SinkExt;
+use quantum_theory_engine::streaming::StreamEvent;
+use quantum_theory_engine::{DataPoint, RollingFitEngine, StreamingManager};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+const NUM_POINTS: usize = 200;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("ws://{}", listener.local_addr()?);
+    tokio::spawn(emit_measurements(listener));
+
+    let mut manager = StreamingManager::new();
+    manager.add_websocket_source("lab".to_string(), url)?;
+    manager.start_source("lab")?;
+
+    // omega, gamma
+    let mut fit = RollingFitEngine::new(50, vec![1.0, 0.1]);
+    let mut received = 0;
+
+    while let Some(event) = manager.next_event().await {
+        match event {
+            StreamEvent::SourceConnected { source_id } => println!("connected: {}", source_id),
+            StreamEvent::NewData { data, .. } => {
+                fit.add_data(data);
+                received += 1;
+
+                if fit.should_refit() {
+                    let result = fit.refit()?;
+                    println!(
+                        "after {:>4} points: params = {:?} ± {:?}",
+                        received, result.best_params, result.uncertainties
+                    );
+                }
+            }
+            StreamEvent::SourceDisconnected { source_id, reason } => {
+                println!("disconnected: {} ({})", source_id, reason);
+                break;
+            }
+            StreamEvent::Dropped { count } => println!("fell behind: {} events dropped", count),
+            StreamEvent::DriftDetected { param, magnitude, .. } => {
+                println!("{} drifted by {:+.3}", param, magnitude)
+            }
+            StreamEvent::FitUpdated { .. } => {}
+        }
+    }
+
+    let (params, age) = fit.current_estimate();
+    println!(
+        "final estimate {:?} ({:?} old), {} points",
+        params, age, received
+    );
+
+    Ok(())
+}
+
+/// Serve one client a damped Rabi signal, then close the connection
+async fn emit_measurements(listener: TcpListener) -> anyhow::Result<()> {
+    let (stream, _) = listener.accept().await?;
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+
+    let (omega, gamma) = (2.0_f64, 0.05_f64);
+    for i in 0..NUM_POINTS {
+        let t = i as f64 * 0.05;
+        let point = DataPoint {
+            observable: "sigma_z".to_string(),
+            value: (omega * t).cos() * (-gamma * t).exp(),
+            uncertainty: 0.01,
+            metadata: serde_json::json!({ "t": t }),
+        };
+        ws.send(Message::Text(serde_json::to_string(&point)?))
+            .await?;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    ws.close(None).await?;
+    Ok(())
+}
@@ -0,0 +1,391 @@
+//! Windowed aggregation and downsampling of streaming data
+//!
+//! Instruments sampling at kHz rates produce far more points than a
+//! [`RollingFitEngine`](crate::streaming::RollingFitEngine) can refit on.
+//! An [`Aggregator`] sits between the sources and the fit, turning the
+//! points of each source and observable into fewer derived points:
+//!
+//! - [`Aggregation::TimeBucket`]: the mean of every interval of fixed
+//!   width, emitted once a point arrives in a later interval
+//! - [`Aggregation::Ewma`]: an exponentially weighted moving average,
+//!   emitted every `every` points
+//! - [`Aggregation::Decimate`]: every `every`-th point, unchanged
+//!
+//! Derived values carry propagated uncertainties, and their metadata
+//! averages the numeric metadata of the points they summarise in the same
+//! way, so a time stored under `"t"` for an [`ObservationModel`] stays
+//! meaningful; `"samples"` records how many points went in.
+//! [`StreamingManager::set_aggregator`] applies an aggregator to every
+//! [`StreamEvent::NewData`](crate::streaming::StreamEvent::NewData) before
+//! [`next_event`](crate::streaming::StreamingManager::next_event) returns
+//! it.
+//!
+//! [`ObservationModel`]: crate::streaming::ObservationModel
+//! [`StreamingManager::set_aggregator`]: crate::streaming::StreamingManager::set_aggregator
+
+use crate::error::{EngineError, Result};
+use crate::streaming::DataPoint;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How the points of one observable are reduced
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Aggregation {
+    /// Every point, unchanged
+    Passthrough,
+    /// Mean of the points in each interval of `window`, intervals aligned
+    /// to the Unix epoch
+    TimeBucket { window: Duration },
+    /// `s ← α x + (1 - α) s`, emitted every `every` points
+    Ewma { alpha: f64, every: usize },
+    /// Every `every`-th point, starting with the first
+    Decimate { every: usize },
+}
+
+impl Aggregation {
+    /// Check the window, smoothing factor and strides
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Aggregation::Passthrough => Ok(()),
+            Aggregation::TimeBucket { window } if window.is_zero() => Err(
+                EngineError::validation_error("Aggregation window must be positive"),
+            ),
+            Aggregation::Ewma { alpha, .. } if !(alpha > 0.0 && alpha <= 1.0) => {
+                Err(EngineError::validation_error(format!(
+                    "EWMA smoothing factor must be in (0, 1], got {}",
+                    alpha
+                )))
+            }
+            Aggregation::Ewma { every: 0, .. } | Aggregation::Decimate { every: 0 } => Err(
+                EngineError::validation_error("Aggregation stride must be at least 1"),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Running state of one source's observable
+#[derive(Debug, Clone)]
+enum Accumulator {
+    Bucket {
+        index: i64,
+        points: Vec<DataPoint>,
+        last: DateTime<Utc>,
+    },
+    Ewma {
+        value: f64,
+        variance: f64,
+        metadata: serde_json::Value,
+        samples: usize,
+        pending: usize,
+    },
+    Count(usize),
+}
+
+/// Reduces streamed points per source and observable, see the
+/// [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct Aggregator {
+    default: Aggregation,
+    per_observable: HashMap<String, Aggregation>,
+    state: HashMap<(String, String), Accumulator>,
+}
+
+impl Aggregator {
+    /// Apply `default` to every observable without its own aggregation
+    pub fn new(default: Aggregation) -> Result<Self> {
+        default.validate()?;
+        Ok(Self {
+            default,
+            per_observable: HashMap::new(),
+            state: HashMap::new(),
+        })
+    }
+
+    /// Reduce `observable` by `aggregation` instead of the default
+    pub fn with_observable(
+        mut self,
+        observable: impl Into<String>,
+        aggregation: Aggregation,
+    ) -> Result<Self> {
+        aggregation.validate()?;
+        self.per_observable.insert(observable.into(), aggregation);
+        Ok(self)
+    }
+
+    fn aggregation(&self, observable: &str) -> Aggregation {
+        self.per_observable
+            .get(observable)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Feed the point `point` received from `source_id` at `timestamp`,
+    /// returning the derived points it completes with their timestamps
+    pub fn push(
+        &mut self,
+        source_id: &str,
+        timestamp: DateTime<Utc>,
+        point: DataPoint,
+    ) -> Vec<(DateTime<Utc>, DataPoint)> {
+        let key = (source_id.to_string(), point.observable.clone());
+        match self.aggregation(&point.observable) {
+            Aggregation::Passthrough => vec![(timestamp, point)],
+            Aggregation::TimeBucket { window } => {
+                let width = (window.as_micros().min(i64::MAX as u128) as i64).max(1);
+                let index = timestamp.timestamp_micros().div_euclid(width);
+                let mut completed = Vec::new();
+                if let Some(Accumulator::Bucket {
+                    index: current,
+                    points,
+                    last,
+                }) = self.state.get_mut(&key)
+                {
+                    if *current == index {
+                        points.push(point);
+                        *last = timestamp;
+                        return completed;
+                    }
+                    completed.push((*last, mean(points)));
+                }
+                self.state.insert(
+                    key,
+                    Accumulator::Bucket {
+                        index,
+                        points: vec![point],
+                        last: timestamp,
+                    },
+                );
+                completed
+            }
+            Aggregation::Ewma { alpha, every } => {
+                let accumulator = self.state.entry(key).or_insert(Accumulator::Ewma {
+                    value: point.value,
+                    variance: point.uncertainty.powi(2),
+                    metadata: point.metadata.clone(),
+                    samples: 0,
+                    pending: 0,
+                });
+                let Accumulator::Ewma {
+                    value,
+                    variance,
+                    metadata,
+                    samples,
+                    pending,
+                } = accumulator
+                else {
+                    return Vec::new();
+                };
+                if *samples > 0 {
+                    *value = alpha * point.value + (1.0 - alpha) * *value;
+                    *variance = alpha.powi(2) * point.uncertainty.powi(2)
+                        + (1.0 - alpha).powi(2) * *variance;
+                    *metadata = blend(metadata, &point.metadata, alpha);
+                }
+                *samples += 1;
+                *pending += 1;
+                if *pending < every {
+                    return Vec::new();
+                }
+                *pending = 0;
+                let mut metadata = metadata.clone();
+                set_samples(&mut metadata, *samples);
+                vec![(
+                    timestamp,
+                    DataPoint {
+                        observable: point.observable,
+                        value: *value,
+                        uncertainty: variance.sqrt(),
+                        metadata,
+                    },
+                )]
+            }
+            Aggregation::Decimate { every } => {
+                let count = match self.state.entry(key).or_insert(Accumulator::Count(0)) {
+                    Accumulator::Count(count) => count,
+                    _ => return Vec::new(),
+                };
+                let keep = *count % every == 0;
+                *count += 1;
+                if keep {
+                    vec![(timestamp, point)]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+    }
+
+    /// Emit the unfinished time buckets of `source_id`, e.g. once it has
+    /// disconnected
+    pub fn flush(&mut self, source_id: &str) -> Vec<(DateTime<Utc>, DataPoint)> {
+        let keys: Vec<_> = self
+            .state
+            .keys()
+            .filter(|(source, _)| source == source_id)
+            .cloned()
+            .collect();
+        let mut flushed = Vec::new();
+        for key in keys {
+            if let Some(Accumulator::Bucket { points, last, .. }) = self.state.remove(&key) {
+                flushed.push((last, mean(&points)));
+            }
+        }
+        flushed.sort_by_key(|(timestamp, _)| *timestamp);
+        flushed
+    }
+}
+
+/// Mean of the non-empty `points` of one observable, with the propagated
+/// uncertainty `sqrt(Σσ²) / n`
+fn mean(points: &[DataPoint]) -> DataPoint {
+    let n = points.len() as f64;
+    let value = points.iter().map(|p| p.value).sum::<f64>() / n;
+    let uncertainty = points
+        .iter()
+        .map(|p| p.uncertainty.powi(2))
+        .sum::<f64>()
+        .sqrt()
+        / n;
+
+    let mut metadata = points[points.len() - 1].metadata.clone();
+    if let Some(fields) = metadata.as_object_mut() {
+        for (key, field) in fields.iter_mut() {
+            let values: Option<Vec<f64>> = points
+                .iter()
+                .map(|p| p.metadata.get(key).and_then(|v| v.as_f64()))
+                .collect();
+            if let Some(values) = values {
+                *field = serde_json::json!(values.iter().sum::<f64>() / n);
+            }
+        }
+    }
+    set_samples(&mut metadata, points.len());
+
+    DataPoint {
+        observable: points[0].observable.clone(),
+        value,
+        uncertainty,
+        metadata,
+    }
+}
+
+/// `previous` with each numeric field moved by `alpha` towards `latest`'s,
+/// and every other field taken from `latest`
+fn blend(
+    previous: &serde_json::Value,
+    latest: &serde_json::Value,
+    alpha: f64,
+) -> serde_json::Value {
+    let mut blended = latest.clone();
+    if let Some(fields) = blended.as_object_mut() {
+        for (key, field) in fields.iter_mut() {
+            if let (Some(new), Some(old)) =
+                (field.as_f64(), previous.get(key).and_then(|v| v.as_f64()))
+            {
+                *field = serde_json::json!(alpha * new + (1.0 - alpha) * old);
+            }
+        }
+    }
+    blended
+}
+
+fn set_samples(metadata: &mut serde_json::Value, samples: usize) {
+    if let Some(fields) = metadata.as_object_mut() {
+        fields.insert("samples".to_string(), serde_json::json!(samples));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(observable: &str, value: f64, t: f64) -> DataPoint {
+        DataPoint {
+            observable: observable.to_string(),
+            value,
+            uncertainty: 0.1,
+            metadata: serde_json::json!({ "t": t }),
+        }
+    }
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[test]
+    fn test_time_buckets_average_per_observable() {
+        let mut aggregator = Aggregator::new(Aggregation::TimeBucket {
+            window: Duration::from_millis(10),
+        })
+        .unwrap();
+
+        assert!(aggregator
+            .push("daq", at(0), point("z", 1.0, 0.0))
+            .is_empty());
+        assert!(aggregator
+            .push("daq", at(4), point("z", 3.0, 4.0))
+            .is_empty());
+        assert!(aggregator
+            .push("daq", at(5), point("x", 7.0, 5.0))
+            .is_empty());
+        // The first point of the next bucket completes the previous one
+        let completed = aggregator.push("daq", at(12), point("z", 5.0, 12.0));
+        assert_eq!(completed.len(), 1);
+        let (timestamp, mean) = &completed[0];
+        assert_eq!(*timestamp, at(4));
+        assert_eq!(mean.value, 2.0);
+        assert!((mean.uncertainty - 0.1 / 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(mean.metadata["t"], 2.0);
+        assert_eq!(mean.metadata["samples"], 2);
+
+        let flushed = aggregator.flush("daq");
+        assert_eq!(flushed.len(), 2);
+        assert_eq!(flushed[0].1.observable, "x");
+        assert_eq!(flushed[1].1.value, 5.0);
+        assert!(aggregator.flush("daq").is_empty());
+    }
+
+    #[test]
+    fn test_ewma_and_decimation() {
+        let mut aggregator = Aggregator::new(Aggregation::Decimate { every: 3 })
+            .unwrap()
+            .with_observable(
+                "z",
+                Aggregation::Ewma {
+                    alpha: 0.5,
+                    every: 2,
+                },
+            )
+            .unwrap();
+
+        let kept: Vec<f64> = (0..7)
+            .flat_map(|i| aggregator.push("daq", at(i), point("x", i as f64, 0.0)))
+            .map(|(_, p)| p.value)
+            .collect();
+        assert_eq!(kept, vec![0.0, 3.0, 6.0]);
+
+        assert!(aggregator
+            .push("daq", at(0), point("z", 0.0, 0.0))
+            .is_empty());
+        let smoothed = aggregator.push("daq", at(1), point("z", 4.0, 2.0));
+        assert_eq!(smoothed.len(), 1);
+        assert_eq!(smoothed[0].1.value, 2.0);
+        assert_eq!(smoothed[0].1.metadata["t"], 1.0);
+        assert!((smoothed[0].1.uncertainty - (0.5f64 * 0.01).sqrt()).abs() < 1e-12);
+
+        assert!(Aggregator::new(Aggregation::Ewma {
+            alpha: 0.0,
+            every: 1
+        })
+        .is_err());
+        assert!(Aggregator::new(Aggregation::Decimate { every: 0 }).is_err());
+        assert!(Aggregator::new(Aggregation::TimeBucket {
+            window: Duration::ZERO
+        })
+        .is_err());
+    }
+}
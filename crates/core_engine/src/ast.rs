@@ -1,12 +1,21 @@
 //! Abstract Syntax Tree (AST) definitions for the quantum DSL
 
+use crate::error::EngineError;
 use num_complex::Complex64;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
 /// Complete program AST
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ast {
     pub statements: Vec<Statement>,
+    /// Source positions of the parsed statements; empty for programs built
+    /// in code
+    #[serde(skip)]
+    pub source_map: SourceMap,
 }
 
 /// Top-level statements
@@ -19,10 +28,22 @@ pub enum Statement {
     SymbolDecl {
         name: String,
     },
+    /// Parameter whose value is bound at run time, optionally restricted
+    /// to a closed interval
+    ParamDecl {
+        name: String,
+        range: Option<(f64, f64)>,
+    },
     MatrixDecl {
         name: String,
         value: MatrixLiteral,
     },
+    /// Builtin operator on a truncated space, `operator a: Fock<10> =
+    /// annihilation;`, held as the call `annihilation(10)` that builds it
+    OperatorDecl {
+        name: String,
+        value: Expr,
+    },
     FunctionDef {
         name: String,
         params: Vec<String>,
@@ -37,13 +58,134 @@ pub enum Statement {
         name: String,
         spec: MeasurementSpec,
     },
+    /// Quantum channel ρ ↦ Σ_k K_k ρ K_k† given by its Kraus operators
+    ChannelDef {
+        name: String,
+        kraus: Vec<Expr>,
+    },
     Experiment {
         name: String,
         body: ExperimentBody,
     },
+    /// Statements repeated for every value of `param`, expanded into one
+    /// program per value by `sweep::expand_sweeps`
+    Sweep {
+        param: String,
+        values: SweepValues,
+        body: Vec<Statement>,
+    },
+    /// `assume <goal>;`: a hypothesis for the `prove` statements after it
+    Assume(ProofGoal),
+    /// `prove <goal>;`: checked by the prover during validation, which
+    /// fails if the goal is refuted
+    Prove(ProofGoal),
+}
+
+/// Claim about operators made by `assume` and `prove`, whose identifiers
+/// stand for arbitrary operators
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProofGoal {
+    /// `lhs == rhs`
+    Identity { lhs: Expr, rhs: Expr },
+    /// `hermitian(H)` and the like
+    Property { kind: PropertyKind, expr: Expr },
+}
+
+/// Operator property a goal can claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PropertyKind {
+    Hermitian,
+    Unitary,
+    Idempotent,
+    TraceOne,
+}
+
+impl PropertyKind {
+    pub const ALL: [PropertyKind; 4] = [
+        PropertyKind::Hermitian,
+        PropertyKind::Unitary,
+        PropertyKind::Idempotent,
+        PropertyKind::TraceOne,
+    ];
+
+    /// Name in the DSL, as in `hermitian(H)`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PropertyKind::Hermitian => "hermitian",
+            PropertyKind::Unitary => "unitary",
+            PropertyKind::Idempotent => "idempotent",
+            PropertyKind::TraceOne => "trace_one",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+impl ProofGoal {
+    /// The identity the goal amounts to: `hermitian(H)` is `dagger(H) ==
+    /// H`, `unitary(U)` is `dagger(U) * U == I`, `idempotent(P)` is `P * P
+    /// == P` and `trace_one(rho)` is `trace(rho) == 1`
+    pub fn identity(&self) -> (Expr, Expr) {
+        let (kind, expr) = match self {
+            ProofGoal::Identity { lhs, rhs } => return (lhs.clone(), rhs.clone()),
+            ProofGoal::Property { kind, expr } => (kind, expr),
+        };
+        let operand = || Box::new(expr.clone());
+        match kind {
+            PropertyKind::Hermitian => (Expr::Dagger(operand()), expr.clone()),
+            PropertyKind::Unitary => (
+                Expr::Mul(Box::new(Expr::Dagger(operand())), operand()),
+                Expr::Identifier("I".to_string()),
+            ),
+            PropertyKind::Idempotent => (Expr::Mul(operand(), operand()), expr.clone()),
+            PropertyKind::TraceOne => (Expr::Trace(operand()), Expr::Number(1.0)),
+        }
+    }
+
+    /// The expressions the goal is made of
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            ProofGoal::Identity { lhs, rhs } => vec![lhs, rhs],
+            ProofGoal::Property { expr, .. } => vec![expr],
+        }
+    }
+
+    pub fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            ProofGoal::Identity { lhs, rhs } => vec![lhs, rhs],
+            ProofGoal::Property { expr, .. } => vec![expr],
+        }
+    }
+}
+
+impl fmt::Display for ProofGoal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofGoal::Identity { lhs, rhs } => write!(f, "{} == {}", lhs, rhs),
+            ProofGoal::Property { kind, expr } => write!(f, "{}({})", kind.as_str(), expr),
+        }
+    }
+}
+
+/// Values taken by a swept parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SweepValues {
+    /// `linspace(start, end, steps)`: evenly spaced, both ends included
+    Linspace { start: f64, end: f64, steps: usize },
+    /// `geomspace(start, end, steps)`: evenly spaced on a log scale
+    Geomspace { start: f64, end: f64, steps: usize },
+    /// `[v1, v2, ...]`
+    List(Vec<f64>),
 }
 
 /// Expressions in the DSL
+///
+/// Equality and hashing are structural. Numeric literals compare by value
+/// with two exceptions that keep `Eq` lawful: `-0.0` equals `0.0`, and all
+/// NaNs equal each other. Numbers are never compared with a tolerance, so
+/// `0.1 + 0.2` and `0.3` stay distinct; use the prover for that.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expr {
     // Literals
@@ -82,13 +224,13 @@ pub enum Expr {
 }
 
 /// Matrix literal (2D array of expressions)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MatrixLiteral {
     pub rows: Vec<Vec<Expr>>,
 }
 
 /// Vector literal (1D array of expressions)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct VectorLiteral {
     pub elements: Vec<Expr>,
 }
@@ -98,6 +240,31 @@ pub struct VectorLiteral {
 pub enum MeasurementSpec {
     Projective { projectors: Vec<MatrixLiteral> },
     POVM { effects: Vec<MatrixLiteral> },
+    /// A standard basis, expanded to projectors by `bases::basis_projectors`
+    Basis { basis: MeasurementBasis, dim: usize },
+}
+
+/// Standard measurement bases that can be named instead of listing projectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeasurementBasis {
+    /// `|k⟩` for k = 0..dim
+    Computational,
+    /// Eigenbasis of σ_x on every qubit of a register
+    X,
+    /// Eigenbasis of σ_y on every qubit of a register
+    Y,
+    /// `|Φ+⟩, |Φ-⟩, |Ψ+⟩, |Ψ-⟩` on two qubits
+    Bell,
+}
+
+impl MeasurementBasis {
+    /// Dimension used when a `measure` statement does not give one
+    pub fn default_dim(self) -> usize {
+        match self {
+            MeasurementBasis::Bell => 4,
+            _ => 2,
+        }
+    }
 }
 
 /// Experiment body containing initialization, evolution, and measurements
@@ -106,6 +273,15 @@ pub struct ExperimentBody {
     pub init: Option<StateSpec>,
     pub evolution: Option<EvolutionSpec>,
     pub measurements: Option<MeasurementSchedule>,
+    /// Channels applied to the state at given times
+    #[serde(default)]
+    pub channels: Vec<ChannelEvent>,
+    /// Operators whose expectation values are recorded at every time step
+    #[serde(default)]
+    pub observables: Vec<TrackedObservable>,
+    /// Gates applied to the initial state in order, before the evolution
+    #[serde(default)]
+    pub gates: Vec<GateApplication>,
 }
 
 /// Initial state specification
@@ -113,6 +289,11 @@ pub struct ExperimentBody {
 pub enum StateSpec {
     Ket(VectorLiteral),
     Rho(MatrixLiteral),
+    /// `from(name.final_state)`: the state another experiment ends in
+    From(String),
+    /// `eigenstate(H, k)`: the eigenvector of level `k` of the Hamiltonian
+    /// `H`, counting from the lowest
+    Eigenstate { hamiltonian: String, level: usize },
 }
 
 /// Evolution specification
@@ -157,9 +338,491 @@ pub struct MeasurementEvent {
     pub measurement_name: String,
 }
 
+/// Application of a declared channel at a time of the evolution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelEvent {
+    pub time: f64,
+    pub channel_name: String,
+}
+
+/// `apply <gate> to <state>[q1, q2, ...];` in an experiment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateApplication {
+    /// A gate of `gates::gate_library` or a declared matrix
+    pub gate: String,
+    /// Angle of a rotation gate, as in `RX(theta)`
+    pub angle: Option<Expr>,
+    /// Qubits acted on, the most significant first; empty is the whole
+    /// register
+    pub targets: Vec<usize>,
+}
+
+/// `<expr>` in an experiment's `observables: track ...` list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedObservable {
+    /// The expression as written, e.g. `sigma_x * sigma_y`
+    pub label: String,
+    pub expr: Expr,
+}
+
+/// A fact about a scalar symbol, or an operator identity, that the prover
+/// may rely on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assumption {
+    pub name: String,
+    pub kind: AssumptionKind,
+}
+
+/// Constraint on the values of a scalar symbol, or a hypothesis naming an
+/// operator identity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AssumptionKind {
+    /// `min ≤ x ≤ max`; a missing bound is unbounded
+    Range { min: Option<f64>, max: Option<f64> },
+    Sign(Sign),
+    /// `x` is an integer
+    Integer,
+    /// `lhs = rhs`, which the prover uses as the rewrite `lhs → rhs`; a
+    /// hypothesis `[A, B] = 0` also lets it swap `A * B` and `B * A`
+    Equation { lhs: Expr, rhs: Expr },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sign {
+    Positive,
+    Nonnegative,
+    Negative,
+    Nonpositive,
+    Nonzero,
+}
+
+impl Assumption {
+    pub fn new(name: impl Into<String>, kind: AssumptionKind) -> Self {
+        Assumption {
+            name: name.into(),
+            kind,
+        }
+    }
+
+    /// `min ≤ name ≤ max`
+    pub fn range(name: impl Into<String>, min: Option<f64>, max: Option<f64>) -> Self {
+        Self::new(name, AssumptionKind::Range { min, max })
+    }
+
+    pub fn sign(name: impl Into<String>, sign: Sign) -> Self {
+        Self::new(name, AssumptionKind::Sign(sign))
+    }
+
+    pub fn integer(name: impl Into<String>) -> Self {
+        Self::new(name, AssumptionKind::Integer)
+    }
+
+    /// The hypothesis `lhs = rhs`, named after its DSL form
+    pub fn equation(lhs: Expr, rhs: Expr) -> Self {
+        Self::new(format!("{} = {}", lhs, rhs), AssumptionKind::Equation { lhs, rhs })
+    }
+
+    /// The hypothesis `[a, b] = 0`
+    pub fn commutes(a: Expr, b: Expr) -> Self {
+        Self::equation(Expr::Commutator(Box::new(a), Box::new(b)), Expr::Number(0.0))
+    }
+}
+
+impl AssumptionKind {
+    /// Whether the value `x` satisfies this constraint; hypotheses about
+    /// operators admit every value
+    pub fn admits(&self, x: f64) -> bool {
+        match self {
+            AssumptionKind::Range { min, max } => {
+                min.is_none_or(|m| x >= m) && max.is_none_or(|m| x <= m)
+            }
+            AssumptionKind::Sign(Sign::Positive) => x > 0.0,
+            AssumptionKind::Sign(Sign::Nonnegative) => x >= 0.0,
+            AssumptionKind::Sign(Sign::Negative) => x < 0.0,
+            AssumptionKind::Sign(Sign::Nonpositive) => x <= 0.0,
+            AssumptionKind::Sign(Sign::Nonzero) => x != 0.0,
+            AssumptionKind::Integer => x.fract() == 0.0,
+            AssumptionKind::Equation { .. } => true,
+        }
+    }
+}
+
 impl Ast {
     pub fn new(statements: Vec<Statement>) -> Self {
-        Ast { statements }
+        Ast {
+            statements,
+            source_map: SourceMap::default(),
+        }
+    }
+}
+
+/// 1-based line and column in DSL source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Where each top-level statement, and each expression inside it, starts
+///
+/// Expressions are looked up structurally, so a subexpression that occurs
+/// twice in a statement resolves to its first occurrence.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    statements: Vec<StatementSpans>,
+    /// Leading statements with no position in this source, such as
+    /// declarations imported from other files
+    unmapped: usize,
+}
+
+#[derive(Debug, Clone)]
+struct StatementSpans {
+    start: Span,
+    exprs: HashMap<u64, Span>,
+}
+
+impl SourceMap {
+    /// Start recording the next statement
+    pub fn push_statement(&mut self, start: Span) {
+        self.statements.push(StatementSpans {
+            start,
+            exprs: HashMap::new(),
+        });
+    }
+
+    /// Record where `expr` appears in the last pushed statement, unless it
+    /// already appeared earlier
+    pub fn record_expr(&mut self, expr: &Expr, span: Span) {
+        if let Some(stmt) = self.statements.last_mut() {
+            stmt.exprs.entry(expr_key(expr)).or_insert(span);
+        }
+    }
+
+    /// Shift the recorded statements behind `count` statements without
+    /// positions
+    pub fn prepend_unmapped(&mut self, count: usize) {
+        self.unmapped += count;
+    }
+
+    fn entry(&self, index: usize) -> Option<&StatementSpans> {
+        self.statements.get(index.checked_sub(self.unmapped)?)
+    }
+
+    pub fn statement(&self, index: usize) -> Option<Span> {
+        self.entry(index).map(|s| s.start)
+    }
+
+    pub fn expr(&self, index: usize, expr: &Expr) -> Option<Span> {
+        self.entry(index)?
+            .exprs
+            .get(&expr_key(expr))
+            .copied()
+    }
+
+    /// Where `expr` appears within statement `index`, or the start of the
+    /// statement if `expr` isn't known
+    pub fn span(&self, index: usize, expr: Option<&Expr>) -> Option<Span> {
+        expr.and_then(|e| self.expr(index, e))
+            .or_else(|| self.statement(index))
+    }
+
+    /// `error` located at `expr` within statement `index`, or at the start
+    /// of the statement if `expr` isn't known
+    pub fn locate(&self, index: usize, expr: Option<&Expr>, error: EngineError) -> EngineError {
+        match self.span(index, expr) {
+            Some(span) => error.at(span),
+            None => error,
+        }
+    }
+}
+
+fn expr_key(expr: &Expr) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    expr.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Bit pattern used for float equality and hashing: `-0.0` is folded into
+/// `0.0` and every NaN into the canonical NaN
+fn float_bits(x: f64) -> u64 {
+    if x == 0.0 {
+        0.0f64.to_bits()
+    } else if x.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        x.to_bits()
+    }
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        use Expr::*;
+        match (self, other) {
+            (Number(a), Number(b)) => float_bits(*a) == float_bits(*b),
+            (ComplexNumber(a), ComplexNumber(b)) => {
+                float_bits(a.re) == float_bits(b.re) && float_bits(a.im) == float_bits(b.im)
+            }
+            (Identifier(a), Identifier(b)) => a == b,
+            (Matrix(a), Matrix(b)) => a == b,
+            (Vector(a), Vector(b)) => a == b,
+
+            (Add(a1, a2), Add(b1, b2))
+            | (Sub(a1, a2), Sub(b1, b2))
+            | (Mul(a1, a2), Mul(b1, b2))
+            | (Div(a1, a2), Div(b1, b2))
+            | (Pow(a1, a2), Pow(b1, b2))
+            | (Tensor(a1, a2), Tensor(b1, b2))
+            | (Commutator(a1, a2), Commutator(b1, b2))
+            | (AntiCommutator(a1, a2), AntiCommutator(b1, b2)) => a1 == b1 && a2 == b2,
+
+            (Dagger(a), Dagger(b))
+            | (Trace(a), Trace(b))
+            | (Expm(a), Expm(b))
+            | (Sqrt(a), Sqrt(b))
+            | (Sin(a), Sin(b))
+            | (Cos(a), Cos(b))
+            | (Exp(a), Exp(b)) => a == b,
+
+            (FuncCall { name: n1, args: a1 }, FuncCall { name: n2, args: a2 }) => {
+                n1 == n2 && a1 == a2
+            }
+
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Expr {}
+
+impl Hash for Expr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use Expr::*;
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Number(x) => float_bits(*x).hash(state),
+            ComplexNumber(c) => {
+                float_bits(c.re).hash(state);
+                float_bits(c.im).hash(state);
+            }
+            Identifier(name) => name.hash(state),
+            Matrix(m) => m.hash(state),
+            Vector(v) => v.hash(state),
+
+            Add(a, b) | Sub(a, b) | Mul(a, b) | Div(a, b) | Pow(a, b) | Tensor(a, b)
+            | Commutator(a, b) | AntiCommutator(a, b) => {
+                a.hash(state);
+                b.hash(state);
+            }
+
+            Dagger(a) | Trace(a) | Expm(a) | Sqrt(a) | Sin(a) | Cos(a) | Exp(a) => a.hash(state),
+
+            FuncCall { name, args } => {
+                name.hash(state);
+                args.hash(state);
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Render as LaTeX math (without surrounding `$`), e.g.
+    /// `\left(A B\right)^\dagger` for `dagger(A * B)`
+    pub fn to_latex(&self) -> String {
+        use Expr::*;
+        match self {
+            Number(x) => latex_number(*x),
+            ComplexNumber(c) => {
+                if c.im == 0.0 {
+                    latex_number(c.re)
+                } else {
+                    let im = match c.im.abs() {
+                        1.0 => String::new(),
+                        m => latex_number(m),
+                    };
+                    let sign = if c.im < 0.0 { "-" } else { "+" };
+                    if c.re == 0.0 {
+                        format!("{}{}i", if c.im < 0.0 { "-" } else { "" }, im)
+                    } else {
+                        format!("{} {} {}i", latex_number(c.re), sign, im)
+                    }
+                }
+            }
+            Identifier(name) => latex_identifier(name),
+            Matrix(m) => {
+                let rows: Vec<String> = m
+                    .rows
+                    .iter()
+                    .map(|row| row.iter().map(Expr::to_latex).collect::<Vec<_>>().join(" & "))
+                    .collect();
+                format!("\\begin{{pmatrix}} {} \\end{{pmatrix}}", rows.join(" \\\\ "))
+            }
+            Vector(v) => {
+                let elements: Vec<String> = v.elements.iter().map(Expr::to_latex).collect();
+                format!("\\begin{{pmatrix}} {} \\end{{pmatrix}}", elements.join(" \\\\ "))
+            }
+
+            Add(a, b) => format!("{} + {}", a.to_latex(), b.latex_operand(1)),
+            Sub(a, b) => format!("{} - {}", a.to_latex(), b.latex_operand(2)),
+            Mul(a, b) => {
+                // Juxtaposition, except between two numbers
+                let sep = if matches!(**b, Number(_) | ComplexNumber(_)) { " \\cdot " } else { " " };
+                format!("{}{}{}", a.latex_operand(2), sep, b.latex_operand(2))
+            }
+            Div(a, b) => format!("\\frac{{{}}}{{{}}}", a.to_latex(), b.to_latex()),
+            Pow(a, b) => format!("{}^{{{}}}", a.latex_operand(3), b.to_latex()),
+            Tensor(a, b) => format!("{} \\otimes {}", a.latex_operand(2), b.latex_operand(2)),
+
+            Dagger(a) => format!("{}^\\dagger", a.latex_operand(3)),
+            Trace(a) => format!("\\operatorname{{Tr}}\\left({}\\right)", a.to_latex()),
+            Commutator(a, b) => format!("\\left[{}, {}\\right]", a.to_latex(), b.to_latex()),
+            AntiCommutator(a, b) => format!("\\left\\{{{}, {}\\right\\}}", a.to_latex(), b.to_latex()),
+
+            Expm(a) | Exp(a) => format!("e^{{{}}}", a.to_latex()),
+            Sqrt(a) => format!("\\sqrt{{{}}}", a.to_latex()),
+            Sin(a) => format!("\\sin\\left({}\\right)", a.to_latex()),
+            Cos(a) => format!("\\cos\\left({}\\right)", a.to_latex()),
+
+            FuncCall { name, args } => {
+                let args: Vec<String> = args.iter().map(Expr::to_latex).collect();
+                format!(
+                    "\\operatorname{{{}}}\\left({}\\right)",
+                    name.replace('_', "\\_"),
+                    args.join(", ")
+                )
+            }
+        }
+    }
+
+    /// Binding strength in LaTeX output: sums 1, products 2, powers 3,
+    /// atoms 4
+    fn latex_precedence(&self) -> u8 {
+        use Expr::*;
+        match self {
+            Add(..) | Sub(..) => 1,
+            Mul(..) | Tensor(..) => 2,
+            Pow(..) | Dagger(..) => 3,
+            Number(x) if *x < 0.0 => 1,
+            ComplexNumber(c) if c.re != 0.0 && c.im != 0.0 => 1,
+            _ => 4,
+        }
+    }
+
+    /// LaTeX for an operand that must bind at least as tightly as `min`
+    fn latex_operand(&self, min: u8) -> String {
+        if self.latex_precedence() < min {
+            format!("\\left({}\\right)", self.to_latex())
+        } else {
+            self.to_latex()
+        }
+    }
+}
+
+fn latex_number(x: f64) -> String {
+    if x.fract() == 0.0 && x.abs() < 1e15 {
+        format!("{}", x as i64)
+    } else {
+        format!("{}", x)
+    }
+}
+
+/// Greek letter names become their LaTeX commands, and a `_suffix` becomes
+/// a subscript: `sigma_x` renders as `\sigma_{x}`
+fn latex_identifier(name: &str) -> String {
+    const GREEK: &[&str] = &[
+        "alpha", "beta", "gamma", "Gamma", "delta", "Delta", "epsilon", "zeta", "eta", "theta",
+        "Theta", "kappa", "lambda", "Lambda", "mu", "nu", "xi", "Xi", "pi", "Pi", "rho", "sigma",
+        "Sigma", "tau", "phi", "Phi", "chi", "psi", "Psi", "omega", "Omega",
+    ];
+    let symbol = |s: &str| {
+        if GREEK.contains(&s) {
+            format!("\\{}", s)
+        } else if s.chars().count() == 1 {
+            s.to_string()
+        } else {
+            format!("\\mathrm{{{}}}", s)
+        }
+    };
+    match name.split_once('_') {
+        Some((base, sub)) if !base.is_empty() && !sub.is_empty() => {
+            format!("{}_{{{}}}", symbol(base), latex_identifier(sub))
+        }
+        _ => symbol(name),
+    }
+}
+
+/// DSL syntax, e.g. `dagger(A * B) - dagger(B) * dagger(A)`
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Expr::*;
+        match self {
+            Number(x) => write!(f, "{}", latex_number(*x)),
+            ComplexNumber(c) if c.im == 0.0 => write!(f, "{}", latex_number(c.re)),
+            ComplexNumber(c) if c.re == 0.0 => write!(f, "{}i", latex_number(c.im)),
+            ComplexNumber(c) => {
+                let sign = if c.im < 0.0 { "-" } else { "+" };
+                write!(f, "({} {} {}i)", latex_number(c.re), sign, latex_number(c.im.abs()))
+            }
+            Identifier(name) => write!(f, "{}", name),
+            Matrix(m) => {
+                let rows: Vec<String> = m
+                    .rows
+                    .iter()
+                    .map(|row| row.iter().map(Expr::to_string).collect::<Vec<_>>().join(", "))
+                    .collect();
+                write!(f, "[{}]", rows.join("; "))
+            }
+            Vector(v) => {
+                let elements: Vec<String> = v.elements.iter().map(Expr::to_string).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+
+            Add(a, b) => write!(f, "{} + {}", a, b.dsl_operand(1)),
+            Sub(a, b) => write!(f, "{} - {}", a, b.dsl_operand(2)),
+            Mul(a, b) => write!(f, "{} * {}", a.dsl_operand(2), b.dsl_operand(3)),
+            Div(a, b) => write!(f, "{} / {}", a.dsl_operand(2), b.dsl_operand(3)),
+            Pow(a, b) => write!(f, "{}^{}", a.dsl_operand(4), b.dsl_operand(4)),
+            Tensor(a, b) => write!(f, "tensor({}, {})", a, b),
+
+            Dagger(a) => write!(f, "dagger({})", a),
+            Trace(a) => write!(f, "trace({})", a),
+            Commutator(a, b) => write!(f, "commutator({}, {})", a, b),
+            AntiCommutator(a, b) => write!(f, "anticommutator({}, {})", a, b),
+
+            Expm(a) => write!(f, "expm({})", a),
+            Sqrt(a) => write!(f, "sqrt({})", a),
+            Sin(a) => write!(f, "sin({})", a),
+            Cos(a) => write!(f, "cos({})", a),
+            Exp(a) => write!(f, "exp({})", a),
+
+            FuncCall { name, args } => {
+                let args: Vec<String> = args.iter().map(Expr::to_string).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Binding strength in DSL output: sums 1, products 2, powers 3, atoms
+    /// and calls 4
+    fn dsl_precedence(&self) -> u8 {
+        use Expr::*;
+        match self {
+            Add(..) | Sub(..) => 1,
+            Mul(..) | Div(..) => 2,
+            Pow(..) => 3,
+            Number(x) if *x < 0.0 => 1,
+            _ => 4,
+        }
+    }
+
+    /// DSL for an operand that must bind at least as tightly as `min`
+    fn dsl_operand(&self, min: u8) -> String {
+        if self.dsl_precedence() < min {
+            format!("({})", self)
+        } else {
+            self.to_string()
+        }
     }
 }
 
@@ -212,4 +875,70 @@ mod tests {
         assert_eq!(grid.len(), 3);
         assert_eq!(grid.get_times(), vec![0.0, 0.5, 1.0]);
     }
+
+    #[test]
+    fn test_expr_to_latex() {
+        let id = |name: &str| Box::new(Expr::Identifier(name.to_string()));
+        let num = |x: f64| Box::new(Expr::Number(x));
+
+        let dagger_product = Expr::Dagger(Box::new(Expr::Mul(id("A"), id("B"))));
+        assert_eq!(dagger_product.to_latex(), r"\left(A B\right)^\dagger");
+
+        let hamiltonian = Expr::Add(
+            Box::new(Expr::Mul(Box::new(Expr::Div(id("omega"), num(2.0))), id("sigma_z"))),
+            Box::new(Expr::Mul(id("Omega"), id("sigma_x"))),
+        );
+        assert_eq!(hamiltonian.to_latex(), r"\frac{\omega}{2} \sigma_{z} + \Omega \sigma_{x}");
+
+        let nested = Expr::Sub(id("A"), Box::new(Expr::Sub(id("B"), id("C"))));
+        assert_eq!(nested.to_latex(), r"A - \left(B - C\right)");
+
+        let commutator = Expr::Commutator(id("H"), Box::new(Expr::Tensor(id("rho"), id("I"))));
+        assert_eq!(commutator.to_latex(), r"\left[H, \rho \otimes I\right]");
+
+        let matrix = Expr::Matrix(MatrixLiteral {
+            rows: vec![vec![Expr::Number(1.0), Expr::Number(0.0)], vec![Expr::Number(0.0), Expr::Number(-1.0)]],
+        });
+        assert_eq!(matrix.to_latex(), r"\begin{pmatrix} 1 & 0 \\ 0 & -1 \end{pmatrix}");
+        assert_eq!(Expr::ComplexNumber(Complex64::new(0.0, -1.0)).to_latex(), "-i");
+    }
+
+    #[test]
+    fn test_expr_display_is_dsl() {
+        let id = |name: &str| Box::new(Expr::Identifier(name.to_string()));
+
+        let dagger_product = Expr::Dagger(Box::new(Expr::Mul(id("A"), id("B"))));
+        assert_eq!(dagger_product.to_string(), "dagger(A * B)");
+
+        let nested = Expr::Mul(id("A"), Box::new(Expr::Add(id("B"), Box::new(Expr::Number(2.0)))));
+        assert_eq!(nested.to_string(), "A * (B + 2)");
+
+        let commutator = Expr::Commutator(id("H"), Box::new(Expr::Pow(id("X"), Box::new(Expr::Number(2.0)))));
+        assert_eq!(commutator.to_string(), "commutator(H, X^2)");
+    }
+
+    fn hash_of(expr: &Expr) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_expr_structural_equality() {
+        let a = Expr::Dagger(Box::new(Expr::Identifier("H".to_string())));
+        let b = Expr::Dagger(Box::new(Expr::Identifier("H".to_string())));
+        let c = Expr::Trace(Box::new(Expr::Identifier("H".to_string())));
+
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_expr_float_policy() {
+        assert_eq!(Expr::Number(0.0), Expr::Number(-0.0));
+        assert_eq!(hash_of(&Expr::Number(0.0)), hash_of(&Expr::Number(-0.0)));
+        assert_eq!(Expr::Number(f64::NAN), Expr::Number(f64::NAN));
+        assert_ne!(Expr::Number(0.1 + 0.2), Expr::Number(0.3));
+    }
 }
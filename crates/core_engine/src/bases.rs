@@ -0,0 +1,138 @@
+//! Standard measurement bases
+//!
+//! Projectors for the bases a `measure` statement can name instead of
+//! listing matrices, e.g. `measure z: computational(4);` or
+//! `measure b: Bell;`. The X and Y bases are product bases over a register
+//! of qubits, with outcome `k` in binary giving each qubit's result, most
+//! significant bit first, matching the ordering of `tensor`.
+
+use crate::ast::MeasurementBasis;
+use crate::error::{EngineError, Result};
+use crate::kernels_cpu::{ket_to_rho, qubit_dims};
+use ndarray::{Array1, Array2};
+use num_complex::Complex64;
+
+/// Check that `basis` exists in dimension `dim`, without building it
+pub fn check_basis_dim(basis: MeasurementBasis, dim: usize) -> Result<()> {
+    match basis {
+        MeasurementBasis::Computational if dim < 2 => Err(EngineError::validation_error(format!(
+            "Computational basis needs dimension at least 2, got {}",
+            dim
+        ))),
+        MeasurementBasis::X | MeasurementBasis::Y => qubit_dims(dim).map(|_| ()),
+        MeasurementBasis::Bell if dim != 4 => Err(EngineError::validation_error(format!(
+            "Bell basis is defined on two qubits (dimension 4), got {}",
+            dim
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Rank-one projectors onto the vectors of `basis` in dimension `dim`
+pub fn basis_projectors(basis: MeasurementBasis, dim: usize) -> Result<Vec<Array2<Complex64>>> {
+    check_basis_dim(basis, dim)?;
+    Ok(basis_vectors(basis, dim).iter().map(ket_to_rho).collect())
+}
+
+fn basis_vectors(basis: MeasurementBasis, dim: usize) -> Vec<Array1<Complex64>> {
+    let r = std::f64::consts::FRAC_1_SQRT_2;
+    let c = |re: f64, im: f64| Complex64::new(re, im);
+    let ket = |amplitudes: [Complex64; 4]| Array1::from_vec(amplitudes.to_vec());
+    match basis {
+        MeasurementBasis::Computational => (0..dim)
+            .map(|k| {
+                let mut v = Array1::zeros(dim);
+                v[k] = c(1.0, 0.0);
+                v
+            })
+            .collect(),
+        MeasurementBasis::X => {
+            product_basis([[c(r, 0.0), c(r, 0.0)], [c(r, 0.0), c(-r, 0.0)]], dim)
+        }
+        MeasurementBasis::Y => {
+            product_basis([[c(r, 0.0), c(0.0, r)], [c(r, 0.0), c(0.0, -r)]], dim)
+        }
+        MeasurementBasis::Bell => {
+            let z = c(0.0, 0.0);
+            vec![
+                ket([c(r, 0.0), z, z, c(r, 0.0)]),
+                ket([c(r, 0.0), z, z, c(-r, 0.0)]),
+                ket([z, c(r, 0.0), c(r, 0.0), z]),
+                ket([z, c(r, 0.0), c(-r, 0.0), z]),
+            ]
+        }
+    }
+}
+
+/// All tensor products of the two single-qubit `vectors` over a register
+/// of dimension `dim`
+fn product_basis(vectors: [[Complex64; 2]; 2], dim: usize) -> Vec<Array1<Complex64>> {
+    let mut basis = vec![Array1::from_elem(1, Complex64::new(1.0, 0.0))];
+    while basis[0].len() < dim {
+        basis = basis
+            .iter()
+            .flat_map(|v| {
+                vectors.iter().map(move |q| {
+                    Array1::from_iter(v.iter().flat_map(|&a| q.iter().map(move |&b| a * b)))
+                })
+            })
+            .collect();
+    }
+    basis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernels_cpu::measure_projective;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_bases_are_complete_and_orthogonal() {
+        for (basis, dim) in [
+            (MeasurementBasis::Computational, 3),
+            (MeasurementBasis::X, 4),
+            (MeasurementBasis::Y, 2),
+            (MeasurementBasis::Bell, 4),
+        ] {
+            let projectors = basis_projectors(basis, dim).unwrap();
+            assert_eq!(projectors.len(), dim);
+            let sum = projectors
+                .iter()
+                .fold(Array2::<Complex64>::zeros((dim, dim)), |acc, p| acc + p);
+            for ((i, j), value) in sum.indexed_iter() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert_relative_eq!(value.re, expected, epsilon = 1e-12);
+                assert_relative_eq!(value.im, 0.0, epsilon = 1e-12);
+            }
+            for p in &projectors {
+                for q in &projectors {
+                    let overlap = p.dot(q).diag().sum().norm();
+                    let expected = if std::ptr::eq(p, q) { 1.0 } else { 0.0 };
+                    assert_relative_eq!(overlap, expected, epsilon = 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bell_state_outcomes() {
+        let bell = basis_projectors(MeasurementBasis::Bell, 4).unwrap();
+        let phi_plus = bell[0].clone();
+        let probabilities = measure_projective(&bell, &phi_plus).unwrap();
+        assert_relative_eq!(probabilities[0], 1.0, epsilon = 1e-12);
+
+        // |Φ+⟩ gives perfectly correlated outcomes in the X basis: ++ or --
+        let xx = measure_projective(
+            &basis_projectors(MeasurementBasis::X, 4).unwrap(),
+            &phi_plus,
+        )
+        .unwrap();
+        assert_relative_eq!(xx[0], 0.5, epsilon = 1e-12);
+        assert_relative_eq!(xx[3], 0.5, epsilon = 1e-12);
+
+        assert!(check_basis_dim(MeasurementBasis::Bell, 8).is_err());
+        assert!(check_basis_dim(MeasurementBasis::X, 3).is_err());
+        assert!(check_basis_dim(MeasurementBasis::Computational, 5).is_ok());
+    }
+}
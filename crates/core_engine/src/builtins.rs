@@ -0,0 +1,724 @@
+//! The functions, operators and constants built into the DSL
+//!
+//! [`builtin_registry`] describes every name a program can use without
+//! declaring it: its arity, how its result shape follows from its
+//! arguments', and what it does. The parser builds calls through
+//! [`Builtin::call`], the type checker infers shapes with
+//! [`ShapeRule::apply`], and the validator takes the values of constants
+//! from [`Builtin::value`], so tooling such as completion or generated docs
+//! reads the same table the pipeline runs on.
+//!
+//! Undeclared words over `I`, `X`, `Y` and `Z`, such as `XZ`, are Pauli
+//! strings; as an open family they are not listed. Operators on qudits, such
+//! as `gellmann(3, 8)` or `spin_x(1.5)`, take their dimension from literal
+//! arguments, which [`Builtin::operator`] builds them from; so do the
+//! truncated Fock-space operators such as `annihilation(10)`.
+
+use crate::ast::Expr;
+use crate::error::{EngineError, Result};
+use crate::fock;
+use crate::qudit;
+use crate::typechecker::Shape;
+use ndarray::Array2;
+use num_complex::Complex64;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinKind {
+    /// Called as `name(args)`
+    Function,
+    /// Binary infix operator
+    Operator,
+    Constant,
+}
+
+/// How the result shape of a builtin follows from its arguments' shapes
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeRule {
+    /// Both operands share a shape, which the result keeps
+    SameShape,
+    /// Matrix product; scalars scale and matrices act on vectors
+    Product,
+    /// Any shape divided by a scalar
+    ScalarDivisor,
+    /// The shape of the base
+    BaseShape,
+    /// `n×m` to `m×n`; vectors and scalars keep their shape
+    Adjoint,
+    /// Square matrix to scalar
+    SquareToScalar,
+    /// Square matrix to the square block of one subsystem, which depends
+    /// on the values of the other arguments
+    PartialTrace,
+    /// Square matrix to the composite space it is embedded in, which
+    /// depends on the values of the other arguments
+    Embedding,
+    /// Kronecker product of two matrices or of two vectors
+    Kronecker,
+    /// Two square matrices of one dimension to that dimension
+    SquarePair,
+    /// A square matrix or a scalar, whose shape is kept
+    SquareOrScalar,
+    /// Square matrix to a matrix of its shape
+    Square,
+    /// Square matrix to a vector of its dimension; further arguments are
+    /// scalars
+    SquareToVector,
+    /// Anything to a scalar
+    Scalar,
+    /// Always this shape
+    Fixed(Shape),
+    /// Square matrix whose dimension follows from the values of the
+    /// arguments, which must be number literals
+    Operator,
+}
+
+/// One entry of the registry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub kind: BuiltinKind,
+    /// Fewest and most arguments
+    pub arity: (usize, usize),
+    pub shape: ShapeRule,
+    /// How it is written, e.g. `ptrace(rho, k[, dims])`
+    pub signature: &'static str,
+    pub doc: &'static str,
+}
+
+const PAULI: Shape = Shape::Matrix(2, 2);
+
+static BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "dagger",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Adjoint,
+        signature: "dagger(A)",
+        doc: "Conjugate transpose A†",
+    },
+    Builtin {
+        name: "trace",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::SquareToScalar,
+        signature: "trace(A)",
+        doc: "Trace of a square matrix",
+    },
+    Builtin {
+        name: "ptrace",
+        kind: BuiltinKind::Function,
+        arity: (2, 3),
+        shape: ShapeRule::PartialTrace,
+        signature: "ptrace(rho, k[, dims])",
+        doc: "Reduced operator on subsystem k, tracing out the others; dims defaults to qubits",
+    },
+    Builtin {
+        name: "embed",
+        kind: BuiltinKind::Function,
+        arity: (3, 3),
+        shape: ShapeRule::Embedding,
+        signature: "embed(A, k, dims)",
+        doc: "A on subsystem k of a space of subsystem dimensions dims, identity on the others",
+    },
+    Builtin {
+        name: "tensor",
+        kind: BuiltinKind::Function,
+        arity: (2, 2),
+        shape: ShapeRule::Kronecker,
+        signature: "tensor(A, B)",
+        doc: "Tensor (Kronecker) product A ⊗ B",
+    },
+    Builtin {
+        name: "commutator",
+        kind: BuiltinKind::Function,
+        arity: (2, 2),
+        shape: ShapeRule::SquarePair,
+        signature: "commutator(A, B)",
+        doc: "Commutator [A, B] = AB - BA",
+    },
+    Builtin {
+        name: "anticommutator",
+        kind: BuiltinKind::Function,
+        arity: (2, 2),
+        shape: ShapeRule::SquarePair,
+        signature: "anticommutator(A, B)",
+        doc: "Anticommutator {A, B} = AB + BA",
+    },
+    Builtin {
+        name: "expm",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::SquareOrScalar,
+        signature: "expm(A)",
+        doc: "Matrix exponential",
+    },
+    Builtin {
+        name: "sqrt",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::SquareOrScalar,
+        signature: "sqrt(A)",
+        doc: "Square root of a scalar, or principal square root of a matrix",
+    },
+    Builtin {
+        name: "sin",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Scalar,
+        signature: "sin(x)",
+        doc: "Sine of a scalar",
+    },
+    Builtin {
+        name: "cos",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Scalar,
+        signature: "cos(x)",
+        doc: "Cosine of a scalar",
+    },
+    Builtin {
+        name: "exp",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Scalar,
+        signature: "exp(x)",
+        doc: "Exponential of a scalar",
+    },
+    Builtin {
+        name: "eig",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Square,
+        signature: "eig(H)",
+        doc: "Unitary whose columns are the eigenvectors of Hermitian H, lowest eigenvalue first",
+    },
+    Builtin {
+        name: "eigvals",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::SquareToVector,
+        signature: "eigvals(H)",
+        doc: "Eigenvalues of Hermitian H in ascending order",
+    },
+    Builtin {
+        name: "eigenstate",
+        kind: BuiltinKind::Function,
+        arity: (2, 2),
+        shape: ShapeRule::SquareToVector,
+        signature: "eigenstate(H, k)",
+        doc: "Eigenvector of level k of Hermitian H, counting from the lowest; k is an integer literal",
+    },
+    Builtin {
+        name: "gellmann",
+        kind: BuiltinKind::Function,
+        arity: (2, 2),
+        shape: ShapeRule::Operator,
+        signature: "gellmann(d, k)",
+        doc: "Generalized Gell-Mann matrix k, from 1 to d²-1, of dimension d",
+    },
+    Builtin {
+        name: "spin_x",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "spin_x(j)",
+        doc: "Spin-j operator Jx on 2j+1 levels",
+    },
+    Builtin {
+        name: "spin_y",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "spin_y(j)",
+        doc: "Spin-j operator Jy on 2j+1 levels",
+    },
+    Builtin {
+        name: "spin_z",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "spin_z(j)",
+        doc: "Spin-j operator Jz = diag(j, ..., -j)",
+    },
+    Builtin {
+        name: "clock",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "clock(d)",
+        doc: "Clock operator diag(1, ω, ..., ω^(d-1)), ω = exp(2πi/d)",
+    },
+    Builtin {
+        name: "shift",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "shift(d)",
+        doc: "Shift operator |k⟩ -> |k+1 mod d⟩ on d levels",
+    },
+    Builtin {
+        name: "annihilation",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "annihilation(n)",
+        doc: "Annihilation operator a on the lowest n Fock states",
+    },
+    Builtin {
+        name: "creation",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "creation(n)",
+        doc: "Creation operator a† on the lowest n Fock states",
+    },
+    Builtin {
+        name: "number",
+        kind: BuiltinKind::Function,
+        arity: (1, 1),
+        shape: ShapeRule::Operator,
+        signature: "number(n)",
+        doc: "Number operator a†a = diag(0, ..., n-1)",
+    },
+    Builtin {
+        name: "displacement",
+        kind: BuiltinKind::Function,
+        arity: (2, 3),
+        shape: ShapeRule::Operator,
+        signature: "displacement(n, re, im)",
+        doc: "Displacement exp(α a† - α* a) on n Fock states, α = re + i·im; im defaults to 0",
+    },
+    Builtin {
+        name: "squeezing",
+        kind: BuiltinKind::Function,
+        arity: (2, 3),
+        shape: ShapeRule::Operator,
+        signature: "squeezing(n, re, im)",
+        doc: "Squeezing exp((ζ* a² - ζ a†²)/2) on n Fock states, ζ = re + i·im; im defaults to 0",
+    },
+    Builtin {
+        name: "+",
+        kind: BuiltinKind::Operator,
+        arity: (2, 2),
+        shape: ShapeRule::SameShape,
+        signature: "A + B",
+        doc: "Sum",
+    },
+    Builtin {
+        name: "-",
+        kind: BuiltinKind::Operator,
+        arity: (2, 2),
+        shape: ShapeRule::SameShape,
+        signature: "A - B",
+        doc: "Difference",
+    },
+    Builtin {
+        name: "*",
+        kind: BuiltinKind::Operator,
+        arity: (2, 2),
+        shape: ShapeRule::Product,
+        signature: "A * B",
+        doc: "Product: scaling, matrix product, or a matrix acting on a vector",
+    },
+    Builtin {
+        name: "/",
+        kind: BuiltinKind::Operator,
+        arity: (2, 2),
+        shape: ShapeRule::ScalarDivisor,
+        signature: "A / c",
+        doc: "Division by a scalar",
+    },
+    Builtin {
+        name: "^",
+        kind: BuiltinKind::Operator,
+        arity: (2, 2),
+        shape: ShapeRule::BaseShape,
+        signature: "A ^ n",
+        doc: "Power by a number literal",
+    },
+    Builtin {
+        name: "sigma_x",
+        kind: BuiltinKind::Constant,
+        arity: (0, 0),
+        shape: ShapeRule::Fixed(PAULI),
+        signature: "sigma_x",
+        doc: "Pauli X matrix",
+    },
+    Builtin {
+        name: "sigma_y",
+        kind: BuiltinKind::Constant,
+        arity: (0, 0),
+        shape: ShapeRule::Fixed(PAULI),
+        signature: "sigma_y",
+        doc: "Pauli Y matrix",
+    },
+    Builtin {
+        name: "sigma_z",
+        kind: BuiltinKind::Constant,
+        arity: (0, 0),
+        shape: ShapeRule::Fixed(PAULI),
+        signature: "sigma_z",
+        doc: "Pauli Z matrix",
+    },
+    Builtin {
+        name: "identity",
+        kind: BuiltinKind::Constant,
+        arity: (0, 0),
+        shape: ShapeRule::Fixed(PAULI),
+        signature: "identity",
+        doc: "2×2 identity matrix",
+    },
+];
+
+/// Every builtin function, operator and constant, functions first
+pub fn builtin_registry() -> &'static [Builtin] {
+    BUILTINS
+}
+
+/// The builtin called `name`, if any
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+/// The builtin `expr` applies, and its arguments, for expressions built by
+/// a builtin function or operator
+pub fn builtin_of(expr: &Expr) -> Option<(&'static Builtin, Vec<&Expr>)> {
+    let (name, args): (&str, Vec<&Expr>) = match expr {
+        Expr::Add(a, b) => ("+", vec![&**a, &**b]),
+        Expr::Sub(a, b) => ("-", vec![&**a, &**b]),
+        Expr::Mul(a, b) => ("*", vec![&**a, &**b]),
+        Expr::Div(a, b) => ("/", vec![&**a, &**b]),
+        Expr::Pow(a, b) => ("^", vec![&**a, &**b]),
+        Expr::Dagger(a) => ("dagger", vec![&**a]),
+        Expr::Trace(a) => ("trace", vec![&**a]),
+        Expr::Tensor(a, b) => ("tensor", vec![&**a, &**b]),
+        Expr::Commutator(a, b) => ("commutator", vec![&**a, &**b]),
+        Expr::AntiCommutator(a, b) => ("anticommutator", vec![&**a, &**b]),
+        Expr::Expm(a) => ("expm", vec![&**a]),
+        Expr::Sqrt(a) => ("sqrt", vec![&**a]),
+        Expr::Sin(a) => ("sin", vec![&**a]),
+        Expr::Cos(a) => ("cos", vec![&**a]),
+        Expr::Exp(a) => ("exp", vec![&**a]),
+        // Builtin functions without an expression of their own
+        Expr::FuncCall { name, args } => (name.as_str(), args.iter().collect()),
+        _ => return None,
+    };
+    lookup(name)
+        .filter(|builtin| builtin.kind != BuiltinKind::Constant)
+        .map(|builtin| (builtin, args))
+}
+
+impl Builtin {
+    /// The expression applying this builtin to `args`
+    pub fn call(&self, args: Vec<Expr>) -> Result<Expr> {
+        let (min, max) = self.arity;
+        if args.len() < min || args.len() > max {
+            let expected = if min == max {
+                min.to_string()
+            } else {
+                format!("{} to {}", min, max)
+            };
+            return Err(EngineError::type_error(format!(
+                "{} takes {} arguments, got {}",
+                self.name,
+                expected,
+                args.len()
+            )));
+        }
+
+        let mut args = args.into_iter();
+        let mut next = || Box::new(args.next().expect("arity checked"));
+        Ok(match self.name {
+            "dagger" => Expr::Dagger(next()),
+            "trace" => Expr::Trace(next()),
+            "tensor" => Expr::Tensor(next(), next()),
+            "commutator" => Expr::Commutator(next(), next()),
+            "anticommutator" => Expr::AntiCommutator(next(), next()),
+            "expm" => Expr::Expm(next()),
+            "sqrt" => Expr::Sqrt(next()),
+            "sin" => Expr::Sin(next()),
+            "cos" => Expr::Cos(next()),
+            "exp" => Expr::Exp(next()),
+            "+" => Expr::Add(next(), next()),
+            "-" => Expr::Sub(next(), next()),
+            "*" => Expr::Mul(next(), next()),
+            "/" => Expr::Div(next(), next()),
+            "^" => Expr::Pow(next(), next()),
+            name if self.kind == BuiltinKind::Constant => Expr::Identifier(name.to_string()),
+            name => Expr::FuncCall {
+                name: name.to_string(),
+                args: args.collect(),
+            },
+        })
+    }
+
+    /// The matrix a [`ShapeRule::Operator`] builtin builds from `args`
+    pub fn operator(&self, args: &[Expr]) -> Result<Array2<Complex64>> {
+        match (self.name, self.operator_args(args)?.as_slice()) {
+            ("gellmann", &[d, k]) => qudit::gell_mann(self.integer(d)?, self.integer(k)?),
+            ("spin_x", &[j]) => qudit::spin_x(j),
+            ("spin_y", &[j]) => qudit::spin_y(j),
+            ("spin_z", &[j]) => qudit::spin_z(j),
+            ("clock", &[d]) => qudit::clock(self.integer(d)?),
+            ("shift", &[d]) => qudit::shift(self.integer(d)?),
+            ("annihilation", &[n]) => fock::annihilation(self.integer(n)?),
+            ("creation", &[n]) => fock::creation(self.integer(n)?),
+            ("number", &[n]) => fock::number(self.integer(n)?),
+            ("displacement", &[n, re, ref im @ ..]) => {
+                fock::displacement(self.integer(n)?, Complex64::new(re, im.first().copied().unwrap_or(0.0)))
+            }
+            ("squeezing", &[n, re, ref im @ ..]) => {
+                fock::squeezing(self.integer(n)?, Complex64::new(re, im.first().copied().unwrap_or(0.0)))
+            }
+            _ => Err(EngineError::Internal(format!("{} builds no operator", self.name))),
+        }
+    }
+
+    fn operator_args(&self, args: &[Expr]) -> Result<Vec<f64>> {
+        if self.shape != ShapeRule::Operator || args.len() < self.arity.0 || args.len() > self.arity.1 {
+            return Err(EngineError::type_error(format!("{} expects {}", self.name, self.signature)));
+        }
+        args.iter()
+            .map(|arg| match arg {
+                Expr::Number(x) => Ok(*x),
+                _ => Err(EngineError::type_error(format!(
+                    "Arguments of {} must be number literals",
+                    self.signature
+                ))),
+            })
+            .collect()
+    }
+
+    fn integer(&self, x: f64) -> Result<usize> {
+        if x >= 0.0 && x.fract() == 0.0 {
+            Ok(x as usize)
+        } else {
+            Err(EngineError::type_error(format!(
+                "Arguments of {} must be non-negative integers, got {}",
+                self.signature, x
+            )))
+        }
+    }
+
+    /// The value of a constant
+    pub fn value(&self) -> Option<Array2<Complex64>> {
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let entries = match self.name {
+            "sigma_x" => [c(0.0, 0.0), c(1.0, 0.0), c(1.0, 0.0), c(0.0, 0.0)],
+            "sigma_y" => [c(0.0, 0.0), c(0.0, -1.0), c(0.0, 1.0), c(0.0, 0.0)],
+            "sigma_z" => [c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(-1.0, 0.0)],
+            "identity" => [c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)],
+            _ => return None,
+        };
+        Array2::from_shape_vec((2, 2), entries.to_vec()).ok()
+    }
+}
+
+impl fmt::Display for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<24} {} [{}]", self.signature, self.doc, self.shape)
+    }
+}
+
+impl ShapeRule {
+    /// Result shape for arguments of shapes `args`
+    ///
+    /// [`ShapeRule::PartialTrace`], [`ShapeRule::Embedding`] and
+    /// [`ShapeRule::Operator`] need the arguments' values, so the type
+    /// checker resolves them itself.
+    pub fn apply(&self, args: &[Shape]) -> Result<Shape> {
+        let arg = |i: usize| {
+            args.get(i)
+                .cloned()
+                .ok_or_else(|| EngineError::Internal(format!("Shape rule {:?} is missing argument {}", self, i)))
+        };
+        match self {
+            ShapeRule::SameShape => {
+                let (left, right) = (arg(0)?, arg(1)?);
+                if left != right {
+                    return Err(EngineError::dimension_mismatch(
+                        format!("{:?}", left),
+                        format!("{:?}", right),
+                    ));
+                }
+                Ok(left)
+            }
+            ShapeRule::Product => match (arg(0)?, arg(1)?) {
+                (Shape::Scalar, s) | (s, Shape::Scalar) => Ok(s),
+                (Shape::Matrix(n1, m1), Shape::Matrix(n2, m2)) => {
+                    if m1 != n2 {
+                        return Err(EngineError::dimension_mismatch(
+                            format!("{}x{}", n1, m1),
+                            format!("{}x{}", n2, m2),
+                        ));
+                    }
+                    Ok(Shape::Matrix(n1, m2))
+                }
+                (Shape::Matrix(n, m), Shape::Vector(v)) => {
+                    if m != v {
+                        return Err(EngineError::dimension_mismatch(
+                            format!("{}x{} matrix", n, m),
+                            format!("vector of length {}", v),
+                        ));
+                    }
+                    Ok(Shape::Vector(n))
+                }
+                _ => Err(EngineError::type_error("Invalid multiplication of incompatible types")),
+            },
+            ShapeRule::ScalarDivisor => match arg(1)? {
+                Shape::Scalar => arg(0),
+                _ => Err(EngineError::type_error("Can only divide by scalar")),
+            },
+            ShapeRule::BaseShape => arg(0),
+            ShapeRule::Adjoint => match arg(0)? {
+                Shape::Matrix(n, m) => Ok(Shape::Matrix(m, n)),
+                shape => Ok(shape),
+            },
+            ShapeRule::SquareToScalar => match arg(0)? {
+                Shape::Matrix(n, m) if n == m => Ok(Shape::Scalar),
+                _ => Err(EngineError::type_error("Trace requires a square matrix")),
+            },
+            ShapeRule::PartialTrace => Err(EngineError::Internal(
+                "The partial trace shape depends on argument values".to_string(),
+            )),
+            ShapeRule::Embedding => Err(EngineError::Internal(
+                "The embedded operator's shape depends on argument values".to_string(),
+            )),
+            ShapeRule::Operator => Err(EngineError::Internal(
+                "The operator shape depends on argument values".to_string(),
+            )),
+            ShapeRule::Kronecker => match (arg(0)?, arg(1)?) {
+                (Shape::Matrix(n1, m1), Shape::Matrix(n2, m2)) => Ok(Shape::Matrix(n1 * n2, m1 * m2)),
+                (Shape::Vector(n1), Shape::Vector(n2)) => Ok(Shape::Vector(n1 * n2)),
+                _ => Err(EngineError::type_error("Tensor product requires compatible types")),
+            },
+            ShapeRule::SquarePair => match (arg(0)?, arg(1)?) {
+                (Shape::Matrix(n1, m1), Shape::Matrix(n2, m2)) if n1 == m1 && n2 == m2 && n1 == n2 => {
+                    Ok(Shape::Matrix(n1, m1))
+                }
+                _ => Err(EngineError::type_error(
+                    "Commutator requires square matrices of same dimension",
+                )),
+            },
+            ShapeRule::SquareOrScalar => match arg(0)? {
+                Shape::Matrix(n, m) if n == m => Ok(Shape::Matrix(n, m)),
+                Shape::Scalar => Ok(Shape::Scalar),
+                _ => Err(EngineError::type_error(
+                    "Matrix function requires square matrix or scalar",
+                )),
+            },
+            ShapeRule::Square => match arg(0)? {
+                Shape::Matrix(n, m) if n == m => Ok(Shape::Matrix(n, m)),
+                _ => Err(EngineError::type_error("Eigendecomposition requires a square matrix")),
+            },
+            ShapeRule::SquareToVector => {
+                if args.iter().skip(1).any(|shape| *shape != Shape::Scalar) {
+                    return Err(EngineError::type_error("Level index must be a scalar"));
+                }
+                match arg(0)? {
+                    Shape::Matrix(n, m) if n == m => Ok(Shape::Vector(n)),
+                    _ => Err(EngineError::type_error("Eigendecomposition requires a square matrix")),
+                }
+            }
+            ShapeRule::Scalar => Ok(Shape::Scalar),
+            ShapeRule::Fixed(shape) => Ok(shape.clone()),
+        }
+    }
+}
+
+impl fmt::Display for ShapeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = match self {
+            ShapeRule::SameShape => "S, S -> S".to_string(),
+            ShapeRule::Product => "n×m, m×k -> n×k; scalars scale".to_string(),
+            ShapeRule::ScalarDivisor => "S, scalar -> S".to_string(),
+            ShapeRule::BaseShape => "S, scalar -> S".to_string(),
+            ShapeRule::Adjoint => "n×m -> m×n".to_string(),
+            ShapeRule::SquareToScalar => "n×n -> scalar".to_string(),
+            ShapeRule::PartialTrace => "n×n -> d_k×d_k".to_string(),
+            ShapeRule::Embedding => "d_k×d_k -> n×n".to_string(),
+            ShapeRule::Kronecker => "n×m, p×q -> np×mq".to_string(),
+            ShapeRule::SquarePair => "n×n, n×n -> n×n".to_string(),
+            ShapeRule::SquareOrScalar => "n×n -> n×n; scalar -> scalar".to_string(),
+            ShapeRule::Square => "n×n -> n×n".to_string(),
+            ShapeRule::SquareToVector => "n×n -> vector of n".to_string(),
+            ShapeRule::Operator => "-> d×d".to_string(),
+            ShapeRule::Scalar => "-> scalar".to_string(),
+            ShapeRule::Fixed(Shape::Scalar) => "scalar".to_string(),
+            ShapeRule::Fixed(Shape::Vector(n)) => format!("vector of {}", n),
+            ShapeRule::Fixed(Shape::Matrix(n, m)) => format!("{}×{}", n, m),
+        };
+        f.write_str(&rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_names_are_unique() {
+        let registry = builtin_registry();
+        for (i, builtin) in registry.iter().enumerate() {
+            assert!(
+                registry[i + 1..].iter().all(|b| b.name != builtin.name),
+                "{}",
+                builtin.name
+            );
+            if builtin.kind == BuiltinKind::Constant {
+                let value = builtin.value().unwrap();
+                assert_eq!(
+                    ShapeRule::Fixed(Shape::Matrix(value.nrows(), value.ncols())),
+                    builtin.shape
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_call_round_trips_through_builtin_of() {
+        let a = Expr::Identifier("A".to_string());
+        let b = Expr::Identifier("B".to_string());
+        for builtin in builtin_registry().iter().filter(|b| b.kind != BuiltinKind::Constant) {
+            let args = vec![a.clone(), b.clone(), Expr::Number(1.0)][..builtin.arity.0].to_vec();
+            let expr = builtin.call(args.clone()).unwrap();
+            let (found, found_args) = builtin_of(&expr).unwrap();
+            assert_eq!(found.name, builtin.name);
+            assert_eq!(found_args.into_iter().cloned().collect::<Vec<_>>(), args);
+        }
+        assert!(lookup("dagger").unwrap().call(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_shape_rules() {
+        let m = |n, k| Shape::Matrix(n, k);
+        assert_eq!(ShapeRule::Adjoint.apply(&[m(2, 3)]).unwrap(), m(3, 2));
+        assert_eq!(ShapeRule::Kronecker.apply(&[m(2, 2), m(3, 3)]).unwrap(), m(6, 6));
+        assert_eq!(ShapeRule::Product.apply(&[Shape::Scalar, m(2, 2)]).unwrap(), m(2, 2));
+        assert!(ShapeRule::SquareToScalar.apply(&[m(2, 3)]).is_err());
+        assert_eq!(
+            ShapeRule::SquareToVector.apply(&[m(3, 3), Shape::Scalar]).unwrap(),
+            Shape::Vector(3)
+        );
+        assert!(ShapeRule::SquareToVector.apply(&[m(3, 3), m(3, 3)]).is_err());
+        let spin = lookup("spin_z").unwrap();
+        assert_eq!(spin.operator(&[Expr::Number(1.5)]).unwrap().dim(), (4, 4));
+        assert!(spin.operator(&[Expr::Identifier("j".to_string())]).is_err());
+        let gellmann = lookup("gellmann").unwrap();
+        assert!(gellmann.operator(&[Expr::Number(3.0), Expr::Number(9.0)]).is_err());
+        assert!(gellmann.operator(&[Expr::Number(3.0), Expr::Number(0.5)]).is_err());
+        let displacement = lookup("displacement").unwrap();
+        let args = [Expr::Number(8.0), Expr::Number(0.3), Expr::Number(-0.2)];
+        assert_eq!(displacement.operator(&args[..2]).unwrap().dim(), (8, 8));
+        assert_eq!(
+            displacement.operator(&args).unwrap(),
+            fock::displacement(8, Complex64::new(0.3, -0.2)).unwrap()
+        );
+        assert!(displacement.operator(&args[..1]).is_err());
+        assert_eq!(
+            lookup("dagger").unwrap().to_string(),
+            "dagger(A)                Conjugate transpose A† [n×m -> m×n]"
+        );
+    }
+}
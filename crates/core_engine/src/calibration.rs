@@ -0,0 +1,391 @@
+//! Two-qubit gate calibration
+//!
+//! A packaged analysis for CZ and CNOT gates that runs the whole engine
+//! pipeline: the `cz_calibration` template models the gate as a
+//! controlled-phase interaction `J |11⟩⟨11|` plus residual single-qubit
+//! phase errors `(δ1/2) Z⊗I + (δ2/2) I⊗Z`, applied for the gate time. The
+//! template is instantiated, parsed, checked, lowered and executed for every
+//! trial of `(J, δ1, δ2)`, and the parameters are fitted to two-qubit Pauli
+//! expectation values from state tomography of the gate output.
+//!
+//! Tomography data are [`MeasurementData`] observables named by their Pauli
+//! string (`"XX"`, `"ZI"`, `"YZ"`, ...), measured on the gate output for the
+//! input `|++⟩` (CZ) or `|+0⟩` (CNOT). A CNOT is modelled as the CZ
+//! interaction between ideal Hadamards on the target.
+
+use crate::error::{EngineError, Result};
+use crate::executor::{BackendConfig, Executor};
+use crate::ir::IrProgram;
+use crate::kernels_cpu;
+use crate::lowering::Lowerer;
+use crate::parser::parse_dsl;
+use crate::stats::{self, FitMethod, FitOptions, FitResult, MeasurementData};
+use crate::templates::TemplateRegistry;
+use crate::typechecker::TypeChecker;
+use crate::validator::QuantumValidator;
+use ndarray::Array2;
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+/// Template simulated by [`GateCalibration`]
+pub const TEMPLATE_ID: &str = "cz_calibration";
+
+/// Gate being calibrated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TwoQubitGate {
+    Cz,
+    Cnot,
+}
+
+impl std::str::FromStr for TwoQubitGate {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "cz" => Ok(TwoQubitGate::Cz),
+            "cnot" | "cx" => Ok(TwoQubitGate::Cnot),
+            _ => Err(EngineError::validation_error(format!(
+                "Unknown two-qubit gate '{}' (expected cz or cnot)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Parameters of the gate model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GateModel {
+    /// Exchange coupling `J` (rad per unit time)
+    pub coupling: f64,
+    /// Phase error rates `δ1`, `δ2` (rad per unit time)
+    pub phase_errors: [f64; 2],
+}
+
+/// A fitted value with its 1σ uncertainty
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    pub value: f64,
+    pub uncertainty: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    pub gate: TwoQubitGate,
+    pub gate_time: f64,
+    pub coupling: Estimate,
+    pub phase_errors: [Estimate; 2],
+    /// Average gate fidelity of the fitted gate to the ideal one
+    pub fidelity: Estimate,
+    pub fit: FitResult,
+}
+
+/// Calibration workflow for one gate at a fixed gate time
+pub struct GateCalibration {
+    gate: TwoQubitGate,
+    gate_time: f64,
+    registry: TemplateRegistry,
+    options: FitOptions,
+}
+
+impl GateCalibration {
+    pub fn new(gate: TwoQubitGate, gate_time: f64) -> Self {
+        // Bounds keep each phase within one period, so the fit is unique
+        let options = FitOptions {
+            method: FitMethod::NelderMead,
+            bounds: Some(vec![
+                (0.0, 2.0 * PI / gate_time),
+                (-PI / gate_time, PI / gate_time),
+                (-PI / gate_time, PI / gate_time),
+            ]),
+            tolerance: 1e-9,
+            max_iterations: 2000,
+            budget: None,
+        };
+        GateCalibration {
+            gate,
+            gate_time,
+            registry: TemplateRegistry::new(),
+            options,
+        }
+    }
+
+    pub fn with_fit_options(mut self, options: FitOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The ideal gate: `J·t = π` and no phase errors
+    pub fn ideal_model(&self) -> GateModel {
+        GateModel {
+            coupling: PI / self.gate_time,
+            phase_errors: [0.0, 0.0],
+        }
+    }
+
+    /// Density matrix of the gate output for the tomography input state
+    pub fn simulate(&self, model: &GateModel) -> Result<Array2<Complex64>> {
+        let ir = self.lower(model)?;
+        let result = Executor::new(BackendConfig::default()).execute(&ir)?;
+        let rho = result
+            .experiment_results
+            .first()
+            .and_then(|experiment| experiment.states.last())
+            .cloned()
+            .ok_or_else(|| {
+                EngineError::ExecutionError(format!("Template '{}' produced no state", TEMPLATE_ID))
+            })?;
+
+        match self.gate {
+            TwoQubitGate::Cz => Ok(rho),
+            TwoQubitGate::Cnot => {
+                let h_target = target_hadamard()?;
+                kernels_cpu::apply_unitary_rho(&h_target, &rho)
+            }
+        }
+    }
+
+    /// Unitary of the modelled gate, `exp(-i H t)` of the template's
+    /// Hamiltonian (conjugated by the target Hadamards for a CNOT)
+    pub fn unitary(&self, model: &GateModel) -> Result<Array2<Complex64>> {
+        let ir = self.lower(model)?;
+        let (_, h) = Executor::new(BackendConfig::default())
+            .hamiltonians(&ir)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                EngineError::ExecutionError(format!(
+                    "Template '{}' declares no Hamiltonian",
+                    TEMPLATE_ID
+                ))
+            })?;
+        let u = kernels_cpu::matrix_exp(&h.mapv(|x| Complex64::new(0.0, -self.gate_time) * x))?;
+
+        match self.gate {
+            TwoQubitGate::Cz => Ok(u),
+            TwoQubitGate::Cnot => {
+                let h_target = target_hadamard()?;
+                Ok(h_target.dot(&u).dot(&h_target))
+            }
+        }
+    }
+
+    /// Average gate fidelity of `model` to the ideal gate
+    pub fn fidelity(&self, model: &GateModel) -> Result<f64> {
+        let ideal = self.unitary(&self.ideal_model())?;
+        Ok(average_gate_fidelity(&self.unitary(model)?, &ideal))
+    }
+
+    /// Predicted Pauli expectation values of the gate output
+    pub fn predict(&self, model: &GateModel, paulis: &[&str]) -> Result<Vec<f64>> {
+        let rho = self.simulate(model)?;
+        paulis
+            .iter()
+            .map(|label| pauli_expectation(&rho, label))
+            .collect()
+    }
+
+    /// Fit `J`, `δ1` and `δ2` to tomography data and report the gate
+    /// fidelity, with uncertainties propagated from the fit covariance
+    pub fn fit(&self, data: &MeasurementData) -> Result<CalibrationReport> {
+        let mut labels: Vec<&String> = data.observables.keys().collect();
+        labels.sort();
+
+        let mut paulis = Vec::new();
+        let mut observed = Vec::new();
+        let mut uncertainties = Vec::new();
+        for label in labels {
+            validate_pauli(label)?;
+            let (values, sigmas) = &data.observables[label];
+            if values.len() != sigmas.len() {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{} uncertainties for '{}'", values.len(), label),
+                    format!("{}", sigmas.len()),
+                ));
+            }
+            for (&value, &sigma) in values.iter().zip(sigmas) {
+                paulis.push(label.as_str());
+                observed.push(value);
+                uncertainties.push(sigma);
+            }
+        }
+        if observed.is_empty() {
+            return Err(EngineError::validation_error(
+                "Calibration needs at least one Pauli expectation value",
+            ));
+        }
+
+        let likelihood = |params: &[f64]| -> Result<f64> {
+            let predicted = self.predict(&model_from(params), &paulis)?;
+            stats::gaussian_log_likelihood(&observed, &uncertainties, &predicted)
+        };
+        let ideal = self.ideal_model();
+        let initial = [ideal.coupling, 0.0, 0.0];
+        let fit = stats::fit_parameters_mle_with(likelihood, &initial, &self.options)?;
+
+        // σ_F² = ∇Fᵀ Σ ∇F
+        let best = model_from(&fit.best_params);
+        let fidelity = self.fidelity(&best)?;
+        let fidelity_uncertainty = match fit.covariance() {
+            Ok(cov) => {
+                let gradient = stats::compute_gradient(
+                    |params: &[f64]| self.fidelity(&model_from(params)),
+                    &fit.best_params,
+                    1e-6,
+                )?;
+                let variance: f64 = (0..3)
+                    .flat_map(|i| (0..3).map(move |j| (i, j)))
+                    .map(|(i, j)| gradient[i] * cov[i][j] * gradient[j])
+                    .sum();
+                variance.max(0.0).sqrt()
+            }
+            Err(_) => f64::INFINITY,
+        };
+
+        let estimate = |i: usize| Estimate {
+            value: fit.best_params[i],
+            uncertainty: fit.uncertainties[i],
+        };
+        Ok(CalibrationReport {
+            gate: self.gate,
+            gate_time: self.gate_time,
+            coupling: estimate(0),
+            phase_errors: [estimate(1), estimate(2)],
+            fidelity: Estimate {
+                value: fidelity,
+                uncertainty: fidelity_uncertainty,
+            },
+            fit,
+        })
+    }
+
+    /// Instantiate the template for `model` and take it down to IR
+    fn lower(&self, model: &GateModel) -> Result<IrProgram> {
+        let params = HashMap::from([
+            ("J".to_string(), model.coupling),
+            ("delta1".to_string(), model.phase_errors[0]),
+            ("delta2".to_string(), model.phase_errors[1]),
+            ("t_gate".to_string(), self.gate_time),
+        ]);
+        let code = self.registry.instantiate(TEMPLATE_ID, &params)?;
+        let typed = TypeChecker::new().check(&parse_dsl(&code)?)?;
+        let validated = QuantumValidator::new().validate(&typed)?;
+        Lowerer::new().lower(&validated)
+    }
+}
+
+fn model_from(params: &[f64]) -> GateModel {
+    GateModel {
+        coupling: params[0],
+        phase_errors: [params[1], params[2]],
+    }
+}
+
+/// Average gate fidelity `(|Tr(V†U)|² + d) / (d(d + 1))` of `u` to `v`
+pub fn average_gate_fidelity(u: &Array2<Complex64>, v: &Array2<Complex64>) -> f64 {
+    let d = u.nrows() as f64;
+    let overlap: Complex64 = u.iter().zip(v.iter()).map(|(a, b)| b.conj() * a).sum();
+    (overlap.norm_sqr() + d) / (d * (d + 1.0))
+}
+
+/// `Tr(ρ P)` for a two-qubit Pauli string such as `"XZ"`
+pub fn pauli_expectation(rho: &Array2<Complex64>, label: &str) -> Result<f64> {
+    validate_pauli(label)?;
+    let mut factors = label.chars().map(pauli);
+    let first = factors.next().unwrap_or_else(|| pauli('I'));
+    let operator = factors.try_fold(first, |acc, p| kernels_cpu::tensor_product(&acc, &p))?;
+    Ok(kernels_cpu::expectation(&operator, rho)?.re)
+}
+
+fn validate_pauli(label: &str) -> Result<()> {
+    if label.len() != 2 || !label.chars().all(|c| "IXYZ".contains(c)) {
+        return Err(EngineError::validation_error(format!(
+            "'{}' is not a two-qubit Pauli string (expected e.g. \"XZ\")",
+            label
+        )));
+    }
+    Ok(())
+}
+
+fn pauli(label: char) -> Array2<Complex64> {
+    let (o, l, i) = (
+        Complex64::new(0.0, 0.0),
+        Complex64::new(1.0, 0.0),
+        Complex64::new(0.0, 1.0),
+    );
+    let entries = match label {
+        'X' => [o, l, l, o],
+        'Y' => [o, -i, i, o],
+        'Z' => [l, o, o, -l],
+        _ => [l, o, o, l],
+    };
+    Array2::from_shape_vec((2, 2), entries.to_vec()).expect("2x2 Pauli matrix")
+}
+
+/// Hadamard on the target (second) qubit
+fn target_hadamard() -> Result<Array2<Complex64>> {
+    let s = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+    let hadamard = Array2::from_shape_vec((2, 2), vec![s, s, s, -s]).expect("2x2 Hadamard");
+    kernels_cpu::tensor_product(&pauli('I'), &hadamard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tomography(calibration: &GateCalibration, model: &GateModel) -> MeasurementData {
+        let labels = [
+            "IX", "IY", "IZ", "XI", "XX", "XY", "XZ", "YI", "YX", "YY", "YZ", "ZI", "ZX", "ZY",
+            "ZZ",
+        ];
+        let values = calibration.predict(model, &labels).unwrap();
+        MeasurementData {
+            observables: labels
+                .iter()
+                .zip(values)
+                .map(|(label, value)| (label.to_string(), (vec![value], vec![0.01])))
+                .collect(),
+            num_shots: 1000,
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn test_ideal_gates() {
+        for gate in [TwoQubitGate::Cz, TwoQubitGate::Cnot] {
+            let calibration = GateCalibration::new(gate, 1.0);
+            let ideal = calibration.ideal_model();
+            assert!((calibration.fidelity(&ideal).unwrap() - 1.0).abs() < 1e-9);
+
+            // CZ|++⟩ and CNOT|+0⟩ are both maximally entangled
+            let rho = calibration.simulate(&ideal).unwrap();
+            let correlator = match gate {
+                TwoQubitGate::Cz => pauli_expectation(&rho, "XZ").unwrap(),
+                TwoQubitGate::Cnot => pauli_expectation(&rho, "ZZ").unwrap(),
+            };
+            assert!((correlator - 1.0).abs() < 1e-6, "{:?}", gate);
+            assert!(pauli_expectation(&rho, "XI").unwrap().abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_fit_recovers_coupling_and_phase_errors() {
+        let calibration = GateCalibration::new(TwoQubitGate::Cz, 1.0);
+        let truth = GateModel {
+            coupling: 0.95 * PI,
+            phase_errors: [0.08, -0.05],
+        };
+
+        let report = calibration.fit(&tomography(&calibration, &truth)).unwrap();
+        assert!((report.coupling.value - truth.coupling).abs() < 1e-3);
+        assert!((report.phase_errors[0].value - 0.08).abs() < 1e-3);
+        assert!((report.phase_errors[1].value + 0.05).abs() < 1e-3);
+        assert!(report.coupling.uncertainty > 0.0 && report.coupling.uncertainty < 0.1);
+
+        let expected = calibration.fidelity(&truth).unwrap();
+        assert!((report.fidelity.value - expected).abs() < 1e-4);
+        assert!(report.fidelity.value < 1.0);
+        assert!(report.fidelity.uncertainty.is_finite());
+    }
+}
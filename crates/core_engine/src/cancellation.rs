@@ -0,0 +1,64 @@
+//! Cooperative cancellation of long-running work
+//!
+//! A [`CancellationToken`] is handed to the work when it starts; whoever
+//! holds a clone can [`cancel`](CancellationToken::cancel) it, and the work
+//! notices at its next [`check`](CancellationToken::check), such as the
+//! next time step of an evolution, and returns [`EngineError::Cancelled`].
+
+use crate::error::{EngineError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag asking work to stop
+///
+/// Clones share the flag, so a clone can be moved into another thread
+/// (e.g. `spawn_blocking` or a rayon worker) and still see a cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every holder of the token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `Err(EngineError::Cancelled)` once the token is cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(EngineError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// [`CancellationToken::check`] of `token`, if there is one
+pub fn check(token: Option<&CancellationToken>) -> Result<()> {
+    token.map_or(Ok(()), CancellationToken::check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(clone.check().is_ok());
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(matches!(clone.check(), Err(EngineError::Cancelled)));
+        assert!(check(None).is_ok());
+    }
+}
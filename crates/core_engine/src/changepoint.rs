@@ -0,0 +1,265 @@
+//! Change-point alarms on streamed fit parameters
+//!
+//! A [`DriftDetector`] watches the estimates a
+//! [`RollingFitEngine`](crate::streaming::RollingFitEngine) produces at
+//! each refit and raises a [`StreamEvent::DriftDetected`] when a parameter
+//! leaves the level it settled at, such as a Rabi frequency drifting with
+//! the drive power.
+//!
+//! Each parameter runs a two-sided CUSUM test. The first `warmup`
+//! estimates after a start or an alarm set the reference level μ; every
+//! later estimate x adds its standardised excess `z = (x - μ) / σ` to the
+//! sums
+//!
+//! ```text
+//! S⁺ ← max(0, S⁺ + z - k)      S⁻ ← max(0, S⁻ - z - k)
+//! ```
+//!
+//! and an alarm fires once either exceeds the threshold `h`. σ is the
+//! estimate's own uncertainty when the fit reports one, and the spread of
+//! the warm-up estimates otherwise. The allowance `k` sets the smallest
+//! shift worth detecting (about `2k` standard deviations) and `h` trades
+//! false alarms against detection delay.
+
+use crate::error::{EngineError, Result};
+use crate::streaming::StreamEvent;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// CUSUM tuning, in units of the parameters' standard deviations
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CusumConfig {
+    /// Allowance `k` subtracted from every standardised excess
+    pub allowance: f64,
+    /// Alarm threshold `h` on the cumulative sums
+    pub threshold: f64,
+    /// Estimates averaged into the reference level after a (re)start
+    pub warmup: usize,
+}
+
+impl Default for CusumConfig {
+    fn default() -> Self {
+        Self {
+            allowance: 0.5,
+            threshold: 5.0,
+            warmup: 5,
+        }
+    }
+}
+
+impl CusumConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.allowance >= 0.0 && self.threshold > 0.0) {
+            return Err(EngineError::validation_error(format!(
+                "CUSUM needs a non-negative allowance and a positive threshold, got k = {} and h = {}",
+                self.allowance, self.threshold
+            )));
+        }
+        if self.warmup == 0 {
+            return Err(EngineError::validation_error(
+                "CUSUM warm-up needs at least one estimate",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// CUSUM state of one parameter
+#[derive(Debug, Clone, Default)]
+struct Cusum {
+    warmup: Vec<f64>,
+    reference: Option<(f64, f64)>,
+    upper: f64,
+    lower: f64,
+}
+
+/// Online change-point detector over a fit's parameter estimates, see the
+/// [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct DriftDetector {
+    config: CusumConfig,
+    /// Per-parameter thresholds overriding `config.threshold`
+    thresholds: Vec<Option<f64>>,
+    names: Vec<String>,
+    cusums: Vec<Cusum>,
+}
+
+impl DriftDetector {
+    /// Detector for the parameters `names`, in the order of the fit's
+    /// parameter vector
+    pub fn new(names: Vec<String>, config: CusumConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            thresholds: vec![None; names.len()],
+            cusums: vec![Cusum::default(); names.len()],
+            names,
+        })
+    }
+
+    /// Alarm on `param` at its own threshold
+    pub fn with_threshold(mut self, param: &str, threshold: f64) -> Result<Self> {
+        let index = self
+            .names
+            .iter()
+            .position(|name| name == param)
+            .ok_or_else(|| {
+                EngineError::validation_error(format!("Unknown parameter '{}'", param))
+            })?;
+        if threshold.is_nan() || threshold <= 0.0 {
+            return Err(EngineError::validation_error(format!(
+                "Drift threshold of '{}' must be positive, got {}",
+                param, threshold
+            )));
+        }
+        self.thresholds[index] = Some(threshold);
+        Ok(self)
+    }
+
+    /// Feed the estimates `params`, with standard deviations
+    /// `uncertainties` (empty if unknown), obtained at `time`; returns a
+    /// [`StreamEvent::DriftDetected`] for every parameter that drifted,
+    /// whose CUSUM then restarts from a new reference level
+    pub fn update(
+        &mut self,
+        time: DateTime<Utc>,
+        params: &[f64],
+        uncertainties: &[f64],
+    ) -> Result<Vec<StreamEvent>> {
+        if params.len() != self.names.len() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{} parameters", self.names.len()),
+                format!("{}", params.len()),
+            ));
+        }
+
+        let mut alarms = Vec::new();
+        for (i, &x) in params.iter().enumerate() {
+            if !x.is_finite() {
+                continue;
+            }
+            let cusum = &mut self.cusums[i];
+            let Some((mean, spread)) = cusum.reference else {
+                cusum.warmup.push(x);
+                if cusum.warmup.len() >= self.config.warmup {
+                    cusum.reference = Some(mean_and_std(&cusum.warmup));
+                    cusum.warmup.clear();
+                }
+                continue;
+            };
+
+            let sigma = match uncertainties.get(i) {
+                Some(&s) if s.is_finite() && s > 0.0 => s,
+                _ => spread,
+            };
+            if sigma <= 0.0 {
+                continue;
+            }
+            let z = (x - mean) / sigma;
+            cusum.upper = (cusum.upper + z - self.config.allowance).max(0.0);
+            cusum.lower = (cusum.lower - z - self.config.allowance).max(0.0);
+
+            let threshold = self.thresholds[i].unwrap_or(self.config.threshold);
+            if cusum.upper > threshold || cusum.lower > threshold {
+                alarms.push(StreamEvent::DriftDetected {
+                    param: self.names[i].clone(),
+                    magnitude: x - mean,
+                    time,
+                });
+                *cusum = Cusum::default();
+            }
+        }
+        Ok(alarms)
+    }
+
+    /// Forget every reference level, e.g. after recalibrating
+    pub fn reset(&mut self) {
+        for cusum in &mut self.cusums {
+            *cusum = Cusum::default();
+        }
+    }
+}
+
+fn mean_and_std(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drifts(events: &[StreamEvent]) -> Vec<(&str, f64)> {
+        events
+            .iter()
+            .map(|event| match event {
+                StreamEvent::DriftDetected {
+                    param, magnitude, ..
+                } => (param.as_str(), *magnitude),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cusum_flags_a_step_and_restarts() {
+        let mut detector = DriftDetector::new(
+            vec!["omega".to_string(), "gamma".to_string()],
+            CusumConfig::default(),
+        )
+        .unwrap();
+        let now = Utc::now();
+
+        // Stable estimates within their uncertainty raise nothing
+        for i in 0..20 {
+            let jitter = if i % 2 == 0 { 0.01 } else { -0.01 };
+            let alarms = detector
+                .update(now, &[1.0 + jitter, 0.1], &[0.02, 0.01])
+                .unwrap();
+            assert!(alarms.is_empty(), "step {}", i);
+        }
+
+        // A shift of six standard deviations in omega is caught at once
+        let alarms = detector.update(now, &[1.12, 0.1], &[0.02, 0.01]).unwrap();
+        let found = drifts(&alarms);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "omega");
+        assert!((found[0].1 - 0.12).abs() < 0.01);
+
+        // The new level becomes the reference after the warm-up
+        for _ in 0..20 {
+            assert!(detector
+                .update(now, &[1.12, 0.1], &[0.02, 0.01])
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_small_drift_accumulates() {
+        let mut detector = DriftDetector::new(vec!["omega".to_string()], CusumConfig::default())
+            .unwrap()
+            .with_threshold("omega", 3.0)
+            .unwrap();
+        let now = Utc::now();
+        for _ in 0..5 {
+            detector.update(now, &[1.0], &[0.1]).unwrap();
+        }
+        // One standard deviation down: each step adds 1 - k = 0.5 to S⁻
+        let steps = (1..=10)
+            .position(|_| !detector.update(now, &[0.9], &[0.1]).unwrap().is_empty())
+            .unwrap();
+        assert_eq!(steps + 1, 7);
+
+        assert!(detector.update(now, &[1.0, 2.0], &[]).is_err());
+        assert!(DriftDetector::new(vec![], CusumConfig::default())
+            .unwrap()
+            .with_threshold("omega", 1.0)
+            .is_err());
+    }
+}
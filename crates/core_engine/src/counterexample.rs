@@ -0,0 +1,606 @@
+//! Counterexample shrinking and divergence reports
+//!
+//! The prover refutes an identity by evaluating both sides at random
+//! parameter values, which on their own say little about why it fails:
+//! θ = 7.3184… is as wrong as θ = 0, but only the latter hints at a
+//! missing term. [`shrink`] moves each parameter towards simpler values
+//! (0, ±1, ±π/2, ±π, 2), by trying them outright and otherwise bisecting
+//! towards the nearest and rounding off decimals, for as long as the
+//! statement stays refuted and the assumptions stay satisfied.
+//! [`locate_divergence`] then walks both sides in step to the innermost pair
+//! of corresponding subexpressions that disagree, and a [`Counterexample`]
+//! displays as a report of both.
+//!
+//! Symbols other than Pauli words and the built-in operators evaluate as
+//! scalars. A scalar stands for that multiple of the identity, so an
+//! identity that holds for every operator also holds for every scalar and
+//! a refutation found this way is genuine; expressions whose meaning
+//! depends on dimension, such as the trace of a scalar, are not evaluated.
+
+use crate::ast::Expr;
+use crate::error::{EngineError, Result};
+use crate::kernels_cpu;
+use crate::operators::PauliString;
+use crate::prover::{Counterexample, MatrixValue};
+use ndarray::Array2;
+use num_complex::Complex64 as C64;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt;
+
+/// Smallest norm of `lhs - rhs` taken as a genuine difference
+pub const TOLERANCE: f64 = 1e-6;
+
+/// Values shrinking tries, simplest first
+const SIMPLE_VALUES: [f64; 8] = [0.0, 1.0, -1.0, FRAC_PI_2, -FRAC_PI_2, PI, -PI, 2.0];
+
+/// Halvings of the distance to the nearest simple value
+const BISECTION_STEPS: usize = 40;
+
+/// Sweeps over all parameters before shrinking gives up
+const MAX_PASSES: usize = 4;
+
+/// Largest matrix power evaluated, by repeated multiplication
+const MAX_POWER: f64 = 64.0;
+
+/// An evaluated expression
+#[derive(Debug, Clone)]
+pub enum Value {
+    Scalar(C64),
+    Matrix(Array2<C64>),
+}
+
+impl Value {
+    /// The value as a matrix, a scalar being a 1×1 one
+    pub fn into_matrix(self) -> Array2<C64> {
+        match self {
+            Value::Scalar(c) => Array2::from_elem((1, 1), c),
+            Value::Matrix(m) => m,
+        }
+    }
+
+    fn matrix_like(self, n: usize) -> Array2<C64> {
+        match self {
+            Value::Scalar(c) => Array2::eye(n).mapv(|x: C64| x * c),
+            Value::Matrix(m) => m,
+        }
+    }
+}
+
+/// Dimension a scalar takes on next to `a` and `b`: that of the matrix
+/// among them, or 1
+fn shared_dim(a: &Value, b: &Value) -> usize {
+    match (a, b) {
+        (Value::Matrix(m), _) | (_, Value::Matrix(m)) => m.nrows(),
+        _ => 1,
+    }
+}
+
+fn mismatch(a: &Array2<C64>, b: &Array2<C64>) -> EngineError {
+    let shape = |m: &Array2<C64>| format!("{}x{}", m.nrows(), m.ncols());
+    EngineError::dimension_mismatch(shape(a), shape(b))
+}
+
+/// The innermost pair of corresponding subexpressions of the two sides
+/// that evaluate differently
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Divergence {
+    pub lhs: Expr,
+    pub rhs: Expr,
+    pub lhs_value: MatrixValue,
+    pub rhs_value: MatrixValue,
+    pub difference_norm: f64,
+}
+
+/// Whether `name` is an operator the evaluator knows, rather than a
+/// parameter
+pub fn is_operator(name: &str) -> bool {
+    operator(name).is_some()
+}
+
+fn operator(name: &str) -> Option<Array2<C64>> {
+    let word = match name {
+        "identity" => "I",
+        "sigma_x" => "X",
+        "sigma_y" => "Y",
+        "sigma_z" => "Z",
+        word => word,
+    };
+    word.parse::<PauliString>()
+        .ok()
+        .map(|pauli| pauli.to_dense())
+}
+
+/// Evaluate `expr` with the given parameter values
+pub fn evaluate(expr: &Expr, params: &HashMap<String, f64>) -> Result<Value> {
+    use Value::{Matrix, Scalar};
+
+    let unsupported = |what: &str| {
+        Err(EngineError::Unsupported(format!(
+            "Cannot evaluate {}",
+            what
+        )))
+    };
+    Ok(match expr {
+        Expr::Number(x) => Scalar(C64::new(*x, 0.0)),
+        Expr::ComplexNumber(c) => Scalar(*c),
+        Expr::Identifier(name) => match params.get(name) {
+            Some(&x) => Scalar(C64::new(x, 0.0)),
+            None => match operator(name) {
+                Some(m) => Matrix(m),
+                None => {
+                    return Err(EngineError::validation_error(format!(
+                        "No value for '{}'",
+                        name
+                    )))
+                }
+            },
+        },
+        Expr::Matrix(literal) => {
+            let rows = literal.rows.len();
+            let cols = literal.rows.first().map_or(0, Vec::len);
+            let mut data = Vec::with_capacity(rows * cols);
+            for element in literal.rows.iter().flatten() {
+                match evaluate(element, params)? {
+                    Scalar(c) => data.push(c),
+                    Matrix(_) => return unsupported("a matrix literal with matrix entries"),
+                }
+            }
+            Matrix(Array2::from_shape_vec((rows, cols), data).map_err(|e| {
+                EngineError::validation_error(format!("Ragged matrix literal: {}", e))
+            })?)
+        }
+        Expr::Vector(_) => return unsupported("a vector"),
+
+        Expr::Add(a, b) => combine(evaluate(a, params)?, evaluate(b, params)?, |x, y| x + y)?,
+        Expr::Sub(a, b) => combine(evaluate(a, params)?, evaluate(b, params)?, |x, y| x - y)?,
+        Expr::Mul(a, b) => multiply(evaluate(a, params)?, evaluate(b, params)?)?,
+        Expr::Div(a, b) => match (evaluate(a, params)?, evaluate(b, params)?) {
+            (Scalar(x), Scalar(y)) => Scalar(x / y),
+            (Matrix(m), Scalar(y)) => Matrix(m.mapv(|x| x / y)),
+            _ => return unsupported("division by a matrix"),
+        },
+        Expr::Pow(a, b) => match (evaluate(a, params)?, evaluate(b, params)?) {
+            (Scalar(x), Scalar(y))
+                if x.im == 0.0 && y.im == 0.0 && (x.re >= 0.0 || y.re.fract() == 0.0) =>
+            {
+                Scalar(C64::new(x.re.powf(y.re), 0.0))
+            }
+            (Scalar(x), Scalar(y)) => Scalar(x.powc(y)),
+            (Matrix(m), Scalar(y))
+                if y.im == 0.0 && (0.0..=MAX_POWER).contains(&y.re) && y.re.fract() == 0.0 =>
+            {
+                let mut power = Array2::eye(m.nrows());
+                for _ in 0..y.re as usize {
+                    power = power.dot(&m);
+                }
+                Matrix(power)
+            }
+            _ => return unsupported("a matrix power other than a natural number"),
+        },
+
+        Expr::Dagger(a) => match evaluate(a, params)? {
+            Scalar(x) => Scalar(x.conj()),
+            Matrix(m) => Matrix(m.t().mapv(|x| x.conj())),
+        },
+        Expr::Trace(a) => match evaluate(a, params)? {
+            Matrix(m) => Scalar(m.diag().sum()),
+            Scalar(_) => return unsupported("the trace of a scalar"),
+        },
+        Expr::Tensor(a, b) => match (evaluate(a, params)?, evaluate(b, params)?) {
+            (Matrix(x), Matrix(y)) => Matrix(kron(&x, &y)),
+            _ => return unsupported("a tensor product with a scalar"),
+        },
+        Expr::Commutator(a, b) | Expr::AntiCommutator(a, b) => {
+            let (x, y) = (evaluate(a, params)?, evaluate(b, params)?);
+            let xy = multiply(x.clone(), y.clone())?;
+            let yx = multiply(y, x)?;
+            if matches!(expr, Expr::Commutator(..)) {
+                combine(xy, yx, |p, q| p - q)?
+            } else {
+                combine(xy, yx, |p, q| p + q)?
+            }
+        }
+
+        Expr::Expm(a) | Expr::Exp(a) => match evaluate(a, params)? {
+            Scalar(x) => Scalar(x.exp()),
+            Matrix(m) => Matrix(kernels_cpu::matrix_exp(&m)?),
+        },
+        Expr::Sqrt(a) | Expr::Sin(a) | Expr::Cos(a) => {
+            let x = match evaluate(a, params)? {
+                Scalar(x) => x,
+                Matrix(_) => return unsupported("a function of a matrix other than expm"),
+            };
+            Scalar(match expr {
+                Expr::Sqrt(_) if x.im == 0.0 && x.re >= 0.0 => C64::new(x.re.sqrt(), 0.0),
+                Expr::Sqrt(_) => x.sqrt(),
+                Expr::Sin(_) => x.sin(),
+                _ => x.cos(),
+            })
+        }
+        Expr::FuncCall { name, .. } => return unsupported(&format!("'{}'", name)),
+    })
+}
+
+/// Elementwise `op`, a scalar standing for that multiple of the identity
+fn combine(a: Value, b: Value, op: impl Fn(C64, C64) -> C64) -> Result<Value> {
+    match (a, b) {
+        (Value::Scalar(x), Value::Scalar(y)) => Ok(Value::Scalar(op(x, y))),
+        (a, b) => {
+            let n = shared_dim(&a, &b);
+            let (x, y) = (a.matrix_like(n), b.matrix_like(n));
+            if x.dim() != y.dim() {
+                return Err(mismatch(&x, &y));
+            }
+            Ok(Value::Matrix(
+                ndarray::Zip::from(&x)
+                    .and(&y)
+                    .map_collect(|&p, &q| op(p, q)),
+            ))
+        }
+    }
+}
+
+fn multiply(a: Value, b: Value) -> Result<Value> {
+    Ok(match (a, b) {
+        (Value::Scalar(x), Value::Scalar(y)) => Value::Scalar(x * y),
+        (Value::Scalar(c), Value::Matrix(m)) | (Value::Matrix(m), Value::Scalar(c)) => {
+            Value::Matrix(m.mapv(|x| x * c))
+        }
+        (Value::Matrix(x), Value::Matrix(y)) => {
+            if x.ncols() != y.nrows() {
+                return Err(mismatch(&x, &y));
+            }
+            Value::Matrix(x.dot(&y))
+        }
+    })
+}
+
+fn kron(a: &Array2<C64>, b: &Array2<C64>) -> Array2<C64> {
+    let (p, q) = b.dim();
+    Array2::from_shape_fn((a.nrows() * p, a.ncols() * q), |(i, j)| {
+        a[[i / p, j / q]] * b[[i % p, j % q]]
+    })
+}
+
+/// Both sides and the norm of their difference, if they evaluate at
+/// `params` and differ by more than [`TOLERANCE`]
+pub fn refutes(
+    lhs: &Expr,
+    rhs: &Expr,
+    params: &HashMap<String, f64>,
+) -> Option<(Array2<C64>, Array2<C64>, f64)> {
+    let (lhs, rhs, norm) = compare(lhs, rhs, params)?;
+    (norm > TOLERANCE).then_some((lhs, rhs, norm))
+}
+
+/// Both sides and the norm of their difference, if both evaluate to
+/// finite values of compatible shape
+fn compare(
+    lhs: &Expr,
+    rhs: &Expr,
+    params: &HashMap<String, f64>,
+) -> Option<(Array2<C64>, Array2<C64>, f64)> {
+    let (lhs, rhs) = (evaluate(lhs, params).ok()?, evaluate(rhs, params).ok()?);
+    let n = shared_dim(&lhs, &rhs);
+    let (lhs, rhs) = (lhs.matrix_like(n), rhs.matrix_like(n));
+    if lhs.dim() != rhs.dim() {
+        return None;
+    }
+    let norm = (&lhs - &rhs)
+        .iter()
+        .map(|c| c.norm_sqr())
+        .sum::<f64>()
+        .sqrt();
+    norm.is_finite().then_some((lhs, rhs, norm))
+}
+
+/// Simpler parameter values at which `refuted` still holds
+///
+/// Each parameter in turn is set to the first simpler entry of
+/// [`SIMPLE_VALUES`] that keeps the statement refuted and is admitted by
+/// `admits`; failing that it is bisected towards the nearest admitted
+/// simple value and rounded to as few decimals as possible. Sweeps repeat
+/// until nothing changes.
+pub fn shrink(
+    params: &HashMap<String, f64>,
+    refuted: impl Fn(&HashMap<String, f64>) -> bool,
+    admits: impl Fn(&str, f64) -> bool,
+) -> HashMap<String, f64> {
+    let mut current = params.clone();
+    let mut names: Vec<String> = current.keys().cloned().collect();
+    names.sort();
+
+    for _ in 0..MAX_PASSES {
+        let mut changed = false;
+        for name in &names {
+            let value = current[name];
+            let holds = |x: f64| {
+                let mut candidate = current.clone();
+                candidate.insert(name.clone(), x);
+                admits(name, x) && refuted(&candidate)
+            };
+
+            let shrunk = SIMPLE_VALUES
+                .iter()
+                .copied()
+                .filter(|&x| simpler(x, value))
+                .find(|&x| holds(x))
+                .unwrap_or_else(|| {
+                    let target = SIMPLE_VALUES
+                        .iter()
+                        .copied()
+                        .filter(|&x| admits(name, x))
+                        .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()));
+                    let mut closest = value;
+                    if let Some(mut towards) = target {
+                        for _ in 0..BISECTION_STEPS {
+                            let mid = (closest + towards) / 2.0;
+                            if holds(mid) {
+                                closest = mid;
+                            } else {
+                                towards = mid;
+                            }
+                        }
+                    }
+                    (0..=6)
+                        .map(|decimals| round_to(closest, decimals))
+                        .find(|&x| x == closest || holds(x))
+                        .unwrap_or(closest)
+                });
+
+            if simpler(shrunk, value) {
+                current.insert(name.clone(), shrunk);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    current
+}
+
+fn round_to(x: f64, decimals: i32) -> f64 {
+    let scale = 10f64.powi(decimals);
+    (x * scale).round() / scale
+}
+
+/// Rank of a value for shrinking: the simple values in order, then by the
+/// decimals needed to write it, then by magnitude
+fn complexity(x: f64) -> (usize, f64) {
+    match SIMPLE_VALUES.iter().position(|&s| s == x) {
+        Some(i) => (i, 0.0),
+        None => {
+            let decimals = (0..16).find(|&d| round_to(x, d) == x).unwrap_or(16) as usize;
+            (SIMPLE_VALUES.len() + decimals, x.abs())
+        }
+    }
+}
+
+fn simpler(x: f64, than: f64) -> bool {
+    let (a, b) = (complexity(x), complexity(than));
+    a.0 < b.0 || (a.0 == b.0 && a.1 < b.1)
+}
+
+/// The innermost pair of corresponding subexpressions of `lhs` and `rhs`
+/// that differ at `params`
+///
+/// The search descends while the two expressions have the same shape and
+/// exactly one pair of their operands differs, all others agreeing; where
+/// several operands differ (as in `A * B` against `B * A`), or the shapes
+/// do, the pair itself is where the sides diverge.
+pub fn locate_divergence(
+    lhs: &Expr,
+    rhs: &Expr,
+    params: &HashMap<String, f64>,
+) -> Option<Divergence> {
+    let (lhs_value, rhs_value, difference_norm) = refutes(lhs, rhs, params)?;
+
+    let pairs = operand_pairs(lhs, rhs);
+    let mut differing = pairs
+        .iter()
+        .filter_map(|(a, b)| match compare(a, b, params) {
+            Some((_, _, norm)) if norm <= TOLERANCE => None,
+            _ => Some((*a, *b)),
+        });
+    if let (Some((a, b)), None) = (differing.next(), differing.next()) {
+        if let Some(inner) = locate_divergence(a, b, params) {
+            return Some(inner);
+        }
+    }
+
+    Some(Divergence {
+        lhs: lhs.clone(),
+        rhs: rhs.clone(),
+        lhs_value: MatrixValue::from_array(lhs_value),
+        rhs_value: MatrixValue::from_array(rhs_value),
+        difference_norm,
+    })
+}
+
+/// Corresponding operands of two expressions of the same shape; empty if
+/// their shapes differ
+fn operand_pairs<'a>(lhs: &'a Expr, rhs: &'a Expr) -> Vec<(&'a Expr, &'a Expr)> {
+    use Expr::*;
+    match (lhs, rhs) {
+        (Add(a, b), Add(c, d))
+        | (Sub(a, b), Sub(c, d))
+        | (Mul(a, b), Mul(c, d))
+        | (Div(a, b), Div(c, d))
+        | (Pow(a, b), Pow(c, d))
+        | (Tensor(a, b), Tensor(c, d))
+        | (Commutator(a, b), Commutator(c, d))
+        | (AntiCommutator(a, b), AntiCommutator(c, d)) => vec![(&**a, &**c), (&**b, &**d)],
+        (Dagger(a), Dagger(b))
+        | (Trace(a), Trace(b))
+        | (Expm(a), Expm(b))
+        | (Sqrt(a), Sqrt(b))
+        | (Sin(a), Sin(b))
+        | (Cos(a), Cos(b))
+        | (Exp(a), Exp(b)) => vec![(&**a, &**b)],
+        (Matrix(a), Matrix(b))
+            if a.rows.len() == b.rows.len()
+                && a.rows.iter().zip(&b.rows).all(|(x, y)| x.len() == y.len()) =>
+        {
+            a.rows
+                .iter()
+                .flatten()
+                .zip(b.rows.iter().flatten())
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Parameter value as written in a report, naming the multiples of π that
+/// shrinking produces
+fn format_param(x: f64) -> String {
+    match x {
+        x if x == FRAC_PI_2 => "π/2".to_string(),
+        x if x == -FRAC_PI_2 => "-π/2".to_string(),
+        x if x == PI => "π".to_string(),
+        x if x == -PI => "-π".to_string(),
+        x => format!("{}", x),
+    }
+}
+
+fn format_params(params: &HashMap<String, f64>) -> String {
+    let mut params: Vec<_> = params.iter().collect();
+    params.sort_by(|a, b| a.0.cmp(b.0));
+    params
+        .iter()
+        .map(|(name, &value)| format!("{} = {}", name, format_param(value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for MatrixValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entry = |&(re, im): &(f64, f64)| match (re, im) {
+            (re, im) if im.abs() < TOLERANCE => format!("{:.4}", re),
+            (re, im) if re.abs() < TOLERANCE => format!("{:.4}i", im),
+            (re, im) => format!("{:.4}{:+.4}i", re, im),
+        };
+        let rows: Vec<String> = self
+            .elements
+            .iter()
+            .map(|row| row.iter().map(entry).collect::<Vec<_>>().join(", "))
+            .collect();
+        write!(f, "[{}]", rows.join("; "))
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "the two sides differ by {:.3e} in norm",
+            self.difference_norm
+        )?;
+        if !self.params.is_empty() {
+            write!(f, "  at {}", format_params(&self.params))?;
+            if !self.sampled_params.is_empty() && self.sampled_params != self.params {
+                write!(f, " (shrunk from {})", format_params(&self.sampled_params))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "  lhs = {}", self.lhs_value)?;
+        writeln!(f, "  rhs = {}", self.rhs_value)?;
+        if let Some(divergence) = &self.divergence {
+            writeln!(f, "  first diverging subexpressions:")?;
+            writeln!(f, "    {}  =  {}", divergence.lhs, divergence.lhs_value)?;
+            writeln!(f, "    {}  =  {}", divergence.rhs, divergence.rhs_value)?;
+            write!(
+                f,
+                "    differing by {:.3e} in norm",
+                divergence.difference_norm
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Box<Expr> {
+        Box::new(Expr::Identifier(name.to_string()))
+    }
+
+    fn params(values: &[(&str, f64)]) -> HashMap<String, f64> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluates_pauli_algebra() {
+        let anticommutator = Expr::AntiCommutator(id("X"), id("Y"));
+        let value = evaluate(&anticommutator, &HashMap::new())
+            .unwrap()
+            .into_matrix();
+        assert!(value.iter().all(|c| c.norm() < 1e-12));
+
+        // A scalar parameter acts as a multiple of the identity
+        let shifted = Expr::Add(id("X"), id("a"));
+        let value = evaluate(&shifted, &params(&[("a", 2.0)]))
+            .unwrap()
+            .into_matrix();
+        assert_eq!(value[[0, 0]], C64::new(2.0, 0.0));
+        assert_eq!(value[[0, 1]], C64::new(1.0, 0.0));
+
+        assert!(evaluate(&Expr::Trace(id("a")), &params(&[("a", 1.0)])).is_err());
+        assert!(is_operator("sigma_z") && is_operator("XZ") && !is_operator("theta"));
+    }
+
+    #[test]
+    fn test_shrinks_towards_simple_values() {
+        // sin(θ) = θ fails everywhere but 0; θ ≥ 0 rules out -1
+        let lhs = Expr::Sin(id("theta"));
+        let rhs = Expr::Identifier("theta".to_string());
+        let refuted = |p: &HashMap<String, f64>| refutes(&lhs, &rhs, p).is_some();
+        let shrunk = shrink(&params(&[("theta", -7.318)]), refuted, |_, x| {
+            !(-2.0..0.0).contains(&x)
+        });
+        assert_eq!(shrunk["theta"], 1.0);
+
+        // sqrt((θ - 3.5)^2) = 3.5 - θ fails only above 3.5, out of reach of
+        // the simple values: bisect towards π and round
+        let offset = Box::new(Expr::Sub(id("theta"), Box::new(Expr::Number(3.5))));
+        let lhs = Expr::Sqrt(Box::new(Expr::Pow(offset, Box::new(Expr::Number(2.0)))));
+        let rhs = Expr::Sub(Box::new(Expr::Number(3.5)), id("theta"));
+        let refuted = |p: &HashMap<String, f64>| refutes(&lhs, &rhs, p).is_some();
+        let shrunk = shrink(&params(&[("theta", 7.318)]), refuted, |_, _| true);
+        assert_eq!(shrunk["theta"], 4.0);
+    }
+
+    #[test]
+    fn test_locates_innermost_divergence() {
+        // X * (a * Y + Z) against X * (a * Y + 2 * Z): only the Z terms differ
+        let lhs = Expr::Mul(
+            id("X"),
+            Box::new(Expr::Add(Box::new(Expr::Mul(id("a"), id("Y"))), id("Z"))),
+        );
+        let rhs = Expr::Mul(
+            id("X"),
+            Box::new(Expr::Add(
+                Box::new(Expr::Mul(id("a"), id("Y"))),
+                Box::new(Expr::Mul(Box::new(Expr::Number(2.0)), id("Z"))),
+            )),
+        );
+        let divergence = locate_divergence(&lhs, &rhs, &params(&[("a", 0.5)])).unwrap();
+        assert_eq!(divergence.lhs.to_string(), "Z");
+        assert_eq!(divergence.rhs.to_string(), "2 * Z");
+
+        // Swapped operands diverge as a whole
+        let lhs = Expr::Mul(id("X"), id("Z"));
+        let rhs = Expr::Mul(id("Z"), id("X"));
+        let divergence = locate_divergence(&lhs, &rhs, &HashMap::new()).unwrap();
+        assert_eq!(divergence.lhs, lhs);
+        assert!((divergence.difference_norm - 2.0 * 2f64.sqrt()).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,395 @@
+//! Diagnostics of DSL programs for `qte validate` and editors
+//!
+//! [`diagnose`] runs the front end of the pipeline over DSL source: parsing,
+//! type checking and quantum validation. Unlike the pipeline it keeps going
+//! past the first error of each stage, so a program's problems are reported
+//! together, and it warns about declarations that nothing refers to and
+//! `prove` goals the prover could not decide.
+//! Validation only runs on programs that type check, since its errors would
+//! otherwise mostly repeat theirs.
+
+use crate::ast::*;
+use crate::error::{EngineError, Result};
+use crate::typechecker::TypeChecker;
+use crate::validator::{GoalStatus, QuantumValidator, ValidatorConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Stage of the front end a diagnostic comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Parse,
+    Typecheck,
+    Validate,
+    /// Checks that don't stop a program from running
+    Lint,
+}
+
+/// One problem found in a program
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub stage: Stage,
+    /// 1-based position in the source, when known
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+    /// Process exit code of the error's category, for errors
+    pub exit_code: Option<u8>,
+}
+
+impl Diagnostic {
+    fn error(stage: Stage, error: &EngineError, span: Option<Span>) -> Self {
+        let (span, message) = match error {
+            EngineError::ParseError { line, column, message } => (
+                Some(Span {
+                    line: *line,
+                    column: *column,
+                }),
+                message.clone(),
+            ),
+            other => (span, other.to_string()),
+        };
+        Diagnostic {
+            severity: Severity::Error,
+            stage,
+            line: span.map(|s| s.line),
+            column: span.map(|s| s.column),
+            message,
+            exit_code: Some(error.exit_code()),
+        }
+    }
+
+    fn warning(stage: Stage, span: Option<Span>, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            stage,
+            line: span.map(|s| s.line),
+            column: span.map(|s| s.column),
+            message,
+            exit_code: None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{} at line {}, column {}: {}", severity, line, column, self.message)
+            }
+            _ => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Every error and warning in DSL `source`, errors first
+///
+/// A parse error ends the run, as nothing after it can be checked. Programs
+/// with `sweep` blocks are checked at each point of the sweep; their errors
+/// are reported once each and without positions, which expansion loses.
+pub fn diagnose(source: &str) -> Vec<Diagnostic> {
+    diagnose_with(source, &ValidatorConfig::default())
+}
+
+/// [`diagnose`] with quantum validation configured by `config`; the
+/// violations it downgrades are reported as validation warnings
+pub fn diagnose_with(source: &str, config: &ValidatorConfig) -> Vec<Diagnostic> {
+    diagnose_parsed(crate::parser::parse_dsl(source), config)
+}
+
+/// Every error and warning in the DSL file `path` and the declarations it
+/// imports, as [`diagnose`] finds them; positions refer to `path`
+pub fn diagnose_file(path: impl AsRef<std::path::Path>) -> Vec<Diagnostic> {
+    diagnose_file_with(path, &ValidatorConfig::default())
+}
+
+/// [`diagnose_file`] with quantum validation configured by `config`
+pub fn diagnose_file_with(path: impl AsRef<std::path::Path>, config: &ValidatorConfig) -> Vec<Diagnostic> {
+    diagnose_parsed(crate::imports::parse_file(path), config)
+}
+
+fn diagnose_parsed(parsed: Result<Ast>, config: &ValidatorConfig) -> Vec<Diagnostic> {
+    let ast = match parsed {
+        Ok(ast) => ast,
+        Err(e) => return vec![Diagnostic::error(Stage::Parse, &e, None)],
+    };
+
+    let mut diagnostics = Vec::new();
+    if ast.statements.iter().any(|s| matches!(s, Statement::Sweep { .. })) {
+        match expand(&ast) {
+            Ok(points) => {
+                for point in &points {
+                    for diagnostic in check(point, config) {
+                        if !diagnostics.contains(&diagnostic) {
+                            diagnostics.push(diagnostic);
+                        }
+                    }
+                }
+            }
+            Err(e) => diagnostics.push(Diagnostic::error(Stage::Validate, &e, None)),
+        }
+    } else {
+        diagnostics = check(&ast, config);
+    }
+
+    // Warnings of later sweep points may precede errors of earlier ones
+    diagnostics.sort_by_key(|d| !d.is_error());
+    diagnostics.extend(unused_declarations(&ast));
+    diagnostics
+}
+
+/// Type check `ast`, then validate it if that succeeded
+fn check(ast: &Ast, config: &ValidatorConfig) -> Vec<Diagnostic> {
+    let (typed, errors) = TypeChecker::new().check_all(ast);
+    if !errors.is_empty() {
+        return errors
+            .iter()
+            .map(|(e, span)| Diagnostic::error(Stage::Typecheck, e, *span))
+            .collect();
+    }
+
+    let (validated, errors) = QuantumValidator::with_config(config.clone()).validate_all(&typed);
+    let warnings = validated
+        .validation_results
+        .warnings()
+        .map(|v| Diagnostic::warning(Stage::Validate, None, v.to_string()))
+        .chain(validated.validation_results.goals.iter().filter_map(|outcome| match &outcome.status {
+            GoalStatus::Undecided { reason } => Some(Diagnostic::warning(
+                Stage::Validate,
+                None,
+                format!("could not prove '{}': {}", outcome.goal, reason),
+            )),
+            GoalStatus::Proven => None,
+        }))
+        .collect::<Vec<_>>();
+    errors
+        .iter()
+        .map(|(e, span)| Diagnostic::error(Stage::Validate, e, *span))
+        .chain(warnings)
+        .collect()
+}
+
+/// The sweep-free program at each point of the sweeps in `ast`
+fn expand(ast: &Ast) -> Result<Vec<Ast>> {
+    let (_, points) = crate::sweep::expand_sweeps(ast)?;
+    Ok(points.into_iter().map(|point| point.ast).collect())
+}
+
+/// A warning for each declaration whose name is never referred to, except
+/// imported ones, as libraries declare more than any one program uses
+fn unused_declarations(ast: &Ast) -> Vec<Diagnostic> {
+    let mut used = HashSet::new();
+    collect_references(&ast.statements, &mut used);
+
+    let mut warnings = Vec::new();
+    for (index, stmt) in ast.statements.iter().enumerate() {
+        let span = ast.source_map.statement(index);
+        let mut declared = Vec::new();
+        collect_declarations(stmt, &mut declared);
+        for (kind, name) in declared {
+            if !used.contains(name) && !name.contains("::") {
+                warnings.push(Diagnostic::warning(Stage::Lint, span, format!("{} '{}' is never used", kind, name)));
+            }
+        }
+    }
+    warnings
+}
+
+fn collect_declarations<'a>(stmt: &'a Statement, declared: &mut Vec<(&'static str, &'a str)>) {
+    let declaration = match stmt {
+        Statement::ConstDecl { name, .. } => ("constant", name),
+        Statement::SymbolDecl { name } => ("symbol", name),
+        Statement::ParamDecl { name, .. } => ("parameter", name),
+        Statement::MatrixDecl { name, .. } => ("matrix", name),
+        Statement::OperatorDecl { name, .. } => ("operator", name),
+        Statement::FunctionDef { name, .. } => ("function", name),
+        Statement::HamiltonianDef { name, .. } => ("Hamiltonian", name),
+        Statement::MeasurementDef { name, .. } => ("measurement", name),
+        Statement::ChannelDef { name, .. } => ("channel", name),
+        Statement::Experiment { .. } | Statement::Assume(_) | Statement::Prove(_) => return,
+        Statement::Sweep { body, .. } => {
+            body.iter().for_each(|stmt| collect_declarations(stmt, declared));
+            return;
+        }
+    };
+    declared.push((declaration.0, declaration.1.as_str()));
+}
+
+/// Names referred to anywhere in `statements`
+fn collect_references<'a>(statements: &'a [Statement], used: &mut HashSet<&'a str>) {
+    for stmt in statements {
+        match stmt {
+            Statement::ConstDecl { .. }
+            | Statement::SymbolDecl { .. }
+            | Statement::ParamDecl { .. }
+            | Statement::OperatorDecl { .. } => {}
+            Statement::MatrixDecl { value, .. } => literal_references(value, used),
+            Statement::FunctionDef { body, .. } => expr_references(body, used),
+            Statement::HamiltonianDef { expr, .. } => expr_references(expr, used),
+            Statement::MeasurementDef { spec, .. } => match spec {
+                MeasurementSpec::Projective { projectors: literals } | MeasurementSpec::POVM { effects: literals } => {
+                    literals.iter().for_each(|l| literal_references(l, used))
+                }
+                MeasurementSpec::Basis { .. } => {}
+            },
+            Statement::ChannelDef { kraus, .. } => kraus.iter().for_each(|k| expr_references(k, used)),
+            Statement::Experiment { body, .. } => {
+                match &body.init {
+                    Some(StateSpec::Ket(ket)) => ket.elements.iter().for_each(|e| expr_references(e, used)),
+                    Some(StateSpec::Rho(rho)) => literal_references(rho, used),
+                    Some(StateSpec::Eigenstate { hamiltonian, .. }) => {
+                        used.insert(hamiltonian);
+                    }
+                    Some(StateSpec::From(_)) | None => {}
+                }
+                if let Some(evolution) = &body.evolution {
+                    used.insert(&evolution.hamiltonian_name);
+                    for term in &evolution.lindblad_ops {
+                        used.insert(&term.operator_name);
+                        expr_references(&term.rate, used);
+                    }
+                }
+                if let Some(schedule) = &body.measurements {
+                    used.extend(schedule.events.iter().map(|e| e.measurement_name.as_str()));
+                }
+                used.extend(body.channels.iter().map(|c| c.channel_name.as_str()));
+                body.observables.iter().for_each(|o| expr_references(&o.expr, used));
+            }
+            Statement::Sweep { body, .. } => collect_references(body, used),
+            Statement::Assume(goal) | Statement::Prove(goal) => {
+                goal.exprs().into_iter().for_each(|e| expr_references(e, used))
+            }
+        }
+    }
+}
+
+fn literal_references<'a>(literal: &'a MatrixLiteral, used: &mut HashSet<&'a str>) {
+    literal.rows.iter().flatten().for_each(|e| expr_references(e, used));
+}
+
+fn expr_references<'a>(expr: &'a Expr, used: &mut HashSet<&'a str>) {
+    match expr {
+        Expr::Identifier(name) => {
+            used.insert(name);
+        }
+        Expr::Number(_) | Expr::ComplexNumber(_) => {}
+        Expr::Matrix(m) => literal_references(m, used),
+        Expr::Vector(v) => v.elements.iter().for_each(|e| expr_references(e, used)),
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Pow(a, b)
+        | Expr::Tensor(a, b)
+        | Expr::Commutator(a, b)
+        | Expr::AntiCommutator(a, b) => {
+            expr_references(a, used);
+            expr_references(b, used);
+        }
+        Expr::Dagger(a)
+        | Expr::Trace(a)
+        | Expr::Expm(a)
+        | Expr::Sqrt(a)
+        | Expr::Sin(a)
+        | Expr::Cos(a)
+        | Expr::Exp(a) => expr_references(a, used),
+        Expr::FuncCall { name, args } => {
+            used.insert(name);
+            args.iter().for_each(|e| expr_references(e, used));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_every_type_error() {
+        let source = r#"
+            matrix A = [1, 0; 0, 1];
+            matrix B = [1, 0, 0; 0, 1, 0; 0, 0, 1];
+            Hamiltonian H1 = A + B;
+            Hamiltonian H2 = A * missing;
+        "#;
+        let diagnostics = diagnose(source);
+        let errors: Vec<_> = diagnostics.iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|d| d.stage == Stage::Typecheck && d.line.is_some()));
+        assert_eq!(errors[0].line, Some(4));
+        assert_eq!(errors[1].line, Some(5));
+    }
+
+    #[test]
+    fn test_warns_about_unused_declarations() {
+        let source = r#"
+            const unused = 2.0;
+            Hamiltonian H = sigma_z;
+            measure M: computational;
+            experiment e {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.5, 3));
+            }
+        "#;
+        let diagnostics = diagnose(source);
+        assert!(diagnostics.iter().all(|d| !d.is_error()), "{:?}", diagnostics);
+        let warnings: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            warnings,
+            ["constant 'unused' is never used", "measurement 'M' is never used"]
+        );
+        assert_eq!(diagnostics[0].line, Some(2));
+    }
+
+    #[test]
+    fn test_downgraded_violations_are_warnings() {
+        let source = r#"
+            Hamiltonian H = sigma_z;
+            experiment e {
+                init: ket(vec(1.000001, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.5, 3));
+            }
+        "#;
+        assert!(diagnose(source).iter().any(|d| d.is_error() && d.stage == Stage::Validate));
+
+        let config = ValidatorConfig {
+            warn_only: [crate::validator::Constraint::Normalization].into(),
+            ..ValidatorConfig::default()
+        };
+        let diagnostics = diagnose_with(source, &config);
+        assert!(diagnostics.iter().all(|d| !d.is_error()), "{:?}", diagnostics);
+        let warning = diagnostics.iter().find(|d| d.stage == Stage::Validate).unwrap();
+        assert!(warning.message.contains("normalization"), "{}", warning.message);
+        assert!(warning.message.contains("2.00e-6"), "{}", warning.message);
+    }
+
+    #[test]
+    fn test_parse_error_is_located() {
+        let diagnostics = diagnose("Hamiltonian H = ;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].stage, Stage::Parse);
+        assert!(diagnostics[0].line.is_some());
+
+        let json = serde_json::to_value(&diagnostics[0]).unwrap();
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["stage"], "parse");
+    }
+}
@@ -0,0 +1,282 @@
+//! Ensembles of initial states
+//!
+//! A [`StateEnsemble`] is a weighted set of initial states run through the
+//! same experiment by [`Executor::execute_state_ensemble`]: the inputs of a
+//! process tomography, the eigenstates of a thermal state, or any list of
+//! preparations. Each member keeps its own trajectory and measurements, and
+//! the weighted average of the members is reported as an ordinary
+//! [`ExperimentResult`]. By linearity of quantum evolution that average is
+//! the trajectory of the mixed initial state `Σ_k w_k ρ_k`.
+//!
+//! Unitary evolutions share one propagator per distinct time step across all
+//! members and apply it to every member in a single matrix product; kets are
+//! stacked as the columns of one matrix and density matrices side by side.
+//!
+//! [`Executor::execute_state_ensemble`]: crate::executor::Executor::execute_state_ensemble
+
+use crate::error::{EngineError, Result};
+use crate::executor::{EvolutionState, ExperimentResult, MeasurementResult};
+use crate::spectrum::Spectrum;
+use ndarray::{Array1, Array2};
+use num_complex::Complex64;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Weighted set of initial states
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateEnsemble {
+    pub members: Vec<EnsembleMember>,
+}
+
+/// One initial state of an ensemble
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnsembleMember {
+    pub label: String,
+    /// Relative weight; weights are normalised over the ensemble
+    pub weight: f64,
+    pub state: EvolutionState,
+}
+
+/// Trajectories of every member of an ensemble, and their average
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateEnsembleResult {
+    pub members: Vec<MemberResult>,
+    /// The weighted average over the members
+    pub average: ExperimentResult,
+}
+
+/// Trajectory and measurements of one ensemble member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberResult {
+    pub label: String,
+    /// Normalised weight
+    pub weight: f64,
+    pub measurements: Vec<MeasurementResult>,
+    /// Density matrix at each time of the experiment
+    #[serde(skip)]
+    pub states: Vec<Array2<Complex64>>,
+}
+
+impl StateEnsemble {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ket(mut self, label: impl Into<String>, ket: Array1<Complex64>, weight: f64) -> Self {
+        self.members.push(EnsembleMember {
+            label: label.into(),
+            weight,
+            state: EvolutionState::Ket(ket),
+        });
+        self
+    }
+
+    pub fn with_rho(mut self, label: impl Into<String>, rho: Array2<Complex64>, weight: f64) -> Self {
+        self.members.push(EnsembleMember {
+            label: label.into(),
+            weight,
+            state: EvolutionState::Rho(rho),
+        });
+        self
+    }
+
+    /// The `4^n_qubits` product states over `|0⟩`, `|1⟩`, `|+⟩` and `|+i⟩`,
+    /// the standard inputs of qubit process tomography, with equal weights;
+    /// labels such as `0+` name each qubit's state, first qubit first
+    pub fn tomography_inputs(n_qubits: usize) -> Result<Self> {
+        if n_qubits == 0 || n_qubits > 6 {
+            return Err(EngineError::validation_error(format!(
+                "Tomography inputs need between 1 and 6 qubits, got {}",
+                n_qubits
+            )));
+        }
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        let single = [
+            ("0", [Complex64::new(1.0, 0.0), Complex64::new(0.0, 0.0)]),
+            ("1", [Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0)]),
+            ("+", [Complex64::new(s, 0.0), Complex64::new(s, 0.0)]),
+            ("+i", [Complex64::new(s, 0.0), Complex64::new(0.0, s)]),
+        ];
+
+        let mut inputs = vec![(String::new(), Array1::from_elem(1, Complex64::new(1.0, 0.0)))];
+        for _ in 0..n_qubits {
+            inputs = inputs
+                .iter()
+                .flat_map(|(label, ket)| {
+                    single.iter().map(move |(name, amplitudes)| {
+                        let product = Array1::from_shape_fn(ket.len() * 2, |i| ket[i / 2] * amplitudes[i % 2]);
+                        (format!("{}{}", label, name), product)
+                    })
+                })
+                .collect();
+        }
+
+        Ok(inputs
+            .into_iter()
+            .fold(Self::new(), |ensemble, (label, ket)| ensemble.with_ket(label, ket, 1.0)))
+    }
+
+    /// The eigenstates of `hamiltonian`, weighted by their Boltzmann factors
+    /// at inverse temperature `beta`: the thermal state as an ensemble
+    pub fn thermal(hamiltonian: &Array2<Complex64>, beta: f64) -> Result<Self> {
+        let (spectrum, weights) = boltzmann(hamiltonian, beta)?;
+        Ok(spectrum
+            .eigenvectors
+            .into_iter()
+            .zip(weights)
+            .enumerate()
+            .fold(Self::new(), |ensemble, (k, (vector, weight))| {
+                ensemble.with_ket(format!("E{}", k), Array1::from_vec(vector), weight)
+            }))
+    }
+
+    /// `samples` eigenstates of `hamiltonian` drawn with their Boltzmann
+    /// probabilities at inverse temperature `beta`, each of equal weight;
+    /// the ensemble converges to the thermal state as samples grow
+    pub fn thermal_samples(
+        hamiltonian: &Array2<Complex64>,
+        beta: f64,
+        samples: usize,
+        seed: Option<u64>,
+    ) -> Result<Self> {
+        if samples == 0 {
+            return Err(EngineError::validation_error(
+                "Thermal ensemble needs at least one sample",
+            ));
+        }
+        let (spectrum, weights) = boltzmann(hamiltonian, beta)?;
+        let mut rng = crate::rng::seeded(seed);
+
+        let mut ensemble = Self::new();
+        for sample in 0..samples {
+            let mut u = rng.gen::<f64>();
+            let k = weights
+                .iter()
+                .position(|&w| {
+                    u -= w;
+                    u < 0.0
+                })
+                .unwrap_or(weights.len() - 1);
+            let ket = Array1::from_vec(spectrum.eigenvectors[k].clone());
+            ensemble = ensemble.with_ket(format!("E{}#{}", k, sample), ket, 1.0);
+        }
+        Ok(ensemble)
+    }
+
+    /// Weights normalised to sum to one
+    pub fn normalized_weights(&self) -> Vec<f64> {
+        let total: f64 = self.members.iter().map(|m| m.weight).sum();
+        self.members.iter().map(|m| m.weight / total).collect()
+    }
+
+    /// Check that the ensemble is non-empty, its weights non-negative with
+    /// a positive sum, and every state `dim`-dimensional
+    pub fn validate(&self, dim: usize) -> Result<()> {
+        if self.members.is_empty() {
+            return Err(EngineError::validation_error("State ensemble has no members"));
+        }
+        for member in &self.members {
+            if !member.weight.is_finite() || member.weight < 0.0 {
+                return Err(EngineError::validation_error(format!(
+                    "Weight of ensemble member '{}' must be finite and non-negative, got {}",
+                    member.label, member.weight
+                )));
+            }
+            let member_dim = match &member.state {
+                EvolutionState::Ket(ket) => ket.len(),
+                EvolutionState::Rho(rho) if rho.is_square() => rho.nrows(),
+                EvolutionState::Rho(rho) => {
+                    return Err(EngineError::dimension_mismatch(
+                        "square density matrix",
+                        format!("{:?} for ensemble member '{}'", rho.dim(), member.label),
+                    ))
+                }
+            };
+            if member_dim != dim {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{}-dimensional initial state", dim),
+                    format!("{}-dimensional ensemble member '{}'", member_dim, member.label),
+                ));
+            }
+        }
+        if self.members.iter().all(|m| m.weight == 0.0) {
+            return Err(EngineError::validation_error("State ensemble weights sum to zero"));
+        }
+        Ok(())
+    }
+}
+
+/// Spectrum of `hamiltonian` and the normalised Boltzmann weight of each
+/// eigenstate at inverse temperature `beta`
+fn boltzmann(hamiltonian: &Array2<Complex64>, beta: f64) -> Result<(Spectrum, Vec<f64>)> {
+    if !beta.is_finite() || beta < 0.0 {
+        return Err(EngineError::validation_error(format!(
+            "Inverse temperature must be finite and non-negative, got {}",
+            beta
+        )));
+    }
+    let spectrum = Spectrum::of("thermal", hamiltonian, 0.0)?;
+    // Relative to the ground state, so large β·E cannot overflow
+    let ground = spectrum.eigenvalues.first().copied().unwrap_or(0.0);
+    let factors: Vec<f64> = spectrum
+        .eigenvalues
+        .iter()
+        .map(|e| (-beta * (e - ground)).exp())
+        .collect();
+    let z: f64 = factors.iter().sum();
+    let weights = factors.iter().map(|f| f / z).collect();
+    Ok((spectrum, weights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tomography_inputs() {
+        let ensemble = StateEnsemble::tomography_inputs(2).unwrap();
+        assert_eq!(ensemble.members.len(), 16);
+        assert_eq!(ensemble.members[2].label, "0+");
+        assert!(ensemble.validate(4).is_ok());
+
+        let EvolutionState::Ket(ket) = &ensemble.members[7].state else {
+            panic!("tomography inputs are kets");
+        };
+        // |1⟩ ⊗ |+i⟩
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        assert_eq!(ensemble.members[7].label, "1+i");
+        assert!((ket[2] - Complex64::new(s, 0.0)).norm() < 1e-15);
+        assert!((ket[3] - Complex64::new(0.0, s)).norm() < 1e-15);
+        assert!(StateEnsemble::tomography_inputs(0).is_err());
+    }
+
+    #[test]
+    fn test_thermal_weights_and_samples() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let h = Array2::from_shape_vec((2, 2), vec![c(0.0), c(0.0), c(0.0), c(1.0)]).unwrap();
+
+        let thermal = StateEnsemble::thermal(&h, 2.0).unwrap();
+        let weights = thermal.normalized_weights();
+        assert!((weights[1] / weights[0] - (-2.0f64).exp()).abs() < 1e-12);
+
+        let samples = StateEnsemble::thermal_samples(&h, 2.0, 4000, Some(5)).unwrap();
+        let excited = samples.members.iter().filter(|m| m.label.starts_with("E1#")).count();
+        assert!((excited as f64 / 4000.0 - weights[1]).abs() < 0.02);
+        assert!(StateEnsemble::thermal(&h, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_members() {
+        let ket = Array1::from_vec(vec![Complex64::new(1.0, 0.0), Complex64::new(0.0, 0.0)]);
+        assert!(StateEnsemble::new().validate(2).is_err());
+        assert!(StateEnsemble::new()
+            .with_ket("a", ket.clone(), -1.0)
+            .validate(2)
+            .is_err());
+        assert!(StateEnsemble::new()
+            .with_ket("a", ket.clone(), 0.0)
+            .validate(2)
+            .is_err());
+        assert!(StateEnsemble::new().with_ket("a", ket, 1.0).validate(3).is_err());
+    }
+}
@@ -5,6 +5,43 @@ use thiserror::Error;
 /// Result type alias for engine operations
 pub type Result<T> = std::result::Result<T, EngineError>;
 
+/// Process exit codes of the `qte` CLI, one per error category
+///
+/// These values are part of the public interface: scripts may rely on them,
+/// and they will not change between releases. Codes in the 10s mean the
+/// model was rejected before running, codes in the 20s that it failed while
+/// running, codes in the 40s that a proof could not be established.
+pub mod exit_code {
+    pub const SUCCESS: u8 = 0;
+    /// Errors outside the categories below
+    pub const FAILURE: u8 = 1;
+    /// Invalid command-line arguments
+    pub const USAGE: u8 = 2;
+    /// Reading or writing files, (de)serializing data
+    pub const IO: u8 = 3;
+    /// DSL syntax errors
+    pub const PARSE: u8 = 10;
+    /// Shape and type errors
+    pub const TYPE: u8 = 11;
+    /// Invalid values, options or dimensions
+    pub const VALIDATION: u8 = 12;
+    /// Physical constraints: Hermiticity, positivity, trace, CPTP
+    pub const QUANTUM_CONSTRAINT: u8 = 13;
+    pub const EXECUTION: u8 = 20;
+    pub const INTEGRATION: u8 = 21;
+    /// A NaN or infinity reached a module boundary
+    pub const NON_FINITE: u8 = 22;
+    pub const UNSUPPORTED: u8 = 30;
+    /// The prover refuted the statement
+    pub const REFUTED: u8 = 40;
+    /// The prover could neither prove nor refute the statement
+    pub const UNDECIDED: u8 = 41;
+    /// A proof certificate failed verification
+    pub const INVALID_CERTIFICATE: u8 = 42;
+    /// A bug in the engine
+    pub const INTERNAL: u8 = 70;
+}
+
 /// Main error type for the quantum theory engine
 #[derive(Error, Debug)]
 pub enum EngineError {
@@ -21,6 +58,10 @@ pub enum EngineError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    /// A job, template or source looked up by id does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("Quantum constraint violation: {0}")]
     QuantumConstraintError(String),
 
@@ -42,9 +83,16 @@ pub enum EngineError {
     #[error("Execution error: {0}")]
     ExecutionError(String),
 
+    /// Work stopped through its [`CancellationToken`](crate::cancellation::CancellationToken)
+    #[error("Cancelled")]
+    Cancelled,
+
     #[error("ODE integration failed: {0}")]
     IntegrationError(String),
 
+    #[error("Non-finite value: {0}")]
+    NonFinite(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -83,6 +131,49 @@ impl EngineError {
         EngineError::QuantumConstraintError(message.into())
     }
 
+    /// Create an error for a NaN or infinity that reached a module boundary
+    pub fn non_finite(message: impl Into<String>) -> Self {
+        EngineError::NonFinite(message.into())
+    }
+
+    /// Prefix the message of a type, validation or constraint error with the
+    /// source position it arose at; other errors are returned unchanged
+    pub fn at(self, span: crate::ast::Span) -> Self {
+        let located =
+            |message: String| format!("line {}, column {}: {}", span.line, span.column, message);
+        match self {
+            EngineError::TypeError(message) => EngineError::TypeError(located(message)),
+            EngineError::ValidationError(message) => EngineError::ValidationError(located(message)),
+            EngineError::QuantumConstraintError(message) => {
+                EngineError::QuantumConstraintError(located(message))
+            }
+            EngineError::Unsupported(message) => EngineError::Unsupported(located(message)),
+            other => other,
+        }
+    }
+
+    /// The [`exit_code`] for this error's category
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            EngineError::ParseError { .. } => exit_code::PARSE,
+            EngineError::TypeError(_) => exit_code::TYPE,
+            EngineError::ValidationError(_)
+            | EngineError::NotFound(_)
+            | EngineError::DimensionMismatch { .. } => exit_code::VALIDATION,
+            EngineError::QuantumConstraintError(_)
+            | EngineError::NotHermitian { .. }
+            | EngineError::NotPSD { .. }
+            | EngineError::TraceError { .. }
+            | EngineError::NotCPTP { .. } => exit_code::QUANTUM_CONSTRAINT,
+            EngineError::ExecutionError(_) | EngineError::Cancelled => exit_code::EXECUTION,
+            EngineError::IntegrationError(_) => exit_code::INTEGRATION,
+            EngineError::NonFinite(_) => exit_code::NON_FINITE,
+            EngineError::IoError(_) | EngineError::SerializationError(_) => exit_code::IO,
+            EngineError::Unsupported(_) => exit_code::UNSUPPORTED,
+            EngineError::Internal(_) => exit_code::INTERNAL,
+        }
+    }
+
     /// Create a dimension mismatch error
     pub fn dimension_mismatch(expected: impl Into<String>, actual: impl Into<String>) -> Self {
         EngineError::DimensionMismatch {
@@ -91,3 +182,59 @@ impl EngineError {
         }
     }
 }
+
+impl From<serde_json::Error> for EngineError {
+    fn from(err: serde_json::Error) -> Self {
+        EngineError::SerializationError(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_by_category() {
+        let cases = [
+            (EngineError::parse_error(1, 1, "x"), 10),
+            (EngineError::type_error("x"), 11),
+            (EngineError::validation_error("x"), 12),
+            (EngineError::dimension_mismatch("2", "3"), 12),
+            (EngineError::NotFound("x".into()), 12),
+            (EngineError::quantum_error("x"), 13),
+            (EngineError::NotHermitian { deviation: 1.0 }, 13),
+            (
+                EngineError::NotPSD {
+                    min_eigenvalue: -1.0,
+                },
+                13,
+            ),
+            (
+                EngineError::TraceError {
+                    expected: 1.0,
+                    actual: 2.0,
+                },
+                13,
+            ),
+            (EngineError::NotCPTP { reason: "x".into() }, 13),
+            (EngineError::ExecutionError("x".into()), 20),
+            (EngineError::Cancelled, 20),
+            (EngineError::IntegrationError("x".into()), 21),
+            (EngineError::non_finite("x"), 22),
+            (
+                std::io::Error::new(std::io::ErrorKind::NotFound, "x").into(),
+                3,
+            ),
+            (EngineError::SerializationError("x".into()), 3),
+            (EngineError::Unsupported("x".into()), 30),
+            (EngineError::Internal("x".into()), 70),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{}", error);
+        }
+
+        // Locating an error keeps its category
+        let located = EngineError::type_error("x").at(crate::ast::Span { line: 2, column: 5 });
+        assert_eq!(located.exit_code(), exit_code::TYPE);
+    }
+}
@@ -1,19 +1,145 @@
 //! Executor for running IR programs on different backends
 
+use crate::cancellation::{self, CancellationToken};
+use crate::ensemble::{MemberResult, StateEnsemble, StateEnsembleResult};
 use crate::error::{EngineError, Result};
+use crate::gates;
 use crate::ir::*;
 use crate::kernels_cpu;
-use crate::ode::{Rk4Integrator, evolve_unitary};
-use ndarray::{Array1, Array2};
+use crate::kernels_gpu::{self, GpuContext};
+use crate::noise::{NoiseModel, NoiseOperator};
+use crate::ode::{
+    AdaptiveIntegrator, GeneratorDerivative, IntegratorKind, Rk4Integrator, StrictMode, evolve_unitary_rho_with,
+    evolve_unitary_with, generator_norm, integrate_sensitivities,
+};
+use crate::plugin::{KernelRegistry, KernelValue};
+use crate::precision::{Precision, PrecisionCheck};
+use crate::progress::{self, ProgressReporter, StepProgress};
+use crate::propagator::{self, PropagatorCache, PropagatorCacheStats};
+#[cfg(feature = "cache")]
+use crate::result_cache::ResultCache;
+use ndarray::{Array1, Array2, ArrayView2, Axis, s};
 use num_complex::Complex64;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Tidy CSV and JSON export of results, from [`crate::io`]
+pub use crate::io::{export_csv, export_json};
 
 /// Backend configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackendConfig {
     pub backend_type: BackendType,
     pub num_threads: Option<usize>,
+    /// Integrator for Lindblad evolution
+    #[serde(default)]
+    pub integrator: IntegratorKind,
+    /// Track trace drift and positivity of ρ along each trajectory
+    #[serde(default)]
+    pub diagnostics: Option<DiagnosticThresholds>,
+    /// Abort evolution as soon as ρ(t) stops being a valid density matrix
+    #[serde(default)]
+    pub strict: Option<StrictMode>,
+    /// Check each trajectory against the Ehrenfest theorem
+    #[serde(default)]
+    pub ehrenfest: Option<EhrenfestCheck>,
+    /// With `BackendType::Gpu`, smaller systems still run on the CPU
+    #[serde(default = "default_gpu_min_dim")]
+    pub gpu_min_dim: usize,
+    /// Periodically save the evolving state so that an interrupted run can
+    /// continue with `Executor::resume_from`
+    #[serde(default)]
+    pub checkpoint: Option<CheckpointConfig>,
+    /// Floating-point type of the CPU RK4 Lindblad integrator
+    #[serde(default)]
+    pub precision: Precision,
+    /// With `Precision::Single`, rerun a sample of evolutions in double
+    /// precision and fail if they disagree
+    #[serde(default)]
+    pub precision_check: Option<PrecisionCheck>,
+    /// Unitary propagators kept for reuse across steps, experiments and
+    /// batch points; 0 keeps none beyond a run of equal steps
+    #[serde(default = "default_propagator_cache")]
+    pub propagator_cache: usize,
+}
+
+fn default_gpu_min_dim() -> usize {
+    kernels_gpu::DEFAULT_MIN_DIM
+}
+
+fn default_propagator_cache() -> usize {
+    propagator::DEFAULT_CAPACITY
+}
+
+/// Where and how often `Executor::execute` saves an `EvolutionCheckpoint`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    /// Checkpoint file, overwritten after every `every_steps` time steps
+    pub path: PathBuf,
+    pub every_steps: usize,
+}
+
+/// Thresholds beyond which physicality diagnostics emit a warning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticThresholds {
+    /// Largest tolerated |Tr(ρ(t)) - Tr(ρ(0))|
+    pub max_trace_drift: f64,
+    /// Most negative tolerated eigenvalue of ρ
+    pub min_eigenvalue: f64,
+}
+
+impl Default for DiagnosticThresholds {
+    fn default() -> Self {
+        DiagnosticThresholds {
+            max_trace_drift: 1e-6,
+            min_eigenvalue: -1e-8,
+        }
+    }
+}
+
+/// Physicality diagnostics over one trajectory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsSummary {
+    pub max_trace_drift: f64,
+    pub min_eigenvalue: f64,
+    pub warnings: Vec<String>,
+}
+
+/// Ehrenfest-theorem consistency check: along each trajectory, compare
+/// d⟨A⟩/dt from the computed states with ⟨i[H, A]⟩ plus the dissipator's
+/// contribution. Large residuals usually mean the integrator step is too
+/// coarse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EhrenfestCheck {
+    /// Named matrices to check; empty checks every declared Hamiltonian and
+    /// Hermitian matrix of the system's dimension
+    #[serde(default)]
+    pub observables: Vec<String>,
+    /// Largest residual, in units of A per unit time, before a warning
+    pub tolerance: f64,
+}
+
+impl Default for EhrenfestCheck {
+    fn default() -> Self {
+        EhrenfestCheck {
+            observables: Vec::new(),
+            tolerance: 1e-3,
+        }
+    }
+}
+
+/// Largest Ehrenfest residual of one observable along a trajectory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EhrenfestResidual {
+    pub observable: String,
+    pub max_residual: f64,
+    /// Time of the largest residual
+    pub time: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +154,15 @@ impl Default for BackendConfig {
         BackendConfig {
             backend_type: BackendType::CpuDense,
             num_threads: None,
+            integrator: IntegratorKind::default(),
+            diagnostics: None,
+            strict: None,
+            ehrenfest: None,
+            gpu_min_dim: kernels_gpu::DEFAULT_MIN_DIM,
+            checkpoint: None,
+            precision: Precision::Double,
+            precision_check: None,
+            propagator_cache: propagator::DEFAULT_CAPACITY,
         }
     }
 }
@@ -45,6 +180,63 @@ pub struct ExperimentResult {
     pub times: Vec<f64>,
     pub state_type: StateType,
     pub measurements: Vec<MeasurementResult>,
+    /// Density matrix at each entry of `times`
+    #[serde(skip)]
+    pub states: Vec<Array2<Complex64>>,
+    /// Present when `BackendConfig::diagnostics` is set
+    #[serde(default)]
+    pub diagnostics: Option<DiagnosticsSummary>,
+    /// Present when `BackendConfig::ehrenfest` is set, except for noise
+    /// ensembles, whose averaged states follow no single generator
+    #[serde(default)]
+    pub ehrenfest: Vec<EhrenfestResidual>,
+    /// Expectation values of the experiment's tracked observables
+    #[serde(default)]
+    pub observables: Vec<ObservableSeries>,
+}
+
+impl ExperimentResult {
+    /// Density matrix at any `t` the trajectory spans, by cubic Hermite
+    /// interpolation between the states on the grid (see
+    /// [`crate::ode::hermite_interpolate`])
+    ///
+    /// A channel applied mid-trajectory makes the state jump, which the
+    /// interpolation smooths over in the steps next to it.
+    pub fn state_at(&self, t: f64) -> Result<Array2<Complex64>> {
+        if self.states.is_empty() {
+            return Err(EngineError::validation_error(format!(
+                "Result of '{}' holds no states; they are not serialized",
+                self.name
+            )));
+        }
+        crate::ode::hermite_interpolate(&self.times, &self.states, t)
+    }
+
+    /// Bloch vector of the qubit state at each time, see
+    /// [`crate::quantum_info::bloch_vector`]
+    pub fn bloch_vectors(&self) -> Result<Vec<(f64, f64, f64)>> {
+        if self.states.is_empty() {
+            return Err(EngineError::validation_error(format!(
+                "Result of '{}' holds no states; they are not serialized",
+                self.name
+            )));
+        }
+        self.states.iter().map(crate::quantum_info::bloch_vector).collect()
+    }
+
+    /// The density matrix at each time in the eigenbasis `basis`, e.g. of
+    /// [`Executor::eigenbasis`]: diagonal entries are level populations and
+    /// off-diagonal ones coherences, which rotate at the Bohr frequencies
+    pub fn in_eigenbasis(&self, basis: &kernels_cpu::Eigendecomposition) -> Result<Vec<Array2<Complex64>>> {
+        let dim = basis.eigenvalues.len();
+        if let Some(rho) = self.states.iter().find(|rho| rho.dim() != (dim, dim)) {
+            return Err(EngineError::dimension_mismatch(
+                format!("{}x{} states", dim, dim),
+                format!("{:?}", rho.dim()),
+            ));
+        }
+        Ok(self.states.iter().map(|rho| basis.to_eigenbasis(rho)).collect())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,26 +252,424 @@ pub struct MeasurementResult {
     pub probabilities: Vec<f64>,
 }
 
+/// Expectation value of one tracked observable along a trajectory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObservableSeries {
+    pub label: String,
+    /// `Tr(ρ A)` at each time of the experiment; real for Hermitian `A`
+    pub values: Vec<Complex64>,
+}
+
+/// Derivative of one tracked expectation value with respect to one
+/// parameter along a trajectory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObservableSensitivity {
+    pub experiment: String,
+    pub label: String,
+    pub parameter: String,
+    /// ∂Tr(ρ A)/∂θ at each time of the experiment
+    pub values: Vec<Complex64>,
+}
+
+/// Generator and tracked observables of one experiment at fixed parameter
+/// values
+struct ExperimentOperators {
+    hamiltonian: Option<Array2<Complex64>>,
+    lindblad_ops: Vec<(Array2<Complex64>, f64)>,
+    observables: Vec<Array2<Complex64>>,
+}
+
+/// Consecutive time steps of one experiment, from a chunked execution
+#[derive(Debug, Clone)]
+pub struct TrajectoryChunk {
+    pub experiment: String,
+    /// Index of `times[0]` in the experiment's full time grid
+    pub start_index: usize,
+    pub times: Vec<f64>,
+    /// Density matrix at each entry of `times`
+    pub states: Vec<Array2<Complex64>>,
+    /// Where to resume after this chunk
+    pub checkpoint: EvolutionCheckpoint,
+}
+
+/// The evolved state of an experiment at one point of its time grid
+///
+/// Evolution draws no random numbers, so the state and its time index are
+/// all a resumed run needs to reproduce the rest of the trajectory exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvolutionCheckpoint {
+    pub experiment: String,
+    pub time_index: usize,
+    pub state: EvolutionState,
+}
+
+impl EvolutionCheckpoint {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Write to `path` via a temporary file, so that a run killed while
+    /// saving leaves the previous checkpoint intact
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_string(self)?)?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+/// State carried between chunks: a ket while the evolution stays pure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "StoredState", try_from = "StoredState")]
+pub enum EvolutionState {
+    Ket(Array1<Complex64>),
+    Rho(Array2<Complex64>),
+}
+
+/// Serialized form of `EvolutionState`, with ρ flattened in row-major order
+#[derive(Serialize, Deserialize)]
+enum StoredState {
+    Ket(Vec<Complex64>),
+    Rho { dim: usize, data: Vec<Complex64> },
+}
+
+impl From<EvolutionState> for StoredState {
+    fn from(state: EvolutionState) -> Self {
+        match state {
+            EvolutionState::Ket(ket) => StoredState::Ket(ket.to_vec()),
+            EvolutionState::Rho(rho) => StoredState::Rho {
+                dim: rho.nrows(),
+                data: rho.iter().copied().collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<StoredState> for EvolutionState {
+    type Error = String;
+
+    fn try_from(state: StoredState) -> std::result::Result<Self, String> {
+        match state {
+            StoredState::Ket(data) => Ok(EvolutionState::Ket(Array1::from_vec(data))),
+            StoredState::Rho { dim, data } => Array2::from_shape_vec((dim, dim), data)
+                .map(EvolutionState::Rho)
+                .map_err(|e| format!("Invalid density matrix in checkpoint: {}", e)),
+        }
+    }
+}
+
 /// Executor for IR programs
 pub struct Executor {
     config: BackendConfig,
     matrix_cache: HashMap<NodeId, Array2<Complex64>>,
     vector_cache: HashMap<NodeId, Array1<Complex64>>,
     scalar_cache: HashMap<NodeId, Complex64>,
+    /// Kraus operators of each `IrNode::ApplyChannel`
+    channel_cache: HashMap<NodeId, Vec<Array2<Complex64>>>,
+    kernels: Arc<KernelRegistry>,
+    /// U(dt) of each Hamiltonian node, shared with the executors of a batch
+    propagators: Arc<PropagatorCache>,
+    /// Results of earlier runs, consulted by `execute`
+    #[cfg(feature = "cache")]
+    result_cache: Option<Arc<ResultCache>>,
+    /// Values of the program's `IrNode::Parameter`s for the current run
+    params: HashMap<String, f64>,
+    /// Checked before each experiment and time step
+    cancellation: Option<CancellationToken>,
+    /// Told the fraction of the program's time steps taken
+    progress: Option<ProgressReporter>,
+    /// Time steps of the current run, counted for `progress`
+    steps: Option<StepProgress>,
 }
 
 impl Executor {
     pub fn new(config: BackendConfig) -> Self {
+        Self::with_kernels(config, Arc::new(KernelRegistry::new()))
+    }
+
+    /// Create an executor that evaluates `IrNode::Custom` nodes with the
+    /// plugin kernels in `kernels`
+    pub fn with_kernels(config: BackendConfig, kernels: Arc<KernelRegistry>) -> Self {
+        let propagators = Arc::new(PropagatorCache::new(config.propagator_cache));
         Executor {
             config,
             matrix_cache: HashMap::new(),
             vector_cache: HashMap::new(),
             scalar_cache: HashMap::new(),
+            channel_cache: HashMap::new(),
+            kernels,
+            propagators,
+            #[cfg(feature = "cache")]
+            result_cache: None,
+            params: HashMap::new(),
+            cancellation: None,
+            progress: None,
+            steps: None,
         }
     }
 
+    /// Stop runs with [`EngineError::Cancelled`] at the first experiment or
+    /// time step after `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Report each run's progress to `reporter` as the fraction of the
+    /// program's time steps taken
+    pub fn with_progress(mut self, reporter: ProgressReporter) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+
+    /// Share `cache` for unitary propagators, e.g. with other executors
+    /// running the same Hamiltonians
+    pub fn with_propagator_cache(mut self, cache: Arc<PropagatorCache>) -> Self {
+        self.propagators = cache;
+        self
+    }
+
+    /// Reuse of unitary propagators so far, across this executor and any
+    /// sharing its cache
+    pub fn propagator_cache_stats(&self) -> PropagatorCacheStats {
+        self.propagators.stats()
+    }
+
+    /// Look up each program `execute` runs in `cache` first, and store
+    /// the results of those it has to run
+    #[cfg(feature = "cache")]
+    pub fn with_result_cache(mut self, cache: Arc<ResultCache>) -> Self {
+        self.result_cache = Some(cache);
+        self
+    }
+
     /// Execute an IR program
     pub fn execute(&mut self, ir: &IrProgram) -> Result<ExecutionResult> {
+        #[cfg(feature = "cache")]
+        if let Some(cache) = self.result_cache.clone() {
+            let Some(key) = ResultCache::key(ir, &self.params, &self.config)? else {
+                return self.execute_uncached(ir);
+            };
+            if let Some(result) = cache.get(&key) {
+                crate::logging::record_count("result_cache.hit", 1);
+                return Ok(result);
+            }
+            crate::logging::record_count("result_cache.miss", 1);
+            let result = self.execute_uncached(ir)?;
+            if let Err(err) = cache.insert(&key, &result) {
+                crate::logging::warn("executor", format!("Could not cache result: {}", err));
+            }
+            return Ok(result);
+        }
+        self.execute_uncached(ir)
+    }
+
+    /// Execute `ir` with its parameters (`IrNode::Parameter`) set to
+    /// `params`
+    ///
+    /// The values are read as the program runs, so fits and sweeps can run
+    /// one lowered program at many points without lowering or copying it
+    /// again. Every parameter needs a value, and every value must be for a
+    /// parameter of the program.
+    pub fn execute_with_params(&mut self, ir: &IrProgram, params: &HashMap<String, f64>) -> Result<ExecutionResult> {
+        let declared = ir.parameters();
+        if let Some(name) = params.keys().find(|name| !declared.contains(&name.as_str())) {
+            return Err(EngineError::validation_error(format!(
+                "'{}' is not a parameter of the program",
+                name
+            )));
+        }
+        self.params = params.clone();
+        let result = self.execute(ir);
+        self.params.clear();
+        result
+    }
+
+    /// [`Executor::execute_with_params`], also returning the derivative of
+    /// every tracked observable with respect to each parameter in `wrt`
+    ///
+    /// The derivatives come from the forward sensitivity equations (see
+    /// [`crate::ode::integrate_sensitivities`]), integrated alongside the
+    /// state; ∂H/∂θ, the derivatives of the jump and tracked operators are
+    /// central differences of the operators, which are cheap next to the
+    /// evolution. Initial states are taken to be independent of the
+    /// parameters, and experiments that start from another's final state or
+    /// apply channels are not supported.
+    pub fn execute_with_sensitivities(
+        &mut self,
+        ir: &IrProgram,
+        params: &HashMap<String, f64>,
+        wrt: &[&str],
+    ) -> Result<(ExecutionResult, Vec<ObservableSensitivity>)> {
+        if let Some(name) = wrt.iter().find(|name| !params.contains_key(**name)) {
+            return Err(EngineError::validation_error(format!(
+                "No value for parameter '{}' to differentiate with respect to",
+                name
+            )));
+        }
+        if let Some(experiment) = ir
+            .experiments
+            .iter()
+            .find(|e| e.initial_from.is_some() || !e.channels.is_empty())
+        {
+            return Err(EngineError::Unsupported(format!(
+                "Sensitivities of '{}': experiments that start from another's final state or apply channels",
+                experiment.name
+            )));
+        }
+        let result = self.execute_with_params(ir, params)?;
+        let operators = self.operators_at(ir, params)?;
+
+        // d[k][e]: derivative of the operators of experiment e by wrt[k]
+        let mut derivatives = Vec::with_capacity(wrt.len());
+        for name in wrt {
+            let theta = params[*name];
+            let h = 1e-6 * theta.abs().max(1.0);
+            let mut shifted = params.clone();
+            shifted.insert(name.to_string(), theta + h);
+            let plus = self.operators_at(ir, &shifted)?;
+            shifted.insert(name.to_string(), theta - h);
+            let minus = self.operators_at(ir, &shifted)?;
+            let scale = Complex64::new(0.5 / h, 0.0);
+            let difference = |a: &Array2<Complex64>, b: &Array2<Complex64>| (a - b).mapv_into(|z| z * scale);
+            let per_experiment: Vec<_> = plus
+                .iter()
+                .zip(&minus)
+                .map(|(p, m)| {
+                    let generator = p.hamiltonian.as_ref().zip(m.hamiltonian.as_ref()).map(|(hp, hm)| {
+                        GeneratorDerivative {
+                            hamiltonian: difference(hp, hm),
+                            lindblad_ops: p
+                                .lindblad_ops
+                                .iter()
+                                .zip(&m.lindblad_ops)
+                                .map(|((lp, _), (lm, _))| difference(lp, lm))
+                                .collect(),
+                        }
+                    });
+                    let observables: Vec<_> =
+                        p.observables.iter().zip(&m.observables).map(|(a, b)| difference(a, b)).collect();
+                    (generator, observables)
+                })
+                .collect();
+            derivatives.push(per_experiment);
+        }
+
+        let mut sensitivities = Vec::new();
+        for (e, (experiment, outcome)) in ir.experiments.iter().zip(&result.experiment_results).enumerate() {
+            let ops = &operators[e];
+            // ∂ρ(tᵢ)/∂θ_k; zero without evolution
+            let state_derivatives: Vec<Vec<Array2<Complex64>>> = match &ops.hamiltonian {
+                Some(hamiltonian) => {
+                    let generators: Vec<_> = derivatives
+                        .iter()
+                        .map(|d| {
+                            d[e].0.clone().ok_or_else(|| {
+                                EngineError::Internal(format!(
+                                    "No Hamiltonian for '{}' at shifted parameters",
+                                    experiment.name
+                                ))
+                            })
+                        })
+                        .collect::<Result<_>>()?;
+                    integrate_sensitivities(
+                        hamiltonian,
+                        &ops.lindblad_ops,
+                        &generators,
+                        outcome.states[0].clone(),
+                        &outcome.times,
+                    )?
+                    .sensitivities
+                }
+                None => wrt
+                    .iter()
+                    .map(|_| outcome.states.iter().map(|rho| Array2::zeros(rho.raw_dim())).collect())
+                    .collect(),
+            };
+            for (k, name) in wrt.iter().enumerate() {
+                for (j, (observable, label)) in ops
+                    .observables
+                    .iter()
+                    .zip(experiment.observables.iter().map(|o| &o.label))
+                    .enumerate()
+                {
+                    let d_observable = &derivatives[k][e].1[j];
+                    // ∂Tr(ρA) = Tr(∂ρ A) + Tr(ρ ∂A)
+                    let values = outcome
+                        .states
+                        .iter()
+                        .zip(&state_derivatives[k])
+                        .map(|(rho, d_rho)| {
+                            Ok(kernels_cpu::expectation(observable, d_rho)?
+                                + kernels_cpu::expectation(d_observable, rho)?)
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    sensitivities.push(ObservableSensitivity {
+                        experiment: experiment.name.clone(),
+                        label: label.clone(),
+                        parameter: name.to_string(),
+                        values,
+                    });
+                }
+            }
+        }
+        Ok((result, sensitivities))
+    }
+
+    /// Generator and tracked observables of each experiment of `ir` with
+    /// its parameters set to `params`
+    fn operators_at(&mut self, ir: &IrProgram, params: &HashMap<String, f64>) -> Result<Vec<ExperimentOperators>> {
+        self.params = params.clone();
+        let loaded = ir.nodes.iter().try_for_each(|node| self.load_node(node));
+        self.params.clear();
+        loaded?;
+
+        ir.experiments
+            .iter()
+            .map(|experiment| {
+                let (hamiltonian, lindblad_ops) = match experiment.evolution.as_ref().map(|e| &e.method) {
+                    Some(EvolutionMethod::Schrodinger { hamiltonian }) => {
+                        (Some(self.get_matrix(*hamiltonian)?), vec![])
+                    }
+                    Some(EvolutionMethod::Lindblad { hamiltonian, operators }) => (
+                        Some(self.get_matrix(*hamiltonian)?),
+                        operators
+                            .iter()
+                            .map(|op| Ok((self.get_matrix(op.operator)?, op.rate)))
+                            .collect::<Result<Vec<_>>>()?,
+                    ),
+                    None => (None, vec![]),
+                };
+                let observables = experiment
+                    .observables
+                    .iter()
+                    .map(|observable| self.get_matrix(observable.operator))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(ExperimentOperators {
+                    hamiltonian,
+                    lindblad_ops,
+                    observables,
+                })
+            })
+            .collect()
+    }
+
+    fn execute_uncached(&mut self, ir: &IrProgram) -> Result<ExecutionResult> {
+        let total_steps = ir
+            .experiments
+            .iter()
+            .filter_map(|e| e.evolution.as_ref())
+            .map(|evolution| evolution.times.len().saturating_sub(1))
+            .sum();
+        self.steps = self.progress.clone().map(|reporter| StepProgress::new(reporter, total_steps));
+        if self.config.checkpoint.is_some() {
+            return self.execute_checkpointed(ir, None);
+        }
+
         // Load all nodes into cache
         for node in &ir.nodes {
             self.load_node(node)?;
@@ -87,8 +677,11 @@ impl Executor {
 
         // Execute experiments
         let mut experiment_results = Vec::new();
+        let mut finals = HashMap::new();
         for experiment in &ir.experiments {
-            let result = self.execute_experiment(experiment)?;
+            self.seed_initial_state(experiment, &finals)?;
+            let result = self.execute_experiment(ir, experiment)?;
+            finals.insert(experiment.name.clone(), self.final_state(experiment, &result.states)?);
             experiment_results.push(result);
         }
 
@@ -97,139 +690,2273 @@ impl Executor {
         })
     }
 
-    fn load_node(&mut self, node: &IrNode) -> Result<()> {
-        match node {
-            IrNode::LoadMatrix { id, data, shape, .. } => {
-                let mat = Array2::from_shape_vec(*shape, data.clone())
-                    .map_err(|e| EngineError::ExecutionError(format!("Failed to load matrix: {}", e)))?;
-                self.matrix_cache.insert(*id, mat);
-            }
-            IrNode::LoadVector { id, data, .. } => {
-                let vec = Array1::from_vec(data.clone());
-                self.vector_cache.insert(*id, vec);
-            }
-            IrNode::Scalar { id, value } => {
-                self.scalar_cache.insert(*id, *value);
+    /// Run `ir_template` once for each set of parameter and constant
+    /// values in `param_sets`, in parallel across rayon workers
+    ///
+    /// Each point passes the values of parameters to
+    /// [`Executor::execute_with_params`], substitutes those of constants
+    /// with [`IrProgram::substitute`], copying the program only then, and
+    /// runs on a fresh executor with this one's configuration and kernels,
+    /// so a sweep lowers its program once instead of at every point. Up to
+    /// `num_threads` points run at a time, when set. The points share this
+    /// executor's propagator cache, so those whose Hamiltonian does not
+    /// depend on the swept values compute its propagators once. Checkpointing
+    /// is off, as the points would share one checkpoint file; a result
+    /// cache, if set, is consulted for each point. Results are in
+    /// the order of `param_sets`, and a failing point does not stop the
+    /// others. With [`Executor::with_progress`], the batch reports the
+    /// points completed, rather than each point its time steps.
+    pub fn execute_batch(
+        &self,
+        ir_template: &IrProgram,
+        param_sets: &[HashMap<String, f64>],
+    ) -> Vec<Result<ExecutionResult>> {
+        use rayon::prelude::*;
+
+        let config = BackendConfig {
+            checkpoint: None,
+            ..self.config.clone()
+        };
+        let declared = ir_template.parameters();
+        let run_point = |params: &HashMap<String, f64>| -> Result<ExecutionResult> {
+            let (runtime, constants): (HashMap<String, f64>, HashMap<String, f64>) = params
+                .iter()
+                .map(|(name, &value)| (name.clone(), value))
+                .partition(|(name, _)| declared.contains(&name.as_str()));
+            let ir = if constants.is_empty() {
+                Cow::Borrowed(ir_template)
+            } else {
+                Cow::Owned(ir_template.substitute(&constants)?)
+            };
+            let mut executor = Executor::with_kernels(config.clone(), Arc::clone(&self.kernels))
+                .with_propagator_cache(Arc::clone(&self.propagators));
+            executor.cancellation = self.cancellation.clone();
+            #[cfg(feature = "cache")]
+            if let Some(cache) = &self.result_cache {
+                executor = executor.with_result_cache(Arc::clone(cache));
             }
-            _ => {} // Other nodes computed on demand
+            executor.execute_with_params(&ir, &runtime)
+        };
+        let completed = AtomicUsize::new(0);
+        let run = || {
+            param_sets
+                .par_iter()
+                .map(|params| {
+                    let result = run_point(params);
+                    if let Some(progress) = &self.progress {
+                        progress.report_steps(completed.fetch_add(1, Ordering::Relaxed) + 1, param_sets.len());
+                    }
+                    result
+                })
+                .collect()
+        };
+        match self
+            .config
+            .num_threads
+            .map(|n| rayon::ThreadPoolBuilder::new().num_threads(n).build())
+        {
+            Some(Ok(pool)) => pool.install(run),
+            _ => run(),
         }
-        Ok(())
     }
 
-    fn execute_experiment(&mut self, experiment: &IrExperiment) -> Result<ExperimentResult> {
-        // Get initial state
-        let initial_state_id = experiment.initial_state;
-        let (times, state_type) = if let Some(evolution) = &experiment.evolution {
-            let times = evolution.times.clone();
-            let state_type = if self.vector_cache.contains_key(&initial_state_id) {
-                StateType::PureState
-            } else {
-                StateType::DensityMatrix
-            };
-            (times, state_type)
-        } else {
-            (vec![0.0], StateType::DensityMatrix)
+    /// Evolve the experiments `chunk_steps` time steps at a time, handing
+    /// each chunk to `on_chunk` as soon as it is computed rather than
+    /// holding whole trajectories in memory
+    ///
+    /// Returning `ControlFlow::Break` from `on_chunk` cancels the run and
+    /// returns the checkpoint after the last chunk; passing it as `resume`
+    /// continues from there, skipping the experiments already finished.
+    /// Returns `None` once every experiment has run to the end of its grid.
+    /// Experiments without evolution yield their initial state as one chunk.
+    pub fn execute_chunked(
+        &mut self,
+        ir: &IrProgram,
+        chunk_steps: usize,
+        resume: Option<&EvolutionCheckpoint>,
+        mut on_chunk: impl FnMut(TrajectoryChunk) -> ControlFlow<()>,
+    ) -> Result<Option<EvolutionCheckpoint>> {
+        if chunk_steps == 0 {
+            return Err(EngineError::validation_error("Chunks must have at least one time step"));
+        }
+        for node in &ir.nodes {
+            self.load_node(node)?;
+        }
+
+        let skip = match resume {
+            Some(checkpoint) => ir
+                .experiments
+                .iter()
+                .position(|e| e.name == checkpoint.experiment)
+                .ok_or_else(|| {
+                    EngineError::validation_error(format!(
+                        "Checkpoint refers to unknown experiment '{}'",
+                        checkpoint.experiment
+                    ))
+                })?,
+            None => 0,
         };
 
-        // Execute evolution
-        let final_states = if let Some(evolution) = &experiment.evolution {
-            self.execute_evolution(initial_state_id, evolution)?
-        } else {
-            // No evolution, just initial state
-            if let Some(ket) = self.vector_cache.get(&initial_state_id) {
-                vec![kernels_cpu::ket_to_rho(ket)]
-            } else if let Some(rho) = self.matrix_cache.get(&initial_state_id) {
-                vec![rho.clone()]
-            } else {
-                return Err(EngineError::ExecutionError(
-                    "Initial state not found".to_string(),
-                ));
+        let mut finals = HashMap::new();
+        for (k, experiment) in ir.experiments[skip..].iter().enumerate() {
+            let resumed = resume.filter(|_| k == 0);
+            let (mut index, mut state) = match resumed {
+                Some(checkpoint) => (checkpoint.time_index, checkpoint.state.clone()),
+                None => {
+                    self.seed_initial_state(experiment, &finals)?;
+                    (0, self.initial_state(experiment.initial_state)?)
+                }
+            };
+            // Chunks end where channels act, so they apply between chunks
+            let next_channel = |index: usize, first: bool| {
+                experiment
+                    .channels
+                    .iter()
+                    .map(|c| c.time_index)
+                    .filter(|&i| i > index || (first && i == index))
+                    .min()
+            };
+            let (times, h) = match &experiment.evolution {
+                Some(evolution) => (
+                    evolution.times.clone(),
+                    Some(self.get_matrix(hamiltonian_id(evolution))?),
+                ),
+                None => (vec![0.0], None),
+            };
+            if index >= times.len() {
+                return Err(EngineError::validation_error(format!(
+                    "Checkpoint index {} is beyond the {} time steps of '{}'",
+                    index,
+                    times.len(),
+                    experiment.name
+                )));
             }
-        };
 
-        // Execute measurements (stub for now)
-        let measurements = Vec::new();
+            // The first chunk starts with the initial state; later ones,
+            // and a resumed run, start after the checkpointed state
+            let mut first = resumed.is_none();
+            while first || index + 1 < times.len() {
+                let end = (index + chunk_steps)
+                    .min(times.len() - 1)
+                    .min(next_channel(index, first).unwrap_or(usize::MAX));
+                let (mut states, next) = match (&experiment.evolution, &h) {
+                    (Some(evolution), Some(h)) => {
+                        self.evolve_segment(h, &state, experiment, evolution, &times[index..=end], index)?
+                    }
+                    _ => {
+                        let state = self.apply_channels(experiment, 0, state.clone())?;
+                        (vec![state.to_rho()], state)
+                    }
+                };
+                let start_index = if first { index } else { index + 1 };
+                if !first {
+                    states.remove(0);
+                }
+                let checkpoint = EvolutionCheckpoint {
+                    experiment: experiment.name.clone(),
+                    time_index: end,
+                    state: next.clone(),
+                };
+                let chunk = TrajectoryChunk {
+                    experiment: experiment.name.clone(),
+                    start_index,
+                    times: times[start_index..=end].to_vec(),
+                    states,
+                    checkpoint: checkpoint.clone(),
+                };
+                if on_chunk(chunk).is_break() {
+                    return Ok(Some(checkpoint));
+                }
+                (index, state, first) = (end, next, false);
+            }
+            finals.insert(experiment.name.clone(), state);
+        }
+        Ok(None)
+    }
 
-        Ok(ExperimentResult {
-            name: experiment.name.clone(),
-            times,
-            state_type,
-            measurements,
-        })
+    /// Continue a run of `ir` from a checkpoint saved by
+    /// `BackendConfig::checkpoint`, which keeps being updated if set
+    ///
+    /// Experiments finished before the checkpoint are left out, and the
+    /// checkpointed one covers its time grid after the checkpoint, with only
+    /// the measurements taken there.
+    pub fn resume_from(&mut self, ir: &IrProgram, checkpoint: &EvolutionCheckpoint) -> Result<ExecutionResult> {
+        self.execute_checkpointed(ir, Some(checkpoint))
     }
 
-    fn execute_evolution(
+    fn execute_checkpointed(
         &mut self,
-        initial_state_id: NodeId,
-        evolution: &IrEvolution,
-    ) -> Result<Vec<Array2<Complex64>>> {
-        match &evolution.method {
-            EvolutionMethod::Schrodinger { hamiltonian } => {
-                let h = self.get_matrix(*hamiltonian)?;
+        ir: &IrProgram,
+        resume: Option<&EvolutionCheckpoint>,
+    ) -> Result<ExecutionResult> {
+        let config = self.config.checkpoint.clone();
+        let chunk_steps = match &config {
+            Some(config) => config.every_steps,
+            None => ir
+                .experiments
+                .iter()
+                .filter_map(|e| e.evolution.as_ref().map(|evolution| evolution.times.len()))
+                .max()
+                .unwrap_or(1),
+        };
 
-                if let Some(ket) = self.vector_cache.get(&initial_state_id).cloned() {
-                    // Pure state evolution
-                    let kets = evolve_unitary(&h, &ket, &evolution.times)?;
-                    Ok(kets.iter().map(|k| kernels_cpu::ket_to_rho(k)).collect())
-                } else {
-                    Err(EngineError::ExecutionError(
-                        "Schrödinger evolution requires initial ket".to_string(),
-                    ))
+        // (experiment, start index, times, states)
+        type Trajectory = (String, usize, Vec<f64>, Vec<Array2<Complex64>>);
+        let mut trajectories: Vec<Trajectory> = Vec::new();
+        let mut save_error = None;
+        self.execute_chunked(ir, chunk_steps, resume, |chunk| {
+            if let Some(config) = &config {
+                if let Err(e) = chunk.checkpoint.save(&config.path) {
+                    save_error = Some(e);
+                    return ControlFlow::Break(());
                 }
             }
-            EvolutionMethod::Lindblad {
-                hamiltonian,
-                operators,
-            } => {
-                let h = self.get_matrix(*hamiltonian)?;
-                let rho0 = if let Some(rho) = self.matrix_cache.get(&initial_state_id).cloned() {
-                    rho
-                } else if let Some(ket) = self.vector_cache.get(&initial_state_id) {
-                    kernels_cpu::ket_to_rho(ket)
-                } else {
-                    return Err(EngineError::ExecutionError(
-                        "Initial state not found".to_string(),
-                    ));
-                };
-
-                // Get Lindblad operators
-                let mut lindblad_ops = Vec::new();
-                for op in operators {
-                    let l = self.get_matrix(op.operator)?;
-                    lindblad_ops.push((l, op.rate));
+            match trajectories.last_mut() {
+                Some((name, _, times, states)) if *name == chunk.experiment => {
+                    times.extend(chunk.times);
+                    states.extend(chunk.states);
                 }
+                _ => trajectories.push((chunk.experiment, chunk.start_index, chunk.times, chunk.states)),
+            }
+            ControlFlow::Continue(())
+        })?;
+        if let Some(e) = save_error {
+            return Err(e);
+        }
 
-                let integrator = Rk4Integrator::new(h, lindblad_ops);
-                let result = integrator.integrate(rho0, &evolution.times)?;
+        let experiment_results = trajectories
+            .into_iter()
+            .map(|(name, start_index, times, states)| {
+                let experiment = ir
+                    .experiments
+                    .iter()
+                    .find(|e| e.name == name)
+                    .ok_or_else(|| EngineError::Internal(format!("Chunk of unknown experiment '{}'", name)))?;
+                self.experiment_result(ir, experiment, start_index, times, states)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ExecutionResult {
+            experiment_results,
+        })
+    }
 
-                Ok(result.states)
-            }
+    /// Evaluate the Hamiltonians declared in `ir`, in declaration order
+    pub fn hamiltonians(&mut self, ir: &IrProgram) -> Result<Vec<(String, Array2<Complex64>)>> {
+        for node in &ir.nodes {
+            self.load_node(node)?;
         }
+
+        ir.hamiltonians
+            .iter()
+            .map(|(name, id)| Ok((name.clone(), self.get_matrix(*id)?)))
+            .collect()
     }
 
-    fn get_matrix(&self, id: NodeId) -> Result<Array2<Complex64>> {
-        self.matrix_cache
-            .get(&id)
-            .cloned()
-            .ok_or_else(|| EngineError::ExecutionError(format!("Matrix {} not found", id)))
+    /// Evaluate node `id` of `ir`, a scalar, vector or matrix
+    pub fn evaluate(&mut self, ir: &IrProgram, id: NodeId) -> Result<KernelValue> {
+        for node in &ir.nodes {
+            self.load_node(node)?;
+        }
+        self.get_value(id)
     }
 
-    fn get_scalar(&self, id: NodeId) -> Result<Complex64> {
-        self.scalar_cache
-            .get(&id)
-            .copied()
-            .ok_or_else(|| EngineError::ExecutionError(format!("Scalar {} not found", id)))
+    /// Execute an IR program averaged over shot-to-shot control noise
+    ///
+    /// Every experiment is evolved once per noise realisation, with the
+    /// realisations split across `num_threads` worker threads, and the
+    /// resulting density matrices are averaged.
+    pub fn execute_ensemble(&mut self, ir: &IrProgram, noise: &NoiseModel) -> Result<ExecutionResult> {
+        noise.validate()?;
+
+        for node in &ir.nodes {
+            self.load_node(node)?;
+        }
+
+        let draws = noise.draw();
+        let mut experiment_results = Vec::new();
+        let mut finals = HashMap::new();
+        for experiment in &ir.experiments {
+            self.seed_initial_state(experiment, &finals)?;
+            let (result, last) = match &experiment.evolution {
+                Some(evolution) => {
+                    let result = self.execute_experiment_ensemble(ir, experiment, evolution, noise, &draws)?;
+                    // The ensemble average is mixed whatever the initial state
+                    let last = result.states.last().cloned().map(EvolutionState::Rho);
+                    (result, last)
+                }
+                None => {
+                    let result = self.execute_experiment(ir, experiment)?;
+                    let last = self.final_state(experiment, &result.states)?;
+                    (result, Some(last))
+                }
+            };
+            finals.extend(last.map(|state| (experiment.name.clone(), state)));
+            experiment_results.push(result);
+        }
+
+        Ok(ExecutionResult {
+            experiment_results,
+        })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Run the experiment named `experiment` once for each initial state of
+    /// `ensemble`, in place of the experiment's own initial state
+    ///
+    /// Schrödinger evolutions advance all members together, applying each
+    /// step's propagator to every member in one matrix product; Lindblad
+    /// evolutions, and the GPU backend, run member by member. Every member
+    /// reports its own measurements, and `average` holds the weighted mean
+    /// of the members' density matrices with the experiment's measurements
+    /// and checks applied to it.
+    pub fn execute_state_ensemble(
+        &mut self,
+        ir: &IrProgram,
+        experiment: &str,
+        ensemble: &StateEnsemble,
+    ) -> Result<StateEnsembleResult> {
+        for node in &ir.nodes {
+            self.load_node(node)?;
+        }
 
-    #[test]
-    fn test_executor_creation() {
-        let config = BackendConfig::default();
-        let executor = Executor::new(config);
-        assert!(executor.matrix_cache.is_empty());
+        let experiment = ir
+            .experiments
+            .iter()
+            .find(|e| e.name == experiment)
+            .ok_or_else(|| EngineError::ExecutionError(format!("Experiment '{}' not found", experiment)))?;
+        let dim = match &experiment.evolution {
+            Some(evolution) => self.get_matrix(hamiltonian_id(evolution))?.nrows(),
+            None => match self.initial_state(experiment.initial_state)? {
+                EvolutionState::Ket(ket) => ket.len(),
+                EvolutionState::Rho(rho) => rho.nrows(),
+            },
+        };
+        ensemble.validate(dim)?;
+
+        let initial: Vec<EvolutionState> = ensemble.members.iter().map(|m| m.state.clone()).collect();
+        let (times, trajectories) = match &experiment.evolution {
+            Some(evolution) => {
+                let h = self.get_matrix(hamiltonian_id(evolution))?;
+                let batched =
+                    matches!(evolution.method, EvolutionMethod::Schrodinger { .. }) && self.gpu(dim)?.is_none();
+                let trajectories = if batched {
+                    self.evolve_members_unitary(hamiltonian_id(evolution), &h, experiment, &evolution.times, initial)?
+                } else {
+                    initial
+                        .into_iter()
+                        .map(|state| self.evolve_state(&h, state, experiment, evolution))
+                        .collect::<Result<Vec<_>>>()?
+                };
+                (evolution.times.clone(), trajectories)
+            }
+            None => {
+                let trajectories = initial
+                    .into_iter()
+                    .map(|state| Ok(vec![self.apply_channels(experiment, 0, state)?.to_rho()]))
+                    .collect::<Result<Vec<_>>>()?;
+                (vec![0.0], trajectories)
+            }
+        };
+
+        let weights = ensemble.normalized_weights();
+        let mut average: Vec<Array2<Complex64>> = vec![Array2::zeros((dim, dim)); times.len()];
+        let mut members = Vec::with_capacity(trajectories.len());
+        for ((member, weight), states) in ensemble.members.iter().zip(&weights).zip(trajectories) {
+            for (acc, rho) in average.iter_mut().zip(&states) {
+                acc.scaled_add(Complex64::new(*weight, 0.0), rho);
+            }
+            members.push(MemberResult {
+                label: member.label.clone(),
+                weight: *weight,
+                measurements: self.execute_measurements(experiment, 0, &times, &states)?,
+                states,
+            });
+        }
+
+        let mut average = self.experiment_result(ir, experiment, 0, times, average)?;
+        let pure = experiment.evolution.is_some()
+            && experiment.channels.is_empty()
+            && matches!(ensemble.members.as_slice(), [member] if matches!(member.state, EvolutionState::Ket(_)));
+        average.state_type = if pure {
+            StateType::PureState
+        } else {
+            StateType::DensityMatrix
+        };
+        Ok(StateEnsembleResult { members, average })
+    }
+
+    /// Unitary evolution of all of `initial` at once over `times`, with the
+    /// channels `experiment` schedules; the trajectory of each state in turn
+    fn evolve_members_unitary(
+        &self,
+        node: NodeId,
+        h: &Array2<Complex64>,
+        experiment: &IrExperiment,
+        times: &[f64],
+        initial: Vec<EvolutionState>,
+    ) -> Result<Vec<Vec<Array2<Complex64>>>> {
+        channel_stops(experiment, times)?;
+        let mut states = initial
+            .into_iter()
+            .map(|state| self.apply_channels(experiment, 0, state))
+            .collect::<Result<Vec<_>>>()?;
+        let mut trajectories = Vec::with_capacity(states.len());
+        for state in &states {
+            let rho = state.to_rho();
+            kernels_cpu::check_finite(&rho, "rho", format_args!("at t={}", times[0]))?;
+            let mut trajectory = Vec::with_capacity(times.len());
+            trajectory.push(rho);
+            trajectories.push(trajectory);
+        }
+
+        // Grids are often uniform, so U(dt) is shared across steps as well
+        let mut propagators = self.propagators.propagators(node, h);
+        for i in 1..times.len() {
+            self.check_step()?;
+            let u = propagators.get(times[i] - times[i - 1])?;
+            states = apply_unitary_batch(&u, &states)?
+                .into_iter()
+                .map(|state| self.apply_channels(experiment, i, state))
+                .collect::<Result<Vec<_>>>()?;
+            for (trajectory, state) in trajectories.iter_mut().zip(&states) {
+                let rho = state.to_rho();
+                kernels_cpu::check_finite(&rho, "rho", format_args!("at t={}", times[i]))?;
+                trajectory.push(rho);
+            }
+        }
+
+        let norm = generator_norm(h, &[]);
+        for trajectory in &trajectories {
+            self.check_strict(trajectory, times, 0, norm)?;
+        }
+        Ok(trajectories)
+    }
+
+    fn load_node(&mut self, node: &IrNode) -> Result<()> {
+        match node {
+            IrNode::LoadMatrix { id, data, shape, .. } => {
+                let mat = Array2::from_shape_vec(*shape, data.clone())
+                    .map_err(|e| EngineError::ExecutionError(format!("Failed to load matrix: {}", e)))?;
+                self.matrix_cache.insert(*id, mat);
+            }
+            IrNode::LoadVector { id, data, .. } => {
+                let vec = Array1::from_vec(data.clone());
+                self.vector_cache.insert(*id, vec);
+            }
+            IrNode::Scalar { id, value } => {
+                self.scalar_cache.insert(*id, *value);
+            }
+            IrNode::Parameter { id, name } => {
+                let value = self.params.get(name).copied().ok_or_else(|| {
+                    EngineError::validation_error(format!(
+                        "Parameter '{}' has no value; pass it to execute_with_params",
+                        name
+                    ))
+                })?;
+                self.scalar_cache.insert(*id, Complex64::new(value, 0.0));
+            }
+            IrNode::MatrixAdd { id, left, right } => {
+                let value = add_values(self.get_value(*left)?, self.get_value(*right)?)?;
+                self.store_value(*id, value);
+            }
+            IrNode::MatrixMul { id, left, right } => {
+                let value = mul_values(self.get_value(*left)?, self.get_value(*right)?)?;
+                self.store_value(*id, value);
+            }
+            IrNode::ScalarMul { id, scalar, matrix } => {
+                let value = mul_values(
+                    KernelValue::Scalar(self.get_scalar(*scalar)?),
+                    KernelValue::Matrix(self.get_matrix(*matrix)?),
+                )?;
+                self.store_value(*id, value);
+            }
+            IrNode::Dagger { id, input } => {
+                let value = kernels_cpu::dagger(&self.get_matrix(*input)?);
+                self.matrix_cache.insert(*id, value);
+            }
+            IrNode::Trace { id, input } => {
+                let value = kernels_cpu::trace(&self.get_matrix(*input)?)?;
+                self.scalar_cache.insert(*id, value);
+            }
+            IrNode::TensorProduct { id, left, right } => {
+                let (a, b) = (self.get_matrix(*left)?, self.get_matrix(*right)?);
+                let value = match self.gpu(a.nrows() * b.nrows())? {
+                    Some(gpu) => gpu.tensor_product(&a, &b)?,
+                    None => kernels_cpu::tensor_product(&a, &b)?,
+                };
+                self.matrix_cache.insert(*id, value);
+            }
+            IrNode::Commutator { id, left, right } => {
+                let value = kernels_cpu::commutator(&self.get_matrix(*left)?, &self.get_matrix(*right)?)?;
+                self.matrix_cache.insert(*id, value);
+            }
+            IrNode::PartialTrace { id, input, dims, keep } => {
+                let rho = self.get_matrix(*input)?;
+                let dims = match dims {
+                    Some(dims) => dims.clone(),
+                    None => kernels_cpu::qubit_dims(rho.nrows())?,
+                };
+                let value = kernels_cpu::partial_trace(&rho, &dims, keep)?;
+                self.matrix_cache.insert(*id, value);
+            }
+            IrNode::Eigendecompose { id, input, output } => {
+                let eig = kernels_cpu::eigendecompose(&self.get_matrix(*input)?)?;
+                match output {
+                    EigenOutput::Eigenvalues => {
+                        let values = eig.eigenvalues.mapv(|e| Complex64::new(e, 0.0));
+                        self.vector_cache.insert(*id, values);
+                    }
+                    EigenOutput::Eigenvectors => {
+                        self.matrix_cache.insert(*id, eig.eigenvectors);
+                    }
+                    EigenOutput::Eigenstate(k) => {
+                        self.vector_cache.insert(*id, eig.eigenstate(*k)?);
+                    }
+                }
+            }
+            IrNode::MatrixExp { id, input } => {
+                let a = self.get_matrix(*input)?;
+                let value = match self.gpu(a.nrows())? {
+                    Some(gpu) => gpu.matrix_exp(&a)?,
+                    None => kernels_cpu::matrix_exp(&a)?,
+                };
+                self.matrix_cache.insert(*id, value);
+            }
+            IrNode::ApplyChannel { id, kraus } => {
+                let ops = kraus.iter().map(|k| self.get_matrix(*k)).collect::<Result<Vec<_>>>()?;
+                if let Some(op) = ops.iter().find(|op| op.dim() != ops[0].dim() || op.nrows() != op.ncols()) {
+                    return Err(EngineError::dimension_mismatch(
+                        format!("{:?} Kraus operator", ops[0].dim()),
+                        format!("{:?}", op.dim()),
+                    ));
+                }
+                self.channel_cache.insert(*id, ops);
+            }
+            IrNode::ApplyGate { id, gate, state, targets } => {
+                let gate = self.get_matrix(*gate)?;
+                match self.get_value(*state)? {
+                    KernelValue::Vector(ket) => {
+                        let n_qubits = gates::register_qubits(ket.len())?;
+                        let unitary = gates::expand_gate(&gate, targets, n_qubits)?;
+                        self.vector_cache.insert(*id, unitary.dot(&ket));
+                    }
+                    KernelValue::Matrix(rho) => {
+                        let n_qubits = gates::register_qubits(rho.nrows())?;
+                        let unitary = gates::expand_gate(&gate, targets, n_qubits)?;
+                        let value = unitary.dot(&rho).dot(&kernels_cpu::dagger(&unitary));
+                        self.matrix_cache.insert(*id, value);
+                    }
+                    KernelValue::Scalar(_) => {
+                        return Err(EngineError::type_error("Gates apply to a ket or density matrix"))
+                    }
+                }
+            }
+            IrNode::Custom { id, kernel, inputs } => {
+                let args = inputs
+                    .iter()
+                    .map(|input| self.get_value(*input))
+                    .collect::<Result<Vec<_>>>()?;
+                let value = self.kernels.execute(kernel, &args)?;
+                self.store_value(*id, value);
+            }
+            _ => {} // Other nodes computed on demand
+        }
+        Ok(())
+    }
+
+    fn get_value(&self, id: NodeId) -> Result<KernelValue> {
+        if let Some(m) = self.matrix_cache.get(&id) {
+            Ok(KernelValue::Matrix(m.clone()))
+        } else if let Some(v) = self.vector_cache.get(&id) {
+            Ok(KernelValue::Vector(v.clone()))
+        } else if let Some(c) = self.scalar_cache.get(&id) {
+            Ok(KernelValue::Scalar(*c))
+        } else {
+            Err(EngineError::ExecutionError(format!("Node {} not evaluated", id)))
+        }
+    }
+
+    fn store_value(&mut self, id: NodeId, value: KernelValue) {
+        match value {
+            KernelValue::Scalar(c) => {
+                self.scalar_cache.insert(id, c);
+            }
+            KernelValue::Vector(v) => {
+                self.vector_cache.insert(id, v);
+            }
+            KernelValue::Matrix(m) => {
+                self.matrix_cache.insert(id, m);
+            }
+        }
+    }
+
+    fn execute_experiment(&self, ir: &IrProgram, experiment: &IrExperiment) -> Result<ExperimentResult> {
+        self.check_cancelled()?;
+        // Get initial state
+        let initial_state_id = experiment.initial_state;
+        let times = match &experiment.evolution {
+            Some(evolution) => evolution.times.clone(),
+            None => vec![0.0],
+        };
+
+        // Execute evolution
+        let final_states = if let Some(evolution) = &experiment.evolution {
+            self.execute_evolution(experiment, evolution)?
+        } else {
+            // No evolution, just initial state and the channels acting on it
+            let initial = if let Some(ket) = self.vector_cache.get(&initial_state_id) {
+                EvolutionState::Ket(ket.clone())
+            } else if let Some(rho) = self.matrix_cache.get(&initial_state_id) {
+                EvolutionState::Rho(rho.clone())
+            } else {
+                return Err(EngineError::ExecutionError(
+                    "Initial state not found".to_string(),
+                ));
+            };
+            vec![self.apply_channels(experiment, 0, initial)?.to_rho()]
+        };
+
+        self.experiment_result(ir, experiment, 0, times, final_states)
+    }
+
+    /// Measurements and checks of the trajectory `states` over `times`,
+    /// which start at `start_index` of the experiment's time grid
+    fn experiment_result(
+        &self,
+        ir: &IrProgram,
+        experiment: &IrExperiment,
+        start_index: usize,
+        times: Vec<f64>,
+        states: Vec<Array2<Complex64>>,
+    ) -> Result<ExperimentResult> {
+        let state_type = if experiment.evolution.is_some()
+            && experiment.channels.is_empty()
+            && self.vector_cache.contains_key(&experiment.initial_state)
+        {
+            StateType::PureState
+        } else {
+            StateType::DensityMatrix
+        };
+
+        let measurements = self.execute_measurements(experiment, start_index, &times, &states)?;
+        let diagnostics = self.diagnose_trajectory(&experiment.name, &times, &states)?;
+        let ehrenfest = match (&self.config.ehrenfest, &experiment.evolution) {
+            (Some(check), Some(evolution)) => {
+                self.check_ehrenfest(ir, &experiment.name, evolution, &times, &states, check)?
+            }
+            _ => Vec::new(),
+        };
+        let observables = self.track_observables(experiment, &states)?;
+
+        Ok(ExperimentResult {
+            name: experiment.name.clone(),
+            times,
+            state_type,
+            measurements,
+            states,
+            diagnostics,
+            ehrenfest,
+            observables,
+        })
+    }
+
+    /// `Tr(ρ A)` of each observable `experiment` tracks, at every state of
+    /// the trajectory `states`
+    fn track_observables(
+        &self,
+        experiment: &IrExperiment,
+        states: &[Array2<Complex64>],
+    ) -> Result<Vec<ObservableSeries>> {
+        experiment
+            .observables
+            .iter()
+            .map(|observable| {
+                let operator = self.get_matrix(observable.operator)?;
+                let values = states
+                    .iter()
+                    .map(|rho| {
+                        if rho.dim() != operator.dim() {
+                            return Err(EngineError::dimension_mismatch(
+                                format!("{:?}", rho.dim()),
+                                format!("{:?} for observable <{}>", operator.dim(), observable.label),
+                            ));
+                        }
+                        kernels_cpu::expectation(&operator, rho)
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(ObservableSeries {
+                    label: observable.label.clone(),
+                    values,
+                })
+            })
+            .collect()
+    }
+
+    fn execute_experiment_ensemble(
+        &self,
+        ir: &IrProgram,
+        experiment: &IrExperiment,
+        evolution: &IrEvolution,
+        noise: &NoiseModel,
+        draws: &[Vec<f64>],
+    ) -> Result<ExperimentResult> {
+        let hamiltonian = self.get_matrix(hamiltonian_id(evolution))?;
+
+        let mut operators = Vec::with_capacity(noise.terms.len());
+        for term in &noise.terms {
+            let op = match &term.operator {
+                NoiseOperator::Hamiltonian => hamiltonian.clone(),
+                NoiseOperator::Named(name) => self.get_named_matrix(ir, name)?,
+            };
+            if op.dim() != hamiltonian.dim() {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{:?}", hamiltonian.dim()),
+                    format!("{:?} for noise operator {:?}", op.dim(), term.operator),
+                ));
+            }
+            operators.push(op);
+        }
+
+        let num_threads = self
+            .config
+            .num_threads
+            .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+            .clamp(1, draws.len());
+        let chunk_size = draws.len().div_ceil(num_threads);
+
+        let hamiltonian = &hamiltonian;
+        let operators = &operators;
+        let partial_sums: Vec<Result<Vec<Array2<Complex64>>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = draws
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut sum: Vec<Array2<Complex64>> = Vec::new();
+                        for xi in chunk {
+                            let mut h = hamiltonian.clone();
+                            for (x, op) in xi.iter().zip(operators) {
+                                h.scaled_add(Complex64::new(*x, 0.0), op);
+                            }
+
+                            let states = self.evolve(&h, experiment, evolution)?;
+                            if sum.is_empty() {
+                                sum = states;
+                            } else {
+                                for (acc, rho) in sum.iter_mut().zip(&states) {
+                                    *acc += rho;
+                                }
+                            }
+                        }
+                        Ok(sum)
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("ensemble worker panicked"))
+                .collect()
+        });
+
+        let mut states: Vec<Array2<Complex64>> = Vec::new();
+        for partial in partial_sums {
+            let partial = partial?;
+            if states.is_empty() {
+                states = partial;
+            } else {
+                for (acc, rho) in states.iter_mut().zip(&partial) {
+                    *acc += rho;
+                }
+            }
+        }
+
+        let n = draws.len() as f64;
+        for rho in &mut states {
+            rho.mapv_inplace(|v| v / n);
+        }
+
+        let diagnostics = self.diagnose_trajectory(&experiment.name, &evolution.times, &states)?;
+        let measurements = self.execute_measurements(experiment, 0, &evolution.times, &states)?;
+        let observables = self.track_observables(experiment, &states)?;
+
+        Ok(ExperimentResult {
+            name: experiment.name.clone(),
+            times: evolution.times.clone(),
+            state_type: StateType::DensityMatrix,
+            measurements,
+            states,
+            diagnostics,
+            ehrenfest: Vec::new(),
+            observables,
+        })
+    }
+
+    /// Outcome probabilities of each scheduled measurement; an observable
+    /// yields its expectation value as the single entry
+    /// Measurements at or after `start_index`, the grid index of `times[0]`
+    fn execute_measurements(
+        &self,
+        experiment: &IrExperiment,
+        start_index: usize,
+        times: &[f64],
+        states: &[Array2<Complex64>],
+    ) -> Result<Vec<MeasurementResult>> {
+        experiment
+            .measurements
+            .iter()
+            .filter(|measurement| measurement.time_index >= start_index)
+            .map(|measurement| {
+                let index = measurement.time_index - start_index;
+                let interpolated;
+                let rho = match measurement.time {
+                    Some(t) => {
+                        interpolated = interpolate_between_channels(experiment, start_index, times, states, index, t)?;
+                        &interpolated
+                    }
+                    None => states.get(index).ok_or_else(|| {
+                        EngineError::ExecutionError(format!(
+                            "Measurement time index {} is beyond the trajectory of '{}'",
+                            measurement.time_index, experiment.name
+                        ))
+                    })?,
+                };
+                let probabilities = match &measurement.measurement_type {
+                    MeasurementType::Projective { projectors: ids }
+                    | MeasurementType::POVM { effects: ids } => {
+                        let elements = ids
+                            .iter()
+                            .map(|&id| self.get_matrix(id))
+                            .collect::<Result<Vec<_>>>()?;
+                        kernels_cpu::measure_projective(&elements, rho)?
+                    }
+                    MeasurementType::Observable { operator } => {
+                        vec![kernels_cpu::expectation(&self.get_matrix(*operator)?, rho)?.re]
+                    }
+                };
+                Ok(MeasurementResult {
+                    time: measurement.time.unwrap_or(times[index]),
+                    probabilities,
+                })
+            })
+            .collect()
+    }
+
+    fn diagnose_trajectory(
+        &self,
+        experiment: &str,
+        times: &[f64],
+        states: &[Array2<Complex64>],
+    ) -> Result<Option<DiagnosticsSummary>> {
+        let Some(thresholds) = &self.config.diagnostics else {
+            return Ok(None);
+        };
+
+        let summary = diagnose_states(times, states, thresholds)?;
+        for warning in &summary.warnings {
+            crate::log_warn!("executor", "{}: {}", experiment, warning);
+        }
+        Ok(Some(summary))
+    }
+
+    fn check_ehrenfest(
+        &self,
+        ir: &IrProgram,
+        experiment: &str,
+        evolution: &IrEvolution,
+        times: &[f64],
+        states: &[Array2<Complex64>],
+        check: &EhrenfestCheck,
+    ) -> Result<Vec<EhrenfestResidual>> {
+        let h = self.get_matrix(hamiltonian_id(evolution))?;
+        let lindblad = match &evolution.method {
+            EvolutionMethod::Schrodinger { .. } => Vec::new(),
+            EvolutionMethod::Lindblad { operators, .. } => operators
+                .iter()
+                .map(|op| Ok((self.get_matrix(op.operator)?, op.rate)))
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        let named = self.named_matrices(ir);
+        let observables = if check.observables.is_empty() {
+            named
+                .into_iter()
+                .filter(|(_, a)| a.dim() == h.dim() && is_hermitian(a))
+                .collect()
+        } else {
+            check
+                .observables
+                .iter()
+                .map(|name| {
+                    named
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .cloned()
+                        .ok_or_else(|| EngineError::ExecutionError(format!("Matrix '{}' not found", name)))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let residuals = ehrenfest_residuals(times, states, &h, &lindblad, &observables)?;
+        for residual in residuals.iter().filter(|r| r.max_residual > check.tolerance) {
+            crate::log_warn!(
+                "executor",
+                "{}: Ehrenfest residual {:.3e} for {} at t = {} (tolerance {:.1e}); the integration step may be too coarse",
+                experiment,
+                residual.max_residual,
+                residual.observable,
+                residual.time,
+                check.tolerance
+            );
+        }
+        Ok(residuals)
+    }
+
+    /// Declared Hamiltonians, then named constant matrices
+    fn named_matrices(&self, ir: &IrProgram) -> Vec<(String, Array2<Complex64>)> {
+        let mut named = ir.hamiltonians.clone();
+        for node in &ir.nodes {
+            if let IrNode::LoadMatrix { id, name, .. } = node {
+                if !named.iter().any(|(n, _)| n == name) {
+                    named.push((name.clone(), *id));
+                }
+            }
+        }
+        named
+            .into_iter()
+            .filter_map(|(name, id)| Some((name, self.matrix_cache.get(&id)?.clone())))
+            .collect()
+    }
+
+    fn execute_evolution(
+        &self,
+        experiment: &IrExperiment,
+        evolution: &IrEvolution,
+    ) -> Result<Vec<Array2<Complex64>>> {
+        let h = self.get_matrix(hamiltonian_id(evolution))?;
+        self.evolve(&h, experiment, evolution)
+    }
+
+    /// Evolve the initial state of `experiment` under `h` (overriding the IR
+    /// Hamiltonian), stopping at each scheduled channel to apply it
+    fn evolve(
+        &self,
+        h: &Array2<Complex64>,
+        experiment: &IrExperiment,
+        evolution: &IrEvolution,
+    ) -> Result<Vec<Array2<Complex64>>> {
+        let initial = self.initial_state(experiment.initial_state)?;
+        self.evolve_state(h, initial, experiment, evolution)
+    }
+
+    /// `evolve` starting from `state` rather than the experiment's own
+    /// initial state
+    fn evolve_state(
+        &self,
+        h: &Array2<Complex64>,
+        mut state: EvolutionState,
+        experiment: &IrExperiment,
+        evolution: &IrEvolution,
+    ) -> Result<Vec<Array2<Complex64>>> {
+        let times = &evolution.times;
+        let stops = channel_stops(experiment, times)?;
+
+        // Each segment starts with the state the previous one ended in
+        let mut states = Vec::with_capacity(times.len());
+        let mut start = 0;
+        for stop in stops {
+            let (mut segment, next) =
+                self.evolve_segment(h, &state, experiment, evolution, &times[start..=stop], start)?;
+            if !states.is_empty() {
+                segment.remove(0);
+            }
+            states.extend(segment);
+            (state, start) = (next, stop);
+        }
+        Ok(states)
+    }
+
+    /// `evolve_from` over `times`, followed by the channels scheduled at
+    /// its last grid index, which also replace the last state
+    fn evolve_segment(
+        &self,
+        h: &Array2<Complex64>,
+        initial: &EvolutionState,
+        experiment: &IrExperiment,
+        evolution: &IrEvolution,
+        times: &[f64],
+        offset: usize,
+    ) -> Result<(Vec<Array2<Complex64>>, EvolutionState)> {
+        let (mut states, next) = self.evolve_from(h, initial, evolution, times, offset)?;
+        let end = offset + times.len() - 1;
+        if !experiment.channels.iter().any(|c| c.time_index == end) {
+            return Ok((states, next));
+        }
+
+        let next = self.apply_channels(experiment, end, next)?;
+        if let Some(last) = states.last_mut() {
+            *last = next.to_rho();
+        }
+        Ok((states, next))
+    }
+
+    /// `state` after the channels `experiment` schedules at grid index
+    /// `index`, applied in schedule order
+    fn apply_channels(&self, experiment: &IrExperiment, index: usize, state: EvolutionState) -> Result<EvolutionState> {
+        let mut scheduled = experiment.channels.iter().filter(|c| c.time_index == index).peekable();
+        if scheduled.peek().is_none() {
+            return Ok(state);
+        }
+
+        let mut rho = state.to_rho();
+        for application in scheduled {
+            let kraus = self.channel_cache.get(&application.channel).ok_or_else(|| {
+                EngineError::ExecutionError(format!("Channel {} not found", application.channel))
+            })?;
+            rho = kernels_cpu::apply_kraus(kraus, &rho)?;
+        }
+        Ok(EvolutionState::Rho(rho))
+    }
+
+    /// Place the final state of the experiment `experiment` starts from, if
+    /// any, where its initial state is expected
+    fn seed_initial_state(
+        &mut self,
+        experiment: &IrExperiment,
+        finals: &HashMap<String, EvolutionState>,
+    ) -> Result<()> {
+        let Some(source) = &experiment.initial_from else {
+            return Ok(());
+        };
+        let state = finals.get(source).ok_or_else(|| {
+            EngineError::ExecutionError(format!(
+                "Experiment '{}' starts from the final state of '{}', which has not run",
+                experiment.name, source
+            ))
+        })?;
+        let dim = match state {
+            EvolutionState::Ket(ket) => ket.len(),
+            EvolutionState::Rho(rho) => rho.nrows(),
+        };
+        if let Some(evolution) = &experiment.evolution {
+            let expected = self.get_matrix(hamiltonian_id(evolution))?.nrows();
+            if expected != dim {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{}-dimensional initial state for '{}'", expected, experiment.name),
+                    format!("{}-dimensional final state of '{}'", dim, source),
+                ));
+            }
+        }
+
+        match state {
+            EvolutionState::Ket(ket) => {
+                self.vector_cache.insert(experiment.initial_state, ket.clone());
+            }
+            EvolutionState::Rho(rho) => {
+                self.matrix_cache.insert(experiment.initial_state, rho.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// The state `experiment` ends in, for experiments starting from it: a
+    /// ket when a pure initial state evolved unitarily, otherwise ρ
+    fn final_state(&self, experiment: &IrExperiment, states: &[Array2<Complex64>]) -> Result<EvolutionState> {
+        let rho = states.last().ok_or_else(|| {
+            EngineError::Internal(format!("Experiment '{}' produced no states", experiment.name))
+        })?;
+        let unitary = experiment.channels.is_empty()
+            && matches!(
+                experiment.evolution.as_ref().map(|e| &e.method),
+                None | Some(EvolutionMethod::Schrodinger { .. })
+            );
+        if !(unitary && self.vector_cache.contains_key(&experiment.initial_state)) {
+            return Ok(EvolutionState::Rho(rho.clone()));
+        }
+
+        // ρ = |ψ⟩⟨ψ|, so column j is ψ ψ_j*: ψ up to a global phase once
+        // divided by |ψ_j|, best conditioned at the largest |ψ_j|
+        let j = (0..rho.nrows())
+            .max_by(|&a, &b| rho[[a, a]].re.total_cmp(&rho[[b, b]].re))
+            .unwrap_or(0);
+        let norm = rho[[j, j]].re.sqrt();
+        Ok(EvolutionState::Ket(rho.column(j).mapv(|z| z / norm)))
+    }
+
+    fn initial_state(&self, id: NodeId) -> Result<EvolutionState> {
+        if let Some(ket) = self.vector_cache.get(&id) {
+            Ok(EvolutionState::Ket(ket.clone()))
+        } else if let Some(rho) = self.matrix_cache.get(&id) {
+            Ok(EvolutionState::Rho(rho.clone()))
+        } else {
+            Err(EngineError::ExecutionError("Initial state not found".to_string()))
+        }
+    }
+
+    /// Evolve `initial` over `times`, whose first entry is grid index
+    /// `offset`, returning the state at each time and the final state
+    fn evolve_from(
+        &self,
+        h: &Array2<Complex64>,
+        initial: &EvolutionState,
+        evolution: &IrEvolution,
+        times: &[f64],
+        offset: usize,
+    ) -> Result<(Vec<Array2<Complex64>>, EvolutionState)> {
+        match &evolution.method {
+            EvolutionMethod::Schrodinger { .. } => match initial {
+                EvolutionState::Ket(ket) => {
+                    // Pure state evolution
+                    let mut kets = match self.gpu(h.nrows())? {
+                        Some(gpu) => gpu.evolve_unitary(h, ket, times)?,
+                        None => {
+                            let mut propagators = self.propagators.propagators(hamiltonian_id(evolution), h);
+                            evolve_unitary_with(ket, times, |dt| {
+                                self.check_step()?;
+                                propagators.get(dt)
+                            })?
+                        }
+                    };
+                    let states: Vec<_> = kets.iter().map(kernels_cpu::ket_to_rho).collect();
+                    self.check_strict(&states, times, offset, generator_norm(h, &[]))?;
+                    let last = kets.pop().unwrap_or_else(|| ket.clone());
+                    Ok((states, EvolutionState::Ket(last)))
+                }
+                // Mixed, e.g. after a channel: ρ(t) = U ρ U†
+                EvolutionState::Rho(rho) => {
+                    let mut propagators = self.propagators.propagators(hamiltonian_id(evolution), h);
+                    let states = evolve_unitary_rho_with(rho, times, |dt| {
+                        self.check_step()?;
+                        propagators.get(dt)
+                    })?;
+                    self.check_strict(&states, times, offset, generator_norm(h, &[]))?;
+                    let last = states.last().cloned().unwrap_or_else(|| rho.clone());
+                    Ok((states, EvolutionState::Rho(last)))
+                }
+            },
+            EvolutionMethod::Lindblad { operators, .. } => {
+                let rho0 = initial.to_rho();
+
+                // Get Lindblad operators
+                let mut lindblad_ops = Vec::new();
+                for op in operators {
+                    let l = self.get_matrix(op.operator)?;
+                    lindblad_ops.push((l, op.rate));
+                }
+
+                if let (IntegratorKind::Rk4, Some(gpu)) = (&self.config.integrator, self.gpu(h.nrows())?) {
+                    let states = gpu.integrate_rk4(h, &lindblad_ops, &rho0, times)?;
+                    self.check_strict(&states, times, offset, generator_norm(h, &lindblad_ops))?;
+                    let last = states.last().cloned().unwrap_or(rho0);
+                    return Ok((states, EvolutionState::Rho(last)));
+                }
+
+                let result = match &self.config.integrator {
+                    IntegratorKind::Rk4 => {
+                        let rk4 = |precision, progress| {
+                            Rk4Integrator::new(h.clone(), lindblad_ops.clone())
+                                .with_strict(self.config.strict.clone())
+                                .with_cancellation(self.cancellation.clone())
+                                .with_progress(progress)
+                                .with_precision(precision)
+                                .integrate(rho0.clone(), times)
+                        };
+                        let result = rk4(self.config.precision, self.steps.clone())?;
+                        if let Some(check) = self.config.precision_check.as_ref().filter(|check| {
+                            self.config.precision == Precision::Single && check.next_is_due()
+                        }) {
+                            let reference = rk4(Precision::Double, None)?;
+                            check.verify(&result.states, &reference.states, times)?;
+                        }
+                        result
+                    }
+                    IntegratorKind::DormandPrince(config) => {
+                        AdaptiveIntegrator::new(h.clone(), lindblad_ops, config.clone())
+                            .with_strict(self.config.strict.clone())
+                            .with_cancellation(self.cancellation.clone())
+                            .with_progress(self.steps.clone())
+                            .integrate(rho0.clone(), times)?
+                    }
+                };
+
+                let last = result.states.last().cloned().unwrap_or(rho0);
+                Ok((result.states, EvolutionState::Rho(last)))
+            }
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        cancellation::check(self.cancellation.as_ref())
+    }
+
+    /// [`Executor::check_cancelled`] before a time step, which is then
+    /// counted towards the run's progress
+    fn check_step(&self) -> Result<()> {
+        self.check_cancelled()?;
+        progress::step(self.steps.as_ref());
+        Ok(())
+    }
+
+    /// GPU for a dense kernel of dimension `dim`, when the GPU backend is
+    /// selected and `dim` reaches `gpu_min_dim`
+    fn gpu(&self, dim: usize) -> Result<Option<Arc<GpuContext>>> {
+        match self.config.backend_type {
+            BackendType::Gpu if dim >= self.config.gpu_min_dim => GpuContext::shared().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Strict-mode checks over states computed without them, e.g. on the GPU
+    fn check_strict(&self, states: &[Array2<Complex64>], times: &[f64], offset: usize, norm: f64) -> Result<()> {
+        if let Some(strict) = &self.config.strict {
+            for (i, rho) in states.iter().enumerate().skip(1) {
+                if strict.is_due(offset + i) {
+                    strict.check(rho, times[i], times[i] - times[i - 1], norm)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_matrix(&self, id: NodeId) -> Result<Array2<Complex64>> {
+        self.matrix_cache
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| EngineError::ExecutionError(format!("Matrix {} not found", id)))
+    }
+
+    /// Eigendecomposition of the Hamiltonian `name` of `ir`, once executed,
+    /// e.g. to view results in its energy eigenbasis with
+    /// [`ExperimentResult::in_eigenbasis`]
+    pub fn eigenbasis(&self, ir: &IrProgram, name: &str) -> Result<kernels_cpu::Eigendecomposition> {
+        let id = ir
+            .hamiltonians
+            .iter()
+            .find(|(hamiltonian, _)| hamiltonian == name)
+            .map(|&(_, id)| id)
+            .ok_or_else(|| EngineError::ExecutionError(format!("Hamiltonian '{}' not found", name)))?;
+        kernels_cpu::eigendecompose(&self.get_matrix(id)?)
+    }
+
+    fn get_named_matrix(&self, ir: &IrProgram, name: &str) -> Result<Array2<Complex64>> {
+        let id = ir
+            .nodes
+            .iter()
+            .find_map(|node| match node {
+                IrNode::LoadMatrix { id, name: n, .. } if n == name => Some(*id),
+                _ => None,
+            })
+            .ok_or_else(|| EngineError::ExecutionError(format!("Matrix '{}' not found", name)))?;
+        self.get_matrix(id)
+    }
+
+    fn get_scalar(&self, id: NodeId) -> Result<Complex64> {
+        self.scalar_cache
+            .get(&id)
+            .copied()
+            .ok_or_else(|| EngineError::ExecutionError(format!("Scalar {} not found", id)))
+    }
+}
+
+impl EvolutionState {
+    pub fn to_rho(&self) -> Array2<Complex64> {
+        match self {
+            EvolutionState::Ket(ket) => kernels_cpu::ket_to_rho(ket),
+            EvolutionState::Rho(rho) => rho.clone(),
+        }
+    }
+}
+
+fn add_values(left: KernelValue, right: KernelValue) -> Result<KernelValue> {
+    match (left, right) {
+        (KernelValue::Scalar(a), KernelValue::Scalar(b)) => Ok(KernelValue::Scalar(a + b)),
+        (KernelValue::Vector(a), KernelValue::Vector(b)) if a.dim() == b.dim() => Ok(KernelValue::Vector(a + b)),
+        (KernelValue::Matrix(a), KernelValue::Matrix(b)) if a.dim() == b.dim() => Ok(KernelValue::Matrix(a + b)),
+        (a, b) => Err(EngineError::ExecutionError(format!(
+            "Cannot add {} and {}",
+            a.kind(),
+            b.kind()
+        ))),
+    }
+}
+
+fn mul_values(left: KernelValue, right: KernelValue) -> Result<KernelValue> {
+    match (left, right) {
+        (KernelValue::Scalar(a), KernelValue::Scalar(b)) => Ok(KernelValue::Scalar(a * b)),
+        (KernelValue::Scalar(a), KernelValue::Matrix(m)) | (KernelValue::Matrix(m), KernelValue::Scalar(a)) => {
+            Ok(KernelValue::Matrix(m.mapv(|x| a * x)))
+        }
+        (KernelValue::Scalar(a), KernelValue::Vector(v)) | (KernelValue::Vector(v), KernelValue::Scalar(a)) => {
+            Ok(KernelValue::Vector(v.mapv(|x| a * x)))
+        }
+        (KernelValue::Matrix(a), KernelValue::Matrix(b)) if a.ncols() == b.nrows() => Ok(KernelValue::Matrix(a.dot(&b))),
+        (KernelValue::Matrix(a), KernelValue::Vector(v)) if a.ncols() == v.len() => Ok(KernelValue::Vector(a.dot(&v))),
+        (a, b) => Err(EngineError::ExecutionError(format!(
+            "Cannot multiply {} by {}",
+            a.kind(),
+            b.kind()
+        ))),
+    }
+}
+
+/// Grid indices at which evolution over `times` must stop: every index with
+/// channels scheduled, then the last one, in increasing order
+fn channel_stops(experiment: &IrExperiment, times: &[f64]) -> Result<Vec<usize>> {
+    let last = times
+        .len()
+        .checked_sub(1)
+        .ok_or_else(|| EngineError::validation_error("Empty time grid"))?;
+    let mut stops = vec![last];
+    for application in &experiment.channels {
+        if application.time_index > last {
+            return Err(EngineError::ExecutionError(format!(
+                "Channel time index {} is beyond the trajectory of '{}'",
+                application.time_index, experiment.name
+            )));
+        }
+        stops.push(application.time_index);
+    }
+    stops.sort_unstable();
+    stops.dedup();
+    Ok(stops)
+}
+
+/// State at `t`, just after grid index `index` of the trajectory `states`
+/// starting at grid index `start_index`, interpolated over the stretch of
+/// the trajectory no channel interrupts
+fn interpolate_between_channels(
+    experiment: &IrExperiment,
+    start_index: usize,
+    times: &[f64],
+    states: &[Array2<Complex64>],
+    index: usize,
+    t: f64,
+) -> Result<Array2<Complex64>> {
+    let global = start_index + index;
+    let channels = experiment.channels.iter().map(|c| c.time_index);
+    // A channel at grid index c leaves the state before it unrecorded, so
+    // its stretch starts at c and the previous one ends at c - 1
+    let lo = channels
+        .clone()
+        .filter(|&c| c <= global)
+        .max()
+        .map_or(0, |c| c.max(start_index) - start_index);
+    let hi = channels
+        .filter(|&c| c > global)
+        .min()
+        .map_or(states.len(), |c| c - start_index)
+        .min(states.len())
+        .saturating_sub(1);
+    if hi <= index {
+        return Err(EngineError::validation_error(format!(
+            "Measurement at t = {} in '{}' falls just before a channel or the end of the trajectory; \
+             schedule it on the time grid",
+            t, experiment.name
+        )));
+    }
+    crate::ode::hermite_interpolate(&times[lo..=hi], &states[lo..=hi], t)
+}
+
+/// `U` applied to every state at once: kets as the columns of one matrix,
+/// and density matrices as `U [ρ_1 … ρ_n]`, whose blocks stacked vertically
+/// give every `U ρ_k U†` in a second product
+fn apply_unitary_batch(u: &Array2<Complex64>, states: &[EvolutionState]) -> Result<Vec<EvolutionState>> {
+    let dim = u.nrows();
+    let mut kets = Vec::new();
+    let mut rhos = Vec::new();
+    for (k, state) in states.iter().enumerate() {
+        match state {
+            EvolutionState::Ket(ket) => kets.push((k, ket.view().insert_axis(Axis(1)))),
+            EvolutionState::Rho(rho) => rhos.push((k, rho.view())),
+        }
+    }
+    fn stack(axis: Axis, views: &[ArrayView2<Complex64>]) -> Result<Array2<Complex64>> {
+        ndarray::concatenate(axis, views)
+            .map_err(|e| EngineError::Internal(format!("Failed to stack ensemble states: {}", e)))
+    }
+
+    let mut evolved: Vec<Option<EvolutionState>> = vec![None; states.len()];
+    if !kets.is_empty() {
+        let views: Vec<_> = kets.iter().map(|(_, ket)| ket.view()).collect();
+        let columns = u.dot(&stack(Axis(1), &views)?);
+        for (c, (k, _)) in kets.iter().enumerate() {
+            evolved[*k] = Some(EvolutionState::Ket(columns.column(c).to_owned()));
+        }
+    }
+    if !rhos.is_empty() {
+        let views: Vec<_> = rhos.iter().map(|(_, rho)| rho.view()).collect();
+        let left = u.dot(&stack(Axis(1), &views)?);
+        let blocks: Vec<_> = (0..rhos.len()).map(|b| left.slice(s![.., b * dim..(b + 1) * dim])).collect();
+        let right = stack(Axis(0), &blocks)?.dot(&kernels_cpu::dagger(u));
+        for (b, (k, _)) in rhos.iter().enumerate() {
+            evolved[*k] = Some(EvolutionState::Rho(right.slice(s![b * dim..(b + 1) * dim, ..]).to_owned()));
+        }
+    }
+    Ok(evolved.into_iter().flatten().collect())
+}
+
+fn hamiltonian_id(evolution: &IrEvolution) -> NodeId {
+    match &evolution.method {
+        EvolutionMethod::Schrodinger { hamiltonian } => *hamiltonian,
+        EvolutionMethod::Lindblad { hamiltonian, .. } => *hamiltonian,
+    }
+}
+
+/// Trace drift relative to ρ(0) and smallest eigenvalue along a trajectory
+///
+/// Only the first crossing of each threshold produces a warning, so a
+/// slowly drifting run reports when it went wrong rather than every step.
+pub fn diagnose_states(
+    times: &[f64],
+    states: &[Array2<Complex64>],
+    thresholds: &DiagnosticThresholds,
+) -> Result<DiagnosticsSummary> {
+    use ndarray_linalg::{EigValsh, UPLO};
+
+    let initial_trace = match states.first() {
+        Some(rho) => kernels_cpu::trace(rho)?.re,
+        None => 0.0,
+    };
+
+    let mut summary = DiagnosticsSummary {
+        max_trace_drift: 0.0,
+        min_eigenvalue: f64::INFINITY,
+        warnings: Vec::new(),
+    };
+    let mut trace_warned = false;
+    let mut positivity_warned = false;
+
+    for (step, rho) in states.iter().enumerate() {
+        let t = times.get(step).copied().unwrap_or(f64::NAN);
+
+        let drift = (kernels_cpu::trace(rho)?.re - initial_trace).abs();
+        summary.max_trace_drift = summary.max_trace_drift.max(drift);
+        if drift > thresholds.max_trace_drift && !trace_warned {
+            trace_warned = true;
+            summary.warnings.push(format!(
+                "trace drifted by {:.3e} at t = {} (tolerance {:.1e})",
+                drift, t, thresholds.max_trace_drift
+            ));
+        }
+
+        // Eigenvalues of the Hermitian part; ρ is Hermitian up to round-off
+        let hermitian = (rho + &kernels_cpu::dagger(rho)).mapv(|x| x * 0.5);
+        let eigenvalues = hermitian
+            .eigvalsh(UPLO::Lower)
+            .map_err(|e| EngineError::ExecutionError(format!("Eigenvalue computation failed: {}", e)))?;
+        let min_eig = eigenvalues.iter().cloned().fold(f64::INFINITY, f64::min);
+        summary.min_eigenvalue = summary.min_eigenvalue.min(min_eig);
+        if min_eig < thresholds.min_eigenvalue && !positivity_warned {
+            positivity_warned = true;
+            summary.warnings.push(format!(
+                "negative eigenvalue {:.3e} at t = {} (tolerance {:.1e})",
+                min_eig, t, thresholds.min_eigenvalue
+            ));
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Ehrenfest residuals `|d⟨A⟩/dt - ⟨G(A)⟩|` along a trajectory, where
+/// `G(A) = i[H, A] + Σ γ (L†AL - ½{L†L, A})` is the Heisenberg-picture
+/// generator for Lindblad operators `(L, γ)`
+///
+/// d⟨A⟩/dt is a second-order finite difference over the given states, so
+/// the residual includes the truncation error of the time grid as well as
+/// the integrator's; it is evaluated at interior time points only.
+pub fn ehrenfest_residuals(
+    times: &[f64],
+    states: &[Array2<Complex64>],
+    h: &Array2<Complex64>,
+    lindblad: &[(Array2<Complex64>, f64)],
+    observables: &[(String, Array2<Complex64>)],
+) -> Result<Vec<EhrenfestResidual>> {
+    if times.len() != states.len() {
+        return Err(EngineError::dimension_mismatch(
+            format!("{} states", times.len()),
+            format!("{} states", states.len()),
+        ));
+    }
+
+    let i = Complex64::new(0.0, 1.0);
+    let expectation = |a: &Array2<Complex64>, rho: &Array2<Complex64>| -> Result<f64> {
+        Ok(kernels_cpu::trace(&a.dot(rho))?.re)
+    };
+
+    let mut residuals = Vec::with_capacity(observables.len());
+    for (name, a) in observables {
+        if a.dim() != h.dim() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{:?}", h.dim()),
+                format!("{:?} for observable {}", a.dim(), name),
+            ));
+        }
+        let mut generator = kernels_cpu::commutator(h, a)?.mapv(|x| i * x);
+        for (l, rate) in lindblad {
+            let l_dag = kernels_cpu::dagger(l);
+            let l_dag_l = l_dag.dot(l);
+            let anticommutator = l_dag_l.dot(a) + a.dot(&l_dag_l);
+            generator = generator + (l_dag.dot(a).dot(l) - anticommutator.mapv(|x| x * 0.5)).mapv(|x| x * *rate);
+        }
+
+        let values = states.iter().map(|rho| expectation(a, rho)).collect::<Result<Vec<_>>>()?;
+        let mut worst = EhrenfestResidual {
+            observable: name.clone(),
+            max_residual: 0.0,
+            time: times.first().copied().unwrap_or(0.0),
+        };
+        for k in 1..states.len().saturating_sub(1) {
+            let (before, after) = (times[k] - times[k - 1], times[k + 1] - times[k]);
+            let derivative = (before * before * values[k + 1] - after * after * values[k - 1]
+                + (after * after - before * before) * values[k])
+                / (before * after * (before + after));
+            let residual = (derivative - expectation(&generator, &states[k])?).abs();
+            if residual > worst.max_residual {
+                worst.max_residual = residual;
+                worst.time = times[k];
+            }
+        }
+        residuals.push(worst);
+    }
+    Ok(residuals)
+}
+
+fn is_hermitian(a: &Array2<Complex64>) -> bool {
+    a.is_square()
+        && a.iter()
+            .zip(kernels_cpu::dagger(a).iter())
+            .all(|(x, y)| (x - y).norm() < 1e-12)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::NoiseTerm;
+    use crate::ode::evolve_unitary_rho;
+
+    /// H = (ω/2) σx acting on |0⟩, plus a named σz/2 detuning operator
+    fn rabi_program(omega: f64, t_final: f64) -> IrProgram {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let mut ir = IrProgram::new();
+        ir.add_node(IrNode::LoadMatrix {
+            id: 0,
+            name: "H".to_string(),
+            data: vec![c(0.0), c(omega / 2.0), c(omega / 2.0), c(0.0)],
+            shape: (2, 2),
+        });
+        ir.hamiltonians.push(("H".to_string(), 0));
+        ir.add_node(IrNode::LoadMatrix {
+            id: 1,
+            name: "Sz".to_string(),
+            data: vec![c(0.5), c(0.0), c(0.0), c(-0.5)],
+            shape: (2, 2),
+        });
+        ir.add_node(IrNode::LoadVector {
+            id: 2,
+            name: "init_ket".to_string(),
+            data: vec![c(1.0), c(0.0)],
+        });
+        ir.experiments.push(IrExperiment {
+            name: "rabi".to_string(),
+            initial_state: 2,
+            evolution: Some(IrEvolution {
+                method: EvolutionMethod::Schrodinger { hamiltonian: 0 },
+                times: vec![0.0, t_final],
+            }),
+            measurements: Vec::new(),
+            initial_from: None,
+            channels: Vec::new(),
+            observables: Vec::new(),
+        });
+        ir
+    }
+
+    #[test]
+    fn test_tracked_observables() {
+        let mut ir = rabi_program(1.0, 1.0);
+        ir.experiments[0].observables.push(IrObservable {
+            label: "Sz".to_string(),
+            operator: 1,
+        });
+        let result = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+
+        // ⟨σz⟩ = cos(ω t) under H = (ω/2) σx
+        let series = &result.experiment_results[0].observables[0];
+        assert_eq!(series.label, "Sz");
+        assert_eq!(series.values.len(), 2);
+        assert!((series.values[0].re - 0.5).abs() < 1e-12);
+        assert!((series.values[1].re - 0.5 * 1.0f64.cos()).abs() < 1e-8);
+        assert!(series.values[1].im.abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_progress_counts_time_steps() {
+        let mut ir = rabi_program(1.0, 1.0);
+        ir.experiments[0].evolution.as_mut().unwrap().times = vec![0.0, 0.25, 0.5, 0.75, 1.0];
+        let reporter = ProgressReporter::default();
+        let mut executor = Executor::new(BackendConfig::default()).with_progress(reporter.clone());
+
+        executor.execute(&ir).unwrap();
+        assert_eq!(reporter.fraction(), 1.0);
+
+        // A Lindblad evolution steps through the integrator instead
+        reporter.report(0.0);
+        ir.experiments[0].evolution.as_mut().unwrap().method = EvolutionMethod::Lindblad {
+            hamiltonian: 0,
+            operators: Vec::new(),
+        };
+        executor.execute(&ir).unwrap();
+        assert_eq!(reporter.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_measurement_between_grid_points_is_interpolated() {
+        let mut ir = rabi_program(1.0, 2.0);
+        let evolution = ir.experiments[0].evolution.as_mut().unwrap();
+        evolution.times = (0..=40).map(|k| k as f64 * 0.05).collect();
+        ir.experiments[0].measurements.push(IrMeasurement {
+            time_index: 10,
+            measurement_type: MeasurementType::Observable { operator: 1 },
+            time: Some(0.512),
+        });
+        let result = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+        let experiment = &result.experiment_results[0];
+
+        let measurement = &experiment.measurements[0];
+        assert_eq!(measurement.time, 0.512);
+        assert!((measurement.probabilities[0] - 0.5 * 0.512f64.cos()).abs() < 1e-4);
+
+        let rho = experiment.state_at(1.234).unwrap();
+        assert!((rho[[0, 0]].re - (0.5 + 0.5 * 1.234f64.cos())).abs() < 1e-4);
+        assert!((rho[[0, 0]] + rho[[1, 1]] - Complex64::new(1.0, 0.0)).norm() < 1e-10);
+        assert!(experiment.state_at(2.5).is_err());
+    }
+
+    #[test]
+    fn test_execute_batch_substitutes_constants() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let mut ir = IrProgram::new();
+        ir.add_node(IrNode::Scalar { id: 0, value: c(1.0) });
+        ir.add_node(IrNode::LoadMatrix {
+            id: 1,
+            name: "Sx".to_string(),
+            data: vec![c(0.0), c(0.5), c(0.5), c(0.0)],
+            shape: (2, 2),
+        });
+        ir.add_node(IrNode::MatrixMul { id: 2, left: 0, right: 1 });
+        ir.add_node(IrNode::LoadVector {
+            id: 3,
+            name: "init_ket".to_string(),
+            data: vec![c(1.0), c(0.0)],
+        });
+        ir.constants.push(("omega".to_string(), 0));
+        ir.experiments.push(IrExperiment {
+            name: "rabi".to_string(),
+            initial_state: 3,
+            evolution: Some(IrEvolution {
+                method: EvolutionMethod::Schrodinger { hamiltonian: 2 },
+                times: vec![0.0, 1.0],
+            }),
+            measurements: Vec::new(),
+            initial_from: None,
+            channels: Vec::new(),
+            observables: Vec::new(),
+        });
+
+        let point = |name: &str, omega: f64| HashMap::from([(name.to_string(), omega)]);
+        let param_sets = [
+            point("omega", std::f64::consts::PI),
+            point("omega", std::f64::consts::PI / 2.0),
+            point("gamma", 1.0),
+        ];
+        let results = Executor::new(BackendConfig::default()).execute_batch(&ir, &param_sets);
+        assert_eq!(results.len(), 3);
+
+        // P(|1⟩) = sin²(ω t / 2)
+        for (result, expected) in results.iter().zip([1.0, 0.5]) {
+            let rho = result.as_ref().unwrap().experiment_results[0].states.last().unwrap().clone();
+            assert!((rho[[1, 1]].re - expected).abs() < 1e-8, "{} vs {}", rho[[1, 1]].re, expected);
+        }
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_propagators_are_reused_across_steps_and_points() {
+        let mut ir = rabi_program(1.0, 1.0);
+        ir.experiments[0].evolution.as_mut().unwrap().times = (0..=20).map(|k| k as f64 * 0.05).collect();
+        // One thread, so that the points cannot both compute the propagator
+        let executor = Executor::new(BackendConfig {
+            num_threads: Some(1),
+            ..BackendConfig::default()
+        });
+        let results = executor.execute_batch(&ir, &[HashMap::new(), HashMap::new()]);
+        for result in &results {
+            let rho = result.as_ref().unwrap().experiment_results[0].states.last().unwrap().clone();
+            assert!((rho[[1, 1]].re - 0.5f64.sin().powi(2)).abs() < 1e-8);
+        }
+        assert_eq!(
+            executor.propagator_cache_stats(),
+            PropagatorCacheStats {
+                hits: 39,
+                misses: 1,
+                entries: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_executor_creation() {
+        let config = BackendConfig::default();
+        let executor = Executor::new(config);
+        assert!(executor.matrix_cache.is_empty());
+    }
+
+    #[test]
+    fn test_custom_kernel_node() {
+        struct Double;
+
+        impl crate::plugin::Kernel for Double {
+            fn name(&self) -> &str {
+                "double"
+            }
+
+            fn execute(&self, args: &[KernelValue]) -> Result<KernelValue> {
+                Ok(KernelValue::Matrix(args[0].as_matrix()? * Complex64::new(2.0, 0.0)))
+            }
+        }
+
+        let mut registry = KernelRegistry::new();
+        registry.register(Double).unwrap();
+
+        let mut ir = rabi_program(1.0, 1.0);
+        ir.add_node(IrNode::Dagger { id: 3, input: 0 });
+        ir.add_node(IrNode::Custom {
+            id: 4,
+            kernel: "double".to_string(),
+            inputs: vec![3],
+        });
+
+        let mut executor = Executor::with_kernels(BackendConfig::default(), Arc::new(registry));
+        executor.execute(&ir).unwrap();
+
+        assert_eq!(executor.get_matrix(4).unwrap()[[0, 1]], Complex64::new(1.0, 0.0));
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_partial_trace_builtin() {
+        let source = r#"
+            matrix X = [0, 1; 1, 0];
+            matrix I = [1, 0; 0, 1];
+            matrix D = [1, 0, 0; 0, 2, 0; 0, 0, 3];
+            Hamiltonian HA = ptrace(tensor(X, I), 0);
+            Hamiltonian HB = ptrace(tensor(I, D), 1, vec(2, 3));
+        "#;
+        let ast = crate::parser::parse_dsl(source).unwrap();
+        let typed = crate::typechecker::TypeChecker::new().check(&ast).unwrap();
+        let validated = crate::validator::QuantumValidator::new()
+            .validate(&typed)
+            .unwrap();
+        let ir = crate::lowering::Lowerer::new().lower(&validated).unwrap();
+        assert!(ir
+            .nodes
+            .iter()
+            .any(|node| matches!(node, IrNode::PartialTrace { .. })));
+
+        let hamiltonians = Executor::new(BackendConfig::default())
+            .hamiltonians(&ir)
+            .unwrap();
+        let c = |re: f64| Complex64::new(re, 0.0);
+        // Tr_B(X ⊗ I) = 2X and Tr_A(I ⊗ D) = 2D
+        assert_eq!(
+            hamiltonians[0].1,
+            Array2::from_shape_vec((2, 2), vec![c(0.0), c(2.0), c(2.0), c(0.0)]).unwrap()
+        );
+        assert_eq!(
+            hamiltonians[1].1,
+            Array2::from_diag(&Array1::from_vec(vec![c(2.0), c(4.0), c(6.0)]))
+        );
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_local_operators_on_composite_spaces() {
+        let source = r#"
+            matrix D = [1, 0, 0; 0, 2, 0; 0, 0, 3];
+            Hamiltonian H = sigma_z on subsystem 0 of vec(2, 3) + D on subsystem 1 of vec(2, 3);
+            Hamiltonian HB = H[1];
+            Hamiltonian Z2 = sigma_z on qubit 1 of 2;
+        "#;
+        let ast = crate::parser::parse_dsl(source).unwrap();
+        let typed = crate::typechecker::TypeChecker::new().check(&ast).unwrap();
+        assert_eq!(typed.subsystems["H"], vec![2, 3]);
+        let validated = crate::validator::QuantumValidator::new()
+            .validate(&typed)
+            .unwrap();
+        let ir = crate::lowering::Lowerer::new().lower(&validated).unwrap();
+
+        let hamiltonians = Executor::new(BackendConfig::default())
+            .hamiltonians(&ir)
+            .unwrap();
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let diag = |entries: &[f64]| Array2::from_diag(&Array1::from_iter(entries.iter().map(|&x| c(x))));
+        // Z ⊗ I + I ⊗ D, and Tr_A of it, split as the 2×3 space H is built on
+        assert_eq!(hamiltonians[0].1, diag(&[2.0, 3.0, 4.0, 0.0, 1.0, 2.0]));
+        assert_eq!(hamiltonians[1].1, diag(&[2.0, 4.0, 6.0]));
+        assert_eq!(hamiltonians[2].1, diag(&[1.0, -1.0, 1.0, -1.0]));
+
+        // A local operator must fit the subsystem it is placed on
+        let ast = crate::parser::parse_dsl("matrix D = [1, 0, 0; 0, 2, 0; 0, 0, 3];\nHamiltonian H = D on qubit 0 of 2;").unwrap();
+        let err = crate::typechecker::TypeChecker::new().check(&ast).unwrap_err().to_string();
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_named_basis_measurements() {
+        let source = r#"
+            measure bell: Bell;
+            measure z: computational(4);
+            experiment prep {
+                init: ket(vec(0, 1, 0, 0));
+                measurements: [(0.0, bell), (0.0, z)];
+            }
+        "#;
+        let ast = crate::parser::parse_dsl(source).unwrap();
+        let typed = crate::typechecker::TypeChecker::new().check(&ast).unwrap();
+        let validated = crate::validator::QuantumValidator::new()
+            .validate(&typed)
+            .unwrap();
+        let ir = crate::lowering::Lowerer::new().lower(&validated).unwrap();
+        let result = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+
+        // |01⟩ = (|Ψ+⟩ + |Ψ-⟩)/√2
+        let measurements = &result.experiment_results[0].measurements;
+        let expected = [vec![0.0, 0.0, 0.5, 0.5], vec![0.0, 1.0, 0.0, 0.0]];
+        for (measurement, expected) in measurements.iter().zip(&expected) {
+            for (p, q) in measurement.probabilities.iter().zip(expected) {
+                assert!((p - q).abs() < 1e-12);
+            }
+        }
+        assert_eq!(measurements.len(), 2);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_gates_prepare_the_initial_state() {
+        let run = |source: &str, params: &[(&str, f64)]| -> Result<Vec<Vec<f64>>> {
+            let ast = crate::parser::parse_dsl(source)?;
+            let typed = crate::typechecker::TypeChecker::new().check(&ast)?;
+            let validated = crate::validator::QuantumValidator::new().validate(&typed)?;
+            let ir = crate::lowering::Lowerer::new().lower(&validated)?;
+            let params = params.iter().map(|&(name, value)| (name.to_string(), value)).collect();
+            let result = Executor::new(BackendConfig::default()).execute_with_params(&ir, &params)?;
+            Ok(result.experiment_results[0]
+                .measurements
+                .iter()
+                .map(|m| m.probabilities.clone())
+                .collect())
+        };
+        let close = |p: &[f64], q: &[f64]| p.iter().zip(q).all(|(p, q)| (p - q).abs() < 1e-12);
+
+        // H then CNOT takes |00⟩ to |Φ+⟩, even with a Hamiltonian named H
+        let bell = run(
+            r#"
+            Hamiltonian H = sigma_z;
+            measure bell: Bell;
+            measure z: computational(4);
+            experiment prep {
+                init: ket(vec(1, 0, 0, 0));
+                apply H to psi[0];
+                apply CNOT to psi[0, 1];
+                measurements: [(0.0, bell), (0.0, z)];
+            }
+            "#,
+            &[],
+        )
+        .unwrap();
+        assert!(close(&bell[0], &[1.0, 0.0, 0.0, 0.0]), "{:?}", bell);
+        assert!(close(&bell[1], &[0.5, 0.0, 0.0, 0.5]), "{:?}", bell);
+
+        // Rotations by a parameter, on a density matrix, and a declared
+        // gate on the whole register
+        let rotated = r#"
+            param theta;
+            matrix flip = [0, 1; 1, 0];
+            measure z: computational(2);
+            experiment rotate {
+                init: rho([1, 0; 0, 0]);
+                apply RX(theta) to rho;
+                apply flip to rho;
+                measurements: [(0.0, z)];
+            }
+        "#;
+        let theta = 0.7f64;
+        let z = run(rotated, &[("theta", theta)]).unwrap();
+        let p0 = (theta / 2.0).cos().powi(2);
+        assert!(close(&z[0], &[1.0 - p0, p0]), "{:?}", z);
+
+        // Declared gates must be unitary, and fit their targets
+        let leaky = rotated.replace("[0, 1; 1, 0]", "[1, 0; 0, 0]");
+        let err = run(&leaky, &[("theta", theta)]).unwrap_err().to_string();
+        assert!(err.contains("not unitary"), "{}", err);
+        let misplaced = rotated.replace("apply flip to rho;", "apply CNOT to rho[0, 1];");
+        assert!(run(&misplaced, &[("theta", theta)]).is_err());
+    }
+
+    #[test]
+    fn test_chunked_execution_matches_and_resumes() {
+        let mut ir = rabi_program(1.0, 0.0);
+        let times: Vec<f64> = (0..=10).map(|i| i as f64 * 0.2).collect();
+        ir.experiments[0].evolution.as_mut().unwrap().times = times.clone();
+        let full = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+
+        // Cancel after the second chunk, then resume from its checkpoint
+        let mut chunks = Vec::new();
+        let checkpoint = Executor::new(BackendConfig::default())
+            .execute_chunked(&ir, 3, None, |chunk| {
+                chunks.push(chunk);
+                if chunks.len() == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.time_index, 6);
+        let finished = Executor::new(BackendConfig::default())
+            .execute_chunked(&ir, 3, Some(&checkpoint), |chunk| {
+                chunks.push(chunk);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert!(finished.is_none());
+
+        let starts: Vec<usize> = chunks.iter().map(|c| c.start_index).collect();
+        assert_eq!(starts, vec![0, 4, 7, 10]);
+        let states: Vec<_> = chunks.iter().flat_map(|c| c.states.clone()).collect();
+        assert_eq!(states.len(), times.len());
+        for (a, b) in states.iter().zip(&full.experiment_results[0].states) {
+            assert!((a - b).iter().all(|d| d.norm() < 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_zero_noise_ensemble_matches_noiseless() {
+        let ir = rabi_program(1.0, 1.3);
+        let noise = NoiseModel::new(4).with_term(NoiseTerm::gaussian("Sz", 0.0)).with_seed(3);
+
+        let clean = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+        let ensemble = Executor::new(BackendConfig::default()).execute_ensemble(&ir, &noise).unwrap();
+
+        let a = &clean.experiment_results[0].states[1];
+        let b = &ensemble.experiment_results[0].states[1];
+        assert!((a - b).iter().all(|d| d.norm() < 1e-10));
+    }
+
+    #[test]
+    fn test_amplitude_noise_damps_rabi_contrast() {
+        // A π pulse: without noise the population is fully transferred
+        let ir = rabi_program(std::f64::consts::PI, 1.0);
+        let noise = NoiseModel::new(200).with_term(NoiseTerm::amplitude(0.3)).with_seed(11);
+        let config = BackendConfig {
+            num_threads: Some(4),
+            ..BackendConfig::default()
+        };
+
+        let result = Executor::new(config).execute_ensemble(&ir, &noise).unwrap();
+        let rho = &result.experiment_results[0].states[1];
+
+        let trace: Complex64 = rho.diag().sum();
+        assert!((trace.re - 1.0).abs() < 1e-8);
+        // Excited population is reduced below 1 by the amplitude spread
+        assert!(rho[[1, 1]].re < 0.95);
+        assert!(rho[[1, 1]].re > 0.5);
+    }
+
+    #[test]
+    fn test_state_ensemble_member_matches_execute() {
+        let mut ir = rabi_program(1.0, 1.0);
+        if let Some(evolution) = &mut ir.experiments[0].evolution {
+            evolution.times = vec![0.0, 0.25, 0.5, 0.9, 1.3];
+        }
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let ensemble = StateEnsemble::new()
+            .with_ket("0", Array1::from_vec(vec![c(1.0, 0.0), c(0.0, 0.0)]), 1.0)
+            .with_rho(
+                "+i",
+                Array2::from_shape_vec((2, 2), vec![c(0.5, 0.0), c(0.0, -0.5), c(0.0, 0.5), c(0.5, 0.0)]).unwrap(),
+                1.0,
+            );
+
+        let clean = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+        let result = Executor::new(BackendConfig::default()).execute_state_ensemble(&ir, "rabi", &ensemble).unwrap();
+
+        assert_eq!(result.members.len(), 2);
+        assert!(matches!(result.average.state_type, StateType::DensityMatrix));
+        for (a, b) in clean.experiment_results[0].states.iter().zip(&result.members[0].states) {
+            assert!((a - b).iter().all(|d| d.norm() < 1e-10));
+        }
+
+        // The ρ member against the reference ρ evolution
+        let h = Executor::new(BackendConfig::default()).hamiltonians(&ir).unwrap().remove(0).1;
+        let times = [0.0, 0.25, 0.5, 0.9, 1.3];
+        let reference = evolve_unitary_rho(&h, &ensemble.members[1].state.to_rho(), &times).unwrap();
+        for (a, b) in reference.iter().zip(&result.members[1].states) {
+            assert!((a - b).iter().all(|d| d.norm() < 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_state_ensemble_average_is_mixed_evolution() {
+        let ir = rabi_program(1.0, 0.7);
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let ensemble = StateEnsemble::new()
+            .with_ket("0", Array1::from_vec(vec![c(1.0), c(0.0)]), 1.0)
+            .with_ket("1", Array1::from_vec(vec![c(0.0), c(1.0)]), 3.0);
+
+        let result = Executor::new(BackendConfig::default()).execute_state_ensemble(&ir, "rabi", &ensemble).unwrap();
+        assert!((result.members[1].weight - 0.75).abs() < 1e-15);
+
+        let h = Executor::new(BackendConfig::default()).hamiltonians(&ir).unwrap().remove(0).1;
+        let mixed = Array2::from_shape_vec((2, 2), vec![c(0.25), c(0.0), c(0.0), c(0.75)]).unwrap();
+        let reference = evolve_unitary_rho(&h, &mixed, &[0.0, 0.7]).unwrap();
+        assert!((&reference[1] - &result.average.states[1]).iter().all(|d| d.norm() < 1e-10));
+
+        assert!(Executor::new(BackendConfig::default()).execute_state_ensemble(&ir, "missing", &ensemble).is_err());
+    }
+
+    #[test]
+    fn test_diagnostics_clean_trajectory() {
+        let config = BackendConfig {
+            diagnostics: Some(DiagnosticThresholds::default()),
+            ..BackendConfig::default()
+        };
+
+        let result = Executor::new(config).execute(&rabi_program(1.0, 2.0)).unwrap();
+        let diagnostics = result.experiment_results[0].diagnostics.as_ref().unwrap();
+
+        assert!(diagnostics.max_trace_drift < 1e-10);
+        assert!(diagnostics.min_eigenvalue > -1e-10);
+        assert!(diagnostics.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_checkpointed_execution_resumes_from_file() {
+        let mut ir = rabi_program(1.0, 0.0);
+        ir.experiments[0].evolution.as_mut().unwrap().times = (0..=10).map(|i| i as f64 * 0.2).collect();
+        let full = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+
+        let path = std::env::temp_dir().join(format!("qte_checkpoint_{}.json", std::process::id()));
+        let config = BackendConfig {
+            checkpoint: Some(CheckpointConfig {
+                path: path.clone(),
+                every_steps: 4,
+            }),
+            ..BackendConfig::default()
+        };
+        let checkpointed = Executor::new(config.clone()).execute(&ir).unwrap();
+        assert_eq!(checkpointed.experiment_results[0].states.len(), 11);
+        assert_eq!(EvolutionCheckpoint::load(&path).unwrap().time_index, 10);
+
+        // Resume from the state saved mid-run, as after an interruption
+        let mid = Executor::new(BackendConfig::default())
+            .execute_chunked(&ir, 4, None, |_| ControlFlow::Break(()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(mid.time_index, 4);
+        mid.save(&path).unwrap();
+        let checkpoint = EvolutionCheckpoint::load(&path).unwrap();
+        let resumed = Executor::new(config).resume_from(&ir, &checkpoint).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let resumed = &resumed.experiment_results[0];
+        assert_eq!(resumed.times, full.experiment_results[0].times[5..].to_vec());
+        for (a, b) in resumed.states.iter().zip(&full.experiment_results[0].states[5..]) {
+            assert!((a - b).iter().all(|d| d.norm() < 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_gpu_backend_falls_back_for_small_systems() {
+        let config = BackendConfig {
+            backend_type: BackendType::Gpu,
+            ..BackendConfig::default()
+        };
+        let gpu = Executor::new(config).execute(&rabi_program(1.0, 2.0)).unwrap();
+        let cpu = Executor::new(BackendConfig::default()).execute(&rabi_program(1.0, 2.0)).unwrap();
+
+        let (gpu, cpu) = (&gpu.experiment_results[0].states, &cpu.experiment_results[0].states);
+        assert_eq!(gpu.len(), cpu.len());
+        assert!(gpu.iter().zip(cpu).all(|(a, b)| (a - b).iter().all(|d| d.norm() < 1e-12)));
+    }
+
+    #[test]
+    fn test_experiment_continues_from_final_state() {
+        // Two Rabi pulses of duration 1 compose to one of duration 2
+        let mut ir = rabi_program(1.0, 1.0);
+        let mut probe = ir.experiments[0].clone();
+        probe.name = "probe".to_string();
+        probe.initial_state = 3;
+        probe.initial_from = Some("rabi".to_string());
+        ir.experiments.push(probe);
+
+        let chained = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+        let direct = Executor::new(BackendConfig::default()).execute(&rabi_program(1.0, 2.0)).unwrap();
+        let (chained, direct) = (&chained.experiment_results[1], &direct.experiment_results[0]);
+        assert!(matches!(chained.state_type, StateType::PureState));
+        assert!((&chained.states[1] - &direct.states[1]).iter().all(|d| d.norm() < 1e-10));
+
+        // Chunked execution hands the state over the same way
+        let mut last = None;
+        Executor::new(BackendConfig::default())
+            .execute_chunked(&ir, 1, None, |chunk| {
+                last = chunk.states.last().cloned();
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert!((last.unwrap() - &direct.states[1]).iter().all(|d| d.norm() < 1e-10));
+    }
+
+    #[test]
+    fn test_channel_applied_at_scheduled_time() {
+        // Full dephasing halfway through a π pulse leaves I/2, which the
+        // rest of the pulse cannot change
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        let mut ir = rabi_program(std::f64::consts::PI, 1.0);
+        ir.add_node(IrNode::LoadMatrix {
+            id: 3,
+            name: "K0".to_string(),
+            data: vec![c(s), c(0.0), c(0.0), c(s)],
+            shape: (2, 2),
+        });
+        ir.add_node(IrNode::LoadMatrix {
+            id: 4,
+            name: "K1".to_string(),
+            data: vec![c(s), c(0.0), c(0.0), c(-s)],
+            shape: (2, 2),
+        });
+        ir.add_node(IrNode::ApplyChannel { id: 5, kraus: vec![3, 4] });
+        let experiment = &mut ir.experiments[0];
+        experiment.evolution.as_mut().unwrap().times = vec![0.0, 0.5, 1.0];
+        experiment.channels.push(IrChannelApplication {
+            time_index: 1,
+            channel: 5,
+        });
+
+        let result = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+        let result = &result.experiment_results[0];
+        let mixed = Array2::from_diag(&Array1::from_elem(2, c(0.5)));
+        assert!(matches!(result.state_type, StateType::DensityMatrix));
+        assert_eq!(result.states.len(), 3);
+        assert!((result.states[0][[0, 0]] - c(1.0)).norm() < 1e-12);
+        for rho in &result.states[1..] {
+            assert!((rho - &mixed).iter().all(|d| d.norm() < 1e-10));
+        }
+
+        // Chunks stop at the channel, whatever their length
+        let mut chunked = Vec::new();
+        Executor::new(BackendConfig::default())
+            .execute_chunked(&ir, 2, None, |chunk| {
+                chunked.extend(chunk.states);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(chunked.len(), 3);
+        assert!((&chunked[2] - &mixed).iter().all(|d| d.norm() < 1e-10));
+    }
+
+    #[test]
+    fn test_single_precision_sampled_against_double() {
+        let mut ir = rabi_program(1.0, 2.0);
+        let times: Vec<f64> = (0..=40).map(|k| k as f64 * 0.05).collect();
+        ir.experiments[0].evolution = Some(IrEvolution {
+            method: EvolutionMethod::Lindblad { hamiltonian: 0, operators: Vec::new() },
+            times,
+        });
+        let check = PrecisionCheck::new(2, 1e-5);
+        let config = BackendConfig {
+            precision: Precision::Single,
+            precision_check: Some(check.clone()),
+            ..BackendConfig::default()
+        };
+
+        let single = Executor::new(config.clone()).execute(&ir).unwrap();
+        let double = Executor::new(BackendConfig::default()).execute(&ir).unwrap();
+        let (single, double) = (&single.experiment_results[0].states, &double.experiment_results[0].states);
+        let deviation = check.verify(single, double, &ir.experiments[0].evolution.as_ref().unwrap().times).unwrap();
+        assert!(deviation > 0.0);
+
+        // Nothing single precision can reach passes a zero tolerance
+        let strict = BackendConfig {
+            precision_check: Some(PrecisionCheck::new(1, 0.0)),
+            ..config
+        };
+        assert!(matches!(Executor::new(strict).execute(&ir), Err(EngineError::ExecutionError(_))));
+    }
+
+    #[test]
+    fn test_diagnostics_flag_unphysical_states() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let good = Array2::from_diag(&Array1::from_vec(vec![c(1.0), c(0.0)]));
+        let leaky = Array2::from_diag(&Array1::from_vec(vec![c(0.9), c(0.0)]));
+        let negative = Array2::from_diag(&Array1::from_vec(vec![c(1.1), c(-0.1)]));
+
+        let summary = diagnose_states(
+            &[0.0, 1.0, 2.0, 3.0],
+            &[good, leaky.clone(), leaky, negative],
+            &DiagnosticThresholds::default(),
+        )
+        .unwrap();
+
+        assert!((summary.max_trace_drift - 0.1).abs() < 1e-12);
+        assert!((summary.min_eigenvalue + 0.1).abs() < 1e-12);
+        // One warning per threshold, at its first crossing
+        assert_eq!(summary.warnings.len(), 2);
+        assert!(summary.warnings[0].contains("t = 1"));
+    }
+
+    #[test]
+    fn test_ehrenfest_check_on_rabi_trajectory() {
+        let mut ir = rabi_program(1.0, 2.0);
+        let evolution = ir.experiments[0].evolution.as_mut().unwrap();
+        evolution.times = (0..=200).map(|k| k as f64 * 0.01).collect();
+        let config = BackendConfig {
+            ehrenfest: Some(EhrenfestCheck::default()),
+            ..BackendConfig::default()
+        };
+
+        let result = Executor::new(config).execute(&ir).unwrap();
+        let residuals = &result.experiment_results[0].ehrenfest;
+        let names: Vec<&str> = residuals.iter().map(|r| r.observable.as_str()).collect();
+        assert_eq!(names, vec!["H", "Sz"]);
+        assert!(residuals.iter().all(|r| r.max_residual < 1e-4));
+    }
+
+    #[test]
+    fn test_ehrenfest_residuals_include_dissipator() {
+        // Amplitude damping at rate 0.5: excited population e^{-t/2}
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let times: Vec<f64> = (0..=100).map(|k| k as f64 * 0.02).collect();
+        let states: Vec<Array2<Complex64>> = times
+            .iter()
+            .map(|t| {
+                let p = (-0.5 * t).exp();
+                Array2::from_diag(&Array1::from_vec(vec![c(1.0 - p), c(p)]))
+            })
+            .collect();
+        let h = Array2::zeros((2, 2));
+        let lowering = Array2::from_shape_vec((2, 2), vec![c(0.0), c(1.0), c(0.0), c(0.0)]).unwrap();
+        let sz = Array2::from_diag(&Array1::from_vec(vec![c(0.5), c(-0.5)]));
+        let observables = vec![("Sz".to_string(), sz)];
+
+        let damped =
+            ehrenfest_residuals(&times, &states, &h, &[(lowering, 0.5)], &observables).unwrap();
+        assert!(damped[0].max_residual < 1e-4);
+
+        // Leaving out the dissipator misses the whole decay
+        let closed = ehrenfest_residuals(&times, &states, &h, &[], &observables).unwrap();
+        assert!(closed[0].max_residual > 0.4);
     }
 }
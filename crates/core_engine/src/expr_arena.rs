@@ -0,0 +1,292 @@
+//! Interned, arena-backed expressions
+//!
+//! An [`ExprArena`] stores expressions as nodes addressed by [`ExprId`], each
+//! node referring to its children by id. Interning is hash-consed:
+//! structurally equal subexpressions get the same id, so a subtree shared by
+//! many expressions is stored once, interned expressions compare equal
+//! exactly when their ids do, and passes can pass expressions around as
+//! `Copy` ids instead of cloning boxed trees.
+//!
+//! [`Expr`] remains the public, serializable form of an expression;
+//! [`ExprArena::intern`] and [`ExprArena::expr`] convert between the two.
+
+use crate::ast::Expr;
+use std::collections::HashMap;
+
+/// Handle to an expression interned in an [`ExprArena`], meaningful only
+/// with the arena that issued it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+/// One node of an interned expression
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExprNode {
+    /// A number, complex number or identifier
+    Leaf(Expr),
+    Matrix(Vec<Vec<ExprId>>),
+    Vector(Vec<ExprId>),
+    Binary(BinaryOp, ExprId, ExprId),
+    Unary(UnaryOp, ExprId),
+    Call {
+        name: String,
+        args: Vec<ExprId>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Tensor,
+    Commutator,
+    AntiCommutator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    Dagger,
+    Trace,
+    Expm,
+    Sqrt,
+    Sin,
+    Cos,
+    Exp,
+}
+
+/// Hash-consed store of expression nodes
+#[derive(Debug, Clone, Default)]
+pub struct ExprArena {
+    nodes: Vec<ExprNode>,
+    /// Tree size of each node, counting shared subtrees at every occurrence
+    sizes: Vec<usize>,
+    ids: HashMap<ExprNode, ExprId>,
+}
+
+impl ExprArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intern `expr` and each of its subexpressions, reusing the nodes of
+    /// any already in the arena
+    pub fn intern(&mut self, expr: &Expr) -> ExprId {
+        let node = match expr {
+            Expr::Number(_) | Expr::ComplexNumber(_) | Expr::Identifier(_) => ExprNode::Leaf(expr.clone()),
+            Expr::Matrix(m) => ExprNode::Matrix(
+                m.rows
+                    .iter()
+                    .map(|row| row.iter().map(|e| self.intern(e)).collect())
+                    .collect(),
+            ),
+            Expr::Vector(v) => ExprNode::Vector(v.elements.iter().map(|e| self.intern(e)).collect()),
+            Expr::FuncCall { name, args } => ExprNode::Call {
+                name: name.clone(),
+                args: args.iter().map(|e| self.intern(e)).collect(),
+            },
+            _ => match binary(expr) {
+                Some((op, a, b)) => {
+                    let a = self.intern(a);
+                    let b = self.intern(b);
+                    ExprNode::Binary(op, a, b)
+                }
+                None => {
+                    let (op, a) = unary(expr).expect("every other expression is unary");
+                    ExprNode::Unary(op, self.intern(a))
+                }
+            },
+        };
+        self.insert(node)
+    }
+
+    /// The id of `node`, adding it if no equal node is interned yet
+    pub fn insert(&mut self, node: ExprNode) -> ExprId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let size = 1 + children(&node).map(|child| self.size(child)).sum::<usize>();
+        let id = ExprId(u32::try_from(self.nodes.len()).expect("expression arena outgrew u32 ids"));
+        self.nodes.push(node.clone());
+        self.sizes.push(size);
+        self.ids.insert(node, id);
+        id
+    }
+
+    /// The id of `expr` if it is interned, without adding it
+    pub fn find(&self, expr: &Expr) -> Option<ExprId> {
+        let node = match expr {
+            Expr::Number(_) | Expr::ComplexNumber(_) | Expr::Identifier(_) => ExprNode::Leaf(expr.clone()),
+            Expr::Matrix(m) => ExprNode::Matrix(
+                m.rows
+                    .iter()
+                    .map(|row| row.iter().map(|e| self.find(e)).collect())
+                    .collect::<Option<_>>()?,
+            ),
+            Expr::Vector(v) => ExprNode::Vector(v.elements.iter().map(|e| self.find(e)).collect::<Option<_>>()?),
+            Expr::FuncCall { name, args } => ExprNode::Call {
+                name: name.clone(),
+                args: args.iter().map(|e| self.find(e)).collect::<Option<_>>()?,
+            },
+            _ => match binary(expr) {
+                Some((op, a, b)) => ExprNode::Binary(op, self.find(a)?, self.find(b)?),
+                None => {
+                    let (op, a) = unary(expr).expect("every other expression is unary");
+                    ExprNode::Unary(op, self.find(a)?)
+                }
+            },
+        };
+        self.ids.get(&node).copied()
+    }
+
+    pub fn node(&self, id: ExprId) -> &ExprNode {
+        &self.nodes[id.0 as usize]
+    }
+
+    /// Number of nodes in the expression tree of `id`, as [`Expr`] would
+    /// store it
+    pub fn size(&self, id: ExprId) -> usize {
+        self.sizes[id.0 as usize]
+    }
+
+    /// Rebuild the boxed expression `id` stands for
+    pub fn expr(&self, id: ExprId) -> Expr {
+        let boxed = |id| Box::new(self.expr(id));
+        match self.node(id) {
+            ExprNode::Leaf(expr) => expr.clone(),
+            ExprNode::Matrix(rows) => Expr::Matrix(crate::ast::MatrixLiteral {
+                rows: rows
+                    .iter()
+                    .map(|row| row.iter().map(|&e| self.expr(e)).collect())
+                    .collect(),
+            }),
+            ExprNode::Vector(elements) => Expr::Vector(crate::ast::VectorLiteral {
+                elements: elements.iter().map(|&e| self.expr(e)).collect(),
+            }),
+            ExprNode::Call { name, args } => Expr::FuncCall {
+                name: name.clone(),
+                args: args.iter().map(|&e| self.expr(e)).collect(),
+            },
+            &ExprNode::Binary(op, a, b) => {
+                let (a, b) = (boxed(a), boxed(b));
+                match op {
+                    BinaryOp::Add => Expr::Add(a, b),
+                    BinaryOp::Sub => Expr::Sub(a, b),
+                    BinaryOp::Mul => Expr::Mul(a, b),
+                    BinaryOp::Div => Expr::Div(a, b),
+                    BinaryOp::Pow => Expr::Pow(a, b),
+                    BinaryOp::Tensor => Expr::Tensor(a, b),
+                    BinaryOp::Commutator => Expr::Commutator(a, b),
+                    BinaryOp::AntiCommutator => Expr::AntiCommutator(a, b),
+                }
+            }
+            &ExprNode::Unary(op, a) => {
+                let a = boxed(a);
+                match op {
+                    UnaryOp::Dagger => Expr::Dagger(a),
+                    UnaryOp::Trace => Expr::Trace(a),
+                    UnaryOp::Expm => Expr::Expm(a),
+                    UnaryOp::Sqrt => Expr::Sqrt(a),
+                    UnaryOp::Sin => Expr::Sin(a),
+                    UnaryOp::Cos => Expr::Cos(a),
+                    UnaryOp::Exp => Expr::Exp(a),
+                }
+            }
+        }
+    }
+
+    /// Number of distinct nodes interned
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+fn children(node: &ExprNode) -> Box<dyn Iterator<Item = ExprId> + '_> {
+    match node {
+        ExprNode::Leaf(_) => Box::new(std::iter::empty()),
+        ExprNode::Matrix(rows) => Box::new(rows.iter().flatten().copied()),
+        ExprNode::Vector(elements) | ExprNode::Call { args: elements, .. } => Box::new(elements.iter().copied()),
+        &ExprNode::Binary(_, a, b) => Box::new([a, b].into_iter()),
+        &ExprNode::Unary(_, a) => Box::new(std::iter::once(a)),
+    }
+}
+
+fn binary(expr: &Expr) -> Option<(BinaryOp, &Expr, &Expr)> {
+    let (op, a, b) = match expr {
+        Expr::Add(a, b) => (BinaryOp::Add, a, b),
+        Expr::Sub(a, b) => (BinaryOp::Sub, a, b),
+        Expr::Mul(a, b) => (BinaryOp::Mul, a, b),
+        Expr::Div(a, b) => (BinaryOp::Div, a, b),
+        Expr::Pow(a, b) => (BinaryOp::Pow, a, b),
+        Expr::Tensor(a, b) => (BinaryOp::Tensor, a, b),
+        Expr::Commutator(a, b) => (BinaryOp::Commutator, a, b),
+        Expr::AntiCommutator(a, b) => (BinaryOp::AntiCommutator, a, b),
+        _ => return None,
+    };
+    Some((op, a, b))
+}
+
+fn unary(expr: &Expr) -> Option<(UnaryOp, &Expr)> {
+    let (op, a) = match expr {
+        Expr::Dagger(a) => (UnaryOp::Dagger, a),
+        Expr::Trace(a) => (UnaryOp::Trace, a),
+        Expr::Expm(a) => (UnaryOp::Expm, a),
+        Expr::Sqrt(a) => (UnaryOp::Sqrt, a),
+        Expr::Sin(a) => (UnaryOp::Sin, a),
+        Expr::Cos(a) => (UnaryOp::Cos, a),
+        Expr::Exp(a) => (UnaryOp::Exp, a),
+        _ => return None,
+    };
+    Some((op, a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Box<Expr> {
+        Box::new(Expr::Identifier(name.to_string()))
+    }
+
+    #[test]
+    fn test_interning_shares_equal_subtrees() {
+        let ab = Expr::Mul(id("A"), id("B"));
+        let expr = Expr::Add(Box::new(ab.clone()), Box::new(Expr::Dagger(Box::new(ab.clone()))));
+
+        let mut arena = ExprArena::new();
+        let root = arena.intern(&expr);
+        // A, B, A B, (A B)†, and the sum
+        assert_eq!(arena.len(), 5);
+        assert_eq!(arena.size(root), 8);
+        assert_eq!(arena.intern(&ab), arena.find(&ab).unwrap());
+        assert_eq!(arena.len(), 5);
+        assert_eq!(arena.find(&Expr::Mul(id("B"), id("A"))), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let expr = Expr::Tensor(
+            Box::new(Expr::Matrix(crate::ast::MatrixLiteral {
+                rows: vec![
+                    vec![Expr::Number(0.0), Expr::Number(1.0)],
+                    vec![Expr::Number(-0.0), Expr::Sqrt(Box::new(Expr::Number(2.0)))],
+                ],
+            })),
+            Box::new(Expr::FuncCall {
+                name: "f".to_string(),
+                args: vec![Expr::Identifier("x".to_string())],
+            }),
+        );
+
+        let mut arena = ExprArena::new();
+        let root = arena.intern(&expr);
+        assert_eq!(arena.expr(root), expr);
+        // -0.0 and 0.0 are one number, as under `Expr` equality
+        assert_eq!(arena.len(), 8);
+    }
+}
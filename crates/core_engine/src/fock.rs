@@ -0,0 +1,146 @@
+//! Operators on a truncated Fock space
+//!
+//! A harmonic oscillator mode is kept to its lowest `n` number states
+//! |0⟩ … |n-1⟩, its cutoff dimension. On them act
+//!
+//! - the annihilation operator a|k⟩ = √k |k-1⟩ and its adjoint a†;
+//! - the number operator n = a†a = diag(0, 1, …, n-1);
+//! - the displacement D(α) = exp(α a† - α* a);
+//! - the squeezing S(ζ) = exp((ζ* a² - ζ a†²) / 2).
+//!
+//! D and S exponentiate the truncated generators, so they are unitary on the
+//! truncated space but agree with the infinite-dimensional operators only on
+//! states well below the cutoff: [a, a†] is 1 except on the top level.
+//!
+//! The DSL declares them with `operator a: Fock<10> = annihilation;`, or
+//! builds them inline with `annihilation(10)`, `displacement(10, 0.5)` and
+//! so on; see [`crate::builtins`].
+
+use crate::error::{EngineError, Result};
+use crate::kernels_cpu;
+use ndarray::Array2;
+use num_complex::Complex64;
+
+/// Annihilation operator a on `n` levels
+pub fn annihilation(n: usize) -> Result<Array2<Complex64>> {
+    check_cutoff(n)?;
+    let mut a = Array2::zeros((n, n));
+    for k in 1..n {
+        a[[k - 1, k]] = Complex64::new((k as f64).sqrt(), 0.0);
+    }
+    Ok(a)
+}
+
+/// Creation operator a† on `n` levels
+pub fn creation(n: usize) -> Result<Array2<Complex64>> {
+    Ok(annihilation(n)?.t().to_owned())
+}
+
+/// Number operator a†a = diag(0, 1, …, n-1)
+pub fn number(n: usize) -> Result<Array2<Complex64>> {
+    check_cutoff(n)?;
+    let mut m = Array2::zeros((n, n));
+    for k in 0..n {
+        m[[k, k]] = Complex64::new(k as f64, 0.0);
+    }
+    Ok(m)
+}
+
+/// Displacement operator D(α) = exp(α a† - α* a) on `n` levels
+pub fn displacement(n: usize, alpha: Complex64) -> Result<Array2<Complex64>> {
+    let a = annihilation(n)?;
+    let generator = a.t().mapv(|z| z * alpha) - a.mapv(|z| z * alpha.conj());
+    exp_anti_hermitian(&generator)
+}
+
+/// Squeezing operator S(ζ) = exp((ζ* a² - ζ a†²) / 2) on `n` levels
+pub fn squeezing(n: usize, zeta: Complex64) -> Result<Array2<Complex64>> {
+    let a = annihilation(n)?;
+    let a2 = a.dot(&a);
+    let generator =
+        (a2.mapv(|z| z * zeta.conj()) - a2.t().mapv(|z| z * zeta)) * Complex64::new(0.5, 0.0);
+    exp_anti_hermitian(&generator)
+}
+
+/// exp(G) for anti-Hermitian G, as exp(-iH) with H = iG Hermitian
+fn exp_anti_hermitian(generator: &Array2<Complex64>) -> Result<Array2<Complex64>> {
+    let decomposition = kernels_cpu::eigendecompose(&generator.mapv(|z| z * Complex64::i()))?;
+    let v = &decomposition.eigenvectors;
+    let phases = decomposition
+        .eigenvalues
+        .mapv(|lambda| Complex64::from_polar(1.0, -lambda));
+    let scaled = v * &phases;
+    Ok(scaled.dot(&kernels_cpu::dagger(v)))
+}
+
+fn check_cutoff(n: usize) -> Result<()> {
+    if n < 2 {
+        return Err(EngineError::validation_error(format!(
+            "Fock space cutoff must be at least 2, got {}",
+            n
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Array2<Complex64>, b: &Array2<Complex64>, tol: f64) -> bool {
+        a.dim() == b.dim() && (a - b).iter().all(|z| z.norm() < tol)
+    }
+
+    fn is_unitary(u: &Array2<Complex64>) -> bool {
+        let n = u.nrows();
+        close(&kernels_cpu::dagger(u).dot(u), &Array2::eye(n), 1e-10)
+    }
+
+    #[test]
+    fn test_ladder_operators() {
+        let n = 5;
+        let (a, a_dag) = (annihilation(n).unwrap(), creation(n).unwrap());
+        assert!(close(&a_dag.dot(&a), &number(n).unwrap(), 1e-12));
+        // [a, a†] = 1 except on the top level, which the truncation cuts off
+        let commutator = a.dot(&a_dag) - a_dag.dot(&a);
+        for k in 0..n {
+            let expected = if k + 1 < n { 1.0 } else { 1.0 - n as f64 };
+            assert!((commutator[[k, k]] - Complex64::new(expected, 0.0)).norm() < 1e-12);
+        }
+        assert!(annihilation(1).is_err());
+    }
+
+    #[test]
+    fn test_displacement_makes_coherent_states() {
+        let (n, alpha) = (30, Complex64::new(0.6, -0.3));
+        let d = displacement(n, alpha).unwrap();
+        assert!(is_unitary(&d));
+        // D(α)|0⟩ = e^{-|α|²/2} Σ αᵏ/√k! |k⟩
+        let mut amplitude = Complex64::new((-alpha.norm_sqr() / 2.0).exp(), 0.0);
+        for k in 0..10 {
+            assert!((d[[k, 0]] - amplitude).norm() < 1e-10);
+            amplitude *= alpha / ((k + 1) as f64).sqrt();
+        }
+        assert!(close(
+            &displacement(n, Complex64::new(0.0, 0.0)).unwrap(),
+            &Array2::eye(n),
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_squeezed_vacuum_photon_number() {
+        let (n, r) = (40, 0.4);
+        let s = squeezing(n, Complex64::new(r, 0.0)).unwrap();
+        assert!(is_unitary(&s));
+        // ⟨n⟩ = sinh²r, with only even number states populated
+        let vacuum = s.column(0);
+        let mean: f64 = vacuum
+            .iter()
+            .enumerate()
+            .map(|(k, z)| k as f64 * z.norm_sqr())
+            .sum();
+        assert!((mean - r.sinh().powi(2)).abs() < 1e-8);
+        assert!(vacuum.iter().skip(1).step_by(2).all(|z| z.norm() < 1e-12));
+    }
+}
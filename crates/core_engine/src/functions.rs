@@ -0,0 +1,266 @@
+//! User-defined functions
+//!
+//! `func rotation(theta) = expm(-i * theta * sigma_x / 2);` defines a
+//! function whose value is whatever its body gives for the arguments of a
+//! call: a scalar, a vector or a matrix. Calls are expanded where they
+//! appear: [`FunctionTable::inline`] replaces `rotation(omega * t)` by the
+//! body with the arguments in place of the parameters, so the type checker
+//! infers the shape of each call from the shapes of its arguments, and
+//! lowering emits the expanded expression like any other.
+//!
+//! A body may call the functions defined before it, so definitions cannot
+//! recurse. Names in a body other than its parameters refer to the
+//! program's declarations.
+
+use crate::ast::Expr;
+use crate::error::{EngineError, Result};
+use std::collections::HashMap;
+
+/// Parameters and body of a defined function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub params: Vec<String>,
+    /// With calls to earlier functions already inlined
+    pub body: Expr,
+}
+
+/// The functions a program has defined so far
+#[derive(Debug, Clone, Default)]
+pub struct FunctionTable {
+    functions: HashMap<String, Function>,
+}
+
+impl FunctionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define `name`, replacing any earlier definition of it
+    pub fn define(&mut self, name: &str, params: &[String], body: &Expr) -> Result<()> {
+        if let Some(repeated) = params
+            .iter()
+            .enumerate()
+            .find_map(|(k, param)| params[..k].contains(param).then_some(param))
+        {
+            return Err(EngineError::type_error(format!(
+                "Function '{}' has parameter '{}' more than once",
+                name, repeated
+            )));
+        }
+        let body = self.inline(body)?;
+        self.functions.insert(
+            name.to_string(),
+            Function {
+                params: params.to_vec(),
+                body,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// `expr` with every call to a defined function replaced by the
+    /// function's body applied to the call's arguments
+    pub fn inline(&self, expr: &Expr) -> Result<Expr> {
+        let mut expr = expr.clone();
+        self.inline_in(&mut expr)?;
+        Ok(expr)
+    }
+
+    fn inline_in(&self, expr: &mut Expr) -> Result<()> {
+        if let Expr::FuncCall { name, args } = expr {
+            for arg in args.iter_mut() {
+                self.inline_in(arg)?;
+            }
+            if let Some(function) = self.functions.get(name.as_str()) {
+                let applied = function.apply(name, args)?;
+                *expr = applied;
+            }
+            return Ok(());
+        }
+        children_mut(expr)
+            .into_iter()
+            .try_for_each(|child| self.inline_in(child))
+    }
+}
+
+impl Function {
+    /// The body with `args` in place of the parameters
+    pub fn apply(&self, name: &str, args: &[Expr]) -> Result<Expr> {
+        if args.len() != self.params.len() {
+            return Err(EngineError::type_error(format!(
+                "Function '{}' takes {} argument{}, got {}",
+                name,
+                self.params.len(),
+                if self.params.len() == 1 { "" } else { "s" },
+                args.len()
+            )));
+        }
+        let bindings: HashMap<&str, &Expr> =
+            self.params.iter().map(String::as_str).zip(args).collect();
+        let mut body = self.body.clone();
+        substitute(&mut body, &bindings);
+        Ok(body)
+    }
+}
+
+/// Replace every identifier of `expr` bound in `bindings` by its value
+///
+/// The values are not searched in turn, so an argument mentioning a
+/// parameter's name is not captured by it.
+pub fn substitute(expr: &mut Expr, bindings: &HashMap<&str, &Expr>) {
+    if let Expr::Identifier(name) = expr {
+        if let Some(&value) = bindings.get(name.as_str()) {
+            *expr = value.clone();
+        }
+        return;
+    }
+    for child in children_mut(expr) {
+        substitute(child, bindings);
+    }
+}
+
+/// Identifiers `expr` refers to, other than the names in `bound`
+pub fn free_names<'a>(expr: &'a Expr, bound: &[String], names: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Identifier(name) => {
+            if !bound.contains(name) && !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        Expr::Number(_) | Expr::ComplexNumber(_) => {}
+        Expr::Matrix(matrix) => matrix
+            .rows
+            .iter()
+            .flatten()
+            .for_each(|e| free_names(e, bound, names)),
+        Expr::Vector(vector) => vector
+            .elements
+            .iter()
+            .for_each(|e| free_names(e, bound, names)),
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Pow(a, b)
+        | Expr::Tensor(a, b)
+        | Expr::Commutator(a, b)
+        | Expr::AntiCommutator(a, b) => {
+            free_names(a, bound, names);
+            free_names(b, bound, names);
+        }
+        Expr::Dagger(a)
+        | Expr::Trace(a)
+        | Expr::Expm(a)
+        | Expr::Sqrt(a)
+        | Expr::Sin(a)
+        | Expr::Cos(a)
+        | Expr::Exp(a) => free_names(a, bound, names),
+        Expr::FuncCall { args, .. } => args.iter().for_each(|e| free_names(e, bound, names)),
+    }
+}
+
+pub(crate) fn children_mut(expr: &mut Expr) -> Vec<&mut Expr> {
+    match expr {
+        Expr::Number(_) | Expr::ComplexNumber(_) | Expr::Identifier(_) => Vec::new(),
+        Expr::Matrix(matrix) => matrix.rows.iter_mut().flatten().collect(),
+        Expr::Vector(vector) => vector.elements.iter_mut().collect(),
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Pow(a, b)
+        | Expr::Tensor(a, b)
+        | Expr::Commutator(a, b)
+        | Expr::AntiCommutator(a, b) => vec![&mut **a, &mut **b],
+        Expr::Dagger(a)
+        | Expr::Trace(a)
+        | Expr::Expm(a)
+        | Expr::Sqrt(a)
+        | Expr::Sin(a)
+        | Expr::Cos(a)
+        | Expr::Exp(a) => vec![&mut **a],
+        Expr::FuncCall { args, .. } => args.iter_mut().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Box<Expr> {
+        Box::new(Expr::Identifier(name.to_string()))
+    }
+
+    fn call(name: &str, args: Vec<Expr>) -> Expr {
+        Expr::FuncCall {
+            name: name.to_string(),
+            args,
+        }
+    }
+
+    fn params(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_calls_are_inlined() {
+        let mut functions = FunctionTable::new();
+        // conj(U, A) = U * A * dagger(U)
+        let conj = Expr::Mul(
+            Box::new(Expr::Mul(id("U"), id("A"))),
+            Box::new(Expr::Dagger(id("U"))),
+        );
+        functions
+            .define("conj", &params(&["U", "A"]), &conj)
+            .unwrap();
+        // flip(A) = conj(sigma_x, A)
+        let flip = call(
+            "conj",
+            vec![Expr::Identifier("sigma_x".to_string()), *id("A")],
+        );
+        functions.define("flip", &params(&["A"]), &flip).unwrap();
+
+        // An argument named like a parameter is not substituted again
+        let inlined = functions.inline(&call("flip", vec![*id("U")])).unwrap();
+        assert_eq!(
+            inlined,
+            Expr::Mul(
+                Box::new(Expr::Mul(id("sigma_x"), id("U"))),
+                Box::new(Expr::Dagger(id("sigma_x"))),
+            )
+        );
+        // Other calls are left alone
+        let ptrace = call("ptrace", vec![*id("rho"), Expr::Number(0.0)]);
+        assert_eq!(functions.inline(&ptrace).unwrap(), ptrace);
+    }
+
+    #[test]
+    fn test_definition_errors() {
+        let mut functions = FunctionTable::new();
+        assert!(functions
+            .define("f", &params(&["x", "x"]), &Expr::Number(1.0))
+            .is_err());
+        functions.define("f", &params(&["x"]), &id("x")).unwrap();
+        let wrong_arity = functions
+            .inline(&call("f", vec![Expr::Number(1.0), Expr::Number(2.0)]))
+            .unwrap_err();
+        assert!(
+            wrong_arity.to_string().contains("takes 1 argument, got 2"),
+            "{}",
+            wrong_arity
+        );
+
+        let product = Expr::Mul(id("x"), id("omega"));
+        let mut names = Vec::new();
+        free_names(&product, &params(&["x"]), &mut names);
+        assert_eq!(names, ["omega"]);
+    }
+}
@@ -0,0 +1,335 @@
+//! The gate library of circuit-style `apply` statements
+//!
+//! `apply <gate> to <state>[q1, q2, ...];` in an experiment applies a gate
+//! to qubits of the experiment's initial state, in order, before any
+//! evolution. [`lookup`] finds the builtin gates: the fixed gates H, X, Y,
+//! Z, S, T, CNOT, CZ and SWAP, whose matrices [`Gate::matrix`] builds, and
+//! the rotations `RX(θ)`, `RY(θ)` and `RZ(θ)`, `exp(-iθσ/2)`, whose angle
+//! may be any scalar expression, parameters included. Gate names are looked
+//! up here before the program's declarations, so `apply H` is the Hadamard
+//! gate even in a program with a Hamiltonian `H`; any other declared
+//! matrix of dimension 2^k applies as a gate on k qubits, once the
+//! validator has found it unitary.
+//!
+//! Qubits are numbered as in [`kernels_cpu::tensor_product`], qubit 0 the
+//! most significant, and the first target of a multi-qubit gate is its
+//! most significant qubit: `CNOT` on `psi[0, 1]` is controlled by qubit 0.
+
+use crate::ast::Expr;
+use crate::error::{EngineError, Result};
+use crate::kernels_cpu;
+use ndarray::Array2;
+use num_complex::Complex64;
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateKind {
+    /// A constant matrix
+    Fixed,
+    /// `exp(-iθσ/2)` about the Pauli matrix named here, taking the angle θ
+    Rotation(&'static str),
+}
+
+/// One gate of the library
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gate {
+    pub name: &'static str,
+    pub kind: GateKind,
+    /// Qubits it acts on
+    pub qubits: usize,
+    pub doc: &'static str,
+}
+
+static GATES: &[Gate] = &[
+    Gate {
+        name: "H",
+        kind: GateKind::Fixed,
+        qubits: 1,
+        doc: "Hadamard gate",
+    },
+    Gate {
+        name: "X",
+        kind: GateKind::Fixed,
+        qubits: 1,
+        doc: "Pauli X (NOT) gate",
+    },
+    Gate {
+        name: "Y",
+        kind: GateKind::Fixed,
+        qubits: 1,
+        doc: "Pauli Y gate",
+    },
+    Gate {
+        name: "Z",
+        kind: GateKind::Fixed,
+        qubits: 1,
+        doc: "Pauli Z gate",
+    },
+    Gate {
+        name: "S",
+        kind: GateKind::Fixed,
+        qubits: 1,
+        doc: "Phase gate diag(1, i)",
+    },
+    Gate {
+        name: "T",
+        kind: GateKind::Fixed,
+        qubits: 1,
+        doc: "π/8 gate diag(1, e^{iπ/4})",
+    },
+    Gate {
+        name: "RX",
+        kind: GateKind::Rotation("sigma_x"),
+        qubits: 1,
+        doc: "Rotation exp(-iθX/2) about the x axis",
+    },
+    Gate {
+        name: "RY",
+        kind: GateKind::Rotation("sigma_y"),
+        qubits: 1,
+        doc: "Rotation exp(-iθY/2) about the y axis",
+    },
+    Gate {
+        name: "RZ",
+        kind: GateKind::Rotation("sigma_z"),
+        qubits: 1,
+        doc: "Rotation exp(-iθZ/2) about the z axis",
+    },
+    Gate {
+        name: "CNOT",
+        kind: GateKind::Fixed,
+        qubits: 2,
+        doc: "Controlled NOT, controlled by the first target",
+    },
+    Gate {
+        name: "CZ",
+        kind: GateKind::Fixed,
+        qubits: 2,
+        doc: "Controlled Z",
+    },
+    Gate {
+        name: "SWAP",
+        kind: GateKind::Fixed,
+        qubits: 2,
+        doc: "Exchange of two qubits",
+    },
+];
+
+/// Every builtin gate
+pub fn gate_library() -> &'static [Gate] {
+    GATES
+}
+
+/// The builtin gate called `name`, if any
+pub fn lookup(name: &str) -> Option<&'static Gate> {
+    GATES.iter().find(|gate| gate.name == name)
+}
+
+impl Gate {
+    /// The matrix of a [`GateKind::Fixed`] gate
+    pub fn matrix(&self) -> Option<Array2<Complex64>> {
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let (o, l) = (c(0.0, 0.0), c(1.0, 0.0));
+        let h = c(FRAC_1_SQRT_2, 0.0);
+        let (dim, entries) = match self.name {
+            "H" => (2, vec![h, h, h, -h]),
+            "X" => (2, vec![o, l, l, o]),
+            "Y" => (2, vec![o, c(0.0, -1.0), c(0.0, 1.0), o]),
+            "Z" => (2, vec![l, o, o, -l]),
+            "S" => (2, vec![l, o, o, c(0.0, 1.0)]),
+            "T" => (2, vec![l, o, o, c(FRAC_1_SQRT_2, FRAC_1_SQRT_2)]),
+            #[rustfmt::skip]
+            "CNOT" => (4, vec![
+                l, o, o, o,
+                o, l, o, o,
+                o, o, o, l,
+                o, o, l, o,
+            ]),
+            "CZ" => return Some(Array2::from_diag(&ndarray::arr1(&[l, l, l, -l]))),
+            #[rustfmt::skip]
+            "SWAP" => (4, vec![
+                l, o, o, o,
+                o, o, l, o,
+                o, l, o, o,
+                o, o, o, l,
+            ]),
+            _ => return None,
+        };
+        Array2::from_shape_vec((dim, dim), entries).ok()
+    }
+
+    /// The expression `expm(-i * θ / 2 * σ)` of a rotation gate by `angle`,
+    /// which lowers like any other so the angle may be a parameter
+    pub fn rotation(&self, angle: Expr) -> Option<Expr> {
+        let GateKind::Rotation(axis) = self.kind else {
+            return None;
+        };
+        let half_angle = Expr::Mul(
+            Box::new(Expr::ComplexNumber(Complex64::new(0.0, -0.5))),
+            Box::new(angle),
+        );
+        Some(Expr::Expm(Box::new(Expr::Mul(
+            Box::new(half_angle),
+            Box::new(Expr::Identifier(axis.to_string())),
+        ))))
+    }
+}
+
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let signature = match self.kind {
+            GateKind::Fixed => self.name.to_string(),
+            GateKind::Rotation(_) => format!("{}(θ)", self.name),
+        };
+        write!(
+            f,
+            "{:<8} {} [{} qubit{}]",
+            signature,
+            self.doc,
+            self.qubits,
+            if self.qubits == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Number of qubits of a register of dimension `dim`
+pub fn register_qubits(dim: usize) -> Result<usize> {
+    Ok(kernels_cpu::qubit_dims(dim)?.len())
+}
+
+/// `gate` acting on the qubits `targets` of a register of `n_qubits`
+/// qubits, as a unitary on the whole register; no targets means `gate`
+/// already acts on all of it
+pub fn expand_gate(
+    gate: &Array2<Complex64>,
+    targets: &[usize],
+    n_qubits: usize,
+) -> Result<Array2<Complex64>> {
+    let dim = 1usize << n_qubits;
+    if targets.is_empty() {
+        if gate.dim() != (dim, dim) {
+            return Err(EngineError::dimension_mismatch(
+                format!("{}x{} gate on the whole register", dim, dim),
+                format!("{}x{}", gate.nrows(), gate.ncols()),
+            ));
+        }
+        return Ok(gate.clone());
+    }
+    check_targets(targets, n_qubits)?;
+    let k = targets.len();
+    if gate.dim() != (1 << k, 1 << k) {
+        return Err(EngineError::dimension_mismatch(
+            format!("{}x{} gate on {} qubits", 1 << k, 1 << k, k),
+            format!("{}x{}", gate.nrows(), gate.ncols()),
+        ));
+    }
+
+    // Bit of qubit q in a register index, qubit 0 the most significant
+    let mask = |q: usize| 1usize << (n_qubits - 1 - q);
+    // Index within the gate of the target qubits' bits of `index`
+    let local = |index: usize| {
+        targets
+            .iter()
+            .fold(0, |acc, &q| (acc << 1) | usize::from(index & mask(q) != 0))
+    };
+    // `index` with the target qubits set to the bits of the gate index `s`
+    let with_local = |index: usize, s: usize| {
+        targets.iter().enumerate().fold(index, |acc, (i, &q)| {
+            if s & (1 << (k - 1 - i)) != 0 {
+                acc | mask(q)
+            } else {
+                acc & !mask(q)
+            }
+        })
+    };
+
+    let mut full = Array2::zeros((dim, dim));
+    for col in 0..dim {
+        let s = local(col);
+        for r in 0..1 << k {
+            let value = gate[[r, s]];
+            if value != Complex64::new(0.0, 0.0) {
+                full[[with_local(col, r), col]] += value;
+            }
+        }
+    }
+    Ok(full)
+}
+
+/// Check that `targets` are distinct qubits of a register of `n_qubits`
+pub fn check_targets(targets: &[usize], n_qubits: usize) -> Result<()> {
+    if let Some(&q) = targets.iter().find(|&&q| q >= n_qubits) {
+        return Err(EngineError::validation_error(format!(
+            "Qubit {} out of range for a register of {} qubits",
+            q, n_qubits
+        )));
+    }
+    for (i, q) in targets.iter().enumerate() {
+        if targets[i + 1..].contains(q) {
+            return Err(EngineError::validation_error(format!(
+                "Qubit {} is targeted more than once in {:?}",
+                q, targets
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Array2<Complex64>, b: &Array2<Complex64>) -> bool {
+        a.dim() == b.dim() && (a - b).iter().all(|x| x.norm() < 1e-12)
+    }
+
+    #[test]
+    fn test_library_gates_are_unitary() {
+        for gate in gate_library()
+            .iter()
+            .filter(|gate| gate.kind == GateKind::Fixed)
+        {
+            let u = gate.matrix().unwrap();
+            assert_eq!(u.nrows(), 1 << gate.qubits, "{}", gate.name);
+            let identity = Array2::eye(u.nrows());
+            assert!(
+                close(&kernels_cpu::dagger(&u).dot(&u), &identity),
+                "{}",
+                gate.name
+            );
+        }
+        let rx = lookup("RX").unwrap().rotation(Expr::Number(1.0)).unwrap();
+        assert!(matches!(rx, Expr::Expm(_)));
+        assert!(lookup("H").unwrap().rotation(Expr::Number(1.0)).is_none());
+        assert_eq!(
+            lookup("CNOT").unwrap().to_string(),
+            "CNOT     Controlled NOT, controlled by the first target [2 qubits]"
+        );
+    }
+
+    #[test]
+    fn test_expand_gate_orders_targets() {
+        let x = lookup("X").unwrap().matrix().unwrap();
+        let cnot = lookup("CNOT").unwrap().matrix().unwrap();
+        let identity = Array2::eye(2);
+
+        // X on qubit 1 of 2 is I ⊗ X
+        let expected = kernels_cpu::tensor_product(&identity, &x).unwrap();
+        assert!(close(&expand_gate(&x, &[1], 2).unwrap(), &expected));
+        assert!(close(&expand_gate(&cnot, &[0, 1], 2).unwrap(), &cnot));
+        // Controlled by qubit 1 instead: |01⟩ ↦ |11⟩
+        let reversed = expand_gate(&cnot, &[1, 0], 2).unwrap();
+        assert_eq!(reversed[[3, 1]], Complex64::new(1.0, 0.0));
+        assert_eq!(reversed[[0, 0]], Complex64::new(1.0, 0.0));
+        // A CNOT across an idle middle qubit flips the last one
+        let wide = expand_gate(&cnot, &[0, 2], 3).unwrap();
+        assert_eq!(wide[[0b101, 0b100]], Complex64::new(1.0, 0.0));
+        assert_eq!(wide[[0b010, 0b010]], Complex64::new(1.0, 0.0));
+
+        assert!(expand_gate(&x, &[2], 2).is_err());
+        assert!(expand_gate(&cnot, &[1, 1], 2).is_err());
+        assert!(expand_gate(&cnot, &[0], 2).is_err());
+        assert!(close(&expand_gate(&cnot, &[], 2).unwrap(), &cnot));
+    }
+}
@@ -0,0 +1,390 @@
+//! Multi-file DSL programs
+//!
+//! `import "operators.phys";` at the top of a program brings in the
+//! declarations of another file, found relative to the importing one. They
+//! stay in a namespace, the file's stem or the name given with
+//! `import "lib/operators.phys" as ops;`: a matrix `sigma_plus` declared
+//! there is `ops::sigma_plus` in the importer, and references to it inside
+//! the imported file are qualified to match. Imported files may import
+//! others in turn, whose names nest (`ops::pauli::x`).
+//!
+//! Imported files provide declarations only; experiments and sweeps in them
+//! are errors, as are import cycles. Problems with an import are parse
+//! errors located at its `import` statement.
+
+use crate::ast::*;
+use crate::error::{EngineError, Result};
+use crate::parser::{self, Import};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Parse the DSL program in the file `path`, with its imported declarations
+/// ahead of its own statements in import order
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Ast> {
+    load(path.as_ref(), &mut Vec::new())
+}
+
+/// The AST of the file `path` with its imports inlined; `stack` holds the
+/// files importing it, outermost first
+fn load(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Ast> {
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        EngineError::IoError(std::io::Error::new(
+            e.kind(),
+            format!("{}: {}", path.display(), e),
+        ))
+    })?;
+    let (imports, mut ast) = parser::parse_module(&source)?;
+
+    stack.push(path.canonicalize()?);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut imported = Vec::new();
+    let mut namespaces: Vec<String> = Vec::new();
+    for import in &imports {
+        let namespace = namespace(import)?;
+        if namespaces.contains(&namespace) {
+            return Err(import_error(
+                import,
+                format!("namespace '{}' is already imported", namespace),
+            ));
+        }
+        let module = load_import(&dir.join(&import.path), import, stack)?;
+        imported.extend(qualify(module.statements, &namespace));
+        namespaces.push(namespace);
+    }
+    stack.pop();
+
+    let count = imported.len();
+    imported.append(&mut ast.statements);
+    ast.statements = imported;
+    ast.source_map.prepend_unmapped(count);
+    Ok(ast)
+}
+
+/// The declarations of the file `path` named by `import`
+fn load_import(path: &Path, import: &Import, stack: &mut Vec<PathBuf>) -> Result<Ast> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| import_error(import, format!("cannot read {}: {}", path.display(), e)))?;
+    if let Some(start) = stack.iter().position(|p| *p == canonical) {
+        let cycle: Vec<String> = stack[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(import_error(
+            import,
+            format!("import cycle {}", cycle.join(" -> ")),
+        ));
+    }
+
+    let module = load(path, stack).map_err(|e| match e {
+        EngineError::ParseError {
+            line,
+            column,
+            message,
+        } => EngineError::parse_error(
+            line,
+            column,
+            format!(
+                "{} (in {}, imported at line {})",
+                message,
+                path.display(),
+                import.span.line
+            ),
+        ),
+        e => e,
+    })?;
+    let misplaced = module.statements.iter().find_map(|stmt| match stmt {
+        Statement::Experiment { name, .. } => Some(format!("experiment '{}'", name)),
+        Statement::Sweep { param, .. } => Some(format!("a sweep over '{}'", param)),
+        Statement::Assume(goal) => Some(format!("assumption '{}'", goal)),
+        Statement::Prove(goal) => Some(format!("goal '{}'", goal)),
+        _ => None,
+    });
+    if let Some(misplaced) = misplaced {
+        return Err(import_error(
+            import,
+            format!(
+                "{} has {}; imported files may only declare",
+                path.display(),
+                misplaced
+            ),
+        ));
+    }
+    Ok(module)
+}
+
+fn import_error(import: &Import, message: String) -> EngineError {
+    EngineError::parse_error(
+        import.span.line,
+        import.span.column,
+        format!("Cannot import \"{}\": {}", import.path, message),
+    )
+}
+
+/// The alias of `import`, or the stem of its file
+fn namespace(import: &Import) -> Result<String> {
+    if let Some(alias) = &import.alias {
+        return Ok(alias.clone());
+    }
+    let stem = Path::new(&import.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let mut chars = stem.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(import_error(
+            import,
+            format!("'{}' is not a valid namespace; name one with `as`", stem),
+        ));
+    }
+    Ok(stem.to_string())
+}
+
+/// `statements` with every name they declare, and every reference to one,
+/// qualified by `namespace`
+fn qualify(mut statements: Vec<Statement>, namespace: &str) -> Vec<Statement> {
+    let names: HashMap<String, String> = statements
+        .iter()
+        .filter_map(declared_name)
+        .map(|name| (name.to_string(), format!("{}::{}", namespace, name)))
+        .collect();
+
+    for stmt in &mut statements {
+        match stmt {
+            Statement::ConstDecl { name, .. }
+            | Statement::SymbolDecl { name }
+            | Statement::ParamDecl { name, .. } => qualify_name(name, &names),
+            Statement::MatrixDecl { name, value } => {
+                qualify_name(name, &names);
+                qualify_matrix(value, &names, &[]);
+            }
+            Statement::OperatorDecl { name, .. } => qualify_name(name, &names),
+            Statement::FunctionDef { name, params, body } => {
+                qualify_name(name, &names);
+                qualify_expr(body, &names, params);
+            }
+            Statement::HamiltonianDef { name, params, expr } => {
+                qualify_name(name, &names);
+                qualify_expr(expr, &names, params);
+            }
+            Statement::MeasurementDef { name, spec } => {
+                qualify_name(name, &names);
+                if let MeasurementSpec::Projective {
+                    projectors: matrices,
+                }
+                | MeasurementSpec::POVM { effects: matrices } = spec
+                {
+                    matrices
+                        .iter_mut()
+                        .for_each(|m| qualify_matrix(m, &names, &[]));
+                }
+            }
+            Statement::ChannelDef { name, kraus } => {
+                qualify_name(name, &names);
+                kraus.iter_mut().for_each(|k| qualify_expr(k, &names, &[]));
+            }
+            // Rejected by `load_import`
+            Statement::Experiment { .. }
+            | Statement::Sweep { .. }
+            | Statement::Assume(_)
+            | Statement::Prove(_) => {}
+        }
+    }
+    statements
+}
+
+fn declared_name(stmt: &Statement) -> Option<&str> {
+    match stmt {
+        Statement::ConstDecl { name, .. }
+        | Statement::SymbolDecl { name }
+        | Statement::ParamDecl { name, .. }
+        | Statement::MatrixDecl { name, .. }
+        | Statement::OperatorDecl { name, .. }
+        | Statement::FunctionDef { name, .. }
+        | Statement::HamiltonianDef { name, .. }
+        | Statement::MeasurementDef { name, .. }
+        | Statement::ChannelDef { name, .. } => Some(name),
+        Statement::Experiment { .. }
+        | Statement::Sweep { .. }
+        | Statement::Assume(_)
+        | Statement::Prove(_) => None,
+    }
+}
+
+fn qualify_name(name: &mut String, names: &HashMap<String, String>) {
+    if let Some(qualified) = names.get(name) {
+        *name = qualified.clone();
+    }
+}
+
+fn qualify_matrix(matrix: &mut MatrixLiteral, names: &HashMap<String, String>, bound: &[String]) {
+    for element in matrix.rows.iter_mut().flatten() {
+        qualify_expr(element, names, bound);
+    }
+}
+
+/// Qualify the names in `expr`, except the parameters `bound` shadows them
+/// with
+fn qualify_expr(expr: &mut Expr, names: &HashMap<String, String>, bound: &[String]) {
+    match expr {
+        Expr::Identifier(name) => {
+            if !bound.contains(name) {
+                qualify_name(name, names);
+            }
+        }
+        Expr::Number(_) | Expr::ComplexNumber(_) => {}
+        Expr::Matrix(matrix) => qualify_matrix(matrix, names, bound),
+        Expr::Vector(vector) => {
+            for element in &mut vector.elements {
+                qualify_expr(element, names, bound);
+            }
+        }
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Pow(a, b)
+        | Expr::Tensor(a, b)
+        | Expr::Commutator(a, b)
+        | Expr::AntiCommutator(a, b) => {
+            qualify_expr(a, names, bound);
+            qualify_expr(b, names, bound);
+        }
+        Expr::Dagger(a)
+        | Expr::Trace(a)
+        | Expr::Expm(a)
+        | Expr::Sqrt(a)
+        | Expr::Sin(a)
+        | Expr::Cos(a)
+        | Expr::Exp(a) => qualify_expr(a, names, bound),
+        Expr::FuncCall { name, args } => {
+            qualify_name(name, names);
+            for arg in args {
+                qualify_expr(arg, names, bound);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory holding `files`
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qte_imports_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (file, source) in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_imports_are_namespaced() {
+        let dir = project(
+            "namespaced",
+            &[
+                (
+                    "lib/operators.phys",
+                    r#"
+                    import "pauli.phys";
+                    const g = 0.5;
+                    matrix sigma_plus = [0, 1; 0, 0];
+                    func drive(g) = g * pauli::x;
+                    Hamiltonian H0 = g * pauli::x;
+                    "#,
+                ),
+                ("lib/pauli.phys", "matrix x = [0, 1; 1, 0];"),
+                (
+                    "main.phys",
+                    r#"
+                    import "lib/operators.phys" as ops;
+                    Hamiltonian H = ops::H0 + ops::sigma_plus;
+                    experiment e {
+                        init: ket(vec(1, 0));
+                        evolution: evolve(init, H, timegrid=(0.0, 1.0, 3));
+                    }
+                    "#,
+                ),
+            ],
+        );
+
+        let ast = parse_file(dir.join("main.phys")).unwrap();
+        let names: Vec<&str> = ast.statements.iter().filter_map(declared_name).collect();
+        assert_eq!(
+            names,
+            [
+                "ops::pauli::x",
+                "ops::g",
+                "ops::sigma_plus",
+                "ops::drive",
+                "ops::H0",
+                "H"
+            ]
+        );
+        let Statement::FunctionDef { body, .. } = &ast.statements[3] else {
+            panic!("expected the imported function");
+        };
+        // The parameter shadows the imported constant
+        assert_eq!(
+            **body,
+            Expr::Mul(
+                Box::new(Expr::Identifier("g".to_string())),
+                Box::new(Expr::Identifier("ops::pauli::x".to_string()))
+            )
+        );
+        // Positions still refer to the main file
+        assert_eq!(ast.source_map.statement(5).map(|s| s.line), Some(3));
+        assert_eq!(ast.source_map.statement(0), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let dir = project(
+            "errors",
+            &[
+                ("a.phys", "import \"b.phys\";\nconst x = 1.0;"),
+                ("b.phys", "import \"a.phys\";"),
+                ("missing.phys", "\n  import \"nowhere.phys\";"),
+                ("runs.phys", "import \"main.phys\";"),
+                (
+                    "main.phys",
+                    "matrix A = [1, 0; 0, 1];\nexperiment e { init: ket(vec(1, 0)); }",
+                ),
+            ],
+        );
+
+        let cycle = parse_file(dir.join("a.phys")).unwrap_err().to_string();
+        assert!(cycle.contains("import cycle"), "{}", cycle);
+
+        let missing = parse_file(dir.join("missing.phys")).unwrap_err();
+        assert!(
+            matches!(
+                missing,
+                EngineError::ParseError {
+                    line: 2,
+                    column: 3,
+                    ..
+                }
+            ),
+            "{}",
+            missing
+        );
+
+        let runs = parse_file(dir.join("runs.phys")).unwrap_err().to_string();
+        assert!(runs.contains("experiment 'e'"), "{}", runs);
+
+        assert!(crate::parser::parse_dsl("import \"a.phys\";").is_err());
+        assert!(crate::parser::parse_dsl("matrix ops::A = [1, 0; 0, 1];").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
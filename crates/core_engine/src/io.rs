@@ -0,0 +1,733 @@
+//! Saving and loading simulation results
+//!
+//! [`ExecutionResult`]s are stored in HDF5 with complex arrays split into
+//! real and imaginary parts, so files open directly in h5py or MATLAB:
+//!
+//! ```text
+//! /                                   attrs: engine_version, metadata (JSON)
+//! /experiments/<i>/                   attrs: name, state_type, diagnostics, ehrenfest (JSON, optional)
+//! /experiments/<i>/times              f64 [T]
+//! /experiments/<i>/states/re          f64 [T, d, d]
+//! /experiments/<i>/states/im          f64 [T, d, d]
+//! /experiments/<i>/measurements/times          f64 [M]
+//! /experiments/<i>/measurements/probabilities  f64 [sum of outcome counts]
+//! /experiments/<i>/measurements/offsets        u64 [M + 1]
+//! /experiments/<i>/observables/       attrs: labels (JSON); only with tracked observables
+//! /experiments/<i>/observables/re     f64 [K, T]
+//! /experiments/<i>/observables/im     f64 [K, T]
+//! ```
+//!
+//! Experiments are numbered in order (`experiments/0`, `experiments/1`, ...)
+//! so names never need escaping. Measurement probabilities are ragged and
+//! stored flat: measurement `k` owns `probabilities[offsets[k]..offsets[k+1]]`.
+//!
+//! For pandas, R and other analysis tools, [`export_csv`] writes results as
+//! a tidy table with one value per row,
+//!
+//! ```text
+//! experiment,time,observable,value
+//! rabi,0.5,rho[1][1],0.229
+//! rabi,0.5,<sigma_z>,0.541
+//! ```
+//!
+//! and [`export_json`] as JSON with states included and every complex
+//! number written as an `[re, im]` pair. [`load_json`] reads that back, as
+//! well as a plain serialized [`ExecutionResult`], which has no states.
+
+use crate::error::{EngineError, Result};
+use crate::executor::ExecutionResult;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A result loaded back from disk, with the metadata stored alongside it
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    pub result: ExecutionResult,
+    pub metadata: serde_json::Value,
+    pub engine_version: String,
+}
+
+/// Write `result` and free-form `metadata` to an HDF5 file at `path`
+#[cfg(feature = "hdf5")]
+pub fn save_hdf5(
+    result: &ExecutionResult,
+    metadata: &serde_json::Value,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    use ndarray::{Array1, Array2, Array3};
+
+    let file = hdf5::File::create(path).map_err(h5_error)?;
+    write_string_attr(&file, "engine_version", crate::VERSION)?;
+    write_string_attr(&file, "metadata", &metadata.to_string())?;
+
+    let experiments = file.create_group("experiments").map_err(h5_error)?;
+    for (index, experiment) in result.experiment_results.iter().enumerate() {
+        let group = experiments
+            .create_group(&index.to_string())
+            .map_err(h5_error)?;
+        write_string_attr(&group, "name", &experiment.name)?;
+        write_string_attr(
+            &group,
+            "state_type",
+            &serde_json::to_string(&experiment.state_type)?,
+        )?;
+        if let Some(diagnostics) = &experiment.diagnostics {
+            write_string_attr(&group, "diagnostics", &serde_json::to_string(diagnostics)?)?;
+        }
+        if !experiment.ehrenfest.is_empty() {
+            write_string_attr(&group, "ehrenfest", &serde_json::to_string(&experiment.ehrenfest)?)?;
+        }
+
+        write_dataset(&group, "times", &Array1::from_vec(experiment.times.clone()))?;
+
+        let dim = experiment.states.first().map_or(0, |rho| rho.nrows());
+        if let Some(rho) = experiment.states.iter().find(|rho| rho.dim() != (dim, dim)) {
+            return Err(EngineError::dimension_mismatch(
+                format!("({}, {})", dim, dim),
+                format!("{:?}", rho.dim()),
+            ));
+        }
+        let shape = (experiment.states.len(), dim, dim);
+        let re = Array3::from_shape_fn(shape, |(t, i, j)| experiment.states[t][[i, j]].re);
+        let im = Array3::from_shape_fn(shape, |(t, i, j)| experiment.states[t][[i, j]].im);
+        let states = group.create_group("states").map_err(h5_error)?;
+        write_dataset(&states, "re", &re)?;
+        write_dataset(&states, "im", &im)?;
+
+        let mut times = Vec::with_capacity(experiment.measurements.len());
+        let mut probabilities = Vec::new();
+        let mut offsets = vec![0u64];
+        for measurement in &experiment.measurements {
+            times.push(measurement.time);
+            probabilities.extend_from_slice(&measurement.probabilities);
+            offsets.push(probabilities.len() as u64);
+        }
+        let measurements = group.create_group("measurements").map_err(h5_error)?;
+        write_dataset(&measurements, "times", &Array1::from_vec(times))?;
+        write_dataset(
+            &measurements,
+            "probabilities",
+            &Array1::from_vec(probabilities),
+        )?;
+        write_dataset(&measurements, "offsets", &Array1::from_vec(offsets))?;
+
+        if !experiment.observables.is_empty() {
+            let series = &experiment.observables;
+            let labels: Vec<&str> = series.iter().map(|s| s.label.as_str()).collect();
+            let shape = (series.len(), experiment.times.len());
+            if let Some(s) = series.iter().find(|s| s.values.len() != shape.1) {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{} values", shape.1),
+                    format!("{} for observable <{}>", s.values.len(), s.label),
+                ));
+            }
+            let observables = group.create_group("observables").map_err(h5_error)?;
+            write_string_attr(&observables, "labels", &serde_json::to_string(&labels)?)?;
+            write_dataset(
+                &observables,
+                "re",
+                &Array2::from_shape_fn(shape, |(k, t)| series[k].values[t].re),
+            )?;
+            write_dataset(
+                &observables,
+                "im",
+                &Array2::from_shape_fn(shape, |(k, t)| series[k].values[t].im),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "hdf5"))]
+pub fn save_hdf5(
+    _result: &ExecutionResult,
+    _metadata: &serde_json::Value,
+    _path: impl AsRef<Path>,
+) -> Result<()> {
+    Err(EngineError::Unsupported(
+        "HDF5 export requires the `hdf5` feature".to_string(),
+    ))
+}
+
+/// Read a file written by [`save_hdf5`]
+#[cfg(feature = "hdf5")]
+pub fn load_hdf5(path: impl AsRef<Path>) -> Result<StoredResult> {
+    use crate::executor::{ExperimentResult, MeasurementResult, ObservableSeries};
+    use ndarray::Array2;
+    use num_complex::Complex64;
+
+    let file = hdf5::File::open(path).map_err(h5_error)?;
+    let engine_version = read_string_attr(&file, "engine_version")?;
+    let metadata = serde_json::from_str(&read_string_attr(&file, "metadata")?)?;
+
+    let experiments = file.group("experiments").map_err(h5_error)?;
+    let mut indices: Vec<usize> = experiments
+        .member_names()
+        .map_err(h5_error)?
+        .iter()
+        .filter_map(|name| name.parse().ok())
+        .collect();
+    indices.sort_unstable();
+
+    let mut experiment_results = Vec::with_capacity(indices.len());
+    for index in indices {
+        let group = experiments.group(&index.to_string()).map_err(h5_error)?;
+        let name = read_string_attr(&group, "name")?;
+        let state_type = serde_json::from_str(&read_string_attr(&group, "state_type")?)?;
+        let diagnostics = match group.attr("diagnostics") {
+            Ok(_) => Some(serde_json::from_str(&read_string_attr(
+                &group,
+                "diagnostics",
+            )?)?),
+            Err(_) => None,
+        };
+        let ehrenfest = match group.attr("ehrenfest") {
+            Ok(_) => serde_json::from_str(&read_string_attr(&group, "ehrenfest")?)?,
+            Err(_) => Vec::new(),
+        };
+
+        let times: Vec<f64> = group
+            .dataset("times")
+            .and_then(|d| d.read_raw())
+            .map_err(h5_error)?;
+
+        let re_dataset = group.dataset("states/re").map_err(h5_error)?;
+        let shape = re_dataset.shape();
+        let re: Vec<f64> = re_dataset.read_raw().map_err(h5_error)?;
+        let im: Vec<f64> = group
+            .dataset("states/im")
+            .and_then(|d| d.read_raw())
+            .map_err(h5_error)?;
+        if shape.len() != 3 || re.len() != im.len() {
+            return Err(EngineError::SerializationError(format!(
+                "Experiment '{}': malformed state arrays (shape {:?})",
+                name, shape
+            )));
+        }
+        let (n_states, dim) = (shape[0], shape[1]);
+        let states = (0..n_states)
+            .map(|t| {
+                let offset = t * dim * dim;
+                Array2::from_shape_fn((dim, dim), |(i, j)| {
+                    let k = offset + i * dim + j;
+                    Complex64::new(re[k], im[k])
+                })
+            })
+            .collect();
+
+        let measurement_times: Vec<f64> = group
+            .dataset("measurements/times")
+            .and_then(|d| d.read_raw())
+            .map_err(h5_error)?;
+        let probabilities: Vec<f64> = group
+            .dataset("measurements/probabilities")
+            .and_then(|d| d.read_raw())
+            .map_err(h5_error)?;
+        let offsets: Vec<u64> = group
+            .dataset("measurements/offsets")
+            .and_then(|d| d.read_raw())
+            .map_err(h5_error)?;
+        if offsets.len() != measurement_times.len() + 1
+            || offsets.last().copied() != Some(probabilities.len() as u64)
+        {
+            return Err(EngineError::SerializationError(format!(
+                "Experiment '{}': measurement offsets do not match probabilities",
+                name
+            )));
+        }
+        let measurements = measurement_times
+            .iter()
+            .zip(offsets.windows(2))
+            .map(|(&time, w)| MeasurementResult {
+                time,
+                probabilities: probabilities[w[0] as usize..w[1] as usize].to_vec(),
+            })
+            .collect();
+
+        let observables = match group.group("observables") {
+            Ok(observables) => {
+                let labels: Vec<String> =
+                    serde_json::from_str(&read_string_attr(&observables, "labels")?)?;
+                let re: Vec<f64> = observables
+                    .dataset("re")
+                    .and_then(|d| d.read_raw())
+                    .map_err(h5_error)?;
+                let im: Vec<f64> = observables
+                    .dataset("im")
+                    .and_then(|d| d.read_raw())
+                    .map_err(h5_error)?;
+                let n = times.len();
+                if re.len() != labels.len() * n || im.len() != re.len() {
+                    return Err(EngineError::SerializationError(format!(
+                        "Experiment '{}': malformed observable arrays",
+                        name
+                    )));
+                }
+                labels
+                    .into_iter()
+                    .enumerate()
+                    .map(|(k, label)| ObservableSeries {
+                        label,
+                        values: (k * n..(k + 1) * n)
+                            .map(|i| Complex64::new(re[i], im[i]))
+                            .collect(),
+                    })
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+
+        experiment_results.push(ExperimentResult {
+            name,
+            times,
+            state_type,
+            measurements,
+            states,
+            diagnostics,
+            ehrenfest,
+            observables,
+        });
+    }
+
+    Ok(StoredResult {
+        result: ExecutionResult { experiment_results },
+        metadata,
+        engine_version,
+    })
+}
+
+#[cfg(not(feature = "hdf5"))]
+pub fn load_hdf5(_path: impl AsRef<Path>) -> Result<StoredResult> {
+    Err(EngineError::Unsupported(
+        "HDF5 import requires the `hdf5` feature".to_string(),
+    ))
+}
+
+/// Text formats results can be exported in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(EngineError::validation_error(format!(
+                "Unknown export format '{}' (expected csv or json)",
+                other
+            ))),
+        }
+    }
+}
+
+/// One value of a result at one time, a row of the tidy table
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TidyRow {
+    pub experiment: String,
+    pub time: f64,
+    /// `rho[i][i]` for populations, `<A>` for tracked observables (`Im<A>`
+    /// for the imaginary part of a complex series) and `P[m][k]` for the
+    /// probability of outcome `k` of the experiment's measurement `m`
+    pub observable: String,
+    pub value: f64,
+}
+
+/// Every value of `result` that varies in time, one per row
+///
+/// Each experiment lists, time by time, the populations of its state and
+/// its tracked observables, then the outcome probabilities of its
+/// measurements.
+pub fn tidy_rows(result: &ExecutionResult) -> Vec<TidyRow> {
+    let mut rows = Vec::new();
+    for experiment in &result.experiment_results {
+        let mut push = |time: f64, observable: String, value: f64| {
+            rows.push(TidyRow {
+                experiment: experiment.name.clone(),
+                time,
+                observable,
+                value,
+            })
+        };
+        let complex: Vec<bool> = experiment
+            .observables
+            .iter()
+            .map(|series| series.values.iter().any(|v| v.im != 0.0))
+            .collect();
+
+        for (t, &time) in experiment.times.iter().enumerate() {
+            if let Some(rho) = experiment.states.get(t) {
+                for i in 0..rho.nrows() {
+                    push(time, format!("rho[{}][{}]", i, i), rho[[i, i]].re);
+                }
+            }
+            for (series, &complex) in experiment.observables.iter().zip(&complex) {
+                if let Some(value) = series.values.get(t) {
+                    push(time, format!("<{}>", series.label), value.re);
+                    if complex {
+                        push(time, format!("Im<{}>", series.label), value.im);
+                    }
+                }
+            }
+        }
+        for (m, measurement) in experiment.measurements.iter().enumerate() {
+            for (k, &p) in measurement.probabilities.iter().enumerate() {
+                push(measurement.time, format!("P[{}][{}]", m, k), p);
+            }
+        }
+    }
+    rows
+}
+
+/// Write [`tidy_rows`] of `result` as CSV with the header
+/// `experiment,time,observable,value`
+pub fn export_csv(result: &ExecutionResult, path: impl AsRef<Path>) -> Result<()> {
+    std::fs::write(path, to_csv(result))?;
+    Ok(())
+}
+
+/// [`tidy_rows`] of `result` as CSV text
+pub fn to_csv(result: &ExecutionResult) -> String {
+    let mut csv = String::from("experiment,time,observable,value\n");
+    for row in tidy_rows(result) {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&row.experiment),
+            row.time,
+            csv_field(&row.observable),
+            row.value
+        ));
+    }
+    csv
+}
+
+/// `field`, quoted if it holds a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Write [`to_json`] of `result`, pretty-printed
+pub fn export_json(result: &ExecutionResult, path: impl AsRef<Path>) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(&to_json(result))?)?;
+    Ok(())
+}
+
+/// `result` as JSON, states included, with complex numbers as `[re, im]`
+///
+/// ```text
+/// { "engine_version": "...",
+///   "experiments": [{ "name", "state_type", "times",
+///                     "states": [T][d][d] of [re, im],
+///                     "observables": [{ "label", "values": [T] of [re, im] }],
+///                     "measurements": [{ "time", "probabilities" }],
+///                     "diagnostics", "ehrenfest" }] }
+/// ```
+pub fn to_json(result: &ExecutionResult) -> serde_json::Value {
+    let pair = |c: &num_complex::Complex64| [c.re, c.im];
+    let experiments: Vec<serde_json::Value> = result
+        .experiment_results
+        .iter()
+        .map(|experiment| {
+            let states: Vec<Vec<Vec<[f64; 2]>>> = experiment
+                .states
+                .iter()
+                .map(|rho| {
+                    rho.rows()
+                        .into_iter()
+                        .map(|row| row.iter().map(pair).collect())
+                        .collect()
+                })
+                .collect();
+            let observables: Vec<serde_json::Value> = experiment
+                .observables
+                .iter()
+                .map(|series| {
+                    serde_json::json!({
+                        "label": series.label,
+                        "values": series.values.iter().map(pair).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "name": experiment.name,
+                "state_type": experiment.state_type,
+                "times": experiment.times,
+                "states": states,
+                "observables": observables,
+                "measurements": experiment.measurements,
+                "diagnostics": experiment.diagnostics,
+                "ehrenfest": experiment.ehrenfest,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "engine_version": crate::VERSION,
+        "experiments": experiments,
+    })
+}
+
+/// Read a result written by [`export_json`], or serialized directly
+pub fn load_json(path: impl AsRef<Path>) -> Result<ExecutionResult> {
+    let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if value.get("experiments").is_some() {
+        from_json(serde_json::from_value(value)?)
+    } else {
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// The layout [`to_json`] writes
+#[derive(Deserialize)]
+struct JsonExport {
+    experiments: Vec<JsonExperiment>,
+}
+
+#[derive(Deserialize)]
+struct JsonExperiment {
+    name: String,
+    state_type: crate::executor::StateType,
+    times: Vec<f64>,
+    #[serde(default)]
+    states: Vec<Vec<Vec<[f64; 2]>>>,
+    #[serde(default)]
+    observables: Vec<JsonSeries>,
+    #[serde(default)]
+    measurements: Vec<crate::executor::MeasurementResult>,
+    #[serde(default)]
+    diagnostics: Option<crate::executor::DiagnosticsSummary>,
+    #[serde(default)]
+    ehrenfest: Vec<crate::executor::EhrenfestResidual>,
+}
+
+#[derive(Deserialize)]
+struct JsonSeries {
+    label: String,
+    values: Vec<[f64; 2]>,
+}
+
+fn from_json(export: JsonExport) -> Result<ExecutionResult> {
+    use crate::executor::{ExperimentResult, ObservableSeries};
+    use num_complex::Complex64;
+
+    let complex = |[re, im]: [f64; 2]| Complex64::new(re, im);
+    let experiment_results = export
+        .experiments
+        .into_iter()
+        .map(|experiment| {
+            let states = experiment
+                .states
+                .into_iter()
+                .map(|rows| {
+                    let dim = rows.len();
+                    let data: Vec<Complex64> = rows.into_iter().flatten().map(complex).collect();
+                    ndarray::Array2::from_shape_vec((dim, dim), data).map_err(|_| {
+                        EngineError::SerializationError(format!(
+                            "State of experiment '{}' is not a square matrix",
+                            experiment.name
+                        ))
+                    })
+                })
+                .collect::<Result<_>>()?;
+            let observables = experiment
+                .observables
+                .into_iter()
+                .map(|series| ObservableSeries {
+                    label: series.label,
+                    values: series.values.into_iter().map(complex).collect(),
+                })
+                .collect();
+            Ok(ExperimentResult {
+                name: experiment.name,
+                times: experiment.times,
+                state_type: experiment.state_type,
+                measurements: experiment.measurements,
+                states,
+                diagnostics: experiment.diagnostics,
+                ehrenfest: experiment.ehrenfest,
+                observables,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(ExecutionResult { experiment_results })
+}
+
+#[cfg(feature = "hdf5")]
+fn h5_error(e: hdf5::Error) -> EngineError {
+    EngineError::SerializationError(format!("HDF5: {}", e))
+}
+
+#[cfg(feature = "hdf5")]
+fn write_dataset<T, D>(group: &hdf5::Group, name: &str, data: &ndarray::Array<T, D>) -> Result<()>
+where
+    T: hdf5::H5Type,
+    D: ndarray::Dimension,
+{
+    group
+        .new_dataset_builder()
+        .with_data(data)
+        .create(name)
+        .map_err(h5_error)?;
+    Ok(())
+}
+
+#[cfg(feature = "hdf5")]
+fn write_string_attr(location: &hdf5::Location, name: &str, value: &str) -> Result<()> {
+    use hdf5::types::VarLenUnicode;
+
+    let value: VarLenUnicode = value.parse().map_err(|e| {
+        EngineError::SerializationError(format!("HDF5 attribute '{}': {}", name, e))
+    })?;
+    location
+        .new_attr::<VarLenUnicode>()
+        .create(name)
+        .and_then(|attr| attr.write_scalar(&value))
+        .map_err(h5_error)
+}
+
+#[cfg(feature = "hdf5")]
+fn read_string_attr(location: &hdf5::Location, name: &str) -> Result<String> {
+    use hdf5::types::VarLenUnicode;
+
+    let value: VarLenUnicode = location
+        .attr(name)
+        .and_then(|attr| attr.read_scalar())
+        .map_err(h5_error)?;
+    Ok(value.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{
+        DiagnosticsSummary, ExperimentResult, MeasurementResult, ObservableSeries, StateType,
+    };
+    use ndarray::Array2;
+    use num_complex::Complex64;
+
+    fn sample_result() -> ExecutionResult {
+        let rho = |p: f64, coherence: Complex64| {
+            let mut m = Array2::zeros((2, 2));
+            m[[0, 0]] = Complex64::new(p, 0.0);
+            m[[1, 1]] = Complex64::new(1.0 - p, 0.0);
+            m[[0, 1]] = coherence;
+            m[[1, 0]] = coherence.conj();
+            m
+        };
+
+        ExecutionResult {
+            experiment_results: vec![ExperimentResult {
+                name: "rabi/fast".to_string(),
+                times: vec![0.0, 0.5],
+                state_type: StateType::PureState,
+                measurements: vec![
+                    MeasurementResult {
+                        time: 0.0,
+                        probabilities: vec![1.0, 0.0],
+                    },
+                    MeasurementResult {
+                        time: 0.5,
+                        probabilities: vec![0.2, 0.3, 0.5],
+                    },
+                ],
+                states: vec![
+                    rho(1.0, Complex64::new(0.0, 0.0)),
+                    rho(0.5, Complex64::new(0.1, -0.4)),
+                ],
+                diagnostics: Some(DiagnosticsSummary {
+                    max_trace_drift: 1e-12,
+                    min_eigenvalue: 0.0,
+                    warnings: vec![],
+                }),
+                ehrenfest: vec![],
+                observables: vec![ObservableSeries {
+                    label: "sigma_x * sigma_y".to_string(),
+                    values: vec![Complex64::new(0.0, 1.0), Complex64::new(0.0, 0.0)],
+                }],
+            }],
+        }
+    }
+
+    #[cfg(feature = "hdf5")]
+    #[test]
+    fn test_hdf5_round_trip() {
+        let result = sample_result();
+        let path = std::env::temp_dir().join(format!("qte_io_{}.h5", std::process::id()));
+        save_hdf5(&result, &serde_json::json!({ "omega": 1.5 }), &path).unwrap();
+        let stored = load_hdf5(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(stored.metadata["omega"], 1.5);
+        assert_eq!(stored.engine_version, crate::VERSION);
+
+        let original = &result.experiment_results[0];
+        let loaded = &stored.result.experiment_results[0];
+        assert_eq!(loaded.name, original.name);
+        assert_eq!(loaded.times, original.times);
+        assert_eq!(loaded.states, original.states);
+        assert_eq!(loaded.measurements[1].probabilities, vec![0.2, 0.3, 0.5]);
+        assert!(matches!(loaded.state_type, StateType::PureState));
+        assert!(loaded.diagnostics.is_some());
+        assert_eq!(loaded.observables[0].label, "sigma_x * sigma_y");
+        assert_eq!(loaded.observables[0].values, original.observables[0].values);
+    }
+
+    #[test]
+    fn test_csv_rows_are_tidy() {
+        let csv = to_csv(&sample_result());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "experiment,time,observable,value");
+        assert_eq!(lines[1], "rabi/fast,0,rho[0][0],1");
+        // A complex series gets a row for its imaginary part too
+        assert!(lines.contains(&"rabi/fast,0,<sigma_x * sigma_y>,0"));
+        assert!(lines.contains(&"rabi/fast,0,Im<sigma_x * sigma_y>,1"));
+        assert!(lines.contains(&"rabi/fast,0.5,P[1][2],0.5"));
+        // 2 populations and 2 observable rows at each of 2 times, 5 probabilities
+        assert_eq!(lines.len(), 1 + 8 + 5);
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_json_writes_complex_pairs() {
+        let json = to_json(&sample_result());
+        let experiment = &json["experiments"][0];
+        assert_eq!(
+            experiment["states"][1][0][1],
+            serde_json::json!([0.1, -0.4])
+        );
+        assert_eq!(
+            experiment["observables"][0]["values"][0],
+            serde_json::json!([0.0, 1.0])
+        );
+        assert_eq!(experiment["measurements"][1]["probabilities"][2], 0.5);
+        assert_eq!(json["engine_version"], crate::VERSION);
+    }
+
+    #[test]
+    fn test_load_json_reads_both_layouts() {
+        let result = sample_result();
+        let path = std::env::temp_dir().join(format!("qte_io_{}.json", std::process::id()));
+
+        export_json(&result, &path).unwrap();
+        let loaded = load_json(&path).unwrap();
+        let (original, exported) = (&result.experiment_results[0], &loaded.experiment_results[0]);
+        assert_eq!(exported.states, original.states);
+        assert_eq!(exported.observables[0].values, original.observables[0].values);
+        assert_eq!(exported.measurements[1].probabilities, vec![0.2, 0.3, 0.5]);
+
+        std::fs::write(&path, serde_json::to_string(&result).unwrap()).unwrap();
+        let plain = load_json(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(plain.experiment_results[0].times, original.times);
+        assert!(plain.experiment_results[0].states.is_empty());
+    }
+}
@@ -3,9 +3,10 @@
 //! The IR represents the computation as a DAG of kernel operations
 //! that can be executed on different backends (CPU/GPU).
 
+use crate::error::{EngineError, Result};
 use num_complex::Complex64;
-use ndarray::Array2;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// IR node ID
 pub type NodeId = usize;
@@ -15,6 +16,14 @@ pub type NodeId = usize;
 pub struct IrProgram {
     pub nodes: Vec<IrNode>,
     pub experiments: Vec<IrExperiment>,
+    /// Declared Hamiltonians and the nodes computing them, in source order
+    #[serde(default)]
+    pub hamiltonians: Vec<(String, NodeId)>,
+    /// Scalar nodes of `const` declarations, by name, for
+    /// [`IrProgram::substitute`]; constants folded into other values while
+    /// lowering, such as Lindblad rates, are left out
+    #[serde(default)]
+    pub constants: Vec<(String, NodeId)>,
 }
 
 /// Single IR node representing an operation
@@ -41,6 +50,13 @@ pub enum IrNode {
         value: Complex64,
     },
 
+    /// Real scalar supplied by name when the program runs, see
+    /// [`Executor::execute_with_params`](crate::executor::Executor::execute_with_params)
+    Parameter {
+        id: NodeId,
+        name: String,
+    },
+
     /// Matrix addition
     MatrixAdd {
         id: NodeId,
@@ -94,6 +110,23 @@ pub enum IrNode {
         right: NodeId,
     },
 
+    /// Partial trace keeping the subsystems `keep`; with no `dims` the
+    /// input is split into qubits
+    PartialTrace {
+        id: NodeId,
+        input: NodeId,
+        dims: Option<Vec<usize>>,
+        keep: Vec<usize>,
+    },
+
+    /// Eigendecomposition of a Hermitian matrix, of which the node keeps
+    /// the part `output`
+    Eigendecompose {
+        id: NodeId,
+        input: NodeId,
+        output: EigenOutput,
+    },
+
     /// Unitary evolution: U(t) = exp(-iHt)
     UnitaryPropagator {
         id: NodeId,
@@ -115,6 +148,15 @@ pub enum IrNode {
         rho: NodeId,
     },
 
+    /// A gate on some qubits of a ket or density matrix, expanded to the
+    /// whole register: U|ψ⟩ or UρU†; no targets means the whole register
+    ApplyGate {
+        id: NodeId,
+        gate: NodeId,
+        state: NodeId,
+        targets: Vec<usize>,
+    },
+
     /// ODE integration for Lindblad master equation
     IntegrateLindblad {
         id: NodeId,
@@ -124,6 +166,13 @@ pub enum IrNode {
         times: Vec<f64>,
     },
 
+    /// Quantum channel in Kraus form: ρ' = Σ_k K_k ρ K_k†, applied to the
+    /// evolving state by the experiments that schedule it
+    ApplyChannel {
+        id: NodeId,
+        kraus: Vec<NodeId>,
+    },
+
     /// Measure observable expectation: ⟨O⟩ = Tr(Oρ)
     MeasureExpectation {
         id: NodeId,
@@ -137,6 +186,25 @@ pub enum IrNode {
         projectors: Vec<NodeId>,
         state: NodeId,
     },
+
+    /// Call to a plugin kernel registered in a `KernelRegistry`
+    Custom {
+        id: NodeId,
+        kernel: String,
+        inputs: Vec<NodeId>,
+    },
+}
+
+/// Part of an eigendecomposition an `IrNode::Eigendecompose` keeps, with
+/// levels in ascending order of eigenvalue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EigenOutput {
+    /// Vector of the eigenvalues
+    Eigenvalues,
+    /// Unitary whose columns are the eigenvectors
+    Eigenvectors,
+    /// Eigenvector of one level, counting from the lowest
+    Eigenstate(usize),
 }
 
 /// Lindblad operator with rate
@@ -153,6 +221,24 @@ pub struct IrExperiment {
     pub initial_state: NodeId,
     pub evolution: Option<IrEvolution>,
     pub measurements: Vec<IrMeasurement>,
+    /// Experiment whose final state the executor places at `initial_state`
+    /// before running this one; lowering orders experiments so that it has
+    /// already run
+    #[serde(default)]
+    pub initial_from: Option<String>,
+    /// Channels applied to the state, in order, at their time index
+    #[serde(default)]
+    pub channels: Vec<IrChannelApplication>,
+    /// Operators whose expectation values are recorded at every time
+    #[serde(default)]
+    pub observables: Vec<IrObservable>,
+}
+
+/// Operator tracked along an experiment's trajectory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrObservable {
+    pub label: String,
+    pub operator: NodeId,
 }
 
 /// Evolution specification
@@ -174,6 +260,18 @@ pub enum EvolutionMethod {
 pub struct IrMeasurement {
     pub time_index: usize,
     pub measurement_type: MeasurementType,
+    /// Scheduled time when it falls strictly between two grid points, where
+    /// the executor interpolates the state; `time_index` is then the earlier
+    /// of the two
+    #[serde(default)]
+    pub time: Option<f64>,
+}
+
+/// Application of an `IrNode::ApplyChannel` at a point of the time grid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrChannelApplication {
+    pub time_index: usize,
+    pub channel: NodeId,
 }
 
 /// Measurement type
@@ -189,6 +287,8 @@ impl IrProgram {
         IrProgram {
             nodes: Vec::new(),
             experiments: Vec::new(),
+            hamiltonians: Vec::new(),
+            constants: Vec::new(),
         }
     }
 
@@ -197,6 +297,47 @@ impl IrProgram {
         self.nodes.push(node);
         id
     }
+
+    /// Names of the parameters supplied at run time, in program order
+    pub fn parameters(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                IrNode::Parameter { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// This program with the named constants set to new values, so one
+    /// lowered program can run at every point of a sweep
+    ///
+    /// Every scalar node bound to a name in `values` takes its value. A name
+    /// that is not a constant of the program, or was folded into another
+    /// value while lowering, is an error.
+    pub fn substitute(&self, values: &HashMap<String, f64>) -> Result<IrProgram> {
+        let mut nodes: HashMap<NodeId, f64> = HashMap::new();
+        for (name, &value) in values {
+            let mut bound = self.constants.iter().filter(|(constant, _)| constant == name).peekable();
+            if bound.peek().is_none() {
+                return Err(EngineError::validation_error(format!(
+                    "'{}' is not a substitutable constant of the program",
+                    name
+                )));
+            }
+            nodes.extend(bound.map(|&(_, id)| (id, value)));
+        }
+
+        let mut program = self.clone();
+        for node in &mut program.nodes {
+            if let IrNode::Scalar { id, value } = node {
+                if let Some(&new) = nodes.get(id) {
+                    *value = Complex64::new(new, 0.0);
+                }
+            }
+        }
+        Ok(program)
+    }
 }
 
 impl Default for IrProgram {
@@ -211,6 +352,7 @@ impl IrNode {
             IrNode::LoadMatrix { id, .. } => *id,
             IrNode::LoadVector { id, .. } => *id,
             IrNode::Scalar { id, .. } => *id,
+            IrNode::Parameter { id, .. } => *id,
             IrNode::MatrixAdd { id, .. } => *id,
             IrNode::MatrixMul { id, .. } => *id,
             IrNode::ScalarMul { id, .. } => *id,
@@ -219,12 +361,17 @@ impl IrNode {
             IrNode::Dagger { id, .. } => *id,
             IrNode::Trace { id, .. } => *id,
             IrNode::Commutator { id, .. } => *id,
+            IrNode::PartialTrace { id, .. } => *id,
+            IrNode::Eigendecompose { id, .. } => *id,
             IrNode::UnitaryPropagator { id, .. } => *id,
             IrNode::ApplyUnitaryKet { id, .. } => *id,
             IrNode::ApplyUnitaryRho { id, .. } => *id,
+            IrNode::ApplyGate { id, .. } => *id,
             IrNode::IntegrateLindblad { id, .. } => *id,
+            IrNode::ApplyChannel { id, .. } => *id,
             IrNode::MeasureExpectation { id, .. } => *id,
             IrNode::MeasureProjective { id, .. } => *id,
+            IrNode::Custom { id, .. } => *id,
         }
     }
 }
@@ -2,32 +2,284 @@
 //!
 //! Provides asynchronous job execution with priority management,
 //! progress tracking, and resource limits.
+//!
+//! All scheduling decisions (timeouts, retry backoff, priority aging) read
+//! time from a [`Clock`]. The default [`TokioClock`] follows `tokio::time`,
+//! so tests can run under `#[tokio::test(start_paused = true)]` and
+//! fast-forward through them deterministically.
+//!
+//! A running job reports how far it has got through its
+//! [`ProgressReporter`], reachable from anywhere in the job's task with
+//! [`report_progress`]; [`JobQueue::status`] turns the reported fraction into
+//! an estimated time to completion. The default runner hands the reporter
+//! to the executor, so simulations report the time steps taken and sweeps
+//! the points completed.
+//!
+//! Each job's lifecycle is stamped on the same clock: when it was
+//! submitted, when its final attempt started and when it finished. Results
+//! and [`JobDetails`] report these as UTC timestamps, anchored to the wall
+//! clock when the queue was created, and split the elapsed time into the
+//! wait before the job ran and the run itself. Run times feed the
+//! `job_wait` and `job_run.<kind>` metrics in [`crate::logging`], and the
+//! mean run time of each kind estimates the time left for jobs that report
+//! no progress.
+//!
+//! Workers always claim the queued job of highest effective priority that
+//! its priority's concurrency limit ([`QueueOptions::max_running`]) lets
+//! run. When every worker is busy, a newly queued `Critical` job preempts
+//! the running job of lowest priority, which goes back to the queue to
+//! start again later; a job preempted [`QueueOptions::max_preemptions`]
+//! times is left to finish, so preemption cannot starve it.
+//!
+//! A job can list other jobs in [`Job::depends_on`]; it stays queued until
+//! they have all completed. When one fails or is cancelled the job's
+//! [`DependencyFailure`] policy either cancels it, and in turn everything
+//! downstream of it, or lets it run on the dependencies that succeeded.
+//! [`JobQueue::submit_workflow`] submits a set of such jobs in dependency
+//! order.
+//!
+//! Operators can [`drain`](JobQueue::drain) the queue, so that workers
+//! finish their running jobs but start no new ones, and
+//! [`retry`](JobQueue::retry) jobs that failed or were cancelled.
+//!
+//! Each run of a job gets a [`CancellationToken`], reachable from its task
+//! with [`current_cancellation`] and passed by the default runner to the
+//! executor, which checks it every time step. [`JobQueue::cancel`] cancels
+//! the token of a running job and frees its worker straight away; the
+//! token is also cancelled when a run is preempted or times out, so work
+//! moved off the task, e.g. with `spawn_blocking`, stops too.
 
-use crate::ast::Ast;
+pub use crate::cancellation::CancellationToken;
 use crate::error::{EngineError, Result};
-use crate::executor::{ExecutionResult, Executor, BackendConfig};
-use crate::ir::IrProgram;
-use crate::stats::MeasurementData;
+use crate::executor::BackendConfig;
+#[cfg(feature = "parser")]
+use crate::executor::{ExecutionResult, Executor};
+pub use crate::executor::DiagnosticsSummary;
+pub use crate::parameter_grid::{
+    ConfidenceEllipsoid, EllipsoidSampling, GridStrategy, ParameterGrid, ParameterRange,
+    ParameterScale,
+};
+pub use crate::progress::ProgressReporter;
+#[cfg(feature = "provenance")]
+use crate::provenance::{ProvenanceGraph, ProvenanceRecord};
+use crate::resources::{ResourceEstimate, ResourceLimits};
+use crate::stats::FitResult;
 use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, oneshot};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Notify};
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use uuid::Uuid;
 
 /// Job queue manager
 pub struct JobQueue {
     sender: mpsc::UnboundedSender<JobCommand>,
     state: Arc<Mutex<QueueState>>,
+    clock: Arc<dyn Clock>,
     _workers: Vec<JoinHandle<()>>,
 }
 
 struct QueueState {
     jobs: HashMap<JobId, JobInfo>,
     queue: VecDeque<JobId>,
-    running: HashMap<JobId, Instant>,
+    running: HashMap<JobId, RunningJob>,
     results: HashMap<JobId, JobResult>,
+    /// Jobs that failed on their last allowed attempt: (error, retries)
+    failures: HashMap<JobId, (String, usize)>,
+    /// Jobs cancelled before they finished
+    cancelled: HashSet<JobId>,
+    /// Workers start no new jobs while set
+    draining: bool,
+    /// Number of workers, and how many of them are running a job
+    workers: usize,
+    busy: usize,
+    /// Wall-clock time of a reference instant, to timestamp the others
+    epoch: (Instant, chrono::DateTime<chrono::Utc>),
+    /// Total run time and number of completed jobs, by job kind
+    run_times: HashMap<&'static str, (Duration, u32)>,
+}
+
+impl QueueState {
+    fn new(now: Instant) -> Self {
+        QueueState {
+            jobs: HashMap::new(),
+            queue: VecDeque::new(),
+            running: HashMap::new(),
+            results: HashMap::new(),
+            failures: HashMap::new(),
+            cancelled: HashSet::new(),
+            draining: false,
+            workers: 0,
+            busy: 0,
+            epoch: (now, chrono::Utc::now()),
+            run_times: HashMap::new(),
+        }
+    }
+
+    /// Wall-clock time of `at`
+    fn timestamp(&self, at: Instant) -> chrono::DateTime<chrono::Utc> {
+        let (instant, utc) = self.epoch;
+        let offset = if at >= instant {
+            chrono::Duration::from_std(at - instant)
+        } else {
+            chrono::Duration::from_std(instant - at).map(|d| -d)
+        };
+        utc + offset.unwrap_or_else(|_| chrono::Duration::zero())
+    }
+
+    /// Mean run time of completed jobs of `kind`
+    fn mean_run_time(&self, kind: &str) -> Option<Duration> {
+        self.run_times
+            .get(kind)
+            .filter(|(_, count)| *count > 0)
+            .map(|(total, count)| *total / *count)
+    }
+}
+
+struct RunningJob {
+    started_at: Instant,
+    progress: ProgressReporter,
+    /// Notified to stop the job so a higher-priority one can run, or
+    /// because it was cancelled
+    preempt: Arc<Notify>,
+    preempted: bool,
+    cancel: CancellationToken,
+}
+
+impl RunningJob {
+    /// Reported progress, and the time left if the job keeps its average
+    /// rate since it started. Before any progress is reported the estimate
+    /// is what remains of `expected`, the usual run time of such jobs.
+    fn status(&self, now: Instant, expected: Option<Duration>) -> JobStatus {
+        let progress = self.progress.fraction();
+        let elapsed = now.saturating_duration_since(self.started_at);
+        let eta = if progress > 0.0 {
+            Duration::try_from_secs_f64(elapsed.as_secs_f64() * (1.0 - progress) / progress).ok()
+        } else {
+            expected.map(|expected| expected.saturating_sub(elapsed))
+        };
+        JobStatus::Running { progress, eta }
+    }
+}
+
+tokio::task_local! {
+    static PROGRESS: ProgressReporter;
+    static CANCELLATION: CancellationToken;
+}
+
+/// Reporter of the queue job running on the current task, if any
+pub fn current_progress() -> Option<ProgressReporter> {
+    PROGRESS.try_with(ProgressReporter::clone).ok()
+}
+
+/// Cancellation token of the queue job running on the current task, if any
+pub fn current_cancellation() -> Option<CancellationToken> {
+    CANCELLATION.try_with(CancellationToken::clone).ok()
+}
+
+/// Report progress of the queue job running on the current task; does
+/// nothing outside a job
+pub fn report_progress(fraction: f64) {
+    if let Some(progress) = current_progress() {
+        progress.report(fraction);
+    }
+}
+
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// Time source for the queue
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration) -> BoxFuture<()>;
+}
+
+/// [`Clock`] backed by `tokio::time`, which honours `tokio::time::pause`
+/// and `tokio::time::advance`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Computation behind a job. Any `Fn(Job) -> impl Future<Output =
+/// Result<JobOutput>>` is a runner; the default runs the engine pipeline.
+/// The future runs with the job's [`ProgressReporter`] and
+/// [`CancellationToken`] in scope, so it can call [`report_progress`] and
+/// [`current_cancellation`].
+pub trait JobRunner: Send + Sync {
+    fn run(&self, job: Job) -> BoxFuture<Result<JobOutput>>;
+}
+
+impl<F, Fut> JobRunner for F
+where
+    F: Fn(Job) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<JobOutput>> + Send + 'static,
+{
+    fn run(&self, job: Job) -> BoxFuture<Result<JobOutput>> {
+        Box::pin(self(job))
+    }
+}
+
+/// Scheduling policy, time source and job runner of a [`JobQueue`]
+#[derive(Clone)]
+pub struct QueueOptions {
+    /// Each full interval a job waits raises its priority by one level, up
+    /// to `Critical`, so low-priority work cannot starve; `None` disables
+    /// aging
+    pub aging_interval: Option<Duration>,
+    /// Most jobs of each priority that may run at once, counted by the
+    /// priority they were submitted with; priorities without an entry are
+    /// unlimited
+    pub max_running: HashMap<Priority, usize>,
+    /// Most jobs of each owner ([`JobConfig::owner`]) that may run at once;
+    /// owners without an entry, and jobs without an owner, are unlimited
+    pub max_running_per_owner: HashMap<String, usize>,
+    /// Whether a queued `Critical` job preempts a lower-priority running
+    /// job when every worker is busy
+    pub preemption: bool,
+    /// Preemptions after which a job is left to run to completion
+    pub max_preemptions: usize,
+    /// Delay before the first retry of a failed job, doubled on each retry
+    pub retry_backoff: Duration,
+    /// How long an idle worker sleeps before looking for work again
+    pub poll_interval: Duration,
+    pub clock: Arc<dyn Clock>,
+    pub runner: Arc<dyn JobRunner>,
+    /// Graph each completed job's result is recorded in, keyed by job id
+    #[cfg(feature = "provenance")]
+    pub provenance: Option<Arc<Mutex<ProvenanceGraph>>>,
+}
+
+impl Default for QueueOptions {
+    fn default() -> Self {
+        Self {
+            aging_interval: Some(Duration::from_secs(60)),
+            max_running: HashMap::new(),
+            max_running_per_owner: HashMap::new(),
+            preemption: true,
+            max_preemptions: 3,
+            retry_backoff: Duration::from_secs(1),
+            poll_interval: Duration::from_millis(100),
+            clock: Arc::new(TokioClock),
+            runner: Arc::new(|job: Job| async move {
+                let cancel = current_cancellation().unwrap_or_default();
+                execute_job(&job, &cancel).await
+            }),
+            #[cfg(feature = "provenance")]
+            provenance: None,
+        }
+    }
 }
 
 pub type JobId = Uuid;
@@ -39,6 +291,9 @@ pub struct Job {
     pub priority: Priority,
     pub params: HashMap<String, f64>,
     pub config: JobConfig,
+    /// Jobs that must complete before this one may start
+    #[serde(default)]
+    pub depends_on: Vec<JobId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,7 +305,7 @@ pub enum JobKind {
     Sweep { base_job: Box<Job>, param_grid: ParameterGrid },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Priority {
     Low = 0,
     Normal = 1,
@@ -63,6 +318,40 @@ pub struct JobConfig {
     pub timeout: Duration,
     pub max_retries: usize,
     pub backend: BackendConfig,
+    /// What happens to the job when one of its dependencies fails
+    #[serde(default)]
+    pub on_dependency_failure: DependencyFailure,
+    /// Client the job was submitted for, whose
+    /// [`QueueOptions::max_running_per_owner`] quota it counts against
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Largest Hilbert-space dimension the job may handle
+    #[serde(default)]
+    pub max_dim: Option<usize>,
+    /// Memory the job may take, in MB, over all the points of a sweep
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    /// Time steps each run of the program may integrate
+    #[serde(default)]
+    pub max_steps: Option<usize>,
+}
+
+impl JobConfig {
+    /// Fail fast if `runs` runs of a program with resource needs `estimate`
+    /// would exceed the job's limits
+    pub fn check_resources(&self, estimate: &ResourceEstimate, runs: usize) -> Result<()> {
+        ResourceLimits {
+            max_dim: self.max_dim,
+            max_memory_mb: None,
+            max_steps: self.max_steps,
+        }
+        .check(estimate)?;
+        ResourceLimits {
+            max_memory_mb: self.max_memory_mb,
+            ..ResourceLimits::default()
+        }
+        .check(&estimate.times(runs))
+    }
 }
 
 impl Default for JobConfig {
@@ -71,53 +360,89 @@ impl Default for JobConfig {
             timeout: Duration::from_secs(300),
             max_retries: 3,
             backend: BackendConfig::default(),
+            on_dependency_failure: DependencyFailure::default(),
+            owner: None,
+            max_dim: None,
+            max_memory_mb: None,
+            max_steps: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ParameterGrid {
-    pub params: Vec<ParameterRange>,
-    pub strategy: GridStrategy,
+/// Response of a job to a dependency that failed or was cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DependencyFailure {
+    /// Cancel the job, and with it every job downstream of it
+    #[default]
+    Cancel,
+    /// Skip the failed dependency: run once the others have completed
+    Skip,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ParameterRange {
-    pub name: String,
-    pub start: f64,
-    pub end: f64,
-    pub steps: usize,
-    pub scale: ParameterScale,
+pub enum JobStatus {
+    Queued { position: usize },
+    Running { progress: f64, eta: Option<Duration> },
+    Complete(JobResult),
+    Failed { error: String, retry_count: usize },
+    Cancelled,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ParameterScale {
-    Linear,
-    Log,
-    Custom(Vec<f64>),
+impl JobStatus {
+    /// `queued`, `running`, `complete`, `failed` or `cancelled`
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Queued { .. } => "queued",
+            JobStatus::Running { .. } => "running",
+            JobStatus::Complete(_) => "complete",
+            JobStatus::Failed { .. } => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum GridStrategy {
-    FullGrid,     // Cartesian product
-    Random(usize), // Random sampling
-    Sobol(usize),  // Quasi-random Sobol sequence
+impl JobKind {
+    /// `simulate`, `prove`, `fit`, `test` or `sweep`
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobKind::Simulate { .. } => "simulate",
+            JobKind::Prove { .. } => "prove",
+            JobKind::Fit { .. } => "fit",
+            JobKind::Test { .. } => "test",
+            JobKind::Sweep { .. } => "sweep",
+        }
+    }
 }
 
+/// A job as submitted, with its current status
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum JobStatus {
-    Queued { position: usize },
-    Running { progress: f64, eta: Option<Duration> },
-    Complete(JobResult),
-    Failed { error: String, retry_count: usize },
-    Cancelled,
+pub struct JobDetails {
+    pub job: Job,
+    pub status: JobStatus,
+    /// Failed attempts retried so far
+    pub retry_count: usize,
+    pub submitted_at: chrono::DateTime<chrono::Utc>,
+    /// Start of the latest attempt
+    #[serde(default)]
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// End of the latest attempt
+    #[serde(default)]
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobResult {
     pub job_id: JobId,
+    #[serde(default)]
+    pub submitted_at: chrono::DateTime<chrono::Utc>,
+    /// Start of the attempt that produced the output
     pub started_at: chrono::DateTime<chrono::Utc>,
     pub completed_at: chrono::DateTime<chrono::Utc>,
+    /// Time from submission to `started_at`, including earlier failed
+    /// attempts and their retry backoff
+    #[serde(default)]
+    pub wait: Duration,
+    /// Run time, from `started_at` to `completed_at`
     pub duration: Duration,
     pub output: JobOutput,
 }
@@ -149,6 +474,20 @@ pub struct FitOutput {
     pub best_params: Vec<f64>,
     pub uncertainties: Vec<f64>,
     pub log_likelihood: f64,
+    /// Best-so-far result from a fit cut short by the job timeout
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl From<FitResult> for FitOutput {
+    fn from(fit: FitResult) -> Self {
+        FitOutput {
+            best_params: fit.best_params,
+            uncertainties: fit.uncertainties,
+            log_likelihood: fit.log_likelihood,
+            truncated: fit.truncated,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -165,13 +504,6 @@ pub struct SweepOutput {
     pub summary: SweepSummary,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DiagnosticsSummary {
-    pub max_trace_drift: f64,
-    pub min_eigenvalue: f64,
-    pub warnings: Vec<String>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SweepSummary {
     pub total_runs: usize,
@@ -180,16 +512,215 @@ pub struct SweepSummary {
     pub total_duration: Duration,
 }
 
+/// A sweep as a table: one row per run, with the objective to minimise
+///
+/// The objective is the negated log-likelihood of a fit and the χ² of a
+/// test; runs with neither, such as plain simulations, have NaN. Columns
+/// follow `names`, sorted, and parameters a run did not set are NaN.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepTable {
+    pub names: Vec<String>,
+    pub points: Vec<Vec<f64>>,
+    pub objective: Vec<f64>,
+}
+
+/// Profile of a sweep's objective over one or two of its parameters: the
+/// smallest objective among the runs at each value, minimising over the
+/// other parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepProfile {
+    pub names: Vec<String>,
+    /// Distinct values of each profiled parameter, in increasing order
+    pub axes: Vec<Vec<f64>>,
+    /// Row-major over `axes`; NaN where no run has a finite objective
+    pub values: Vec<f64>,
+}
+
+impl SweepOutput {
+    pub fn table(&self) -> SweepTable {
+        let mut names: Vec<String> = self
+            .results
+            .iter()
+            .flat_map(|(params, _)| params.keys().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let points = self
+            .results
+            .iter()
+            .map(|(params, _)| {
+                names
+                    .iter()
+                    .map(|name| params.get(name).copied().unwrap_or(f64::NAN))
+                    .collect()
+            })
+            .collect();
+        let objective = self
+            .results
+            .iter()
+            .map(|(_, result)| match &result.output {
+                JobOutput::Fit(fit) => -fit.log_likelihood,
+                JobOutput::Test(test) => test.chi_square,
+                _ => f64::NAN,
+            })
+            .collect();
+        SweepTable {
+            names,
+            points,
+            objective,
+        }
+    }
+}
+
+impl SweepTable {
+    /// Row of the smallest finite objective
+    pub fn argmin(&self) -> Option<usize> {
+        self.objective
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.is_finite())
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(row, _)| row)
+    }
+
+    /// Rows whose objective is no larger than that of any neighbour on the
+    /// grid, the runs one step away along a single parameter
+    ///
+    /// Neighbours are found among the distinct values each parameter
+    /// takes, so this suits full grids; on random or Sobol samples hardly
+    /// any run has neighbours and nearly every one is a minimum.
+    pub fn local_minima(&self) -> Vec<usize> {
+        let axes: Vec<Vec<f64>> = (0..self.names.len()).map(|k| self.axis(k)).collect();
+        let index_of = |point: &[f64]| -> Option<Vec<usize>> {
+            point
+                .iter()
+                .zip(&axes)
+                .map(|(x, axis)| axis.iter().position(|v| v == x))
+                .collect()
+        };
+        let mut by_index = HashMap::new();
+        for (row, point) in self.points.iter().enumerate() {
+            if let Some(index) = index_of(point) {
+                by_index.insert(index, row);
+            }
+        }
+
+        let mut minima: Vec<usize> = by_index
+            .iter()
+            .filter(|(index, &row)| {
+                let value = self.objective[row];
+                value.is_finite()
+                    && (0..index.len()).all(|k| {
+                        [index[k].checked_sub(1), Some(index[k] + 1)]
+                            .into_iter()
+                            .flatten()
+                            .all(|step| {
+                                let mut neighbour = (*index).clone();
+                                neighbour[k] = step;
+                                by_index.get(&neighbour).is_none_or(|&other| {
+                                    self.objective[other].is_nan() || self.objective[other] >= value
+                                })
+                            })
+                    })
+            })
+            .map(|(_, &row)| row)
+            .collect();
+        minima.sort_by(|&a, &b| self.objective[a].total_cmp(&self.objective[b]));
+        minima
+    }
+
+    /// Profile of the objective over one or two parameters, see
+    /// [`SweepProfile`]
+    pub fn profile(&self, names: &[&str]) -> Result<SweepProfile> {
+        if names.is_empty() || names.len() > 2 {
+            return Err(EngineError::validation_error(format!(
+                "A profile is over one or two parameters, not {}",
+                names.len()
+            )));
+        }
+        let columns = names
+            .iter()
+            .map(|name| {
+                self.names.iter().position(|n| n == name).ok_or_else(|| {
+                    EngineError::validation_error(format!(
+                        "Parameter '{}' is not part of the sweep",
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let axes: Vec<Vec<f64>> = columns.iter().map(|&k| self.axis(k)).collect();
+
+        let size = axes.iter().map(Vec::len).product();
+        let mut values = vec![f64::NAN; size];
+        for (point, &objective) in self.points.iter().zip(&self.objective) {
+            if !objective.is_finite() {
+                continue;
+            }
+            let Some(offset) = columns.iter().zip(&axes).try_fold(0, |offset, (&k, axis)| {
+                Some(offset * axis.len() + axis.iter().position(|v| *v == point[k])?)
+            }) else {
+                continue;
+            };
+            if values[offset].is_nan() || objective < values[offset] {
+                values[offset] = objective;
+            }
+        }
+
+        Ok(SweepProfile {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            axes,
+            values,
+        })
+    }
+
+    /// Distinct values of column `k`, in increasing order, NaN left out
+    fn axis(&self, k: usize) -> Vec<f64> {
+        let mut values: Vec<f64> = self
+            .points
+            .iter()
+            .map(|point| point[k])
+            .filter(|x| !x.is_nan())
+            .collect();
+        values.sort_by(f64::total_cmp);
+        values.dedup();
+        values
+    }
+}
+
+#[derive(Clone)]
 struct JobInfo {
     job: Job,
     submitted_at: Instant,
     retry_count: usize,
+    /// Times the job was stopped to make way for a higher-priority one
+    preemptions: usize,
+    /// Earliest time a retry may start
+    not_before: Option<Instant>,
+    /// Start and end of the latest attempt
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+}
+
+impl JobInfo {
+    fn new(job: Job, submitted_at: Instant) -> Self {
+        JobInfo {
+            job,
+            submitted_at,
+            retry_count: 0,
+            preemptions: 0,
+            not_before: None,
+            started_at: None,
+            finished_at: None,
+        }
+    }
 }
 
 enum JobCommand {
     Submit {
-        job: Job,
-        response: oneshot::Sender<JobId>,
+        job: Box<Job>,
+        response: oneshot::Sender<Result<JobId>>,
     },
     Cancel {
         job_id: JobId,
@@ -206,36 +737,53 @@ enum JobCommand {
     ListJobs {
         response: oneshot::Sender<Vec<(JobId, JobStatus)>>,
     },
+    GetDetails {
+        job_id: Option<JobId>,
+        response: oneshot::Sender<Vec<JobDetails>>,
+    },
+    Retry {
+        job_id: JobId,
+        response: oneshot::Sender<Result<()>>,
+    },
+    SetDraining {
+        draining: bool,
+        response: oneshot::Sender<()>,
+    },
 }
 
 impl JobQueue {
     pub fn new(num_workers: usize) -> Self {
+        Self::with_options(num_workers, QueueOptions::default())
+    }
+
+    /// Create a queue with a custom scheduling policy, clock or runner
+    pub fn with_options(num_workers: usize, options: QueueOptions) -> Self {
         let (sender, receiver) = mpsc::unbounded_channel();
-        let state = Arc::new(Mutex::new(QueueState {
-            jobs: HashMap::new(),
-            queue: VecDeque::new(),
-            running: HashMap::new(),
-            results: HashMap::new(),
-        }));
+        let mut queue_state = QueueState::new(options.clock.now());
+        queue_state.workers = num_workers;
+        let state = Arc::new(Mutex::new(queue_state));
 
         let workers = (0..num_workers)
             .map(|id| {
                 let state = Arc::clone(&state);
+                let options = options.clone();
                 tokio::spawn(async move {
-                    worker_loop(id, state).await;
+                    worker_loop(id, state, options).await;
                 })
             })
             .collect();
 
         // Spawn command processor
         let cmd_state = Arc::clone(&state);
+        let cmd_options = options.clone();
         tokio::spawn(async move {
-            command_processor(receiver, cmd_state).await;
+            command_processor(receiver, cmd_state, cmd_options).await;
         });
 
         Self {
             sender,
             state,
+            clock: options.clock,
             _workers: workers,
         }
     }
@@ -243,15 +791,63 @@ impl JobQueue {
     pub async fn submit(&self, job: Job) -> Result<JobId> {
         let (tx, rx) = oneshot::channel();
         self.sender
-            .send(JobCommand::Submit { job, response: tx })
+            .send(JobCommand::Submit { job: Box::new(job), response: tx })
             .map_err(|_| EngineError::Internal("Job queue closed".to_string()))?;
         
         rx.await
-            .map_err(|_| EngineError::Internal("Failed to receive job ID".to_string()))
+            .map_err(|_| EngineError::Internal("Failed to receive job ID".to_string()))?
+    }
+
+    /// Submit jobs that depend on one another, each after its dependencies
+    ///
+    /// Dependencies may be other jobs in `jobs` or jobs already submitted.
+    /// Nothing is submitted if one is neither, or if the dependencies form
+    /// a cycle. Returns the job ids in the order of `jobs`.
+    pub async fn submit_workflow(&self, jobs: Vec<Job>) -> Result<Vec<JobId>> {
+        let job_ids: Vec<JobId> = jobs.iter().map(|job| job.id).collect();
+        let in_workflow: HashSet<JobId> = job_ids.iter().copied().collect();
+        {
+            let state = self.state.lock().unwrap();
+            for job in &jobs {
+                if let Some(dep) = job.depends_on.iter()
+                    .find(|dep| !in_workflow.contains(dep) && !state.jobs.contains_key(dep))
+                {
+                    return Err(EngineError::NotFound(format!(
+                        "Job {} depends on unknown job {}",
+                        job.id, dep
+                    )));
+                }
+            }
+        }
+
+        // Kahn's algorithm over the dependencies within the workflow
+        let mut pending: Vec<Option<Job>> = jobs.into_iter().map(Some).collect();
+        let mut submitted: HashSet<JobId> = HashSet::new();
+        while submitted.len() < pending.len() {
+            let ready: Vec<usize> = (0..pending.len())
+                .filter(|&i| {
+                    pending[i].as_ref().is_some_and(|job| {
+                        job.depends_on.iter().all(|dep| !in_workflow.contains(dep) || submitted.contains(dep))
+                    })
+                })
+                .collect();
+            if ready.is_empty() {
+                return Err(EngineError::validation_error(
+                    "Workflow dependencies form a cycle".to_string(),
+                ));
+            }
+            for i in ready {
+                let job = pending[i].take().unwrap();
+                submitted.insert(job.id);
+                self.submit(job).await?;
+            }
+        }
+
+        Ok(job_ids)
     }
 
     pub async fn submit_sweep(&self, base_job: Job, grid: ParameterGrid) -> Result<Vec<JobId>> {
-        let param_combinations = generate_parameter_combinations(&grid);
+        let param_combinations = grid.points()?;
         let mut job_ids = Vec::new();
 
         for params in param_combinations {
@@ -303,11 +899,64 @@ impl JobQueue {
         rx.await.unwrap_or_default()
     }
 
+    /// Job, status and retry count of `job_id`
+    pub async fn details(&self, job_id: JobId) -> Option<JobDetails> {
+        self.query_details(Some(job_id)).await.pop()
+    }
+
+    /// Details of every job the queue knows, in submission order
+    pub async fn all_details(&self) -> Vec<JobDetails> {
+        self.query_details(None).await
+    }
+
+    async fn query_details(&self, job_id: Option<JobId>) -> Vec<JobDetails> {
+        let (tx, rx) = oneshot::channel();
+        if self.sender.send(JobCommand::GetDetails { job_id, response: tx }).is_err() {
+            return vec![];
+        }
+
+        rx.await.unwrap_or_default()
+    }
+
+    /// Queue a failed or cancelled job again, with its retry count reset
+    pub async fn retry(&self, job_id: JobId) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(JobCommand::Retry { job_id, response: tx })
+            .map_err(|_| EngineError::Internal("Job queue closed".to_string()))?;
+
+        rx.await
+            .map_err(|_| EngineError::Internal("Failed to receive retry response".to_string()))?
+    }
+
+    /// Let running jobs finish but start no new ones until [`resume`](Self::resume)
+    pub async fn drain(&self) -> Result<()> {
+        self.set_draining(true).await
+    }
+
+    pub async fn resume(&self) -> Result<()> {
+        self.set_draining(false).await
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.state.lock().unwrap().draining
+    }
+
+    async fn set_draining(&self, draining: bool) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(JobCommand::SetDraining { draining, response: tx })
+            .map_err(|_| EngineError::Internal("Job queue closed".to_string()))?;
+
+        rx.await
+            .map_err(|_| EngineError::Internal("Failed to receive drain response".to_string()))
+    }
+
     pub async fn aggregate_sweep(&self, job_ids: &[JobId]) -> Result<SweepOutput> {
         let mut results = Vec::new();
         let mut successful = 0;
         let mut failed = 0;
-        let start = Instant::now();
+        let start = self.clock.now();
 
         for job_id in job_ids {
             if let Some(result) = self.get_result(*job_id).await {
@@ -343,7 +992,7 @@ impl JobQueue {
                 total_runs: job_ids.len(),
                 successful,
                 failed,
-                total_duration: start.elapsed(),
+                total_duration: self.clock.now() - start,
             },
         })
     }
@@ -352,56 +1001,58 @@ impl JobQueue {
 async fn command_processor(
     mut receiver: mpsc::UnboundedReceiver<JobCommand>,
     state: Arc<Mutex<QueueState>>,
+    options: QueueOptions,
 ) {
+    let clock = &options.clock;
     while let Some(cmd) = receiver.recv().await {
         match cmd {
             JobCommand::Submit { job, response } => {
+                let job = *job;
                 let job_id = job.id;
                 let mut state = state.lock().unwrap();
                 
-                state.jobs.insert(job_id, JobInfo {
-                    job: job.clone(),
-                    submitted_at: Instant::now(),
-                    retry_count: 0,
-                });
-                
-                // Insert into priority queue
-                let insert_pos = state.queue.iter()
-                    .position(|id| {
-                        state.jobs.get(id).map(|j| j.job.priority).unwrap_or(Priority::Low)
-                            < job.priority
-                    })
-                    .unwrap_or(state.queue.len());
-                
-                state.queue.insert(insert_pos, job_id);
+                let unknown = job.depends_on.iter().find(|dep| !state.jobs.contains_key(dep)).copied();
+                let result = match unknown {
+                    Some(dep) => Err(EngineError::NotFound(format!(
+                        "Job {} depends on unknown job {}",
+                        job_id, dep
+                    ))),
+                    None => {
+                        state.jobs.insert(job_id, JobInfo::new(job, clock.now()));
+                        schedule(&mut state, job_id, clock.now(), &options);
+                        Ok(job_id)
+                    }
+                };
                 
-                let _ = response.send(job_id);
+                let _ = response.send(result);
             }
 
             JobCommand::Cancel { job_id, response } => {
                 let mut state = state.lock().unwrap();
                 
                 // Remove from queue if not running
+                let queued = state.queue.contains(&job_id);
                 state.queue.retain(|id| *id != job_id);
-                state.running.remove(&job_id);
+                let running = state.running.remove(&job_id);
+                // Stop the run and free its worker
+                if let Some(running) = &running {
+                    running.cancel.cancel();
+                    running.preempt.notify_one();
+                }
+                if running.is_some() || queued {
+                    state.cancelled.insert(job_id);
+                    if let Some(info) = state.jobs.get_mut(&job_id) {
+                        info.finished_at = Some(clock.now());
+                    }
+                    cancel_dependents(&mut state, job_id, clock.now());
+                }
                 
                 let _ = response.send(Ok(()));
             }
 
             JobCommand::GetStatus { job_id, response } => {
                 let state = state.lock().unwrap();
-                
-                let status = if let Some(result) = state.results.get(&job_id) {
-                    Some(JobStatus::Complete(result.clone()))
-                } else if state.running.contains_key(&job_id) {
-                    Some(JobStatus::Running { progress: 0.5, eta: None })
-                } else if let Some(pos) = state.queue.iter().position(|id| *id == job_id) {
-                    Some(JobStatus::Queued { position: pos })
-                } else {
-                    None
-                };
-                
-                let _ = response.send(status);
+                let _ = response.send(current_status(&state, job_id, clock.now()));
             }
 
             JobCommand::GetResult { job_id, response } => {
@@ -411,101 +1062,454 @@ async fn command_processor(
 
             JobCommand::ListJobs { response } => {
                 let state = state.lock().unwrap();
+                let now = clock.now();
                 let jobs: Vec<_> = state.jobs.keys()
-                    .filter_map(|id| {
-                        let status = if state.results.contains_key(id) {
-                            JobStatus::Complete(state.results[id].clone())
-                        } else if state.running.contains_key(id) {
-                            JobStatus::Running { progress: 0.5, eta: None }
-                        } else {
-                            JobStatus::Queued { 
-                                position: state.queue.iter().position(|qid| qid == id).unwrap_or(0) 
-                            }
-                        };
-                        Some((*id, status))
-                    })
+                    .filter_map(|id| Some((*id, current_status(&state, *id, now)?)))
                     .collect();
                 
                 let _ = response.send(jobs);
             }
+
+            JobCommand::GetDetails { job_id, response } => {
+                let state = state.lock().unwrap();
+                let now = clock.now();
+                let mut infos: Vec<&JobInfo> = match job_id {
+                    Some(id) => state.jobs.get(&id).into_iter().collect(),
+                    None => state.jobs.values().collect(),
+                };
+                infos.sort_by_key(|info| info.submitted_at);
+                let details = infos
+                    .into_iter()
+                    .filter_map(|info| {
+                        Some(JobDetails {
+                            job: info.job.clone(),
+                            status: current_status(&state, info.job.id, now)?,
+                            retry_count: info.retry_count,
+                            submitted_at: state.timestamp(info.submitted_at),
+                            started_at: info.started_at.map(|t| state.timestamp(t)),
+                            finished_at: info.finished_at.map(|t| state.timestamp(t)),
+                        })
+                    })
+                    .collect();
+
+                let _ = response.send(details);
+            }
+
+            JobCommand::Retry { job_id, response } => {
+                let mut state = state.lock().unwrap();
+                let now = clock.now();
+                let result = match current_status(&state, job_id, now) {
+                    None => Err(EngineError::NotFound(format!("Job not found: {}", job_id))),
+                    Some(JobStatus::Failed { .. } | JobStatus::Cancelled) => {
+                        state.failures.remove(&job_id);
+                        state.cancelled.remove(&job_id);
+                        // A retried job starts its lifecycle afresh
+                        if let Some(info) = state.jobs.get_mut(&job_id) {
+                            *info = JobInfo::new(info.job.clone(), now);
+                        }
+                        schedule(&mut state, job_id, now, &options);
+                        Ok(())
+                    }
+                    Some(status) => Err(EngineError::validation_error(format!(
+                        "Job {} is {}; only failed or cancelled jobs can be retried",
+                        job_id,
+                        status.label()
+                    ))),
+                };
+
+                let _ = response.send(result);
+            }
+
+            JobCommand::SetDraining { draining, response } => {
+                state.lock().unwrap().draining = draining;
+                let _ = response.send(());
+            }
+        }
+    }
+}
+
+/// Status of `job_id`, or `None` for a job the queue never saw
+fn current_status(state: &QueueState, job_id: JobId, now: Instant) -> Option<JobStatus> {
+    if let Some(result) = state.results.get(&job_id) {
+        Some(JobStatus::Complete(result.clone()))
+    } else if let Some((error, retry_count)) = state.failures.get(&job_id) {
+        Some(JobStatus::Failed { error: error.clone(), retry_count: *retry_count })
+    } else if state.cancelled.contains(&job_id) {
+        Some(JobStatus::Cancelled)
+    } else if let Some(running) = state.running.get(&job_id) {
+        let expected = state.jobs.get(&job_id).and_then(|info| state.mean_run_time(info.job.kind.label()));
+        Some(running.status(now, expected))
+    } else {
+        state.queue.iter()
+            .position(|id| *id == job_id)
+            .map(|position| JobStatus::Queued { position })
+    }
+}
+
+/// Insert a job into the queue behind every job of equal or higher priority
+fn enqueue(state: &mut QueueState, job_id: JobId) {
+    let priority = state.jobs[&job_id].job.priority;
+    let insert_pos = state.queue.iter()
+        .position(|id| {
+            state.jobs.get(id).map(|j| j.job.priority).unwrap_or(Priority::Low) < priority
+        })
+        .unwrap_or(state.queue.len());
+
+    state.queue.insert(insert_pos, job_id);
+}
+
+/// Queue a newly submitted or retried job, or cancel it straight away if
+/// a dependency has already failed and its policy is to cancel
+fn schedule(state: &mut QueueState, job_id: JobId, now: Instant, options: &QueueOptions) {
+    enqueue(state, job_id);
+    let info = &state.jobs[&job_id];
+    if info.job.config.on_dependency_failure == DependencyFailure::Cancel
+        && info.job.depends_on.iter().any(|dep| has_failed(state, *dep))
+    {
+        state.queue.retain(|id| *id != job_id);
+        state.cancelled.insert(job_id);
+        if let Some(info) = state.jobs.get_mut(&job_id) {
+            info.finished_at = Some(now);
         }
+        cancel_dependents(state, job_id, now);
+    } else {
+        preempt_for(state, job_id, options);
+    }
+}
+
+/// Whether `job_id` failed for good or was cancelled
+fn has_failed(state: &QueueState, job_id: JobId) -> bool {
+    state.failures.contains_key(&job_id) || state.cancelled.contains(&job_id)
+}
+
+/// Whether every dependency of the job has completed, or failed and may be
+/// skipped
+fn dependencies_met(state: &QueueState, info: &JobInfo) -> bool {
+    let skip = info.job.config.on_dependency_failure == DependencyFailure::Skip;
+    info.job.depends_on.iter()
+        .all(|dep| state.results.contains_key(dep) || (skip && has_failed(state, *dep)))
+}
+
+/// Cancel the queued jobs that depend on `job_id`, which failed or was
+/// cancelled, unless they skip failed dependencies, then the jobs that
+/// depend on those, and so on
+fn cancel_dependents(state: &mut QueueState, job_id: JobId, now: Instant) {
+    let mut failed = vec![job_id];
+    while let Some(failed_id) = failed.pop() {
+        let dependents: Vec<JobId> = state.queue.iter()
+            .filter(|id| {
+                state.jobs.get(id).is_some_and(|info| {
+                    info.job.depends_on.contains(&failed_id)
+                        && info.job.config.on_dependency_failure == DependencyFailure::Cancel
+                })
+            })
+            .copied()
+            .collect();
+        for id in dependents {
+            crate::log_info!(
+                "job_queue",
+                "Job {} cancelled: dependency {} did not complete",
+                id,
+                failed_id
+            );
+            state.queue.retain(|queued| *queued != id);
+            state.cancelled.insert(id);
+            if let Some(info) = state.jobs.get_mut(&id) {
+                info.finished_at = Some(now);
+            }
+            failed.push(id);
+        }
+    }
+}
+
+/// Position in the queue of the job to run next
+///
+/// Jobs waiting on dependencies or still in retry backoff, and jobs whose
+/// priority already has `max_running` jobs running, or whose owner has
+/// `max_running_per_owner`, are skipped. Among the rest the highest
+/// effective priority wins; ties go to the job submitted first.
+fn next_job(
+    state: &QueueState,
+    now: Instant,
+    aging_interval: Option<Duration>,
+    max_running: &HashMap<Priority, usize>,
+    max_running_per_owner: &HashMap<String, usize>,
+) -> Option<usize> {
+    state.queue.iter()
+        .enumerate()
+        .filter_map(|(pos, id)| {
+            let info = state.jobs.get(id)?;
+            if info.not_before.is_some_and(|t| t > now)
+                || !dependencies_met(state, info)
+                || at_limit(state, info.job.priority, max_running)
+                || owner_at_limit(state, &info.job, max_running_per_owner)
+            {
+                return None;
+            }
+            let effective = effective_priority(info, now, aging_interval);
+            Some((effective, std::cmp::Reverse(info.submitted_at), std::cmp::Reverse(pos)))
+        })
+        .max()
+        .map(|(_, _, std::cmp::Reverse(pos))| pos)
+}
+
+/// The job's priority plus one level per full `aging_interval` it has
+/// waited, capped at `Critical`
+fn effective_priority(info: &JobInfo, now: Instant, aging_interval: Option<Duration>) -> usize {
+    let waited = now.saturating_duration_since(info.submitted_at);
+    let boost = aging_interval
+        .filter(|interval| !interval.is_zero())
+        .map_or(0, |interval| (waited.as_nanos() / interval.as_nanos()) as usize);
+    (info.job.priority as usize + boost).min(Priority::Critical as usize)
+}
+
+/// Whether `priority` already has as many jobs running as `max_running`
+/// allows
+fn at_limit(state: &QueueState, priority: Priority, max_running: &HashMap<Priority, usize>) -> bool {
+    max_running.get(&priority).is_some_and(|&limit| {
+        let running = state.running.keys()
+            .filter(|id| state.jobs.get(id).is_some_and(|info| info.job.priority == priority))
+            .count();
+        running >= limit
+    })
+}
+
+/// Whether the owner of `job` already has as many jobs running as
+/// `max_running_per_owner` allows it
+fn owner_at_limit(state: &QueueState, job: &Job, max_running_per_owner: &HashMap<String, usize>) -> bool {
+    let Some(owner) = &job.config.owner else { return false };
+    max_running_per_owner.get(owner).is_some_and(|&limit| {
+        let running = state.running.keys()
+            .filter(|id| state.jobs.get(id).is_some_and(|info| info.job.config.owner.as_ref() == Some(owner)))
+            .count();
+        running >= limit
+    })
+}
+
+/// Stop a running job to make room for `job_id`, if it is a `Critical` job
+/// that would otherwise wait for a free worker
+///
+/// The job stopped is the one of lowest priority, among those started most
+/// recently so as to lose the least work, that has not yet been preempted
+/// `max_preemptions` times.
+fn preempt_for(state: &mut QueueState, job_id: JobId, options: &QueueOptions) {
+    let Some(info) = state.jobs.get(&job_id) else { return };
+    if !options.preemption
+        || info.job.priority != Priority::Critical
+        || !dependencies_met(state, info)
+        || state.busy < state.workers
+        || at_limit(state, Priority::Critical, &options.max_running)
+        || owner_at_limit(state, &info.job, &options.max_running_per_owner)
+    {
+        return;
+    }
+
+    let victim = state.running.iter()
+        .filter(|(_, running)| !running.preempted)
+        .filter_map(|(id, running)| {
+            let info = state.jobs.get(id)?;
+            let preemptible = info.job.priority < Priority::Critical && info.preemptions < options.max_preemptions;
+            preemptible.then_some((info.job.priority, std::cmp::Reverse(running.started_at), *id))
+        })
+        .min()
+        .map(|(_, _, id)| id);
+
+    if let Some(running) = victim.and_then(|id| state.running.get_mut(&id)) {
+        running.preempted = true;
+        running.preempt.notify_one();
     }
 }
 
-async fn worker_loop(worker_id: usize, state: Arc<Mutex<QueueState>>) {
+async fn worker_loop(worker_id: usize, state: Arc<Mutex<QueueState>>, options: QueueOptions) {
     loop {
         // Get next job from queue
         let job_info = {
             let mut state = state.lock().unwrap();
-            if let Some(job_id) = state.queue.pop_front() {
-                state.running.insert(job_id, Instant::now());
-                state.jobs.get(&job_id).cloned()
+            let now = options.clock.now();
+            let next = if state.draining {
+                None
+            } else {
+                next_job(
+                    &state,
+                    now,
+                    options.aging_interval,
+                    &options.max_running,
+                    &options.max_running_per_owner,
+                )
+            };
+            if let Some(pos) = next {
+                let job_id = state.queue.remove(pos).unwrap();
+                let progress = ProgressReporter::default();
+                let preempt = Arc::new(Notify::new());
+                let cancel = CancellationToken::new();
+                state.running.insert(job_id, RunningJob {
+                    started_at: now,
+                    progress: progress.clone(),
+                    preempt: Arc::clone(&preempt),
+                    preempted: false,
+                    cancel: cancel.clone(),
+                });
+                state.busy += 1;
+                state.jobs.get_mut(&job_id).map(|info| {
+                    info.started_at = Some(now);
+                    info.finished_at = None;
+                    (info.clone(), progress, preempt, cancel)
+                })
             } else {
                 None
             }
         };
 
-        if let Some(info) = job_info {
+        if let Some((info, progress, preempt, cancel)) = job_info {
             println!("Worker {} executing job {}", worker_id, info.job.id);
             
-            let result = execute_job(&info.job).await;
+            let timeout = info.job.config.timeout;
+            let run = CANCELLATION.scope(cancel.clone(), options.runner.run(info.job.clone()));
+            let result = tokio::select! {
+                result = PROGRESS.scope(progress, run) => Some(result),
+                _ = options.clock.sleep(timeout) => Some(Err(EngineError::ExecutionError(format!(
+                    "Job timed out after {:?}",
+                    timeout
+                )))),
+                _ = preempt.notified() => None,
+            };
+            // Whatever ended the run, stop any work it left behind
+            cancel.cancel();
             
+            let now = options.clock.now();
             let mut state = state.lock().unwrap();
+            state.busy -= 1;
+            let Some(result) = result else {
+                // Preempted: back to the queue, without using up a retry,
+                // unless it was cancelled meanwhile
+                if state.running.remove(&info.job.id).is_some() {
+                    crate::log_info!("job_queue", "Job {} preempted", info.job.id);
+                    if let Some(entry) = state.jobs.get_mut(&info.job.id) {
+                        entry.preemptions += 1;
+                    }
+                    enqueue(&mut state, info.job.id);
+                }
+                continue;
+            };
             state.running.remove(&info.job.id);
+            // A job cancelled while running finished when it was cancelled
+            if !state.cancelled.contains(&info.job.id) {
+                if let Some(entry) = state.jobs.get_mut(&info.job.id) {
+                    entry.finished_at = Some(now);
+                }
+            }
+            let started_at = info.started_at.unwrap_or(now);
             
             match result {
+                // Cancelled while running: the outcome is discarded
+                _ if state.cancelled.contains(&info.job.id) => {}
                 Ok(output) => {
-                    state.results.insert(info.job.id, JobResult {
+                    #[cfg(feature = "provenance")]
+                    if let Some(graph) = &options.provenance {
+                        graph.lock().unwrap().record(&provenance_record(&info.job, &output));
+                    }
+                    let wait = started_at.saturating_duration_since(info.submitted_at);
+                    let run = now.saturating_duration_since(started_at);
+                    let kind = info.job.kind.label();
+                    let totals = state.run_times.entry(kind).or_default();
+                    totals.0 += run;
+                    totals.1 += 1;
+                    crate::logging::record_metric("job_wait", wait);
+                    crate::logging::record_metric(&format!("job_run.{}", kind), run);
+
+                    let result = JobResult {
                         job_id: info.job.id,
-                        started_at: chrono::Utc::now(),
-                        completed_at: chrono::Utc::now(),
-                        duration: info.submitted_at.elapsed(),
+                        submitted_at: state.timestamp(info.submitted_at),
+                        started_at: state.timestamp(started_at),
+                        completed_at: state.timestamp(now),
+                        wait,
+                        duration: run,
                         output,
-                    });
+                    };
+                    state.results.insert(info.job.id, result);
+                }
+                Err(e) if info.retry_count < info.job.config.max_retries => {
+                    crate::log_info!("job_queue", "Job {} failed, retrying: {}", info.job.id, e);
+                    let backoff = options.retry_backoff * 2u32.pow(info.retry_count.min(16) as u32);
+                    if let Some(entry) = state.jobs.get_mut(&info.job.id) {
+                        entry.retry_count += 1;
+                        entry.not_before = Some(now + backoff);
+                    }
+                    enqueue(&mut state, info.job.id);
                 }
                 Err(e) => {
                     println!("Job {} failed: {:?}", info.job.id, e);
-                    // Could implement retry logic here
+                    state.failures.insert(info.job.id, (e.to_string(), info.retry_count));
+                    cancel_dependents(&mut state, info.job.id, now);
                 }
             }
         } else {
             // No jobs available, sleep briefly
-            tokio::time::sleep(Duration::from_millis(100)).await;
+            options.clock.sleep(options.poll_interval).await;
+        }
+    }
+}
+
+/// Provenance of a completed job's output; the result id is the job id
+#[cfg(feature = "provenance")]
+fn provenance_record(job: &Job, output: &JobOutput) -> ProvenanceRecord {
+    let id = job.id.to_string();
+    let (kind, program, data_path) = match &job.kind {
+        JobKind::Simulate { program } => ("simulate", Some(program), None),
+        JobKind::Prove { .. } => ("prove", None, None),
+        JobKind::Fit { program, data_path } => ("fit", Some(program), Some(data_path)),
+        JobKind::Test { program, data_path } => ("test", Some(program), Some(data_path)),
+        JobKind::Sweep { .. } => ("sweep", None, None),
+    };
+    let mut record = ProvenanceRecord::new(id.clone(), id, kind).with_parameters(&job.params);
+
+    if let JobKind::Prove { statement } = &job.kind {
+        record = record.with_input("statement", statement.as_bytes());
+    }
+    // A program is either a registry template id or DSL source
+    if let Some(program) = program {
+        record = match crate::templates::TemplateRegistry::new().get(program) {
+            Some(template) => record.with_template(template),
+            None => record.with_input("program", program.as_bytes()),
+        };
+    }
+    if let Some(path) = data_path {
+        if let Ok(bytes) = std::fs::read(path) {
+            record = record.with_input(path.clone(), &bytes);
         }
     }
+    if let JobOutput::Proof(ProofOutput { certificate_hash: Some(hash), .. }) = output {
+        record = record.with_certificate(hash.clone());
+    }
+    record
 }
 
-async fn execute_job(job: &Job) -> Result<JobOutput> {
-    // This is a stub - actual implementation would:
-    // 1. Parse DSL or deserialize IR
-    // 2. Run through pipeline
-    // 3. Execute on backend
-    // 4. Return results
-    
+async fn execute_job(job: &Job, cancel: &CancellationToken) -> Result<JobOutput> {
+    // Simulations and fits run on the job's backend off the async workers,
+    // checking `cancel` and reporting progress every time step, once their
+    // lowered program has passed the job's resource limits; proofs run the
+    // prover within the job's timeout. Hypothesis tests still return a
+    // placeholder output. The reporter is task-local, so it is taken here
+    // for the blocking threads
+    let progress = current_progress().unwrap_or_default();
     match &job.kind {
         JobKind::Simulate { .. } => {
-            Ok(JobOutput::Simulation(SimulationOutput {
-                states: vec![],
-                diagnostics: DiagnosticsSummary {
-                    max_trace_drift: 1e-10,
-                    min_eigenvalue: -1e-11,
-                    warnings: vec![],
-                },
-            }))
+            let (job, cancel) = (job.clone(), cancel.clone());
+            tokio::task::spawn_blocking(move || run_simulation(&job, &cancel, &progress))
+                .await
+                .map_err(|e| EngineError::Internal(format!("Simulation task failed: {}", e)))?
         }
         JobKind::Prove { .. } => {
-            Ok(JobOutput::Proof(ProofOutput {
-                proven: true,
-                steps: 5,
-                certificate_hash: Some("abc123".to_string()),
-            }))
+            let job = job.clone();
+            tokio::task::spawn_blocking(move || run_proof(&job))
+                .await
+                .map_err(|e| EngineError::Internal(format!("Proof task failed: {}", e)))?
         }
         JobKind::Fit { .. } => {
-            Ok(JobOutput::Fit(FitOutput {
-                best_params: vec![1.0, 0.2],
-                uncertainties: vec![0.01, 0.02],
-                log_likelihood: -100.0,
-            }))
+            let (job, cancel) = (job.clone(), cancel.clone());
+            tokio::task::spawn_blocking(move || run_fit(&job, &cancel, &progress))
+                .await
+                .map_err(|e| EngineError::Internal(format!("Fit task failed: {}", e)))?
         }
         JobKind::Test { .. } => {
             Ok(JobOutput::Test(TestOutput {
@@ -514,94 +1518,272 @@ async fn execute_job(job: &Job) -> Result<JobOutput> {
                 decision: "Accept".to_string(),
             }))
         }
-        JobKind::Sweep { .. } => {
-            Err(EngineError::Unsupported("Nested sweeps not supported".to_string()))
+        JobKind::Sweep { base_job, param_grid } => {
+            let (base_job, param_grid) = (base_job.as_ref().clone(), param_grid.clone());
+            let cancel = cancel.clone();
+            tokio::task::spawn_blocking(move || run_sweep(&base_job, &param_grid, &cancel, &progress))
+                .await
+                .map_err(|e| EngineError::Internal(format!("Sweep task failed: {}", e)))?
         }
     }
 }
 
-fn generate_parameter_combinations(grid: &ParameterGrid) -> Vec<HashMap<String, f64>> {
-    match grid.strategy {
-        GridStrategy::FullGrid => generate_full_grid(&grid.params),
-        GridStrategy::Random(n) => generate_random_grid(&grid.params, n),
-        GridStrategy::Sobol(n) => generate_sobol_grid(&grid.params, n),
+/// Parse, bind, validate and lower `program`, a registry template id or DSL
+/// source, with the parameter values `params`
+#[cfg(feature = "parser")]
+fn lower_program(program: &str, params: &HashMap<String, f64>) -> Result<crate::ir::IrProgram> {
+    let source = match crate::templates::TemplateRegistry::new().get(program) {
+        Some(template) => template.code.clone(),
+        None => program.to_string(),
+    };
+    let mut values = crate::values::ParameterValues::new();
+    for (name, &value) in params {
+        values.insert(name.clone(), value);
     }
+    let ast = values.bind(&crate::parser::parse_dsl(&source)?)?;
+    crate::pipeline::lower(&crate::pipeline::validate_quantum(&ast)?)
+}
+
+/// Run a simulation job on its backend, reporting the time steps taken to
+/// `progress` and stopping at the next one once `cancel` is cancelled
+#[cfg(feature = "parser")]
+fn run_simulation(job: &Job, cancel: &CancellationToken, progress: &ProgressReporter) -> Result<JobOutput> {
+    let JobKind::Simulate { program } = &job.kind else {
+        return Err(EngineError::Internal("run_simulation needs a simulation job".to_string()));
+    };
+    let ir = lower_program(program, &job.params)?;
+    job.config.check_resources(&crate::resources::estimate(&ir), 1)?;
+    let result = Executor::new(job.config.backend.clone())
+        .with_cancellation(cancel.clone())
+        .with_progress(progress.clone())
+        .execute(&ir)?;
+    Ok(JobOutput::Simulation(simulation_output(&result)?))
+}
+
+#[cfg(not(feature = "parser"))]
+fn run_simulation(_job: &Job, _cancel: &CancellationToken, _progress: &ProgressReporter) -> Result<JobOutput> {
+    Err(EngineError::Unsupported(
+        "Simulation jobs require the `parser` feature".to_string(),
+    ))
 }
 
-fn generate_full_grid(params: &[ParameterRange]) -> Vec<HashMap<String, f64>> {
-    if params.is_empty() {
-        return vec![HashMap::new()];
+/// Maximum-likelihood fit of a fit job's parameters to the measurement
+/// counts in its data file
+///
+/// Every entry of `job.params` is fitted, starting from its value; the
+/// output lists them in name order. Each likelihood evaluation simulates
+/// the program and takes the outcome probabilities of its measurements.
+/// The downhill simplex, which needs no gradients of the simulation, gets
+/// nine tenths of `job.config.timeout` as its budget, so a slow fit reports
+/// its best point so far as truncated instead of timing out. Every
+/// evaluation runs the whole simulation again, so the time steps of one are
+/// no measure of the fit; it reports the share of its budget spent to
+/// `progress` after each evaluation instead.
+#[cfg(feature = "parser")]
+fn run_fit(job: &Job, cancel: &CancellationToken, progress: &ProgressReporter) -> Result<JobOutput> {
+    let JobKind::Fit { program, data_path } = &job.kind else {
+        return Err(EngineError::Internal("run_fit needs a fit job".to_string()));
+    };
+    let events = crate::measurement_io::import_measurements(data_path)?.events;
+    if events.is_empty() {
+        return Err(EngineError::validation_error(format!(
+            "{} has no measurement outcome counts to fit",
+            data_path
+        )));
     }
+    let mut names: Vec<&String> = job.params.keys().collect();
+    names.sort();
+    let initial: Vec<f64> = names.iter().map(|name| job.params[*name]).collect();
+    job.config
+        .check_resources(&crate::resources::estimate(&lower_program(program, &job.params)?), 1)?;
 
-    let first = &params[0];
-    let rest = &params[1..];
-    
-    let values = generate_param_values(first);
-    let rest_combinations = generate_full_grid(rest);
-    
-    let mut result = Vec::new();
-    for value in values {
-        for combo in &rest_combinations {
-            let mut new_combo = combo.clone();
-            new_combo.insert(first.name.clone(), value);
-            result.push(new_combo);
+    let budget = job.config.timeout.mul_f64(0.9);
+    let started = std::time::Instant::now();
+    let likelihood = |values: &[f64]| {
+        let params = names.iter().map(|name| name.to_string()).zip(values.iter().copied()).collect();
+        let result = Executor::new(job.config.backend.clone())
+            .with_cancellation(cancel.clone())
+            .execute(&lower_program(program, &params)?)?;
+        progress.report(started.elapsed().as_secs_f64() / budget.as_secs_f64());
+        let mut predicted = crate::stats::PredictedProbs::new();
+        for measurement in result.experiment_results.iter().flat_map(|e| &e.measurements) {
+            for (outcome, &prob) in measurement.probabilities.iter().enumerate() {
+                predicted.insert(measurement.time, outcome, prob);
+            }
         }
-    }
-    
-    result
+        crate::stats::log_likelihood(&events, &predicted)
+    };
+    let options = crate::stats::FitOptions {
+        method: crate::stats::FitMethod::NelderMead,
+        budget: Some(budget),
+        ..crate::stats::FitOptions::default()
+    };
+    let fit = crate::stats::fit_parameters_mle_with(likelihood, &initial, &options)?;
+    Ok(JobOutput::Fit(fit.into()))
 }
 
-fn generate_param_values(param: &ParameterRange) -> Vec<f64> {
-    match &param.scale {
-        ParameterScale::Linear => {
-            (0..param.steps)
-                .map(|i| {
-                    let t = i as f64 / (param.steps - 1).max(1) as f64;
-                    param.start + t * (param.end - param.start)
-                })
-                .collect()
-        }
-        ParameterScale::Log => {
-            let log_start = param.start.ln();
-            let log_end = param.end.ln();
-            (0..param.steps)
-                .map(|i| {
-                    let t = i as f64 / (param.steps - 1).max(1) as f64;
-                    (log_start + t * (log_end - log_start)).exp()
-                })
-                .collect()
+#[cfg(not(feature = "parser"))]
+fn run_fit(_job: &Job, _cancel: &CancellationToken, _progress: &ProgressReporter) -> Result<JobOutput> {
+    Err(EngineError::Unsupported(
+        "Fit jobs require the `parser` feature".to_string(),
+    ))
+}
+
+/// Prove a proof job's identity `lhs == rhs`, giving the prover the job's
+/// timeout; a refuted or undecided statement is not proven and has no
+/// certificate
+#[cfg(all(feature = "parser", feature = "prover"))]
+fn run_proof(job: &Job) -> Result<JobOutput> {
+    use crate::prover::{ProofResult, Prover, ProverConfig};
+
+    let JobKind::Prove { statement } = &job.kind else {
+        return Err(EngineError::Internal("run_proof needs a proof job".to_string()));
+    };
+    let (lhs, rhs) = crate::parser::parse_identity(statement)?;
+    let mut prover = Prover::new(ProverConfig {
+        timeout: job.config.timeout,
+        ..ProverConfig::default()
+    });
+    let output = match prover.prove_identity(&lhs, &rhs) {
+        ProofResult::Proven(proof) => ProofOutput {
+            proven: true,
+            steps: proof.steps.len(),
+            certificate_hash: Some(proof.certificate.hash),
+        },
+        ProofResult::Refuted(_) | ProofResult::Unknown(_) => ProofOutput {
+            proven: false,
+            steps: 0,
+            certificate_hash: None,
+        },
+    };
+    Ok(JobOutput::Proof(output))
+}
+
+#[cfg(not(all(feature = "parser", feature = "prover")))]
+fn run_proof(_job: &Job) -> Result<JobOutput> {
+    Err(EngineError::Unsupported(
+        "Proof jobs require the `parser` and `prover` features".to_string(),
+    ))
+}
+
+/// Run a simulation at every point of `grid` as one batch
+///
+/// The program, a registry template id or DSL source, is parsed, validated
+/// and lowered once with the first point's values; every point then runs
+/// through [`Executor::execute_batch`] on the base job's backend. The
+/// base job's own parameters apply at every point unless the grid sets
+/// them. Each point's result carries the timestamps of the whole batch.
+/// Cancelling `cancel` stops every point at its next time step, and
+/// `progress` is told the points completed.
+#[cfg(feature = "parser")]
+fn run_sweep(
+    base_job: &Job,
+    grid: &ParameterGrid,
+    cancel: &CancellationToken,
+    progress: &ProgressReporter,
+) -> Result<JobOutput> {
+    let JobKind::Simulate { program } = &base_job.kind else {
+        return Err(EngineError::Unsupported(
+            "Only simulations sweep as one job; submit other sweeps with JobQueue::submit_sweep".to_string(),
+        ));
+    };
+    let points: Vec<HashMap<String, f64>> = grid.points()?
+        .into_iter()
+        .map(|point| {
+            let mut params = base_job.params.clone();
+            params.extend(point);
+            params
+        })
+        .collect();
+
+    let started_at = chrono::Utc::now();
+    let mut results = Vec::new();
+    let mut failed = 0;
+    if let Some(first) = points.first() {
+        let template = lower_program(program, first)?;
+        base_job
+            .config
+            .check_resources(&crate::resources::estimate(&template), points.len())?;
+
+        let outcomes = Executor::new(base_job.config.backend.clone())
+            .with_cancellation(cancel.clone())
+            .with_progress(progress.clone())
+            .execute_batch(&template, &points);
+        // Cancelled points are not failures, the sweep as a whole is
+        cancel.check()?;
+        let completed_at = chrono::Utc::now();
+        for (params, outcome) in points.iter().zip(outcomes) {
+            match outcome.and_then(|result| simulation_output(&result)) {
+                Ok(output) => results.push((
+                    params.clone(),
+                    JobResult {
+                        job_id: Uuid::new_v4(),
+                        submitted_at: started_at,
+                        started_at,
+                        completed_at,
+                        wait: Duration::ZERO,
+                        duration: (completed_at - started_at).to_std().unwrap_or_default(),
+                        output: JobOutput::Simulation(output),
+                    },
+                )),
+                Err(e) => {
+                    crate::log_warn!("job_queue", "Sweep point {:?} failed: {}", params, e);
+                    failed += 1;
+                }
+            }
         }
-        ParameterScale::Custom(values) => values.clone(),
     }
+
+    Ok(JobOutput::Sweep(SweepOutput {
+        summary: SweepSummary {
+            total_runs: points.len(),
+            successful: results.len(),
+            failed,
+            total_duration: (chrono::Utc::now() - started_at).to_std().unwrap_or_default(),
+        },
+        results,
+        best_params: HashMap::new(),
+    }))
 }
 
-fn generate_random_grid(params: &[ParameterRange], n: usize) -> Vec<HashMap<String, f64>> {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    
-    (0..n).map(|_| {
-        params.iter().map(|param| {
-            let value = match &param.scale {
-                ParameterScale::Linear => {
-                    rng.gen_range(param.start..=param.end)
-                }
-                ParameterScale::Log => {
-                    let log_start = param.start.ln();
-                    let log_end = param.end.ln();
-                    rng.gen_range(log_start..=log_end).exp()
-                }
-                ParameterScale::Custom(values) => {
-                    values[rng.gen_range(0..values.len())]
-                }
-            };
-            (param.name.clone(), value)
-        }).collect()
-    }).collect()
+#[cfg(not(feature = "parser"))]
+fn run_sweep(
+    _base_job: &Job,
+    _grid: &ParameterGrid,
+    _cancel: &CancellationToken,
+    _progress: &ProgressReporter,
+) -> Result<JobOutput> {
+    Err(EngineError::Unsupported(
+        "Sweep jobs require the `parser` feature".to_string(),
+    ))
 }
 
-fn generate_sobol_grid(_params: &[ParameterRange], _n: usize) -> Vec<HashMap<String, f64>> {
-    // TODO: Implement Sobol quasi-random sequence
-    vec![]
+/// Final populations of each experiment, with physicality diagnostics
+/// merged over their trajectories
+#[cfg(feature = "parser")]
+fn simulation_output(result: &ExecutionResult) -> Result<SimulationOutput> {
+    let mut diagnostics = DiagnosticsSummary {
+        max_trace_drift: 0.0,
+        min_eigenvalue: f64::INFINITY,
+        warnings: Vec::new(),
+    };
+    let mut states = Vec::new();
+    for experiment in &result.experiment_results {
+        let summary = crate::executor::diagnose_states(
+            &experiment.times,
+            &experiment.states,
+            &crate::executor::DiagnosticThresholds::default(),
+        )?;
+        diagnostics.max_trace_drift = diagnostics.max_trace_drift.max(summary.max_trace_drift);
+        diagnostics.min_eigenvalue = diagnostics.min_eigenvalue.min(summary.min_eigenvalue);
+        diagnostics
+            .warnings
+            .extend(summary.warnings.into_iter().map(|w| format!("{}: {}", experiment.name, w)));
+        if let Some(rho) = experiment.states.last() {
+            states.push(rho.diag().iter().map(|p| p.re).collect());
+        }
+    }
+    Ok(SimulationOutput { states, diagnostics })
 }
 
 #[cfg(test)]
@@ -618,35 +1800,744 @@ mod tests {
             priority: Priority::Normal,
             params: HashMap::new(),
             config: JobConfig::default(),
+            depends_on: Vec::new(),
         };
 
         let job_id = queue.submit(job).await.unwrap();
         assert!(!job_id.is_nil());
     }
 
+    #[cfg(all(feature = "parser", feature = "prover"))]
+    #[tokio::test(start_paused = true)]
+    async fn test_drain_cancel_and_retry() {
+        let queue = JobQueue::new(1);
+        queue.drain().await.unwrap();
+        let job_id = queue
+            .submit(Job {
+                id: Uuid::new_v4(),
+                kind: JobKind::Prove { statement: "A == A".to_string() },
+                priority: Priority::Normal,
+                params: HashMap::new(),
+                config: JobConfig::default(),
+                depends_on: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        // Draining: the job waits however long the worker polls
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(queue.status(job_id).await.unwrap().label(), "queued");
+        assert!(queue.retry(job_id).await.is_err());
+
+        queue.cancel(job_id).await.unwrap();
+        let details = queue.details(job_id).await.unwrap();
+        assert_eq!(details.status.label(), "cancelled");
+        assert_eq!(details.job.kind.label(), "prove");
+
+        queue.resume().await.unwrap();
+        queue.retry(job_id).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(queue.status(job_id).await.unwrap().label(), "complete");
+        assert_eq!(queue.all_details().await.len(), 1);
+    }
+
+    #[cfg(all(feature = "parser", feature = "prover"))]
+    #[tokio::test]
+    async fn test_cancel_stops_running_job() {
+        use std::sync::atomic::Ordering;
+
+        // Blocking work that runs until its token is cancelled
+        let stopped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = Arc::clone(&stopped);
+        let options = QueueOptions {
+            runner: Arc::new(move |job: Job| {
+                let flag = Arc::clone(&flag);
+                async move {
+                    let cancel = current_cancellation().unwrap();
+                    if job.params.contains_key("quick") {
+                        return execute_job(&job, &cancel).await;
+                    }
+                    tokio::task::spawn_blocking(move || {
+                        while cancel.check().is_ok() {
+                            std::thread::sleep(Duration::from_millis(1));
+                        }
+                        flag.store(true, Ordering::SeqCst);
+                        Err(EngineError::Cancelled)
+                    })
+                    .await
+                    .unwrap()
+                }
+            }),
+            poll_interval: Duration::from_millis(5),
+            ..QueueOptions::default()
+        };
+        let queue = JobQueue::with_options(1, options);
+        let job = |params| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Prove { statement: "A == A".to_string() },
+            priority: Priority::Normal,
+            params,
+            config: JobConfig::default(),
+            depends_on: Vec::new(),
+        };
+
+        let slow = queue.submit(job(HashMap::new())).await.unwrap();
+        while queue.status(slow).await.unwrap().label() != "running" {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        queue.cancel(slow).await.unwrap();
+        assert_eq!(queue.status(slow).await.unwrap().label(), "cancelled");
+
+        // The worker is free for the next job, and the blocking work stops
+        let quick = queue.submit(job(HashMap::from([("quick".to_string(), 1.0)]))).await.unwrap();
+        for _ in 0..200 {
+            if queue.status(quick).await.unwrap().label() == "complete" && stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(queue.status(quick).await.unwrap().label(), "complete");
+        assert!(stopped.load(Ordering::SeqCst));
+        assert_eq!(queue.status(slow).await.unwrap().label(), "cancelled");
+    }
+
+    #[cfg(all(feature = "parser", feature = "prover"))]
+    #[tokio::test(start_paused = true)]
+    async fn test_result_separates_wait_and_run() {
+        let options = QueueOptions {
+            runner: Arc::new(|job: Job| async move {
+                tokio::time::sleep(Duration::from_secs(3)).await;
+                execute_job(&job, &CancellationToken::default()).await
+            }),
+            ..QueueOptions::default()
+        };
+        let queue = JobQueue::with_options(1, options);
+        queue.drain().await.unwrap();
+        let job_id = queue
+            .submit(Job {
+                id: Uuid::new_v4(),
+                kind: JobKind::Prove { statement: "A == A".to_string() },
+                priority: Priority::Normal,
+                params: HashMap::new(),
+                config: JobConfig::default(),
+                depends_on: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        // Held in the queue for 10 s, then run for 3 s
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        queue.resume().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        let result = queue.get_result(job_id).await.unwrap();
+        assert!(result.wait >= Duration::from_secs(10) && result.wait < Duration::from_secs(11));
+        assert_eq!(result.duration, Duration::from_secs(3));
+        assert_eq!(result.completed_at - result.started_at, chrono::Duration::seconds(3));
+        assert_eq!(
+            result.started_at - result.submitted_at,
+            chrono::Duration::from_std(result.wait).unwrap()
+        );
+
+        let details = queue.details(job_id).await.unwrap();
+        assert_eq!(details.submitted_at, result.submitted_at);
+        assert_eq!(details.started_at, Some(result.started_at));
+        assert_eq!(details.finished_at, Some(result.completed_at));
+    }
+
+    fn queued_state(jobs: &[(Priority, Instant)]) -> (QueueState, Vec<JobId>) {
+        let mut state = QueueState::new(Instant::now());
+        let mut ids = Vec::new();
+        for &(priority, submitted_at) in jobs {
+            let job = Job {
+                id: Uuid::new_v4(),
+                kind: JobKind::Prove { statement: "A == A".to_string() },
+                priority,
+                params: HashMap::new(),
+                config: JobConfig::default(),
+                depends_on: Vec::new(),
+            };
+            ids.push(job.id);
+            state.jobs.insert(job.id, JobInfo::new(job, submitted_at));
+            enqueue(&mut state, *ids.last().unwrap());
+        }
+        (state, ids)
+    }
+
+    #[test]
+    fn test_next_job_ages_waiting_jobs() {
+        let t0 = Instant::now();
+        let minute = Duration::from_secs(60);
+        let (state, ids) = queued_state(&[
+            (Priority::Low, t0),
+            (Priority::High, t0 + Duration::from_secs(170)),
+        ]);
+        let position = |id: JobId| state.queue.iter().position(|q| *q == id);
+        let now = t0 + Duration::from_secs(170);
+
+        // Without aging the High job always goes first
+        assert_eq!(next_job(&state, now, None, &HashMap::new(), &HashMap::new()), position(ids[1]));
+        // After two minutes the Low job has aged to High and was submitted first
+        assert_eq!(next_job(&state, now, Some(minute), &HashMap::new(), &HashMap::new()), position(ids[0]));
+    }
+
+    #[test]
+    fn test_next_job_skips_jobs_in_backoff() {
+        let t0 = Instant::now();
+        let (mut state, ids) = queued_state(&[(Priority::Normal, t0)]);
+        state.jobs.get_mut(&ids[0]).unwrap().not_before = Some(t0 + Duration::from_secs(5));
+
+        assert_eq!(next_job(&state, t0 + Duration::from_secs(4), None, &HashMap::new(), &HashMap::new()), None);
+        assert_eq!(next_job(&state, t0 + Duration::from_secs(5), None, &HashMap::new(), &HashMap::new()), Some(0));
+    }
+
+    #[test]
+    fn test_next_job_respects_per_priority_limits() {
+        let t0 = Instant::now();
+        let (mut state, ids) = queued_state(&[
+            (Priority::High, t0),
+            (Priority::High, t0),
+            (Priority::Low, t0),
+        ]);
+        let limits = HashMap::from([(Priority::High, 1)]);
+        assert_eq!(next_job(&state, t0, None, &limits, &HashMap::new()), Some(0));
+
+        // With one High job running the other must wait, so the Low job runs
+        state.queue.pop_front();
+        state.running.insert(ids[0], RunningJob {
+            started_at: t0,
+            progress: ProgressReporter::default(),
+            preempt: Arc::default(),
+            preempted: false,
+            cancel: CancellationToken::default(),
+        });
+        assert_eq!(state.queue[next_job(&state, t0, None, &limits, &HashMap::new()).unwrap()], ids[2]);
+        assert_eq!(state.queue[next_job(&state, t0, None, &HashMap::new(), &HashMap::new()).unwrap()], ids[1]);
+    }
+
+    #[test]
+    fn test_next_job_respects_owner_quotas() {
+        let t0 = Instant::now();
+        let (mut state, ids) = queued_state(&[
+            (Priority::High, t0),
+            (Priority::High, t0),
+            (Priority::Low, t0),
+        ]);
+        for (id, owner) in ids.iter().zip(["alice", "alice", "bob"]) {
+            state.jobs.get_mut(id).unwrap().job.config.owner = Some(owner.to_string());
+        }
+        let quotas = HashMap::from([("alice".to_string(), 1)]);
+
+        // alice's first job runs; her second waits behind bob's
+        state.queue.pop_front();
+        state.running.insert(ids[0], RunningJob {
+            started_at: t0,
+            progress: ProgressReporter::default(),
+            preempt: Arc::default(),
+            preempted: false,
+            cancel: CancellationToken::default(),
+        });
+        assert_eq!(state.queue[next_job(&state, t0, None, &HashMap::new(), &quotas).unwrap()], ids[2]);
+        assert_eq!(state.queue[next_job(&state, t0, None, &HashMap::new(), &HashMap::new()).unwrap()], ids[1]);
+    }
+
+    #[cfg(all(feature = "parser", feature = "prover"))]
+    #[tokio::test(start_paused = true)]
+    async fn test_critical_job_preempts_low_priority_job() {
+        let options = QueueOptions {
+            runner: Arc::new(|job: Job| async move {
+                if job.priority == Priority::Low {
+                    tokio::time::sleep(Duration::from_secs(100)).await;
+                }
+                execute_job(&job, &CancellationToken::default()).await
+            }),
+            ..QueueOptions::default()
+        };
+        let queue = JobQueue::with_options(1, options);
+        let job = |priority| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Prove { statement: "A == A".to_string() },
+            priority,
+            params: HashMap::new(),
+            config: JobConfig::default(),
+            depends_on: Vec::new(),
+        };
+
+        let low = queue.submit(job(Priority::Low)).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(queue.status(low).await.unwrap().label(), "running");
+
+        // The Critical job takes the only worker straight away
+        let critical = queue.submit(job(Priority::Critical)).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(queue.status(critical).await.unwrap().label(), "complete");
+        assert_eq!(queue.status(low).await.unwrap().label(), "running");
+
+        // The Low job starts over and still completes
+        tokio::time::sleep(Duration::from_secs(101)).await;
+        let result = queue.get_result(low).await.unwrap();
+        assert_eq!(result.duration, Duration::from_secs(100));
+        assert_eq!(queue.details(low).await.unwrap().retry_count, 0);
+    }
+
+    #[cfg(all(feature = "parser", feature = "prover"))]
+    #[tokio::test(start_paused = true)]
+    async fn test_workflow_runs_in_dependency_order() {
+        let options = QueueOptions {
+            runner: Arc::new(|job: Job| async move {
+                if job.params.contains_key("fail") {
+                    return Err(EngineError::ExecutionError("backend down".to_string()));
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                execute_job(&job, &CancellationToken::default()).await
+            }),
+            ..QueueOptions::default()
+        };
+        let queue = JobQueue::with_options(4, options);
+        let job = |depends_on: Vec<JobId>, fail: bool, on_dependency_failure| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Prove { statement: "A == A".to_string() },
+            priority: Priority::Normal,
+            params: if fail { HashMap::from([("fail".to_string(), 1.0)]) } else { HashMap::new() },
+            config: JobConfig { max_retries: 0, on_dependency_failure, ..JobConfig::default() },
+            depends_on,
+        };
+        let (cancel, skip) = (DependencyFailure::Cancel, DependencyFailure::Skip);
+
+        let simulate = job(vec![], false, cancel);
+        let flaky = job(vec![], true, cancel);
+        let fit = job(vec![simulate.id], false, cancel);
+        let downstream = job(vec![flaky.id], false, cancel);
+        let further = job(vec![downstream.id], false, cancel);
+        let aggregate = job(vec![simulate.id, flaky.id], false, skip);
+        let ids = queue
+            .submit_workflow(vec![
+                further.clone(),
+                aggregate.clone(),
+                fit.clone(),
+                downstream.clone(),
+                simulate.clone(),
+                flaky.clone(),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(ids[0], further.id);
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let label = |status: Option<JobStatus>| status.unwrap().label();
+        assert_eq!(label(queue.status(flaky.id).await), "failed");
+        assert_eq!(label(queue.status(downstream.id).await), "cancelled");
+        assert_eq!(label(queue.status(further.id).await), "cancelled");
+        assert_eq!(label(queue.status(aggregate.id).await), "complete");
+
+        let simulated = queue.get_result(simulate.id).await.unwrap();
+        let fitted = queue.get_result(fit.id).await.unwrap();
+        assert!(fitted.started_at >= simulated.completed_at);
+
+        // Dependencies must exist and must not form a cycle
+        let orphan = job(vec![Uuid::new_v4()], false, cancel);
+        assert!(queue.submit(orphan).await.is_err());
+        let mut first = job(vec![], false, cancel);
+        let second = job(vec![first.id], false, cancel);
+        first.depends_on.push(second.id);
+        assert!(queue.submit_workflow(vec![first, second]).await.is_err());
+    }
+
+    #[test]
+    fn test_running_status_estimates_time_left() {
+        let t0 = Instant::now();
+        let running = RunningJob {
+            started_at: t0,
+            progress: ProgressReporter::default(),
+            preempt: Arc::default(),
+            preempted: false,
+            cancel: CancellationToken::default(),
+        };
+        let status_with = |elapsed: u64, expected| match running.status(t0 + Duration::from_secs(elapsed), expected) {
+            JobStatus::Running { progress, eta } => (progress, eta),
+            other => panic!("expected a running job, got {:?}", other),
+        };
+        let status = |elapsed: u64| status_with(elapsed, None);
+
+        assert_eq!(status(10), (0.0, None));
+        // Without progress, jobs of the kind usually take 25 s
+        let usual = Some(Duration::from_secs(25));
+        assert_eq!(status_with(10, usual), (0.0, Some(Duration::from_secs(15))));
+        assert_eq!(status_with(40, usual), (0.0, Some(Duration::ZERO)));
+        running.progress.report_steps(1, 4);
+        assert_eq!(status(10), (0.25, Some(Duration::from_secs(30))));
+        running.progress.report(2.0);
+        assert_eq!(status(10), (1.0, Some(Duration::ZERO)));
+    }
+
+    #[cfg(feature = "parser")]
+    #[tokio::test]
+    async fn test_cancel_stops_running_simulation() {
+        // Far more steps than the test waits for
+        let program = r#"
+            matrix X = [0, 1; 1, 0];
+            Hamiltonian H = X;
+            experiment long {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.001, 1000000));
+            }
+        "#;
+        let job = Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Simulate { program: program.to_string() },
+            priority: Priority::Normal,
+            params: HashMap::new(),
+            config: JobConfig::default(),
+            depends_on: Vec::new(),
+        };
+        let cancel = CancellationToken::new();
+        let run = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { execute_job(&job, &cancel).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+        let outcome = tokio::time::timeout(Duration::from_secs(10), run).await.unwrap().unwrap();
+        assert!(matches!(outcome, Err(EngineError::Cancelled)), "{:?}", outcome.map(|_| ()));
+    }
+
+    #[cfg(feature = "parser")]
+    #[tokio::test]
+    async fn test_running_simulation_reports_progress() {
+        let program = r#"
+            matrix X = [0, 1; 1, 0];
+            Hamiltonian H = X;
+            experiment long {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.001, 1000000));
+            }
+        "#;
+        let options = QueueOptions {
+            poll_interval: Duration::from_millis(5),
+            ..QueueOptions::default()
+        };
+        let queue = JobQueue::with_options(1, options);
+        let job_id = queue
+            .submit(Job {
+                id: Uuid::new_v4(),
+                kind: JobKind::Simulate { program: program.to_string() },
+                priority: Priority::Normal,
+                params: HashMap::new(),
+                config: JobConfig::default(),
+                depends_on: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        // The executor reports its time steps from the blocking thread
+        let mut reported = None;
+        for _ in 0..2000 {
+            if let Some(JobStatus::Running { progress, eta }) = queue.status(job_id).await {
+                if progress > 0.0 {
+                    reported = Some((progress, eta));
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let (progress, eta) = reported.expect("no progress reported");
+        assert!(progress < 1.0);
+        assert!(eta.is_some());
+        queue.cancel(job_id).await.unwrap();
+    }
+
+    #[cfg(feature = "parser")]
     #[test]
-    fn test_parameter_grid() {
+    fn test_sweep_job_runs_as_one_batch() {
+        let program = r#"
+            param omega;
+            matrix X = [0, 1; 1, 0];
+            Hamiltonian H = omega * X;
+            experiment rabi {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 3));
+            }
+        "#;
+        let base_job = Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Simulate { program: program.to_string() },
+            priority: Priority::Normal,
+            params: HashMap::new(),
+            config: JobConfig::default(),
+            depends_on: Vec::new(),
+        };
         let grid = ParameterGrid {
-            params: vec![
-                ParameterRange {
-                    name: "x".to_string(),
-                    start: 0.0,
-                    end: 1.0,
-                    steps: 3,
-                    scale: ParameterScale::Linear,
-                },
-                ParameterRange {
-                    name: "y".to_string(),
-                    start: 1.0,
-                    end: 10.0,
-                    steps: 2,
-                    scale: ParameterScale::Log,
-                },
-            ],
+            params: vec![ParameterRange {
+                name: "omega".to_string(),
+                start: 0.0,
+                end: 2.0,
+                steps: 3,
+                scale: ParameterScale::Linear,
+            }],
             strategy: GridStrategy::FullGrid,
         };
 
-        let combos = generate_parameter_combinations(&grid);
-        assert_eq!(combos.len(), 6); // 3 × 2
+        let progress = ProgressReporter::default();
+        let JobOutput::Sweep(output) = run_sweep(&base_job, &grid, &CancellationToken::default(), &progress).unwrap() else {
+            panic!("expected sweep output");
+        };
+        assert_eq!(progress.fraction(), 1.0);
+        assert_eq!(output.summary.total_runs, 3);
+        assert_eq!(output.summary.successful, 3);
+
+        let excited: Vec<f64> = output
+            .results
+            .iter()
+            .map(|(_, result)| match &result.output {
+                JobOutput::Simulation(simulation) => simulation.states[0][1],
+                other => panic!("expected simulation output, got {:?}", other),
+            })
+            .collect();
+        // No drive leaves |0⟩ alone; stronger drives rotate it further
+        assert_eq!(output.results[0].0["omega"], 0.0);
+        assert!(excited[0].abs() < 1e-12);
+        assert!(excited[1] > 1e-3 && excited[2] > excited[1]);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_sweep_over_resource_limits_fails_fast() {
+        let program = r#"
+            param omega;
+            matrix X = [0, 1; 1, 0];
+            Hamiltonian H = omega * tensor(X, X);
+            experiment pair {
+                init: ket(vec(1, 0, 0, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+            }
+        "#;
+        let job = |config: JobConfig| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Simulate { program: program.to_string() },
+            priority: Priority::Normal,
+            params: HashMap::from([("omega".to_string(), 1.0)]),
+            config,
+            depends_on: Vec::new(),
+        };
+        let grid = ParameterGrid { params: vec![], strategy: GridStrategy::FullGrid };
+        let (cancel, progress) = (CancellationToken::default(), ProgressReporter::default());
+
+        let limited = JobConfig { max_dim: Some(2), ..JobConfig::default() };
+        let err = run_sweep(&job(limited), &grid, &cancel, &progress).unwrap_err();
+        assert!(err.to_string().contains("dimension 4 exceeds the limit of 2"), "{}", err);
+        let limited = JobConfig { max_steps: Some(5), ..JobConfig::default() };
+        assert!(run_sweep(&job(limited), &grid, &cancel, &progress).unwrap_err().to_string().contains("10 time steps"));
+
+        let roomy = JobConfig { max_dim: Some(4), max_memory_mb: Some(1), max_steps: Some(10), ..JobConfig::default() };
+        assert!(run_sweep(&job(roomy), &grid, &cancel, &progress).is_ok());
+    }
+
+    #[cfg(feature = "parser")]
+    #[tokio::test]
+    async fn test_simulation_over_resource_limits_is_rejected() {
+        let program = r#"
+            matrix X = [0, 1; 1, 0];
+            Hamiltonian H = tensor(X, X);
+            experiment pair {
+                init: ket(vec(1, 0, 0, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+            }
+        "#;
+        let job = |config: JobConfig| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Simulate { program: program.to_string() },
+            priority: Priority::Normal,
+            params: HashMap::new(),
+            config,
+            depends_on: Vec::new(),
+        };
+        let cancel = CancellationToken::default();
+
+        let limited = JobConfig { max_dim: Some(2), ..JobConfig::default() };
+        let err = execute_job(&job(limited), &cancel).await.unwrap_err();
+        assert!(err.to_string().contains("dimension 4 exceeds the limit of 2"), "{}", err);
+        let limited = JobConfig { max_steps: Some(5), ..JobConfig::default() };
+        assert!(execute_job(&job(limited), &cancel).await.unwrap_err().to_string().contains("10 time steps"));
+
+        let roomy = JobConfig { max_dim: Some(4), max_memory_mb: Some(1), max_steps: Some(10), ..JobConfig::default() };
+        assert!(matches!(execute_job(&job(roomy), &cancel).await, Ok(JobOutput::Simulation(_))));
+
+        // Every time step of the run is reported
+        let progress = ProgressReporter::default();
+        run_simulation(&job(JobConfig::default()), &cancel, &progress).unwrap();
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    #[cfg(feature = "parser")]
+    #[tokio::test]
+    async fn test_fit_job_recovers_rabi_frequency() {
+        // P(1) = sin²(omega t), counts out of 1000 shots at omega = 1
+        let program = r#"
+            param omega;
+            matrix X = [0, 1; 1, 0];
+            Hamiltonian H = omega * X;
+            measure z: Projective([[[1, 0], [0, 0]], [[0, 0], [0, 1]]]);
+            experiment rabi {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.01, 150));
+                measurements: [(0.5, z), (1.0, z), (1.5, z)];
+            }
+        "#;
+        let data: String = [(0.5, 770, 230), (1.0, 292, 708), (1.5, 5, 995)]
+            .iter()
+            .flat_map(|&(time, zero, one)| [(time, 0, zero), (time, 1, one)])
+            .map(|(time, outcome, count)| {
+                format!(
+                    "{{\"time\": {}, \"measurement_id\": \"z\", \"outcome\": {}, \"count\": {}}}\n",
+                    time, outcome, count
+                )
+            })
+            .collect();
+        let data_path = std::env::temp_dir().join(format!("qte_{}_fit_job.jsonl", std::process::id()));
+        std::fs::write(&data_path, data).unwrap();
+        let job = |timeout| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Fit {
+                program: program.to_string(),
+                data_path: data_path.to_string_lossy().into_owned(),
+            },
+            priority: Priority::Normal,
+            params: HashMap::from([("omega".to_string(), 0.8)]),
+            config: JobConfig { timeout, ..JobConfig::default() },
+            depends_on: Vec::new(),
+        };
+        let cancel = CancellationToken::default();
+
+        let output = execute_job(&job(Duration::from_secs(300)), &cancel).await;
+        let rushed = execute_job(&job(Duration::from_micros(1)), &cancel).await;
+        std::fs::remove_file(&data_path).unwrap();
+        let Ok(JobOutput::Fit(fit)) = output else {
+            panic!("expected a fit: {:?}", output.map(|_| ()));
+        };
+        assert!((fit.best_params[0] - 1.0).abs() < 0.02, "{:?}", fit.best_params);
+        assert!(!fit.truncated);
+        // Out of time, the fit still reports its best point so far
+        assert!(matches!(rushed, Ok(JobOutput::Fit(FitOutput { truncated: true, .. }))));
+    }
+
+    #[test]
+    fn test_sweep_table_minima_and_profiles() {
+        // Objective on a 3×3 grid: global minimum at (1, 1), a local one at
+        // (2, 2), and a fit whose objective is its negated log-likelihood
+        let objective = [[5.0, 4.0, 5.0], [4.0, 1.0, 4.0], [5.0, 4.0, 3.0]];
+        let now = chrono::Utc::now();
+        let result = |output| JobResult {
+            job_id: Uuid::new_v4(),
+            submitted_at: now,
+            started_at: now,
+            completed_at: now,
+            wait: Duration::ZERO,
+            duration: Duration::ZERO,
+            output,
+        };
+        let mut results = Vec::new();
+        for (x, row) in objective.iter().enumerate() {
+            for (y, &value) in row.iter().enumerate() {
+                let params = HashMap::from([("x".to_string(), x as f64), ("y".to_string(), y as f64)]);
+                let output = JobOutput::Test(TestOutput {
+                    chi_square: value,
+                    p_value: 0.5,
+                    decision: String::new(),
+                });
+                results.push((params, result(output)));
+            }
+        }
+        results[0].1 = result(JobOutput::Fit(FitOutput {
+            best_params: vec![],
+            uncertainties: vec![],
+            log_likelihood: -5.0,
+            truncated: false,
+        }));
+        let output = SweepOutput {
+            results,
+            best_params: HashMap::new(),
+            summary: SweepSummary {
+                total_runs: 9,
+                successful: 9,
+                failed: 0,
+                total_duration: Duration::ZERO,
+            },
+        };
+
+        let table = output.table();
+        assert_eq!(table.names, ["x", "y"]);
+        assert_eq!(table.objective[0], 5.0);
+        assert_eq!(table.argmin(), Some(4));
+        assert_eq!(table.local_minima(), [4, 8]);
+
+        let profile = table.profile(&["x"]).unwrap();
+        assert_eq!(profile.axes, [vec![0.0, 1.0, 2.0]]);
+        assert_eq!(profile.values, [4.0, 1.0, 3.0]);
+        let surface = table.profile(&["y", "x"]).unwrap();
+        assert_eq!(surface.values.len(), 9);
+        assert_eq!(surface.values[2 * 3 + 1], 4.0);
+        assert!(table.profile(&["z"]).is_err());
+        assert!(table.profile(&[]).is_err());
+    }
+
+    #[cfg(feature = "provenance")]
+    #[test]
+    fn test_provenance_record_links_template() {
+        let job = Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Simulate { program: "rabi".to_string() },
+            priority: Priority::Normal,
+            params: HashMap::from([("omega".to_string(), 1.5)]),
+            config: JobConfig::default(),
+            depends_on: Vec::new(),
+        };
+        let output = JobOutput::Simulation(SimulationOutput {
+            states: Vec::new(),
+            diagnostics: DiagnosticsSummary {
+                max_trace_drift: 0.0,
+                min_eigenvalue: 0.0,
+                warnings: Vec::new(),
+            },
+        });
+
+        let mut graph = ProvenanceGraph::new();
+        let result = graph.record(&provenance_record(&job, &output));
+        assert_eq!(result, format!("result:{}", job.id));
+        let lineage: Vec<String> =
+            graph.lineage(&result).unwrap().nodes().map(|(id, _)| id.clone()).collect();
+        assert!(lineage.contains(&"template:rabi@1.0".to_string()));
+        assert!(!lineage.iter().any(|id| id.starts_with("certificate:")));
+    }
+
+    #[cfg(all(feature = "provenance", feature = "parser", feature = "prover"))]
+    #[tokio::test]
+    async fn test_proof_job_records_its_certificate() {
+        let job = |statement: &str| Job {
+            id: Uuid::new_v4(),
+            kind: JobKind::Prove { statement: statement.to_string() },
+            priority: Priority::Normal,
+            params: HashMap::new(),
+            config: JobConfig::default(),
+            depends_on: Vec::new(),
+        };
+        let cancel = CancellationToken::default();
+
+        let proven = job("dagger(A * B) == dagger(B) * dagger(A)");
+        let output = execute_job(&proven, &cancel).await.unwrap();
+        let JobOutput::Proof(ProofOutput { proven: true, certificate_hash: Some(hash), .. }) = &output else {
+            panic!("expected a proof with a certificate: {:?}", output);
+        };
+        let mut graph = ProvenanceGraph::new();
+        let result = graph.record(&provenance_record(&proven, &output));
+        let lineage: Vec<String> =
+            graph.lineage(&result).unwrap().nodes().map(|(id, _)| id.clone()).collect();
+        assert!(lineage.contains(&format!("certificate:{}", hash)));
+
+        let refuted = execute_job(&job("A * B == B * A"), &cancel).await.unwrap();
+        assert!(matches!(
+            refuted,
+            JobOutput::Proof(ProofOutput { proven: false, certificate_hash: None, .. })
+        ));
     }
 }
@@ -1,11 +1,15 @@
 //! CPU kernels for linear algebra operations
 
 use crate::error::{EngineError, Result};
-use ndarray::{Array1, Array2};
-use ndarray_linalg::{Eigh, Inverse, UPLO};
+use ndarray::{Array1, Array2, ShapeBuilder};
+use ndarray_linalg::{Eigh, UPLO};
 use num_complex::Complex64;
 
-/// Matrix exponential via eigendecomposition
+/// Matrix exponential
+///
+/// Hermitian and anti-Hermitian matrices, such as the `-iH dt` of a
+/// propagator, go through the eigendecomposition of a Hermitian matrix; any
+/// other matrix through scaling and squaring of its Taylor series.
 pub fn matrix_exp(mat: &Array2<Complex64>) -> Result<Array2<Complex64>> {
     let (n, m) = mat.dim();
     if n != m {
@@ -15,29 +19,94 @@ pub fn matrix_exp(mat: &Array2<Complex64>) -> Result<Array2<Complex64>> {
         ));
     }
 
-    // For small matrices (2x2, 4x4), use direct eigendecomposition
-    let (eigenvalues, eigenvectors) = mat
-        .eigh(UPLO::Upper)
-        .map_err(|e| EngineError::ExecutionError(format!("Eigendecomposition failed: {}", e)))?;
-
-    // Compute exp(eigenvalues)
-    let exp_eigenvalues = eigenvalues.mapv(|lambda| Complex64::new(lambda.exp(), 0.0));
+    check_finite(mat, "A", "before exponentiation")?;
+
+    let scale = mat.iter().map(|z| z.norm()).fold(1.0_f64, f64::max);
+    let deviation = |sign: f64| {
+        mat.indexed_iter()
+            .map(|((i, j), z)| (z - mat[[j, i]].conj() * sign).norm())
+            .fold(0.0_f64, f64::max)
+    };
+    let result = if deviation(1.0) <= 1e-12 * scale {
+        exp_hermitian(mat, |lambda| Complex64::new(lambda.exp(), 0.0))?
+    } else if deviation(-1.0) <= 1e-12 * scale {
+        // A = -iK with K = iA Hermitian, so exp(A) has eigenvalues e^{-iλ}
+        let k = mat.mapv(|z| z * Complex64::i());
+        exp_hermitian(&k, |lambda| Complex64::new(0.0, -lambda).exp())?
+    } else {
+        exp_taylor(mat)
+    };
+
+    check_finite(&result, "exp(A)", "after exponentiation")?;
+    Ok(result)
+}
 
-    // Reconstruct: V * diag(exp(λ)) * V†
+/// `V f(Λ) V†` for the Hermitian `h = V Λ V†`
+fn exp_hermitian(h: &Array2<Complex64>, f: impl Fn(f64) -> Complex64) -> Result<Array2<Complex64>> {
+    let (eigenvalues, eigenvectors) = eigh_column_major(h)?;
     let n = eigenvalues.len();
-    let mut result = Array2::zeros((n, n));
+    let scaled = Array2::from_shape_fn((n, n), |(i, k)| eigenvectors[[i, k]] * f(eigenvalues[k]));
+    Ok(scaled.dot(&dagger(&eigenvectors)))
+}
 
-    for i in 0..n {
-        for j in 0..n {
-            let mut sum = Complex64::new(0.0, 0.0);
-            for k in 0..n {
-                sum += eigenvectors[[i, k]] * exp_eigenvalues[k] * eigenvectors[[j, k]].conj();
-            }
-            result[[i, j]] = sum;
+/// `exp(A) = exp(A / 2^s)^(2^s)`, with `s` bringing the norm under 1/2 so
+/// the series converges in a few terms
+fn exp_taylor(a: &Array2<Complex64>) -> Array2<Complex64> {
+    let n = a.nrows();
+    let norm = a
+        .rows()
+        .into_iter()
+        .map(|row| row.iter().map(|z| z.norm()).sum::<f64>())
+        .fold(0.0_f64, f64::max);
+    let squarings = if norm > 0.5 { (norm / 0.5).log2().ceil() as i32 } else { 0 };
+    let b = a.mapv(|z| z / 2f64.powi(squarings));
+
+    let mut result = Array2::<Complex64>::eye(n);
+    let mut term = Array2::<Complex64>::eye(n);
+    for k in 1..=30 {
+        term = term.dot(&b).mapv(|z| z / k as f64);
+        result += &term;
+        if term.iter().all(|z| z.norm() < f64::EPSILON) {
+            break;
         }
     }
+    for _ in 0..squarings {
+        result = result.dot(&result);
+    }
+    result
+}
 
-    Ok(result)
+/// Fail on the first NaN or infinite entry of `mat`, with an error such as
+/// "NaN first appeared at t=3.2 in rho[1,1]"
+pub fn check_finite(mat: &Array2<Complex64>, name: &str, context: impl std::fmt::Display) -> Result<()> {
+    match mat.indexed_iter().find_map(|(index, z)| non_finite_kind(*z).map(|kind| (index, kind))) {
+        Some(((i, j), kind)) => Err(EngineError::non_finite(format!(
+            "{} first appeared {} in {}[{},{}]",
+            kind, context, name, i, j
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// [`check_finite`] for a state vector
+pub fn check_finite_ket(ket: &Array1<Complex64>, name: &str, context: impl std::fmt::Display) -> Result<()> {
+    match ket.iter().enumerate().find_map(|(i, z)| non_finite_kind(*z).map(|kind| (i, kind))) {
+        Some((i, kind)) => Err(EngineError::non_finite(format!(
+            "{} first appeared {} in {}[{}]",
+            kind, context, name, i
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn non_finite_kind(z: Complex64) -> Option<&'static str> {
+    if z.re.is_nan() || z.im.is_nan() {
+        Some("NaN")
+    } else if z.re.is_infinite() || z.im.is_infinite() {
+        Some("Inf")
+    } else {
+        None
+    }
 }
 
 /// Tensor product of two matrices
@@ -109,6 +178,24 @@ pub fn apply_unitary_rho(
     Ok(temp.dot(&u_dagger))
 }
 
+/// Apply a channel in Kraus form: ρ' = Σ_k K_k ρ K_k†
+pub fn apply_kraus(
+    kraus: &[Array2<Complex64>],
+    rho: &Array2<Complex64>,
+) -> Result<Array2<Complex64>> {
+    let mut out = Array2::zeros(rho.raw_dim());
+    for k in kraus {
+        if k.dim() != rho.dim() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{:?} Kraus operator", rho.dim()),
+                format!("{:?}", k.dim()),
+            ));
+        }
+        out += &apply_unitary_rho(k, rho)?;
+    }
+    Ok(out)
+}
+
 /// Compute expectation value: ⟨O⟩ = Tr(Oρ)
 pub fn expectation(
     observable: &Array2<Complex64>,
@@ -155,11 +242,238 @@ pub fn ket_to_rho(ket: &Array1<Complex64>) -> Array2<Complex64> {
     rho
 }
 
+/// Reduced density matrix of the subsystems `keep` of a state on subsystems
+/// of dimensions `dims`
+///
+/// Subsystems are ordered as in [`tensor_product`], the first one most
+/// significant, and the kept subsystems stay in that order.
+pub fn partial_trace(
+    rho: &Array2<Complex64>,
+    dims: &[usize],
+    keep: &[usize],
+) -> Result<Array2<Complex64>> {
+    let (n, m) = rho.dim();
+    let total: usize = dims.iter().product();
+    if n != m || n != total {
+        return Err(EngineError::dimension_mismatch(
+            format!("{}x{} for subsystem dimensions {:?}", total, total, dims),
+            format!("{}x{}", n, m),
+        ));
+    }
+
+    let mut kept = keep.to_vec();
+    kept.sort_unstable();
+    kept.dedup();
+    if kept.len() != keep.len() {
+        return Err(EngineError::validation_error(format!(
+            "Subsystems {:?} listed more than once",
+            keep
+        )));
+    }
+    if let Some(&k) = kept.iter().find(|&&k| k >= dims.len()) {
+        return Err(EngineError::validation_error(format!(
+            "Subsystem {} out of range for {} subsystems",
+            k,
+            dims.len()
+        )));
+    }
+    let traced: Vec<usize> = (0..dims.len()).filter(|k| !kept.contains(k)).collect();
+
+    let mut strides = vec![1; dims.len()];
+    for k in (0..dims.len().saturating_sub(1)).rev() {
+        strides[k] = strides[k + 1] * dims[k + 1];
+    }
+    // Flat offsets of every basis state of a group of subsystems
+    let offsets = |subsystems: &[usize]| {
+        subsystems.iter().fold(vec![0], |offsets: Vec<usize>, &k| {
+            let (dim, stride) = (dims[k], strides[k]);
+            offsets
+                .iter()
+                .flat_map(|&offset| (0..dim).map(move |i| offset + i * stride))
+                .collect::<Vec<_>>()
+        })
+    };
+    let kept_offsets = offsets(&kept);
+    let traced_offsets = offsets(&traced);
+
+    let d = kept_offsets.len();
+    let mut reduced = Array2::zeros((d, d));
+    for (a, &row) in kept_offsets.iter().enumerate() {
+        for (b, &col) in kept_offsets.iter().enumerate() {
+            reduced[[a, b]] = traced_offsets
+                .iter()
+                .map(|&offset| rho[[row + offset, col + offset]])
+                .sum();
+        }
+    }
+
+    Ok(reduced)
+}
+
+/// `operator` acting on subsystem `subsystem` of a composite space of
+/// subsystem dimensions `dims`, padded with identities on the others
+///
+/// Subsystems are ordered as in [`tensor_product`], so this is
+/// `I ⊗ … ⊗ operator ⊗ … ⊗ I`.
+pub fn embed_operator(
+    operator: &Array2<Complex64>,
+    dims: &[usize],
+    subsystem: usize,
+) -> Result<Array2<Complex64>> {
+    match dims.get(subsystem) {
+        Some(&d) if operator.dim() == (d, d) => {}
+        Some(&d) => {
+            return Err(EngineError::dimension_mismatch(
+                format!("{}x{} operator for subsystem {} of {:?}", d, d, subsystem, dims),
+                format!("{}x{}", operator.nrows(), operator.ncols()),
+            ))
+        }
+        None => {
+            return Err(EngineError::validation_error(format!(
+                "Subsystem {} out of range for {} subsystems",
+                subsystem,
+                dims.len()
+            )))
+        }
+    }
+
+    dims.iter()
+        .enumerate()
+        .try_fold(Array2::eye(1), |product, (k, &d)| {
+            if k == subsystem {
+                tensor_product(&product, operator)
+            } else {
+                tensor_product(&product, &Array2::eye(d))
+            }
+        })
+}
+
+/// Subsystem dimensions of a register of qubits with Hilbert space
+/// dimension `dim`
+pub fn qubit_dims(dim: usize) -> Result<Vec<usize>> {
+    if dim < 2 || !dim.is_power_of_two() {
+        return Err(EngineError::validation_error(format!(
+            "Dimension {} is not a register of qubits; give the subsystem dimensions",
+            dim
+        )));
+    }
+    Ok(vec![2; dim.trailing_zeros() as usize])
+}
+
+/// Von Neumann entropy `-Tr(ρ log2 ρ)` in bits; for a pure bipartite state,
+/// the entropy of either reduced state measures its entanglement
+pub fn von_neumann_entropy(rho: &Array2<Complex64>) -> Result<f64> {
+    let (eigenvalues, _) = rho
+        .eigh(UPLO::Lower)
+        .map_err(|e| EngineError::ExecutionError(format!("Diagonalisation failed: {}", e)))?;
+    Ok(eigenvalues
+        .iter()
+        .filter(|&&p| p > 1e-15)
+        .map(|&p| -p * p.log2())
+        .sum())
+}
+
+/// Eigenvalues and eigenvectors of a Hermitian matrix
+#[derive(Debug, Clone)]
+pub struct Eigendecomposition {
+    /// In ascending order
+    pub eigenvalues: Array1<f64>,
+    /// Column `k` is the normalised eigenvector of `eigenvalues[k]`
+    pub eigenvectors: Array2<Complex64>,
+}
+
+impl Eigendecomposition {
+    /// The eigenvector of level `k`, counting from the lowest
+    pub fn eigenstate(&self, k: usize) -> Result<Array1<Complex64>> {
+        if k >= self.eigenvalues.len() {
+            return Err(EngineError::validation_error(format!(
+                "Eigenstate {} out of range for {} levels",
+                k,
+                self.eigenvalues.len()
+            )));
+        }
+        Ok(self.eigenvectors.column(k).to_owned())
+    }
+
+    /// Bohr frequencies `E_j - E_i`, with `[i, j]` the transition from
+    /// level `i` to level `j`
+    pub fn bohr_frequencies(&self) -> Array2<f64> {
+        let n = self.eigenvalues.len();
+        Array2::from_shape_fn((n, n), |(i, j)| self.eigenvalues[j] - self.eigenvalues[i])
+    }
+
+    /// `V† A V`: the operator or density matrix `a` in the eigenbasis
+    pub fn to_eigenbasis(&self, a: &Array2<Complex64>) -> Array2<Complex64> {
+        dagger(&self.eigenvectors).dot(a).dot(&self.eigenvectors)
+    }
+}
+
+/// Diagonalise the Hermitian matrix `h`
+pub fn eigendecompose(h: &Array2<Complex64>) -> Result<Eigendecomposition> {
+    let (n, m) = h.dim();
+    if n != m {
+        return Err(EngineError::dimension_mismatch(
+            format!("{}x{}", n, n),
+            format!("{}x{}", n, m),
+        ));
+    }
+    check_finite(h, "H", "before diagonalisation")?;
+    let deviation = h
+        .indexed_iter()
+        .map(|((i, j), z)| (z - h[[j, i]].conj()).norm())
+        .fold(0.0_f64, f64::max);
+    if deviation > crate::VALIDATION_TOL {
+        return Err(EngineError::NotHermitian { deviation });
+    }
+
+    let (eigenvalues, eigenvectors) = eigh_column_major(h)?;
+    Ok(Eigendecomposition {
+        eigenvalues,
+        eigenvectors,
+    })
+}
+
+/// `eigh` of a Hermitian matrix, on a column-major copy: LAPACK reads a
+/// row-major matrix as its transpose `conj(h)`, whose eigenvectors are the
+/// conjugates of those of `h`
+pub(crate) fn eigh_column_major(h: &Array2<Complex64>) -> Result<(Array1<f64>, Array2<Complex64>)> {
+    let mut column_major = Array2::zeros(h.raw_dim().f());
+    column_major.assign(h);
+    column_major
+        .eigh(UPLO::Lower)
+        .map_err(|e| EngineError::ExecutionError(format!("Diagonalisation failed: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use approx::assert_relative_eq;
 
+    #[test]
+    fn test_matrix_exp() {
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        // exp(-iσx t) = cos t - i sin t σx
+        let t = 0.3;
+        let generator = Array2::from_shape_vec((2, 2), vec![c(0.0, 0.0), c(0.0, -t), c(0.0, -t), c(0.0, 0.0)]).unwrap();
+        let u = matrix_exp(&generator).unwrap();
+        assert_relative_eq!(u[[0, 0]].re, t.cos(), epsilon = 1e-12);
+        assert_relative_eq!(u[[0, 1]].im, -t.sin(), epsilon = 1e-12);
+        assert_relative_eq!(u[[1, 0]].im, -t.sin(), epsilon = 1e-12);
+
+        // Hermitian: exp(σz) = diag(e, 1/e)
+        let sz = Array2::from_shape_vec((2, 2), vec![c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(-1.0, 0.0)]).unwrap();
+        let e = matrix_exp(&sz).unwrap();
+        assert_relative_eq!(e[[0, 0]].re, 1f64.exp(), epsilon = 1e-12);
+        assert_relative_eq!(e[[1, 1]].re, (-1f64).exp(), epsilon = 1e-12);
+
+        // Neither: exp([[0, 3], [0, 0]]) = [[1, 3], [0, 1]]
+        let nilpotent = Array2::from_shape_vec((2, 2), vec![c(0.0, 0.0), c(3.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)]).unwrap();
+        let e = matrix_exp(&nilpotent).unwrap();
+        assert_relative_eq!(e[[0, 1]].re, 3.0, epsilon = 1e-12);
+        assert_relative_eq!(e[[1, 0]].norm(), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(e[[1, 1]].re, 1.0, epsilon = 1e-12);
+    }
+
     #[test]
     fn test_dagger() {
         let mat = Array2::from_shape_vec(
@@ -206,4 +520,69 @@ mod tests {
         assert_eq!(rho[[0, 0]], Complex64::new(1.0, 0.0));
         assert_eq!(rho[[1, 1]], Complex64::new(0.0, 0.0));
     }
+
+    #[test]
+    fn test_partial_trace() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let a = Array2::from_shape_vec((2, 2), vec![c(0.7), c(0.1), c(0.1), c(0.3)]).unwrap();
+        let b = Array2::from_shape_fn((3, 3), |(i, j)| if i == j { c(1.0 / 3.0) } else { c(0.0) });
+        let rho = tensor_product(&a, &b).unwrap();
+
+        let reduced_a = partial_trace(&rho, &[2, 3], &[0]).unwrap();
+        let reduced_b = partial_trace(&rho, &[2, 3], &[1]).unwrap();
+        assert!((&reduced_a - &a).iter().all(|x| x.norm() < 1e-12));
+        assert!((&reduced_b - &b).iter().all(|x| x.norm() < 1e-12));
+        assert_eq!(partial_trace(&rho, &[2, 3], &[0, 1]).unwrap(), rho);
+
+        assert!(partial_trace(&rho, &[2, 2], &[0]).is_err());
+        assert!(partial_trace(&rho, &[2, 3], &[2]).is_err());
+
+        // Embedding and tracing back out recovers the operator, scaled by
+        // the dimension of the identity it was padded with
+        let embedded = embed_operator(&a, &[3, 2], 1).unwrap();
+        assert_eq!(embedded.dim(), (6, 6));
+        let traced = partial_trace(&embedded, &[3, 2], &[1]).unwrap();
+        assert!((&traced - &a.mapv(|x| x * 3.0)).iter().all(|x| x.norm() < 1e-12));
+        assert!(embed_operator(&a, &[3, 2], 0).is_err());
+        assert!(embed_operator(&a, &[3, 2], 2).is_err());
+    }
+
+    #[test]
+    fn test_bell_state_entanglement() {
+        let s = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+        let zero = Complex64::new(0.0, 0.0);
+        let bell = ket_to_rho(&Array1::from_vec(vec![s, zero, zero, s]));
+
+        let dims = qubit_dims(4).unwrap();
+        assert_eq!(dims, vec![2, 2]);
+        let reduced = partial_trace(&bell, &dims, &[1]).unwrap();
+        assert_relative_eq!(reduced[[0, 0]].re, 0.5, epsilon = 1e-12);
+        assert_relative_eq!(reduced[[0, 1]].norm(), 0.0, epsilon = 1e-12);
+
+        assert_relative_eq!(von_neumann_entropy(&reduced).unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(von_neumann_entropy(&bell).unwrap(), 0.0, epsilon = 1e-10);
+        assert!(qubit_dims(6).is_err());
+    }
+
+    #[test]
+    fn test_eigendecompose() {
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        // σy, with levels ∓1
+        let h = Array2::from_shape_vec((2, 2), vec![c(0.0, 0.0), c(0.0, -1.0), c(0.0, 1.0), c(0.0, 0.0)]).unwrap();
+        let eig = eigendecompose(&h).unwrap();
+        assert_relative_eq!(eig.eigenvalues[0], -1.0, epsilon = 1e-12);
+        assert_relative_eq!(eig.eigenvalues[1], 1.0, epsilon = 1e-12);
+        assert_relative_eq!(eig.bohr_frequencies()[[0, 1]], 2.0, epsilon = 1e-12);
+
+        let ground = eig.eigenstate(0).unwrap();
+        let h_ground = h.dot(&ground);
+        assert!((&h_ground + &ground).iter().all(|z| z.norm() < 1e-12));
+        let diagonal = eig.to_eigenbasis(&h);
+        assert_relative_eq!(diagonal[[1, 1]].re, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(diagonal[[0, 1]].norm(), 0.0, epsilon = 1e-12);
+
+        assert!(eig.eigenstate(2).is_err());
+        let upper = Array2::from_shape_vec((2, 2), vec![c(0.0, 0.0), c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)]).unwrap();
+        assert!(matches!(eigendecompose(&upper), Err(EngineError::NotHermitian { .. })));
+    }
 }
@@ -0,0 +1,614 @@
+//! GPU kernels for dense linear algebra (CUDA, via cudarc)
+//!
+//! Complex matrices live on the device as interleaved `(re, im)` doubles in
+//! row-major order. Kernels are compiled from CUDA C with NVRTC once per
+//! process, the first time [`GpuContext::shared`] is called. Besides single
+//! matrix products, tensor products and exponentials, whole trajectories
+//! run on the device: unitary evolution reuses `exp(-iH dt)` while the step
+//! is constant, and RK4 for the Lindblad equation keeps ρ and every stage on
+//! the device, copying back only the states on the time grid.
+//!
+//! Without the `gpu` feature this module only provides a [`GpuContext`]
+//! that cannot be created, so selecting [`BackendType::Gpu`] reports that
+//! the feature is missing.
+//!
+//! [`BackendType::Gpu`]: crate::executor::BackendType::Gpu
+
+#[cfg(feature = "gpu")]
+pub use cuda::GpuContext;
+#[cfg(not(feature = "gpu"))]
+pub use disabled::GpuContext;
+
+/// Smallest dimension sent to the GPU by default; for smaller matrices
+/// transfers and kernel launches cost more than the arithmetic
+pub const DEFAULT_MIN_DIM: usize = 64;
+
+/// Degree of the Taylor polynomial used after scaling to norm ≤ 1/2,
+/// accurate to below 1e-20
+#[cfg(feature = "gpu")]
+const TAYLOR_DEGREE: usize = 16;
+
+#[cfg(feature = "gpu")]
+mod cuda {
+    use super::TAYLOR_DEGREE;
+    use crate::error::{EngineError, Result};
+    use cudarc::driver::{CudaDevice, CudaFunction, CudaSlice, LaunchAsync, LaunchConfig};
+    use ndarray::{Array1, Array2};
+    use num_complex::Complex64;
+    use once_cell::sync::OnceCell;
+    use std::sync::Arc;
+
+    const MODULE: &str = "qte_kernels";
+    const KERNEL_NAMES: &[&str] = &["cmatmul", "ckron", "caxpby", "caxpy", "cidentity_plus"];
+    const KERNELS: &str = r#"
+// c (n x m) = a (n x k) * b (k x m)
+extern "C" __global__ void cmatmul(const double* a, const double* b, double* c, int n, int k, int m) {
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= n * m) return;
+    int i = idx / m, j = idx % m;
+    double re = 0.0, im = 0.0;
+    for (int l = 0; l < k; ++l) {
+        double ar = a[2 * (i * k + l)], ai = a[2 * (i * k + l) + 1];
+        double br = b[2 * (l * m + j)], bi = b[2 * (l * m + j) + 1];
+        re += ar * br - ai * bi;
+        im += ar * bi + ai * br;
+    }
+    c[2 * idx] = re;
+    c[2 * idx + 1] = im;
+}
+
+// c (na nb x ma mb) = a (na x ma) ⊗ b (nb x mb)
+extern "C" __global__ void ckron(const double* a, const double* b, double* c, int na, int ma, int nb, int mb) {
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    int cols = ma * mb;
+    if (idx >= na * nb * cols) return;
+    int row = idx / cols, col = idx % cols;
+    int ia = 2 * ((row / nb) * ma + col / mb), ib = 2 * ((row % nb) * mb + col % mb);
+    c[2 * idx] = a[ia] * b[ib] - a[ia + 1] * b[ib + 1];
+    c[2 * idx + 1] = a[ia] * b[ib + 1] + a[ia + 1] * b[ib];
+}
+
+// out = alpha x + beta y over len complex entries
+extern "C" __global__ void caxpby(const double* x, const double* y, double* out, double ar, double ai, double br, double bi, int len) {
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= len) return;
+    double xr = x[2 * idx], xi = x[2 * idx + 1], yr = y[2 * idx], yi = y[2 * idx + 1];
+    out[2 * idx] = ar * xr - ai * xi + br * yr - bi * yi;
+    out[2 * idx + 1] = ar * xi + ai * xr + br * yi + bi * yr;
+}
+
+// y += alpha x over len complex entries
+extern "C" __global__ void caxpy(const double* x, double* y, double ar, double ai, int len) {
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= len) return;
+    double xr = x[2 * idx], xi = x[2 * idx + 1];
+    y[2 * idx] += ar * xr - ai * xi;
+    y[2 * idx + 1] += ar * xi + ai * xr;
+}
+
+// out = I + s x for an n x n matrix x
+extern "C" __global__ void cidentity_plus(const double* x, double* out, double s, int n) {
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= n * n) return;
+    out[2 * idx] = s * x[2 * idx] + (idx / n == idx % n ? 1.0 : 0.0);
+    out[2 * idx + 1] = s * x[2 * idx + 1];
+}
+"#;
+
+    static SHARED: OnceCell<std::result::Result<Arc<GpuContext>, String>> = OnceCell::new();
+
+    fn cuda_error(e: impl std::fmt::Display) -> EngineError {
+        EngineError::ExecutionError(format!("CUDA: {}", e))
+    }
+
+    /// Complex matrix resident on the device
+    struct DeviceMatrix {
+        data: CudaSlice<f64>,
+        rows: usize,
+        cols: usize,
+    }
+
+    impl DeviceMatrix {
+        fn len(&self) -> usize {
+            self.rows * self.cols
+        }
+    }
+
+    /// A CUDA device with the engine's kernels loaded
+    pub struct GpuContext {
+        device: Arc<CudaDevice>,
+    }
+
+    impl GpuContext {
+        /// Open device `ordinal` and compile the kernels
+        pub fn new(ordinal: usize) -> Result<Self> {
+            let device = CudaDevice::new(ordinal).map_err(cuda_error)?;
+            let ptx = cudarc::nvrtc::compile_ptx(KERNELS).map_err(cuda_error)?;
+            device
+                .load_ptx(ptx, MODULE, KERNEL_NAMES)
+                .map_err(cuda_error)?;
+            Ok(GpuContext { device })
+        }
+
+        /// Context on device 0, created on first use and shared by every
+        /// executor in the process
+        pub fn shared() -> Result<Arc<Self>> {
+            SHARED
+                .get_or_init(|| GpuContext::new(0).map(Arc::new).map_err(|e| e.to_string()))
+                .clone()
+                .map_err(EngineError::ExecutionError)
+        }
+
+        pub fn matmul(
+            &self,
+            a: &Array2<Complex64>,
+            b: &Array2<Complex64>,
+        ) -> Result<Array2<Complex64>> {
+            if a.ncols() != b.nrows() {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{} rows", a.ncols()),
+                    format!("{}", b.nrows()),
+                ));
+            }
+            let (a, b) = (self.upload(a)?, self.upload(b)?);
+            let mut c = self.zeros(a.rows, b.cols)?;
+            self.matmul_into(&a, &b, &mut c)?;
+            self.download(&c)
+        }
+
+        pub fn tensor_product(
+            &self,
+            a: &Array2<Complex64>,
+            b: &Array2<Complex64>,
+        ) -> Result<Array2<Complex64>> {
+            let (a, b) = (self.upload(a)?, self.upload(b)?);
+            let mut c = self.zeros(a.rows * b.rows, a.cols * b.cols)?;
+            let len = c.len();
+            self.launch(
+                "ckron",
+                len,
+                (
+                    &a.data,
+                    &b.data,
+                    &mut c.data,
+                    a.rows as i32,
+                    a.cols as i32,
+                    b.rows as i32,
+                    b.cols as i32,
+                ),
+            )?;
+            self.download(&c)
+        }
+
+        /// Matrix exponential by scaling and squaring
+        pub fn matrix_exp(&self, a: &Array2<Complex64>) -> Result<Array2<Complex64>> {
+            let exp = self.expm_device(a)?;
+            self.download(&exp)
+        }
+
+        /// `|ψ(t)⟩` on `times`, as [`crate::ode::evolve_unitary`]
+        pub fn evolve_unitary(
+            &self,
+            hamiltonian: &Array2<Complex64>,
+            initial_ket: &Array1<Complex64>,
+            times: &[f64],
+        ) -> Result<Vec<Array1<Complex64>>> {
+            let n = initial_ket.len();
+            let mut states = Vec::with_capacity(times.len());
+            states.push(initial_ket.clone());
+
+            let column = initial_ket.clone().into_shape((n, 1)).map_err(cuda_error)?;
+            let mut psi = self.upload(&column)?;
+            let mut next = self.zeros(n, 1)?;
+            let mut propagator: Option<(f64, DeviceMatrix)> = None;
+            for i in 1..times.len() {
+                let dt = times[i] - times[i - 1];
+                let u = match propagator.take() {
+                    Some((step, u)) if step == dt => u,
+                    _ => self.expm_device(&hamiltonian.mapv(|x| x * Complex64::new(0.0, -dt)))?,
+                };
+                self.matmul_into(&u, &psi, &mut next)?;
+                std::mem::swap(&mut psi, &mut next);
+                states.push(self.download(&psi)?.column(0).to_owned());
+                propagator = Some((dt, u));
+            }
+            Ok(states)
+        }
+
+        /// ρ(t) on `times` from fixed-step RK4 for the Lindblad equation, as
+        /// [`crate::ode::Rk4Integrator`]
+        pub fn integrate_rk4(
+            &self,
+            hamiltonian: &Array2<Complex64>,
+            lindblad_ops: &[(Array2<Complex64>, f64)],
+            initial_rho: &Array2<Complex64>,
+            times: &[f64],
+        ) -> Result<Vec<Array2<Complex64>>> {
+            if times.is_empty() {
+                return Err(EngineError::validation_error("Empty time grid"));
+            }
+            let n = initial_rho.nrows();
+            let mut generator = LindbladDevice::new(self, hamiltonian, lindblad_ops)?;
+            let mut rho = self.upload(initial_rho)?;
+            let mut k = self.zeros(n, n)?;
+            let mut stage = self.zeros(n, n)?;
+            let mut next = self.zeros(n, n)?;
+            let real = |x: f64| Complex64::new(x, 0.0);
+            let one = real(1.0);
+
+            let mut states = Vec::with_capacity(times.len());
+            states.push(initial_rho.clone());
+            for i in 1..times.len() {
+                let dt = times[i] - times[i - 1];
+                if dt <= 0.0 {
+                    return Err(EngineError::validation_error(
+                        "Time grid must be increasing",
+                    ));
+                }
+
+                generator.rhs(self, &rho, &mut k)?;
+                self.axpby_into(&k, &rho, real(dt / 6.0), one, &mut next)?;
+                self.axpby_into(&k, &rho, real(dt / 2.0), one, &mut stage)?;
+                generator.rhs(self, &stage, &mut k)?;
+                self.axpy(&k, &mut next, real(dt / 3.0))?;
+                self.axpby_into(&k, &rho, real(dt / 2.0), one, &mut stage)?;
+                generator.rhs(self, &stage, &mut k)?;
+                self.axpy(&k, &mut next, real(dt / 3.0))?;
+                self.axpby_into(&k, &rho, real(dt), one, &mut stage)?;
+                generator.rhs(self, &stage, &mut k)?;
+                self.axpy(&k, &mut next, real(dt / 6.0))?;
+
+                std::mem::swap(&mut rho, &mut next);
+                states.push(self.download(&rho)?);
+            }
+            Ok(states)
+        }
+
+        fn expm_device(&self, a: &Array2<Complex64>) -> Result<DeviceMatrix> {
+            let n = a.nrows();
+            if a.ncols() != n {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{}x{}", n, n),
+                    format!("{}x{}", n, a.ncols()),
+                ));
+            }
+
+            // Scale to 1-norm ≤ 1/2, where the Taylor series converges fast
+            let norm = a
+                .columns()
+                .into_iter()
+                .map(|col| col.iter().map(|z| z.norm()).sum::<f64>())
+                .fold(0.0, f64::max);
+            let squarings = if norm > 0.5 {
+                (norm / 0.5).log2().ceil() as u32
+            } else {
+                0
+            };
+            let scale = 0.5f64.powi(squarings as i32);
+            let x = self.upload(&a.mapv(|z| z * scale))?;
+
+            // Horner: T = I + X/k (I + X/(k+1) (...))
+            let mut t = self.upload(&Array2::eye(n))?;
+            let mut product = self.zeros(n, n)?;
+            for k in (1..=TAYLOR_DEGREE).rev() {
+                self.matmul_into(&x, &t, &mut product)?;
+                self.launch(
+                    "cidentity_plus",
+                    n * n,
+                    (&product.data, &mut t.data, 1.0 / k as f64, n as i32),
+                )?;
+            }
+            for _ in 0..squarings {
+                self.matmul_into(&t, &t, &mut product)?;
+                std::mem::swap(&mut t, &mut product);
+            }
+            Ok(t)
+        }
+
+        fn matmul_into(
+            &self,
+            a: &DeviceMatrix,
+            b: &DeviceMatrix,
+            c: &mut DeviceMatrix,
+        ) -> Result<()> {
+            let len = c.len();
+            self.launch(
+                "cmatmul",
+                len,
+                (
+                    &a.data,
+                    &b.data,
+                    &mut c.data,
+                    a.rows as i32,
+                    a.cols as i32,
+                    b.cols as i32,
+                ),
+            )
+        }
+
+        /// `out = alpha x + beta y`
+        fn axpby_into(
+            &self,
+            x: &DeviceMatrix,
+            y: &DeviceMatrix,
+            alpha: Complex64,
+            beta: Complex64,
+            out: &mut DeviceMatrix,
+        ) -> Result<()> {
+            let len = out.len();
+            self.launch(
+                "caxpby",
+                len,
+                (
+                    &x.data,
+                    &y.data,
+                    &mut out.data,
+                    alpha.re,
+                    alpha.im,
+                    beta.re,
+                    beta.im,
+                    len as i32,
+                ),
+            )
+        }
+
+        /// `y += alpha x`
+        fn axpy(&self, x: &DeviceMatrix, y: &mut DeviceMatrix, alpha: Complex64) -> Result<()> {
+            let len = y.len();
+            self.launch(
+                "caxpy",
+                len,
+                (&x.data, &mut y.data, alpha.re, alpha.im, len as i32),
+            )
+        }
+
+        fn launch<Params>(&self, kernel: &str, threads: usize, params: Params) -> Result<()>
+        where
+            CudaFunction: LaunchAsync<Params>,
+        {
+            let function = self.device.get_func(MODULE, kernel).ok_or_else(|| {
+                EngineError::Internal(format!("CUDA kernel '{}' is not loaded", kernel))
+            })?;
+            let config = LaunchConfig::for_num_elems(threads as u32);
+            // SAFETY: every kernel in KERNELS bounds-checks its thread index,
+            // and callers pass buffers of the sizes its signature expects
+            unsafe { function.launch(config, params) }.map_err(cuda_error)
+        }
+
+        fn upload(&self, a: &Array2<Complex64>) -> Result<DeviceMatrix> {
+            let host: Vec<f64> = a.iter().flat_map(|z| [z.re, z.im]).collect();
+            Ok(DeviceMatrix {
+                data: self.device.htod_sync_copy(&host).map_err(cuda_error)?,
+                rows: a.nrows(),
+                cols: a.ncols(),
+            })
+        }
+
+        fn download(&self, a: &DeviceMatrix) -> Result<Array2<Complex64>> {
+            let host = self.device.dtoh_sync_copy(&a.data).map_err(cuda_error)?;
+            let values = host
+                .chunks_exact(2)
+                .map(|z| Complex64::new(z[0], z[1]))
+                .collect();
+            Array2::from_shape_vec((a.rows, a.cols), values).map_err(cuda_error)
+        }
+
+        fn zeros(&self, rows: usize, cols: usize) -> Result<DeviceMatrix> {
+            Ok(DeviceMatrix {
+                data: self
+                    .device
+                    .alloc_zeros(2 * rows * cols)
+                    .map_err(cuda_error)?,
+                rows,
+                cols,
+            })
+        }
+    }
+
+    /// Lindblad generator on the device, written with the effective
+    /// Hamiltonian `H_eff = H - (i/2) Σ γ L†L` as
+    /// `dρ/dt = -i H_eff ρ + i ρ H_eff† + Σ γ L ρ L†`
+    struct LindbladDevice {
+        h_eff: DeviceMatrix,
+        h_eff_dagger: DeviceMatrix,
+        jumps: Vec<(DeviceMatrix, DeviceMatrix, f64)>,
+        scratch: (DeviceMatrix, DeviceMatrix),
+    }
+
+    impl LindbladDevice {
+        fn new(
+            gpu: &GpuContext,
+            hamiltonian: &Array2<Complex64>,
+            lindblad_ops: &[(Array2<Complex64>, f64)],
+        ) -> Result<Self> {
+            let n = hamiltonian.nrows();
+            let mut h_eff = hamiltonian.clone();
+            let mut jumps = Vec::with_capacity(lindblad_ops.len());
+            for (l, gamma) in lindblad_ops {
+                let l_dagger = crate::kernels_cpu::dagger(l);
+                h_eff = h_eff
+                    - l_dagger
+                        .dot(l)
+                        .mapv(|x| x * Complex64::new(0.0, 0.5 * gamma));
+                jumps.push((gpu.upload(l)?, gpu.upload(&l_dagger)?, *gamma));
+            }
+            Ok(LindbladDevice {
+                h_eff_dagger: gpu.upload(&crate::kernels_cpu::dagger(&h_eff))?,
+                h_eff: gpu.upload(&h_eff)?,
+                jumps,
+                scratch: (gpu.zeros(n, n)?, gpu.zeros(n, n)?),
+            })
+        }
+
+        fn rhs(
+            &mut self,
+            gpu: &GpuContext,
+            rho: &DeviceMatrix,
+            out: &mut DeviceMatrix,
+        ) -> Result<()> {
+            let (a, b) = &mut self.scratch;
+            gpu.matmul_into(&self.h_eff, rho, a)?;
+            gpu.matmul_into(rho, &self.h_eff_dagger, b)?;
+            gpu.axpby_into(
+                a,
+                b,
+                Complex64::new(0.0, -1.0),
+                Complex64::new(0.0, 1.0),
+                out,
+            )?;
+            for (l, l_dagger, gamma) in &self.jumps {
+                gpu.matmul_into(l, rho, a)?;
+                gpu.matmul_into(a, l_dagger, b)?;
+                gpu.axpy(b, out, Complex64::new(*gamma, 0.0))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+mod disabled {
+    use crate::error::{EngineError, Result};
+    use ndarray::{Array1, Array2};
+    use num_complex::Complex64;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    /// Stand-in for the CUDA context in builds without the `gpu` feature;
+    /// it cannot be created
+    pub struct GpuContext {
+        never: Infallible,
+    }
+
+    impl GpuContext {
+        pub fn new(_ordinal: usize) -> Result<Self> {
+            Err(EngineError::Unsupported(
+                "GPU backend requires building with the `gpu` feature".to_string(),
+            ))
+        }
+
+        pub fn shared() -> Result<Arc<Self>> {
+            Self::new(0).map(Arc::new)
+        }
+
+        pub fn matmul(
+            &self,
+            _: &Array2<Complex64>,
+            _: &Array2<Complex64>,
+        ) -> Result<Array2<Complex64>> {
+            match self.never {}
+        }
+
+        pub fn tensor_product(
+            &self,
+            _: &Array2<Complex64>,
+            _: &Array2<Complex64>,
+        ) -> Result<Array2<Complex64>> {
+            match self.never {}
+        }
+
+        pub fn matrix_exp(&self, _: &Array2<Complex64>) -> Result<Array2<Complex64>> {
+            match self.never {}
+        }
+
+        pub fn evolve_unitary(
+            &self,
+            _: &Array2<Complex64>,
+            _: &Array1<Complex64>,
+            _: &[f64],
+        ) -> Result<Vec<Array1<Complex64>>> {
+            match self.never {}
+        }
+
+        pub fn integrate_rk4(
+            &self,
+            _: &Array2<Complex64>,
+            _: &[(Array2<Complex64>, f64)],
+            _: &Array2<Complex64>,
+            _: &[f64],
+        ) -> Result<Vec<Array2<Complex64>>> {
+            match self.never {}
+        }
+    }
+}
+
+#[cfg(all(test, feature = "gpu"))]
+mod tests {
+    use super::*;
+    use crate::kernels_cpu;
+    use crate::ode::{evolve_unitary, Rk4Integrator};
+    use ndarray::{Array1, Array2};
+    use num_complex::Complex64;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_hermitian(n: usize, rng: &mut StdRng) -> Array2<Complex64> {
+        let a = Array2::from_shape_fn((n, n), |_| {
+            Complex64::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+        });
+        (&a + &kernels_cpu::dagger(&a)).mapv(|x| x * 0.5)
+    }
+
+    fn max_diff(a: &Array2<Complex64>, b: &Array2<Complex64>) -> f64 {
+        (a - b).iter().map(|z| z.norm()).fold(0.0, f64::max)
+    }
+
+    // Needs a CUDA device; skipped (passes trivially) where none is present
+    fn gpu() -> Option<std::sync::Arc<GpuContext>> {
+        GpuContext::shared().ok()
+    }
+
+    #[test]
+    fn test_dense_kernels_match_cpu() {
+        let Some(gpu) = gpu() else { return };
+        let mut rng = StdRng::seed_from_u64(7);
+        let a = random_hermitian(12, &mut rng);
+        let b = random_hermitian(12, &mut rng);
+
+        assert!(max_diff(&gpu.matmul(&a, &b).unwrap(), &a.dot(&b)) < 1e-12);
+        let small = random_hermitian(3, &mut rng);
+        assert!(
+            max_diff(
+                &gpu.tensor_product(&a, &small).unwrap(),
+                &kernels_cpu::tensor_product(&a, &small).unwrap()
+            ) < 1e-12
+        );
+        let big = a.mapv(|x| x * 5.0);
+        assert!(
+            max_diff(
+                &gpu.matrix_exp(&big).unwrap(),
+                &kernels_cpu::matrix_exp(&big).unwrap()
+            ) < 1e-8
+        );
+    }
+
+    #[test]
+    fn test_trajectories_match_cpu() {
+        let Some(gpu) = gpu() else { return };
+        let mut rng = StdRng::seed_from_u64(11);
+        let h = random_hermitian(8, &mut rng);
+        let times: Vec<f64> = (0..51).map(|i| i as f64 * 0.02).collect();
+
+        let mut ket = Array1::zeros(8);
+        ket[0] = Complex64::new(1.0, 0.0);
+        let on_gpu = gpu.evolve_unitary(&h, &ket, &times).unwrap();
+        let on_cpu = evolve_unitary(&h, &ket, &times).unwrap();
+        for (g, c) in on_gpu.iter().zip(&on_cpu) {
+            assert!((g - c).iter().all(|z| z.norm() < 1e-10));
+        }
+
+        let mut lowering = Array2::zeros((8, 8));
+        for k in 1..8 {
+            lowering[[k - 1, k]] = Complex64::new((k as f64).sqrt(), 0.0);
+        }
+        let ops = vec![(lowering, 0.3)];
+        let rho0 = kernels_cpu::ket_to_rho(&ket.mapv(|_| Complex64::new(8f64.sqrt().recip(), 0.0)));
+        let on_gpu = gpu.integrate_rk4(&h, &ops, &rho0, &times).unwrap();
+        let on_cpu = Rk4Integrator::new(h.clone(), ops)
+            .integrate(rho0, &times)
+            .unwrap();
+        for (g, c) in on_gpu.iter().zip(&on_cpu.states) {
+            assert!(max_diff(g, c) < 1e-10);
+        }
+    }
+}
@@ -0,0 +1,602 @@
+//! Streaming sources for lab instruments publishing on message brokers
+//!
+//! Many DAQ systems publish readings on a ZeroMQ PUB socket or an MQTT
+//! broker rather than writing files. [`ZmqSource`] (feature `zeromq`)
+//! subscribes to a PUB socket and [`MqttSource`] (feature `mqtt`) to MQTT
+//! topics; both turn each message into [`DataPoint`]s through a
+//! [`PayloadDecoder`], [`JsonDecoder`] by default or [`CsvDecoder`] for
+//! comma-separated lines, and report on the [`StreamingManager`]'s event
+//! channel like the file and WebSocket sources.
+//!
+//! A dropped or refused connection is retried after the delays of a
+//! [`ReconnectPolicy`], growing geometrically while attempts keep failing
+//! and starting over once a connection succeeds; every loss is reported as
+//! a [`StreamEvent::SourceDisconnected`] and every (re)connection as a
+//! [`StreamEvent::SourceConnected`].
+//!
+//! [`StreamingManager`]: crate::streaming::StreamingManager
+//! [`StreamEvent::SourceDisconnected`]: crate::streaming::StreamEvent::SourceDisconnected
+//! [`StreamEvent::SourceConnected`]: crate::streaming::StreamEvent::SourceConnected
+
+use crate::error::{EngineError, Result};
+use crate::streaming::{parse_csv_line, DataPoint};
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+use crate::streaming::{DataSource, EventSender, StreamEvent};
+use serde::Deserialize;
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+use std::future::Future;
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Turns the payload of one broker message into data points
+///
+/// Closures `Fn(&str, &[u8]) -> Result<Vec<DataPoint>>` are decoders too,
+/// for instruments with their own formats.
+pub trait PayloadDecoder: Send + Sync {
+    /// Data points of the message `payload` received on `topic`
+    fn decode(&self, topic: &str, payload: &[u8]) -> Result<Vec<DataPoint>>;
+}
+
+impl<F> PayloadDecoder for F
+where
+    F: Fn(&str, &[u8]) -> Result<Vec<DataPoint>> + Send + Sync,
+{
+    fn decode(&self, topic: &str, payload: &[u8]) -> Result<Vec<DataPoint>> {
+        self(topic, payload)
+    }
+}
+
+/// JSON payloads: one object `{"observable": "sigma_z", "value": 0.5,
+/// "uncertainty": 0.01}` or an array of them
+///
+/// `uncertainty` defaults to 0 and `metadata` to an empty object; without
+/// `observable`, the message topic names the observable.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonDecoder;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonPayload {
+    One(JsonPoint),
+    Many(Vec<JsonPoint>),
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    observable: Option<String>,
+    value: f64,
+    #[serde(default)]
+    uncertainty: f64,
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+}
+
+impl PayloadDecoder for JsonDecoder {
+    fn decode(&self, topic: &str, payload: &[u8]) -> Result<Vec<DataPoint>> {
+        let points = match serde_json::from_slice(payload) {
+            Ok(JsonPayload::One(point)) => vec![point],
+            Ok(JsonPayload::Many(points)) => points,
+            Err(e) => {
+                return Err(EngineError::SerializationError(format!(
+                    "Invalid JSON payload on '{}': {}",
+                    topic, e
+                )))
+            }
+        };
+        Ok(points
+            .into_iter()
+            .map(|point| DataPoint {
+                observable: point.observable.unwrap_or_else(|| topic.to_string()),
+                value: point.value,
+                uncertainty: point.uncertainty,
+                metadata: point.metadata.unwrap_or_else(|| serde_json::json!({})),
+            })
+            .collect())
+    }
+}
+
+/// CSV payloads: lines `observable,value,uncertainty`, as written to the
+/// files [`CsvFileWatcher`](crate::streaming::CsvFileWatcher) follows;
+/// blank lines and `#` comments are skipped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvDecoder;
+
+impl PayloadDecoder for CsvDecoder {
+    fn decode(&self, topic: &str, payload: &[u8]) -> Result<Vec<DataPoint>> {
+        let text = std::str::from_utf8(payload).map_err(|e| {
+            EngineError::SerializationError(format!("Non-UTF-8 CSV payload on '{}': {}", topic, e))
+        })?;
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(parse_csv_line)
+            .collect()
+    }
+}
+
+/// Delays between attempts to reach a broker
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Wait before the first retry
+    pub initial_delay: Duration,
+    /// Longest wait between retries
+    pub max_delay: Duration,
+    /// Factor the wait grows by after each failed attempt
+    pub multiplier: f64,
+    /// Consecutive failed attempts after which the source gives up; `None`
+    /// retries forever
+    pub max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Never reconnect
+    pub fn never() -> Self {
+        Self {
+            max_attempts: Some(0),
+            ..Self::default()
+        }
+    }
+
+    /// Wait after the `attempt`-th consecutive failure (counting from 1),
+    /// or `None` once the source should give up
+    pub fn delay(&self, attempt: usize) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempt > max) {
+            return None;
+        }
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let seconds = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        Some(Duration::from_secs_f64(
+            seconds.min(self.max_delay.as_secs_f64()),
+        ))
+    }
+}
+
+/// Splits a single-frame ZeroMQ message `topic payload` into its topic,
+/// the longest of `topics` it starts with, and the payload after it
+pub fn split_topic<'a>(frame: &'a [u8], topics: &[String]) -> (String, &'a [u8]) {
+    let topic = topics
+        .iter()
+        .filter(|topic| !topic.is_empty() && frame.starts_with(topic.as_bytes()))
+        .max_by_key(|topic| topic.len());
+    match topic {
+        Some(topic) => {
+            let rest = &frame[topic.len()..];
+            let start = rest
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(rest.len());
+            (topic.clone(), &rest[start..])
+        }
+        None => (String::new(), frame),
+    }
+}
+
+/// How one connection to a broker ended
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+enum SessionEnd {
+    /// The source was stopped
+    Stopped,
+    /// The connection failed, or dropped after being established if
+    /// `connected`
+    Lost { connected: bool, reason: String },
+}
+
+/// What a connection needs to report its messages
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+#[derive(Clone)]
+struct Session {
+    id: String,
+    sender: EventSender,
+    decoder: Arc<dyn PayloadDecoder>,
+    active: Arc<Mutex<bool>>,
+}
+
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+impl Session {
+    fn is_active(&self) -> bool {
+        *self.active.lock().unwrap()
+    }
+
+    fn connected(&self) {
+        let _ = self.sender.send(StreamEvent::SourceConnected {
+            source_id: self.id.clone(),
+        });
+    }
+
+    fn emit(&self, topic: &str, payload: &[u8]) {
+        match self.decoder.decode(topic, payload) {
+            Ok(points) => {
+                for point in points {
+                    let _ = self.sender.send(StreamEvent::NewData {
+                        source_id: self.id.clone(),
+                        timestamp: chrono::Utc::now(),
+                        data: point,
+                    });
+                }
+            }
+            Err(e) => crate::log_warn!("lab_sources", "Error decoding message from {}: {}", self.id, e),
+        }
+    }
+}
+
+/// Run `connect` until the source is stopped, waiting between lost
+/// connections as `policy` says
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+async fn run_with_reconnect<F, Fut>(session: Session, policy: ReconnectPolicy, connect: F)
+where
+    F: Fn(Session) -> Fut,
+    Fut: Future<Output = SessionEnd>,
+{
+    let mut attempt = 0;
+    loop {
+        match connect(session.clone()).await {
+            SessionEnd::Stopped => return,
+            SessionEnd::Lost { connected, reason } => {
+                attempt = if connected { 1 } else { attempt + 1 };
+                let delay = policy.delay(attempt);
+                let reason = match delay {
+                    Some(_) => reason,
+                    None => format!("{}; giving up after {} attempts", reason, attempt),
+                };
+                let _ = session.sender.send(StreamEvent::SourceDisconnected {
+                    source_id: session.id.clone(),
+                    reason,
+                });
+                match delay {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => break,
+                }
+            }
+        }
+    }
+    *session.active.lock().unwrap() = false;
+}
+
+/// State shared by the broker sources
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+struct Subscription {
+    session: Session,
+    topics: Vec<String>,
+    reconnect: ReconnectPolicy,
+    handle: Option<tokio::task::JoinHandle<()>>,
+}
+
+#[cfg(any(feature = "zeromq", feature = "mqtt"))]
+impl Subscription {
+    fn new(id: String, topics: Vec<String>, sender: EventSender) -> Self {
+        Self {
+            session: Session {
+                id,
+                sender,
+                decoder: Arc::new(JsonDecoder),
+                active: Arc::new(Mutex::new(false)),
+            },
+            topics,
+            reconnect: ReconnectPolicy::default(),
+            handle: None,
+        }
+    }
+
+    fn start<F, Fut>(&mut self, connect: F)
+    where
+        F: Fn(Session) -> Fut + Send + 'static,
+        Fut: Future<Output = SessionEnd> + Send + 'static,
+    {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+        *self.session.active.lock().unwrap() = true;
+        let session = self.session.clone();
+        let policy = self.reconnect.clone();
+        self.handle = Some(tokio::spawn(run_with_reconnect(session, policy, connect)));
+    }
+
+    fn stop(&mut self) {
+        *self.session.active.lock().unwrap() = false;
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+        let _ = self.session.sender.send(StreamEvent::SourceDisconnected {
+            source_id: self.session.id.clone(),
+            reason: "Stopped by user".to_string(),
+        });
+    }
+}
+
+/// ZeroMQ SUB socket connected to an instrument's PUB socket
+///
+/// Multipart messages are read as `[topic, ..., payload]`; single-frame
+/// messages as `topic payload`, the topic being the longest subscribed
+/// prefix. No topics subscribes to everything.
+#[cfg(feature = "zeromq")]
+pub struct ZmqSource {
+    endpoint: String,
+    subscription: Subscription,
+}
+
+#[cfg(feature = "zeromq")]
+impl ZmqSource {
+    /// Subscribe to `topics` on `endpoint`, such as `tcp://daq.lab:5556`
+    pub fn new(id: String, endpoint: String, topics: Vec<String>, sender: EventSender) -> Self {
+        Self {
+            endpoint,
+            subscription: Subscription::new(id, topics, sender),
+        }
+    }
+
+    pub fn with_decoder(mut self, decoder: Arc<dyn PayloadDecoder>) -> Self {
+        self.subscription.session.decoder = decoder;
+        self
+    }
+
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.subscription.reconnect = policy;
+        self
+    }
+}
+
+#[cfg(feature = "zeromq")]
+async fn zmq_session(session: Session, endpoint: String, topics: Vec<String>) -> SessionEnd {
+    use zeromq::{Socket, SocketRecv};
+
+    let lost = |connected, reason: String| SessionEnd::Lost { connected, reason };
+    let mut socket = zeromq::SubSocket::new();
+    if let Err(e) = socket.connect(&endpoint).await {
+        return lost(false, format!("Connection failed: {}", e));
+    }
+    let subscriptions = if topics.is_empty() {
+        vec![String::new()]
+    } else {
+        topics
+    };
+    for topic in &subscriptions {
+        if let Err(e) = socket.subscribe(topic).await {
+            return lost(false, format!("Subscription to '{}' failed: {}", topic, e));
+        }
+    }
+    session.connected();
+
+    while session.is_active() {
+        match socket.recv().await {
+            Ok(message) => match message.into_vec().as_slice() {
+                [frame] => {
+                    let (topic, payload) = split_topic(frame, &subscriptions);
+                    session.emit(&topic, payload);
+                }
+                [topic, .., payload] => session.emit(&String::from_utf8_lossy(topic), payload),
+                [] => {}
+            },
+            Err(e) => return lost(true, format!("Receive failed: {}", e)),
+        }
+    }
+    SessionEnd::Stopped
+}
+
+#[cfg(feature = "zeromq")]
+impl DataSource for ZmqSource {
+    fn id(&self) -> &str {
+        &self.subscription.session.id
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let endpoint = self.endpoint.clone();
+        let topics = self.subscription.topics.clone();
+        self.subscription
+            .start(move |session| zmq_session(session, endpoint.clone(), topics.clone()));
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.subscription.stop();
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.session.is_active()
+    }
+}
+
+/// MQTT subscriber on a broker instruments publish to
+///
+/// Topics may use the `+` and `#` wildcards, and no topics subscribes to
+/// `#`. Subscriptions use QoS 1 and are renewed on every reconnection.
+#[cfg(feature = "mqtt")]
+pub struct MqttSource {
+    host: String,
+    port: u16,
+    subscription: Subscription,
+}
+
+#[cfg(feature = "mqtt")]
+impl MqttSource {
+    pub fn new(
+        id: String,
+        host: String,
+        port: u16,
+        topics: Vec<String>,
+        sender: EventSender,
+    ) -> Self {
+        Self {
+            host,
+            port,
+            subscription: Subscription::new(id, topics, sender),
+        }
+    }
+
+    pub fn with_decoder(mut self, decoder: Arc<dyn PayloadDecoder>) -> Self {
+        self.subscription.session.decoder = decoder;
+        self
+    }
+
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.subscription.reconnect = policy;
+        self
+    }
+}
+
+#[cfg(feature = "mqtt")]
+async fn mqtt_session(
+    session: Session,
+    host: String,
+    port: u16,
+    topics: Vec<String>,
+) -> SessionEnd {
+    use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, SubscribeFilter};
+
+    // Brokers disconnect the older of two clients with the same id
+    let client_id = format!("qte-{}-{}", session.id, uuid::Uuid::new_v4().simple());
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(5));
+    let (client, mut events) = AsyncClient::new(options, 16);
+    let filters: Vec<SubscribeFilter> = if topics.is_empty() {
+        vec![SubscribeFilter::new("#".to_string(), QoS::AtLeastOnce)]
+    } else {
+        topics
+            .into_iter()
+            .map(|topic| SubscribeFilter::new(topic, QoS::AtLeastOnce))
+            .collect()
+    };
+
+    let mut connected = false;
+    while session.is_active() {
+        match events.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                connected = true;
+                session.connected();
+                if let Err(e) = client.subscribe_many(filters.clone()).await {
+                    return SessionEnd::Lost {
+                        connected,
+                        reason: format!("Subscription failed: {}", e),
+                    };
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                session.emit(&publish.topic, &publish.payload)
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return SessionEnd::Lost {
+                    connected,
+                    reason: format!("Connection failed: {}", e),
+                }
+            }
+        }
+    }
+    SessionEnd::Stopped
+}
+
+#[cfg(feature = "mqtt")]
+impl DataSource for MqttSource {
+    fn id(&self) -> &str {
+        &self.subscription.session.id
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let host = self.host.clone();
+        let port = self.port;
+        let topics = self.subscription.topics.clone();
+        self.subscription
+            .start(move |session| mqtt_session(session, host.clone(), port, topics.clone()));
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.subscription.stop();
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.session.is_active()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_decoders() {
+        let one = JsonDecoder
+            .decode(
+                "lab/qubit0",
+                br#"{"observable": "sigma_z", "value": 0.5, "uncertainty": 0.01}"#,
+            )
+            .unwrap();
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].observable, "sigma_z");
+        assert_eq!(one[0].uncertainty, 0.01);
+
+        // Without an observable the topic names it
+        let many = JsonDecoder
+            .decode(
+                "sigma_x",
+                br#"[{"value": 0.1}, {"value": 0.2, "metadata": {"t": 1.0}}]"#,
+            )
+            .unwrap();
+        assert_eq!(many.len(), 2);
+        assert_eq!(many[0].observable, "sigma_x");
+        assert_eq!(many[0].uncertainty, 0.0);
+        assert_eq!(many[1].metadata["t"], 1.0);
+        assert!(JsonDecoder.decode("t", b"not json").is_err());
+
+        let csv = CsvDecoder
+            .decode("", b"# header\nsigma_z,0.5,0.01\n\nsigma_x,-0.25,0.02\n")
+            .unwrap();
+        assert_eq!(csv.len(), 2);
+        assert_eq!(csv[1].observable, "sigma_x");
+        assert_eq!(csv[1].value, -0.25);
+
+        let custom = |topic: &str, payload: &[u8]| -> Result<Vec<DataPoint>> {
+            Ok(vec![DataPoint {
+                observable: topic.to_string(),
+                value: payload.len() as f64,
+                uncertainty: 0.0,
+                metadata: serde_json::json!({}),
+            }])
+        };
+        let decoder: std::sync::Arc<dyn PayloadDecoder> = std::sync::Arc::new(custom);
+        assert_eq!(decoder.decode("counts", b"abc").unwrap()[0].value, 3.0);
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_attempts: Some(6),
+        };
+        let delays: Vec<_> = (1..=7).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(delays[0], Some(Duration::from_millis(100)));
+        assert_eq!(delays[1], Some(Duration::from_millis(200)));
+        assert_eq!(delays[3], Some(Duration::from_millis(800)));
+        assert_eq!(delays[4], Some(Duration::from_secs(1)));
+        assert_eq!(delays[5], Some(Duration::from_secs(1)));
+        assert_eq!(delays[6], None);
+        assert_eq!(ReconnectPolicy::never().delay(1), None);
+        assert!(ReconnectPolicy::default().delay(10_000).is_some());
+    }
+
+    #[test]
+    fn test_split_topic() {
+        let topics = vec!["daq".to_string(), "daq/qubit0".to_string()];
+        let (topic, payload) = split_topic(b"daq/qubit0 {\"value\": 1}", &topics);
+        assert_eq!(topic, "daq/qubit0");
+        assert_eq!(payload, b"{\"value\": 1}");
+        let (topic, payload) = split_topic(b"{\"value\": 1}", &topics);
+        assert_eq!(topic, "");
+        assert_eq!(payload, b"{\"value\": 1}");
+    }
+}
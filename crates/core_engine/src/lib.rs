@@ -15,55 +15,175 @@
 //!
 //! # Example
 //!
-//! ```rust,ignore
-//! use quantum_theory_engine::{parse_dsl, execute, BackendConfig};
+//! Each stage has a one-call entry point at the crate root:
 //!
+//! ```rust
+//! use quantum_theory_engine::{execute, lower, parse_dsl, validate_quantum, BackendConfig};
+//!
+//! # fn main() -> quantum_theory_engine::Result<()> {
 //! let source = r#"
+//!     const omega = 1.0;
+//!     const Omega = 0.2;
+//!     matrix sigma_z = [1, 0; 0, -1];
+//!     matrix sigma_x = [0, 1; 1, 0];
+//!     Hamiltonian H = (omega/2) * sigma_z + Omega * sigma_x;
+//!
 //!     experiment rabi {
-//!         const omega = 1.0;
-//!         Hamiltonian H = (omega/2) * sigma_z;
 //!         init: ket(vec(1, 0));
-//!         evolution: evolve(init, H, timegrid=(0.0, 0.01, 101));
+//!         evolution: evolve(init, H, timegrid=(0.0, 0.01, 100));
 //!     }
 //! "#;
 //!
 //! let ast = parse_dsl(source)?;
 //! let validated = validate_quantum(&ast)?;
-//! let result = execute(&validated, BackendConfig::default())?;
+//! let ir = lower(&validated)?;
+//! let result = execute(&ir, BackendConfig::default())?;
+//!
+//! let rabi = &result.experiment_results[0];
+//! assert_eq!(rabi.times.len(), 101);
+//! let excited = rabi.states[100][[1, 1]].re;
+//! assert!(excited > 0.0 && excited < 1.0);
+//! # Ok(())
+//! # }
 //! ```
+//!
+//! [`run`] does all four in one go; the stage types ([`TypeChecker`],
+//! [`QuantumValidator`], [`lowering::Lowerer`], [`Executor`]) expose the
+//! options the shortcuts leave at their defaults.
+//!
+//! # Cargo features
+//!
+//! The numerical core (`kernels_cpu`, `ode`, `stats`, `executor`) is always
+//! built. Heavier subsystems are opt-in, and all of them are enabled by default:
+//!
+//! - `parser`: the pest-based DSL front-end ([`parse_dsl`],
+//!   [`imports::parse_file`] for programs importing other files, and
+//!   [`qasm`] for OpenQASM circuits)
+//! - `prover`: the symbolic prover, signed proof certificates and Lean 4 / Coq export
+//! - `provenance`: the graph linking results to their jobs, inputs and certificates
+//! - `cache`: the on-disk [`result_cache`] of execution results
+//! - `async`: the tokio stack behind `job_queue` and `streaming`
+//! - `server`: the HTTP API in front of the job queue (implies `async`)
+//! - `openblas`: statically linked OpenBLAS as the LAPACK provider
+//! - `hdf5`: HDF5 import/export
+//! - `npz`: numpy `.npz` measurement import
+//!
+//! The opt-in `gpu` feature (not in the defaults) adds CUDA kernels behind
+//! `BackendType::Gpu`; see [`kernels_gpu`]. The opt-in `zeromq` and `mqtt`
+//! features add streaming sources for lab instruments publishing on those
+//! protocols; see `lab_sources`.
+//!
+//! A kernels-only build is `default-features = false, features = ["openblas"]`.
 
+#[cfg(feature = "openblas")]
+extern crate blas_src;
+#[cfg(feature = "openblas")]
+extern crate lapack_src;
+
+#[cfg(feature = "async")]
+pub mod aggregation;
 pub mod ast;
+pub mod bases;
+pub mod builtins;
+#[cfg(feature = "parser")]
+pub mod calibration;
+pub mod cancellation;
+#[cfg(feature = "async")]
+pub mod changepoint;
+#[cfg(feature = "prover")]
+pub mod counterexample;
+#[cfg(feature = "parser")]
+pub mod diagnostics;
+pub mod ensemble;
 pub mod error;
 pub mod executor;
+pub mod expr_arena;
+pub mod fock;
+pub mod functions;
+pub mod gates;
+#[cfg(feature = "parser")]
+pub mod imports;
+pub mod io;
 pub mod ir;
+#[cfg(feature = "async")]
 pub mod job_queue;
 pub mod kernels_cpu;
+pub mod kernels_gpu;
+#[cfg(feature = "async")]
+pub mod lab_sources;
 pub mod logging;
 pub mod lowering;
+pub mod measurement_io;
+pub mod noise;
 pub mod ode;
+pub mod operators;
 pub mod optimizer;
+pub mod parameter_grid;
+#[cfg(feature = "parser")]
 pub mod parser;
+pub mod pipeline;
+pub mod plugin;
+pub mod precision;
+pub mod progress;
+#[cfg(feature = "prover")]
+pub mod proof_export;
+#[cfg(feature = "prover")]
+pub mod proof_trace;
+pub mod propagator;
+#[cfg(feature = "prover")]
 pub mod prover;
+#[cfg(feature = "provenance")]
+pub mod provenance;
+#[cfg(feature = "parser")]
+pub mod qasm;
+pub mod quantum_info;
+pub mod qudit;
+pub mod report;
+pub mod resources;
+#[cfg(feature = "cache")]
+pub mod result_cache;
+pub mod rng;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "prover")]
+pub mod signing;
+pub mod sobol;
+pub mod spectrum;
 pub mod stats;
+#[cfg(feature = "async")]
 pub mod streaming;
+pub mod sweep;
 pub mod templates;
 pub mod typechecker;
 pub mod validator;
+pub mod values;
 
 // Re-exports for convenience
 pub use ast::{Ast, Expr, Statement};
+#[cfg(feature = "async")]
 pub use job_queue::{JobQueue, Job, JobKind, Priority, JobStatus};
-pub use logging::{LogLevel, Timer, HealthChecker, HealthStatus};
-pub use prover::{Prover, ProofResult, Property, PropertyProof};
-pub use streaming::{StreamingManager, RollingFitEngine, DataPoint};
+pub use logging::{BenchmarkCheck, BenchmarkTimings, HealthChecker, HealthStatus, LogConfig, LogFormat, LogLevel, Timer};
+#[cfg(feature = "async")]
+pub use streaming::{StreamingManager, RollingFitEngine, ObservationModel, DataPoint};
 pub use templates::{TemplateRegistry, Template};
+pub use ensemble::{StateEnsemble, StateEnsembleResult};
 pub use error::{EngineError, Result};
 pub use executor::{BackendConfig, ExecutionResult, Executor};
+pub use noise::{NoiseModel, NoiseTerm};
+pub use plugin::{Kernel, KernelRegistry, KernelValue};
+#[cfg(feature = "parser")]
+pub use imports::parse_file;
+#[cfg(feature = "parser")]
 pub use parser::parse_dsl;
+pub use pipeline::{execute, lower, validate_quantum, validate_quantum_with};
+#[cfg(feature = "parser")]
+pub use pipeline::run;
+#[cfg(feature = "prover")]
 pub use prover::{Prover, ProofResult, Property, PropertyProof};
 pub use stats::{FitResult, TestResult};
 pub use typechecker::TypeChecker;
-pub use validator::QuantumValidator;
+pub use validator::{QuantumValidator, ValidatorConfig};
+pub use values::ParameterValues;
 
 /// Version of the quantum theory engine
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -1,9 +1,25 @@
 //! Structured logging and telemetry
 //!
 //! Provides comprehensive logging, performance metrics, and diagnostics.
+//!
+//! The global logger keeps its most recent entries in memory, up to
+//! [`LogConfig::max_entries`], and writes every entry to its sinks: the
+//! console by default, and optionally a [`RotatingFileSink`]. Either writes
+//! plain text or JSON lines ([`LogFormat`]) for log shippers such as
+//! Logstash. With [`LogConfig::channel_capacity`] set, the logging functions
+//! only queue entries for a background [`LogWriter`], so hot loops don't
+//! contend on the logger's lock; entries arriving while the queue is full
+//! are dropped and counted.
 
+use crate::error::{EngineError, Result};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -11,6 +27,12 @@ use std::time::{Duration, Instant};
 static LOGGER: once_cell::sync::Lazy<Arc<Mutex<Logger>>> =
     once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(Logger::new())));
 
+/// Level of the global logger, read without taking its lock
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Background writer of the global logger, once one is configured
+static WRITER: once_cell::sync::OnceCell<LogWriter> = once_cell::sync::OnceCell::new();
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LogLevel {
     Trace = 0,
@@ -39,10 +61,193 @@ pub struct PerformanceMetric {
     pub min_duration: Duration,
 }
 
+/// How a sink writes each entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `[LEVEL] module: message`, after an RFC 3339 timestamp in files
+    #[default]
+    Text,
+    /// One JSON object per line, the serialized [`LogEntry`]
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" | "jsonl" => Ok(LogFormat::Json),
+            other => Err(EngineError::validation_error(format!(
+                "Unknown log format '{}', expected text or json",
+                other
+            ))),
+        }
+    }
+}
+
+impl LogFormat {
+    /// `entry` as one line, without the newline
+    pub fn line(&self, entry: &LogEntry) -> std::io::Result<String> {
+        match self {
+            LogFormat::Text => Ok(format!("{} {}", entry.timestamp.to_rfc3339(), text_line(entry))),
+            LogFormat::Json => Ok(serde_json::to_string(entry)?),
+        }
+    }
+}
+
+fn text_line(entry: &LogEntry) -> String {
+    let tag = match entry.level {
+        LogLevel::Error => "[ERROR]",
+        LogLevel::Warn => "[WARN] ",
+        LogLevel::Info => "[INFO] ",
+        LogLevel::Debug => "[DEBUG]",
+        LogLevel::Trace => "[TRACE]",
+    };
+    format!("{} {}: {}", tag, entry.module, entry.message)
+}
+
+/// Destination of log entries
+pub trait LogSink: Send {
+    fn write(&mut self, entry: &LogEntry) -> std::io::Result<()>;
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Standard output, or standard error for warnings and errors
+pub struct ConsoleSink {
+    format: LogFormat,
+}
+
+impl ConsoleSink {
+    pub fn new(format: LogFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl LogSink for ConsoleSink {
+    fn write(&mut self, entry: &LogEntry) -> std::io::Result<()> {
+        // Terminals show their own time, so text lines go without one
+        let line = match self.format {
+            LogFormat::Text => text_line(entry),
+            LogFormat::Json => self.format.line(entry)?,
+        };
+        if entry.level >= LogLevel::Warn {
+            writeln!(std::io::stderr().lock(), "{}", line)
+        } else {
+            writeln!(std::io::stdout().lock(), "{}", line)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()?;
+        std::io::stderr().flush()
+    }
+}
+
+/// Where and how [`RotatingFileSink`] writes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSinkConfig {
+    pub path: PathBuf,
+    pub format: LogFormat,
+    /// Size at which the file is rotated
+    pub max_bytes: u64,
+    /// Rotated files kept beside the live one, as `<path>.1` (newest)
+    /// through `<path>.<max_files>`
+    pub max_files: usize,
+}
+
+impl FileSinkConfig {
+    /// Text to `path`, rotated at 10 MiB with five old files kept
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: LogFormat::Text,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// File that is renamed to `<path>.1` once it reaches a size limit, shifting
+/// older files up and deleting the oldest
+pub struct RotatingFileSink {
+    config: FileSinkConfig,
+    file: File,
+    /// Bytes in the live file
+    written: u64,
+}
+
+impl RotatingFileSink {
+    /// Append to `config.path`, creating it and its directory if needed
+    pub fn open(config: FileSinkConfig) -> Result<Self> {
+        if config.max_bytes == 0 {
+            return Err(EngineError::validation_error(
+                "Log file size limit must be positive",
+            ));
+        }
+        if let Some(dir) = config.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { config, file, written })
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let path = &self.config.path;
+        if self.config.max_files == 0 {
+            std::fs::remove_file(path)?;
+        } else {
+            for k in (1..self.config.max_files).rev() {
+                let older = rotated_path(path, k);
+                if older.exists() {
+                    std::fs::rename(&older, rotated_path(path, k + 1))?;
+                }
+            }
+            std::fs::rename(path, rotated_path(path, 1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl LogSink for RotatingFileSink {
+    fn write(&mut self, entry: &LogEntry) -> std::io::Result<()> {
+        let mut line = self.config.format.line(entry)?;
+        line.push('\n');
+        let len = line.len() as u64;
+        if self.written > 0 && self.written + len > self.config.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// `<path>.<k>`
+fn rotated_path(path: &Path, k: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", k));
+    PathBuf::from(name)
+}
+
 pub struct Logger {
     level: LogLevel,
-    entries: Vec<LogEntry>,
+    entries: VecDeque<LogEntry>,
     max_entries: usize,
+    sinks: Vec<Box<dyn LogSink>>,
+    /// Sink writes that failed, which are not retried
+    failed_writes: u64,
     metrics: HashMap<String, MetricStats>,
 }
 
@@ -54,11 +259,15 @@ struct MetricStats {
 }
 
 impl Logger {
+    /// Logger at `Info` that keeps 10 000 entries and prints text to the
+    /// console
     pub fn new() -> Self {
         Self {
             level: LogLevel::Info,
-            entries: Vec::new(),
+            entries: VecDeque::new(),
             max_entries: 10000,
+            sinks: vec![Box::new(ConsoleSink::new(LogFormat::Text))],
+            failed_writes: 0,
             metrics: HashMap::new(),
         }
     }
@@ -67,30 +276,58 @@ impl Logger {
         self.level = level;
     }
 
+    /// Keep at most `max_entries` entries in memory, dropping the oldest
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries;
+        while self.entries.len() > max_entries {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn add_sink(&mut self, sink: Box<dyn LogSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Replace every sink, flushing the old ones
+    pub fn set_sinks(&mut self, sinks: Vec<Box<dyn LogSink>>) {
+        self.flush();
+        self.sinks = sinks;
+    }
+
+    pub fn failed_writes(&self) -> u64 {
+        self.failed_writes
+    }
+
     pub fn log(&mut self, level: LogLevel, module: &str, message: String, context: HashMap<String, serde_json::Value>) {
         if level >= self.level {
-            let entry = LogEntry {
+            self.record(LogEntry {
                 timestamp: chrono::Utc::now(),
                 level,
                 module: module.to_string(),
                 message,
                 context,
-            };
+            });
+        }
+    }
 
-            // Print to stdout/stderr based on level
-            match level {
-                LogLevel::Error => eprintln!("[ERROR] {}: {}", module, entry.message),
-                LogLevel::Warn => eprintln!("[WARN]  {}: {}", module, entry.message),
-                LogLevel::Info => println!("[INFO]  {}: {}", module, entry.message),
-                LogLevel::Debug => println!("[DEBUG] {}: {}", module, entry.message),
-                LogLevel::Trace => println!("[TRACE] {}: {}", module, entry.message),
+    /// Write `entry` to every sink and keep it, whatever its level
+    pub fn record(&mut self, entry: LogEntry) {
+        for sink in &mut self.sinks {
+            if sink.write(&entry).is_err() {
+                self.failed_writes += 1;
             }
+        }
 
-            self.entries.push(entry);
+        self.entries.push_back(entry);
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+    }
 
-            // Trim old entries if we exceed max
-            if self.entries.len() > self.max_entries {
-                self.entries.drain(0..self.entries.len() - self.max_entries);
+    pub fn flush(&mut self) {
+        for sink in &mut self.sinks {
+            if sink.flush().is_err() {
+                self.failed_writes += 1;
             }
         }
     }
@@ -109,6 +346,16 @@ impl Logger {
         stats.min_duration = stats.min_duration.min(duration);
     }
 
+    /// Count `count` occurrences of an event under `name`, without a duration
+    pub fn record_count(&mut self, name: &str, count: u64) {
+        self.metrics.entry(name.to_string()).or_insert(MetricStats {
+            count: 0,
+            total_duration: Duration::ZERO,
+            max_duration: Duration::ZERO,
+            min_duration: Duration::MAX,
+        }).count += count;
+    }
+
     pub fn get_metrics(&self) -> Vec<PerformanceMetric> {
         self.metrics.iter().map(|(name, stats)| {
             let avg = if stats.count > 0 {
@@ -133,19 +380,15 @@ impl Logger {
     }
 
     pub fn get_recent_logs(&self, n: usize) -> Vec<LogEntry> {
-        let start = if self.entries.len() > n {
-            self.entries.len() - n
-        } else {
-            0
-        };
-        self.entries[start..].to_vec()
+        let start = self.entries.len().saturating_sub(n);
+        self.entries.iter().skip(start).cloned().collect()
     }
 
     pub fn filter_logs(&self, level: LogLevel, module: Option<&str>) -> Vec<LogEntry> {
         self.entries.iter()
             .filter(|entry| {
                 entry.level >= level &&
-                    module.map_or(true, |m| entry.module == m)
+                    module.is_none_or(|m| entry.module == m)
             })
             .cloned()
             .collect()
@@ -163,40 +406,206 @@ impl Default for Logger {
     }
 }
 
+enum WriterMessage {
+    Entry(LogEntry),
+    /// Acknowledged once every entry queued before it is written
+    Flush(SyncSender<()>),
+}
+
+/// Background thread recording queued entries into a logger
+///
+/// The thread takes the logger's lock once for every batch of entries it
+/// finds queued, rather than once per entry, and runs for as long as any
+/// handle to it is alive.
+#[derive(Clone)]
+pub struct LogWriter {
+    sender: SyncSender<WriterMessage>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LogWriter {
+    /// Start a writer into `logger` that queues up to `capacity` entries
+    pub fn spawn(logger: Arc<Mutex<Logger>>, capacity: usize) -> Result<Self> {
+        if capacity == 0 {
+            return Err(EngineError::validation_error(
+                "Log writer queue capacity must be positive",
+            ));
+        }
+        let (sender, receiver) = mpsc::sync_channel::<WriterMessage>(capacity);
+        std::thread::Builder::new()
+            .name("qte-log-writer".to_string())
+            .spawn(move || {
+                while let Ok(first) = receiver.recv() {
+                    let mut logger = logger.lock().unwrap();
+                    let mut next = Some(first);
+                    while let Some(message) = next {
+                        match message {
+                            WriterMessage::Entry(entry) => logger.record(entry),
+                            WriterMessage::Flush(done) => {
+                                logger.flush();
+                                let _ = done.send(());
+                            }
+                        }
+                        next = receiver.try_recv().ok();
+                    }
+                }
+            })?;
+        Ok(Self {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Queue `entry` without waiting; false if it was dropped because the
+    /// queue is full
+    pub fn submit(&self, entry: LogEntry) -> bool {
+        match self.sender.try_send(WriterMessage::Entry(entry)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Wait until every entry queued so far is written and the sinks are
+    /// flushed
+    pub fn flush(&self) {
+        let (done, finished) = mpsc::sync_channel(1);
+        if self.sender.send(WriterMessage::Flush(done)).is_ok() {
+            let _ = finished.recv();
+        }
+    }
+
+    /// Entries dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Configuration of the global logger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogConfig {
+    pub level: LogLevel,
+    /// Entries kept in memory for [`get_recent_logs`] and [`filter_logs`]
+    pub max_entries: usize,
+    /// Format of console output; `None` turns it off
+    pub console: Option<LogFormat>,
+    pub file: Option<FileSinkConfig>,
+    /// Queue entries for a background writer of this capacity instead of
+    /// writing them on the calling thread. The writer, once started, serves
+    /// the global logger for the rest of the process.
+    pub channel_capacity: Option<usize>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            max_entries: 10000,
+            console: Some(LogFormat::Text),
+            file: None,
+            channel_capacity: None,
+        }
+    }
+}
+
 // Global logging functions
 
+/// Set the level, memory bound and sinks of the global logger
+pub fn configure(config: LogConfig) -> Result<()> {
+    let mut sinks: Vec<Box<dyn LogSink>> = Vec::new();
+    if let Some(format) = config.console {
+        sinks.push(Box::new(ConsoleSink::new(format)));
+    }
+    if let Some(file) = config.file {
+        sinks.push(Box::new(RotatingFileSink::open(file)?));
+    }
+    if let Some(capacity) = config.channel_capacity {
+        WRITER.get_or_try_init(|| LogWriter::spawn(LOGGER.clone(), capacity))?;
+    }
+
+    // Entries already queued go to the sinks they were logged under
+    flush_logs();
+    let mut logger = LOGGER.lock().unwrap();
+    logger.set_level(config.level);
+    logger.set_max_entries(config.max_entries);
+    logger.set_sinks(sinks);
+    LEVEL.store(config.level as u8, Ordering::Relaxed);
+    Ok(())
+}
+
 pub fn set_log_level(level: LogLevel) {
     LOGGER.lock().unwrap().set_level(level);
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Log through the background writer when there is one, skipping entries
+/// below the level before building them
+fn submit(level: LogLevel, module: &str, message: String, context: HashMap<String, serde_json::Value>) {
+    if (level as u8) < LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
+    let entry = LogEntry {
+        timestamp: chrono::Utc::now(),
+        level,
+        module: module.to_string(),
+        message,
+        context,
+    };
+    match WRITER.get() {
+        Some(writer) => {
+            writer.submit(entry);
+        }
+        None => LOGGER.lock().unwrap().record(entry),
+    }
 }
 
 pub fn trace(module: &str, message: String) {
-    LOGGER.lock().unwrap().log(LogLevel::Trace, module, message, HashMap::new());
+    submit(LogLevel::Trace, module, message, HashMap::new());
 }
 
 pub fn debug(module: &str, message: String) {
-    LOGGER.lock().unwrap().log(LogLevel::Debug, module, message, HashMap::new());
+    submit(LogLevel::Debug, module, message, HashMap::new());
 }
 
 pub fn info(module: &str, message: String) {
-    LOGGER.lock().unwrap().log(LogLevel::Info, module, message, HashMap::new());
+    submit(LogLevel::Info, module, message, HashMap::new());
 }
 
 pub fn warn(module: &str, message: String) {
-    LOGGER.lock().unwrap().log(LogLevel::Warn, module, message, HashMap::new());
+    submit(LogLevel::Warn, module, message, HashMap::new());
 }
 
 pub fn error(module: &str, message: String) {
-    LOGGER.lock().unwrap().log(LogLevel::Error, module, message, HashMap::new());
+    submit(LogLevel::Error, module, message, HashMap::new());
 }
 
 pub fn log_with_context(level: LogLevel, module: &str, message: String, context: HashMap<String, serde_json::Value>) {
-    LOGGER.lock().unwrap().log(level, module, message, context);
+    submit(level, module, message, context);
+}
+
+/// Wait for queued entries to be written and flush the sinks
+pub fn flush_logs() {
+    match WRITER.get() {
+        Some(writer) => writer.flush(),
+        None => LOGGER.lock().unwrap().flush(),
+    }
+}
+
+/// Entries the background writer dropped because its queue was full
+pub fn dropped_logs() -> u64 {
+    WRITER.get().map_or(0, LogWriter::dropped)
 }
 
 pub fn record_metric(name: &str, duration: Duration) {
     LOGGER.lock().unwrap().record_metric(name, duration);
 }
 
+pub fn record_count(name: &str, count: u64) {
+    LOGGER.lock().unwrap().record_count(name, count);
+}
+
 pub fn get_metrics() -> Vec<PerformanceMetric> {
     LOGGER.lock().unwrap().get_metrics()
 }
@@ -282,6 +691,9 @@ macro_rules! log_error {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthStatus {
     pub healthy: bool,
+    /// Some check found the node working but slower than it should be
+    #[serde(default)]
+    pub degraded: bool,
     pub checks: HashMap<String, CheckResult>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
@@ -289,6 +701,8 @@ pub struct HealthStatus {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CheckResult {
     pub passed: bool,
+    #[serde(default)]
+    pub degraded: bool,
     pub message: String,
     pub details: serde_json::Value,
 }
@@ -311,20 +725,29 @@ impl HealthChecker {
         self.checks.insert(name.into(), Box::new(check));
     }
 
+    /// Also run `benchmark`, reporting the node degraded when it regresses
+    pub fn with_benchmark(mut self, benchmark: BenchmarkCheck) -> Self {
+        self.register_check("benchmark", move || benchmark.run());
+        self
+    }
+
     pub fn run_checks(&self) -> HealthStatus {
         let mut results = HashMap::new();
         let mut all_healthy = true;
+        let mut degraded = false;
 
         for (name, check) in &self.checks {
             let result = check();
             if !result.passed {
                 all_healthy = false;
             }
+            degraded |= result.degraded;
             results.insert(name.clone(), result);
         }
 
         HealthStatus {
             healthy: all_healthy,
+            degraded,
             checks: results,
             timestamp: chrono::Utc::now(),
         }
@@ -340,6 +763,7 @@ impl Default for HealthChecker {
             // Simple memory check (could be enhanced with real metrics)
             CheckResult {
                 passed: true,
+                degraded: false,
                 message: "Memory usage normal".to_string(),
                 details: serde_json::json!({"allocated_mb": "unknown"}),
             }
@@ -348,6 +772,7 @@ impl Default for HealthChecker {
         checker.register_check("cpu", || {
             CheckResult {
                 passed: true,
+                degraded: false,
                 message: "CPU usage normal".to_string(),
                 details: serde_json::json!({"usage_percent": "unknown"}),
             }
@@ -357,6 +782,142 @@ impl Default for HealthChecker {
     }
 }
 
+/// Runtimes of the engine's self-benchmark, in microseconds
+///
+/// Recorded once on a machine known to be configured well, they are the
+/// baseline that [`BenchmarkCheck`] compares later runs against.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkTimings {
+    /// One `matrix_exp` of a 4×4 matrix
+    pub matrix_exp_us: f64,
+    /// 1000 RK4 steps of a damped 4-level Lindblad equation
+    pub rk4_us: f64,
+}
+
+impl BenchmarkTimings {
+    /// Run the benchmark, keeping the fastest of a few repetitions of each
+    /// kernel so that one-off scheduling noise is not mistaken for a
+    /// regression
+    pub fn measure() -> crate::error::Result<Self> {
+        use crate::kernels_cpu::matrix_exp;
+        use crate::ode::Rk4Integrator;
+        use ndarray::Array2;
+        use num_complex::Complex64;
+
+        const REPEATS: usize = 5;
+        const EXP_CALLS: usize = 200;
+        const RK4_STEPS: usize = 1000;
+
+        let dim = 4;
+        let h = Array2::from_shape_fn((dim, dim), |(i, j)| {
+            Complex64::new(1.0 / (1.0 + i as f64 + j as f64), 0.0)
+        });
+        let lowering = Array2::from_shape_fn((dim, dim), |(i, j)| {
+            Complex64::new(if j == i + 1 { (j as f64).sqrt() } else { 0.0 }, 0.0)
+        });
+        let mut rho0 = Array2::zeros((dim, dim));
+        rho0[[dim - 1, dim - 1]] = Complex64::new(1.0, 0.0);
+        let times: Vec<f64> = (0..=RK4_STEPS).map(|k| k as f64 * 1e-3).collect();
+        let generator = h.mapv(|x| x * Complex64::new(0.0, -0.1));
+        let integrator = Rk4Integrator::new(h, vec![(lowering, 0.1)]);
+
+        let mut timings = BenchmarkTimings {
+            matrix_exp_us: f64::INFINITY,
+            rk4_us: f64::INFINITY,
+        };
+        for _ in 0..REPEATS {
+            let start = Instant::now();
+            for _ in 0..EXP_CALLS {
+                std::hint::black_box(matrix_exp(std::hint::black_box(&generator))?);
+            }
+            let per_call = start.elapsed().as_secs_f64() * 1e6 / EXP_CALLS as f64;
+            timings.matrix_exp_us = timings.matrix_exp_us.min(per_call);
+
+            let start = Instant::now();
+            std::hint::black_box(integrator.integrate(rho0.clone(), &times)?);
+            timings.rk4_us = timings.rk4_us.min(start.elapsed().as_secs_f64() * 1e6);
+        }
+        Ok(timings)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> crate::error::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> crate::error::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Health check comparing the self-benchmark with a stored baseline
+///
+/// A kernel running more than `threshold` times slower than its baseline
+/// marks the node degraded but not unhealthy: results stay correct, only
+/// slower, as with a throttled CPU or a reference BLAS picked up instead of
+/// the tuned one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkCheck {
+    pub baseline: BenchmarkTimings,
+    /// Largest tolerated ratio of measured to baseline runtime
+    pub threshold: f64,
+}
+
+impl BenchmarkCheck {
+    pub fn new(baseline: BenchmarkTimings) -> Self {
+        Self {
+            baseline,
+            threshold: 2.0,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn run(&self) -> CheckResult {
+        match BenchmarkTimings::measure() {
+            Ok(timings) => self.compare(&timings),
+            Err(e) => CheckResult {
+                passed: false,
+                degraded: false,
+                message: format!("Benchmark failed: {}", e),
+                details: serde_json::json!({}),
+            },
+        }
+    }
+
+    /// Verdict on `timings` measured now
+    pub fn compare(&self, timings: &BenchmarkTimings) -> CheckResult {
+        let ratios = [
+            ("matrix_exp", timings.matrix_exp_us / self.baseline.matrix_exp_us),
+            ("rk4", timings.rk4_us / self.baseline.rk4_us),
+        ];
+        let regressed: Vec<String> = ratios
+            .iter()
+            .filter(|(_, ratio)| *ratio > self.threshold)
+            .map(|(name, ratio)| format!("{} {:.1}x slower", name, ratio))
+            .collect();
+
+        CheckResult {
+            passed: true,
+            degraded: !regressed.is_empty(),
+            message: if regressed.is_empty() {
+                "Benchmark within baseline".to_string()
+            } else {
+                format!("Performance regression: {}", regressed.join(", "))
+            },
+            details: serde_json::json!({
+                "timings_us": timings,
+                "baseline_us": self.baseline,
+                "ratios": ratios.iter().map(|(name, ratio)| (name.to_string(), *ratio)).collect::<HashMap<_, _>>(),
+                "threshold": self.threshold,
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -374,6 +935,75 @@ mod tests {
         assert_eq!(logs[0].message, "Test message");
     }
 
+    #[test]
+    fn test_entries_are_bounded() {
+        let mut logger = Logger::new();
+        logger.set_sinks(Vec::new());
+        logger.set_max_entries(3);
+        for k in 0..5 {
+            logger.log(LogLevel::Info, "test", format!("entry {}", k), HashMap::new());
+        }
+
+        let logs = logger.get_recent_logs(10);
+        assert_eq!(logs.len(), 3);
+        assert_eq!(logs[0].message, "entry 2");
+        assert_eq!(logger.get_recent_logs(1)[0].message, "entry 4");
+    }
+
+    #[test]
+    fn test_file_sink_rotates_json_lines() {
+        let dir = std::env::temp_dir().join(format!("qte_logs_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("engine.log");
+        let config = FileSinkConfig {
+            format: LogFormat::Json,
+            max_bytes: 400,
+            max_files: 2,
+            ..FileSinkConfig::new(&path)
+        };
+
+        let mut logger = Logger::new();
+        logger.set_sinks(vec![Box::new(RotatingFileSink::open(config).unwrap())]);
+        for k in 0..20 {
+            logger.log(LogLevel::Warn, "test", format!("entry {}", k), HashMap::new());
+        }
+        logger.flush();
+        assert_eq!(logger.failed_writes(), 0);
+
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+        let live = std::fs::read_to_string(&path).unwrap();
+        assert!(live.len() <= 400);
+        let last: LogEntry = serde_json::from_str(live.lines().last().unwrap()).unwrap();
+        assert_eq!(last.message, "entry 19");
+        assert_eq!(last.level, LogLevel::Warn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_writer_records_queued_entries() {
+        let mut logger = Logger::new();
+        logger.set_sinks(Vec::new());
+        let logger = Arc::new(Mutex::new(logger));
+        let writer = LogWriter::spawn(logger.clone(), 256).unwrap();
+
+        for k in 0..100 {
+            writer.submit(LogEntry {
+                timestamp: chrono::Utc::now(),
+                level: LogLevel::Debug,
+                module: "test".to_string(),
+                message: format!("entry {}", k),
+                context: HashMap::new(),
+            });
+        }
+        writer.flush();
+
+        let logs = logger.lock().unwrap().get_recent_logs(200);
+        assert_eq!(logs.len() as u64 + writer.dropped(), 100);
+        assert_eq!(logs.last().unwrap().message, "entry 99");
+        assert!(LogWriter::spawn(logger, 0).is_err());
+    }
+
     #[test]
     fn test_metrics() {
         let mut logger = Logger::new();
@@ -385,6 +1015,15 @@ mod tests {
         assert_eq!(metrics.len(), 1);
         assert_eq!(metrics[0].count, 2);
         assert_eq!(metrics[0].max_duration, Duration::from_millis(200));
+
+        logger.record_count("test_operation", 3);
+        logger.record_count("test_event", 2);
+        let metrics = logger.get_metrics();
+        let operation = metrics.iter().find(|m| m.name == "test_operation").unwrap();
+        assert_eq!(operation.count, 5);
+        assert_eq!(operation.duration, Duration::from_millis(300));
+        let event = metrics.iter().find(|m| m.name == "test_event").unwrap();
+        assert_eq!((event.count, event.duration), (2, Duration::ZERO));
     }
 
     #[test]
@@ -408,12 +1047,14 @@ mod tests {
         
         checker.register_check("always_pass", || CheckResult {
             passed: true,
+            degraded: false,
             message: "OK".to_string(),
             details: serde_json::json!({}),
         });
         
         checker.register_check("always_fail", || CheckResult {
             passed: false,
+            degraded: false,
             message: "FAIL".to_string(),
             details: serde_json::json!({}),
         });
@@ -422,4 +1063,34 @@ mod tests {
         assert!(!status.healthy); // Should fail because one check fails
         assert_eq!(status.checks.len(), 2);
     }
+
+    #[test]
+    fn test_benchmark_regression_degrades() {
+        let baseline = BenchmarkTimings {
+            matrix_exp_us: 10.0,
+            rk4_us: 1000.0,
+        };
+        let check = BenchmarkCheck::new(baseline);
+
+        let steady = check.compare(&BenchmarkTimings {
+            matrix_exp_us: 12.0,
+            rk4_us: 900.0,
+        });
+        assert!(steady.passed && !steady.degraded);
+
+        let throttled = check.compare(&BenchmarkTimings {
+            matrix_exp_us: 11.0,
+            rk4_us: 3500.0,
+        });
+        assert!(throttled.passed && throttled.degraded);
+        assert_eq!(throttled.message, "Performance regression: rk4 3.5x slower");
+
+        // A fresh measurement against itself is never a regression
+        let measured = BenchmarkTimings::measure().unwrap();
+        assert!(measured.matrix_exp_us > 0.0 && measured.rk4_us > 0.0);
+        let status = HealthChecker::new()
+            .with_benchmark(BenchmarkCheck::new(measured).with_threshold(f64::INFINITY))
+            .run_checks();
+        assert!(status.healthy && !status.degraded);
+    }
 }
@@ -1,38 +1,86 @@
 //! AST to IR lowering pass
 
 use crate::ast::*;
+use crate::bases;
+use crate::builtins;
 use crate::error::{EngineError, Result};
+use crate::functions::FunctionTable;
+use crate::gates;
 use crate::ir::*;
+use crate::operators::PauliString;
+use crate::plugin::KernelRegistry;
+use crate::typechecker::{eigenstate_level, embedding_args, is_operator, partial_trace_args, subsystem_dims};
 use crate::validator::ValidatedAst;
 use num_complex::Complex64;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Lowerer converts validated AST to IR
 pub struct Lowerer {
     ir: IrProgram,
     node_map: HashMap<String, NodeId>,
+    /// Already-lowered compound expressions, so structurally equal
+    /// subexpressions share one IR node (common subexpression elimination)
+    expr_cache: HashMap<Expr, NodeId>,
+    /// Lowered `measure` definitions, referenced by experiment schedules
+    measurements: HashMap<String, MeasurementType>,
+    /// `IrNode::ApplyChannel` nodes of `channel` definitions
+    channels: HashMap<String, NodeId>,
+    /// Constants read by `evaluate_constant`, whose values are baked into
+    /// the program
+    folded: HashSet<String>,
+    /// User-defined functions, inlined at each call
+    functions: FunctionTable,
+    /// Subsystem dimensions of composite operators, from the type checker
+    subsystems: HashMap<String, Vec<usize>>,
+    kernels: Arc<KernelRegistry>,
     next_id: NodeId,
 }
 
 impl Lowerer {
     pub fn new() -> Self {
+        Self::with_kernels(Arc::new(KernelRegistry::new()))
+    }
+
+    /// Create a lowerer that turns calls to registered plugin kernels
+    /// into `IrNode::Custom` nodes
+    pub fn with_kernels(kernels: Arc<KernelRegistry>) -> Self {
         Lowerer {
             ir: IrProgram::new(),
             node_map: HashMap::new(),
+            expr_cache: HashMap::new(),
+            measurements: HashMap::new(),
+            channels: HashMap::new(),
+            folded: HashSet::new(),
+            functions: FunctionTable::new(),
+            subsystems: HashMap::new(),
+            kernels,
             next_id: 0,
         }
     }
 
     /// Lower validated AST to IR
     pub fn lower(&mut self, validated: &ValidatedAst) -> Result<IrProgram> {
+        self.subsystems = validated.typed_ast.subsystems.clone();
         // Process statements
         for stmt in &validated.typed_ast.ast.statements {
             self.lower_statement(stmt)?;
         }
+        order_experiments(&mut self.ir.experiments)?;
+        self.ir.constants.retain(|(name, _)| !self.folded.contains(name));
 
         Ok(self.ir.clone())
     }
 
+    /// Lower validated AST to IR together with `expr`, which may refer to
+    /// the program's declarations; returns the program and the node
+    /// computing `expr`
+    pub fn lower_expression(&mut self, validated: &ValidatedAst, expr: &Expr) -> Result<(IrProgram, NodeId)> {
+        self.lower(validated)?;
+        let id = self.lower_expr(expr)?;
+        Ok((self.ir.clone(), id))
+    }
+
     fn lower_statement(&mut self, stmt: &Statement) -> Result<()> {
         match stmt {
             Statement::ConstDecl { name, value } => {
@@ -42,17 +90,50 @@ impl Lowerer {
                     value: Complex64::new(*value, 0.0),
                 };
                 self.ir.nodes.push(node);
-                self.node_map.insert(name.clone(), id);
+                self.ir.constants.push((name.clone(), id));
+                self.bind(name, id);
+                Ok(())
+            }
+            // Left unbound here, the value is supplied when the program runs
+            Statement::ParamDecl { name, .. } => {
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::Parameter { id, name: name.clone() });
+                self.bind(name, id);
                 Ok(())
             }
             Statement::MatrixDecl { name, value } => {
                 let id = self.lower_matrix_literal(name, value)?;
-                self.node_map.insert(name.clone(), id);
+                self.bind(name, id);
                 Ok(())
             }
+            Statement::OperatorDecl { name, value } => {
+                let id = self.lower_expr(value)?;
+                self.bind(name, id);
+                Ok(())
+            }
+            Statement::FunctionDef { name, params, body } => {
+                // Cached calls may have used an earlier definition
+                if self.functions.contains(name) {
+                    self.expr_cache.clear();
+                }
+                self.functions.define(name, params, body)
+            }
             Statement::HamiltonianDef { name, expr, .. } => {
                 let id = self.lower_expr(expr)?;
-                self.node_map.insert(name.clone(), id);
+                self.bind(name, id);
+                self.ir.hamiltonians.push((name.clone(), id));
+                Ok(())
+            }
+            Statement::MeasurementDef { name, spec } => {
+                let measurement = self.lower_measurement(name, spec)?;
+                self.measurements.insert(name.clone(), measurement);
+                Ok(())
+            }
+            Statement::ChannelDef { name, kraus } => {
+                let kraus = kraus.iter().map(|k| self.lower_expr(k)).collect::<Result<Vec<_>>>()?;
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::ApplyChannel { id, kraus });
+                self.channels.insert(name.clone(), id);
                 Ok(())
             }
             Statement::Experiment { name, body } => {
@@ -64,7 +145,27 @@ impl Lowerer {
         }
     }
 
+    /// Bind `name` to a node. Rebinding a name invalidates cached
+    /// expressions, which may refer to the old binding.
+    fn bind(&mut self, name: &str, id: NodeId) {
+        if self.node_map.insert(name.to_string(), id).is_some() {
+            self.expr_cache.clear();
+        }
+    }
+
     fn lower_expr(&mut self, expr: &Expr) -> Result<NodeId> {
+        if let Some(&id) = self.expr_cache.get(expr) {
+            return Ok(id);
+        }
+
+        let id = self.lower_expr_uncached(expr)?;
+        if !matches!(expr, Expr::Identifier(_)) {
+            self.expr_cache.insert(expr.clone(), id);
+        }
+        Ok(id)
+    }
+
+    fn lower_expr_uncached(&mut self, expr: &Expr) -> Result<NodeId> {
         match expr {
             Expr::Number(x) => {
                 let id = self.allocate_id();
@@ -74,10 +175,32 @@ impl Lowerer {
                 });
                 Ok(id)
             }
+            Expr::ComplexNumber(value) => {
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::Scalar { id, value: *value });
+                Ok(id)
+            }
             Expr::Identifier(name) => {
-                self.node_map.get(name).copied().ok_or_else(|| {
-                    EngineError::Internal(format!("Undefined identifier in IR lowering: {}", name))
-                })
+                if let Some(&id) = self.node_map.get(name) {
+                    return Ok(id);
+                }
+                // Builtin constants, then Pauli strings
+                let matrix = match builtins::lookup(name).and_then(|builtin| builtin.value()) {
+                    Some(matrix) => matrix,
+                    None => name
+                        .parse::<PauliString>()
+                        .map_err(|_| EngineError::Internal(format!("Undefined identifier in IR lowering: {}", name)))?
+                        .to_dense(),
+                };
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::LoadMatrix {
+                    id,
+                    name: name.clone(),
+                    data: matrix.iter().copied().collect(),
+                    shape: matrix.dim(),
+                });
+                self.node_map.insert(name.clone(), id);
+                Ok(id)
             }
             Expr::Add(left, right) => {
                 let left_id = self.lower_expr(left)?;
@@ -90,6 +213,41 @@ impl Lowerer {
                 });
                 Ok(id)
             }
+            // a - b as a + (-1) b
+            Expr::Sub(left, right) => {
+                let left_id = self.lower_expr(left)?;
+                let right_id = self.lower_expr(right)?;
+                let minus_one = self.lower_expr(&Expr::Number(-1.0))?;
+                let negated = self.allocate_id();
+                self.ir.nodes.push(IrNode::MatrixMul {
+                    id: negated,
+                    left: minus_one,
+                    right: right_id,
+                });
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::MatrixAdd {
+                    id,
+                    left: left_id,
+                    right: negated,
+                });
+                Ok(id)
+            }
+            // Division by a constant, as multiplication by its reciprocal
+            Expr::Div(left, right) => {
+                let divisor = self.evaluate_constant(right)?;
+                if divisor == 0.0 {
+                    return Err(EngineError::validation_error(format!("Division by zero in {:?}", expr)));
+                }
+                let left_id = self.lower_expr(left)?;
+                let reciprocal = self.lower_expr(&Expr::Number(1.0 / divisor))?;
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::MatrixMul {
+                    id,
+                    left: reciprocal,
+                    right: left_id,
+                });
+                Ok(id)
+            }
             Expr::Mul(left, right) => {
                 let left_id = self.lower_expr(left)?;
                 let right_id = self.lower_expr(right)?;
@@ -130,6 +288,86 @@ impl Lowerer {
                 });
                 Ok(id)
             }
+            Expr::FuncCall { name, args } if name == "ptrace" => {
+                let (subsystem, dims) = partial_trace_args(args)?;
+                let dims = subsystem_dims(&args[0], dims, &self.subsystems);
+                let input = self.lower_expr(&args[0])?;
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::PartialTrace {
+                    id,
+                    input,
+                    dims,
+                    keep: vec![subsystem],
+                });
+                Ok(id)
+            }
+            // I ⊗ … ⊗ A ⊗ … ⊗ I, multiplied out from the left
+            Expr::FuncCall { name, args } if name == "embed" => {
+                let (subsystem, dims) = embedding_args(args)?;
+                let operator = self.lower_expr(&args[0])?;
+                let mut identities = HashMap::new();
+                let mut product = None;
+                for (k, &d) in dims.iter().enumerate() {
+                    let factor = if k == subsystem {
+                        operator
+                    } else {
+                        *identities.entry(d).or_insert_with(|| self.lower_identity(d))
+                    };
+                    product = Some(match product {
+                        None => factor,
+                        Some(left) => {
+                            let id = self.allocate_id();
+                            self.ir.nodes.push(IrNode::TensorProduct { id, left, right: factor });
+                            id
+                        }
+                    });
+                }
+                Ok(product.expect("embed has the embedded subsystem"))
+            }
+            // Literals inside expressions, such as Kraus operators, with
+            // entries evaluated now like those of a `matrix` declaration
+            Expr::Matrix(matrix) => self.lower_matrix_literal(&expr.to_string(), matrix),
+            Expr::FuncCall { name, args } if is_operator(name) => {
+                let builtin = builtins::lookup(name).expect("checked by is_operator");
+                let matrix = builtin.operator(args)?;
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::LoadMatrix {
+                    id,
+                    name: expr.to_string(),
+                    data: matrix.iter().copied().collect(),
+                    shape: matrix.dim(),
+                });
+                Ok(id)
+            }
+            Expr::FuncCall { name, args } if matches!(name.as_str(), "eig" | "eigvals" | "eigenstate") => {
+                let output = match name.as_str() {
+                    "eig" => EigenOutput::Eigenvectors,
+                    "eigvals" => EigenOutput::Eigenvalues,
+                    _ => EigenOutput::Eigenstate(eigenstate_level(args)?),
+                };
+                let input = self.lower_expr(&args[0])?;
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::Eigendecompose { id, input, output });
+                Ok(id)
+            }
+            // User-defined functions shadow plugin kernels of the same name
+            Expr::FuncCall { name, .. } if self.functions.contains(name) => {
+                let inlined = self.functions.inline(expr)?;
+                self.lower_expr(&inlined)
+            }
+            Expr::FuncCall { name, args } if self.kernels.contains(name) => {
+                let mut inputs = Vec::with_capacity(args.len());
+                for arg in args {
+                    inputs.push(self.lower_expr(arg)?);
+                }
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::Custom {
+                    id,
+                    kernel: name.clone(),
+                    inputs,
+                });
+                Ok(id)
+            }
             _ => Err(EngineError::Unsupported(format!(
                 "Expression lowering not yet implemented: {:?}",
                 expr
@@ -137,6 +375,20 @@ impl Lowerer {
         }
     }
 
+    fn lower_identity(&mut self, dim: usize) -> NodeId {
+        let data = (0..dim * dim)
+            .map(|i| Complex64::new(if i % (dim + 1) == 0 { 1.0 } else { 0.0 }, 0.0))
+            .collect();
+        let id = self.allocate_id();
+        self.ir.nodes.push(IrNode::LoadMatrix {
+            id,
+            name: format!("identity({})", dim),
+            data,
+            shape: (dim, dim),
+        });
+        id
+    }
+
     fn lower_matrix_literal(&mut self, name: &str, mat: &MatrixLiteral) -> Result<NodeId> {
         let n_rows = mat.rows.len();
         let n_cols = mat.rows[0].len();
@@ -160,12 +412,68 @@ impl Lowerer {
         Ok(id)
     }
 
+    /// Lower each projector or effect to a `LoadMatrix` node named
+    /// `name[k]`; named bases are expanded here
+    fn lower_measurement(&mut self, name: &str, spec: &MeasurementSpec) -> Result<MeasurementType> {
+        let element = |k: usize| format!("{}[{}]", name, k);
+        Ok(match spec {
+            MeasurementSpec::Projective { projectors } => MeasurementType::Projective {
+                projectors: projectors
+                    .iter()
+                    .enumerate()
+                    .map(|(k, p)| self.lower_matrix_literal(&element(k), p))
+                    .collect::<Result<_>>()?,
+            },
+            MeasurementSpec::POVM { effects } => MeasurementType::POVM {
+                effects: effects
+                    .iter()
+                    .enumerate()
+                    .map(|(k, e)| self.lower_matrix_literal(&element(k), e))
+                    .collect::<Result<_>>()?,
+            },
+            MeasurementSpec::Basis { basis, dim } => MeasurementType::Projective {
+                projectors: bases::basis_projectors(*basis, *dim)?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(k, p)| {
+                        let id = self.allocate_id();
+                        self.ir.nodes.push(IrNode::LoadMatrix {
+                            id,
+                            name: element(k),
+                            data: p.iter().copied().collect(),
+                            shape: p.dim(),
+                        });
+                        id
+                    })
+                    .collect(),
+            },
+        })
+    }
+
     fn lower_experiment(&mut self, name: &str, body: &ExperimentBody) -> Result<IrExperiment> {
-        // Lower initial state
+        // Lower initial state; a state taken from another experiment gets
+        // an id with no node, filled in by the executor
+        let mut initial_from = None;
         let initial_state = if let Some(init) = &body.init {
             match init {
                 StateSpec::Ket(vec) => self.lower_vector_literal("init_ket", vec)?,
                 StateSpec::Rho(mat) => self.lower_matrix_literal("init_rho", mat)?,
+                StateSpec::From(source) => {
+                    initial_from = Some(source.clone());
+                    self.allocate_id()
+                }
+                StateSpec::Eigenstate { hamiltonian, level } => {
+                    if !self.node_map.contains_key(hamiltonian) {
+                        return Err(EngineError::validation_error(format!(
+                            "Unknown Hamiltonian '{}' in experiment '{}'",
+                            hamiltonian, name
+                        )));
+                    }
+                    self.lower_expr(&Expr::FuncCall {
+                        name: "eigenstate".to_string(),
+                        args: vec![Expr::Identifier(hamiltonian.clone()), Expr::Number(*level as f64)],
+                    })?
+                }
             }
         } else {
             return Err(EngineError::validation_error(
@@ -173,6 +481,19 @@ impl Lowerer {
             ));
         };
 
+        // Gates act on the initial state in order, the last result being
+        // the state the experiment starts from
+        if initial_from.is_some() && !body.gates.is_empty() {
+            return Err(EngineError::validation_error(format!(
+                "Experiment '{}' cannot apply gates to a state taken from another experiment",
+                name
+            )));
+        }
+        let initial_state = body
+            .gates
+            .iter()
+            .try_fold(initial_state, |state, application| self.lower_gate(application, state))?;
+
         // Lower evolution
         let evolution = if let Some(evol) = &body.evolution {
             Some(self.lower_evolution(evol)?)
@@ -180,17 +501,111 @@ impl Lowerer {
             None
         };
 
-        // Lower measurements (stub for now)
-        let measurements = Vec::new();
+        // Measurements on the time grid happen there; ones between grid
+        // points see the interpolated state
+        let times = evolution.as_ref().map_or_else(|| vec![0.0], |e| e.times.clone());
+        let mut measurements = Vec::new();
+        for event in body.measurements.iter().flat_map(|m| &m.events) {
+            let measurement_type = self
+                .measurements
+                .get(&event.measurement_name)
+                .cloned()
+                .ok_or_else(|| {
+                    EngineError::validation_error(format!(
+                        "Unknown measurement '{}' in experiment '{}'",
+                        event.measurement_name, name
+                    ))
+                })?;
+            let (time_index, time) = measurement_time(&times, event.time);
+            measurements.push(IrMeasurement {
+                time_index,
+                measurement_type,
+                time,
+            });
+        }
+
+        // Channels likewise act at the nearest time on the grid
+        let channels = body
+            .channels
+            .iter()
+            .map(|event| {
+                let channel = *self.channels.get(&event.channel_name).ok_or_else(|| {
+                    EngineError::validation_error(format!(
+                        "Unknown channel '{}' in experiment '{}'",
+                        event.channel_name, name
+                    ))
+                })?;
+                Ok(IrChannelApplication {
+                    time_index: nearest_time_index(&times, event.time),
+                    channel,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let observables = body
+            .observables
+            .iter()
+            .map(|observable| {
+                Ok(IrObservable {
+                    label: observable.label.clone(),
+                    operator: self.lower_expr(&observable.expr)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(IrExperiment {
             name: name.to_string(),
             initial_state,
             evolution,
             measurements,
+            initial_from,
+            channels,
+            observables,
         })
     }
 
+    /// `apply` of a library gate, or else of the declared matrix or Pauli
+    /// string of that name, to `state`
+    fn lower_gate(&mut self, application: &GateApplication, state: NodeId) -> Result<NodeId> {
+        let name = &application.gate;
+        let gate = match (gates::lookup(name), &application.angle) {
+            (Some(gate), Some(angle)) => {
+                let rotation = gate.rotation(angle.clone()).ok_or_else(|| {
+                    EngineError::validation_error(format!("Gate {} takes no angle", name))
+                })?;
+                self.lower_expr(&rotation)?
+            }
+            (Some(gate), None) => {
+                let matrix = gate.matrix().ok_or_else(|| {
+                    EngineError::validation_error(format!("Gate {} needs an angle, as in {}(theta)", name, name))
+                })?;
+                let id = self.allocate_id();
+                self.ir.nodes.push(IrNode::LoadMatrix {
+                    id,
+                    name: name.clone(),
+                    data: matrix.iter().copied().collect(),
+                    shape: matrix.dim(),
+                });
+                id
+            }
+            (None, None) => self.lower_expr(&Expr::Identifier(name.clone()))?,
+            (None, Some(_)) => {
+                return Err(EngineError::validation_error(format!(
+                    "Only rotation gates take an angle, not {}",
+                    name
+                )))
+            }
+        };
+        let id = self.allocate_id();
+        self.ir.nodes.push(IrNode::ApplyGate {
+            id,
+            gate,
+            state,
+            targets: application.targets.clone(),
+        });
+        Ok(id)
+    }
+
     fn lower_evolution(&mut self, evol: &EvolutionSpec) -> Result<IrEvolution> {
         let hamiltonian_id = self.node_map.get(&evol.hamiltonian_name).copied().ok_or_else(|| {
             EngineError::Internal(format!("Hamiltonian '{}' not found", evol.hamiltonian_name))
@@ -210,14 +625,13 @@ impl Lowerer {
                     EngineError::Internal(format!("Lindblad operator '{}' not found", term.operator_name))
                 })?;
                 
-                // Evaluate rate (assume constant for now)
-                let rate = if let Expr::Number(r) = &*term.rate {
-                    *r
-                } else {
-                    return Err(EngineError::Unsupported(
-                        "Non-constant Lindblad rates not yet supported".to_string(),
-                    ));
-                };
+                let rate = self.evaluate_constant(&term.rate)?;
+                if !rate.is_finite() || rate < 0.0 {
+                    return Err(EngineError::validation_error(format!(
+                        "Lindblad rate for '{}' must be finite and non-negative, got {}",
+                        term.operator_name, rate
+                    )));
+                }
 
                 operators.push(LindbladOperator {
                     operator: op_id,
@@ -262,6 +676,48 @@ impl Lowerer {
         }
     }
 
+    /// Evaluate a real expression built from numbers and declared constants
+    fn evaluate_constant(&mut self, expr: &Expr) -> Result<f64> {
+        match expr {
+            Expr::Number(x) => Ok(*x),
+            Expr::ComplexNumber(c) if c.im == 0.0 => Ok(c.re),
+            Expr::Identifier(name) => {
+                let id = self.node_map.get(name).copied().ok_or_else(|| {
+                    EngineError::validation_error(format!("Undefined constant: {}", name))
+                })?;
+                self.folded.insert(name.clone());
+                match self.ir.nodes.iter().find(|node| node.id() == id) {
+                    Some(IrNode::Scalar { value, .. }) if value.im == 0.0 => Ok(value.re),
+                    Some(IrNode::Parameter { .. }) => Err(EngineError::validation_error(format!(
+                        "Parameter '{}' is only known at run time; bind it with a parameter file to use it here",
+                        name
+                    ))),
+                    _ => Err(EngineError::validation_error(format!(
+                        "'{}' is not a real constant",
+                        name
+                    ))),
+                }
+            }
+            Expr::Add(a, b) => Ok(self.evaluate_constant(a)? + self.evaluate_constant(b)?),
+            Expr::Sub(a, b) => Ok(self.evaluate_constant(a)? - self.evaluate_constant(b)?),
+            Expr::Mul(a, b) => Ok(self.evaluate_constant(a)? * self.evaluate_constant(b)?),
+            Expr::Div(a, b) => Ok(self.evaluate_constant(a)? / self.evaluate_constant(b)?),
+            Expr::Pow(a, b) => Ok(self.evaluate_constant(a)?.powf(self.evaluate_constant(b)?)),
+            Expr::Sqrt(a) => Ok(self.evaluate_constant(a)?.sqrt()),
+            Expr::Exp(a) => Ok(self.evaluate_constant(a)?.exp()),
+            Expr::Sin(a) => Ok(self.evaluate_constant(a)?.sin()),
+            Expr::Cos(a) => Ok(self.evaluate_constant(a)?.cos()),
+            Expr::FuncCall { name, .. } if self.functions.contains(name) => {
+                let inlined = self.functions.inline(expr)?;
+                self.evaluate_constant(&inlined)
+            }
+            _ => Err(EngineError::Unsupported(format!(
+                "Expression is not a compile-time constant: {:?}",
+                expr
+            ))),
+        }
+    }
+
     fn allocate_id(&mut self) -> NodeId {
         let id = self.next_id;
         self.next_id += 1;
@@ -275,6 +731,62 @@ impl Default for Lowerer {
     }
 }
 
+/// Index of the grid time closest to `t`
+fn nearest_time_index(times: &[f64], t: f64) -> usize {
+    times
+        .iter()
+        .enumerate()
+        .min_by(|a, b| (a.1 - t).abs().total_cmp(&(b.1 - t).abs()))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Grid index of a measurement at `t`, with `t` itself when it falls
+/// strictly between two grid points; times on the grid up to rounding, or
+/// outside it, go to the nearest grid point
+fn measurement_time(times: &[f64], t: f64) -> (usize, Option<f64>) {
+    let nearest = nearest_time_index(times, t);
+    let (first, last) = (times[0], times[times.len() - 1]);
+    let tolerance = 1e-9 * (last - first).abs().max(1.0);
+    if (times[nearest] - t).abs() <= tolerance || t < first || t > last {
+        return (nearest, None);
+    }
+    (times.partition_point(|&s| s <= t) - 1, Some(t))
+}
+
+/// Reorder `experiments` so that each one starting from another's final
+/// state runs after it, keeping declaration order otherwise
+fn order_experiments(experiments: &mut Vec<IrExperiment>) -> Result<()> {
+    for experiment in experiments.iter() {
+        if let Some(source) = &experiment.initial_from {
+            if !experiments.iter().any(|e| &e.name == source) {
+                return Err(EngineError::validation_error(format!(
+                    "Experiment '{}' starts from unknown experiment '{}'",
+                    experiment.name, source
+                )));
+            }
+        }
+    }
+
+    let mut pending = std::mem::take(experiments);
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|e| match &e.initial_from {
+            Some(source) => experiments.iter().any(|done| &done.name == source),
+            None => true,
+        });
+        match ready {
+            Some(i) => experiments.push(pending.remove(i)),
+            None => {
+                let names: Vec<&str> = pending.iter().map(|e| e.name.as_str()).collect();
+                return Err(EngineError::validation_error(format!(
+                    "Experiments {} start from each other's final states",
+                    names.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,4 +796,203 @@ mod tests {
         let lowerer = Lowerer::new();
         assert_eq!(lowerer.next_id, 0);
     }
+
+    #[test]
+    fn test_lindblad_rate_from_constants() {
+        let mut lowerer = Lowerer::new();
+        lowerer
+            .lower_statement(&Statement::ConstDecl {
+                name: "gamma".to_string(),
+                value: 0.4,
+            })
+            .unwrap();
+        lowerer.node_map.insert("H".to_string(), 0);
+        lowerer.node_map.insert("L".to_string(), 0);
+
+        let evol = EvolutionSpec {
+            state_name: "init".to_string(),
+            hamiltonian_name: "H".to_string(),
+            timegrid: TimeGrid::Explicit { times: vec![0.0, 1.0] },
+            lindblad_ops: vec![LindbladTerm {
+                operator_name: "L".to_string(),
+                rate: Box::new(Expr::Div(
+                    Box::new(Expr::Identifier("gamma".to_string())),
+                    Box::new(Expr::Number(2.0)),
+                )),
+            }],
+        };
+
+        let ir_evol = lowerer.lower_evolution(&evol).unwrap();
+        let EvolutionMethod::Lindblad { operators, .. } = ir_evol.method else {
+            panic!("expected Lindblad evolution");
+        };
+        assert!((operators[0].rate - 0.2).abs() < 1e-12);
+        assert!(lowerer.folded.contains("gamma"));
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_experiments_ordered_by_final_state_dependencies() {
+        let lower = |source: &str| {
+            let ast = crate::parser::parse_dsl(source).unwrap();
+            crate::lower(&crate::validate_quantum(&ast).unwrap())
+        };
+        let ir = lower(
+            "
+            matrix sigma_x = [0, 1; 1, 0];
+            Hamiltonian H = sigma_x;
+            experiment probe {
+                init: from(prepare.final_state);
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 11));
+            }
+            experiment prepare {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 11));
+            }
+        ",
+        )
+        .unwrap();
+        let names: Vec<&str> = ir.experiments.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["prepare", "probe"]);
+        assert_eq!(ir.experiments[1].initial_from.as_deref(), Some("prepare"));
+
+        let err = lower(
+            "
+            matrix sigma_x = [0, 1; 1, 0];
+            Hamiltonian H = sigma_x;
+            experiment a { init: from(b.final_state); }
+            experiment b { init: from(a.final_state); }
+        ",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Experiments a, b start from each other's final states"));
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_channel_lowered_and_scheduled() {
+        let program = |k1: &str, channel: &str| {
+            format!(
+                "
+                matrix sigma_x = [0, 1; 1, 0];
+                Hamiltonian H = sigma_x;
+                channel dephase = Kraus([[0.8, 0; 0, 0.8], {}]);
+                experiment noisy {{
+                    init: ket(vec(1, 0));
+                    evolution: evolve(init, H, timegrid=(0.0, 0.1, 11));
+                    channels: [(0.52, {})];
+                }}
+            ",
+                k1, channel
+            )
+        };
+        let validate = |source: String| crate::validate_quantum(&crate::parser::parse_dsl(&source).unwrap());
+
+        let ir = crate::lower(&validate(program("0.6 * Z", "dephase")).unwrap()).unwrap();
+        let application = &ir.experiments[0].channels[0];
+        assert_eq!(application.time_index, 5);
+        assert!(matches!(
+            &ir.nodes[application.channel],
+            IrNode::ApplyChannel { kraus, .. } if kraus.len() == 2
+        ));
+
+        let err = validate(program("0.5 * Z", "dephase")).unwrap_err();
+        assert!(matches!(err, EngineError::NotCPTP { .. }), "{}", err);
+        assert!(crate::lower(&validate(program("0.6 * Z", "missing")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_negative_lindblad_rate_rejected() {
+        let mut lowerer = Lowerer::new();
+        lowerer.node_map.insert("H".to_string(), 0);
+        lowerer.node_map.insert("L".to_string(), 0);
+
+        let evol = EvolutionSpec {
+            state_name: "init".to_string(),
+            hamiltonian_name: "H".to_string(),
+            timegrid: TimeGrid::Explicit { times: vec![0.0] },
+            lindblad_ops: vec![LindbladTerm {
+                operator_name: "L".to_string(),
+                rate: Box::new(Expr::Number(-1.0)),
+            }],
+        };
+
+        assert!(lowerer.lower_evolution(&evol).is_err());
+    }
+
+    #[test]
+    fn test_named_basis_measurement_scheduled() {
+        let mut lowerer = Lowerer::new();
+        lowerer
+            .lower_statement(&Statement::MeasurementDef {
+                name: "bell".to_string(),
+                spec: MeasurementSpec::Basis {
+                    basis: MeasurementBasis::Bell,
+                    dim: 4,
+                },
+            })
+            .unwrap();
+        assert_eq!(lowerer.ir.nodes.len(), 4);
+
+        lowerer.node_map.insert("H".to_string(), 0);
+        let measure_at = |time: f64| MeasurementEvent {
+            time,
+            measurement_name: "bell".to_string(),
+        };
+        let body = ExperimentBody {
+            init: Some(StateSpec::Ket(VectorLiteral {
+                elements: [1.0, 0.0, 0.0, 0.0].map(Expr::Number).to_vec(),
+            })),
+            evolution: Some(EvolutionSpec {
+                state_name: "init".to_string(),
+                hamiltonian_name: "H".to_string(),
+                timegrid: TimeGrid::Explicit {
+                    times: vec![0.0, 0.5, 1.0],
+                },
+                lindblad_ops: vec![],
+            }),
+            measurements: Some(MeasurementSchedule {
+                events: vec![measure_at(0.0), measure_at(0.9), measure_at(1.0 + 1e-12)],
+            }),
+            channels: Vec::new(),
+            observables: Vec::new(),
+            gates: Vec::new(),
+        };
+        let experiment = lowerer.lower_experiment("bell_prep", &body).unwrap();
+        let indices: Vec<usize> = experiment.measurements.iter().map(|m| m.time_index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        let times: Vec<Option<f64>> = experiment.measurements.iter().map(|m| m.time).collect();
+        assert_eq!(times, vec![None, Some(0.9), None]);
+        assert!(matches!(
+            &experiment.measurements[0].measurement_type,
+            MeasurementType::Projective { projectors } if projectors.len() == 4
+        ));
+
+        let mut unknown = body;
+        unknown.measurements = Some(MeasurementSchedule {
+            events: vec![MeasurementEvent {
+                time: 0.0,
+                measurement_name: "missing".to_string(),
+            }],
+        });
+        assert!(lowerer.lower_experiment("bell_prep", &unknown).is_err());
+    }
+
+    #[test]
+    fn test_common_subexpressions_share_nodes() {
+        let mut lowerer = Lowerer::new();
+        lowerer.node_map.insert("A".to_string(), 0);
+        lowerer.next_id = 1;
+
+        let dagger = || Expr::Dagger(Box::new(Expr::Identifier("A".to_string())));
+        let expr = Expr::Add(Box::new(dagger()), Box::new(dagger()));
+        lowerer.lower_expr(&expr).unwrap();
+
+        // One Dagger node reused for both operands, plus the Add
+        assert_eq!(lowerer.ir.nodes.len(), 2);
+        assert!(matches!(
+            lowerer.ir.nodes[1],
+            IrNode::MatrixAdd { left, right, .. } if left == right
+        ));
+    }
 }
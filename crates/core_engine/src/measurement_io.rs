@@ -0,0 +1,588 @@
+//! Measurement data import
+//!
+//! Readers for the measurement file formats our instruments and analysis
+//! scripts produce, all mapped onto [`MeasurementData`] (observable
+//! estimates) and [`MeasurementEvent`] (outcome counts). The layout of each
+//! format is documented in `docs/MEASUREMENT_DATA.md`.
+//!
+//! [`import_measurements`] picks the reader from the file extension and, if
+//! that is inconclusive, from the leading bytes of the file.
+
+use crate::error::{EngineError, Result};
+use crate::stats::{self, MeasurementData, MeasurementEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Name of the group/prefix holding outcome counts in NPZ and HDF5 files
+pub const EVENTS_GROUP: &str = "events";
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const HDF5_MAGIC: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+/// Supported measurement file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeasurementFormat {
+    Csv,
+    JsonLines,
+    Npz,
+    Hdf5,
+}
+
+/// Everything read from a measurement file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedMeasurements {
+    pub data: MeasurementData,
+    pub events: Vec<MeasurementEvent>,
+}
+
+/// What importing does with NaN or infinite values in a measurement file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NonFinitePolicy {
+    /// Fail, naming the first offending entry
+    #[default]
+    Error,
+    /// Drop observable points and events holding a non-finite value
+    Skip,
+    /// Replace ±∞ by the largest finite value of the same sign, so that an
+    /// infinite uncertainty still gives the point no weight; NaNs are dropped
+    Clamp,
+}
+
+impl std::str::FromStr for NonFinitePolicy {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(NonFinitePolicy::Error),
+            "skip" => Ok(NonFinitePolicy::Skip),
+            "clamp" => Ok(NonFinitePolicy::Clamp),
+            _ => Err(EngineError::validation_error(format!(
+                "Unknown non-finite policy '{}' (expected error, skip or clamp)",
+                s
+            ))),
+        }
+    }
+}
+
+/// One line of a JSON-lines measurement file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MeasurementRecord {
+    Observable {
+        observable: String,
+        value: f64,
+        uncertainty: f64,
+    },
+    Event(MeasurementEvent),
+}
+
+impl MeasurementFormat {
+    /// Detect the format of `path` from its extension, falling back to
+    /// sniffing the first bytes of the file
+    pub fn detect(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("csv") => return Ok(MeasurementFormat::Csv),
+            Some("jsonl") | Some("ndjson") | Some("json") => return Ok(MeasurementFormat::JsonLines),
+            Some("npz") => return Ok(MeasurementFormat::Npz),
+            Some("h5") | Some("hdf5") | Some("he5") => return Ok(MeasurementFormat::Hdf5),
+            _ => {}
+        }
+
+        let mut header = [0u8; 8];
+        let mut file = File::open(path)?;
+        let n = file.read(&mut header)?;
+        Ok(Self::sniff(&header[..n]))
+    }
+
+    fn sniff(header: &[u8]) -> Self {
+        if header.starts_with(ZIP_MAGIC) {
+            MeasurementFormat::Npz
+        } else if header.starts_with(HDF5_MAGIC) {
+            MeasurementFormat::Hdf5
+        } else if header.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            MeasurementFormat::JsonLines
+        } else {
+            MeasurementFormat::Csv
+        }
+    }
+}
+
+impl ImportedMeasurements {
+    /// Apply `policy` to every NaN or infinite value, returning how many
+    /// observable points and events were dropped or clamped
+    pub fn handle_non_finite(&mut self, policy: NonFinitePolicy) -> Result<usize> {
+        let mut affected = 0;
+
+        let mut names: Vec<&String> = self.data.observables.keys().collect();
+        names.sort();
+        let names: Vec<String> = names.into_iter().cloned().collect();
+        for name in names {
+            let (values, uncertainties) = self.data.observables.get_mut(&name).unwrap();
+            let mut kept = (Vec::new(), Vec::new());
+            for (i, (&value, &uncertainty)) in values.iter().zip(uncertainties.iter()).enumerate() {
+                if value.is_finite() && uncertainty.is_finite() {
+                    kept.0.push(value);
+                    kept.1.push(uncertainty);
+                    continue;
+                }
+                affected += 1;
+                match policy {
+                    NonFinitePolicy::Error => {
+                        let (field, bad) = if value.is_finite() {
+                            ("uncertainty", uncertainty)
+                        } else {
+                            ("value", value)
+                        };
+                        return Err(EngineError::non_finite(format!(
+                            "{} {} for observable '{}' at index {}",
+                            bad, field, name, i
+                        )));
+                    }
+                    NonFinitePolicy::Clamp if !value.is_nan() && !uncertainty.is_nan() => {
+                        kept.0.push(clamp_finite(value));
+                        kept.1.push(clamp_finite(uncertainty));
+                    }
+                    _ => self.data.num_shots = self.data.num_shots.saturating_sub(1),
+                }
+            }
+            *values = kept.0;
+            *uncertainties = kept.1;
+        }
+
+        let mut events = Vec::with_capacity(self.events.len());
+        for (i, event) in std::mem::take(&mut self.events).into_iter().enumerate() {
+            if event.time.is_finite() {
+                events.push(event);
+                continue;
+            }
+            affected += 1;
+            match policy {
+                NonFinitePolicy::Error => {
+                    return Err(EngineError::non_finite(format!(
+                        "{} time for event {} of measurement '{}'",
+                        event.time, i, event.measurement_id
+                    )));
+                }
+                NonFinitePolicy::Clamp if !event.time.is_nan() => events.push(MeasurementEvent {
+                    time: clamp_finite(event.time),
+                    ..event
+                }),
+                _ => self.data.num_shots = self.data.num_shots.saturating_sub(event.count),
+            }
+        }
+        self.events = events;
+
+        Ok(affected)
+    }
+}
+
+fn clamp_finite(x: f64) -> f64 {
+    x.clamp(f64::MIN, f64::MAX)
+}
+
+/// Load a measurement file, detecting its format automatically, and fail on
+/// NaN or infinite values
+pub fn import_measurements(path: impl AsRef<Path>) -> Result<ImportedMeasurements> {
+    import_measurements_with(path, NonFinitePolicy::Error)
+}
+
+/// [`import_measurements`], handling NaN and infinite values by `policy`
+pub fn import_measurements_with(
+    path: impl AsRef<Path>,
+    policy: NonFinitePolicy,
+) -> Result<ImportedMeasurements> {
+    let format = MeasurementFormat::detect(path.as_ref())?;
+    let mut imported = read_measurements(path.as_ref(), format)?;
+    let affected = imported.handle_non_finite(policy)?;
+    if affected > 0 {
+        crate::log_warn!(
+            "measurement_io",
+            "{}: {} non-finite entries handled by {:?} policy",
+            path.as_ref().display(),
+            affected,
+            policy
+        );
+    }
+    Ok(imported)
+}
+
+/// Load a measurement file in a known format, failing on NaN or infinite
+/// values
+pub fn import_measurements_as(
+    path: impl AsRef<Path>,
+    format: MeasurementFormat,
+) -> Result<ImportedMeasurements> {
+    let mut imported = read_measurements(path.as_ref(), format)?;
+    imported.handle_non_finite(NonFinitePolicy::Error)?;
+    Ok(imported)
+}
+
+fn read_measurements(path: &Path, format: MeasurementFormat) -> Result<ImportedMeasurements> {
+    let mut imported = match format {
+        MeasurementFormat::Csv => {
+            let path_str = path.to_str().ok_or_else(|| {
+                EngineError::SerializationError(format!("Non UTF-8 path: {}", path.display()))
+            })?;
+            ImportedMeasurements {
+                data: stats::load_measurements(path_str)?,
+                events: Vec::new(),
+            }
+        }
+        MeasurementFormat::JsonLines => load_json_lines(path)?,
+        MeasurementFormat::Npz => load_npz(path)?,
+        MeasurementFormat::Hdf5 => load_hdf5(path)?,
+    };
+
+    imported.data.metadata = serde_json::json!({
+        "source": path.display().to_string(),
+        "format": format,
+    });
+    Ok(imported)
+}
+
+/// Load a JSON-lines file where each line is either an observable record
+/// (`observable`, `value`, `uncertainty`) or a [`MeasurementEvent`]
+pub fn load_json_lines(path: impl AsRef<Path>) -> Result<ImportedMeasurements> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut builder = Builder::default();
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+
+        let record: MeasurementRecord = serde_json::from_str(trimmed).map_err(|e| {
+            EngineError::parse_error(idx + 1, e.column(), format!("Invalid measurement record: {}", e))
+        })?;
+
+        match record {
+            MeasurementRecord::Observable {
+                observable,
+                value,
+                uncertainty,
+            } => builder.push_observable(observable, value, uncertainty),
+            MeasurementRecord::Event(event) => builder.events.push(event),
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+/// Load a numpy `.npz` archive
+///
+/// Observables are stored as `<name>/value` and `<name>/uncertainty`
+/// float arrays; outcome counts as `events/<measurement_id>/{time,outcome,count}`.
+#[cfg(feature = "npz")]
+pub fn load_npz(path: impl AsRef<Path>) -> Result<ImportedMeasurements> {
+    use ndarray::Array1;
+    use ndarray_npy::NpzReader;
+
+    let npz_error = |e: ndarray_npy::ReadNpzError| EngineError::SerializationError(format!("NPZ: {}", e));
+
+    let mut npz = NpzReader::new(File::open(path)?).map_err(npz_error)?;
+    let names = npz.names().map_err(npz_error)?;
+    let mut arrays = ArraySet::default();
+
+    for name in &names {
+        let key = name.strip_suffix(".npy").unwrap_or(name);
+        if is_integer_column(key) {
+            let array: Array1<i64> = npz.by_name(name).map_err(npz_error)?;
+            arrays.ints.insert(key.to_string(), array.to_vec());
+        } else {
+            let array: Array1<f64> = npz.by_name(name).map_err(npz_error)?;
+            arrays.floats.insert(key.to_string(), array.to_vec());
+        }
+    }
+
+    arrays.into_measurements()
+}
+
+#[cfg(not(feature = "npz"))]
+pub fn load_npz(_path: impl AsRef<Path>) -> Result<ImportedMeasurements> {
+    Err(EngineError::Unsupported(
+        "NPZ import requires the `npz` feature".to_string(),
+    ))
+}
+
+/// Load an HDF5 file laid out like the NPZ archive, with one group per
+/// observable and an `events` group of per-measurement groups
+#[cfg(feature = "hdf5")]
+pub fn load_hdf5(path: impl AsRef<Path>) -> Result<ImportedMeasurements> {
+    let h5_error = |e: hdf5::Error| EngineError::SerializationError(format!("HDF5: {}", e));
+
+    let file = hdf5::File::open(path).map_err(h5_error)?;
+    let mut arrays = ArraySet::default();
+
+    for key in dataset_paths(&file, "").map_err(h5_error)? {
+        let dataset = file.dataset(&key).map_err(h5_error)?;
+        if is_integer_column(&key) {
+            let values = dataset.read_raw::<i64>().map_err(h5_error)?;
+            arrays.ints.insert(key, values);
+        } else {
+            let values = dataset.read_raw::<f64>().map_err(h5_error)?;
+            arrays.floats.insert(key, values);
+        }
+    }
+
+    arrays.into_measurements()
+}
+
+#[cfg(not(feature = "hdf5"))]
+pub fn load_hdf5(_path: impl AsRef<Path>) -> Result<ImportedMeasurements> {
+    Err(EngineError::Unsupported(
+        "HDF5 import requires the `hdf5` feature".to_string(),
+    ))
+}
+
+/// Recursively collect dataset paths below `prefix`, without leading slash
+#[cfg(feature = "hdf5")]
+fn dataset_paths(group: &hdf5::Group, prefix: &str) -> hdf5::Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for name in group.member_names()? {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if let Ok(child) = group.group(&name) {
+            paths.extend(dataset_paths(&child, &path)?);
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// `events/<id>/outcome` and `events/<id>/count` are integer columns
+#[cfg(any(feature = "npz", feature = "hdf5"))]
+fn is_integer_column(key: &str) -> bool {
+    key.starts_with(EVENTS_GROUP) && (key.ends_with("/outcome") || key.ends_with("/count"))
+}
+
+/// Flat name -> column map shared by the NPZ and HDF5 readers
+#[cfg(any(feature = "npz", feature = "hdf5"))]
+#[derive(Default)]
+struct ArraySet {
+    floats: HashMap<String, Vec<f64>>,
+    ints: HashMap<String, Vec<i64>>,
+}
+
+#[cfg(any(feature = "npz", feature = "hdf5"))]
+impl ArraySet {
+    fn into_measurements(mut self) -> Result<ImportedMeasurements> {
+        let mut builder = Builder::default();
+
+        let event_prefix = format!("{}/", EVENTS_GROUP);
+        let mut event_ids: Vec<String> = self
+            .floats
+            .keys()
+            .filter_map(|key| key.strip_prefix(&event_prefix))
+            .filter_map(|rest| rest.strip_suffix("/time"))
+            .map(str::to_string)
+            .collect();
+        event_ids.sort();
+
+        for id in event_ids {
+            let column = |field: &str| format!("{}{}/{}", event_prefix, id, field);
+            let times = self.floats.remove(&column("time")).unwrap_or_default();
+            let outcomes = self
+                .ints
+                .remove(&column("outcome"))
+                .ok_or_else(|| missing_column(&column("outcome")))?;
+            let counts = self
+                .ints
+                .remove(&column("count"))
+                .ok_or_else(|| missing_column(&column("count")))?;
+
+            if outcomes.len() != times.len() || counts.len() != times.len() {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{} entries in {}", times.len(), column("time")),
+                    format!("{} outcomes, {} counts", outcomes.len(), counts.len()),
+                ));
+            }
+
+            for ((time, outcome), count) in times.into_iter().zip(outcomes).zip(counts) {
+                builder.events.push(MeasurementEvent {
+                    time,
+                    measurement_id: id.clone(),
+                    outcome: to_usize(outcome, &column("outcome"))?,
+                    count: to_usize(count, &column("count"))?,
+                });
+            }
+        }
+
+        let mut observables: Vec<String> = self
+            .floats
+            .keys()
+            .filter_map(|key| key.strip_suffix("/value"))
+            .map(str::to_string)
+            .collect();
+        observables.sort();
+
+        for name in observables {
+            let values = self.floats.remove(&format!("{}/value", name)).unwrap_or_default();
+            let uncertainties = self
+                .floats
+                .remove(&format!("{}/uncertainty", name))
+                .ok_or_else(|| missing_column(&format!("{}/uncertainty", name)))?;
+
+            if values.len() != uncertainties.len() {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{} uncertainties for {}", values.len(), name),
+                    uncertainties.len().to_string(),
+                ));
+            }
+
+            for (value, uncertainty) in values.into_iter().zip(uncertainties) {
+                builder.push_observable(name.clone(), value, uncertainty);
+            }
+        }
+
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(any(feature = "npz", feature = "hdf5"))]
+fn missing_column(name: &str) -> EngineError {
+    EngineError::SerializationError(format!("Missing array: {}", name))
+}
+
+#[cfg(any(feature = "npz", feature = "hdf5"))]
+fn to_usize(value: i64, column: &str) -> Result<usize> {
+    usize::try_from(value).map_err(|_| {
+        EngineError::validation_error(format!("Negative value {} in {}", value, column))
+    })
+}
+
+#[derive(Default)]
+struct Builder {
+    observables: HashMap<String, (Vec<f64>, Vec<f64>)>,
+    num_shots: usize,
+    events: Vec<MeasurementEvent>,
+}
+
+impl Builder {
+    fn push_observable(&mut self, name: String, value: f64, uncertainty: f64) {
+        let entry = self.observables.entry(name).or_insert_with(|| (Vec::new(), Vec::new()));
+        entry.0.push(value);
+        entry.1.push(uncertainty);
+        self.num_shots += 1;
+    }
+
+    fn finish(self) -> ImportedMeasurements {
+        let num_shots = self.num_shots + self.events.iter().map(|e| e.count).sum::<usize>();
+        ImportedMeasurements {
+            data: MeasurementData {
+                observables: self.observables,
+                num_shots,
+                metadata: serde_json::json!({}),
+            },
+            events: self.events,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_temp(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("qte_{}_{}", std::process::id(), name));
+        File::create(&path).unwrap().write_all(contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_sniff_magic_bytes() {
+        assert_eq!(MeasurementFormat::sniff(b"PK\x03\x04rest"), MeasurementFormat::Npz);
+        assert_eq!(MeasurementFormat::sniff(HDF5_MAGIC), MeasurementFormat::Hdf5);
+        assert_eq!(MeasurementFormat::sniff(b"  {\"time\""), MeasurementFormat::JsonLines);
+        assert_eq!(MeasurementFormat::sniff(b"sx,0.1,0.01"), MeasurementFormat::Csv);
+    }
+
+    #[test]
+    fn test_json_lines_mixed_records() {
+        let path = write_temp(
+            "mixed.jsonl",
+            br#"{"observable": "sx", "value": 0.5, "uncertainty": 0.01}
+{"time": 0.1, "measurement_id": "z", "outcome": 1, "count": 40}
+
+{"observable": "sx", "value": 0.4, "uncertainty": 0.01}
+"#,
+        );
+
+        let imported = import_measurements(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(imported.data.observables["sx"].0, vec![0.5, 0.4]);
+        assert_eq!(imported.events.len(), 1);
+        assert_eq!(imported.events[0].count, 40);
+        assert_eq!(imported.data.num_shots, 42);
+        assert_eq!(imported.data.metadata["format"], "JsonLines");
+    }
+
+    #[test]
+    fn test_json_lines_reports_line() {
+        let path = write_temp("bad.jsonl", b"{\"observable\": \"sx\", \"value\": 1.0, \"uncertainty\": 0.1}\n{\"value\": 2}\n");
+
+        let err = load_json_lines(&path).unwrap_err();
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(err, EngineError::ParseError { line: 2, .. }));
+    }
+
+    #[test]
+    fn test_non_finite_policies() {
+        let imported = || {
+            let mut builder = Builder::default();
+            builder.push_observable("sx".to_string(), 0.5, 0.01);
+            builder.push_observable("sx".to_string(), f64::NAN, 0.01);
+            builder.push_observable("sx".to_string(), 0.4, f64::INFINITY);
+            builder.finish()
+        };
+
+        let err = imported().handle_non_finite(NonFinitePolicy::Error).unwrap_err();
+        assert!(err.to_string().contains("NaN value for observable 'sx' at index 1"));
+
+        let mut skipped = imported();
+        assert_eq!(skipped.handle_non_finite(NonFinitePolicy::Skip).unwrap(), 2);
+        assert_eq!(skipped.data.observables["sx"].0, vec![0.5]);
+        assert_eq!(skipped.data.num_shots, 1);
+
+        let mut clamped = imported();
+        assert_eq!(clamped.handle_non_finite(NonFinitePolicy::Clamp).unwrap(), 2);
+        assert_eq!(clamped.data.observables["sx"].0, vec![0.5, 0.4]);
+        assert_eq!(clamped.data.observables["sx"].1[1], f64::MAX);
+
+        assert_eq!("skip".parse::<NonFinitePolicy>().unwrap(), NonFinitePolicy::Skip);
+    }
+
+    #[test]
+    #[cfg(any(feature = "npz", feature = "hdf5"))]
+    fn test_array_set_events_and_observables() {
+        let mut arrays = ArraySet::default();
+        arrays.floats.insert("events/z/time".to_string(), vec![0.0, 1.0]);
+        arrays.ints.insert("events/z/outcome".to_string(), vec![0, 1]);
+        arrays.ints.insert("events/z/count".to_string(), vec![70, 30]);
+        arrays.floats.insert("sx/value".to_string(), vec![0.2]);
+        arrays.floats.insert("sx/uncertainty".to_string(), vec![0.05]);
+
+        let imported = arrays.into_measurements().unwrap();
+
+        assert_eq!(imported.events.len(), 2);
+        assert_eq!(imported.events[1].outcome, 1);
+        assert_eq!(imported.data.observables["sx"].1, vec![0.05]);
+    }
+}
@@ -1,19 +1,193 @@
 //! ODE integrators for quantum evolution
 
+use crate::cancellation::{self, CancellationToken};
 use crate::error::{EngineError, Result};
-use ndarray::Array2;
-use num_complex::Complex64;
+use crate::kernels_cpu;
+use crate::precision::{self, Precision, Scalar};
+use crate::progress::{self, StepProgress};
+use crate::propagator::PropagatorCache;
+use ndarray::{Array2, Zip};
+use num_complex::{Complex, Complex64};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// ODE integration result
+#[derive(Debug)]
 pub struct OdeResult {
     pub times: Vec<f64>,
     pub states: Vec<Array2<Complex64>>,
+    pub stats: IntegratorStats,
+}
+
+/// Step counters reported by an integrator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegratorStats {
+    pub accepted_steps: usize,
+    pub rejected_steps: usize,
+    pub rhs_evaluations: usize,
+}
+
+/// Integrator used for Lindblad evolution
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum IntegratorKind {
+    /// Classic RK4, one step per interval of the time grid
+    #[default]
+    Rk4,
+    /// Dormand-Prince 5(4) with error control and dense output
+    DormandPrince(AdaptiveConfig),
+}
+
+/// Tolerances and step limits for [`AdaptiveIntegrator`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveConfig {
+    pub abs_tol: f64,
+    pub rel_tol: f64,
+    /// First trial step; estimated from the initial derivative if `None`
+    pub initial_step: Option<f64>,
+    pub min_step: f64,
+    pub max_step: Option<f64>,
+    pub max_steps: usize,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        AdaptiveConfig {
+            abs_tol: 1e-8,
+            rel_tol: 1e-6,
+            initial_step: None,
+            min_step: 1e-12,
+            max_step: None,
+            max_steps: 1_000_000,
+        }
+    }
+}
+
+/// Physicality checks applied to ρ(t) while evolving
+///
+/// A violation aborts the evolution with a
+/// [`QuantumConstraintError`](EngineError::QuantumConstraintError) naming the
+/// time, the size of the violation and the most likely cause.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrictMode {
+    /// Check every `interval`-th state of the time grid (1 = every state)
+    pub interval: usize,
+    /// Tolerance on |ρ - ρ†|, |Tr ρ - 1| and on negative eigenvalues
+    pub tolerance: f64,
+}
+
+impl Default for StrictMode {
+    fn default() -> Self {
+        StrictMode {
+            interval: 1,
+            tolerance: 1e-8,
+        }
+    }
+}
+
+impl StrictMode {
+    /// Whether the state at grid index `index` is due for a check
+    pub fn is_due(&self, index: usize) -> bool {
+        index.is_multiple_of(self.interval.max(1))
+    }
+
+    /// Check that `rho` is Hermitian, trace-one and PSD
+    ///
+    /// `dt` is the step that produced the state and `generator_norm` a bound
+    /// on the norm of the generator (see [`generator_norm`]); together they
+    /// tell an under-resolved step apart from an unphysical model.
+    pub fn check(
+        &self,
+        rho: &Array2<Complex64>,
+        t: f64,
+        dt: f64,
+        generator_norm: f64,
+    ) -> Result<()> {
+        use ndarray_linalg::{EigValsh, UPLO};
+
+        let hermiticity = Zip::from(rho)
+            .and(&rho.t())
+            .fold(0.0_f64, |acc, a, b| acc.max((a - b.conj()).norm()));
+        if hermiticity > self.tolerance {
+            return Err(self.violation("not Hermitian", "max |ρ - ρ†|", hermiticity, t, dt, generator_norm));
+        }
+
+        let trace_error = (rho.diag().sum() - Complex64::new(1.0, 0.0)).norm();
+        if trace_error > self.tolerance {
+            return Err(self.violation("not trace-one", "|Tr ρ - 1|", trace_error, t, dt, generator_norm));
+        }
+
+        let hermitian_part = (rho + &rho.t().mapv(|x| x.conj())).mapv(|x| x * 0.5);
+        let eigenvalues = hermitian_part
+            .eigvalsh(UPLO::Lower)
+            .map_err(|e| EngineError::IntegrationError(format!("Eigenvalue computation failed: {}", e)))?;
+        let min_eigenvalue = eigenvalues.iter().cloned().fold(f64::INFINITY, f64::min);
+        if min_eigenvalue < -self.tolerance {
+            return Err(self.violation(
+                "not positive semi-definite",
+                "minimum eigenvalue",
+                min_eigenvalue,
+                t,
+                dt,
+                generator_norm,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn violation(
+        &self,
+        property: &str,
+        measure: &str,
+        value: f64,
+        t: f64,
+        dt: f64,
+        generator_norm: f64,
+    ) -> EngineError {
+        let stiffness = dt * generator_norm;
+        let cause = if stiffness > 1.0 {
+            format!(
+                "step dt = {:.3e} is too large for the generator norm {:.3e} (dt·‖L‖ = {:.2}); \
+                 refine the time grid or use the adaptive Dormand-Prince integrator",
+                dt, generator_norm, stiffness
+            )
+        } else {
+            format!(
+                "the step is well resolved (dt·‖L‖ = {:.2e}); check for a non-Hermitian \
+                 Hamiltonian, negative rates or an unnormalised initial state",
+                stiffness
+            )
+        };
+
+        EngineError::quantum_error(format!(
+            "ρ(t = {}) is {}: {} = {:.3e} (tolerance {:.1e}); suspected cause: {}",
+            t, property, measure, value, self.tolerance, cause
+        ))
+    }
+}
+
+/// Upper bound on the norm of the Lindblad generator, used to judge whether
+/// a time step resolves the dynamics
+pub fn generator_norm(
+    hamiltonian: &Array2<Complex64>,
+    lindblad_ops: &[(Array2<Complex64>, f64)],
+) -> f64 {
+    let frobenius = |m: &Array2<Complex64>| m.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+    2.0 * frobenius(hamiltonian)
+        + lindblad_ops
+            .iter()
+            .map(|(l, gamma)| 2.0 * gamma.abs() * frobenius(l).powi(2))
+            .sum::<f64>()
 }
 
 /// RK4 (4th order Runge-Kutta) integrator for Lindblad master equation
 pub struct Rk4Integrator {
     hamiltonian: Array2<Complex64>,
     lindblad_ops: Vec<(Array2<Complex64>, f64)>, // (L, gamma)
+    strict: Option<StrictMode>,
+    precision: Precision,
+    cancellation: Option<CancellationToken>,
+    progress: Option<StepProgress>,
 }
 
 impl Rk4Integrator {
@@ -24,126 +198,780 @@ impl Rk4Integrator {
         Rk4Integrator {
             hamiltonian,
             lindblad_ops,
+            strict: None,
+            precision: Precision::Double,
+            cancellation: None,
+            progress: None,
         }
     }
 
+    /// Abort integration when the state stops being physical
+    pub fn with_strict(mut self, strict: Option<StrictMode>) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Stop with [`EngineError::Cancelled`] at the first time step after
+    /// `token` is cancelled
+    pub fn with_cancellation(mut self, token: Option<CancellationToken>) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Count each time step of the grid in `progress`
+    pub fn with_progress(mut self, progress: Option<StepProgress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Floating-point type to step in; the returned states are `Complex64`
+    /// either way
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
     /// Integrate from initial density matrix over time grid
     pub fn integrate(
         &self,
         initial_rho: Array2<Complex64>,
         times: &[f64],
+    ) -> Result<OdeResult> {
+        match self.precision {
+            Precision::Double => self.integrate_in::<f64>(initial_rho, times),
+            Precision::Single => self.integrate_in::<f32>(initial_rho, times),
+        }
+    }
+
+    fn integrate_in<T: Scalar>(
+        &self,
+        initial_rho: Array2<Complex64>,
+        times: &[f64],
     ) -> Result<OdeResult> {
         if times.is_empty() {
             return Err(EngineError::validation_error("Empty time grid"));
         }
 
+        kernels_cpu::check_finite(&initial_rho, "rho", format_args!("at t={}", times[0]))?;
+        let hamiltonian = precision::narrow::<T>(&self.hamiltonian);
+        let lindblad_ops: Vec<_> = self
+            .lindblad_ops
+            .iter()
+            .map(|(l, gamma)| (precision::narrow::<T>(l), T::from_f64(*gamma)))
+            .collect();
         let mut states = Vec::with_capacity(times.len());
-        let mut rho = initial_rho;
-        states.push(rho.clone());
+        let mut rho = precision::narrow::<T>(&initial_rho);
+        states.push(initial_rho);
+        let norm = generator_norm(&self.hamiltonian, &self.lindblad_ops);
 
         for i in 1..times.len() {
             let dt = times[i] - times[i - 1];
             if dt <= 0.0 {
                 return Err(EngineError::validation_error("Time grid must be increasing"));
             }
+            cancellation::check(self.cancellation.as_ref())?;
 
-            rho = self.step_rk4(&rho, dt)?;
-            states.push(rho.clone());
+            rho = step_rk4(&hamiltonian, &lindblad_ops, &rho, T::from_f64(dt))?;
+            let state = precision::widen(&rho);
+            kernels_cpu::check_finite(&state, "rho", format_args!("at t={}", times[i]))?;
+            if let Some(strict) = self.strict.as_ref().filter(|s| s.is_due(i)) {
+                strict.check(&state, times[i], dt, norm)?;
+            }
+            states.push(state);
+            progress::step(self.progress.as_ref());
         }
 
+        let steps = times.len() - 1;
         Ok(OdeResult {
             times: times.to_vec(),
             states,
+            stats: IntegratorStats {
+                accepted_steps: steps,
+                rejected_steps: 0,
+                rhs_evaluations: 4 * steps,
+            },
         })
     }
+}
 
-    /// Single RK4 step
-    fn step_rk4(&self, rho: &Array2<Complex64>, dt: f64) -> Result<Array2<Complex64>> {
-        let k1 = self.lindblad_derivative(rho)?;
-        let rho2 = rho + &k1 * Complex64::new(dt / 2.0, 0.0);
+/// Single RK4 step
+fn step_rk4<T: Scalar>(
+    hamiltonian: &Array2<Complex<T>>,
+    lindblad_ops: &[(Array2<Complex<T>>, T)],
+    rho: &Array2<Complex<T>>,
+    dt: T,
+) -> Result<Array2<Complex<T>>> {
+    let half = Complex::from(dt / T::from_f64(2.0));
+    let k1 = lindblad_rhs(hamiltonian, lindblad_ops, rho)?;
+    let mut rho2 = rho.clone();
+    rho2.scaled_add(half, &k1);
 
-        let k2 = self.lindblad_derivative(&rho2)?;
-        let rho3 = rho + &k2 * Complex64::new(dt / 2.0, 0.0);
+    let k2 = lindblad_rhs(hamiltonian, lindblad_ops, &rho2)?;
+    let mut rho3 = rho.clone();
+    rho3.scaled_add(half, &k2);
 
-        let k3 = self.lindblad_derivative(&rho3)?;
-        let rho4 = rho + &k3 * Complex64::new(dt, 0.0);
+    let k3 = lindblad_rhs(hamiltonian, lindblad_ops, &rho3)?;
+    let mut rho4 = rho.clone();
+    rho4.scaled_add(Complex::from(dt), &k3);
 
-        let k4 = self.lindblad_derivative(&rho4)?;
+    let k4 = lindblad_rhs(hamiltonian, lindblad_ops, &rho4)?;
 
-        // rho_new = rho + (dt/6) * (k1 + 2*k2 + 2*k3 + k4)
-        let result = rho
-            + &((&k1 + &(&k2 * Complex64::new(2.0, 0.0)) + &(&k3 * Complex64::new(2.0, 0.0)) + &k4)
-                * Complex64::new(dt / 6.0, 0.0));
+    // rho_new = rho + (dt/6) * (k1 + 2*k2 + 2*k3 + k4)
+    let sixth = Complex::from(dt / T::from_f64(6.0));
+    let third = Complex::from(dt / T::from_f64(3.0));
+    let mut result = rho.clone();
+    result.scaled_add(sixth, &k1);
+    result.scaled_add(third, &k2);
+    result.scaled_add(third, &k3);
+    result.scaled_add(sixth, &k4);
+
+    Ok(result)
+}
 
-        Ok(result)
+/// Lindblad master equation: dρ/dt = -i[H,ρ] + Σ_k γ_k (L_k ρ L_k† - 1/2{L_k†L_k, ρ})
+fn lindblad_rhs<T: Scalar>(
+    hamiltonian: &Array2<Complex<T>>,
+    lindblad_ops: &[(Array2<Complex<T>>, T)],
+    rho: &Array2<Complex<T>>,
+) -> Result<Array2<Complex<T>>> {
+    // Unitary part: -i[H, ρ]
+    let minus_i = Complex::new(T::zero(), -T::one());
+    let commutator = hamiltonian.dot(rho) - rho.dot(hamiltonian);
+    let mut drho = commutator.mapv_into(|z| z * minus_i);
+
+    // Dissipative part
+    for (l_op, gamma) in lindblad_ops {
+        let l_dagger = l_op.t().mapv(|x| x.conj());
+
+        // L ρ L†
+        let l_rho_l_dag = l_op.dot(rho).dot(&l_dagger);
+
+        // L†L
+        let l_dag_l = l_dagger.dot(l_op);
+
+        // {L†L, ρ} = L†Lρ + ρL†L
+        let anticomm = l_dag_l.dot(rho) + rho.dot(&l_dag_l);
+
+        // γ(LρL† - 1/2{L†L,ρ})
+        drho.scaled_add(Complex::from(*gamma), &l_rho_l_dag);
+        drho.scaled_add(Complex::from(-*gamma / T::from_f64(2.0)), &anticomm);
     }
 
-    /// Lindblad master equation: dρ/dt = -i[H,ρ] + Σ_k γ_k (L_k ρ L_k† - 1/2{L_k†L_k, ρ})
-    fn lindblad_derivative(&self, rho: &Array2<Complex64>) -> Result<Array2<Complex64>> {
-        let n = rho.nrows();
-        let mut drho = Array2::zeros((n, n));
+    Ok(drho)
+}
+
+/// Largest ‖𝓛‖ dt of a step of [`integrate_sensitivities`]
+const SENSITIVITY_STEP: f64 = 0.1;
 
-        // Unitary part: -i[H, ρ]
-        let h_rho = self.hamiltonian.dot(rho);
-        let rho_h = rho.dot(&self.hamiltonian);
-        let commutator = h_rho - rho_h;
-        drho = drho + commutator * Complex64::new(0.0, -1.0);
+/// Derivative of a Lindblad generator with respect to one parameter: ∂H/∂θ
+/// and ∂L_k/∂θ for each jump operator, in the generator's order; rates do
+/// not depend on parameters
+#[derive(Debug, Clone)]
+pub struct GeneratorDerivative {
+    pub hamiltonian: Array2<Complex64>,
+    pub lindblad_ops: Vec<Array2<Complex64>>,
+}
 
-        // Dissipative part
-        for (l_op, gamma) in &self.lindblad_ops {
-            let l_dagger = l_op.t().mapv(|x| x.conj());
+impl GeneratorDerivative {
+    /// (∂𝓛/∂θ)(ρ) = -i[∂H, ρ] + Σ_k γ_k (∂L ρ L† + L ρ ∂L† - ½{∂L†L + L†∂L, ρ})
+    fn apply(
+        &self,
+        lindblad_ops: &[(Array2<Complex64>, f64)],
+        rho: &Array2<Complex64>,
+    ) -> Array2<Complex64> {
+        let minus_i = Complex64::new(0.0, -1.0);
+        let commutator = self.hamiltonian.dot(rho) - rho.dot(&self.hamiltonian);
+        let mut drho = commutator.mapv_into(|z| z * minus_i);
+        for ((l, gamma), dl) in lindblad_ops.iter().zip(&self.lindblad_ops) {
+            let l_dagger = l.t().mapv(|x| x.conj());
+            let dl_dagger = dl.t().mapv(|x| x.conj());
+            let jump = dl.dot(rho).dot(&l_dagger) + l.dot(rho).dot(&dl_dagger);
+            let decay = dl_dagger.dot(l) + l_dagger.dot(dl);
+            let anticomm = decay.dot(rho) + rho.dot(&decay);
+            drho.scaled_add(Complex64::new(*gamma, 0.0), &jump);
+            drho.scaled_add(Complex64::new(-gamma / 2.0, 0.0), &anticomm);
+        }
+        drho
+    }
+}
 
-            // L ρ L†
-            let l_rho = l_op.dot(rho);
-            let l_rho_l_dag = l_rho.dot(&l_dagger);
+/// States and their derivatives with respect to parameters on a time grid
+pub struct SensitivityResult {
+    pub times: Vec<f64>,
+    pub states: Vec<Array2<Complex64>>,
+    /// `sensitivities[k][i]` is ∂ρ(tᵢ)/∂θ_k
+    pub sensitivities: Vec<Vec<Array2<Complex64>>>,
+}
 
-            // L†L
-            let l_dag_l = l_dagger.dot(l_op);
+/// Integrate ρ together with its sensitivities S_k = ∂ρ/∂θ_k by RK4
+///
+/// The forward sensitivity equations dS_k/dt = 𝓛(S_k) + (∂𝓛/∂θ_k)(ρ) are
+/// solved alongside the master equation from S_k(0) = 0, the initial state
+/// being taken as independent of the parameters. One run gives the
+/// derivatives with respect to every parameter, where finite differences
+/// would evolve the state twice per parameter. Each interval of the grid is
+/// split into steps with ‖𝓛‖ dt at most 0.1.
+pub fn integrate_sensitivities(
+    hamiltonian: &Array2<Complex64>,
+    lindblad_ops: &[(Array2<Complex64>, f64)],
+    derivatives: &[GeneratorDerivative],
+    initial_rho: Array2<Complex64>,
+    times: &[f64],
+) -> Result<SensitivityResult> {
+    if times.is_empty() {
+        return Err(EngineError::validation_error("Empty time grid"));
+    }
+    kernels_cpu::check_finite(&initial_rho, "rho", format_args!("at t={}", times[0]))?;
 
-            // {L†L, ρ} = L†Lρ + ρL†L
-            let anticomm = l_dag_l.dot(rho) + rho.dot(&l_dag_l);
+    // ρ followed by the S_k
+    let rhs = |y: &[Array2<Complex64>]| -> Result<Vec<Array2<Complex64>>> {
+        let mut dy = Vec::with_capacity(y.len());
+        dy.push(lindblad_rhs(hamiltonian, lindblad_ops, &y[0])?);
+        for (s, derivative) in y[1..].iter().zip(derivatives) {
+            let ds = lindblad_rhs(hamiltonian, lindblad_ops, s)? + derivative.apply(lindblad_ops, &y[0]);
+            dy.push(ds);
+        }
+        Ok(dy)
+    };
+    let advance = |y: &[Array2<Complex64>], k: &[Array2<Complex64>], h: f64| {
+        y.iter()
+            .zip(k)
+            .map(|(a, b)| {
+                let mut next = a.clone();
+                next.scaled_add(Complex64::new(h, 0.0), b);
+                next
+            })
+            .collect::<Vec<_>>()
+    };
 
-            // γ(LρL† - 1/2{L†L,ρ})
-            let dissipator = (l_rho_l_dag - anticomm * Complex64::new(0.5, 0.0))
-                * Complex64::new(*gamma, 0.0);
+    let zero = Array2::<Complex64>::zeros(initial_rho.raw_dim());
+    let mut y = vec![initial_rho];
+    y.extend(derivatives.iter().map(|_| zero.clone()));
+    let mut states = vec![y[0].clone()];
+    let mut sensitivities: Vec<Vec<_>> = y[1..].iter().map(|s| vec![s.clone()]).collect();
+    let norm = generator_norm(hamiltonian, lindblad_ops);
 
-            drho = drho + dissipator;
+    for i in 1..times.len() {
+        let dt = times[i] - times[i - 1];
+        if dt <= 0.0 {
+            return Err(EngineError::validation_error("Time grid must be increasing"));
+        }
+        let steps = (norm * dt / SENSITIVITY_STEP).ceil().max(1.0) as usize;
+        let h = dt / steps as f64;
+        for _ in 0..steps {
+            let k1 = rhs(&y)?;
+            let k2 = rhs(&advance(&y, &k1, h / 2.0))?;
+            let k3 = rhs(&advance(&y, &k2, h / 2.0))?;
+            let k4 = rhs(&advance(&y, &k3, h))?;
+            y = advance(&y, &k1, h / 6.0);
+            y = advance(&y, &k2, h / 3.0);
+            y = advance(&y, &k3, h / 3.0);
+            y = advance(&y, &k4, h / 6.0);
+        }
+        kernels_cpu::check_finite(&y[0], "rho", format_args!("at t={}", times[i]))?;
+        states.push(y[0].clone());
+        for (series, s) in sensitivities.iter_mut().zip(&y[1..]) {
+            series.push(s.clone());
         }
+    }
+
+    Ok(SensitivityResult {
+        times: times.to_vec(),
+        states,
+        sensitivities,
+    })
+}
+
+// Dormand-Prince 5(4) tableau (Hairer, Nørsett & Wanner, Table II.5.2).
+// The right-hand side is autonomous, so the nodes c_i are not needed
+const A21: f64 = 1.0 / 5.0;
+const A31: f64 = 3.0 / 40.0;
+const A32: f64 = 9.0 / 40.0;
+const A41: f64 = 44.0 / 45.0;
+const A42: f64 = -56.0 / 15.0;
+const A43: f64 = 32.0 / 9.0;
+const A51: f64 = 19372.0 / 6561.0;
+const A52: f64 = -25360.0 / 2187.0;
+const A53: f64 = 64448.0 / 6561.0;
+const A54: f64 = -212.0 / 729.0;
+const A61: f64 = 9017.0 / 3168.0;
+const A62: f64 = -355.0 / 33.0;
+const A63: f64 = 46732.0 / 5247.0;
+const A64: f64 = 49.0 / 176.0;
+const A65: f64 = -5103.0 / 18656.0;
+
+// 5th-order weights (also the last stage row, FSAL)
+const B1: f64 = 35.0 / 384.0;
+const B3: f64 = 500.0 / 1113.0;
+const B4: f64 = 125.0 / 192.0;
+const B5: f64 = -2187.0 / 6784.0;
+const B6: f64 = 11.0 / 84.0;
+
+// Difference between 5th- and embedded 4th-order weights
+const E1: f64 = 71.0 / 57600.0;
+const E3: f64 = -71.0 / 16695.0;
+const E4: f64 = 71.0 / 1920.0;
+const E5: f64 = -17253.0 / 339200.0;
+const E6: f64 = 22.0 / 525.0;
+const E7: f64 = -1.0 / 40.0;
+
+// Dense output coefficients (4th-order continuous extension)
+const D1: f64 = -12715105075.0 / 11282082432.0;
+const D3: f64 = 87487479700.0 / 32700410799.0;
+const D4: f64 = -10690763975.0 / 1880347072.0;
+const D5: f64 = 701980252875.0 / 199316789632.0;
+const D6: f64 = -1453857185.0 / 822651844.0;
+const D7: f64 = 69997945.0 / 29380423.0;
+
+/// Adaptive Dormand-Prince 5(4) integrator for the Lindblad master equation
+///
+/// Steps are chosen from the embedded 4th-order error estimate so that the
+/// local error stays within `abs_tol + rel_tol * |ρ|` (elementwise RMS).
+/// States on the requested grid come from the method's continuous extension,
+/// so the grid spacing does not constrain the internal step size.
+pub struct AdaptiveIntegrator {
+    hamiltonian: Array2<Complex64>,
+    lindblad_ops: Vec<(Array2<Complex64>, f64)>,
+    config: AdaptiveConfig,
+    strict: Option<StrictMode>,
+    cancellation: Option<CancellationToken>,
+    progress: Option<StepProgress>,
+}
+
+/// One accepted step, kept for dense output
+struct DenseStep {
+    t0: f64,
+    h: f64,
+    r1: Array2<Complex64>,
+    r2: Array2<Complex64>,
+    r3: Array2<Complex64>,
+    r4: Array2<Complex64>,
+    r5: Array2<Complex64>,
+}
 
-        Ok(drho)
+impl DenseStep {
+    /// Interpolated state at `t` in `[t0, t0 + h]`
+    fn eval(&self, t: f64) -> Array2<Complex64> {
+        let theta = (t - self.t0) / self.h;
+        let theta1 = 1.0 - theta;
+        let inner = &self.r4 + &(&self.r5 * Complex64::new(theta1, 0.0));
+        let inner = &self.r3 + &(inner * Complex64::new(theta, 0.0));
+        let inner = &self.r2 + &(inner * Complex64::new(theta1, 0.0));
+        &self.r1 + &(inner * Complex64::new(theta, 0.0))
+    }
+}
+
+impl AdaptiveIntegrator {
+    pub fn new(
+        hamiltonian: Array2<Complex64>,
+        lindblad_ops: Vec<(Array2<Complex64>, f64)>,
+        config: AdaptiveConfig,
+    ) -> Self {
+        AdaptiveIntegrator {
+            hamiltonian,
+            lindblad_ops,
+            config,
+            strict: None,
+            cancellation: None,
+            progress: None,
+        }
+    }
+
+    /// Abort integration when the state stops being physical
+    pub fn with_strict(mut self, strict: Option<StrictMode>) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Stop with [`EngineError::Cancelled`] at the first step after `token`
+    /// is cancelled
+    pub fn with_cancellation(mut self, token: Option<CancellationToken>) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Count each time of the output grid reached in `progress`
+    pub fn with_progress(mut self, progress: Option<StepProgress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Integrate from initial density matrix, reporting states on `times`
+    pub fn integrate(
+        &self,
+        initial_rho: Array2<Complex64>,
+        times: &[f64],
+    ) -> Result<OdeResult> {
+        if times.is_empty() {
+            return Err(EngineError::validation_error("Empty time grid"));
+        }
+        if times.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(EngineError::validation_error("Time grid must be increasing"));
+        }
+        if !(self.config.abs_tol > 0.0 && self.config.rel_tol >= 0.0) {
+            return Err(EngineError::validation_error(
+                "Adaptive integrator needs abs_tol > 0 and rel_tol >= 0",
+            ));
+        }
+
+        let t_end = times[times.len() - 1];
+        let norm = generator_norm(&self.hamiltonian, &self.lindblad_ops);
+        let max_step = self.config.max_step.unwrap_or(f64::INFINITY);
+        let mut stats = IntegratorStats::default();
+
+        kernels_cpu::check_finite(&initial_rho, "rho", format_args!("at t={}", times[0]))?;
+        let mut states = Vec::with_capacity(times.len());
+        states.push(initial_rho.clone());
+        let mut next_output = 1;
+
+        let mut t = times[0];
+        let mut rho = initial_rho;
+        let mut k1 = self.rhs(&rho, &mut stats)?;
+        let mut h = match self.config.initial_step {
+            Some(h0) => h0,
+            None => self.initial_step(&rho, &k1, t_end - t),
+        }
+        .min(max_step);
+
+        while next_output < times.len() {
+            cancellation::check(self.cancellation.as_ref())?;
+            if stats.accepted_steps + stats.rejected_steps >= self.config.max_steps {
+                return Err(EngineError::IntegrationError(format!(
+                    "Adaptive integrator exceeded {} steps at t = {}",
+                    self.config.max_steps, t
+                )));
+            }
+
+            // Land exactly on the final time
+            let last = t + h >= t_end;
+            if last {
+                h = t_end - t;
+            }
+
+            let (rho_new, k7, dense, err) = self.step(t, &rho, &k1, h, &mut stats)?;
+
+            if err <= 1.0 {
+                stats.accepted_steps += 1;
+                let t_new = if last { t_end } else { t + h };
+
+                while next_output < times.len() && times[next_output] <= t_new {
+                    let state = if times[next_output] == t_new {
+                        rho_new.clone()
+                    } else {
+                        dense.eval(times[next_output])
+                    };
+                    kernels_cpu::check_finite(&state, "rho", format_args!("at t={}", times[next_output]))?;
+                    if let Some(strict) = self.strict.as_ref().filter(|s| s.is_due(next_output)) {
+                        strict.check(&state, times[next_output], h, norm)?;
+                    }
+                    states.push(state);
+                    next_output += 1;
+                    progress::step(self.progress.as_ref());
+                }
+
+                t = t_new;
+                rho = rho_new;
+                k1 = k7;
+            } else {
+                stats.rejected_steps += 1;
+            }
+
+            // Standard controller: h ∝ err^(-1/5), bounded growth/shrink
+            let factor = if err == 0.0 {
+                5.0
+            } else {
+                (0.9 * err.powf(-0.2)).clamp(0.2, 5.0)
+            };
+            h = (h * factor).min(max_step);
+
+            if h < self.config.min_step && next_output < times.len() {
+                return Err(EngineError::IntegrationError(format!(
+                    "Step size fell below {} at t = {}",
+                    self.config.min_step, t
+                )));
+            }
+        }
+
+        Ok(OdeResult {
+            times: times.to_vec(),
+            states,
+            stats,
+        })
+    }
+
+    /// One Dormand-Prince step: new state, FSAL derivative, dense output
+    /// coefficients and the scaled error norm
+    fn step(
+        &self,
+        t: f64,
+        rho: &Array2<Complex64>,
+        k1: &Array2<Complex64>,
+        h: f64,
+        stats: &mut IntegratorStats,
+    ) -> Result<(Array2<Complex64>, Array2<Complex64>, DenseStep, f64)> {
+        let stage = |terms: &[(f64, &Array2<Complex64>)]| {
+            let mut y = rho.clone();
+            for (a, k) in terms {
+                y.scaled_add(Complex64::new(h * a, 0.0), *k);
+            }
+            y
+        };
+
+        let k2 = self.rhs(&stage(&[(A21, k1)]), stats)?;
+        let k3 = self.rhs(&stage(&[(A31, k1), (A32, &k2)]), stats)?;
+        let k4 = self.rhs(&stage(&[(A41, k1), (A42, &k2), (A43, &k3)]), stats)?;
+        let k5 = self.rhs(
+            &stage(&[(A51, k1), (A52, &k2), (A53, &k3), (A54, &k4)]),
+            stats,
+        )?;
+        let k6 = self.rhs(
+            &stage(&[(A61, k1), (A62, &k2), (A63, &k3), (A64, &k4), (A65, &k5)]),
+            stats,
+        )?;
+        let rho_new = stage(&[(B1, k1), (B3, &k3), (B4, &k4), (B5, &k5), (B6, &k6)]);
+        let k7 = self.rhs(&rho_new, stats)?;
+
+        let mut err_est = Array2::<Complex64>::zeros(rho.raw_dim());
+        for (e, k) in [(E1, k1), (E3, &k3), (E4, &k4), (E5, &k5), (E6, &k6), (E7, &k7)] {
+            err_est.scaled_add(Complex64::new(h * e, 0.0), k);
+        }
+        let err = self.error_norm(&err_est, rho, &rho_new);
+
+        let ydiff = &rho_new - rho;
+        let bspl = &(k1 * Complex64::new(h, 0.0)) - &ydiff;
+        let r4 = &(&ydiff - &(&k7 * Complex64::new(h, 0.0))) - &bspl;
+        let mut r5 = Array2::<Complex64>::zeros(rho.raw_dim());
+        for (d, k) in [(D1, k1), (D3, &k3), (D4, &k4), (D5, &k5), (D6, &k6), (D7, &k7)] {
+            r5.scaled_add(Complex64::new(h * d, 0.0), k);
+        }
+
+        let dense = DenseStep {
+            t0: t,
+            h,
+            r1: rho.clone(),
+            r2: ydiff,
+            r3: bspl,
+            r4,
+            r5,
+        };
+
+        Ok((rho_new, k7, dense, err))
+    }
+
+    /// RMS of the error scaled by `abs_tol + rel_tol * max(|y0|, |y1|)`
+    fn error_norm(
+        &self,
+        err: &Array2<Complex64>,
+        y0: &Array2<Complex64>,
+        y1: &Array2<Complex64>,
+    ) -> f64 {
+        let mut sum = 0.0;
+        Zip::from(err).and(y0).and(y1).for_each(|e, a, b| {
+            let scale = self.config.abs_tol + self.config.rel_tol * a.norm().max(b.norm());
+            sum += (e.norm() / scale).powi(2);
+        });
+        (sum / err.len() as f64).sqrt()
+    }
+
+    /// Heuristic first step from the size of ρ and dρ/dt
+    fn initial_step(&self, rho: &Array2<Complex64>, drho: &Array2<Complex64>, span: f64) -> f64 {
+        let d0 = rho.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+        let d1 = drho.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+        let h = if d0 < 1e-5 || d1 < 1e-5 {
+            1e-6
+        } else {
+            0.01 * d0 / d1
+        };
+        h.min(span).max(self.config.min_step)
+    }
+
+    fn rhs(
+        &self,
+        rho: &Array2<Complex64>,
+        stats: &mut IntegratorStats,
+    ) -> Result<Array2<Complex64>> {
+        stats.rhs_evaluations += 1;
+        lindblad_rhs(&self.hamiltonian, &self.lindblad_ops, rho)
     }
 }
 
 /// Unitary evolution for pure states: |ψ(t)⟩ = U(t)|ψ(0)⟩ where U(t) = exp(-iHt)
+///
+/// Consecutive steps of one size share their propagator.
 pub fn evolve_unitary(
     hamiltonian: &Array2<Complex64>,
     initial_ket: &ndarray::Array1<Complex64>,
     times: &[f64],
 ) -> Result<Vec<ndarray::Array1<Complex64>>> {
-    use crate::kernels_cpu::{apply_unitary_ket, matrix_exp};
+    let cache = PropagatorCache::new(0);
+    let mut propagators = cache.propagators(0, hamiltonian);
+    evolve_unitary_with(initial_ket, times, |dt| propagators.get(dt))
+}
+
+/// [`evolve_unitary`] with the propagator U(dt) of each step from
+/// `propagator`, e.g. a [`PropagatorCache`]
+pub fn evolve_unitary_with(
+    initial_ket: &ndarray::Array1<Complex64>,
+    times: &[f64],
+    mut propagator: impl FnMut(f64) -> Result<Arc<Array2<Complex64>>>,
+) -> Result<Vec<ndarray::Array1<Complex64>>> {
+    use crate::kernels_cpu::{apply_unitary_ket, check_finite_ket};
 
+    if let Some(&t0) = times.first() {
+        check_finite_ket(initial_ket, "psi", format_args!("at t={}", t0))?;
+    }
     let mut states = Vec::with_capacity(times.len());
     states.push(initial_ket.clone());
 
     for i in 1..times.len() {
-        let dt = times[i] - times[i - 1];
-
         // U(dt) = exp(-i H dt)
-        let h_dt = hamiltonian * Complex64::new(0.0, -dt);
-        let u = matrix_exp(&h_dt)?;
+        let u = propagator(times[i] - times[i - 1])?;
 
         let new_state = apply_unitary_ket(&u, &states[i - 1])?;
+        check_finite_ket(&new_state, "psi", format_args!("at t={}", times[i]))?;
         states.push(new_state);
     }
 
     Ok(states)
 }
 
+/// Unitary evolution for density matrices: ρ(t) = U(t) ρ(0) U(t)†
+pub fn evolve_unitary_rho(
+    hamiltonian: &Array2<Complex64>,
+    initial_rho: &Array2<Complex64>,
+    times: &[f64],
+) -> Result<Vec<Array2<Complex64>>> {
+    let cache = PropagatorCache::new(0);
+    let mut propagators = cache.propagators(0, hamiltonian);
+    evolve_unitary_rho_with(initial_rho, times, |dt| propagators.get(dt))
+}
+
+/// [`evolve_unitary_rho`] with the propagator of each step from `propagator`
+pub fn evolve_unitary_rho_with(
+    initial_rho: &Array2<Complex64>,
+    times: &[f64],
+    mut propagator: impl FnMut(f64) -> Result<Arc<Array2<Complex64>>>,
+) -> Result<Vec<Array2<Complex64>>> {
+    use crate::kernels_cpu::{apply_unitary_rho, check_finite};
+
+    if let Some(&t0) = times.first() {
+        check_finite(initial_rho, "rho", format_args!("at t={}", t0))?;
+    }
+    let mut states = Vec::with_capacity(times.len());
+    states.push(initial_rho.clone());
+
+    for i in 1..times.len() {
+        let u = propagator(times[i] - times[i - 1])?;
+
+        let new_state = apply_unitary_rho(&u, &states[i - 1])?;
+        check_finite(&new_state, "rho", format_args!("at t={}", times[i]))?;
+        states.push(new_state);
+    }
+
+    Ok(states)
+}
+
+/// Cubic Hermite interpolation of the trajectory `states` over the
+/// increasing grid `times`, at `t` between its ends
+///
+/// The slope at each grid point is the derivative of the parabola through
+/// it and its neighbours, one-sided at the ends, so the interpolant is
+/// continuously differentiable and exact for trajectories quadratic in
+/// time. As a real-weighted combination of states it keeps their
+/// Hermiticity and trace, but not necessarily their positivity.
+pub fn hermite_interpolate(times: &[f64], states: &[Array2<Complex64>], t: f64) -> Result<Array2<Complex64>> {
+    if times.len() != states.len() {
+        return Err(EngineError::dimension_mismatch(
+            format!("{} states", times.len()),
+            format!("{} states", states.len()),
+        ));
+    }
+    let (Some(&first), Some(&last)) = (times.first(), times.last()) else {
+        return Err(EngineError::validation_error("Cannot interpolate an empty trajectory"));
+    };
+    if !(first..=last).contains(&t) {
+        return Err(EngineError::validation_error(format!(
+            "Time {} is outside the trajectory, which spans [{}, {}]",
+            t, first, last
+        )));
+    }
+
+    // times[k] <= t < times[k + 1]
+    let k = times.partition_point(|&s| s <= t) - 1;
+    if times[k] == t || k + 1 == times.len() {
+        return Ok(states[k].clone());
+    }
+    let h = times[k + 1] - times[k];
+    let s = (t - times[k]) / h;
+    let c = |x: f64| Complex64::new(x, 0.0);
+    let h00 = (1.0 + 2.0 * s) * (1.0 - s) * (1.0 - s);
+    let h10 = s * (1.0 - s) * (1.0 - s);
+    let h01 = s * s * (3.0 - 2.0 * s);
+    let h11 = s * s * (s - 1.0);
+
+    Ok(&states[k] * c(h00)
+        + hermite_slope(times, states, k) * c(h * h10)
+        + &states[k + 1] * c(h01)
+        + hermite_slope(times, states, k + 1) * c(h * h11))
+}
+
+/// dρ/dt at grid point `k`, from the parabola through three neighbouring
+/// states, or the chord of a two-point trajectory
+fn hermite_slope(times: &[f64], states: &[Array2<Complex64>], k: usize) -> Array2<Complex64> {
+    let c = |x: f64| Complex64::new(x, 0.0);
+    let n = times.len();
+    if n == 2 {
+        return (&states[1] - &states[0]) * c(1.0 / (times[1] - times[0]));
+    }
+    let j = k.clamp(1, n - 2);
+    let (t0, t1, t2) = (times[j - 1], times[j], times[j + 1]);
+    let x = times[k];
+    let w0 = (2.0 * x - t1 - t2) / ((t0 - t1) * (t0 - t2));
+    let w1 = (2.0 * x - t0 - t2) / ((t1 - t0) * (t1 - t2));
+    let w2 = (2.0 * x - t0 - t1) / ((t2 - t0) * (t2 - t1));
+    &states[j - 1] * c(w0) + &states[j] * c(w1) + &states[j + 1] * c(w2)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use approx::assert_relative_eq;
 
+    #[test]
+    fn test_integrators_report_first_nan() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let h = Array2::from_shape_vec((2, 2), vec![c(0.0), c(f64::NAN), c(f64::NAN), c(0.0)]).unwrap();
+        let rho0 = Array2::from_diag(&ndarray::Array1::from_vec(vec![c(1.0), c(0.0)]));
+        let times = [0.0, 0.5, 1.0];
+
+        let Err(err) = Rk4Integrator::new(h.clone(), vec![]).integrate(rho0, &times) else {
+            panic!("integrating through a NaN Hamiltonian succeeded");
+        };
+        assert!(matches!(err, EngineError::NonFinite(_)));
+        assert!(err.to_string().contains("NaN first appeared at t=0.5 in rho[0,0]"));
+
+        let ket = ndarray::Array1::from_vec(vec![c(1.0), c(0.0)]);
+        assert!(matches!(evolve_unitary(&h, &ket, &times), Err(EngineError::NonFinite(_))));
+    }
+
+    #[test]
+    fn test_integrators_stop_when_cancelled() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let h = Array2::from_diag(&ndarray::Array1::from_vec(vec![c(0.5), c(-0.5)]));
+        let rho0 = Array2::from_diag(&ndarray::Array1::from_vec(vec![c(1.0), c(0.0)]));
+        let times: Vec<f64> = (0..10).map(|i| i as f64 * 0.1).collect();
+        let token = CancellationToken::new();
+
+        let rk4 = Rk4Integrator::new(h.clone(), vec![]).with_cancellation(Some(token.clone()));
+        assert!(rk4.integrate(rho0.clone(), &times).is_ok());
+        token.cancel();
+        assert!(matches!(rk4.integrate(rho0.clone(), &times), Err(EngineError::Cancelled)));
+
+        let adaptive = AdaptiveIntegrator::new(h, vec![], AdaptiveConfig::default())
+            .with_cancellation(Some(token));
+        assert!(matches!(adaptive.integrate(rho0, &times), Err(EngineError::Cancelled)));
+    }
+
     #[test]
     fn test_rk4_constant_state() {
         // With H=0 and no Lindblad ops, state should remain constant
@@ -169,4 +997,186 @@ mod tests {
             epsilon = 1e-6
         );
     }
+
+    fn excited_state() -> Array2<Complex64> {
+        let mut rho = Array2::zeros((2, 2));
+        rho[[1, 1]] = Complex64::new(1.0, 0.0);
+        rho
+    }
+
+    #[test]
+    fn test_adaptive_amplitude_damping_matches_exact() {
+        // ρ_11(t) = exp(-γt) for L = σ-
+        let gamma = 0.7;
+        let mut sigma_minus = Array2::zeros((2, 2));
+        sigma_minus[[0, 1]] = Complex64::new(1.0, 0.0);
+
+        let integrator = AdaptiveIntegrator::new(
+            Array2::zeros((2, 2)),
+            vec![(sigma_minus, gamma)],
+            AdaptiveConfig::default(),
+        );
+        let times: Vec<f64> = (0..=40).map(|i| i as f64 * 0.25).collect();
+        let result = integrator.integrate(excited_state(), &times).unwrap();
+
+        assert_eq!(result.states.len(), times.len());
+        for (t, rho) in times.iter().zip(&result.states) {
+            assert_relative_eq!(rho[[1, 1]].re, (-gamma * t).exp(), epsilon = 1e-6);
+        }
+        assert!(result.stats.accepted_steps > 0);
+    }
+
+    #[test]
+    fn test_single_precision_rk4_tracks_double() {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let h = Array2::from_shape_vec((2, 2), vec![c(0.5), c(0.3), c(0.3), c(-0.5)]).unwrap();
+        let mut sigma_minus = Array2::zeros((2, 2));
+        sigma_minus[[0, 1]] = c(1.0);
+        let times: Vec<f64> = (0..=100).map(|i| i as f64 * 0.05).collect();
+
+        let integrate = |precision| {
+            Rk4Integrator::new(h.clone(), vec![(sigma_minus.clone(), 0.2)])
+                .with_precision(precision)
+                .integrate(excited_state(), &times)
+                .unwrap()
+        };
+        let single = integrate(Precision::Single);
+        let double = integrate(Precision::Double);
+
+        assert_eq!(single.states.len(), times.len());
+        let deviation = crate::precision::PrecisionCheck::default()
+            .verify(&single.states, &double.states, &times)
+            .unwrap();
+        assert!(deviation > 0.0 && deviation < 1e-5);
+    }
+
+    #[test]
+    fn test_adaptive_dense_output_rabi() {
+        // H = (Ω/2) σx from |1⟩: ρ_11(t) = cos²(Ωt/2). A coarse tolerance
+        // forces steps much longer than the output spacing.
+        let omega = 3.0;
+        let mut h = Array2::zeros((2, 2));
+        h[[0, 1]] = Complex64::new(omega / 2.0, 0.0);
+        h[[1, 0]] = Complex64::new(omega / 2.0, 0.0);
+
+        let config = AdaptiveConfig {
+            abs_tol: 1e-7,
+            rel_tol: 1e-7,
+            ..AdaptiveConfig::default()
+        };
+        let integrator = AdaptiveIntegrator::new(h, Vec::new(), config);
+        let times: Vec<f64> = (0..=500).map(|i| i as f64 * 0.01).collect();
+        let result = integrator.integrate(excited_state(), &times).unwrap();
+
+        assert!(result.stats.accepted_steps < times.len() - 1);
+        for (t, rho) in times.iter().zip(&result.states) {
+            let expected = (omega * t / 2.0).cos().powi(2);
+            assert_relative_eq!(rho[[1, 1]].re, expected, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_rejects_bad_grid() {
+        let integrator =
+            AdaptiveIntegrator::new(Array2::zeros((2, 2)), Vec::new(), AdaptiveConfig::default());
+        assert!(integrator.integrate(excited_state(), &[0.0, 1.0, 0.5]).is_err());
+        assert!(integrator.integrate(excited_state(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_sensitivities_match_finite_differences() {
+        // Damped Rabi oscillation, H = (Ω/2) σx with decay |1⟩ → |0⟩:
+        // ∂ρ/∂Ω against central differences of two runs at Ω ± δ
+        let sigma_x = |scale: f64| {
+            let mut m = Array2::zeros((2, 2));
+            m[[0, 1]] = Complex64::new(scale, 0.0);
+            m[[1, 0]] = Complex64::new(scale, 0.0);
+            m
+        };
+        let mut lowering = Array2::zeros((2, 2));
+        lowering[[0, 1]] = Complex64::new(1.0, 0.0);
+        let decay = vec![(lowering.clone(), 0.2)];
+        let (omega, delta) = (2.0, 1e-4);
+        let times: Vec<f64> = (0..=20).map(|i| i as f64 * 0.1).collect();
+
+        let derivative = GeneratorDerivative {
+            hamiltonian: sigma_x(0.5),
+            lindblad_ops: vec![Array2::zeros((2, 2))],
+        };
+        let result =
+            integrate_sensitivities(&sigma_x(omega / 2.0), &decay, &[derivative], excited_state(), &times).unwrap();
+        // On a grid 20 times finer, so that step errors do not swamp δ
+        let fine: Vec<f64> = (0..=400).map(|i| i as f64 * 0.005).collect();
+        let run = |omega: f64| {
+            let states = Rk4Integrator::new(sigma_x(omega / 2.0), decay.clone())
+                .integrate(excited_state(), &fine)
+                .unwrap()
+                .states;
+            states.into_iter().step_by(20).collect::<Vec<_>>()
+        };
+        let (plus, minus) = (run(omega + delta), run(omega - delta));
+
+        for i in 0..times.len() {
+            let expected = (&plus[i] - &minus[i]).mapv(|z| z / (2.0 * delta));
+            for (s, e) in result.sensitivities[0][i].iter().zip(expected.iter()) {
+                assert_relative_eq!(s.re, e.re, epsilon = 1e-5);
+                assert_relative_eq!(s.im, e.im, epsilon = 1e-5);
+            }
+        }
+        // Without decay ρ_11 = cos²(Ωt/2), so ∂ρ_11/∂Ω = -(t/2) sin(Ωt)
+        let derivative = GeneratorDerivative {
+            hamiltonian: sigma_x(0.5),
+            lindblad_ops: vec![],
+        };
+        let result = integrate_sensitivities(&sigma_x(omega / 2.0), &[], &[derivative], excited_state(), &times).unwrap();
+        for (t, s) in times.iter().zip(&result.sensitivities[0]) {
+            assert_relative_eq!(s[[1, 1]].re, -(t / 2.0) * (omega * t).sin(), epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_strict_mode_flags_coarse_steps() {
+        // γ·dt = 6 is far outside RK4's stability region
+        let mut sigma_minus = Array2::zeros((2, 2));
+        sigma_minus[[0, 1]] = Complex64::new(1.0, 0.0);
+        let integrator = Rk4Integrator::new(Array2::zeros((2, 2)), vec![(sigma_minus, 3.0)])
+            .with_strict(Some(StrictMode::default()));
+
+        let err = integrator
+            .integrate(excited_state(), &[0.0, 2.0, 4.0])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("t = 2"), "{}", err);
+        assert!(err.contains("too large"), "{}", err);
+    }
+
+    #[test]
+    fn test_strict_mode_accepts_resolved_evolution() {
+        let mut sigma_minus = Array2::zeros((2, 2));
+        sigma_minus[[0, 1]] = Complex64::new(1.0, 0.0);
+        let integrator = Rk4Integrator::new(Array2::zeros((2, 2)), vec![(sigma_minus, 0.5)])
+            .with_strict(Some(StrictMode { interval: 10, tolerance: 1e-8 }));
+
+        let times: Vec<f64> = (0..=200).map(|i| i as f64 * 0.01).collect();
+        assert!(integrator.integrate(excited_state(), &times).is_ok());
+    }
+
+    #[test]
+    fn test_hermite_interpolation_is_exact_for_quadratics() {
+        // ρ(t) = A + B t + C t² on an uneven grid
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let a = Array2::from_shape_vec((2, 2), vec![c(1.0, 0.0), c(0.0, 0.5), c(0.0, -0.5), c(0.0, 0.0)]).unwrap();
+        let b = Array2::from_shape_vec((2, 2), vec![c(-1.0, 0.0), c(0.3, 0.0), c(0.3, 0.0), c(1.0, 0.0)]).unwrap();
+        let q = Array2::from_shape_vec((2, 2), vec![c(0.2, 0.0), c(0.0, 0.1), c(0.0, -0.1), c(-0.2, 0.0)]).unwrap();
+        let rho = |t: f64| &a + &(&b * c(t, 0.0)) + &q * c(t * t, 0.0);
+
+        let times = [0.0, 0.1, 0.4, 0.5, 1.0];
+        let states: Vec<_> = times.iter().map(|&t| rho(t)).collect();
+        for t in [0.0, 0.05, 0.27, 0.4, 0.93, 1.0] {
+            let interpolated = hermite_interpolate(&times, &states, t).unwrap();
+            assert!((&interpolated - &rho(t)).iter().all(|d| d.norm() < 1e-12), "t = {}", t);
+        }
+        assert!(hermite_interpolate(&times, &states, 1.5).is_err());
+        assert!(hermite_interpolate(&times[..2], &states, 0.5).is_err());
+    }
 }
@@ -23,7 +23,8 @@ impl Optimizer {
         // Pass 1: Constant folding
         optimized = self.constant_folding(optimized)?;
 
-        // Pass 2: Common subexpression elimination (TODO)
+        // Pass 2: Common subexpression elimination happens in lowering, where
+        // structurally equal expressions are mapped to a single IR node
         // Pass 3: Algebraic simplifications (TODO)
 
         Ok(optimized)
@@ -43,13 +44,13 @@ impl Optimizer {
     }
 
     /// Simplify an expression
-    fn simplify_expr(&self, expr: &Expr) -> Expr {
+    pub fn simplify_expr(&self, expr: &Expr) -> Expr {
         match expr {
             // 0 * x = 0
             Expr::Mul(left, right) => {
-                if matches!(**left, Expr::Number(x) if x == 0.0) {
-                    Expr::Number(0.0)
-                } else if matches!(**right, Expr::Number(x) if x == 0.0) {
+                if matches!(**left, Expr::Number(x) if x == 0.0)
+                    || matches!(**right, Expr::Number(x) if x == 0.0)
+                {
                     Expr::Number(0.0)
                 } else {
                     expr.clone()
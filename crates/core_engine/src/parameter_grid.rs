@@ -0,0 +1,479 @@
+//! Parameter grids for sweeps
+//!
+//! A [`ParameterGrid`] names the parameters a sweep varies, the range of
+//! each, and how points are drawn from them: the full Cartesian grid,
+//! seeded random or Sobol samples, or points in a fit's confidence
+//! ellipsoid. Both the job queue's sweep jobs and the DSL's `sweep` blocks
+//! ([`crate::sweep`]) describe their points with it.
+
+use crate::error::{EngineError, Result};
+use crate::stats::FitResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterGrid {
+    pub params: Vec<ParameterRange>,
+    pub strategy: GridStrategy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterRange {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+    pub steps: usize,
+    pub scale: ParameterScale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ParameterScale {
+    Linear,
+    Log,
+    Custom(Vec<f64>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GridStrategy {
+    FullGrid,     // Cartesian product
+    Random { n: usize, seed: Option<u64> }, // Random sampling, repeatable with a seed
+    Sobol(usize),  // Quasi-random Sobol sequence
+    Ellipsoid(ConfidenceEllipsoid), // Points in a fit's confidence region
+}
+
+/// Confidence region `(x - c)ᵀ Σ⁻¹ (x - c) ≤ n_sigma²` around a fit
+///
+/// `center` and `covariance` are ordered like [`ParameterGrid::params`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceEllipsoid {
+    pub center: Vec<f64>,
+    pub covariance: Vec<Vec<f64>>,
+    pub n_sigma: f64,
+    pub sampling: EllipsoidSampling,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EllipsoidSampling {
+    /// `n` points drawn uniformly from the interior
+    Uniform(usize),
+    /// `steps` points along each principal axis from -n_sigma to +n_sigma;
+    /// the center is included once
+    PrincipalAxes(usize),
+}
+
+impl ParameterGrid {
+    /// The parameter values at each point of the grid, drawn by its
+    /// strategy
+    pub fn points(&self) -> Result<Vec<HashMap<String, f64>>> {
+        generate_parameter_combinations(self)
+    }
+
+    /// Grid over the `n_sigma` confidence ellipsoid of `fit`
+    ///
+    /// `names` label the fitted parameters in order. The ranges are set to
+    /// the ellipsoid's bounding box for reference; the points themselves
+    /// follow the covariance, so correlated parameters are swept along
+    /// their joint uncertainty instead of a rectangular grid.
+    pub fn around_fit(
+        names: &[String],
+        fit: &FitResult,
+        n_sigma: f64,
+        sampling: EllipsoidSampling,
+    ) -> Result<Self> {
+        if names.len() != fit.best_params.len() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{} parameter names", fit.best_params.len()),
+                format!("{}", names.len()),
+            ));
+        }
+        if !(n_sigma.is_finite() && n_sigma > 0.0) {
+            return Err(EngineError::validation_error(format!(
+                "n_sigma must be positive, got {}",
+                n_sigma
+            )));
+        }
+
+        let covariance = fit.covariance()?;
+        let params = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let half_width = n_sigma * covariance[i][i].sqrt();
+                ParameterRange {
+                    name: name.clone(),
+                    start: fit.best_params[i] - half_width,
+                    end: fit.best_params[i] + half_width,
+                    steps: 1,
+                    scale: ParameterScale::Linear,
+                }
+            })
+            .collect();
+
+        Ok(ParameterGrid {
+            params,
+            strategy: GridStrategy::Ellipsoid(ConfidenceEllipsoid {
+                center: fit.best_params.clone(),
+                covariance,
+                n_sigma,
+                sampling,
+            }),
+        })
+    }
+}
+
+fn generate_parameter_combinations(grid: &ParameterGrid) -> Result<Vec<HashMap<String, f64>>> {
+    Ok(match grid.strategy {
+        GridStrategy::FullGrid => generate_full_grid(&grid.params),
+        GridStrategy::Random { n, seed } => generate_random_grid(&grid.params, n, seed),
+        GridStrategy::Sobol(n) => generate_sobol_grid(&grid.params, n)?,
+        GridStrategy::Ellipsoid(ref ellipsoid) => generate_ellipsoid_grid(&grid.params, ellipsoid),
+    })
+}
+
+fn generate_full_grid(params: &[ParameterRange]) -> Vec<HashMap<String, f64>> {
+    if params.is_empty() {
+        return vec![HashMap::new()];
+    }
+
+    let first = &params[0];
+    let rest = &params[1..];
+    
+    let values = generate_param_values(first);
+    let rest_combinations = generate_full_grid(rest);
+    
+    let mut result = Vec::new();
+    for value in values {
+        for combo in &rest_combinations {
+            let mut new_combo = combo.clone();
+            new_combo.insert(first.name.clone(), value);
+            result.push(new_combo);
+        }
+    }
+    
+    result
+}
+
+impl ParameterRange {
+    /// The values this range takes, in order
+    pub fn values(&self) -> Vec<f64> {
+        generate_param_values(self)
+    }
+}
+
+fn generate_param_values(param: &ParameterRange) -> Vec<f64> {
+    match &param.scale {
+        ParameterScale::Linear => {
+            (0..param.steps)
+                .map(|i| {
+                    let t = i as f64 / (param.steps - 1).max(1) as f64;
+                    param.start + t * (param.end - param.start)
+                })
+                .collect()
+        }
+        ParameterScale::Log => {
+            let log_start = param.start.ln();
+            let log_end = param.end.ln();
+            (0..param.steps)
+                .map(|i| {
+                    let t = i as f64 / (param.steps - 1).max(1) as f64;
+                    (log_start + t * (log_end - log_start)).exp()
+                })
+                .collect()
+        }
+        ParameterScale::Custom(values) => values.clone(),
+    }
+}
+
+fn generate_random_grid(params: &[ParameterRange], n: usize, seed: Option<u64>) -> Vec<HashMap<String, f64>> {
+    use rand::Rng;
+    let mut rng = crate::rng::seeded(seed);
+    
+    (0..n).map(|_| {
+        params.iter().map(|param| {
+            let value = match &param.scale {
+                ParameterScale::Linear => {
+                    rng.gen_range(param.start..=param.end)
+                }
+                ParameterScale::Log => {
+                    let log_start = param.start.ln();
+                    let log_end = param.end.ln();
+                    rng.gen_range(log_start..=log_end).exp()
+                }
+                ParameterScale::Custom(values) => {
+                    values[rng.gen_range(0..values.len())]
+                }
+            };
+            (param.name.clone(), value)
+        }).collect()
+    }).collect()
+}
+
+/// The first `n` points of a Sobol sequence, one dimension per parameter,
+/// mapped onto each range (uniformly in `ln` for log scales)
+fn generate_sobol_grid(
+    params: &[ParameterRange],
+    n: usize,
+) -> Result<Vec<HashMap<String, f64>>> {
+    if params.is_empty() {
+        return Ok(vec![HashMap::new(); n]);
+    }
+    let sequence = crate::sobol::SobolSequence::new(params.len())?;
+
+    Ok(sequence
+        .take(n)
+        .map(|u| {
+            params
+                .iter()
+                .zip(u)
+                .map(|(param, u)| {
+                    let value = match &param.scale {
+                        ParameterScale::Linear => param.start + u * (param.end - param.start),
+                        ParameterScale::Log => {
+                            let log_start = param.start.ln();
+                            let log_end = param.end.ln();
+                            (log_start + u * (log_end - log_start)).exp()
+                        }
+                        ParameterScale::Custom(values) => {
+                            values[((u * values.len() as f64) as usize).min(values.len() - 1)]
+                        }
+                    };
+                    (param.name.clone(), value)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn generate_ellipsoid_grid(
+    params: &[ParameterRange],
+    ellipsoid: &ConfidenceEllipsoid,
+) -> Vec<HashMap<String, f64>> {
+    use crate::noise::NoiseDistribution;
+    use ndarray::Array2;
+    use ndarray_linalg::{Eigh, UPLO};
+
+    let k = params.len();
+    if ellipsoid.center.len() != k || ellipsoid.covariance.len() != k {
+        return vec![];
+    }
+
+    let cov = Array2::from_shape_fn((k, k), |(i, j)| ellipsoid.covariance[i][j]);
+    let (eigenvalues, eigenvectors) = match cov.eigh(UPLO::Lower) {
+        Ok(decomposition) => decomposition,
+        Err(_) => return vec![],
+    };
+
+    // Semi-axis i: n_sigma * sqrt(λ_i) along eigenvector i
+    let axes: Vec<Vec<f64>> = (0..k)
+        .map(|i| {
+            let length = ellipsoid.n_sigma * eigenvalues[i].max(0.0).sqrt();
+            eigenvectors.column(i).iter().map(|v| v * length).collect()
+        })
+        .collect();
+
+    // center + Σ_i coords[i] * axis_i, for coords in the unit ball
+    let point = |coords: &[f64]| -> HashMap<String, f64> {
+        params
+            .iter()
+            .enumerate()
+            .map(|(d, param)| {
+                let offset: f64 = coords.iter().zip(&axes).map(|(c, axis)| c * axis[d]).sum();
+                (param.name.clone(), ellipsoid.center[d] + offset)
+            })
+            .collect()
+    };
+
+    match ellipsoid.sampling {
+        EllipsoidSampling::Uniform(n) => {
+            let mut rng = crate::rng::seeded(None);
+            let normal = NoiseDistribution::Gaussian { std_dev: 1.0 };
+
+            (0..n)
+                .map(|_| {
+                    // Uniform direction, radius ~ u^(1/k) for uniform volume density
+                    let mut z: Vec<f64> = (0..k).map(|_| normal.sample(&mut rng)).collect();
+                    let norm = z.iter().map(|x| x * x).sum::<f64>().sqrt().max(f64::EPSILON);
+                    let radius = rand::Rng::gen::<f64>(&mut rng).powf(1.0 / k as f64);
+                    z.iter_mut().for_each(|x| *x *= radius / norm);
+                    point(&z)
+                })
+                .collect()
+        }
+        EllipsoidSampling::PrincipalAxes(steps) => {
+            let mut points = vec![point(&vec![0.0; k])];
+            for axis in 0..k {
+                for step in 0..steps {
+                    let t = -1.0 + 2.0 * step as f64 / (steps - 1).max(1) as f64;
+                    if t.abs() < 1e-12 {
+                        continue;
+                    }
+                    let mut coords = vec![0.0; k];
+                    coords[axis] = t;
+                    points.push(point(&coords));
+                }
+            }
+            points
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_grid() {
+        let grid = ParameterGrid {
+            params: vec![
+                ParameterRange {
+                    name: "x".to_string(),
+                    start: 0.0,
+                    end: 1.0,
+                    steps: 3,
+                    scale: ParameterScale::Linear,
+                },
+                ParameterRange {
+                    name: "y".to_string(),
+                    start: 1.0,
+                    end: 10.0,
+                    steps: 2,
+                    scale: ParameterScale::Log,
+                },
+            ],
+            strategy: GridStrategy::FullGrid,
+        };
+
+        let combos = generate_parameter_combinations(&grid).unwrap();
+        assert_eq!(combos.len(), 6); // 3 × 2
+    }
+
+    #[test]
+    fn test_seeded_random_grid_is_reproducible() {
+        let grid = |seed| ParameterGrid {
+            params: vec![ParameterRange {
+                name: "x".to_string(),
+                start: 0.0,
+                end: 1.0,
+                steps: 0,
+                scale: ParameterScale::Linear,
+            }],
+            strategy: GridStrategy::Random { n: 16, seed },
+        };
+
+        let points = generate_parameter_combinations(&grid(Some(5))).unwrap();
+        assert_eq!(points.len(), 16);
+        assert!(points.iter().all(|p| (0.0..=1.0).contains(&p["x"])));
+        assert_eq!(points, generate_parameter_combinations(&grid(Some(5))).unwrap());
+        assert_ne!(points, generate_parameter_combinations(&grid(Some(6))).unwrap());
+    }
+
+    #[test]
+    fn test_sobol_grid_covers_ranges() {
+        let grid = ParameterGrid {
+            params: vec![
+                ParameterRange {
+                    name: "x".to_string(),
+                    start: -1.0,
+                    end: 1.0,
+                    steps: 0,
+                    scale: ParameterScale::Linear,
+                },
+                ParameterRange {
+                    name: "y".to_string(),
+                    start: 1.0,
+                    end: 1000.0,
+                    steps: 0,
+                    scale: ParameterScale::Log,
+                },
+            ],
+            strategy: GridStrategy::Sobol(32),
+        };
+
+        let points = generate_parameter_combinations(&grid).unwrap();
+        assert_eq!(points.len(), 32);
+
+        // Each of 32 equal slices of x, and of log10(y), holds one point
+        let mut x_slices: Vec<usize> = points
+            .iter()
+            .map(|p| ((p["x"] + 1.0) * 16.0) as usize)
+            .collect();
+        let mut y_slices: Vec<usize> = points
+            .iter()
+            .map(|p| (p["y"].log10() / 3.0 * 32.0 + 1e-9) as usize)
+            .collect();
+        x_slices.sort_unstable();
+        y_slices.sort_unstable();
+        assert_eq!(x_slices, (0..32).collect::<Vec<_>>());
+        assert_eq!(y_slices, (0..32).collect::<Vec<_>>());
+
+        let too_many = ParameterGrid {
+            params: (0..=crate::sobol::MAX_DIMENSIONS)
+                .map(|i| ParameterRange {
+                    name: format!("p{}", i),
+                    start: 0.0,
+                    end: 1.0,
+                    steps: 0,
+                    scale: ParameterScale::Linear,
+                })
+                .collect(),
+            strategy: GridStrategy::Sobol(4),
+        };
+        assert!(generate_parameter_combinations(&too_many).is_err());
+    }
+
+    fn correlated_fit() -> FitResult {
+        // Fisher information of a fit with strongly correlated parameters
+        FitResult {
+            best_params: vec![1.0, 2.0],
+            uncertainties: vec![0.1, 0.1],
+            log_likelihood: -3.0,
+            fisher_info: vec![vec![200.0, -180.0], vec![-180.0, 200.0]],
+            converged: true,
+            iterations: 10,
+            truncated: false,
+        }
+    }
+
+    fn mahalanobis_sq(point: &HashMap<String, f64>, fit: &FitResult) -> f64 {
+        let d = [point["a"] - fit.best_params[0], point["b"] - fit.best_params[1]];
+        let f = &fit.fisher_info;
+        d[0] * (f[0][0] * d[0] + f[0][1] * d[1]) + d[1] * (f[1][0] * d[0] + f[1][1] * d[1])
+    }
+
+    #[test]
+    fn test_ellipsoid_uniform_points_inside() {
+        let fit = correlated_fit();
+        let names = vec!["a".to_string(), "b".to_string()];
+        let grid =
+            ParameterGrid::around_fit(&names, &fit, 2.0, EllipsoidSampling::Uniform(200)).unwrap();
+
+        let points = generate_parameter_combinations(&grid).unwrap();
+        assert_eq!(points.len(), 200);
+        for p in &points {
+            assert!(mahalanobis_sq(p, &fit) <= 4.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_ellipsoid_principal_axes_on_boundary() {
+        let fit = correlated_fit();
+        let names = vec!["a".to_string(), "b".to_string()];
+        let grid = ParameterGrid::around_fit(&names, &fit, 2.0, EllipsoidSampling::PrincipalAxes(3))
+            .unwrap();
+
+        // Center plus the two ends of each axis
+        let points = generate_parameter_combinations(&grid).unwrap();
+        assert_eq!(points.len(), 5);
+        assert!(mahalanobis_sq(&points[0], &fit) < 1e-12);
+        for p in &points[1..] {
+            assert!((mahalanobis_sq(p, &fit) - 4.0).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_around_fit_rejects_name_mismatch() {
+        let fit = correlated_fit();
+        let names = vec!["a".to_string()];
+        assert!(ParameterGrid::around_fit(&names, &fit, 2.0, EllipsoidSampling::Uniform(1)).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 //! Parser for the quantum DSL using Pest PEG parser
 
 use crate::ast::*;
+use crate::builtins::{self, BuiltinKind};
 use crate::error::{EngineError, Result};
 use num_complex::Complex64;
 use pest::Parser;
@@ -11,23 +12,53 @@ use pest_derive::Parser;
 struct DslParser;
 
 /// Parse DSL source text into an AST
+///
+/// Imports are resolved relative to the importing file, so programs with
+/// `import` statements are read with [`crate::imports::parse_file`].
 pub fn parse_dsl(source: &str) -> Result<Ast> {
-    let pairs = DslParser::parse(Rule::program, source).map_err(|e| {
-        let (line, col) = match e.line_col {
-            pest::error::LineColLocation::Pos((l, c)) => (l, c),
-            pest::error::LineColLocation::Span((l, c), _) => (l, c),
-        };
-        EngineError::parse_error(line, col, format!("Parse error: {}", e))
-    })?;
+    let (imports, ast) = parse_module(source)?;
+    if let Some(import) = imports.first() {
+        return Err(EngineError::parse_error(
+            import.span.line,
+            import.span.column,
+            format!(
+                "Cannot import \"{}\" from source without a file; parse the program from its file",
+                import.path
+            ),
+        ));
+    }
+    Ok(ast)
+}
 
+/// `import "path" [as alias];` statement, before it is resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub path: String,
+    pub alias: Option<String>,
+    pub span: Span,
+}
+
+/// Parse DSL source text into its imports, unresolved, and the AST of its
+/// own statements
+pub fn parse_module(source: &str) -> Result<(Vec<Import>, Ast)> {
+    let pairs = DslParser::parse(Rule::program, source).map_err(pest_error)?;
+
+    let mut imports = Vec::new();
     let mut statements = Vec::new();
+    let mut source_map = SourceMap::default();
 
     for pair in pairs {
         match pair.as_rule() {
             Rule::program => {
                 for inner in pair.into_inner() {
-                    if inner.as_rule() == Rule::statement {
-                        statements.push(parse_statement(inner)?);
+                    match inner.as_rule() {
+                        Rule::import_stmt => imports.push(parse_import(inner)?),
+                        Rule::statement => {
+                            source_map.push_statement(span_of(&inner));
+                            record_expr_spans(&inner, &mut source_map);
+                            statements.push(parse_statement(inner)?);
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -36,7 +67,99 @@ pub fn parse_dsl(source: &str) -> Result<Ast> {
         }
     }
 
-    Ok(Ast::new(statements))
+    let mut ast = Ast::new(statements);
+    ast.source_map = source_map;
+    Ok((imports, ast))
+}
+
+fn parse_import(pair: pest::iterators::Pair<Rule>) -> Result<Import> {
+    let span = span_of(&pair);
+    let mut parts = pair.into_inner();
+    let path = parts.next().unwrap().as_str().trim_matches('"').to_string();
+    let alias = parts.next().map(declared_name).transpose()?;
+    Ok(Import { path, alias, span })
+}
+
+/// The name a declaration introduces, which only imports may qualify
+fn declared_name(pair: pest::iterators::Pair<Rule>) -> Result<String> {
+    let name = pair.as_str();
+    if name.contains("::") {
+        return Err(error_at(
+            &pair,
+            format!("Declared name '{}' cannot be qualified with '::'", name),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Parse an identity `lhs == rhs` into its two sides
+pub fn parse_identity(source: &str) -> Result<(Expr, Expr)> {
+    let identity = DslParser::parse(Rule::identity, source)
+        .map_err(pest_error)?
+        .next()
+        .unwrap();
+    let mut sides = identity.into_inner().filter(|p| p.as_rule() == Rule::expr);
+    let lhs = parse_expr(sides.next().unwrap())?;
+    let rhs = parse_expr(sides.next().unwrap())?;
+    Ok((lhs, rhs))
+}
+
+/// Translate an OpenQASM 2.0 or 3.0 circuit into a program of one
+/// experiment, its gates as `apply` statements and its measurements as one
+/// projective measurement; see [`crate::qasm`]
+pub fn import_qasm(source: &str) -> Result<Ast> {
+    Ok(crate::qasm::parse_qasm(source)?.to_ast())
+}
+
+/// Parse a single expression, such as `dagger(H) * H`
+pub fn parse_expression(source: &str) -> Result<Expr> {
+    let expression = DslParser::parse(Rule::expression, source)
+        .map_err(pest_error)?
+        .next()
+        .unwrap();
+    let expr = expression
+        .into_inner()
+        .find(|p| p.as_rule() == Rule::expr)
+        .unwrap();
+    parse_expr(expr)
+}
+
+fn span_of(pair: &pest::iterators::Pair<Rule>) -> Span {
+    let (line, column) = pair.as_span().start_pos().line_col();
+    Span { line, column }
+}
+
+fn error_at(pair: &pest::iterators::Pair<Rule>, message: impl Into<String>) -> EngineError {
+    let span = span_of(pair);
+    EngineError::parse_error(span.line, span.column, message)
+}
+
+/// Record where every expression inside `statement` starts, parsing each
+/// expression node again to get the `Expr` it is looked up by
+fn record_expr_spans(statement: &pest::iterators::Pair<Rule>, source_map: &mut SourceMap) {
+    for pair in statement.clone().into_inner().flatten() {
+        let expr = match pair.as_rule() {
+            Rule::expr | Rule::term | Rule::factor | Rule::primary => parse_expr(pair.clone()),
+            Rule::builtin_function => parse_builtin_function(pair.clone()),
+            Rule::func_call => parse_func_call(pair.clone()),
+            Rule::subsystem_index => parse_subsystem_index(pair.clone()),
+            Rule::identifier => Ok(Expr::Identifier(pair.as_str().to_string())),
+            Rule::matrix_literal => parse_matrix_literal(pair.clone()).map(Expr::Matrix),
+            Rule::vector_literal => parse_vector_literal(pair.clone()).map(Expr::Vector),
+            _ => continue,
+        };
+        if let Ok(expr) = expr {
+            source_map.record_expr(&expr, span_of(&pair));
+        }
+    }
+}
+
+fn pest_error(e: pest::error::Error<Rule>) -> EngineError {
+    let (line, col) = match e.line_col {
+        pest::error::LineColLocation::Pos((l, c)) => (l, c),
+        pest::error::LineColLocation::Span((l, c), _) => (l, c),
+    };
+    EngineError::parse_error(line, col, format!("Parse error: {}", e))
 }
 
 fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Result<Statement> {
@@ -45,23 +168,46 @@ fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Result<Statement> {
     match inner.as_rule() {
         Rule::const_decl => {
             let mut parts = inner.into_inner();
-            let name = parts.next().unwrap().as_str().to_string();
+            let name = declared_name(parts.next().unwrap())?;
             let value = parse_number(parts.next().unwrap())?;
             Ok(Statement::ConstDecl { name, value })
         }
         Rule::symbol_decl => {
-            let name = inner.into_inner().next().unwrap().as_str().to_string();
+            let name = declared_name(inner.into_inner().next().unwrap())?;
             Ok(Statement::SymbolDecl { name })
         }
+        Rule::param_decl => {
+            let mut parts = inner.into_inner();
+            let name = declared_name(parts.next().unwrap())?;
+            let range = match (parts.next(), parts.next()) {
+                (Some(lo), Some(hi)) => Some((parse_number(lo)?, parse_number(hi)?)),
+                _ => None,
+            };
+            Ok(Statement::ParamDecl { name, range })
+        }
         Rule::matrix_decl => {
             let mut parts = inner.into_inner();
-            let name = parts.next().unwrap().as_str().to_string();
+            let name = declared_name(parts.next().unwrap())?;
             let value = parse_matrix_literal(parts.next().unwrap())?;
             Ok(Statement::MatrixDecl { name, value })
         }
+        Rule::operator_decl => {
+            let declaration = inner.clone();
+            let mut parts = inner.into_inner();
+            let name = declared_name(parts.next().unwrap())?;
+            // The cutoff dimension is the builtin's first argument
+            let mut args = vec![Expr::Number(parse_number(parts.next().unwrap())?)];
+            let mut operator = parts.next().unwrap().into_inner();
+            let builtin = builtins::lookup(operator.next().unwrap().as_str()).expect("Fock operators are builtins");
+            for arg in operator {
+                args.push(Expr::Number(parse_number(arg)?));
+            }
+            let value = builtin.call(args).map_err(|e| error_at(&declaration, e.to_string()))?;
+            Ok(Statement::OperatorDecl { name, value })
+        }
         Rule::function_def => {
             let mut parts = inner.into_inner();
-            let name = parts.next().unwrap().as_str().to_string();
+            let name = declared_name(parts.next().unwrap())?;
             let params = if let Some(param_list) = parts.peek() {
                 if param_list.as_rule() == Rule::param_list {
                     parse_param_list(parts.next().unwrap())?
@@ -76,7 +222,7 @@ fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Result<Statement> {
         }
         Rule::hamiltonian_def => {
             let mut parts = inner.into_inner();
-            let name = parts.next().unwrap().as_str().to_string();
+            let name = declared_name(parts.next().unwrap())?;
             let params = if let Some(param_list) = parts.peek() {
                 if param_list.as_rule() == Rule::param_list {
                     parse_param_list(parts.next().unwrap())?
@@ -91,24 +237,58 @@ fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Result<Statement> {
         }
         Rule::measurement_def => {
             let mut parts = inner.into_inner();
-            let name = parts.next().unwrap().as_str().to_string();
+            let name = declared_name(parts.next().unwrap())?;
             let spec = parse_measurement_spec(parts.next().unwrap())?;
             Ok(Statement::MeasurementDef { name, spec })
         }
+        Rule::channel_def => {
+            let mut parts = inner.into_inner();
+            let name = declared_name(parts.next().unwrap())?;
+            let kraus = parts.map(parse_expr).collect::<Result<Vec<_>>>()?;
+            Ok(Statement::ChannelDef { name, kraus })
+        }
         Rule::experiment => {
             let mut parts = inner.into_inner();
-            let name = parts.next().unwrap().as_str().to_string();
+            let name = declared_name(parts.next().unwrap())?;
             let body = parse_experiment_body(parts.next().unwrap())?;
             Ok(Statement::Experiment { name, body })
         }
-        _ => Err(EngineError::parse_error(
-            0,
-            0,
+        Rule::sweep_block => {
+            let mut parts = inner.into_inner();
+            let param = parts.next().unwrap().as_str().to_string();
+            let values = parse_sweep_values(parts.next().unwrap())?;
+            let body = parts.map(parse_statement).collect::<Result<Vec<_>>>()?;
+            Ok(Statement::Sweep {
+                param,
+                values,
+                body,
+            })
+        }
+        Rule::assume_stmt => Ok(Statement::Assume(parse_proof_goal(inner.into_inner().next().unwrap())?)),
+        Rule::prove_stmt => Ok(Statement::Prove(parse_proof_goal(inner.into_inner().next().unwrap())?)),
+        _ => Err(error_at(
+            &inner,
             format!("Unexpected statement rule: {:?}", inner.as_rule()),
         )),
     }
 }
 
+fn parse_proof_goal(pair: pest::iterators::Pair<Rule>) -> Result<ProofGoal> {
+    let mut parts = pair.into_inner();
+    let first = parts.next().unwrap();
+    if first.as_rule() == Rule::property_goal {
+        let mut property = first.into_inner();
+        let kind = PropertyKind::from_name(property.next().unwrap().as_str())
+            .expect("the grammar only admits known properties");
+        let expr = parse_expr(property.next().unwrap())?;
+        Ok(ProofGoal::Property { kind, expr })
+    } else {
+        let lhs = parse_expr(first)?;
+        let rhs = parse_expr(parts.next().unwrap())?;
+        Ok(ProofGoal::Identity { lhs, rhs })
+    }
+}
+
 fn parse_expr(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
     match pair.as_rule() {
         Rule::expr => {
@@ -155,18 +335,25 @@ fn parse_factor(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
         return parse_primary(pair);
     }
 
-    let mut inner = pair.into_inner();
-    let base = parse_primary(inner.next().unwrap())?;
-
-    if let Some(pow_op) = inner.next() {
-        if pow_op.as_rule() == Rule::pow_op {
-            let exponent = parse_number(inner.next().unwrap())?;
-            Ok(Expr::Pow(Box::new(base), Box::new(Expr::Number(exponent))))
-        } else {
-            Ok(base)
+    let mut inner = pair.into_inner().peekable();
+    let negated = inner.next_if(|p| p.as_rule() == Rule::neg_op).is_some();
+    let mut factor = parse_primary(inner.next().unwrap())?;
+
+    while let Some(next) = inner.next() {
+        match next.as_rule() {
+            Rule::pow_op => {
+                let exponent = parse_number(inner.next().unwrap())?;
+                factor = Expr::Pow(Box::new(factor), Box::new(Expr::Number(exponent)));
+            }
+            Rule::placement => factor = parse_placement(factor, next)?,
+            _ => {}
         }
+    }
+    // -x is -1 * x
+    if negated {
+        Ok(Expr::Mul(Box::new(Expr::Number(-1.0)), Box::new(factor)))
     } else {
-        Ok(base)
+        Ok(factor)
     }
 }
 
@@ -175,60 +362,142 @@ fn parse_primary(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
 
     match inner.as_rule() {
         Rule::number => Ok(Expr::Number(parse_number(inner)?)),
+        Rule::imaginary => parse_imaginary(inner),
         Rule::identifier => Ok(Expr::Identifier(inner.as_str().to_string())),
-        Rule::matrix_literal => Ok(Expr::Matrix(parse_matrix_literal(inner)?)),
+        Rule::func_call => parse_func_call(inner),
+        Rule::subsystem_index => parse_subsystem_index(inner),
+        Rule::bracket_commutator => {
+            let mut operands = inner.into_inner();
+            let a = parse_expr(operands.next().unwrap())?;
+            let b = parse_expr(operands.next().unwrap())?;
+            Ok(Expr::Commutator(Box::new(a), Box::new(b)))
+        }
+        Rule::matrix_literal | Rule::nested_matrix_literal => {
+            Ok(Expr::Matrix(parse_matrix_literal(inner)?))
+        }
         Rule::vector_literal => Ok(Expr::Vector(parse_vector_literal(inner)?)),
         Rule::expr => parse_expr(inner),
         Rule::builtin_function => parse_builtin_function(inner),
-        _ => Err(EngineError::parse_error(
-            0,
-            0,
+        _ => Err(error_at(
+            &inner,
             format!("Unexpected primary rule: {:?}", inner.as_rule()),
         )),
     }
 }
 
 fn parse_builtin_function(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
-    let inner = pair.into_inner().next().unwrap();
-    let func_name = inner.as_str();
+    // The function name is a literal in the grammar, not a child pair
+    let func_name = pair.as_str().split('(').next().unwrap_or("").trim();
+    let location = span_of(&pair);
+    let builtin = builtins::lookup(func_name)
+        .filter(|builtin| builtin.kind == BuiltinKind::Function)
+        .ok_or_else(|| {
+            EngineError::parse_error(
+                location.line,
+                location.column,
+                format!("Unknown builtin function: {}", func_name),
+            )
+        })?;
 
-    let mut args = inner.into_inner();
+    let args = pair
+        .into_inner()
+        .map(|arg| match arg.as_rule() {
+            Rule::vector_literal => Ok(Expr::Vector(parse_vector_literal(arg)?)),
+            _ => parse_expr(arg),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    builtin
+        .call(args)
+        .map_err(|e| EngineError::parse_error(location.line, location.column, e.to_string()))
+}
 
-    match func_name {
-        "dagger" => Ok(Expr::Dagger(Box::new(parse_expr(args.next().unwrap())?))),
-        "trace" => Ok(Expr::Trace(Box::new(parse_expr(args.next().unwrap())?))),
-        "tensor" => {
-            let left = parse_expr(args.next().unwrap())?;
-            let right = parse_expr(args.next().unwrap())?;
-            Ok(Expr::Tensor(Box::new(left), Box::new(right)))
-        }
-        "commutator" => {
-            let left = parse_expr(args.next().unwrap())?;
-            let right = parse_expr(args.next().unwrap())?;
-            Ok(Expr::Commutator(Box::new(left), Box::new(right)))
-        }
-        "anticommutator" => {
-            let left = parse_expr(args.next().unwrap())?;
-            let right = parse_expr(args.next().unwrap())?;
-            Ok(Expr::AntiCommutator(Box::new(left), Box::new(right)))
+/// `operator on qubit k of n` or `operator on subsystem k of vec(d1, ...)`,
+/// as the `embed` call padding it with identities
+fn parse_placement(operator: Expr, pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+    let location = span_of(&pair);
+    let placement = pair.into_inner().next().unwrap();
+    let rule = placement.as_rule();
+    let mut parts = placement.into_inner();
+    let subsystem = Expr::Number(parse_number(parts.next().unwrap())?);
+    let of = parts.next().unwrap();
+    let dims = match rule {
+        Rule::qubit_placement => {
+            let n: usize = of
+                .as_str()
+                .parse()
+                .map_err(|e| error_at(&of, format!("Invalid qubit count: {}", e)))?;
+            VectorLiteral {
+                elements: vec![Expr::Number(2.0); n],
+            }
         }
-        "expm" => Ok(Expr::Expm(Box::new(parse_expr(args.next().unwrap())?))),
-        "sqrt" => Ok(Expr::Sqrt(Box::new(parse_expr(args.next().unwrap())?))),
-        "sin" => Ok(Expr::Sin(Box::new(parse_expr(args.next().unwrap())?))),
-        "cos" => Ok(Expr::Cos(Box::new(parse_expr(args.next().unwrap())?))),
-        "exp" => Ok(Expr::Exp(Box::new(parse_expr(args.next().unwrap())?))),
-        _ => Err(EngineError::parse_error(
-            0,
-            0,
-            format!("Unknown builtin function: {}", func_name),
-        )),
-    }
+        _ => parse_vector_literal(of)?,
+    };
+    builtins::lookup("embed")
+        .expect("embed is a builtin")
+        .call(vec![operator, subsystem, Expr::Vector(dims)])
+        .map_err(|e| EngineError::parse_error(location.line, location.column, e.to_string()))
+}
+
+/// `rho[k]`, the reduced operator `ptrace(rho, k)` of subsystem `k`
+fn parse_subsystem_index(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+    let mut parts = pair.into_inner();
+    let name = Expr::Identifier(parts.next().unwrap().as_str().to_string());
+    let subsystem = Expr::Number(parse_number(parts.next().unwrap())?);
+    Ok(Expr::FuncCall {
+        name: "ptrace".to_string(),
+        args: vec![name, subsystem],
+    })
+}
+
+/// Call of a user-defined function, or of a plugin kernel
+fn parse_func_call(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+    let mut parts = pair.into_inner();
+    let name = parts.next().unwrap().as_str().to_string();
+    let args = parts.map(parse_expr).collect::<Result<Vec<_>>>()?;
+    Ok(Expr::FuncCall { name, args })
+}
+
+fn parse_imaginary(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+    let coefficient = match pair.as_str().trim_end_matches('i') {
+        "" => 1.0,
+        "-" => -1.0,
+        digits => digits
+            .parse()
+            .map_err(|_| error_at(&pair, "Invalid imaginary number"))?,
+    };
+    Ok(Expr::ComplexNumber(Complex64::new(0.0, coefficient)))
 }
 
 fn parse_number(pair: pest::iterators::Pair<Rule>) -> Result<f64> {
     pair.as_str()
         .parse()
-        .map_err(|_| EngineError::parse_error(0, 0, "Invalid number"))
+        .map_err(|_| error_at(&pair, "Invalid number"))
+}
+
+fn parse_sweep_values(pair: pest::iterators::Pair<Rule>) -> Result<SweepValues> {
+    let inner = pair.into_inner().next().unwrap();
+    let rule = inner.as_rule();
+    let mut parts = inner.into_inner();
+    match rule {
+        Rule::linspace | Rule::geomspace => {
+            let start = parse_number(parts.next().unwrap())?;
+            let end = parse_number(parts.next().unwrap())?;
+            let steps_pair = parts.next().unwrap();
+            let steps = steps_pair
+                .as_str()
+                .parse()
+                .map_err(|_| error_at(&steps_pair, "Invalid number of sweep steps"))?;
+            Ok(if rule == Rule::linspace {
+                SweepValues::Linspace { start, end, steps }
+            } else {
+                SweepValues::Geomspace { start, end, steps }
+            })
+        }
+        Rule::sweep_list => Ok(SweepValues::List(
+            parts.map(parse_number).collect::<Result<Vec<_>>>()?,
+        )),
+        _ => unreachable!(),
+    }
 }
 
 fn parse_matrix_literal(pair: pest::iterators::Pair<Rule>) -> Result<MatrixLiteral> {
@@ -252,10 +521,7 @@ fn parse_vector_literal(pair: pest::iterators::Pair<Rule>) -> Result<VectorLiter
 }
 
 fn parse_param_list(pair: pest::iterators::Pair<Rule>) -> Result<Vec<String>> {
-    Ok(pair
-        .into_inner()
-        .map(|p| p.as_str().to_string())
-        .collect())
+    pair.into_inner().map(declared_name).collect()
 }
 
 fn parse_measurement_spec(pair: pest::iterators::Pair<Rule>) -> Result<MeasurementSpec> {
@@ -275,14 +541,39 @@ fn parse_measurement_spec(pair: pest::iterators::Pair<Rule>) -> Result<Measureme
             }
             Ok(MeasurementSpec::POVM { effects })
         }
+        Rule::basis_measurement => {
+            let mut parts = inner.into_inner();
+            let basis = match parts.next().unwrap().as_str() {
+                "computational" => MeasurementBasis::Computational,
+                "X" => MeasurementBasis::X,
+                "Y" => MeasurementBasis::Y,
+                "Bell" => MeasurementBasis::Bell,
+                _ => unreachable!(),
+            };
+            let dim = match parts.next() {
+                Some(n) => n
+                    .as_str()
+                    .parse()
+                    .map_err(|_| error_at(&n, "Invalid basis dimension"))?,
+                None => basis.default_dim(),
+            };
+            Ok(MeasurementSpec::Basis { basis, dim })
+        }
         _ => unreachable!(),
     }
 }
 
 fn parse_experiment_body(pair: pest::iterators::Pair<Rule>) -> Result<ExperimentBody> {
     let mut init = None;
-    let mut evolution = None;
+    let mut evolution: Option<EvolutionSpec> = None;
     let mut measurements = None;
+    let mut channels = Vec::new();
+    let mut observables = Vec::new();
+    let mut lindblad_ops = Vec::new();
+    let mut lindblad_pos = (0, 0);
+    let mut bloch_states = Vec::new();
+    let mut gates = Vec::new();
+    let mut gate_states: Vec<(String, (usize, usize))> = Vec::new();
 
     for stmt in pair.into_inner() {
         match stmt.as_rule() {
@@ -292,27 +583,138 @@ fn parse_experiment_body(pair: pest::iterators::Pair<Rule>) -> Result<Experiment
             Rule::evolution_statement => {
                 evolution = Some(parse_evolution_spec(stmt.into_inner().next().unwrap())?);
             }
+            Rule::lindblad_statement => {
+                lindblad_pos = stmt.as_span().start_pos().line_col();
+                for block in stmt.into_inner() {
+                    lindblad_ops.push(parse_lindblad_term(block)?);
+                }
+            }
             Rule::measurement_schedule_statement => {
-                measurements = Some(parse_measurement_schedule(
-                    stmt.into_inner().next().unwrap(),
-                )?);
+                measurements = Some(parse_measurement_schedule(stmt)?);
+            }
+            Rule::channel_schedule_statement => {
+                channels.extend(parse_channel_schedule(stmt)?);
+            }
+            Rule::observables_statement => {
+                for observable in stmt.into_inner() {
+                    if observable.as_rule() == Rule::bloch_observable {
+                        let pos = observable.as_span().start_pos().line_col();
+                        let state = observable.into_inner().next().unwrap().as_str().to_string();
+                        observables.extend(bloch_observables(&state));
+                        bloch_states.push((state, pos));
+                    } else {
+                        observables.push(parse_observable(observable)?);
+                    }
+                }
+            }
+            Rule::apply_statement => {
+                let pos = stmt.as_span().start_pos().line_col();
+                let (gate, state) = parse_apply_statement(stmt)?;
+                gates.push(gate);
+                gate_states.push((state, pos));
             }
             _ => {}
         }
     }
 
+    if !lindblad_ops.is_empty() {
+        match evolution.as_mut() {
+            Some(evolution) => evolution.lindblad_ops.extend(lindblad_ops),
+            None => {
+                return Err(EngineError::parse_error(
+                    lindblad_pos.0,
+                    lindblad_pos.1,
+                    "lindblad block requires an evolution statement",
+                ))
+            }
+        }
+    }
+
+    for (state, (line, col)) in bloch_states {
+        match &evolution {
+            Some(evolution) if evolution.state_name == state => {}
+            Some(evolution) => {
+                return Err(EngineError::parse_error(
+                    line,
+                    col,
+                    format!(
+                        "bloch({}) must name the evolved state '{}'",
+                        state, evolution.state_name
+                    ),
+                ))
+            }
+            None => {
+                return Err(EngineError::parse_error(
+                    line,
+                    col,
+                    "bloch() requires an evolution statement",
+                ))
+            }
+        }
+    }
+
+    // Gates act on the one state the experiment starts in, the evolved
+    // state if there is an evolution
+    let applied_state = evolution.as_ref().map(|evolution| evolution.state_name.clone());
+    if let Some((first, _)) = gate_states.first() {
+        let expected = applied_state.unwrap_or_else(|| first.clone());
+        if let Some((state, (line, col))) = gate_states.iter().find(|(state, _)| *state != expected) {
+            return Err(EngineError::parse_error(
+                *line,
+                *col,
+                format!("apply ... to {} must name the experiment's state '{}'", state, expected),
+            ));
+        }
+    }
+
     Ok(ExperimentBody {
         init,
         evolution,
         measurements,
+        channels,
+        observables,
+        gates,
     })
 }
 
+/// `apply <gate>[(angle)] to <state>[targets];`, and the state it names
+fn parse_apply_statement(pair: pest::iterators::Pair<Rule>) -> Result<(GateApplication, String)> {
+    let mut parts = pair.into_inner();
+    let mut gate_ref = parts.next().unwrap().into_inner();
+    let gate = gate_ref.next().unwrap().as_str().to_string();
+    let angle = gate_ref.next().map(parse_expr).transpose()?;
+    let state = parts.next().unwrap().as_str().to_string();
+    let targets = match parts.next() {
+        Some(targets) => targets
+            .into_inner()
+            .map(|qubit| {
+                qubit
+                    .as_str()
+                    .parse()
+                    .map_err(|e| error_at(&qubit, format!("Invalid qubit index: {}", e)))
+            })
+            .collect::<Result<Vec<usize>>>()?,
+        None => Vec::new(),
+    };
+    Ok((GateApplication { gate, angle, targets }, state))
+}
+
 fn parse_state_spec(pair: pest::iterators::Pair<Rule>) -> Result<StateSpec> {
     let inner = pair.into_inner().next().unwrap();
     match inner.as_rule() {
         Rule::vector_literal => Ok(StateSpec::Ket(parse_vector_literal(inner)?)),
         Rule::matrix_literal => Ok(StateSpec::Rho(parse_matrix_literal(inner)?)),
+        Rule::identifier => Ok(StateSpec::From(inner.as_str().to_string())),
+        Rule::eigenstate_spec => {
+            let mut parts = inner.into_inner();
+            let hamiltonian = parts.next().unwrap().as_str().to_string();
+            let level = parts.next().unwrap();
+            let level = level
+                .as_str()
+                .parse()
+                .map_err(|e| error_at(&level, format!("Invalid level: {}", e)))?;
+            Ok(StateSpec::Eigenstate { hamiltonian, level })
+        }
         _ => unreachable!(),
     }
 }
@@ -337,10 +739,10 @@ fn parse_evolution_spec(pair: pest::iterators::Pair<Rule>) -> Result<EvolutionSp
 }
 
 fn parse_timegrid(pair: pest::iterators::Pair<Rule>) -> Result<TimeGrid> {
-    let inner = pair.into_inner().next().unwrap();
-    let mut nums = inner.into_inner();
+    let regular = pair.as_str().starts_with("timegrid");
+    let mut nums = pair.into_inner();
 
-    if inner.as_str().starts_with("timegrid") {
+    if regular {
         let t0 = parse_number(nums.next().unwrap())?;
         let dt = parse_number(nums.next().unwrap())?;
         let n_steps = parse_number(nums.next().unwrap())? as usize;
@@ -375,6 +777,39 @@ fn parse_measurement_schedule(pair: pest::iterators::Pair<Rule>) -> Result<Measu
     Ok(MeasurementSchedule { events })
 }
 
+fn parse_channel_schedule(pair: pest::iterators::Pair<Rule>) -> Result<Vec<ChannelEvent>> {
+    pair.into_inner()
+        .map(|event| {
+            let mut parts = event.into_inner();
+            let time = parse_number(parts.next().unwrap())?;
+            let channel_name = parts.next().unwrap().as_str().to_string();
+            Ok(ChannelEvent { time, channel_name })
+        })
+        .collect()
+}
+
+/// `<expr>`, labelled with the expression as written, whitespace collapsed
+fn parse_observable(pair: pest::iterators::Pair<Rule>) -> Result<TrackedObservable> {
+    let expr = pair.into_inner().next().unwrap();
+    let label = expr.as_str().split_whitespace().collect::<Vec<_>>().join(" ");
+    Ok(TrackedObservable {
+        label,
+        expr: parse_expr(expr)?,
+    })
+}
+
+/// `bloch(state)`: the Pauli expectation values, labelled `bloch(state).x`
+/// and so on
+fn bloch_observables(state: &str) -> Vec<TrackedObservable> {
+    ["x", "y", "z"]
+        .iter()
+        .map(|axis| TrackedObservable {
+            label: format!("bloch({}).{}", state, axis),
+            expr: Expr::Identifier(format!("sigma_{}", axis)),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -386,6 +821,447 @@ mod tests {
         assert_eq!(ast.statements.len(), 1);
     }
 
+    #[test]
+    fn test_parse_param_decl() {
+        let ast = parse_dsl("param omega; param gamma in [0.0, 1.5];").unwrap();
+
+        assert!(matches!(
+            &ast.statements[0],
+            Statement::ParamDecl { name, range: None } if name == "omega"
+        ));
+        assert!(matches!(
+            &ast.statements[1],
+            Statement::ParamDecl { name, range: Some((lo, hi)) } if name == "gamma" && *lo == 0.0 && *hi == 1.5
+        ));
+    }
+
+    #[test]
+    fn test_parse_sweep() {
+        let ast = parse_dsl(
+            "sweep omega in linspace(0.5, 2.0, 20) {
+                sweep gamma in [0.0, 0.1] { const n = 1.0; }
+                Hamiltonian H = omega * [[1, 0], [0, -1]];
+            }
+            sweep kappa in geomspace(0.01, 1.0, 3) {}",
+        )
+        .unwrap();
+
+        let Statement::Sweep { param, values, body } = &ast.statements[0] else {
+            panic!("expected a sweep");
+        };
+        assert_eq!(param, "omega");
+        assert_eq!(
+            values,
+            &SweepValues::Linspace {
+                start: 0.5,
+                end: 2.0,
+                steps: 20
+            }
+        );
+        assert!(matches!(
+            &body[0],
+            Statement::Sweep { param, values: SweepValues::List(v), .. } if param == "gamma" && v == &[0.0, 0.1]
+        ));
+        assert!(matches!(&body[1], Statement::HamiltonianDef { .. }));
+        assert!(matches!(
+            &ast.statements[1],
+            Statement::Sweep { values: SweepValues::Geomspace { steps: 3, .. }, body, .. } if body.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_parse_ptrace() {
+        let ast = parse_dsl("Hamiltonian HA = ptrace(H, 1, vec(2, 3));").unwrap();
+        let Statement::HamiltonianDef { expr, .. } = &ast.statements[0] else {
+            panic!("expected a Hamiltonian");
+        };
+        let Expr::FuncCall { name, args } = expr.as_ref() else {
+            panic!("expected a call, got {:?}", expr);
+        };
+        assert_eq!(name, "ptrace");
+        assert_eq!(args[1], Expr::Number(1.0));
+        assert!(matches!(&args[2], Expr::Vector(dims) if dims.elements.len() == 2));
+
+        assert!(parse_dsl("Hamiltonian HA = ptrace(H, 0);").is_ok());
+        assert!(parse_dsl("Hamiltonian HA = ptrace(H);").is_err());
+    }
+
+    #[test]
+    fn test_parse_subsystems() {
+        let rhs = |source: &str| {
+            let ast = parse_dsl(source).unwrap();
+            let Statement::HamiltonianDef { expr, .. } = &ast.statements[0] else {
+                panic!("expected a Hamiltonian");
+            };
+            *expr.clone()
+        };
+        let embed = |operator: &str, k: f64, dims: &[f64]| Expr::FuncCall {
+            name: "embed".to_string(),
+            args: vec![
+                Expr::Identifier(operator.to_string()),
+                Expr::Number(k),
+                Expr::Vector(VectorLiteral {
+                    elements: dims.iter().map(|&d| Expr::Number(d)).collect(),
+                }),
+            ],
+        };
+
+        assert_eq!(
+            rhs("Hamiltonian H = sigma_x on qubit 2 of 4;"),
+            embed("sigma_x", 2.0, &[2.0, 2.0, 2.0, 2.0])
+        );
+        assert_eq!(rhs("Hamiltonian H = embed(a, 1, vec(2, 10));"), embed("a", 1.0, &[2.0, 10.0]));
+        // Placement binds tighter than products and sums
+        assert_eq!(
+            rhs("Hamiltonian H = J * a on subsystem 1 of vec(2, 10) + sigma_z on qubit 0 of 2;"),
+            Expr::Add(
+                Box::new(Expr::Mul(
+                    Box::new(Expr::Identifier("J".to_string())),
+                    Box::new(embed("a", 1.0, &[2.0, 10.0]))
+                )),
+                Box::new(embed("sigma_z", 0.0, &[2.0, 2.0]))
+            )
+        );
+        assert_eq!(
+            rhs("Hamiltonian HB = H[1];"),
+            Expr::FuncCall {
+                name: "ptrace".to_string(),
+                args: vec![Expr::Identifier("H".to_string()), Expr::Number(1.0)],
+            }
+        );
+
+        assert!(parse_dsl("Hamiltonian H = sigma_x on qubit 1.5 of 4;").is_err());
+        assert!(parse_dsl("Hamiltonian H = a on subsystem 1 of 3;").is_err());
+    }
+
+    #[test]
+    fn test_parse_apply_statements() {
+        let ast = parse_dsl(
+            "experiment e {
+                 init: ket(vec(1, 0, 0, 0));
+                 apply H to psi[0];
+                 apply RX(theta / 2) to psi[1];
+                 apply CNOT to psi[1, 0];
+                 apply U to psi;
+             }",
+        )
+        .unwrap();
+        let Statement::Experiment { body, .. } = &ast.statements[0] else {
+            panic!("expected an experiment");
+        };
+        let gates: Vec<_> = body.gates.iter().map(|g| (g.gate.as_str(), g.targets.clone())).collect();
+        assert_eq!(
+            gates,
+            [("H", vec![0]), ("RX", vec![1]), ("CNOT", vec![1, 0]), ("U", vec![])]
+        );
+        assert_eq!(
+            body.gates[1].angle,
+            Some(Expr::Div(
+                Box::new(Expr::Identifier("theta".to_string())),
+                Box::new(Expr::Number(2.0))
+            ))
+        );
+
+        // Every gate acts on the experiment's one state
+        let err = parse_dsl(
+            "experiment e {
+                 init: ket(vec(1, 0));
+                 apply X to psi;
+                 evolution: evolve(phi, H, timegrid=(0, 0.1, 10));
+             }",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("'phi'"), "{}", err);
+        assert!(parse_dsl("experiment e { apply X to a; apply X to b; }").is_err());
+        assert!(parse_dsl("experiment e { apply X to psi[-1]; }").is_err());
+    }
+
+    #[test]
+    fn test_parse_eigenstates() {
+        let ast = parse_dsl(
+            "Hamiltonian D = dagger(eig(H)) * H * eig(H);
+             experiment e { init: eigenstate(H, 2); }",
+        )
+        .unwrap();
+        let Statement::Experiment { body, .. } = &ast.statements[1] else {
+            panic!("expected an experiment");
+        };
+        assert!(matches!(
+            &body.init,
+            Some(StateSpec::Eigenstate { hamiltonian, level: 2 }) if hamiltonian == "H"
+        ));
+        assert!(parse_dsl("experiment e { init: eigenstate(H, 0.5); }").is_err());
+    }
+
+    #[test]
+    fn test_parse_fock_operators() {
+        let ast = parse_dsl(
+            "operator a: Fock<10> = annihilation;
+             operator D: Fock<10> = displacement(0.5, -0.2);",
+        )
+        .unwrap();
+        let values: Vec<String> = ast
+            .statements
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::OperatorDecl { value, .. } => value.to_string(),
+                other => panic!("expected an operator, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(values, ["annihilation(10)", "displacement(10, 0.5, -0.2)"]);
+        assert!(parse_dsl("operator D: Fock<10> = displacement;").is_err());
+        assert!(parse_dsl("operator a: Fock<10> = sigma_x;").is_err());
+    }
+
+    #[test]
+    fn test_parse_function_calls() {
+        let ast = parse_dsl(
+            "func rotation(theta) = expm(-i * theta * sigma_x / 2);
+             Hamiltonian H = -rotation(0.5 * omega)^2 + 2i;",
+        )
+        .unwrap();
+        let Statement::FunctionDef { params, body, .. } = &ast.statements[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(params, &["theta"]);
+        let Expr::Expm(exponent) = body.as_ref() else {
+            panic!("expected expm, got {:?}", body);
+        };
+        assert!(matches!(
+            exponent.as_ref(),
+            Expr::Div(product, _) if format!("{:?}", product).starts_with("Mul(Mul(ComplexNumber(Complex { re: 0.0, im: -1.0 })")
+        ));
+
+        let Statement::HamiltonianDef { expr, .. } = &ast.statements[1] else {
+            panic!("expected a Hamiltonian");
+        };
+        let Expr::Add(negated, imaginary) = expr.as_ref() else {
+            panic!("expected a sum, got {:?}", expr);
+        };
+        assert_eq!(**imaginary, Expr::ComplexNumber(Complex64::new(0.0, 2.0)));
+        // The minus applies to the power
+        let Expr::Mul(minus_one, power) = negated.as_ref() else {
+            panic!("expected a negation, got {:?}", negated);
+        };
+        assert_eq!(**minus_one, Expr::Number(-1.0));
+        assert!(matches!(
+            power.as_ref(),
+            Expr::Pow(call, _) if matches!(call.as_ref(), Expr::FuncCall { name, args } if name == "rotation" && args.len() == 1)
+        ));
+
+        // Identifiers starting with i are not imaginary numbers
+        let ast = parse_dsl("Hamiltonian H = identity * f();").unwrap();
+        let Statement::HamiltonianDef { expr, .. } = &ast.statements[0] else {
+            panic!("expected a Hamiltonian");
+        };
+        assert!(matches!(expr.as_ref(), Expr::Mul(a, b)
+            if **a == Expr::Identifier("identity".to_string())
+                && matches!(b.as_ref(), Expr::FuncCall { args, .. } if args.is_empty())));
+    }
+
+    #[test]
+    fn test_parse_basis_measurement() {
+        let ast = parse_dsl("measure z: computational(8); measure b: Bell; measure x: X;").unwrap();
+        let bases: Vec<_> = ast
+            .statements
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::MeasurementDef {
+                    spec: MeasurementSpec::Basis { basis, dim },
+                    ..
+                } => (*basis, *dim),
+                other => panic!("expected a basis measurement, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            bases,
+            vec![
+                (MeasurementBasis::Computational, 8),
+                (MeasurementBasis::Bell, 4),
+                (MeasurementBasis::X, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_identity() {
+        let (lhs, rhs) = parse_identity("dagger(A * B) == dagger(B) * dagger(A)").unwrap();
+        let ident = |name: &str| Box::new(Expr::Identifier(name.to_string()));
+
+        assert_eq!(lhs, Expr::Dagger(Box::new(Expr::Mul(ident("A"), ident("B")))));
+        assert_eq!(
+            rhs,
+            Expr::Mul(Box::new(Expr::Dagger(ident("B"))), Box::new(Expr::Dagger(ident("A"))))
+        );
+        assert!(parse_identity("dagger(A)").is_err());
+
+        assert_eq!(parse_expression("dagger(B) * dagger(A)").unwrap(), rhs);
+        assert!(parse_expression("A == B").is_err());
+    }
+
+    #[test]
+    fn test_parse_proof_goals() {
+        let source = r#"
+            assume hermitian(H);
+            assume commutator(A, B) == 0;
+            prove [H, [A, B]] == 0;
+        "#;
+        let ast = parse_dsl(source).unwrap();
+        let ident = |name: &str| Box::new(Expr::Identifier(name.to_string()));
+
+        assert!(matches!(
+            &ast.statements[0],
+            Statement::Assume(ProofGoal::Property { kind: PropertyKind::Hermitian, expr }) if *expr == *ident("H")
+        ));
+        let Statement::Prove(goal) = &ast.statements[2] else {
+            panic!("expected a goal, got {:?}", ast.statements[2]);
+        };
+        let nested = Expr::Commutator(ident("H"), Box::new(Expr::Commutator(ident("A"), ident("B"))));
+        assert_eq!(goal, &ProofGoal::Identity { lhs: nested, rhs: Expr::Number(0.0) });
+        assert_eq!(goal.to_string(), "commutator(H, commutator(A, B)) == 0");
+
+        let (lhs, rhs) = ProofGoal::Property { kind: PropertyKind::Unitary, expr: *ident("U") }.identity();
+        assert_eq!(lhs, Expr::Mul(Box::new(Expr::Dagger(ident("U"))), ident("U")));
+        assert_eq!(rhs, *ident("I"));
+        assert!(parse_dsl("prove hermitian(H)").is_err());
+
+        // Row-by-row matrices are not commutators
+        let ast = parse_dsl("Hamiltonian H = [[1, 0], [0, -1]] + [A, B];").unwrap();
+        let Statement::HamiltonianDef { expr, .. } = &ast.statements[0] else {
+            panic!("expected a Hamiltonian");
+        };
+        assert!(matches!(
+            expr.as_ref(),
+            Expr::Add(m, c) if matches!(m.as_ref(), Expr::Matrix(m) if m.rows.len() == 2)
+                && matches!(c.as_ref(), Expr::Commutator(..))
+        ));
+    }
+
+    #[test]
+    fn test_parse_lindblad_block() {
+        let source = r#"
+            experiment decay {
+                init: ket(vec(0, 1));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+                lindblad: { L: sigma_minus, rate: gamma };
+                lindblad: [{ L: sz, rate: gamma_phi / 2 }];
+            }
+        "#;
+        let ast = parse_dsl(source).unwrap();
+        let Statement::Experiment { body, .. } = &ast.statements[0] else {
+            panic!("expected experiment");
+        };
+        let ops = &body.evolution.as_ref().unwrap().lindblad_ops;
+
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].operator_name, "sigma_minus");
+        assert_eq!(*ops[0].rate, Expr::Identifier("gamma".to_string()));
+        assert!(matches!(*ops[1].rate, Expr::Div(_, _)));
+    }
+
+    #[test]
+    fn test_parse_channel() {
+        let source = r#"
+            channel dephase = Kraus([[0.8, 0; 0, 0.8], 0.6 * sigma_z]);
+            experiment noisy {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+                channels: [(0.5, dephase), (1.0, dephase)];
+            }
+        "#;
+        let ast = parse_dsl(source).unwrap();
+        let Statement::ChannelDef { name, kraus } = &ast.statements[0] else {
+            panic!("expected channel");
+        };
+        assert_eq!(name, "dephase");
+        assert!(matches!(&kraus[0], Expr::Matrix(m) if m.rows.len() == 2));
+        assert!(matches!(&kraus[1], Expr::Mul(_, _)));
+
+        let Statement::Experiment { body, .. } = &ast.statements[1] else {
+            panic!("expected experiment");
+        };
+        let times: Vec<f64> = body.channels.iter().map(|c| c.time).collect();
+        assert_eq!(times, vec![0.5, 1.0]);
+        assert_eq!(body.channels[0].channel_name, "dephase");
+    }
+
+    #[test]
+    fn test_parse_observables() {
+        let source = r#"
+            experiment rabi {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+                observables: track <sigma_z>, <sigma_x  *  sigma_y>;
+            }
+        "#;
+        let ast = parse_dsl(source).unwrap();
+        let Statement::Experiment { body, .. } = &ast.statements[0] else {
+            panic!("expected experiment");
+        };
+        let labels: Vec<&str> = body.observables.iter().map(|o| o.label.as_str()).collect();
+        assert_eq!(labels, ["sigma_z", "sigma_x * sigma_y"]);
+        assert_eq!(body.observables[0].expr, Expr::Identifier("sigma_z".to_string()));
+        assert!(matches!(body.observables[1].expr, Expr::Mul(_, _)));
+    }
+
+    #[test]
+    fn test_parse_bloch_observable() {
+        let source = r#"
+            experiment rabi {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+                observables: track <sigma_z>, bloch(init);
+            }
+        "#;
+        let ast = parse_dsl(source).unwrap();
+        let Statement::Experiment { body, .. } = &ast.statements[0] else {
+            panic!("expected experiment");
+        };
+        let labels: Vec<&str> = body.observables.iter().map(|o| o.label.as_str()).collect();
+        assert_eq!(labels, ["sigma_z", "bloch(init).x", "bloch(init).y", "bloch(init).z"]);
+        assert_eq!(body.observables[1].expr, Expr::Identifier("sigma_x".to_string()));
+
+        assert!(parse_dsl(&source.replace("bloch(init)", "bloch(psi)")).is_err());
+    }
+
+    #[test]
+    fn test_lindblad_block_requires_evolution() {
+        let source = r#"
+            experiment decay {
+                init: ket(vec(0, 1));
+                lindblad: { L: sigma_minus, rate: 0.1 };
+            }
+        "#;
+        assert!(parse_dsl(source).is_err());
+    }
+
+    #[test]
+    fn test_errors_carry_source_locations() {
+        use crate::typechecker::TypeChecker;
+        use crate::validator::QuantumValidator;
+
+        let source = "matrix sigma = [0, 1; 1, 0];\nHamiltonian H = 0.5 * sigma + omega * sigma;\n";
+        let ast = parse_dsl(source).unwrap();
+        assert_eq!(ast.source_map.statement(1), Some(Span { line: 2, column: 1 }));
+        let err = TypeChecker::new().check(&ast).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Type error: line 2, column 31: Unknown identifier: omega"
+        );
+
+        // Constraint violations point at their statement
+        let source = "matrix sigma = [0, 1; 1, 0];\n\n  experiment e {\n    init: ket(vec(1, 1));\n  }\n";
+        let typed = TypeChecker::new().check(&parse_dsl(source).unwrap()).unwrap();
+        let err = QuantumValidator::new().validate(&typed).unwrap_err();
+        assert!(err.to_string().contains("line 3, column 3: Initial ket is not normalized"));
+
+        // Programs built in code have no positions
+        let err = TypeChecker::new()
+            .check(&Ast::new(ast.statements.clone()))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Type error: Unknown identifier: omega");
+    }
+
     #[test]
     fn test_parse_matrix() {
         let source = "matrix sigma_x = [[0, 1], [1, 0]];";
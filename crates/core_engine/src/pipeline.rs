@@ -0,0 +1,341 @@
+//! One-call entry points for each stage of the compilation pipeline
+//!
+//! Each function runs a stage with its default settings; construct the
+//! stage's type ([`TypeChecker`], [`QuantumValidator`], [`Lowerer`],
+//! [`Executor`]) directly for anything more.
+
+use crate::ast::{Ast, Expr};
+use crate::error::Result;
+use crate::executor::{BackendConfig, ExecutionResult, Executor};
+use crate::ir::IrProgram;
+use crate::lowering::Lowerer;
+use crate::plugin::{KernelRegistry, KernelValue};
+use crate::typechecker::TypeChecker;
+use crate::validator::{QuantumValidator, ValidatedAst, ValidatorConfig};
+use std::sync::Arc;
+
+/// Type check `ast`, then verify its quantum constraints (Hermitian
+/// Hamiltonians, valid states, complete measurements)
+pub fn validate_quantum(ast: &Ast) -> Result<ValidatedAst> {
+    let typed = TypeChecker::new().check(ast)?;
+    QuantumValidator::new().validate(&typed)
+}
+
+/// [`validate_quantum`] with the tolerance and strictness of `config`;
+/// violations it downgrades are listed in the results' `violations`
+pub fn validate_quantum_with(ast: &Ast, config: &ValidatorConfig) -> Result<ValidatedAst> {
+    let typed = TypeChecker::new().check(ast)?;
+    QuantumValidator::with_config(config.clone()).validate(&typed)
+}
+
+/// [`validate_quantum`] for a program calling the plugin kernels of
+/// `kernels`, whose calls take the shape each kernel reports
+pub fn validate_quantum_with_kernels(ast: &Ast, kernels: Arc<KernelRegistry>) -> Result<ValidatedAst> {
+    let typed = TypeChecker::with_kernels(kernels).check(ast)?;
+    QuantumValidator::new().validate(&typed)
+}
+
+/// Lower a validated program to IR
+pub fn lower(validated: &ValidatedAst) -> Result<IrProgram> {
+    lower_with_kernels(validated, Arc::new(KernelRegistry::new()))
+}
+
+/// [`lower`], turning calls to the plugin kernels of `kernels` into
+/// `IrNode::Custom` nodes for an executor with the same registry
+pub fn lower_with_kernels(validated: &ValidatedAst, kernels: Arc<KernelRegistry>) -> Result<IrProgram> {
+    Lowerer::with_kernels(kernels).lower(validated)
+}
+
+/// Run every experiment of `ir` on the backend described by `config`
+pub fn execute(ir: &IrProgram, config: BackendConfig) -> Result<ExecutionResult> {
+    Executor::new(config).execute(ir)
+}
+
+/// Evaluate `expr` in the scope of the declarations of `ast`, which is
+/// validated first
+pub fn evaluate(ast: &Ast, expr: &Expr) -> Result<KernelValue> {
+    let mut checker = TypeChecker::new();
+    let typed = checker.check(ast)?;
+    checker.shape_of(expr)?;
+    let validated = QuantumValidator::new().validate(&typed)?;
+    let (ir, id) = Lowerer::new().lower_expression(&validated, expr)?;
+    Executor::new(BackendConfig::default()).evaluate(&ir, id)
+}
+
+/// Parse, validate, lower and execute DSL `source`
+#[cfg(feature = "parser")]
+pub fn run(source: &str, config: BackendConfig) -> Result<ExecutionResult> {
+    let ast = crate::parser::parse_dsl(source)?;
+    execute(&lower(&validate_quantum(&ast)?)?, config)
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use super::*;
+    use num_complex::Complex64;
+    use std::collections::HashMap;
+
+    const RABI: &str = "
+        const Omega = 0.5;
+        matrix sigma_x = [0, 1; 1, 0];
+        Hamiltonian H = Omega * sigma_x;
+        experiment rabi {
+            init: ket(vec(1, 0));
+            evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+        }
+    ";
+
+    #[test]
+    fn test_run_matches_stages() {
+        let ast = crate::parser::parse_dsl(RABI).unwrap();
+        let ir = lower(&validate_quantum(&ast).unwrap()).unwrap();
+        let staged = execute(&ir, BackendConfig::default()).unwrap();
+        let direct = run(RABI, BackendConfig::default()).unwrap();
+
+        let (a, b) = (&staged.experiment_results[0], &direct.experiment_results[0]);
+        assert_eq!(a.times, b.times);
+        assert_eq!(a.states, b.states);
+
+        // Population of |1⟩ is sin²(Ωt)
+        let p1 = a.states[10][[1, 1]].re;
+        assert!((p1 - 0.5f64.sin().powi(2)).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_function_calls_are_evaluated() {
+        let source = "
+            const Omega = 0.5;
+            func drive(g) = g * sigma_x - 0.25 * sigma_z;
+            func rotation(theta) = expm(-i * theta * sigma_x / 2);
+            func rotated(theta, A) = dagger(rotation(theta)) * A * rotation(theta);
+            Hamiltonian H = drive(Omega);
+            experiment e {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 11));
+                observables: track <rotated(0.3, sigma_z)>;
+            }
+        ";
+        let inlined = RABI.replace("Omega * sigma_x", "Omega * sigma_x - 0.25 * sigma_z");
+        let called = run(source, BackendConfig::default()).unwrap();
+        let direct = run(&inlined, BackendConfig::default()).unwrap();
+        let (a, b) = (&called.experiment_results[0], &direct.experiment_results[0]);
+        for (x, y) in a.states.iter().zip(&b.states) {
+            assert!((x - y).iter().all(|d| d.norm() < 1e-12));
+        }
+
+        // ⟨0| Rx(θ)† σz Rx(θ) |0⟩ = cos θ
+        let series = &a.observables[0];
+        assert!((series.values[0].re - 0.3f64.cos()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_eigenstates_and_energy_eigenbasis() {
+        let source = "
+            Hamiltonian H = 0.5 * sigma_x + 0.25 * sigma_z;
+            experiment excited {
+                init: eigenstate(H, 1);
+                evolution: evolve(init, H, timegrid=(0.0, 2.0, 11));
+                observables: track <H>, <eig(H) * sigma_z * dagger(eig(H))>;
+            }
+        ";
+        let ast = crate::parser::parse_dsl(source).unwrap();
+        let ir = lower(&validate_quantum(&ast).unwrap()).unwrap();
+        let mut executor = Executor::new(BackendConfig::default());
+        let result = executor.execute(&ir).unwrap();
+        let excited = &result.experiment_results[0];
+
+        // An eigenstate is stationary, at energy √(0.5² + 0.25²)
+        let energy = (0.5f64.powi(2) + 0.25f64.powi(2)).sqrt();
+        for (e, z) in excited.observables[0].values.iter().zip(&excited.observables[1].values) {
+            assert!((e.re - energy).abs() < 1e-10);
+            // σz of the eigenbasis, with the excited level as |1⟩
+            assert!((z.re + 1.0).abs() < 1e-10);
+        }
+
+        let basis = executor.eigenbasis(&ir, "H").unwrap();
+        assert!((basis.bohr_frequencies()[[0, 1]] - 2.0 * energy).abs() < 1e-12);
+        for rho in excited.in_eigenbasis(&basis).unwrap() {
+            assert!((rho[[1, 1]].re - 1.0).abs() < 1e-10);
+        }
+        assert!(crate::parser::parse_dsl("experiment e { init: eigenstate(H, -1); }").is_err());
+    }
+
+    #[test]
+    fn test_qutrit_operators() {
+        let source = "
+            Hamiltonian H = 0.5 * spin_x(1) + 0.2 * gellmann(3, 8) + 0.1 * (clock(3) + dagger(clock(3)));
+            experiment spin1 {
+                init: ket(vec(1, 0, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 1.0, 11));
+                observables: track <spin_z(1)>, <shift(3) + dagger(shift(3))>;
+            }
+        ";
+        let result = run(source, BackendConfig::default()).unwrap();
+        let spin1 = &result.experiment_results[0];
+        assert_eq!(spin1.states[10].dim(), (3, 3));
+        let trace: Complex64 = spin1.states[10].diag().sum();
+        assert!((trace.re - 1.0).abs() < 1e-10);
+        // |m = 1⟩ to begin with
+        assert!((spin1.observables[0].values[0].re - 1.0).abs() < 1e-12);
+        assert!(spin1.observables[1].values[0].norm() < 1e-12);
+        assert!(spin1.observables[0].values[10].re < 1.0 - 1e-3);
+
+        let ast = crate::parser::parse_dsl("Hamiltonian H = gellmann(3, 9);").unwrap();
+        assert!(validate_quantum(&ast).is_err());
+        let ast = crate::parser::parse_dsl("Hamiltonian H = spin_x(1) + sigma_x;").unwrap();
+        assert!(validate_quantum(&ast).is_err());
+    }
+
+    #[test]
+    fn test_fock_operators() {
+        // A driven oscillator leaves the vacuum as a coherent state with
+        // ⟨n⟩ = 4 (g/ω)² sin²(ωt/2)
+        let source = "
+            operator a: Fock<10> = annihilation;
+            operator n: Fock<10> = number;
+            Hamiltonian H = n + 0.2 * (a + dagger(a));
+            experiment drive {
+                init: ket(vec(1, 0, 0, 0, 0, 0, 0, 0, 0, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+                observables: track <n>, <dagger(a) * a>, <displacement(10, 0.5)>;
+            }
+        ";
+        let result = run(source, BackendConfig::default()).unwrap();
+        let drive = &result.experiment_results[0];
+        let expected = 4.0 * 0.04 * 0.5f64.sin().powi(2);
+        assert!((drive.observables[0].values[10].re - expected).abs() < 1e-6);
+        assert!((drive.observables[1].values[10] - drive.observables[0].values[10]).norm() < 1e-12);
+        // ⟨0|D(α)|0⟩ = e^{-|α|²/2}
+        assert!((drive.observables[2].values[0].re - (-0.125f64).exp()).abs() < 1e-10);
+
+        let ast = crate::parser::parse_dsl("operator a: Fock<1> = annihilation;").unwrap();
+        assert!(validate_quantum(&ast).is_err());
+        let ast = crate::parser::parse_dsl("operator a: Fock<3> = annihilation; Hamiltonian H = a;").unwrap();
+        assert!(validate_quantum(&ast).is_err());
+    }
+
+    #[test]
+    fn test_validate_quantum_rejects_non_hermitian_hamiltonian() {
+        let source = "
+            matrix A = [0, 1; 0, 0];
+            Hamiltonian H = A;
+        ";
+        let ast = crate::parser::parse_dsl(source).unwrap();
+        assert!(validate_quantum(&ast).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_in_program_scope() {
+        let ast = crate::parser::parse_dsl(RABI).unwrap();
+        let expr = crate::parser::parse_expression("dagger(H) * H").unwrap();
+        let KernelValue::Matrix(h2) = evaluate(&ast, &expr).unwrap() else {
+            panic!("expected a matrix");
+        };
+        // (Ω σx)² = Ω² I
+        assert!((h2[[0, 0]].re - 0.25).abs() < 1e-12);
+        assert!(h2[[0, 1]].norm() < 1e-12);
+
+        let expr = crate::parser::parse_expression("2 * Omega").unwrap();
+        assert!(matches!(evaluate(&ast, &expr).unwrap(), KernelValue::Scalar(c) if c == Complex64::new(1.0, 0.0)));
+        let expr = crate::parser::parse_expression("undefined_name").unwrap();
+        assert!(evaluate(&ast, &expr).is_err());
+    }
+
+    #[test]
+    fn test_parameters_bound_at_run_time() {
+        let source = RABI.replace("const Omega = 0.5;", "param Omega;");
+        let ir = lower(&validate_quantum(&crate::parser::parse_dsl(&source).unwrap()).unwrap()).unwrap();
+        assert_eq!(ir.parameters(), ["Omega"]);
+
+        let mut executor = Executor::new(BackendConfig::default());
+        for omega in [0.5, 1.5] {
+            let params = HashMap::from([("Omega".to_string(), omega)]);
+            let result = executor.execute_with_params(&ir, &params).unwrap();
+            let expected = run(&RABI.replace("0.5", &omega.to_string()), BackendConfig::default()).unwrap();
+            assert_eq!(result.experiment_results[0].states, expected.experiment_results[0].states);
+        }
+
+        assert!(executor.execute(&ir).is_err());
+        let unknown = HashMap::from([("Omega".to_string(), 0.5), ("gamma".to_string(), 0.1)]);
+        assert!(executor.execute_with_params(&ir, &unknown).is_err());
+        let batch = executor.execute_batch(&ir, &[HashMap::from([("Omega".to_string(), 1.5)])]);
+        assert!(batch[0].is_ok());
+    }
+
+    #[test]
+    fn test_observable_sensitivities() {
+        // ⟨σz⟩ = cos(2Ωt) from |0⟩, so ∂⟨σz⟩/∂Ω = -2t sin(2Ωt)
+        let source = RABI.replace("const Omega = 0.5;", "param Omega;\n matrix sigma_z = [1, 0; 0, -1];").replace(
+            "timegrid=(0.0, 0.1, 10));",
+            "timegrid=(0.0, 0.1, 10));\n observables: track <sigma_z>;",
+        );
+        let ir = lower(&validate_quantum(&crate::parser::parse_dsl(&source).unwrap()).unwrap()).unwrap();
+        let params = HashMap::from([("Omega".to_string(), 0.5)]);
+
+        let mut executor = Executor::new(BackendConfig::default());
+        let (result, sensitivities) = executor.execute_with_sensitivities(&ir, &params, &["Omega"]).unwrap();
+        assert_eq!(sensitivities.len(), 1);
+        assert_eq!(sensitivities[0].parameter, "Omega");
+        let times = &result.experiment_results[0].times;
+        for (t, value) in times.iter().zip(&sensitivities[0].values) {
+            assert!((value.re + 2.0 * t * (2.0 * 0.5 * t).sin()).abs() < 1e-6);
+        }
+        assert!(executor.execute_with_sensitivities(&ir, &params, &["gamma"]).is_err());
+    }
+
+    #[test]
+    fn test_plugin_kernels_through_the_pipeline() {
+        use crate::plugin::Kernel;
+        use crate::typechecker::Shape;
+
+        // Matrix-valued stand-in for a transfer-tensor step: ρ ↦ XρX
+        struct TtmPropagate;
+
+        impl Kernel for TtmPropagate {
+            fn name(&self) -> &str {
+                "ttm_propagate"
+            }
+
+            fn arity(&self) -> Option<usize> {
+                Some(1)
+            }
+
+            fn output_shape(&self, args: &[Shape]) -> Result<Shape> {
+                Ok(args[0].clone())
+            }
+
+            fn execute(&self, args: &[KernelValue]) -> Result<KernelValue> {
+                let x = ndarray::arr2(&[[0.0, 1.0], [1.0, 0.0]]).mapv(|v| Complex64::new(v, 0.0));
+                Ok(KernelValue::Matrix(x.dot(args[0].as_matrix()?).dot(&x)))
+            }
+        }
+
+        let source = "
+            matrix Z = [1, 0; 0, -1];
+            Hamiltonian R = ttm_propagate(Z);
+            Hamiltonian H = sigma_x + R;
+            experiment e {
+                init: ket(vec(1, 0));
+                evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+            }
+        ";
+        let mut registry = KernelRegistry::new();
+        registry.register(TtmPropagate).unwrap();
+        let registry = Arc::new(registry);
+        let ast = crate::parser::parse_dsl(source).unwrap();
+
+        let validated = validate_quantum_with_kernels(&ast, registry.clone()).unwrap();
+        assert_eq!(validated.typed_ast.shapes["R"], Shape::Matrix(2, 2));
+        let ir = lower_with_kernels(&validated, registry.clone()).unwrap();
+        let two_args = crate::parser::parse_dsl("Hamiltonian R = ttm_propagate(sigma_x, sigma_z);").unwrap();
+        assert!(validate_quantum_with_kernels(&two_args, registry.clone()).is_err());
+
+        // H = σx + XσzX = σx - σz
+        let hamiltonians = Executor::with_kernels(BackendConfig::default(), registry)
+            .hamiltonians(&ir)
+            .unwrap();
+        let (_, h) = hamiltonians.iter().find(|(name, _)| name == "H").unwrap();
+        let c = |re: f64| Complex64::new(re, 0.0);
+        assert_eq!(h.iter().copied().collect::<Vec<_>>(), vec![c(-1.0), c(1.0), c(1.0), c(1.0)]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Kernel plugin interface
+//!
+//! Lets other crates add kernels without forking the engine. A plugin
+//! implements [`Kernel`] and is registered in a [`KernelRegistry`], which is
+//! handed to the [`TypeChecker`](crate::typechecker::TypeChecker), the
+//! [`Lowerer`](crate::lowering::Lowerer) and the
+//! [`Executor`](crate::executor::Executor):
+//!
+//! - type checking gives a DSL call `name(args...)` whose `name` is
+//!   registered the shape [`Kernel::output_shape`] reports;
+//! - lowering turns the call into an
+//!   [`IrNode::Custom`](crate::ir::IrNode::Custom) node;
+//! - the executor evaluates that node by calling [`Kernel::execute`] with the
+//!   already-evaluated arguments.
+//!
+//! ```rust,ignore
+//! struct TransferTensor { memory: Vec<Array2<Complex64>> }
+//!
+//! impl Kernel for TransferTensor {
+//!     fn name(&self) -> &str { "ttm_propagate" }
+//!     fn arity(&self) -> Option<usize> { Some(1) }
+//!     fn output_shape(&self, args: &[Shape]) -> Result<Shape> { Ok(args[0].clone()) }
+//!     fn execute(&self, args: &[KernelValue]) -> Result<KernelValue> {
+//!         let rho = args[0].as_matrix()?;
+//!         Ok(KernelValue::Matrix(self.propagate(rho)))
+//!     }
+//! }
+//!
+//! let mut registry = KernelRegistry::new();
+//! registry.register(TransferTensor::load("bath.ttm")?)?;
+//! let registry = Arc::new(registry);
+//!
+//! let validated = pipeline::validate_quantum_with_kernels(&ast, registry.clone())?;
+//! let ir = pipeline::lower_with_kernels(&validated, registry.clone())?;
+//! let result = Executor::with_kernels(BackendConfig::default(), registry).execute(&ir)?;
+//! ```
+//!
+//! # Stability
+//!
+//! [`Kernel`] and [`KernelValue`] are part of the stable API: new trait
+//! methods are only ever added with default implementations, and new
+//! `KernelValue` variants only in a major release.
+
+use crate::error::{EngineError, Result};
+use crate::typechecker::Shape;
+use ndarray::{Array1, Array2};
+use num_complex::Complex64;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A value produced or consumed by a kernel
+#[derive(Debug, Clone, PartialEq)]
+pub enum KernelValue {
+    Scalar(Complex64),
+    Vector(Array1<Complex64>),
+    Matrix(Array2<Complex64>),
+}
+
+/// A custom kernel callable from the DSL and executable by the engine
+///
+/// Kernels must be pure: lowering shares one IR node between structurally
+/// equal calls, so a kernel may run once for several call sites.
+pub trait Kernel: Send + Sync {
+    /// Name under which the kernel is called from the DSL
+    fn name(&self) -> &str;
+
+    /// Number of arguments, or `None` if the kernel is variadic
+    fn arity(&self) -> Option<usize> {
+        None
+    }
+
+    /// One-line description shown in listings
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// Shape of the result for arguments of shapes `args`, which the type
+    /// checker gives each call; a scalar unless overridden
+    fn output_shape(&self, _args: &[Shape]) -> Result<Shape> {
+        Ok(Shape::Scalar)
+    }
+
+    /// Evaluate the kernel on already-evaluated arguments
+    fn execute(&self, args: &[KernelValue]) -> Result<KernelValue>;
+}
+
+/// Registry of custom kernels, keyed by name
+#[derive(Clone, Default)]
+pub struct KernelRegistry {
+    kernels: HashMap<String, Arc<dyn Kernel>>,
+}
+
+impl KernelValue {
+    pub fn as_scalar(&self) -> Result<Complex64> {
+        match self {
+            KernelValue::Scalar(c) => Ok(*c),
+            other => Err(other.kind_error("scalar")),
+        }
+    }
+
+    pub fn as_vector(&self) -> Result<&Array1<Complex64>> {
+        match self {
+            KernelValue::Vector(v) => Ok(v),
+            other => Err(other.kind_error("vector")),
+        }
+    }
+
+    pub fn as_matrix(&self) -> Result<&Array2<Complex64>> {
+        match self {
+            KernelValue::Matrix(m) => Ok(m),
+            other => Err(other.kind_error("matrix")),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            KernelValue::Scalar(_) => "scalar",
+            KernelValue::Vector(_) => "vector",
+            KernelValue::Matrix(_) => "matrix",
+        }
+    }
+
+    fn kind_error(&self, expected: &str) -> EngineError {
+        EngineError::type_error(format!("Expected {}, got {}", expected, self.kind()))
+    }
+}
+
+impl KernelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a kernel; names must be unique
+    pub fn register(&mut self, kernel: impl Kernel + 'static) -> Result<()> {
+        let name = kernel.name().to_string();
+        if self.kernels.contains_key(&name) {
+            return Err(EngineError::validation_error(format!(
+                "Kernel '{}' is already registered",
+                name
+            )));
+        }
+        self.kernels.insert(name, Arc::new(kernel));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Kernel>> {
+        self.kernels.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.kernels.contains_key(name)
+    }
+
+    /// Registered kernel names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.kernels.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Shape of the result of calling `name` on arguments of shapes
+    /// `args`, checking the argument count
+    pub fn output_shape(&self, name: &str, args: &[Shape]) -> Result<Shape> {
+        let kernel = self.get(name).ok_or_else(|| {
+            EngineError::type_error(format!("Kernel '{}' is not registered", name))
+        })?;
+
+        if let Some(arity) = kernel.arity() {
+            if args.len() != arity {
+                return Err(EngineError::type_error(format!(
+                    "Kernel '{}' expects {} arguments, got {}",
+                    name,
+                    arity,
+                    args.len()
+                )));
+            }
+        }
+
+        kernel.output_shape(args)
+    }
+
+    /// Look up `name` and run it, checking the argument count
+    pub fn execute(&self, name: &str, args: &[KernelValue]) -> Result<KernelValue> {
+        let kernel = self.get(name).ok_or_else(|| {
+            EngineError::ExecutionError(format!("Kernel '{}' is not registered", name))
+        })?;
+
+        if let Some(arity) = kernel.arity() {
+            if args.len() != arity {
+                return Err(EngineError::ExecutionError(format!(
+                    "Kernel '{}' expects {} arguments, got {}",
+                    name,
+                    arity,
+                    args.len()
+                )));
+            }
+        }
+
+        kernel.execute(args)
+    }
+}
+
+impl std::fmt::Debug for KernelRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KernelRegistry")
+            .field("kernels", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Scale;
+
+    impl Kernel for Scale {
+        fn name(&self) -> &str {
+            "scale2"
+        }
+
+        fn arity(&self) -> Option<usize> {
+            Some(1)
+        }
+
+        fn execute(&self, args: &[KernelValue]) -> Result<KernelValue> {
+            let m = args[0].as_matrix()?;
+            Ok(KernelValue::Matrix(m.mapv(|x| x * 2.0)))
+        }
+    }
+
+    #[test]
+    fn test_register_and_execute() {
+        let mut registry = KernelRegistry::new();
+        registry.register(Scale).unwrap();
+
+        let input = KernelValue::Matrix(Array2::eye(2));
+        let output = registry.execute("scale2", &[input]).unwrap();
+
+        assert_eq!(output.as_matrix().unwrap()[[1, 1]], Complex64::new(2.0, 0.0));
+        assert_eq!(registry.names(), vec!["scale2"]);
+    }
+
+    #[test]
+    fn test_duplicate_and_arity_errors() {
+        let mut registry = KernelRegistry::new();
+        registry.register(Scale).unwrap();
+
+        assert!(registry.register(Scale).is_err());
+        assert!(registry.execute("scale2", &[]).is_err());
+        assert!(registry.execute("missing", &[]).is_err());
+    }
+}
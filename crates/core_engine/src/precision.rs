@@ -0,0 +1,178 @@
+//! Floating-point precision of the dense evolution kernels
+//!
+//! Large parameter sweeps rarely need more than ~1e-6 accuracy, and single
+//! precision halves the memory traffic of every matrix product. With
+//! [`Precision::Single`] the RK4 Lindblad integrator works in `f32`
+//! throughout; states are widened back to `Complex64` on output. A
+//! [`PrecisionCheck`] reruns a sample of those evolutions in `f64` and fails
+//! when the two disagree by more than its tolerance, so a sweep's error is
+//! bounded by measurement rather than by hope.
+
+use crate::error::{EngineError, Result};
+use ndarray::Array2;
+use num_complex::{Complex, Complex64};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Floating-point type of the evolution kernels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Precision {
+    /// `f64`, the engine's native precision
+    #[default]
+    Double,
+    /// `f32`, roughly 1e-7 relative accuracy per operation
+    Single,
+}
+
+impl FromStr for Precision {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "double" | "f64" => Ok(Precision::Double),
+            "single" | "f32" => Ok(Precision::Single),
+            other => Err(EngineError::validation_error(format!(
+                "Unknown precision '{}' (expected double or single)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Real type the generic kernels are instantiated with
+pub trait Scalar: num_traits::Float + Debug + Send + Sync + 'static {
+    fn from_f64(value: f64) -> Self;
+    fn into_f64(self) -> f64;
+}
+
+impl Scalar for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn into_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Scalar for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+/// `m` rounded to precision `T`
+pub fn narrow<T: Scalar>(m: &Array2<Complex64>) -> Array2<Complex<T>> {
+    m.mapv(|z| Complex::new(T::from_f64(z.re), T::from_f64(z.im)))
+}
+
+/// `m` widened back to `Complex64`
+pub fn widen<T: Scalar>(m: &Array2<Complex<T>>) -> Array2<Complex64> {
+    m.mapv(|z| Complex64::new(z.re.into_f64(), z.im.into_f64()))
+}
+
+/// Sampled double-precision verification of single-precision evolutions
+///
+/// The first evolution run with the configuration, and every
+/// `sample_every`-th after it, is repeated in `f64`. Clones share the count,
+/// so a sweep that clones one `BackendConfig` per point verifies every
+/// `sample_every`-th point rather than the first evolution of each.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrecisionCheck {
+    pub sample_every: usize,
+    /// Largest tolerated elementwise |ρ_f32(t) - ρ_f64(t)|
+    pub tolerance: f64,
+    #[serde(skip)]
+    evolutions: Arc<AtomicUsize>,
+}
+
+impl Default for PrecisionCheck {
+    fn default() -> Self {
+        PrecisionCheck::new(10, 1e-5)
+    }
+}
+
+impl PrecisionCheck {
+    pub fn new(sample_every: usize, tolerance: f64) -> Self {
+        PrecisionCheck {
+            sample_every,
+            tolerance,
+            evolutions: Arc::default(),
+        }
+    }
+
+    /// Count one evolution, returning whether it is to be verified
+    pub fn next_is_due(&self) -> bool {
+        let index = self.evolutions.fetch_add(1, Ordering::Relaxed);
+        self.sample_every > 0 && index.is_multiple_of(self.sample_every)
+    }
+
+    /// Largest elementwise deviation of `states` from the double-precision
+    /// `reference`, failing when it exceeds the tolerance
+    pub fn verify(
+        &self,
+        states: &[Array2<Complex64>],
+        reference: &[Array2<Complex64>],
+        times: &[f64],
+    ) -> Result<f64> {
+        let mut worst = (0.0, times.first().copied().unwrap_or(0.0));
+        for ((state, exact), &t) in states.iter().zip(reference).zip(times) {
+            let deviation = state
+                .iter()
+                .zip(exact)
+                .map(|(a, b)| (a - b).norm())
+                .fold(0.0, f64::max);
+            if deviation > worst.0 {
+                worst = (deviation, t);
+            }
+        }
+
+        let (deviation, time) = worst;
+        if deviation > self.tolerance {
+            return Err(EngineError::ExecutionError(format!(
+                "Single-precision evolution deviates from double precision by {:.3e} at t = {} (tolerance {:.1e})",
+                deviation, time, self.tolerance
+            )));
+        }
+        Ok(deviation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_check_samples_shared_count() {
+        let check = PrecisionCheck::new(3, 1e-5);
+        let clone = check.clone();
+        let due: Vec<bool> = (0..7)
+            .map(|i| if i % 2 == 0 { check.next_is_due() } else { clone.next_is_due() })
+            .collect();
+        assert_eq!(due, [true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_verify_bounds_deviation() {
+        let exact = array![[Complex64::new(0.5, 0.0), Complex64::new(0.0, 0.1)]];
+        let close = exact.mapv(|z| z + Complex64::new(2e-6, 0.0));
+        let far = exact.mapv(|z| z + Complex64::new(0.0, 1e-3));
+        let check = PrecisionCheck::default();
+
+        let deviation = check
+            .verify(&[exact.clone(), close], &[exact.clone(), exact.clone()], &[0.0, 1.0])
+            .unwrap();
+        assert!((deviation - 2e-6).abs() < 1e-12);
+        assert!(check.verify(&[far], &[exact], &[0.0]).is_err());
+        assert_eq!(narrow::<f32>(&array![[Complex64::new(0.1, 0.0)]])[[0, 0]].re, 0.1f32);
+        assert_eq!("single".parse::<Precision>().unwrap(), Precision::Single);
+    }
+}
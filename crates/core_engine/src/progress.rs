@@ -0,0 +1,93 @@
+//! Progress reporting of long-running work
+//!
+//! A [`ProgressReporter`] is handed to the work when it starts, like a
+//! [`CancellationToken`](crate::cancellation::CancellationToken); the work
+//! records the fraction it has done, and whoever holds a clone reads it,
+//! such as the job queue estimating the time left. [`StepProgress`] turns
+//! time steps counted against a run's total into that fraction.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Handle through which a running job reports the fraction of its work done
+///
+/// Clones share the same progress, so a clone can be moved to another
+/// thread (e.g. into `spawn_blocking`) where
+/// `job_queue::report_progress` cannot see the job.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressReporter {
+    fraction: Arc<AtomicU64>,
+}
+
+impl ProgressReporter {
+    /// Record the completed fraction of the job, clamped to `[0, 1]`
+    pub fn report(&self, fraction: f64) {
+        if !fraction.is_nan() {
+            self.fraction.store(fraction.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Record `done` of `total` units of work, such as time steps or sweep
+    /// points
+    pub fn report_steps(&self, done: usize, total: usize) {
+        if total > 0 {
+            self.report(done as f64 / total as f64);
+        }
+    }
+
+    pub fn fraction(&self) -> f64 {
+        f64::from_bits(self.fraction.load(Ordering::Relaxed))
+    }
+}
+
+/// Time steps of a run counted against its total, reported to a
+/// [`ProgressReporter`] as they are taken
+///
+/// Clones share the count, so an integrator can step a clone while its
+/// executor steps the original.
+#[derive(Debug, Clone)]
+pub struct StepProgress {
+    reporter: ProgressReporter,
+    done: Arc<AtomicUsize>,
+    total: usize,
+}
+
+impl StepProgress {
+    pub fn new(reporter: ProgressReporter, total: usize) -> Self {
+        StepProgress {
+            reporter,
+            done: Arc::default(),
+            total,
+        }
+    }
+
+    /// Count one more step done
+    pub fn step(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.reporter.report_steps(done, self.total);
+    }
+}
+
+/// [`StepProgress::step`] of `progress`, if there is one
+pub fn step(progress: Option<&StepProgress>) {
+    if let Some(progress) = progress {
+        progress.step();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_report_fraction_of_total() {
+        let reporter = ProgressReporter::default();
+        let steps = StepProgress::new(reporter.clone(), 4);
+        let clone = steps.clone();
+        steps.step();
+        clone.step();
+        step(Some(&steps));
+        step(None);
+        assert_eq!(reporter.fraction(), 0.75);
+    }
+}
@@ -0,0 +1,563 @@
+//! Export of identity proofs to Lean 4 and Coq
+//!
+//! A [`Proof`] becomes a theorem over an abstract operator algebra whose
+//! axioms are the prover's rewrite rules, stated once in a small prelude
+//! ([`LEAN_PRELUDE`], [`COQ_PRELUDE`]) at the top of every export. Each
+//! rewrite step becomes one link of an equational chain, discharged by
+//! rewriting with the matching axiom, so the proof assistant re-checks
+//! every step independently of the engine. Gaps between the statement and
+//! the rewrite chain are closed by normalising with the prelude's
+//! simplification set; steps whose rule has no axiom are left as `sorry`
+//! (Lean) or `admit` (Coq) for the user to discharge.
+//!
+//! A published [`Certificate`] that records its statement and rewrite
+//! steps exports the same way through [`export_certificate`], so a third
+//! party can re-check it without rerunning the prover.
+//!
+//! Identifiers become operator variables, `I` becomes the unit, and other
+//! numeric literals become opaque constants, so an exported theorem is
+//! only as strong as the algebra laws it uses.
+
+use crate::ast::Expr;
+use crate::error::{EngineError, Result};
+use crate::prover::{Certificate, Proof, RewriteRule, Statement};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Operator-algebra prelude for Lean 4 exports; needs only core Lean
+pub const LEAN_PRELUDE: &str = r#"/-! Operator-algebra prelude for proofs exported by the Quantum Theory Engine.
+Only the laws used by the engine's rewrite rules are assumed. -/
+
+class QteAlgebra (α : Type) extends Add α, Sub α, Mul α, Neg α where
+  zero : α
+  one : α
+  star : α → α
+  tensor : α → α → α
+  comm : α → α → α
+  acomm : α → α → α
+  tr : α → α
+  expm : α → α
+  sqrt : α → α
+  sin : α → α
+  cos : α → α
+  exp : α → α
+  div : α → α → α
+  pow : α → α → α
+  add_zero : ∀ a : α, a + zero = a
+  zero_add : ∀ a : α, zero + a = a
+  mul_zero : ∀ a : α, a * zero = zero
+  zero_mul : ∀ a : α, zero * a = zero
+  mul_one : ∀ a : α, a * one = a
+  one_mul : ∀ a : α, one * a = a
+  star_star : ∀ a : α, star (star a) = a
+  star_add : ∀ a b : α, star (a + b) = star a + star b
+  star_mul : ∀ a b : α, star (a * b) = star b * star a
+  star_tensor : ∀ a b : α, star (tensor a b) = tensor (star a) (star b)
+  tensor_assoc : ∀ a b c : α, tensor (tensor a b) c = tensor a (tensor b c)
+  comm_self : ∀ a : α, comm a a = zero
+  comm_anti : ∀ a b : α, comm a b = -(comm b a)
+  jacobi : ∀ a b c : α, comm a (comm b c) + comm b (comm c a) + comm c (comm a b) = zero
+  tr_mul_comm : ∀ a b : α, tr (a * b) = tr (b * a)
+  tensor_add : ∀ a b c : α, tensor a (b + c) = tensor a b + tensor a c
+  add_tensor : ∀ a b c : α, tensor (a + b) c = tensor a c + tensor b c
+
+open QteAlgebra
+
+macro "qte_simp" : tactic =>
+  `(tactic| simp only [QteAlgebra.add_zero, QteAlgebra.zero_add, QteAlgebra.mul_zero,
+    QteAlgebra.zero_mul, QteAlgebra.mul_one, QteAlgebra.one_mul, QteAlgebra.star_star,
+    QteAlgebra.star_add, QteAlgebra.star_mul, QteAlgebra.star_tensor,
+    QteAlgebra.tensor_assoc, QteAlgebra.comm_self])
+"#;
+
+/// Operator-algebra prelude for Coq exports; needs no libraries
+pub const COQ_PRELUDE: &str = r#"(* Operator-algebra prelude for proofs exported by the Quantum Theory Engine.
+   Only the laws used by the engine's rewrite rules are assumed. *)
+
+Parameter Op : Type.
+Parameters zero one : Op.
+Parameters add sub mul tensor comm acomm div pow : Op -> Op -> Op.
+Parameters neg star tr expm sqrt sin cos exp : Op -> Op.
+
+Declare Scope qte_scope.
+Infix "+" := add : qte_scope.
+Infix "-" := sub : qte_scope.
+Infix "*" := mul : qte_scope.
+Notation "- x" := (neg x) : qte_scope.
+Open Scope qte_scope.
+
+Axiom add_zero : forall a, a + zero = a.
+Axiom zero_add : forall a, zero + a = a.
+Axiom mul_zero : forall a, a * zero = zero.
+Axiom zero_mul : forall a, zero * a = zero.
+Axiom mul_one : forall a, a * one = a.
+Axiom one_mul : forall a, one * a = a.
+Axiom star_star : forall a, star (star a) = a.
+Axiom star_add : forall a b, star (a + b) = star a + star b.
+Axiom star_mul : forall a b, star (a * b) = star b * star a.
+Axiom star_tensor : forall a b, star (tensor a b) = tensor (star a) (star b).
+Axiom tensor_assoc : forall a b c, tensor (tensor a b) c = tensor a (tensor b c).
+Axiom comm_self : forall a, comm a a = zero.
+Axiom comm_anti : forall a b, comm a b = - comm b a.
+Axiom jacobi : forall a b c, comm a (comm b c) + comm b (comm c a) + comm c (comm a b) = zero.
+Axiom tr_mul_comm : forall a b, tr (a * b) = tr (b * a).
+Axiom tensor_add : forall a b c, tensor a (b + c) = tensor a b + tensor a c.
+Axiom add_tensor : forall a b c, tensor (a + b) c = tensor a c + tensor b c.
+
+Hint Rewrite add_zero zero_add mul_zero zero_mul mul_one one_mul star_star star_add
+  star_mul star_tensor tensor_assoc comm_self : qte.
+Ltac qte_simp := autorewrite with qte; reflexivity.
+"#;
+
+/// Names the preludes and the two languages reserve
+const RESERVED: &[&str] = &[
+    "zero", "one", "star", "tensor", "comm", "acomm", "tr", "expm", "sqrt", "sin", "cos", "exp",
+    "div", "pow", "add", "sub", "mul", "neg", "Op", "fun", "let", "in", "at", "by", "do", "then",
+    "else", "if", "match", "with", "end", "forall", "exists", "Type", "Prop", "Set", "theorem",
+    "Theorem", "Proof", "Qed", "def", "where", "open",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofAssistant {
+    Lean4,
+    Coq,
+}
+
+impl ProofAssistant {
+    /// `.lean` files are Lean 4, `.v` files Coq
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("lean") => Some(ProofAssistant::Lean4),
+            Some("v") => Some(ProofAssistant::Coq),
+            _ => None,
+        }
+    }
+}
+
+/// Translate an identity proof into a self-contained Lean 4 or Coq file
+pub fn export_proof(proof: &Proof, assistant: ProofAssistant) -> Result<String> {
+    let Statement::Identity { lhs, rhs } = &proof.statement else {
+        return Err(EngineError::Unsupported(
+            "Only identity proofs can be exported to a proof assistant".to_string(),
+        ));
+    };
+
+    // The equational chain lhs = e1 = ... = rhs, each link justified by the
+    // rule of the step producing it, or by normalisation where the
+    // statement and the rewrite steps do not meet
+    let mut chain: Vec<(&Expr, Option<&RewriteRule>)> = vec![(lhs, None)];
+    for step in &proof.steps {
+        if &step.before != chain.last().unwrap().0 {
+            chain.push((&step.before, None));
+        }
+        chain.push((&step.after, Some(&step.rule)));
+    }
+    if rhs != chain.last().unwrap().0 {
+        chain.push((rhs, None));
+    }
+
+    let mut printer = TermPrinter::default();
+    let terms = chain
+        .iter()
+        .map(|(expr, _)| printer.term(expr))
+        .collect::<Result<Vec<_>>>()?;
+    let name = format!(
+        "qte_{}",
+        proof.certificate.hash.chars().take(12).collect::<String>()
+    );
+    let header = format!(
+        "Proven by the Quantum Theory Engine {} (certificate {})",
+        proof.certificate.engine_version, proof.certificate.hash
+    );
+
+    let mut out = String::new();
+    match assistant {
+        ProofAssistant::Lean4 => {
+            out.push_str(LEAN_PRELUDE);
+            let binders: String = printer
+                .variables
+                .iter()
+                .map(|(v, &arity)| format!(" ({} : {})", v, arrow("α", arity, " → ")))
+                .collect();
+            out.push_str(&format!(
+                "\n/-- {} -/\ntheorem {} {{α : Type}} [QteAlgebra α]{} :\n    {} = {} := by\n",
+                header,
+                name,
+                binders,
+                terms[0],
+                terms[terms.len() - 1]
+            ));
+            if terms.len() == 1 {
+                out.push_str("  rfl\n");
+            }
+            for (k, (_, rule)) in chain.iter().enumerate().skip(1) {
+                let prefix = if k == 1 {
+                    format!("  calc {}", terms[0])
+                } else {
+                    "    _".to_string()
+                };
+                out.push_str(&format!(
+                    "{} = {} := by {}\n",
+                    prefix,
+                    terms[k],
+                    lean_tactic(*rule)
+                ));
+            }
+        }
+        ProofAssistant::Coq => {
+            out.push_str(COQ_PRELUDE);
+            let binders: String = printer
+                .variables
+                .iter()
+                .map(|(v, &arity)| format!(" ({} : {})", v, arrow("Op", arity, " -> ")))
+                .collect();
+            let quantifier = if binders.is_empty() {
+                String::new()
+            } else {
+                format!("forall{}, ", binders)
+            };
+            out.push_str(&format!(
+                "\n(* {} *)\nTheorem {} : {}{} = {}.\nProof.\n",
+                header,
+                name,
+                quantifier,
+                terms[0],
+                terms[terms.len() - 1]
+            ));
+            if !printer.variables.is_empty() {
+                let names: Vec<&str> = printer.variables.keys().map(String::as_str).collect();
+                out.push_str(&format!("  intros {}.\n", names.join(" ")));
+            }
+            let mut admitted = false;
+            if terms.len() == 1 {
+                out.push_str("  reflexivity.\n");
+            }
+            for (k, (_, rule)) in chain.iter().enumerate().skip(1) {
+                let tactic = coq_tactic(*rule);
+                admitted |= tactic == "admit";
+                if k + 1 < chain.len() {
+                    out.push_str(&format!(
+                        "  transitivity ({}).\n  {{ {}. }}\n",
+                        terms[k], tactic
+                    ));
+                } else {
+                    out.push_str(&format!("  {}.\n", tactic));
+                }
+            }
+            out.push_str(if admitted { "Admitted.\n" } else { "Qed.\n" });
+        }
+    }
+    Ok(out)
+}
+
+/// Translate the proof recorded in a certificate; fails for certificates
+/// of numeric checks and for ones issued before certificates recorded
+/// their rewrite steps
+pub fn export_certificate(certificate: &Certificate, assistant: ProofAssistant) -> Result<String> {
+    let statement = certificate.statement.clone().ok_or_else(|| {
+        EngineError::validation_error("The certificate does not record its statement")
+    })?;
+    let steps = certificate
+        .verification_steps
+        .iter()
+        .map(|step| step.step.clone())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            EngineError::validation_error("The certificate does not record its rewrite steps")
+        })?;
+    let proof = Proof {
+        statement,
+        steps,
+        assumptions_used: certificate.assumptions.clone(),
+        certificate: certificate.clone(),
+    };
+    export_proof(&proof, assistant)
+}
+
+/// Prelude axioms a rule rewrites with, in the order to try them
+fn axioms(rule: &RewriteRule) -> Option<&'static [&'static str]> {
+    match rule {
+        RewriteRule::DaggerDagger => Some(&["star_star"]),
+        RewriteRule::DaggerSum => Some(&["star_add"]),
+        RewriteRule::DaggerProduct => Some(&["star_mul"]),
+        RewriteRule::CommutatorSelf => Some(&["comm_self"]),
+        RewriteRule::CommutatorAnti => Some(&["comm_anti"]),
+        RewriteRule::JacobiIdentity => Some(&["jacobi"]),
+        RewriteRule::TraceCyclic => Some(&["tr_mul_comm"]),
+        RewriteRule::TensorDistribute => Some(&["tensor_add", "add_tensor"]),
+        RewriteRule::TensorDagger => Some(&["star_tensor"]),
+        RewriteRule::TensorAssoc => Some(&["tensor_assoc"]),
+        RewriteRule::MultiplyZero => Some(&["mul_zero", "zero_mul"]),
+        RewriteRule::AddZero => Some(&["add_zero", "zero_add"]),
+        RewriteRule::MultiplyIdentity => Some(&["mul_one", "one_mul"]),
+        _ => None,
+    }
+}
+
+fn lean_tactic(rule: Option<&RewriteRule>) -> String {
+    match rule {
+        None | Some(RewriteRule::Canonicalize) => "qte_simp".to_string(),
+        Some(rule) => match axioms(rule) {
+            Some([axiom]) => format!("rw [QteAlgebra.{}]", axiom),
+            Some(axioms) => {
+                let alternatives: Vec<String> = axioms
+                    .iter()
+                    .map(|a| format!("rw [QteAlgebra.{}]", a))
+                    .collect();
+                format!("first | {}", alternatives.join(" | "))
+            }
+            None => "sorry".to_string(),
+        },
+    }
+}
+
+fn coq_tactic(rule: Option<&RewriteRule>) -> String {
+    match rule {
+        None | Some(RewriteRule::Canonicalize) => "qte_simp".to_string(),
+        Some(rule) => match axioms(rule) {
+            Some(axioms) => {
+                let alternatives: Vec<String> = axioms
+                    .iter()
+                    .map(|a| format!("rewrite {}; reflexivity", a))
+                    .collect();
+                format!("first [ {} ]", alternatives.join(" | "))
+            }
+            None => "admit".to_string(),
+        },
+    }
+}
+
+/// `ty -> ty -> ... -> ty` for a function of `arity` operator arguments
+fn arrow(ty: &str, arity: usize, sep: &str) -> String {
+    vec![ty; arity + 1].join(sep)
+}
+
+/// Prints expressions in the syntax both languages share, collecting the
+/// free variables (with their arities) the theorem must bind
+#[derive(Default)]
+struct TermPrinter {
+    variables: BTreeMap<String, usize>,
+}
+
+impl TermPrinter {
+    fn term(&mut self, expr: &Expr) -> Result<String> {
+        Ok(match expr {
+            Expr::Number(x) if *x == 0.0 => "zero".to_string(),
+            Expr::Number(x) if *x == 1.0 => "one".to_string(),
+            Expr::Number(x) => self.variable(format!("c_{}", x), 0),
+            Expr::ComplexNumber(z) => self.variable(format!("c_{}_{}i", z.re, z.im), 0),
+            Expr::Identifier(name) if name == "I" || name == "identity" => "one".to_string(),
+            Expr::Identifier(name) => self.variable(name.clone(), 0),
+            Expr::Add(a, b) => format!("{} + {}", self.operand(a)?, self.operand(b)?),
+            Expr::Sub(a, b) => format!("{} - {}", self.operand(a)?, self.operand(b)?),
+            // The parser's encoding of unary minus
+            Expr::Mul(a, b) if **a == Expr::Number(-1.0) => format!("-{}", self.argument(b)?),
+            Expr::Mul(a, b) => format!("{} * {}", self.operand(a)?, self.operand(b)?),
+            Expr::Div(a, b) => self.apply("div", &[a, b])?,
+            Expr::Pow(a, b) => self.apply("pow", &[a, b])?,
+            Expr::Dagger(a) => self.apply("star", &[a])?,
+            Expr::Trace(a) => self.apply("tr", &[a])?,
+            Expr::Tensor(a, b) => self.apply("tensor", &[a, b])?,
+            Expr::Commutator(a, b) => self.apply("comm", &[a, b])?,
+            Expr::AntiCommutator(a, b) => self.apply("acomm", &[a, b])?,
+            Expr::Expm(a) => self.apply("expm", &[a])?,
+            Expr::Sqrt(a) => self.apply("sqrt", &[a])?,
+            Expr::Sin(a) => self.apply("sin", &[a])?,
+            Expr::Cos(a) => self.apply("cos", &[a])?,
+            Expr::Exp(a) => self.apply("exp", &[a])?,
+            Expr::FuncCall { name, args } => {
+                let f = self.variable(format!("f_{}", name), args.len());
+                let args: Vec<&Expr> = args.iter().collect();
+                self.apply(&f, &args)?
+            }
+            Expr::Matrix(_) | Expr::Vector(_) => {
+                return Err(EngineError::Unsupported(
+                    "Matrix and vector literals cannot be exported to a proof assistant"
+                        .to_string(),
+                ))
+            }
+        })
+    }
+
+    /// An operand of `+`, `-` or `*`, parenthesised when itself one of
+    /// them; applications bind tighter
+    fn operand(&mut self, expr: &Expr) -> Result<String> {
+        let term = self.term(expr)?;
+        Ok(match expr {
+            Expr::Add(..) | Expr::Sub(..) | Expr::Mul(..) => format!("({})", term),
+            _ => term,
+        })
+    }
+
+    /// An argument of an application, parenthesised unless atomic
+    fn argument(&mut self, expr: &Expr) -> Result<String> {
+        let term = self.term(expr)?;
+        Ok(if term.contains(' ') {
+            format!("({})", term)
+        } else {
+            term
+        })
+    }
+
+    fn apply(&mut self, function: &str, args: &[&Expr]) -> Result<String> {
+        let mut term = function.to_string();
+        for arg in args {
+            term.push(' ');
+            term.push_str(&self.argument(arg)?);
+        }
+        Ok(term)
+    }
+
+    fn variable(&mut self, name: String, arity: usize) -> String {
+        let mut name: String = name
+            .chars()
+            .map(|c| match c {
+                '-' => 'm',
+                c if c.is_ascii_alphanumeric() => c,
+                _ => '_',
+            })
+            .collect();
+        if RESERVED.contains(&name.as_str()) || !name.starts_with(|c: char| c.is_alphabetic()) {
+            name = format!("v_{}", name);
+        }
+        self.variables.insert(name.clone(), arity);
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::{Certificate, ProofStep};
+
+    fn id(name: &str) -> Expr {
+        Expr::Identifier(name.to_string())
+    }
+
+    fn dagger(e: Expr) -> Expr {
+        Expr::Dagger(Box::new(e))
+    }
+
+    fn step(rule: RewriteRule, before: Expr, after: Expr) -> ProofStep {
+        ProofStep {
+            justification: rule.description(),
+            rule,
+            before,
+            after,
+        }
+    }
+
+    fn proof(lhs: Expr, rhs: Expr, steps: Vec<ProofStep>) -> Proof {
+        Proof {
+            statement: Statement::Identity { lhs, rhs },
+            steps,
+            assumptions_used: vec![],
+            certificate: Certificate {
+                hash: "0123456789abcdef".to_string(),
+                timestamp: chrono::Utc::now(),
+                engine_version: crate::VERSION.to_string(),
+                assumptions: vec![],
+                verification_steps: vec![],
+                statement: None,
+                signature: None,
+            },
+        }
+    }
+
+    /// (A†)† · I = A by dropping the identity, then the double dagger
+    fn double_dagger_proof() -> Proof {
+        let lhs = Expr::Mul(Box::new(dagger(dagger(id("A")))), Box::new(id("I")));
+        proof(
+            lhs.clone(),
+            id("A"),
+            vec![
+                step(RewriteRule::MultiplyIdentity, lhs, dagger(dagger(id("A")))),
+                step(RewriteRule::DaggerDagger, dagger(dagger(id("A"))), id("A")),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_export_lean_chain() {
+        let lean = export_proof(&double_dagger_proof(), ProofAssistant::Lean4).unwrap();
+        assert!(lean.starts_with(LEAN_PRELUDE));
+        assert!(lean.contains(
+            "theorem qte_0123456789ab {α : Type} [QteAlgebra α] (A : α) :\n    star (star A) * one = A := by"
+        ));
+        assert!(lean.contains("  calc star (star A) * one = star (star A) := by first | rw [QteAlgebra.mul_one] | rw [QteAlgebra.one_mul]\n"));
+        assert!(lean.contains("    _ = A := by rw [QteAlgebra.star_star]\n"));
+        assert!(!lean.contains("sorry"));
+    }
+
+    #[test]
+    fn test_export_coq_chain() {
+        let coq = export_proof(&double_dagger_proof(), ProofAssistant::Coq).unwrap();
+        assert!(
+            coq.contains("Theorem qte_0123456789ab : forall (A : Op), star (star A) * one = A.")
+        );
+        assert!(coq.contains("  intros A.\n  transitivity (star (star A)).\n"));
+        assert!(coq.ends_with("  first [ rewrite star_star; reflexivity ].\nQed.\n"));
+    }
+
+    #[test]
+    fn test_export_recorded_certificate() {
+        let proof = double_dagger_proof();
+        let mut certificate = proof.certificate.clone();
+        assert!(export_certificate(&certificate, ProofAssistant::Lean4).is_err());
+
+        certificate.statement = Some(proof.statement.clone());
+        certificate.verification_steps = proof
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| crate::prover::VerificationStep {
+                step_number: i + 1,
+                description: step.justification.clone(),
+                verified: true,
+                step: Some(step.clone()),
+            })
+            .collect();
+        assert_eq!(
+            export_certificate(&certificate, ProofAssistant::Coq).unwrap(),
+            export_proof(&proof, ProofAssistant::Coq).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_uncovered_rules_are_left_open() {
+        let sq = Expr::Mul(Box::new(id("X")), Box::new(id("X")));
+        let pauli = proof(
+            sq.clone(),
+            id("I"),
+            vec![step(RewriteRule::PauliSquare, sq, id("I"))],
+        );
+        assert!(export_proof(&pauli, ProofAssistant::Lean4)
+            .unwrap()
+            .contains("  calc X * X = one := by sorry\n"));
+        assert!(export_proof(&pauli, ProofAssistant::Coq)
+            .unwrap()
+            .ends_with("  admit.\nAdmitted.\n"));
+
+        let matrix = Expr::Matrix(crate::ast::MatrixLiteral { rows: vec![] });
+        let literal = proof(matrix.clone(), matrix, vec![]);
+        assert!(export_proof(&literal, ProofAssistant::Lean4).is_err());
+
+        // Tr(AB) = Tr(BA) and [A, B] = -[B, A] are axioms
+        let ab = Expr::Trace(Box::new(Expr::Mul(Box::new(id("A")), Box::new(id("B")))));
+        let ba = RewriteRule::TraceCyclic.apply(&ab).unwrap();
+        let cyclic = proof(ab.clone(), ba.clone(), vec![step(RewriteRule::TraceCyclic, ab, ba)]);
+        assert!(export_proof(&cyclic, ProofAssistant::Lean4)
+            .unwrap()
+            .contains("  calc tr (A * B) = tr (B * A) := by rw [QteAlgebra.tr_mul_comm]\n"));
+        let comm = Expr::Commutator(Box::new(id("A")), Box::new(id("B")));
+        let anti = RewriteRule::CommutatorAnti.apply(&comm).unwrap();
+        let flipped = proof(comm.clone(), anti.clone(), vec![step(RewriteRule::CommutatorAnti, comm, anti)]);
+        assert!(export_proof(&flipped, ProofAssistant::Coq)
+            .unwrap()
+            .contains("forall (A : Op) (B : Op), comm A B = -(comm B A)."));
+        assert_eq!(
+            ProofAssistant::from_path("identity.v"),
+            Some(ProofAssistant::Coq)
+        );
+    }
+}
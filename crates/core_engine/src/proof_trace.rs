@@ -0,0 +1,319 @@
+//! Step-by-step proof traces for teaching
+//!
+//! A [`ProofTrace`] lays out how the prover reached its verdict: every
+//! rewrite step with its rule, a plain-text justification, the law applied
+//! and the expressions before and after it, all rendered with
+//! [`Expr::to_latex`]. Traces serialize to JSON for tooling, and
+//! [`ProofTrace::to_latex`] turns them into an `align*` derivation to paste
+//! into lecture notes.
+
+use crate::ast::Expr;
+use crate::prover::{Counterexample, ProofResult, RewriteRule, Statement};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How a statement was settled, and each step that got there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofTrace {
+    /// The statement, as LaTeX
+    pub statement: String,
+    pub outcome: TraceOutcome,
+    pub steps: Vec<TraceStep>,
+    /// Assumptions the proof relied on, as LaTeX
+    pub assumptions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TraceOutcome {
+    Proven {
+        certificate: String,
+    },
+    Refuted {
+        params: BTreeMap<String, f64>,
+        difference_norm: f64,
+    },
+    Undecided {
+        reason: String,
+    },
+}
+
+/// One rewrite of the derivation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub number: usize,
+    pub rule: String,
+    pub justification: String,
+    /// The law the rule applies, as LaTeX
+    pub law: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl ProofTrace {
+    /// Trace of `result`, the prover's verdict on `statement`
+    pub fn new(statement: &Statement, result: &ProofResult) -> Self {
+        let (outcome, steps, assumptions) = match result {
+            ProofResult::Proven(proof) => {
+                let steps = proof
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .map(|(i, step)| TraceStep {
+                        number: i + 1,
+                        rule: format!("{:?}", step.rule),
+                        justification: step.justification.clone(),
+                        law: law_latex(&step.rule),
+                        before: step.before.to_latex(),
+                        after: step.after.to_latex(),
+                    })
+                    .collect();
+                let assumptions = proof
+                    .assumptions_used
+                    .iter()
+                    .map(|a| assumption_latex(&a.name, &a.kind))
+                    .collect();
+                let outcome = TraceOutcome::Proven {
+                    certificate: proof.certificate.hash.clone(),
+                };
+                (outcome, steps, assumptions)
+            }
+            ProofResult::Refuted(Counterexample {
+                params,
+                difference_norm,
+                ..
+            }) => {
+                let outcome = TraceOutcome::Refuted {
+                    params: params.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+                    difference_norm: *difference_norm,
+                };
+                (outcome, Vec::new(), Vec::new())
+            }
+            ProofResult::Unknown(reason) => {
+                let outcome = TraceOutcome::Undecided {
+                    reason: format!("{:?}", reason),
+                };
+                (outcome, Vec::new(), Vec::new())
+            }
+        };
+
+        ProofTrace {
+            statement: statement_latex(statement),
+            outcome,
+            steps,
+            assumptions,
+        }
+    }
+
+    /// The derivation as a LaTeX `align*` block, one rewrite per line with
+    /// its law on the right, preceded by the statement and followed by the
+    /// verdict
+    pub fn to_latex(&self) -> String {
+        let mut out = format!("\\noindent\\textbf{{Claim.}} ${}$\n\n", self.statement);
+        if !self.assumptions.is_empty() {
+            out.push_str(&format!(
+                "\\noindent\\textbf{{Assuming}} ${}$.\n\n",
+                self.assumptions.join(",\\ ")
+            ));
+        }
+
+        if let Some(first) = self.steps.first() {
+            // Steps may apply to a rearranged form of the previous result
+            let mut lines = Vec::new();
+            let mut current = &first.before;
+            for step in &self.steps {
+                if &step.before != current {
+                    lines.push((&step.before, "\\text{rearranging}".to_string()));
+                }
+                lines.push((
+                    &step.after,
+                    format!("\\text{{({})}}\\ {}", step.number, step.law),
+                ));
+                current = &step.after;
+            }
+            let body: Vec<String> = lines
+                .iter()
+                .enumerate()
+                .map(|(i, (rhs, note))| {
+                    let lhs = if i == 0 { first.before.as_str() } else { "" };
+                    format!("  {} &= {} && {}", lhs, rhs, note)
+                })
+                .collect();
+            out.push_str(&format!(
+                "\\begin{{align*}}\n{}\n\\end{{align*}}\n\n",
+                body.join(" \\\\\n")
+            ));
+        }
+
+        let verdict = match &self.outcome {
+            TraceOutcome::Proven { certificate } => format!(
+                "\\noindent\\textbf{{Proven}} in {} steps (certificate \\texttt{{{}}}).\n",
+                self.steps.len(),
+                certificate
+            ),
+            TraceOutcome::Refuted {
+                params,
+                difference_norm,
+            } => {
+                let values: Vec<String> = params
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", latex_name(name), value))
+                    .collect();
+                format!(
+                    "\\noindent\\textbf{{Refuted}}: at ${}$ the two sides differ by ${:.3e}$ in norm.\n",
+                    if values.is_empty() { "\\text{any values}".to_string() } else { values.join(",\\ ") },
+                    difference_norm
+                )
+            }
+            TraceOutcome::Undecided { reason } => {
+                format!("\\noindent\\textbf{{Undecided}} ({}).\n", reason)
+            }
+        };
+        out.push_str(&verdict);
+        out
+    }
+}
+
+fn statement_latex(statement: &Statement) -> String {
+    match statement {
+        Statement::Identity { lhs, rhs } => format!("{} = {}", lhs.to_latex(), rhs.to_latex()),
+        Statement::Property { kind, expr } => {
+            format!("{} \\text{{ is {:?}}}", expr.to_latex(), kind)
+        }
+    }
+}
+
+fn assumption_latex(name: &str, kind: &crate::ast::AssumptionKind) -> String {
+    use crate::ast::{AssumptionKind, Sign};
+    let name = latex_name(name);
+    match kind {
+        AssumptionKind::Range { min, max } => match (min, max) {
+            (Some(lo), Some(hi)) => format!("{} \\le {} \\le {}", lo, name, hi),
+            (Some(lo), None) => format!("{} \\ge {}", name, lo),
+            (None, Some(hi)) => format!("{} \\le {}", name, hi),
+            (None, None) => format!("{} \\in \\mathbb{{R}}", name),
+        },
+        AssumptionKind::Sign(Sign::Positive) => format!("{} > 0", name),
+        AssumptionKind::Sign(Sign::Nonnegative) => format!("{} \\ge 0", name),
+        AssumptionKind::Sign(Sign::Negative) => format!("{} < 0", name),
+        AssumptionKind::Sign(Sign::Nonpositive) => format!("{} \\le 0", name),
+        AssumptionKind::Sign(Sign::Nonzero) => format!("{} \\neq 0", name),
+        AssumptionKind::Integer => format!("{} \\in \\mathbb{{Z}}", name),
+        AssumptionKind::Equation { lhs, rhs } => format!("{} = {}", lhs.to_latex(), rhs.to_latex()),
+    }
+}
+
+fn latex_name(name: &str) -> String {
+    Expr::Identifier(name.to_string()).to_latex()
+}
+
+/// The law behind `rule`, for the rules with a closed form
+fn law_latex(rule: &RewriteRule) -> String {
+    let law = match rule {
+        RewriteRule::DaggerDagger => r"\left(A^\dagger\right)^\dagger = A",
+        RewriteRule::DaggerSum => r"\left(A + B\right)^\dagger = A^\dagger + B^\dagger",
+        RewriteRule::DaggerProduct => r"\left(A B\right)^\dagger = B^\dagger A^\dagger",
+        RewriteRule::DaggerScalar => r"\left(c A\right)^\dagger = \bar{c} A^\dagger",
+        RewriteRule::TraceCyclic => {
+            r"\operatorname{Tr}\left(A B\right) = \operatorname{Tr}\left(B A\right)"
+        }
+        RewriteRule::TraceLinear => {
+            r"\operatorname{Tr}\left(A + B\right) = \operatorname{Tr}\left(A\right) + \operatorname{Tr}\left(B\right)"
+        }
+        RewriteRule::CommutatorSelf => r"\left[A, A\right] = 0",
+        RewriteRule::CommutatorAnti => r"\left[A, B\right] = -\left[B, A\right]",
+        RewriteRule::JacobiIdentity => {
+            r"\left[A, \left[B, C\right]\right] + \left[B, \left[C, A\right]\right] + \left[C, \left[A, B\right]\right] = 0"
+        }
+        RewriteRule::TensorDistribute => {
+            r"A \otimes \left(B + C\right) = A \otimes B + A \otimes C"
+        }
+        RewriteRule::TensorDagger => {
+            r"\left(A \otimes B\right)^\dagger = A^\dagger \otimes B^\dagger"
+        }
+        RewriteRule::TensorAssoc => {
+            r"\left(A \otimes B\right) \otimes C = A \otimes \left(B \otimes C\right)"
+        }
+        RewriteRule::PauliSquare => r"\sigma_{i}^{2} = I",
+        RewriteRule::PauliCommutator => r"\left[P, Q\right] = 2 P Q \text{ for } P Q = -Q P",
+        RewriteRule::PauliAnticommutator => r"\left\{P, Q\right\} = 2 P Q \text{ for } P Q = Q P",
+        RewriteRule::MultiplyZero => r"0 A = 0",
+        RewriteRule::AddZero => r"A + 0 = A",
+        RewriteRule::MultiplyIdentity => r"I A = A",
+        RewriteRule::SqrtSquare => r"\sqrt{x^{2}} = x \text{ for } x \ge 0",
+        RewriteRule::SqrtProduct => r"\sqrt{a b} = \sqrt{a} \sqrt{b} \text{ for } a, b \ge 0",
+        RewriteRule::Hypothesis(name) => return format!("\\text{{assumption {}}}", name),
+        other => return format!("\\text{{{:?}}}", other),
+    };
+    law.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::{Certificate, Proof, ProofStep, Reason};
+
+    fn id(name: &str) -> Box<Expr> {
+        Box::new(Expr::Identifier(name.to_string()))
+    }
+
+    #[test]
+    fn test_trace_of_proof() {
+        let before = Expr::Dagger(Box::new(Expr::Mul(id("A"), id("B"))));
+        let after = Expr::Mul(
+            Box::new(Expr::Dagger(id("B"))),
+            Box::new(Expr::Dagger(id("A"))),
+        );
+        let statement = Statement::Identity {
+            lhs: before.clone(),
+            rhs: after.clone(),
+        };
+        let proof = Proof {
+            statement: statement.clone(),
+            steps: vec![ProofStep {
+                rule: RewriteRule::DaggerProduct,
+                before,
+                after,
+                justification: RewriteRule::DaggerProduct.description(),
+            }],
+            assumptions_used: vec![],
+            certificate: Certificate {
+                hash: "abc123".to_string(),
+                timestamp: chrono::Utc::now(),
+                engine_version: crate::VERSION.to_string(),
+                assumptions: vec![],
+                verification_steps: vec![],
+                statement: None,
+                signature: None,
+            },
+        };
+
+        let trace = ProofTrace::new(&statement, &ProofResult::Proven(proof));
+        assert_eq!(trace.steps.len(), 1);
+        assert_eq!(trace.steps[0].before, r"\left(A B\right)^\dagger");
+        assert_eq!(trace.steps[0].after, r"B^\dagger A^\dagger");
+        assert_eq!(trace.steps[0].justification, "(AB)† = B†A†");
+
+        let latex = trace.to_latex();
+        assert!(latex.contains(r"\begin{align*}"));
+        assert!(latex.contains(
+            r"&= B^\dagger A^\dagger && \text{(1)}\ \left(A B\right)^\dagger = B^\dagger A^\dagger"
+        ));
+        assert!(latex.contains(r"\textbf{Proven} in 1 steps (certificate \texttt{abc123})"));
+
+        let json = serde_json::to_string(&trace).unwrap();
+        let parsed: ProofTrace = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.steps[0].rule, "DaggerProduct");
+    }
+
+    #[test]
+    fn test_trace_of_undecided_statement() {
+        let statement = Statement::Identity {
+            lhs: Expr::Identifier("A".to_string()),
+            rhs: Expr::Identifier("B".to_string()),
+        };
+        let trace = ProofTrace::new(&statement, &ProofResult::Unknown(Reason::Exhausted));
+        assert!(trace.steps.is_empty());
+        assert_eq!(trace.statement, "A = B");
+        assert!(trace.to_latex().contains(r"\textbf{Undecided} (Exhausted)"));
+    }
+}
@@ -0,0 +1,287 @@
+//! Cache of unitary propagators
+//!
+//! Unitary evolution advances the state by `U(dt) = exp(-i H dt)` at each
+//! step of its time grid, and computing `U` is the costly part of a step.
+//! Grids are usually uniform, so a [`PropagatorCache`] keeps the propagators
+//! computed for each Hamiltonian node and step size: later steps, the
+//! segments between channels, other experiments and other sweep points with
+//! the same Hamiltonian reuse them.
+//!
+//! Entries are keyed by the node, the step size and a fingerprint of the
+//! Hamiltonian's entries, since a sweep point or noise realisation may give
+//! one node another value. Step sizes within a relative 2^-40 of each other,
+//! as on a grid `t0 + k dt` in floating point, share a propagator.
+//!
+//! Besides [`PropagatorCache::stats`], uses are recorded in the engine's
+//! metrics: `propagator_cache.hit` counts reused propagators and
+//! `propagator_cache.miss` times the ones computed.
+
+use crate::error::Result;
+use crate::ir::NodeId;
+use crate::kernels_cpu;
+use crate::logging;
+use ndarray::Array2;
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Entries a cache holds unless configured otherwise
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// Node, step size key and fingerprint of the Hamiltonian
+type Key = (NodeId, u64, u64);
+
+/// Propagators shared by the evolutions of an executor, and of the
+/// executors of a batch; safe to use from several threads
+#[derive(Debug)]
+pub struct PropagatorCache {
+    /// Most entries held; the oldest is evicted beyond it, and 0 disables
+    /// caching
+    capacity: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    propagators: HashMap<Key, Arc<Array2<Complex64>>>,
+    /// Keys in insertion order, for eviction
+    order: VecDeque<Key>,
+}
+
+/// Use counts of a [`PropagatorCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PropagatorCacheStats {
+    /// Steps whose propagator was already computed
+    pub hits: u64,
+    /// Propagators computed
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl PropagatorCacheStats {
+    /// Fraction of steps that reused a propagator, 0 before any step
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+impl PropagatorCache {
+    pub fn new(capacity: usize) -> Self {
+        PropagatorCache {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Propagators of the Hamiltonian `hamiltonian` of node `node`, for one
+    /// evolution
+    pub fn propagators<'a>(
+        &'a self,
+        node: NodeId,
+        hamiltonian: &'a Array2<Complex64>,
+    ) -> Propagators<'a> {
+        Propagators {
+            cache: self,
+            node,
+            hamiltonian,
+            fingerprint: fingerprint(hamiltonian),
+            last: None,
+            hits: 0,
+        }
+    }
+
+    pub fn stats(&self) -> PropagatorCacheStats {
+        PropagatorCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().propagators.len(),
+        }
+    }
+
+    /// Drop every entry, keeping the counts
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.propagators.clear();
+        entries.order.clear();
+    }
+
+    fn lookup(&self, key: &Key) -> Option<Arc<Array2<Complex64>>> {
+        self.entries.lock().unwrap().propagators.get(key).cloned()
+    }
+
+    fn insert(&self, key: Key, propagator: Arc<Array2<Complex64>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.propagators.insert(key, propagator).is_none() {
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.propagators.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl Default for PropagatorCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// The propagators of one Hamiltonian, looked up by step size
+///
+/// Consecutive steps of one size skip the shared cache altogether.
+pub struct Propagators<'a> {
+    cache: &'a PropagatorCache,
+    node: NodeId,
+    hamiltonian: &'a Array2<Complex64>,
+    fingerprint: u64,
+    last: Option<(u64, Arc<Array2<Complex64>>)>,
+    /// Reported to the metrics when dropped, rather than step by step
+    hits: u64,
+}
+
+impl Propagators<'_> {
+    /// `exp(-i H dt)`
+    pub fn get(&mut self, dt: f64) -> Result<Arc<Array2<Complex64>>> {
+        let step = step_key(dt);
+        if let Some(u) = self
+            .last
+            .as_ref()
+            .filter(|(last, _)| *last == step)
+            .map(|(_, u)| Arc::clone(u))
+        {
+            self.hit();
+            return Ok(u);
+        }
+
+        let key = (self.node, step, self.fingerprint);
+        let u = match self.cache.lookup(&key) {
+            Some(u) => {
+                self.hit();
+                u
+            }
+            None => {
+                // Computed outside the lock, so other threads carry on
+                let start = Instant::now();
+                let u = Arc::new(kernels_cpu::matrix_exp(
+                    &(self.hamiltonian * Complex64::new(0.0, -dt)),
+                )?);
+                logging::record_metric("propagator_cache.miss", start.elapsed());
+                self.cache.misses.fetch_add(1, Ordering::Relaxed);
+                self.cache.insert(key, Arc::clone(&u));
+                u
+            }
+        };
+        self.last = Some((step, Arc::clone(&u)));
+        Ok(u)
+    }
+
+    fn hit(&mut self) {
+        self.cache.hits.fetch_add(1, Ordering::Relaxed);
+        self.hits += 1;
+    }
+}
+
+impl Drop for Propagators<'_> {
+    fn drop(&mut self) {
+        if self.hits > 0 {
+            logging::record_count("propagator_cache.hit", self.hits);
+        }
+    }
+}
+
+/// `dt` rounded to 40 bits of mantissa
+fn step_key(dt: f64) -> u64 {
+    const DROPPED: u32 = 12;
+    dt.to_bits().wrapping_add(1 << (DROPPED - 1)) & !((1 << DROPPED) - 1)
+}
+
+fn fingerprint(hamiltonian: &Array2<Complex64>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hamiltonian.dim().hash(&mut hasher);
+    for z in hamiltonian.iter() {
+        z.re.to_bits().hash(&mut hasher);
+        z.im.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sigma_x(scale: f64) -> Array2<Complex64> {
+        let c = |re: f64| Complex64::new(re, 0.0);
+        Array2::from_shape_vec((2, 2), vec![c(0.0), c(scale), c(scale), c(0.0)]).unwrap()
+    }
+
+    #[test]
+    fn test_propagators_are_shared_by_node_step_and_value() {
+        let cache = PropagatorCache::new(8);
+        let h = sigma_x(1.0);
+        let times: Vec<f64> = (0..=10).map(|k| k as f64 * 0.1).collect();
+
+        let mut propagators = cache.propagators(0, &h);
+        for pair in times.windows(2) {
+            propagators.get(pair[1] - pair[0]).unwrap();
+        }
+        // The grid's steps differ in their last bits, but share one entry
+        assert_eq!(
+            cache.stats(),
+            PropagatorCacheStats {
+                hits: 9,
+                misses: 1,
+                entries: 1
+            }
+        );
+
+        // A second evolution of the same Hamiltonian computes nothing
+        let u = cache.propagators(0, &h).get(0.1).unwrap();
+        assert_eq!(cache.stats().misses, 1);
+        let expected = kernels_cpu::matrix_exp(&(&h * Complex64::new(0.0, -0.1))).unwrap();
+        assert!((&*u - &expected).iter().all(|d| d.norm() < 1e-12));
+
+        // Another value of the node, or another step, is computed afresh
+        cache.propagators(0, &sigma_x(2.0)).get(0.1).unwrap();
+        cache.propagators(0, &h).get(0.2).unwrap();
+        assert_eq!(cache.stats().misses, 3);
+        assert_eq!(cache.stats().entries, 3);
+    }
+
+    #[test]
+    fn test_capacity_bounds_entries() {
+        let cache = PropagatorCache::new(2);
+        let h = sigma_x(1.0);
+        for k in 1..=3 {
+            cache.propagators(0, &h).get(k as f64).unwrap();
+        }
+        assert_eq!(cache.stats().entries, 2);
+        // The oldest was evicted
+        cache.propagators(0, &h).get(1.0).unwrap();
+        assert_eq!(cache.stats().misses, 4);
+
+        let disabled = PropagatorCache::new(0);
+        disabled.propagators(0, &h).get(1.0).unwrap();
+        disabled.propagators(0, &h).get(1.0).unwrap();
+        assert_eq!(disabled.stats().entries, 0);
+        assert_eq!(disabled.stats().misses, 2);
+    }
+}
@@ -0,0 +1,444 @@
+//! Result provenance
+//!
+//! Every recorded result is linked to what produced it: the job, the SHA-256
+//! hashes of its input data, the template it instantiated (id and version),
+//! the engine version, and any proof certificates it relies on. Records
+//! accumulate in a [`ProvenanceGraph`], persisted as JSON, and
+//! [`ProvenanceGraph::lineage`] extracts everything upstream of one result,
+//! so a published figure can be traced back to its exact inputs.
+//!
+//! Node ids are prefixed by their kind (`result:`, `job:`, `data:`,
+//! `template:`, `engine:`, `certificate:`). Data, templates, engines and
+//! certificates are identified by content, so results that share an input
+//! share its node.
+
+use crate::error::{EngineError, Result};
+use crate::templates::Template;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::Path;
+
+/// Default location of the provenance store, relative to the working
+/// directory
+pub const DEFAULT_STORE: &str = ".qte/provenance.json";
+
+/// Hex-encoded SHA-256 of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProvenanceNode {
+    Result {
+        description: String,
+    },
+    Job {
+        kind: String,
+        parameters: BTreeMap<String, f64>,
+        recorded_at: DateTime<Utc>,
+    },
+    Data {
+        name: String,
+        sha256: String,
+    },
+    Template {
+        id: String,
+        version: String,
+    },
+    Engine {
+        version: String,
+    },
+    Certificate {
+        hash: String,
+    },
+}
+
+impl ProvenanceNode {
+    /// One-line human-readable summary
+    pub fn label(&self) -> String {
+        match self {
+            ProvenanceNode::Result { description } => description.clone(),
+            ProvenanceNode::Job {
+                kind, recorded_at, ..
+            } => format!("{} at {}", kind, recorded_at.to_rfc3339()),
+            ProvenanceNode::Data { name, sha256 } => format!("{} (sha256 {})", name, sha256),
+            ProvenanceNode::Template { id, version } => format!("template {} v{}", id, version),
+            ProvenanceNode::Engine { version } => format!("engine v{}", version),
+            ProvenanceNode::Certificate { hash } => format!("certificate {}", hash),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    /// Result → job
+    ProducedBy,
+    /// Job → data
+    Read,
+    /// Job → template
+    Instantiated,
+    /// Job → engine
+    RanOn,
+    /// Result → certificate
+    ReliesOn,
+}
+
+impl std::fmt::Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Relation::ProducedBy => "produced by",
+            Relation::Read => "read",
+            Relation::Instantiated => "instantiated",
+            Relation::RanOn => "ran on",
+            Relation::ReliesOn => "relies on",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub relation: Relation,
+}
+
+/// Everything known about how one result was produced
+#[derive(Debug, Clone)]
+pub struct ProvenanceRecord {
+    result_id: String,
+    description: String,
+    job_id: String,
+    job_kind: String,
+    parameters: BTreeMap<String, f64>,
+    inputs: Vec<(String, String)>,
+    template: Option<(String, String)>,
+    certificates: Vec<String>,
+}
+
+impl ProvenanceRecord {
+    /// Record for result `result_id`, produced by job `job_id` of the given
+    /// kind (e.g. `"simulate"`)
+    pub fn new(
+        result_id: impl Into<String>,
+        job_id: impl Into<String>,
+        job_kind: impl Into<String>,
+    ) -> Self {
+        let result_id = result_id.into();
+        ProvenanceRecord {
+            description: result_id.clone(),
+            result_id,
+            job_id: job_id.into(),
+            job_kind: job_kind.into(),
+            parameters: BTreeMap::new(),
+            inputs: Vec::new(),
+            template: None,
+            certificates: Vec::new(),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_parameters(mut self, parameters: &HashMap<String, f64>) -> Self {
+        self.parameters
+            .extend(parameters.iter().map(|(k, v)| (k.clone(), *v)));
+        self
+    }
+
+    /// Input data given in memory, hashed now
+    pub fn with_input(mut self, name: impl Into<String>, bytes: &[u8]) -> Self {
+        self.inputs.push((name.into(), sha256_hex(bytes)));
+        self
+    }
+
+    /// Input file, hashed now
+    pub fn with_input_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        Ok(self.with_input(path.display().to_string(), &bytes))
+    }
+
+    pub fn with_template(mut self, template: &Template) -> Self {
+        self.template = Some((template.id.clone(), template.version.clone()));
+        self
+    }
+
+    pub fn with_certificate(mut self, hash: impl Into<String>) -> Self {
+        self.certificates.push(hash.into());
+        self
+    }
+}
+
+/// Results and their inputs, linked by [`Relation`]s
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceGraph {
+    nodes: BTreeMap<String, ProvenanceNode>,
+    edges: BTreeSet<Edge>,
+}
+
+impl ProvenanceGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a store written by [`save`](Self::save); a missing file is an
+    /// empty graph
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(serde_json::from_str(&text)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Add a record, returning the id of its result node
+    pub fn record(&mut self, record: &ProvenanceRecord) -> String {
+        let result = format!("result:{}", record.result_id);
+        let job = format!("job:{}", record.job_id);
+        self.nodes.insert(
+            result.clone(),
+            ProvenanceNode::Result {
+                description: record.description.clone(),
+            },
+        );
+        self.nodes.insert(
+            job.clone(),
+            ProvenanceNode::Job {
+                kind: record.job_kind.clone(),
+                parameters: record.parameters.clone(),
+                recorded_at: Utc::now(),
+            },
+        );
+        self.link(&result, &job, Relation::ProducedBy);
+
+        for (name, sha256) in &record.inputs {
+            let data = format!("data:{}", sha256);
+            self.nodes
+                .entry(data.clone())
+                .or_insert_with(|| ProvenanceNode::Data {
+                    name: name.clone(),
+                    sha256: sha256.clone(),
+                });
+            self.link(&job, &data, Relation::Read);
+        }
+        if let Some((id, version)) = &record.template {
+            let template = format!("template:{}@{}", id, version);
+            self.nodes.insert(
+                template.clone(),
+                ProvenanceNode::Template {
+                    id: id.clone(),
+                    version: version.clone(),
+                },
+            );
+            self.link(&job, &template, Relation::Instantiated);
+        }
+        let engine = format!("engine:{}", crate::VERSION);
+        self.nodes.insert(
+            engine.clone(),
+            ProvenanceNode::Engine {
+                version: crate::VERSION.to_string(),
+            },
+        );
+        self.link(&job, &engine, Relation::RanOn);
+        for hash in &record.certificates {
+            let certificate = format!("certificate:{}", hash);
+            self.nodes.insert(
+                certificate.clone(),
+                ProvenanceNode::Certificate { hash: hash.clone() },
+            );
+            self.link(&result, &certificate, Relation::ReliesOn);
+        }
+
+        result
+    }
+
+    fn link(&mut self, from: &str, to: &str, relation: Relation) {
+        self.edges.insert(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            relation,
+        });
+    }
+
+    pub fn node(&self, id: &str) -> Option<&ProvenanceNode> {
+        self.nodes.get(id)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (&String, &ProvenanceNode)> {
+        self.nodes.iter()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter()
+    }
+
+    /// Edges leading from `id` to its inputs
+    pub fn edges_from<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Edge> + 'a {
+        self.edges.iter().filter(move |edge| edge.from == id)
+    }
+
+    /// Id of the result named by `query`: a node id, a result id, or a
+    /// unique prefix of a result id
+    pub fn resolve(&self, query: &str) -> Result<String> {
+        if self.nodes.contains_key(query) {
+            return Ok(query.to_string());
+        }
+        let prefix = format!("result:{}", query);
+        let matches: Vec<&String> = self
+            .nodes
+            .range(prefix.clone()..)
+            .take_while(|(id, _)| id.starts_with(&prefix))
+            .map(|(id, _)| id)
+            .collect();
+        match matches.as_slice() {
+            [id] => Ok((*id).clone()),
+            [] => Err(EngineError::validation_error(format!(
+                "No result '{}' in the provenance store",
+                query
+            ))),
+            _ => Err(EngineError::validation_error(format!(
+                "'{}' matches {} results; give more of the id",
+                query,
+                matches.len()
+            ))),
+        }
+    }
+
+    /// The subgraph of `id` and everything upstream of it
+    pub fn lineage(&self, id: &str) -> Result<ProvenanceGraph> {
+        if !self.nodes.contains_key(id) {
+            return Err(EngineError::validation_error(format!(
+                "No node '{}' in the provenance store",
+                id
+            )));
+        }
+
+        let mut lineage = ProvenanceGraph::new();
+        let mut pending = VecDeque::from([id.to_string()]);
+        while let Some(current) = pending.pop_front() {
+            if lineage.nodes.contains_key(&current) {
+                continue;
+            }
+            lineage
+                .nodes
+                .insert(current.clone(), self.nodes[&current].clone());
+            for edge in self.edges_from(&current) {
+                lineage.edges.insert(edge.clone());
+                pending.push_back(edge.to.clone());
+            }
+        }
+        Ok(lineage)
+    }
+
+    /// Graphviz rendering
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph provenance {\n  rankdir=LR;\n");
+        for (id, node) in &self.nodes {
+            dot.push_str(&format!(
+                "  {:?} [label={:?}];\n",
+                id,
+                format!("{}\n{}", id, node.label())
+            ));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  {:?} -> {:?} [label={:?}];\n",
+                edge.from,
+                edge.to,
+                edge.relation.to_string()
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::TemplateRegistry;
+
+    fn graph() -> ProvenanceGraph {
+        let registry = TemplateRegistry::new();
+        let rabi = registry.get("rabi").unwrap();
+        let mut graph = ProvenanceGraph::new();
+        graph.record(
+            &ProvenanceRecord::new("a1b2", "job-1", "fit")
+                .with_parameters(&HashMap::from([("omega".to_string(), 1.5)]))
+                .with_input("data.csv", b"t,p\n0,1\n")
+                .with_template(rabi),
+        );
+        graph.record(
+            &ProvenanceRecord::new("a1c3", "job-2", "prove")
+                .with_input("data.csv", b"t,p\n0,1\n")
+                .with_certificate("ffee"),
+        );
+        graph
+    }
+
+    #[test]
+    fn test_lineage_follows_inputs_only() {
+        let graph = graph();
+        let data = format!("data:{}", sha256_hex(b"t,p\n0,1\n"));
+        // Both jobs read the same data
+        assert_eq!(graph.edges().filter(|edge| edge.to == data).count(), 2);
+
+        let lineage = graph.lineage("result:a1b2").unwrap();
+        let ids: Vec<String> = lineage.nodes().map(|(id, _)| id.clone()).collect();
+        let version = TemplateRegistry::new().get("rabi").unwrap().version.clone();
+        assert_eq!(
+            ids,
+            vec![
+                data,
+                format!("engine:{}", crate::VERSION),
+                "job:job-1".to_string(),
+                "result:a1b2".to_string(),
+                format!("template:rabi@{}", version),
+            ]
+        );
+        assert!(lineage.edges().all(|edge| edge.from != "job:job-2"));
+
+        let proof = graph.lineage("result:a1c3").unwrap();
+        assert!(proof
+            .edges()
+            .any(|edge| edge.relation == Relation::ReliesOn && edge.to == "certificate:ffee"));
+    }
+
+    #[test]
+    fn test_store_roundtrip_and_resolve() {
+        let graph = graph();
+        let path =
+            std::env::temp_dir().join(format!("qte-provenance-{}/store.json", std::process::id()));
+        assert_eq!(
+            ProvenanceGraph::load(&path).unwrap(),
+            ProvenanceGraph::new()
+        );
+        graph.save(&path).unwrap();
+        let loaded = ProvenanceGraph::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded, graph);
+
+        assert_eq!(loaded.resolve("a1b").unwrap(), "result:a1b2");
+        assert_eq!(loaded.resolve("result:a1c3").unwrap(), "result:a1c3");
+        assert!(loaded.resolve("a1").is_err());
+        assert!(loaded.resolve("ff").is_err());
+
+        let dot = loaded.lineage("result:a1b2").unwrap().to_dot();
+        assert!(dot.contains("\"result:a1b2\" -> \"job:job-1\" [label=\"produced by\"]"));
+    }
+}
@@ -6,12 +6,17 @@
 //! - Produce verifiable proof traces
 //! - Find counterexamples when proofs fail
 
-use crate::ast::{Expr, Assumption, AssumptionKind, PropertyKind, ProofGoal};
-use crate::error::{EngineError, ProofError};
+use crate::ast::{Expr, Assumption, AssumptionKind, PropertyKind, ProofGoal, Sign};
+use crate::counterexample::{self, Divergence};
+use crate::error::EngineError;
+use crate::expr_arena::{ExprArena, ExprId};
+use crate::operators::{PauliString, PauliSum};
+use crate::signing::{self, CertificateSignature, EngineKey, SignatureStatus};
 use ndarray::Array2;
 use num_complex::Complex64 as C64;
 use serde::{Serialize, Deserialize};
 use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 pub type Result<T> = std::result::Result<T, EngineError>;
@@ -31,6 +36,16 @@ pub struct ProverConfig {
     pub timeout: Duration,
     pub enable_smt: bool,
     pub counterexample_samples: usize,
+    /// Largest expression, in AST nodes, the search will hold; bigger
+    /// inputs are `Unknown(TooLarge)` and bigger rewrites are pruned
+    pub max_expr_size: usize,
+    /// Most expressions one side of the search may hold in its frontier
+    pub max_frontier: usize,
+    /// Seed of the counterexample search; `None` falls back to
+    /// [`crate::rng::seeded`]'s global seed or entropy
+    pub seed: Option<u64>,
+    /// Key signing every certificate; `None` leaves them unsigned
+    pub signing_key: Option<EngineKey>,
 }
 
 impl Default for ProverConfig {
@@ -41,6 +56,10 @@ impl Default for ProverConfig {
             timeout: Duration::from_secs(5),
             enable_smt: false,  // Z3 integration TODO
             counterexample_samples: 100,
+            max_expr_size: 1_000,
+            max_frontier: 10_000,
+            seed: None,
+            signing_key: None,
         }
     }
 }
@@ -57,10 +76,18 @@ impl Prover {
 
     pub fn add_assumption(&mut self, assumption: Assumption) {
         self.assumptions.add(assumption);
+        // Cached results were reached without it
+        self.proof_cache = ProofCache::new();
     }
 
     pub fn prove_identity(&mut self, lhs: &Expr, rhs: &Expr) -> ProofResult {
         let start = Instant::now();
+
+        // Refuse oversized inputs before cloning or hashing them
+        let limit = self.config.max_expr_size;
+        if expr_size(lhs, limit) > limit || expr_size(rhs, limit) > limit {
+            return ProofResult::Unknown(Reason::TooLarge);
+        }
         
         // Check cache
         let cache_key = (lhs.clone(), rhs.clone());
@@ -74,21 +101,23 @@ impl Prover {
 
         // Trivial case: already equal
         if expr_equal(&lhs_canon, &rhs_canon) {
-            let proof = Proof {
-                statement: Statement::Identity {
-                    lhs: lhs.clone(),
-                    rhs: rhs.clone(),
+            let statement = Statement::Identity {
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            };
+            let steps = vec![
+                ProofStep {
+                    rule: RewriteRule::Canonicalize,
+                    before: lhs.clone(),
+                    after: lhs_canon.clone(),
+                    justification: "Canonical form".to_string(),
                 },
-                steps: vec![
-                    ProofStep {
-                        rule: RewriteRule::Canonicalize,
-                        before: lhs.clone(),
-                        after: lhs_canon.clone(),
-                        justification: "Canonical form".to_string(),
-                    },
-                ],
+            ];
+            let proof = Proof {
+                certificate: self.generate_certificate(&[], Some(&statement), &steps),
+                statement,
+                steps,
                 assumptions_used: vec![],
-                certificate: self.generate_certificate(&[]),
             };
             return ProofResult::Proven(proof);
         }
@@ -102,6 +131,12 @@ impl Prover {
         result
     }
 
+    /// Prove the identity a DSL goal amounts to, see [`ProofGoal::identity`]
+    pub fn prove_goal(&mut self, goal: &ProofGoal) -> ProofResult {
+        let (lhs, rhs) = goal.identity();
+        self.prove_identity(&lhs, &rhs)
+    }
+
     pub fn prove_property(&mut self, prop: Property) -> PropertyProof {
         match &prop {
             Property::Hermitian(expr) => self.prove_hermitian(expr),
@@ -114,32 +149,39 @@ impl Prover {
         }
     }
 
+    /// Parameter values at which the two sides differ, shrunk towards
+    /// simple values, with the subexpressions where they first diverge
     pub fn find_counterexample(&self, lhs: &Expr, rhs: &Expr) -> Option<Counterexample> {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+        // Sampled values need not satisfy operator hypotheses, so a
+        // difference at them would refute nothing
+        if self.assumptions.has_hypotheses() {
+            return None;
+        }
+        let mut rng = crate::rng::seeded(self.config.seed);
+        let names = self.counterexample_params(lhs, rhs);
 
         for _ in 0..self.config.counterexample_samples {
-            // Generate random parameter assignment
-            let params = self.generate_random_params(&mut rng);
-            
-            // Evaluate both sides
-            let lhs_val = self.evaluate_expr(lhs, &params);
-            let rhs_val = self.evaluate_expr(rhs, &params);
-
-            if let (Ok(lhs_mat), Ok(rhs_mat)) = (lhs_val, rhs_val) {
-                // Check if they differ
-                let diff = &lhs_mat - &rhs_mat;
-                let norm = diff.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
-                
-                if norm > 1e-6 {
-                    return Some(Counterexample {
-                        params,
-                        lhs_value: MatrixValue::from_array(lhs_mat),
-                        rhs_value: MatrixValue::from_array(rhs_mat),
-                        difference_norm: norm,
-                    });
-                }
+            // Generate random parameter assignment; none exists if the
+            // assumptions cannot be satisfied
+            let sampled = self.generate_random_params(&names, &mut rng)?;
+            if counterexample::refutes(lhs, rhs, &sampled).is_none() {
+                continue;
             }
+
+            let params = counterexample::shrink(
+                &sampled,
+                |params| counterexample::refutes(lhs, rhs, params).is_some(),
+                |name, value| self.assumptions.admits(name, value),
+            );
+            let (lhs_mat, rhs_mat, norm) = counterexample::refutes(lhs, rhs, &params)?;
+            return Some(Counterexample {
+                divergence: counterexample::locate_divergence(lhs, rhs, &params),
+                params,
+                sampled_params: sampled,
+                lhs_value: MatrixValue::from_array(lhs_mat),
+                rhs_value: MatrixValue::from_array(rhs_mat),
+                difference_norm: norm,
+            });
         }
 
         None
@@ -202,41 +244,47 @@ impl Prover {
     fn bidirectional_search(&self, lhs: &Expr, rhs: &Expr, start: Instant) 
         -> ProofResult 
     {
-        let mut lhs_frontier = vec![(lhs.clone(), vec![])];
-        let mut rhs_frontier = vec![(rhs.clone(), vec![])];
-        let mut lhs_visited = HashSet::new();
-        let mut rhs_visited = HashSet::new();
-
-        lhs_visited.insert(expr_hash(lhs));
-        rhs_visited.insert(expr_hash(rhs));
-
-        for depth in 0..self.config.max_depth {
+        // Both directions intern into one arena, so their ids are comparable
+        let mut arena = ExprArena::new();
+        let lhs_id = arena.intern(lhs);
+        let rhs_id = arena.intern(rhs);
+        let mut lhs_frontier = vec![SearchNode { expr: Rc::new(lhs.clone()), id: lhs_id, trace: None }];
+        let mut rhs_frontier = vec![SearchNode { expr: Rc::new(rhs.clone()), id: rhs_id, trace: None }];
+        let mut lhs_visited = HashSet::from([lhs_id]);
+        let mut rhs_visited = HashSet::from([rhs_id]);
+        let mut pruned = false;
+
+        for _ in 0..self.config.max_depth {
             if start.elapsed() > self.config.timeout {
                 return ProofResult::Unknown(Reason::Timeout);
             }
 
             // Expand LHS frontier
-            let new_lhs = self.expand_frontier(&lhs_frontier, &mut lhs_visited);
+            let new_lhs = match self.expand_frontier(&lhs_frontier, &mut arena, &mut lhs_visited, &mut pruned) {
+                Some(frontier) => frontier,
+                None => return ProofResult::Unknown(Reason::TooLarge),
+            };
             
             // Check for intersection with RHS
-            for (expr, steps) in &new_lhs {
-                let hash = expr_hash(expr);
-                if rhs_visited.contains(&hash) {
+            for node in &new_lhs {
+                if rhs_visited.contains(&node.id) {
                     // Found proof!
-                    return self.construct_proof(lhs, rhs, steps);
+                    return self.construct_proof(lhs, rhs, &node.steps());
                 }
             }
 
             lhs_frontier = new_lhs;
 
             // Expand RHS frontier (backward)
-            let new_rhs = self.expand_frontier(&rhs_frontier, &mut rhs_visited);
+            let new_rhs = match self.expand_frontier(&rhs_frontier, &mut arena, &mut rhs_visited, &mut pruned) {
+                Some(frontier) => frontier,
+                None => return ProofResult::Unknown(Reason::TooLarge),
+            };
             
             // Check for intersection with LHS
-            for (expr, steps) in &new_rhs {
-                let hash = expr_hash(expr);
-                if lhs_visited.contains(&hash) {
-                    return self.construct_proof(lhs, rhs, steps);
+            for node in &new_rhs {
+                if lhs_visited.contains(&node.id) {
+                    return self.construct_proof(lhs, rhs, &node.steps());
                 }
             }
 
@@ -250,49 +298,71 @@ impl Prover {
         // No proof found, try counterexample
         if let Some(ce) = self.find_counterexample(lhs, rhs) {
             ProofResult::Refuted(ce)
+        } else if pruned {
+            // The search space was cut short, so "exhausted" would overclaim
+            ProofResult::Unknown(Reason::TooLarge)
         } else {
             ProofResult::Unknown(Reason::Exhausted)
         }
     }
 
+    /// Apply every rule to every frontier node, returning the unvisited
+    /// results, which are interned in `arena`. Rewrites above
+    /// `max_expr_size` are dropped and flagged in `pruned`; `None` means the
+    /// new frontier outgrew `max_frontier`.
     fn expand_frontier(
         &self,
-        frontier: &[(Expr, Vec<ProofStep>)],
-        visited: &mut HashSet<u64>,
-    ) -> Vec<(Expr, Vec<ProofStep>)> {
+        frontier: &[SearchNode],
+        arena: &mut ExprArena,
+        visited: &mut HashSet<ExprId>,
+        pruned: &mut bool,
+    ) -> Option<Vec<SearchNode>> {
+        let limit = self.config.max_expr_size;
+        let hypotheses = self.assumptions.hypotheses();
         let mut new_frontier = Vec::new();
 
-        for (expr, steps) in frontier {
-            for rule in &self.rewrite_rules.rules {
-                if let Some(new_expr) = rule.apply(expr) {
-                    let hash = expr_hash(&new_expr);
-                    if !visited.contains(&hash) {
-                        visited.insert(hash);
-                        let mut new_steps = steps.clone();
-                        new_steps.push(ProofStep {
-                            rule: rule.clone(),
-                            before: expr.clone(),
-                            after: new_expr.clone(),
-                            justification: rule.description(),
+        for node in frontier {
+            for rule in self.rewrite_rules.rules.iter().chain(&hypotheses) {
+                if let Some(new_expr) = rule.apply_under(&node.expr, &self.assumptions) {
+                    if expr_size(&new_expr, limit) > limit {
+                        *pruned = true;
+                        continue;
+                    }
+                    let id = arena.intern(&new_expr);
+                    if visited.insert(id) {
+                        if new_frontier.len() == self.config.max_frontier {
+                            return None;
+                        }
+                        let new_expr = Rc::new(new_expr);
+                        new_frontier.push(SearchNode {
+                            expr: new_expr.clone(),
+                            id,
+                            trace: Some(Rc::new(Trace {
+                                rule: rule.clone(),
+                                before: node.expr.clone(),
+                                after: new_expr,
+                                parent: node.trace.clone(),
+                            })),
                         });
-                        new_frontier.push((new_expr, new_steps));
                     }
                 }
             }
         }
 
-        new_frontier
+        Some(new_frontier)
     }
 
     fn construct_proof(&self, lhs: &Expr, rhs: &Expr, steps: &[ProofStep]) -> ProofResult {
+        let statement = Statement::Identity {
+            lhs: lhs.clone(),
+            rhs: rhs.clone(),
+        };
+        let assumptions_used = self.assumptions.used_in_proof(steps);
         let proof = Proof {
-            statement: Statement::Identity {
-                lhs: lhs.clone(),
-                rhs: rhs.clone(),
-            },
+            certificate: self.generate_certificate(&assumptions_used, Some(&statement), steps),
+            statement,
             steps: steps.to_vec(),
-            assumptions_used: self.assumptions.used_in_proof(steps),
-            certificate: self.generate_certificate(&self.assumptions.used_in_proof(steps)),
+            assumptions_used,
         };
 
         ProofResult::Proven(proof)
@@ -304,7 +374,7 @@ impl Prover {
             return PropertyProof {
                 property: Property::Hermitian(expr.clone()),
                 result: PropertyResult::SymbolicProof(symbolic_steps),
-                certificate: Some(self.generate_certificate(&[])),
+                certificate: Some(self.generate_certificate(&[], None, &[])),
             };
         }
 
@@ -322,7 +392,7 @@ impl Prover {
                         norm_diff: norm,
                         params_used: self.assumptions.bound_params.clone(),
                     }),
-                    certificate: Some(self.generate_certificate(&[])),
+                    certificate: Some(self.generate_certificate(&[], None, &[])),
                 }
             } else {
                 PropertyProof {
@@ -344,7 +414,7 @@ impl Prover {
         // Check if expr matches pattern: A + dagger(A)
         // Or: expr == dagger(expr)
         
-        let dagger_expr = Expr::Dagger(Box::new(expr.clone()), expr.get_span());
+        let dagger_expr = Expr::Dagger(Box::new(expr.clone()));
         
         if expr_equal(expr, &dagger_expr) {
             Some(vec![ProofStep {
@@ -389,41 +459,140 @@ impl Prover {
     }
 
     fn verify_rule_application(&self, rule: &RewriteRule, before: &Expr, after: &Expr) -> bool {
-        if let Some(result) = rule.apply(before) {
+        if let Some(result) = rule.apply_under(before, &self.assumptions) {
             expr_equal(&result, after)
         } else {
             false
         }
     }
 
-    fn generate_certificate(&self, assumptions: &[Assumption]) -> Certificate {
-        use chrono::Utc;
-        use sha2::{Sha256, Digest};
-
-        let mut hasher = Sha256::new();
-        hasher.update(format!("{:?}", assumptions));
-        let hash = format!("{:x}", hasher.finalize());
-
-        Certificate {
-            hash,
-            timestamp: Utc::now(),
+    /// Certificate of `steps` proving `statement` under `assumptions`,
+    /// signed if the config has a key
+    fn generate_certificate(
+        &self,
+        assumptions: &[Assumption],
+        statement: Option<&Statement>,
+        steps: &[ProofStep],
+    ) -> Certificate {
+        let mut certificate = Certificate {
+            hash: String::new(),
+            timestamp: chrono::Utc::now(),
             engine_version: env!("CARGO_PKG_VERSION").to_string(),
             assumptions: assumptions.to_vec(),
-            verification_steps: vec![],  // TODO: populate
+            verification_steps: steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| VerificationStep {
+                    step_number: i + 1,
+                    description: format!("{:?}: {}", step.rule, step.justification),
+                    verified: true,
+                    step: Some(step.clone()),
+                })
+                .collect(),
+            statement: statement.cloned(),
+            signature: None,
+        };
+        certificate.hash = certificate.content_hash();
+        if let Some(key) = &self.config.signing_key {
+            certificate.signature = Some(key.sign(&certificate));
         }
+        certificate
     }
 
-    fn generate_random_params(&self, rng: &mut impl rand::Rng) -> HashMap<String, f64> {
-        let mut params = HashMap::new();
-        for name in self.assumptions.free_params() {
-            params.insert(name.clone(), rng.gen_range(-10.0..10.0));
+    /// Audit `certificate` without trusting the engine that issued it:
+    /// recompute its hash, replay its steps under its own assumptions from
+    /// the left-hand side of its statement to the right, and check its
+    /// signature
+    pub fn verify_certificate(&self, certificate: &Certificate) -> CertificateVerification {
+        let mut problems = Vec::new();
+        if certificate.content_hash() != certificate.hash {
+            problems.push("hash does not match the certificate's contents".to_string());
+        }
+
+        let mut context = AssumptionContext::new();
+        for assumption in &certificate.assumptions {
+            context.add(assumption.clone());
+        }
+        let mut previous = match &certificate.statement {
+            Some(Statement::Identity { lhs, .. }) => Some(lhs.clone()),
+            _ => None,
+        };
+        let mut steps_replayed = 0;
+        for verification in &certificate.verification_steps {
+            let n = verification.step_number;
+            if !verification.verified {
+                problems.push(format!("step {} is recorded as unverified", n));
+            }
+            let Some(step) = &verification.step else {
+                problems.push(format!("step {} records no rewrite to replay", n));
+                continue;
+            };
+            if let Some(expected) = &previous {
+                if !expr_equal(&step.before, expected) {
+                    problems.push(format!("step {} does not start where the previous one ended", n));
+                }
+            }
+            let replayed = match step.rule {
+                RewriteRule::Canonicalize => Some(self.canonicalize(&step.before)),
+                _ => step.rule.apply_under(&step.before, &context),
+            };
+            match replayed {
+                Some(result) if expr_equal(&result, &step.after) => steps_replayed += 1,
+                _ => problems.push(format!("step {} ({:?}) does not rewrite as recorded", n, step.rule)),
+            }
+            previous = Some(step.after.clone());
+        }
+
+        match &certificate.statement {
+            Some(Statement::Identity { rhs, .. }) => {
+                let reached = previous.is_some_and(|last| {
+                    expr_equal(&last, rhs) || expr_equal(&last, &self.canonicalize(rhs))
+                });
+                if !reached {
+                    problems.push("the steps do not end at the right-hand side".to_string());
+                }
+            }
+            Some(Statement::Property { .. }) => {}
+            None => problems.push("the certificate records no statement".to_string()),
+        }
+
+        let signature = signing::verify_signature(certificate);
+        if let SignatureStatus::Invalid { reason } = &signature {
+            problems.push(format!("invalid signature: {}", reason));
+        }
+
+        CertificateVerification {
+            steps_replayed,
+            signature,
+            problems,
         }
-        params
     }
 
-    fn evaluate_expr(&self, _expr: &Expr, _params: &HashMap<String, f64>) -> Result<Array2<C64>> {
-        // TODO: implement expression evaluation with parameter substitution
-        Err(EngineError::Unsupported("Expression evaluation not yet implemented".to_string()))
+    /// The assumed parameters, then the other symbols of `lhs` and `rhs`
+    /// that are not operators, in sorted order
+    fn counterexample_params(&self, lhs: &Expr, rhs: &Expr) -> Vec<String> {
+        let mut names = self.assumptions.free_params();
+        let mut symbols = HashSet::new();
+        collect_identifiers(lhs, &mut symbols);
+        collect_identifiers(rhs, &mut symbols);
+        let mut symbols: Vec<String> = symbols
+            .into_iter()
+            .filter(|name| !names.contains(name) && !counterexample::is_operator(name))
+            .collect();
+        symbols.sort();
+        names.extend(symbols);
+        names
+    }
+
+    /// A random value for each of `names`, within its assumptions
+    fn generate_random_params(&self, names: &[String], rng: &mut impl rand::Rng) -> Option<HashMap<String, f64>> {
+        names
+            .iter()
+            .map(|name| {
+                let value = self.assumptions.sample(name, rng)?;
+                Some((name.clone(), value))
+            })
+            .collect()
     }
 
     fn evaluate_with_bound_params(&self, _expr: &Expr) -> Result<Array2<C64>> {
@@ -470,6 +639,31 @@ pub struct Certificate {
     pub engine_version: String,
     pub assumptions: Vec<Assumption>,
     pub verification_steps: Vec<VerificationStep>,
+    /// What was proven; absent from certificates of numeric checks
+    #[serde(default)]
+    pub statement: Option<Statement>,
+    /// Engine signature on `hash`, see [`crate::signing`]
+    #[serde(default)]
+    pub signature: Option<CertificateSignature>,
+}
+
+impl Certificate {
+    /// SHA-256 of everything the certificate records but its hash and
+    /// signature
+    pub fn content_hash(&self) -> String {
+        use sha2::{Sha256, Digest};
+
+        let contents = serde_json::json!({
+            "timestamp": self.timestamp,
+            "engine_version": self.engine_version,
+            "statement": self.statement,
+            "assumptions": self.assumptions,
+            "verification_steps": self.verification_steps,
+        });
+        let mut hasher = Sha256::new();
+        hasher.update(contents.to_string());
+        format!("{:x}", hasher.finalize())
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -477,14 +671,40 @@ pub struct VerificationStep {
     pub step_number: usize,
     pub description: String,
     pub verified: bool,
+    /// The rewrite itself, which [`Prover::verify_certificate`] replays
+    #[serde(default)]
+    pub step: Option<ProofStep>,
 }
 
+/// Outcome of [`Prover::verify_certificate`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CertificateVerification {
+    pub steps_replayed: usize,
+    pub signature: SignatureStatus,
+    /// Everything that failed to check, empty for a valid certificate
+    pub problems: Vec<String>,
+}
+
+impl CertificateVerification {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Parameter values refuting an identity; displays as a readable report
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Counterexample {
+    /// Values after shrinking
     pub params: HashMap<String, f64>,
+    /// Values as first sampled
+    #[serde(default)]
+    pub sampled_params: HashMap<String, f64>,
     pub lhs_value: MatrixValue,
     pub rhs_value: MatrixValue,
     pub difference_norm: f64,
+    /// Innermost subexpressions of the two sides that disagree
+    #[serde(default)]
+    pub divergence: Option<Divergence>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -495,7 +715,7 @@ pub struct MatrixValue {
 }
 
 impl MatrixValue {
-    fn from_array(arr: Array2<C64>) -> Self {
+    pub(crate) fn from_array(arr: Array2<C64>) -> Self {
         let (rows, cols) = arr.dim();
         let mut elements = Vec::new();
         
@@ -516,6 +736,8 @@ impl MatrixValue {
 pub enum Reason {
     Timeout,
     Exhausted,
+    /// An expression or the search frontier exceeded the configured limits
+    TooLarge,
     InsufficientAssumptions,
     NumericViolation(f64),
     CannotEvaluate,
@@ -569,7 +791,7 @@ impl Default for RewriteSystem {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RewriteRule {
     // Dagger properties
     DaggerDagger,
@@ -602,12 +824,20 @@ pub enum RewriteRule {
     MultiplyZero,
     AddZero,
     MultiplyIdentity,
+
+    // Scalar functions, valid only under assumptions
+    SqrtSquare,
+    SqrtProduct,
     
     // Canonicalization
     Canonicalize,
     
     // Property-based
     HermitianByConstruction,
+
+    /// The equation assumption of this name, see
+    /// [`AssumptionKind::Equation`]
+    Hypothesis(String),
 }
 
 impl RewriteRule {
@@ -616,12 +846,21 @@ impl RewriteRule {
             Self::DaggerDagger,
             Self::DaggerSum,
             Self::DaggerProduct,
+            Self::TraceCyclic,
             Self::CommutatorSelf,
             Self::CommutatorAnti,
+            Self::JacobiIdentity,
+            Self::TensorDistribute,
+            Self::TensorAssoc,
+            Self::TensorDagger,
             Self::MultiplyZero,
             Self::AddZero,
             Self::MultiplyIdentity,
             Self::PauliSquare,
+            Self::PauliCommutator,
+            Self::PauliAnticommutator,
+            Self::SqrtSquare,
+            Self::SqrtProduct,
         ]
     }
 
@@ -639,25 +878,46 @@ impl RewriteRule {
 
         match (self, expr) {
             // (A†)† = A
-            (Self::DaggerDagger, Dagger(inner, _)) => {
-                if let Dagger(inner_inner, span) = &**inner {
-                    Some((**inner_inner).clone())
-                } else {
-                    None
-                }
-            }
+            (Self::DaggerDagger, Dagger(inner)) => match &**inner {
+                Dagger(inner_inner) => Some((**inner_inner).clone()),
+                _ => None,
+            },
+
+            // (A + B)† = A† + B†
+            (Self::DaggerSum, Dagger(inner)) => match &**inner {
+                Add(a, b) => Some(Add(
+                    Box::new(Dagger(a.clone())),
+                    Box::new(Dagger(b.clone())),
+                )),
+                _ => None,
+            },
+
+            // (AB)† = B†A†
+            (Self::DaggerProduct, Dagger(inner)) => match &**inner {
+                Mul(a, b) => Some(Mul(
+                    Box::new(Dagger(b.clone())),
+                    Box::new(Dagger(a.clone())),
+                )),
+                _ => None,
+            },
+
+            // Tr(AB) = Tr(BA)
+            (Self::TraceCyclic, Trace(inner)) => match &**inner {
+                Mul(a, b) => Some(Trace(Box::new(Mul(b.clone(), a.clone())))),
+                _ => None,
+            },
 
             // 0 * A = 0
-            (Self::MultiplyZero, Mul(lhs, rhs, span)) => {
+            (Self::MultiplyZero, Mul(lhs, rhs)) => {
                 if is_zero(lhs) || is_zero(rhs) {
-                    Some(Number(C64::new(0.0, 0.0), span.clone()))
+                    Some(Number(0.0))
                 } else {
                     None
                 }
             }
 
             // A + 0 = A
-            (Self::AddZero, Add(lhs, rhs, _)) => {
+            (Self::AddZero, Add(lhs, rhs)) => {
                 if is_zero(rhs) {
                     Some((**lhs).clone())
                 } else if is_zero(lhs) {
@@ -668,7 +928,7 @@ impl RewriteRule {
             }
 
             // I * A = A
-            (Self::MultiplyIdentity, Mul(lhs, rhs, _)) => {
+            (Self::MultiplyIdentity, Mul(lhs, rhs)) => {
                 if is_identity(lhs) {
                     Some((**rhs).clone())
                 } else if is_identity(rhs) {
@@ -679,36 +939,139 @@ impl RewriteRule {
             }
 
             // [A, A] = 0
-            (Self::CommutatorSelf, Commutator(lhs, rhs, span)) => {
+            (Self::CommutatorSelf, Commutator(lhs, rhs)) => {
                 if expr_equal(lhs, rhs) {
-                    Some(Number(C64::new(0.0, 0.0), span.clone()))
+                    Some(Number(0.0))
                 } else {
                     None
                 }
             }
 
             // [A, B] = -[B, A]
-            (Self::CommutatorAnti, Commutator(lhs, rhs, span)) => {
-                Some(Neg(
-                    Box::new(Commutator(rhs.clone(), lhs.clone(), span.clone())),
-                    span.clone(),
-                ))
+            (Self::CommutatorAnti, Commutator(lhs, rhs)) => Some(Mul(
+                Box::new(Number(-1.0)),
+                Box::new(Commutator(rhs.clone(), lhs.clone())),
+            )),
+
+            // [A, [B, C]] + [B, [C, A]] + [C, [A, B]] = 0
+            (Self::JacobiIdentity, Add(ab, c)) => match &**ab {
+                Add(a, b) if is_jacobi_sum(a, b, c) => Some(Number(0.0)),
+                _ => None,
+            },
+
+            // A ⊗ (B + C) = A ⊗ B + A ⊗ C, and (A + B) ⊗ C likewise
+            (Self::TensorDistribute, Tensor(lhs, rhs)) => match (&**lhs, &**rhs) {
+                (_, Add(b, c)) => Some(Add(
+                    Box::new(Tensor(lhs.clone(), b.clone())),
+                    Box::new(Tensor(lhs.clone(), c.clone())),
+                )),
+                (Add(a, b), _) => Some(Add(
+                    Box::new(Tensor(a.clone(), rhs.clone())),
+                    Box::new(Tensor(b.clone(), rhs.clone())),
+                )),
+                _ => None,
+            },
+
+            // (A ⊗ B) ⊗ C = A ⊗ (B ⊗ C)
+            (Self::TensorAssoc, Tensor(lhs, c)) => match &**lhs {
+                Tensor(a, b) => Some(Tensor(
+                    a.clone(),
+                    Box::new(Tensor(b.clone(), c.clone())),
+                )),
+                _ => None,
+            },
+
+            // (A ⊗ B)† = A† ⊗ B†
+            (Self::TensorDagger, Dagger(inner)) => match &**inner {
+                Tensor(a, b) => Some(Tensor(
+                    Box::new(Dagger(a.clone())),
+                    Box::new(Dagger(b.clone())),
+                )),
+                _ => None,
+            },
+
+            // P P = P² = I for a Pauli string P
+            (Self::PauliSquare, Mul(lhs, rhs)) => match (pauli_string(lhs), pauli_string(rhs)) {
+                (Some(p), Some(q)) if p == q => pauli_identity(&p),
+                _ => None,
+            },
+            (Self::PauliSquare, Pow(base, exponent)) if **exponent == Number(2.0) => {
+                pauli_string(base).and_then(|pauli| pauli_identity(&pauli))
+            }
+
+            // [P, Q] = 2PQ if P and Q anticommute, else 0
+            (Self::PauliCommutator, Commutator(lhs, rhs)) => {
+                let (p, q) = (pauli_sum(lhs)?, pauli_sum(rhs)?);
+                Some(pauli_sum_expr(&p.commutator(&q).ok()?))
+            }
+
+            // {P, Q} = 2PQ if P and Q commute, else 0
+            (Self::PauliAnticommutator, AntiCommutator(lhs, rhs)) => {
+                let (p, q) = (pauli_sum(lhs)?, pauli_sum(rhs)?);
+                Some(pauli_sum_expr(&p.anticommutator(&q).ok()?))
             }
 
             _ => None,
         }
     }
 
+    /// Apply the rule, checking the side conditions of rules that hold
+    /// only for some scalar values against `assumptions`
+    pub fn apply_under(&self, expr: &Expr, assumptions: &AssumptionContext) -> Option<Expr> {
+        match (self, expr) {
+            // √(x²) = x for x ≥ 0
+            (Self::SqrtSquare, Expr::Sqrt(inner)) => match &**inner {
+                Expr::Pow(base, exponent)
+                    if **exponent == Expr::Number(2.0) && assumptions.is_nonnegative(base) =>
+                {
+                    Some((**base).clone())
+                }
+                _ => None,
+            },
+
+            // √(ab) = √a √b for a, b ≥ 0
+            (Self::SqrtProduct, Expr::Sqrt(inner)) => match &**inner {
+                Expr::Mul(a, b) if assumptions.is_nonnegative(a) && assumptions.is_nonnegative(b) => {
+                    Some(Expr::Mul(
+                        Box::new(Expr::Sqrt(a.clone())),
+                        Box::new(Expr::Sqrt(b.clone())),
+                    ))
+                }
+                _ => None,
+            },
+
+            (Self::SqrtSquare | Self::SqrtProduct, _) => None,
+            (Self::Hypothesis(name), _) => assumptions.rewrite_with(name, expr),
+            _ => self.apply(expr),
+        }
+    }
+
+    /// Whether the rule only holds under assumptions about its operands
+    pub fn is_conditional(&self) -> bool {
+        matches!(self, Self::SqrtSquare | Self::SqrtProduct | Self::Hypothesis(_))
+    }
+
     pub fn description(&self) -> String {
         match self {
             Self::DaggerDagger => "(A†)† = A".to_string(),
             Self::DaggerSum => "(A + B)† = A† + B†".to_string(),
             Self::DaggerProduct => "(AB)† = B†A†".to_string(),
             Self::CommutatorSelf => "[A, A] = 0".to_string(),
+            Self::TraceCyclic => "Tr(AB) = Tr(BA)".to_string(),
             Self::CommutatorAnti => "[A, B] = -[B, A]".to_string(),
+            Self::JacobiIdentity => "[A, [B, C]] + [B, [C, A]] + [C, [A, B]] = 0".to_string(),
+            Self::TensorDistribute => "A ⊗ (B + C) = A ⊗ B + A ⊗ C".to_string(),
+            Self::TensorAssoc => "(A ⊗ B) ⊗ C = A ⊗ (B ⊗ C)".to_string(),
+            Self::TensorDagger => "(A ⊗ B)† = A† ⊗ B†".to_string(),
             Self::MultiplyZero => "0 * A = 0".to_string(),
             Self::AddZero => "A + 0 = A".to_string(),
             Self::MultiplyIdentity => "I * A = A".to_string(),
+            Self::PauliSquare => "P² = I for a Pauli string P".to_string(),
+            Self::PauliCommutator => "[P, Q] = 2PQ if PQ = -QP, else 0".to_string(),
+            Self::PauliAnticommutator => "{P, Q} = 2PQ if PQ = QP, else 0".to_string(),
+            Self::SqrtSquare => "√(x²) = x for x ≥ 0".to_string(),
+            Self::SqrtProduct => "√(ab) = √a √b for a, b ≥ 0".to_string(),
+            Self::Hypothesis(name) => format!("by assumption {}", name),
             _ => format!("{:?}", self),
         }
     }
@@ -733,14 +1096,191 @@ impl AssumptionContext {
         self.assumptions.push(assumption);
     }
 
+    /// Names of the parameters with assumptions, in order of first mention
     fn free_params(&self) -> Vec<String> {
-        // Extract free parameter names from assumptions
-        vec![]  // TODO: implement
+        let mut names: Vec<String> = Vec::new();
+        for assumption in self.assumptions.iter().filter(|a| !is_hypothesis(a)) {
+            if !names.contains(&assumption.name) {
+                names.push(assumption.name.clone());
+            }
+        }
+        names
+    }
+
+    /// The hypotheses the steps rewrite with, and the assumptions about
+    /// the symbols rewritten by the other conditional rules
+    fn used_in_proof(&self, steps: &[ProofStep]) -> Vec<Assumption> {
+        let mut names = HashSet::new();
+        for step in steps.iter().filter(|step| step.rule.is_conditional()) {
+            match &step.rule {
+                RewriteRule::Hypothesis(name) => {
+                    names.insert(name.clone());
+                }
+                _ => collect_identifiers(&step.before, &mut names),
+            }
+        }
+        self.assumptions
+            .iter()
+            .filter(|assumption| names.contains(&assumption.name))
+            .cloned()
+            .collect()
+    }
+
+    fn has_hypotheses(&self) -> bool {
+        self.assumptions.iter().any(is_hypothesis)
     }
 
-    fn used_in_proof(&self, _steps: &[ProofStep]) -> Vec<Assumption> {
-        // Determine which assumptions were used
-        vec![]  // TODO: implement
+    /// A rule for each equation assumption
+    fn hypotheses(&self) -> Vec<RewriteRule> {
+        self.assumptions
+            .iter()
+            .filter(|assumption| is_hypothesis(assumption))
+            .map(|assumption| RewriteRule::Hypothesis(assumption.name.clone()))
+            .collect()
+    }
+
+    /// `expr` rewritten with the hypothesis `name`: its right-hand side if
+    /// `expr` is its left-hand side, or the swapped product if it makes
+    /// the two factors commute
+    fn rewrite_with(&self, name: &str, expr: &Expr) -> Option<Expr> {
+        self.kinds(name).find_map(|kind| {
+            let AssumptionKind::Equation { lhs, rhs } = kind else {
+                return None;
+            };
+            if expr_equal(expr, lhs) {
+                return Some(rhs.clone());
+            }
+            match (lhs, expr) {
+                (Expr::Commutator(a, b), Expr::Mul(x, y))
+                    if is_zero(rhs)
+                        && ((expr_equal(x, a) && expr_equal(y, b))
+                            || (expr_equal(x, b) && expr_equal(y, a))) =>
+                {
+                    Some(Expr::Mul(y.clone(), x.clone()))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn kinds<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a AssumptionKind> + 'a {
+        self.assumptions
+            .iter()
+            .filter(move |assumption| assumption.name == name)
+            .map(|assumption| &assumption.kind)
+    }
+
+    /// Interval the range and sign assumptions confine `name` to
+    fn bounds(&self, name: &str) -> (f64, f64) {
+        let mut bounds = (f64::NEG_INFINITY, f64::INFINITY);
+        for kind in self.kinds(name) {
+            match kind {
+                AssumptionKind::Range { min, max } => {
+                    bounds.0 = bounds.0.max(min.unwrap_or(f64::NEG_INFINITY));
+                    bounds.1 = bounds.1.min(max.unwrap_or(f64::INFINITY));
+                }
+                AssumptionKind::Sign(Sign::Positive | Sign::Nonnegative) => {
+                    bounds.0 = bounds.0.max(0.0)
+                }
+                AssumptionKind::Sign(Sign::Negative | Sign::Nonpositive) => {
+                    bounds.1 = bounds.1.min(0.0)
+                }
+                AssumptionKind::Sign(Sign::Nonzero)
+                | AssumptionKind::Integer
+                | AssumptionKind::Equation { .. } => {}
+            }
+        }
+        bounds
+    }
+
+    /// Whether a scalar expression is nonnegative for every value of its
+    /// parameters the assumptions allow
+    pub fn is_nonnegative(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Number(x) => *x >= 0.0,
+            Expr::Identifier(name) => self.bounds(name).0 >= 0.0,
+            Expr::Add(a, b) | Expr::Mul(a, b) => self.is_nonnegative(a) && self.is_nonnegative(b),
+            Expr::Sqrt(a) => self.is_nonnegative(a),
+            _ => false,
+        }
+    }
+
+    /// Whether `x` satisfies every assumption about `name`
+    fn admits(&self, name: &str, x: f64) -> bool {
+        self.kinds(name).all(|kind| kind.admits(x))
+    }
+
+    /// A random value of `name` satisfying all its assumptions, found by
+    /// rejection from a window inside its bounds; `None` if none was found
+    fn sample(&self, name: &str, rng: &mut impl rand::Rng) -> Option<f64> {
+        let (lo, hi) = match self.bounds(name) {
+            (lo, hi) if lo > hi => return None,
+            (lo, hi) if lo.is_finite() && hi.is_finite() => (lo, hi),
+            (lo, _) if lo.is_finite() => (lo, lo + 2.0 * SAMPLE_RADIUS),
+            (_, hi) if hi.is_finite() => (hi - 2.0 * SAMPLE_RADIUS, hi),
+            _ => (-SAMPLE_RADIUS, SAMPLE_RADIUS),
+        };
+        let integer = self.kinds(name).any(|kind| *kind == AssumptionKind::Integer);
+        (0..SAMPLE_ATTEMPTS)
+            .map(|_| {
+                let x = rng.gen_range(lo..=hi);
+                if integer {
+                    x.round()
+                } else {
+                    x
+                }
+            })
+            .find(|&x| self.admits(name, x))
+    }
+}
+
+fn is_hypothesis(assumption: &Assumption) -> bool {
+    matches!(assumption.kind, AssumptionKind::Equation { .. })
+}
+
+/// Half-width of the window parameters without finite bounds are drawn from
+const SAMPLE_RADIUS: f64 = 10.0;
+
+/// Draws per parameter before its assumptions are deemed unsatisfiable
+const SAMPLE_ATTEMPTS: usize = 1_000;
+
+// ========== SEARCH STATE ==========
+
+/// An expression reached by the search. Expressions are shared between the
+/// frontier and the traces, and traces share their prefix with the parent
+/// node, so expanding a node never copies its history. The visited sets hold
+/// only arena ids, whose subtrees are stored once however many expressions
+/// contain them.
+struct SearchNode {
+    expr: Rc<Expr>,
+    id: ExprId,
+    trace: Option<Rc<Trace>>,
+}
+
+/// The last rewrite leading to a node, linked to the rewrites before it
+struct Trace {
+    rule: RewriteRule,
+    before: Rc<Expr>,
+    after: Rc<Expr>,
+    parent: Option<Rc<Trace>>,
+}
+
+impl SearchNode {
+    /// Materialise the rewrite path, oldest step first
+    fn steps(&self) -> Vec<ProofStep> {
+        let mut steps = Vec::new();
+        let mut trace = self.trace.as_deref();
+        while let Some(t) = trace {
+            steps.push(ProofStep {
+                rule: t.rule.clone(),
+                before: (*t.before).clone(),
+                after: (*t.after).clone(),
+                justification: t.rule.description(),
+            });
+            trace = t.parent.as_deref();
+        }
+        steps.reverse();
+        steps
     }
 }
 
@@ -749,7 +1289,7 @@ impl AssumptionContext {
 type CacheKey = (Expr, Expr);
 
 struct ProofCache {
-    cache: HashMap<u64, ProofResult>,
+    cache: HashMap<CacheKey, ProofResult>,
 }
 
 impl ProofCache {
@@ -760,66 +1300,463 @@ impl ProofCache {
     }
 
     fn get(&self, key: &CacheKey) -> Option<&ProofResult> {
-        let hash = Self::hash_key(key);
-        self.cache.get(&hash)
+        self.cache.get(key)
     }
 
     fn insert(&mut self, key: CacheKey, value: ProofResult) {
-        let hash = Self::hash_key(&key);
-        self.cache.insert(hash, value);
-    }
-
-    fn hash_key(key: &CacheKey) -> u64 {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        
-        let mut hasher = DefaultHasher::new();
-        format!("{:?}", key).hash(&mut hasher);
-        hasher.finish()
+        self.cache.insert(key, value);
     }
 }
 
 // ========== UTILITY FUNCTIONS ==========
 
 fn expr_equal(lhs: &Expr, rhs: &Expr) -> bool {
-    // Structural equality (for now)
-    // TODO: implement more sophisticated equality checking
-    format!("{:?}", lhs) == format!("{:?}", rhs)
+    // Structural equality; see `Expr`'s `PartialEq` for the float policy
+    lhs == rhs
 }
 
-fn expr_hash(expr: &Expr) -> u64 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    
-    let mut hasher = DefaultHasher::new();
-    format!("{:?}", expr).hash(&mut hasher);
-    hasher.finish()
+/// Number of nodes in `expr`, counting no further than `limit + 1` so an
+/// oversized expression is rejected without walking all of it. Iterative,
+/// so deeply nested input cannot overflow the stack.
+fn expr_size(expr: &Expr, limit: usize) -> usize {
+    let mut stack = vec![expr];
+    let mut size = 0;
+    while let Some(expr) = stack.pop() {
+        size += 1;
+        if size > limit {
+            break;
+        }
+        match expr {
+            Expr::Number(_) | Expr::ComplexNumber(_) | Expr::Identifier(_) => {}
+            Expr::Matrix(m) => stack.extend(m.rows.iter().flatten()),
+            Expr::Vector(v) => stack.extend(&v.elements),
+            Expr::Add(a, b)
+            | Expr::Sub(a, b)
+            | Expr::Mul(a, b)
+            | Expr::Div(a, b)
+            | Expr::Pow(a, b)
+            | Expr::Tensor(a, b)
+            | Expr::Commutator(a, b)
+            | Expr::AntiCommutator(a, b) => {
+                stack.push(a);
+                stack.push(b);
+            }
+            Expr::Dagger(a)
+            | Expr::Trace(a)
+            | Expr::Expm(a)
+            | Expr::Sqrt(a)
+            | Expr::Sin(a)
+            | Expr::Cos(a)
+            | Expr::Exp(a) => stack.push(a),
+            Expr::FuncCall { args, .. } => stack.extend(args),
+        }
+    }
+    size
+}
+
+fn collect_identifiers(expr: &Expr, names: &mut HashSet<String>) {
+    match expr {
+        Expr::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        Expr::Number(_) | Expr::ComplexNumber(_) => {}
+        Expr::Matrix(m) => m.rows.iter().flatten().for_each(|e| collect_identifiers(e, names)),
+        Expr::Vector(v) => v.elements.iter().for_each(|e| collect_identifiers(e, names)),
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Pow(a, b)
+        | Expr::Tensor(a, b)
+        | Expr::Commutator(a, b)
+        | Expr::AntiCommutator(a, b) => {
+            collect_identifiers(a, names);
+            collect_identifiers(b, names);
+        }
+        Expr::Dagger(a)
+        | Expr::Trace(a)
+        | Expr::Expm(a)
+        | Expr::Sqrt(a)
+        | Expr::Sin(a)
+        | Expr::Cos(a)
+        | Expr::Exp(a) => collect_identifiers(a, names),
+        Expr::FuncCall { args, .. } => args.iter().for_each(|e| collect_identifiers(e, names)),
+    }
 }
 
 fn is_zero(expr: &Expr) -> bool {
-    matches!(expr, Expr::Number(c, _) if c.norm() < 1e-15)
+    match expr {
+        Expr::Number(x) => x.abs() < 1e-15,
+        Expr::ComplexNumber(c) => c.norm() < 1e-15,
+        _ => false,
+    }
 }
 
 fn is_identity(expr: &Expr) -> bool {
-    matches!(expr, Expr::Identifier(name, _) if name == "I" || name == "identity")
+    matches!(expr, Expr::Identifier(name) if name == "I" || name == "identity")
+}
+
+/// Whether `x + y + z` is `[A, [B, C]] + [B, [C, A]] + [C, [A, B]]`
+fn is_jacobi_sum(x: &Expr, y: &Expr, z: &Expr) -> bool {
+    fn nested(expr: &Expr) -> Option<(&Expr, &Expr, &Expr)> {
+        match expr {
+            Expr::Commutator(a, inner) => match &**inner {
+                Expr::Commutator(b, c) => Some((a, b, c)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    match (nested(x), nested(y), nested(z)) {
+        (Some((a, b, c)), Some((b2, c2, a2)), Some((c3, a3, b3))) => {
+            expr_equal(a, a2) && expr_equal(a, a3)
+                && expr_equal(b, b2) && expr_equal(b, b3)
+                && expr_equal(c, c2) && expr_equal(c, c3)
+        }
+        _ => false,
+    }
+}
+
+/// The Pauli string a word such as `XZI` names
+fn pauli_string(expr: &Expr) -> Option<PauliString> {
+    match expr {
+        Expr::Identifier(name) => name.parse().ok(),
+        _ => None,
+    }
+}
+
+fn pauli_sum(expr: &Expr) -> Option<PauliSum> {
+    pauli_string(expr).map(|pauli| PauliSum::term(C64::new(1.0, 0.0), pauli))
+}
+
+fn pauli_identity(pauli: &PauliString) -> Option<Expr> {
+    let identity = PauliString::identity(pauli.n_qubits()).ok()?;
+    Some(Expr::Identifier(identity.to_string()))
+}
+
+/// `sum` as a sum of scaled Pauli-string identifiers, or 0 if it is empty
+fn pauli_sum_expr(sum: &PauliSum) -> Expr {
+    sum.terms()
+        .map(|(pauli, &c)| {
+            let word = Expr::Identifier(pauli.to_string());
+            let coefficient = if c.im == 0.0 {
+                Expr::Number(c.re)
+            } else {
+                Expr::ComplexNumber(c)
+            };
+            if c == C64::new(1.0, 0.0) {
+                word
+            } else {
+                Expr::Mul(Box::new(coefficient), Box::new(word))
+            }
+        })
+        .reduce(|acc, term| Expr::Add(Box::new(acc), Box::new(term)))
+        .unwrap_or(Expr::Number(0.0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn id(name: &str) -> Box<Expr> {
+        Box::new(Expr::Identifier(name.to_string()))
+    }
+
     #[test]
     fn test_rewrite_dagger_dagger() {
-        // TODO: implement test
+        let twice = Expr::Dagger(Box::new(Expr::Dagger(id("A"))));
+        assert_eq!(RewriteRule::DaggerDagger.apply(&twice), Some(*id("A")));
+        assert_eq!(RewriteRule::DaggerDagger.apply(&Expr::Dagger(id("A"))), None);
     }
 
     #[test]
     fn test_commutator_self() {
-        // TODO: implement test
+        let self_commutator = Expr::Commutator(id("A"), id("A"));
+        assert_eq!(RewriteRule::CommutatorSelf.apply(&self_commutator), Some(Expr::Number(0.0)));
+        assert_eq!(RewriteRule::CommutatorSelf.apply(&Expr::Commutator(id("A"), id("B"))), None);
+    }
+
+    #[test]
+    fn test_hypotheses_rewrite_and_are_recorded() {
+        let ab = Expr::Mul(id("A"), id("B"));
+        let ba = Expr::Mul(id("B"), id("A"));
+        let mut prover = Prover::new(ProverConfig::default());
+        assert!(!matches!(prover.prove_identity(&ab, &ba), ProofResult::Proven(_)));
+
+        let commute = Assumption::commutes(*id("A"), *id("B"));
+        prover.add_assumption(commute.clone());
+        prover.add_assumption(Assumption::sign("x", Sign::Positive));
+        let ProofResult::Proven(proof) = prover.prove_identity(&ab, &ba) else {
+            panic!("AB = BA not proven from [A, B] = 0");
+        };
+        assert_eq!(proof.steps[0].rule, RewriteRule::Hypothesis(commute.name.clone()));
+        assert_eq!(proof.assumptions_used, vec![commute]);
+        assert!(prover.verify_proof(&proof));
+        assert!(prover.verify_certificate(&proof.certificate).is_valid());
+
+        // The hypothesis itself rewrites left to right
+        let commutator = Expr::Commutator(id("A"), id("B"));
+        assert!(matches!(
+            prover.prove_identity(&commutator, &Expr::Number(0.0)),
+            ProofResult::Proven(_)
+        ));
+        assert!(prover.find_counterexample(&ab, &ba).is_none());
+    }
+
+    #[test]
+    fn test_structural_rules_prove_identities() {
+        let mut prover = Prover::new(ProverConfig::default());
+        let mut prove = |lhs: Expr, rhs: Expr, rule: RewriteRule| match prover.prove_identity(&lhs, &rhs) {
+            ProofResult::Proven(proof) => {
+                assert!(prover.verify_proof(&proof));
+                assert!(proof.steps.iter().any(|step| step.rule == rule), "{:?}", proof.steps);
+            }
+            other => panic!("{:?} = {:?} not proven: {:?}", lhs, rhs, other),
+        };
+
+        // Tr(AB) = Tr(BA)
+        prove(
+            Expr::Trace(Box::new(Expr::Mul(id("A"), id("B")))),
+            Expr::Trace(Box::new(Expr::Mul(id("B"), id("A")))),
+            RewriteRule::TraceCyclic,
+        );
+        // (AB)† = B†A†
+        prove(
+            Expr::Dagger(Box::new(Expr::Mul(id("A"), id("B")))),
+            Expr::Mul(Box::new(Expr::Dagger(id("B"))), Box::new(Expr::Dagger(id("A")))),
+            RewriteRule::DaggerProduct,
+        );
+        // (A ⊗ B)† = A† ⊗ B†
+        prove(
+            Expr::Dagger(Box::new(Expr::Tensor(id("A"), id("B")))),
+            Expr::Tensor(Box::new(Expr::Dagger(id("A"))), Box::new(Expr::Dagger(id("B")))),
+            RewriteRule::TensorDagger,
+        );
+        // (A ⊗ B) ⊗ C = A ⊗ (B ⊗ C)
+        prove(
+            Expr::Tensor(Box::new(Expr::Tensor(id("A"), id("B"))), id("C")),
+            Expr::Tensor(id("A"), Box::new(Expr::Tensor(id("B"), id("C")))),
+            RewriteRule::TensorAssoc,
+        );
+        // A ⊗ (B + C) = A ⊗ B + A ⊗ C
+        prove(
+            Expr::Tensor(id("A"), Box::new(Expr::Add(id("B"), id("C")))),
+            Expr::Add(
+                Box::new(Expr::Tensor(id("A"), id("B"))),
+                Box::new(Expr::Tensor(id("A"), id("C"))),
+            ),
+            RewriteRule::TensorDistribute,
+        );
+
+        // [A, [B, C]] + [B, [C, A]] + [C, [A, B]] = 0, in that cyclic order only
+        let nested = |a: &str, b: &str, c: &str| {
+            Box::new(Expr::Commutator(id(a), Box::new(Expr::Commutator(id(b), id(c)))))
+        };
+        let jacobi = Expr::Add(
+            Box::new(Expr::Add(nested("A", "B", "C"), nested("B", "C", "A"))),
+            nested("C", "A", "B"),
+        );
+        prove(jacobi, Expr::Number(0.0), RewriteRule::JacobiIdentity);
+        let scrambled = Expr::Add(
+            Box::new(Expr::Add(nested("A", "B", "C"), nested("B", "A", "C"))),
+            nested("C", "A", "B"),
+        );
+        assert_eq!(RewriteRule::JacobiIdentity.apply(&scrambled), None);
     }
 
     #[test]
     fn test_proof_caching() {
         // TODO: implement test
     }
+
+    fn daggers(depth: usize) -> Expr {
+        (0..depth).fold(Expr::Identifier("A".to_string()), |e, _| {
+            Expr::Dagger(Box::new(e))
+        })
+    }
+
+    #[test]
+    fn test_expr_size_stops_at_limit() {
+        assert_eq!(expr_size(&daggers(3), 100), 4);
+        assert_eq!(expr_size(&daggers(1_000), 10), 11);
+    }
+
+    #[test]
+    fn test_sqrt_rules_need_nonnegative_operands() {
+        let x = || Box::new(Expr::Identifier("x".to_string()));
+        let sqrt_square = Expr::Sqrt(Box::new(Expr::Pow(x(), Box::new(Expr::Number(2.0)))));
+
+        let mut context = AssumptionContext::new();
+        assert_eq!(RewriteRule::SqrtSquare.apply_under(&sqrt_square, &context), None);
+
+        context.add(Assumption::sign("x", Sign::Nonnegative));
+        assert_eq!(
+            RewriteRule::SqrtSquare.apply_under(&sqrt_square, &context),
+            Some(*x())
+        );
+
+        let product = Expr::Sqrt(Box::new(Expr::Mul(x(), Box::new(Expr::Identifier("y".to_string())))));
+        assert_eq!(RewriteRule::SqrtProduct.apply_under(&product, &context), None);
+        context.add(Assumption::range("y", Some(1.0), None));
+        assert!(RewriteRule::SqrtProduct.apply_under(&product, &context).is_some());
+
+        let steps = vec![ProofStep {
+            rule: RewriteRule::SqrtSquare,
+            before: sqrt_square,
+            after: *x(),
+            justification: RewriteRule::SqrtSquare.description(),
+        }];
+        assert_eq!(
+            context.used_in_proof(&steps),
+            vec![Assumption::sign("x", Sign::Nonnegative)]
+        );
+    }
+
+    #[test]
+    fn test_counterexample_is_shrunk_and_reported() {
+        let x = || Box::new(Expr::Identifier("x".to_string()));
+        let sqrt_square = Expr::Sqrt(Box::new(Expr::Pow(x(), Box::new(Expr::Number(2.0)))));
+
+        let mut prover = Prover::new(ProverConfig::default());
+        prover.add_assumption(Assumption::range("x", None, Some(-0.5)));
+        let counterexample = prover.find_counterexample(&sqrt_square, &x()).unwrap();
+
+        // √(x²) = |x|, so any admitted x refutes it; -1 is the simplest
+        assert_eq!(counterexample.params["x"], -1.0);
+        assert!(counterexample.sampled_params["x"] <= -0.5);
+        assert!((counterexample.difference_norm - 2.0).abs() < 1e-12);
+        let divergence = counterexample.divergence.as_ref().unwrap();
+        assert_eq!(divergence.lhs, sqrt_square);
+
+        let report = counterexample.to_string();
+        assert!(report.contains("at x = -1 (shrunk from x = "));
+        assert!(report.contains("sqrt(x^2)  =  [1.0000]"));
+    }
+
+    #[test]
+    fn test_certificates_replay_and_verify_signatures() {
+        let x = || Box::new(Expr::Identifier("x".to_string()));
+        let sqrt_square = Expr::Sqrt(Box::new(Expr::Pow(x(), Box::new(Expr::Number(2.0)))));
+        let statement = Statement::Identity { lhs: sqrt_square.clone(), rhs: *x() };
+        let steps = vec![ProofStep {
+            rule: RewriteRule::SqrtSquare,
+            before: sqrt_square,
+            after: *x(),
+            justification: RewriteRule::SqrtSquare.description(),
+        }];
+        let nonnegative = vec![Assumption::sign("x", Sign::Nonnegative)];
+
+        let key = EngineKey::generate();
+        let prover = Prover::new(ProverConfig {
+            signing_key: Some(key.clone()),
+            ..ProverConfig::default()
+        });
+        let certificate = prover.generate_certificate(&nonnegative, Some(&statement), &steps);
+        let check = prover.verify_certificate(&certificate);
+        assert!(check.is_valid(), "{:?}", check.problems);
+        assert_eq!(check.steps_replayed, 1);
+        assert_eq!(check.signature, SignatureStatus::Valid { public_key: key.public_key_hex() });
+
+        // Editing a certificate breaks its hash
+        let mut edited = certificate.clone();
+        edited.assumptions.clear();
+        assert!(!prover.verify_certificate(&edited).is_valid());
+
+        // Re-hashing the edit breaks the signature, and the step no
+        // longer replays without its assumption
+        edited.hash = edited.content_hash();
+        let check = prover.verify_certificate(&edited);
+        assert!(matches!(check.signature, SignatureStatus::Invalid { .. }));
+        assert_eq!(check.steps_replayed, 0);
+        assert_eq!(check.problems.len(), 2);
+
+        // Unsigned certificates verify on their steps alone
+        let unsigned = Prover::new(ProverConfig::default())
+            .generate_certificate(&nonnegative, Some(&statement), &steps);
+        let check = prover.verify_certificate(&unsigned);
+        assert!(check.is_valid());
+        assert_eq!(check.signature, SignatureStatus::Unsigned);
+    }
+
+    #[test]
+    fn test_pauli_rules() {
+        let word = |w: &str| Box::new(Expr::Identifier(w.to_string()));
+
+        let square = Expr::Mul(word("XZ"), word("XZ"));
+        assert_eq!(RewriteRule::PauliSquare.apply(&square), Some(*word("II")));
+        let power = Expr::Pow(word("Y"), Box::new(Expr::Number(2.0)));
+        assert_eq!(RewriteRule::PauliSquare.apply(&power), Some(*word("I")));
+        assert_eq!(RewriteRule::PauliSquare.apply(&Expr::Mul(word("XZ"), word("ZX"))), None);
+
+        // [X, Y] = 2iZ; XX and ZZ commute
+        let commutator = Expr::Commutator(word("X"), word("Y"));
+        assert_eq!(
+            RewriteRule::PauliCommutator.apply(&commutator),
+            Some(Expr::Mul(Box::new(Expr::ComplexNumber(C64::new(0.0, 2.0))), word("Z")))
+        );
+        let commuting = Expr::Commutator(word("XX"), word("ZZ"));
+        assert_eq!(RewriteRule::PauliCommutator.apply(&commuting), Some(Expr::Number(0.0)));
+
+        // {XX, ZZ} = 2 XX·ZZ = -2 YY
+        let anticommutator = Expr::AntiCommutator(word("XX"), word("ZZ"));
+        assert_eq!(
+            RewriteRule::PauliAnticommutator.apply(&anticommutator),
+            Some(Expr::Mul(Box::new(Expr::Number(-2.0)), word("YY")))
+        );
+        assert_eq!(RewriteRule::PauliCommutator.apply(&Expr::Commutator(word("A"), word("X"))), None);
+    }
+
+    #[test]
+    fn test_sampling_respects_assumptions() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+
+        let mut context = AssumptionContext::new();
+        context.add(Assumption::range("gamma", Some(0.0), Some(0.5)));
+        context.add(Assumption::sign("n", Sign::Positive));
+        context.add(Assumption::integer("n"));
+        context.add(Assumption::sign("kappa", Sign::Negative));
+        assert_eq!(context.free_params(), vec!["gamma", "n", "kappa"]);
+
+        for _ in 0..200 {
+            let gamma = context.sample("gamma", &mut rng).unwrap();
+            assert!((0.0..=0.5).contains(&gamma));
+            let n = context.sample("n", &mut rng).unwrap();
+            assert!(n >= 1.0 && n.fract() == 0.0);
+            assert!(context.sample("kappa", &mut rng).unwrap() < 0.0);
+        }
+
+        // 0.2 ≤ m ≤ 0.8 has no integer solution
+        context.add(Assumption::range("m", Some(0.2), Some(0.8)));
+        context.add(Assumption::integer("m"));
+        assert_eq!(context.sample("m", &mut rng), None);
+    }
+
+    #[test]
+    fn test_oversized_input_is_too_large() {
+        let mut prover = Prover::new(ProverConfig {
+            max_expr_size: 50,
+            ..ProverConfig::default()
+        });
+        let a = Expr::Identifier("A".to_string());
+        let result = prover.prove_identity(&daggers(1_000), &a);
+        assert!(matches!(result, ProofResult::Unknown(Reason::TooLarge)));
+    }
+
+    #[test]
+    fn test_pruned_search_is_too_large() {
+        // [A, B] only rewrites to -[B, A], which is over the size limit;
+        // without sampling, a counterexample cannot settle the goal instead
+        let commutator = Expr::Commutator(
+            Box::new(Expr::Identifier("A".to_string())),
+            Box::new(Expr::Identifier("B".to_string())),
+        );
+        let mut prover = Prover::new(ProverConfig {
+            max_expr_size: 3,
+            counterexample_samples: 0,
+            ..ProverConfig::default()
+        });
+        let result = prover.prove_identity(&commutator, &Expr::Identifier("C".to_string()));
+        assert!(matches!(result, ProofResult::Unknown(Reason::TooLarge)));
+    }
 }
@@ -0,0 +1,806 @@
+//! OpenQASM 2.0 and 3.0 import
+//!
+//! [`parse_qasm`] reads the straight-line part of OpenQASM shared by both
+//! versions: qubit and bit declarations (`qreg q[2];`, `qubit[2] q;`),
+//! calls of the standard gates, `barrier`, and measurements
+//! (`measure q[0] -> c[0];`, `c[0] = measure q[0];`). The qubits of all
+//! registers form one register in declaration order, and a gate called on
+//! whole registers is applied qubit by qubit. [`QasmCircuit::to_ast`]
+//! turns the circuit into an experiment that starts in `|0…0⟩`, applies
+//! the gates with `apply` statements and ends with one projective
+//! measurement of the measured qubits; [`QasmCircuit::to_dsl`] writes the
+//! same program as DSL source.
+//!
+//! Gate definitions, classical control, resets and mid-circuit
+//! measurements have no counterpart in an experiment and are rejected.
+//! `sdg`, `tdg` and the phase gates `p` and `u1` import as Z rotations,
+//! which differ from them only by a global phase.
+
+use crate::ast::*;
+use crate::error::{EngineError, Result};
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+/// Name of the measurement and experiment of an imported circuit
+const MEASUREMENT: &str = "readout";
+const EXPERIMENT: &str = "circuit";
+/// Name of the state gates apply to in the DSL
+const STATE: &str = "psi";
+
+/// A circuit read from OpenQASM
+#[derive(Debug, Clone)]
+pub struct QasmCircuit {
+    /// `2.0` or `3.0`, as declared
+    pub version: String,
+    /// Qubit registers with their sizes, in declaration order
+    pub registers: Vec<(String, usize)>,
+    pub gates: Vec<GateApplication>,
+    /// Qubits measured at the end, in the order first measured
+    pub measured: Vec<usize>,
+}
+
+impl QasmCircuit {
+    pub fn n_qubits(&self) -> usize {
+        self.registers.iter().map(|(_, size)| size).sum()
+    }
+
+    /// The measurement of the measured qubits: the computational basis
+    /// when that is all of them in order, else a projector per outcome
+    fn measurement(&self) -> Option<MeasurementSpec> {
+        let n = self.n_qubits();
+        if self.measured.is_empty() {
+            return None;
+        }
+        if self.measured.iter().copied().eq(0..n) {
+            return Some(MeasurementSpec::Basis {
+                basis: MeasurementBasis::Computational,
+                dim: 1 << n,
+            });
+        }
+        let k = self.measured.len();
+        let projectors = (0..1usize << k)
+            .map(|outcome| {
+                let selected = |index: usize| {
+                    self.measured.iter().enumerate().all(|(i, &q)| {
+                        let bit = (index >> (n - 1 - q)) & 1;
+                        bit == (outcome >> (k - 1 - i)) & 1
+                    })
+                };
+                let rows = (0..1usize << n)
+                    .map(|row| {
+                        (0..1usize << n)
+                            .map(|col| {
+                                Expr::Number(if row == col && selected(row) {
+                                    1.0
+                                } else {
+                                    0.0
+                                })
+                            })
+                            .collect()
+                    })
+                    .collect();
+                MatrixLiteral { rows }
+            })
+            .collect();
+        Some(MeasurementSpec::Projective { projectors })
+    }
+
+    fn initial_state(&self) -> VectorLiteral {
+        let elements = (0..1usize << self.n_qubits())
+            .map(|i| Expr::Number(if i == 0 { 1.0 } else { 0.0 }))
+            .collect();
+        VectorLiteral { elements }
+    }
+
+    /// The circuit as a program of one experiment, `circuit`, measured by
+    /// `readout`
+    pub fn to_ast(&self) -> Ast {
+        let mut statements = Vec::new();
+        let measurement = self.measurement();
+        if let Some(spec) = &measurement {
+            statements.push(Statement::MeasurementDef {
+                name: MEASUREMENT.to_string(),
+                spec: spec.clone(),
+            });
+        }
+        let measurements = measurement.map(|_| MeasurementSchedule {
+            events: vec![MeasurementEvent {
+                time: 0.0,
+                measurement_name: MEASUREMENT.to_string(),
+            }],
+        });
+        statements.push(Statement::Experiment {
+            name: EXPERIMENT.to_string(),
+            body: ExperimentBody {
+                init: Some(StateSpec::Ket(self.initial_state())),
+                evolution: None,
+                measurements,
+                channels: Vec::new(),
+                observables: Vec::new(),
+                gates: self.gates.clone(),
+            },
+        });
+        Ast {
+            statements,
+            source_map: SourceMap::default(),
+        }
+    }
+
+    /// The program of [`QasmCircuit::to_ast`] as DSL source
+    pub fn to_dsl(&self) -> String {
+        let mut out = String::new();
+        let mut offset = 0;
+        let layout: Vec<String> = self
+            .registers
+            .iter()
+            .map(|(name, size)| {
+                let range = format!(
+                    "{}[{}] = {}[{}..{}]",
+                    name,
+                    size,
+                    STATE,
+                    offset,
+                    offset + size
+                );
+                offset += size;
+                range
+            })
+            .collect();
+        let _ = writeln!(
+            out,
+            "// Imported from OpenQASM {}: {}",
+            self.version,
+            layout.join(", ")
+        );
+        out.push('\n');
+
+        match self.measurement() {
+            Some(MeasurementSpec::Basis { dim, .. }) => {
+                let _ = writeln!(out, "measure {}: computational({});\n", MEASUREMENT, dim);
+            }
+            Some(MeasurementSpec::Projective { projectors }) => {
+                let projectors: Vec<String> = projectors
+                    .into_iter()
+                    .map(|p| Expr::Matrix(p).to_string())
+                    .collect();
+                let _ = writeln!(
+                    out,
+                    "measure {}: Projective([{}]);\n",
+                    MEASUREMENT,
+                    projectors.join(", ")
+                );
+            }
+            _ => {}
+        }
+
+        let init: Vec<String> = self
+            .initial_state()
+            .elements
+            .iter()
+            .map(Expr::to_string)
+            .collect();
+        let _ = writeln!(out, "experiment {} {{", EXPERIMENT);
+        let _ = writeln!(out, "    init: ket(vec({}));", init.join(", "));
+        for gate in &self.gates {
+            let angle = gate
+                .angle
+                .as_ref()
+                .map_or_else(String::new, |angle| format!("({})", angle));
+            let targets: Vec<String> = gate.targets.iter().map(usize::to_string).collect();
+            let _ = writeln!(
+                out,
+                "    apply {}{} to {}[{}];",
+                gate.gate,
+                angle,
+                STATE,
+                targets.join(", ")
+            );
+        }
+        if !self.measured.is_empty() {
+            let _ = writeln!(out, "    measurements: [(0.0, {})];", MEASUREMENT);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Read an OpenQASM 2.0 or 3.0 circuit
+pub fn parse_qasm(source: &str) -> Result<QasmCircuit> {
+    let mut circuit = QasmCircuit {
+        version: String::new(),
+        registers: Vec::new(),
+        gates: Vec::new(),
+        measured: Vec::new(),
+    };
+    for (i, (statement, (line, col))) in statements(source).into_iter().enumerate() {
+        let error = |message: String| EngineError::parse_error(line, col, message);
+        let unsupported = |what: &str| {
+            EngineError::Unsupported(format!(
+                "line {}, column {}: {} cannot be imported",
+                line, col, what
+            ))
+        };
+        let (keyword, rest) = statement
+            .split_once(char::is_whitespace)
+            .map_or((statement.as_str(), ""), |(keyword, rest)| {
+                (keyword, rest.trim())
+            });
+
+        if i == 0 {
+            if keyword != "OPENQASM" {
+                return Err(error(
+                    "expected an 'OPENQASM 2.0;' or 'OPENQASM 3.0;' header".to_string(),
+                ));
+            }
+            circuit.version = match rest {
+                "2" | "2.0" => "2.0".to_string(),
+                "3" | "3.0" => "3.0".to_string(),
+                other => return Err(error(format!("unsupported OpenQASM version {}", other))),
+            };
+            continue;
+        }
+        // Leading word, for statements like `if(c == 1) x q[0]`
+        let head = statement
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or("");
+        match keyword {
+            // Before declarations, as in `bit[2] c = measure q`
+            _ if statement.contains("= measure ") || statement.contains("=measure ") => {
+                let (_, qubits) = statement.split_once("measure").unwrap();
+                circuit.measure(qubits.trim()).map_err(error)?;
+            }
+            "include" | "barrier" | "creg" => {}
+            _ if head == "bit" => {}
+            "qreg" => {
+                let (name, size) = register(rest)
+                    .ok_or_else(|| error(format!("expected name[size], got '{}'", rest)))?;
+                circuit.add_register(name, size).map_err(error)?;
+            }
+            _ if head == "qubit" => {
+                let declaration = statement["qubit".len()..].trim();
+                let (size, name) = match declaration.strip_prefix('[') {
+                    Some(sized) => sized
+                        .split_once(']')
+                        .and_then(|(size, name)| Some((size.trim().parse().ok()?, name.trim())))
+                        .ok_or_else(|| {
+                            error(format!("expected qubit[size] name, got '{}'", statement))
+                        })?,
+                    None => (1, declaration),
+                };
+                circuit
+                    .add_register(name.to_string(), size)
+                    .map_err(error)?;
+            }
+            "measure" => {
+                let qubits = rest
+                    .split_once("->")
+                    .map_or(rest, |(qubits, _)| qubits)
+                    .trim();
+                circuit.measure(qubits).map_err(error)?;
+            }
+            _ => match head {
+                "gate" | "opaque" | "def" | "defcal" => {
+                    return Err(unsupported("A gate definition"))
+                }
+                "if" | "for" | "while" => return Err(unsupported("Classical control")),
+                "reset" => return Err(unsupported("A reset")),
+                "input" | "output" | "const" | "let" | "float" | "angle" | "int" | "uint"
+                | "bool" => return Err(unsupported("A classical declaration")),
+                _ if statement.contains('@') => return Err(unsupported("A gate modifier")),
+                _ => {
+                    if !circuit.measured.is_empty() {
+                        return Err(unsupported("A gate after a measurement"));
+                    }
+                    circuit.call_gate(&statement).map_err(error)?;
+                }
+            },
+        }
+    }
+    if circuit.version.is_empty() {
+        return Err(EngineError::parse_error(1, 1, "empty OpenQASM program"));
+    }
+    if circuit.n_qubits() == 0 {
+        return Err(EngineError::validation_error(
+            "The circuit declares no qubits",
+        ));
+    }
+    Ok(circuit)
+}
+
+impl QasmCircuit {
+    fn add_register(&mut self, name: String, size: usize) -> std::result::Result<(), String> {
+        if size == 0 {
+            return Err(format!("register '{}' has no qubits", name));
+        }
+        if self.registers.iter().any(|(existing, _)| *existing == name) {
+            return Err(format!("register '{}' is declared twice", name));
+        }
+        self.registers.push((name, size));
+        Ok(())
+    }
+
+    /// Qubits of the whole register `name`, or of `name[index]`
+    fn qubits(&self, argument: &str) -> std::result::Result<Vec<usize>, String> {
+        let (name, index) = match register(argument) {
+            Some((name, index)) => (name, Some(index)),
+            None => (argument.trim().to_string(), None),
+        };
+        let mut offset = 0;
+        for (register, size) in &self.registers {
+            if *register == name {
+                return match index {
+                    Some(index) if index < *size => Ok(vec![offset + index]),
+                    Some(index) => Err(format!(
+                        "{}[{}] is out of range for {} qubits",
+                        name, index, size
+                    )),
+                    None => Ok((offset..offset + size).collect()),
+                };
+            }
+            offset += size;
+        }
+        Err(format!("unknown qubit register '{}'", name))
+    }
+
+    fn measure(&mut self, argument: &str) -> std::result::Result<(), String> {
+        for q in self.qubits(argument)? {
+            if !self.measured.contains(&q) {
+                self.measured.push(q);
+            }
+        }
+        Ok(())
+    }
+
+    /// `name[(angle)] q1, q2, ...`, applied qubit by qubit when the
+    /// arguments are whole registers
+    fn call_gate(&mut self, statement: &str) -> std::result::Result<(), String> {
+        let (name, parameter, arguments) = match statement.find('(') {
+            Some(open)
+                if statement[..open]
+                    .trim()
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                let close = statement
+                    .rfind(')')
+                    .ok_or("unclosed '(' in gate parameters")?;
+                (
+                    statement[..open].trim(),
+                    Some(&statement[open + 1..close]),
+                    statement[close + 1..].trim(),
+                )
+            }
+            _ => {
+                let (name, arguments) = statement
+                    .split_once(char::is_whitespace)
+                    .ok_or("expected a gate call")?;
+                (name, None, arguments.trim())
+            }
+        };
+        let (gate, angle) = match (name, parameter) {
+            ("h", None) => ("H", None),
+            ("x", None) => ("X", None),
+            ("y", None) => ("Y", None),
+            ("z", None) => ("Z", None),
+            ("s", None) => ("S", None),
+            ("t", None) => ("T", None),
+            ("sdg", None) => ("RZ", Some(-PI / 2.0)),
+            ("tdg", None) => ("RZ", Some(-PI / 4.0)),
+            ("id" | "i", None) => return Ok(()),
+            ("rx", Some(angle)) => ("RX", Some(evaluate_angle(angle)?)),
+            ("ry", Some(angle)) => ("RY", Some(evaluate_angle(angle)?)),
+            ("rz" | "p" | "phase" | "u1", Some(angle)) => ("RZ", Some(evaluate_angle(angle)?)),
+            ("cx" | "CX" | "cnot", None) => ("CNOT", None),
+            ("cz", None) => ("CZ", None),
+            ("swap", None) => ("SWAP", None),
+            (name, _) => return Err(format!("unsupported gate '{}' with these parameters", name)),
+        };
+        let arity = if matches!(gate, "CNOT" | "CZ" | "SWAP") {
+            2
+        } else {
+            1
+        };
+
+        let operands = arguments
+            .split(',')
+            .map(|argument| self.qubits(argument))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if operands.len() != arity {
+            return Err(format!(
+                "{} takes {} qubit(s), got {}",
+                name,
+                arity,
+                operands.len()
+            ));
+        }
+        // Whole registers broadcast, qubit by qubit, against single qubits
+        let width = operands.iter().map(Vec::len).max().unwrap_or(1);
+        if operands
+            .iter()
+            .any(|qubits| qubits.len() != 1 && qubits.len() != width)
+        {
+            return Err(format!("registers of different sizes in '{}'", statement));
+        }
+        for j in 0..width {
+            let targets: Vec<usize> = operands
+                .iter()
+                .map(|qubits| {
+                    if qubits.len() == 1 {
+                        qubits[0]
+                    } else {
+                        qubits[j]
+                    }
+                })
+                .collect();
+            if arity == 2 && targets[0] == targets[1] {
+                return Err(format!("{} applied twice to qubit {}", name, targets[0]));
+            }
+            self.gates.push(GateApplication {
+                gate: gate.to_string(),
+                angle: angle.map(Expr::Number),
+                targets,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// `name[n]`
+fn register(text: &str) -> Option<(String, usize)> {
+    let (name, rest) = text.trim().split_once('[')?;
+    let size = rest.strip_suffix(']')?.trim().parse().ok()?;
+    Some((name.trim().to_string(), size))
+}
+
+/// The statements of `source` without comments, whitespace collapsed, each
+/// with the line and column it starts at
+fn statements(source: &str) -> Vec<(String, (usize, usize))> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut start = None;
+    let (mut line, mut col) = (1, 1);
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let here = (line, col);
+        if c == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        col = 1;
+                        break;
+                    }
+                }
+                current.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                col += 1;
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        col = 1;
+                    } else {
+                        col += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                current.push(' ');
+            }
+            ';' => {
+                let statement = current.split_whitespace().collect::<Vec<_>>().join(" ");
+                if let Some(start) = start.take() {
+                    statements.push((statement, start));
+                }
+                current.clear();
+            }
+            c if c.is_whitespace() => current.push(' '),
+            c => {
+                start.get_or_insert(here);
+                current.push(c);
+            }
+        }
+    }
+    statements
+}
+
+/// Value of a gate parameter such as `pi/2`, `-3*pi/4` or `0.25`
+fn evaluate_angle(text: &str) -> std::result::Result<f64, String> {
+    let tokens = tokenize(text)?;
+    let mut parser = AngleParser { tokens, pos: 0 };
+    let value = parser.sum()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(value),
+        Some(token) => Err(format!(
+            "unexpected '{:?}' in parameter '{}'",
+            token,
+            text.trim()
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+}
+
+fn tokenize(text: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                let exponent_sign = (c == '-' || c == '+') && number.ends_with(['e', 'E']);
+                if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let value = number
+                .parse()
+                .map_err(|_| format!("invalid number '{}'", number))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if c == '*' {
+            chars.next();
+            // `**` is OpenQASM 3's power
+            if chars.peek() == Some(&'*') {
+                chars.next();
+                tokens.push(Token::Op('^'));
+            } else {
+                tokens.push(Token::Op('*'));
+            }
+        } else if "+-/^()".contains(c) {
+            chars.next();
+            tokens.push(Token::Op(c));
+        } else {
+            return Err(format!("unexpected '{}' in gate parameter", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over parameter expressions: sums of products of
+/// signed powers of atoms
+struct AngleParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl AngleParser {
+    fn next_op(&mut self, ops: &str) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(*op) => {
+                self.pos += 1;
+                Some(*op)
+            }
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> std::result::Result<f64, String> {
+        let mut value = self.product()?;
+        while let Some(op) = self.next_op("+-") {
+            let right = self.product()?;
+            value = if op == '+' {
+                value + right
+            } else {
+                value - right
+            };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> std::result::Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.next_op("*/") {
+            let right = self.unary()?;
+            value = if op == '*' {
+                value * right
+            } else {
+                value / right
+            };
+        }
+        Ok(value)
+    }
+
+    /// A sign binds more loosely than a power: `-2^2` is -4
+    fn unary(&mut self) -> std::result::Result<f64, String> {
+        match self.next_op("+-") {
+            Some('-') => Ok(-self.unary()?),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> std::result::Result<f64, String> {
+        let base = self.atom()?;
+        match self.next_op("^") {
+            Some(_) => Ok(base.powf(self.unary()?)),
+            None => Ok(base),
+        }
+    }
+
+    fn atom(&mut self) -> std::result::Result<f64, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("incomplete gate parameter")?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(value),
+            Token::Op('(') => {
+                let value = self.sum()?;
+                self.next_op(")").ok_or("expected ')' in gate parameter")?;
+                Ok(value)
+            }
+            Token::Name(name) if name == "pi" || name == "π" => Ok(PI),
+            Token::Name(name) => {
+                let function: fn(f64) -> f64 = match name.as_str() {
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "tan" => f64::tan,
+                    "exp" => f64::exp,
+                    "ln" => f64::ln,
+                    "sqrt" => f64::sqrt,
+                    _ => return Err(format!("unknown name '{}' in gate parameter", name)),
+                };
+                self.next_op("(")
+                    .ok_or_else(|| format!("expected '(' after {}", name))?;
+                let value = self.sum()?;
+                self.next_op(")").ok_or("expected ')' in gate parameter")?;
+                Ok(function(value))
+            }
+            Token::Op(op) => Err(format!("unexpected '{}' in gate parameter", op)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_qasm_versions() {
+        let qasm2 = r#"
+            OPENQASM 2.0;
+            include "qelib1.inc";
+            qreg q[2];
+            qreg anc[1];
+            creg c[3];
+            h q[0];
+            cx q[0], anc[0];  // entangle
+            rz(-pi/4) q[1];
+            barrier q;
+            /* final
+               readout */
+            measure q -> c;
+            measure anc[0] -> c[2];
+        "#;
+        let circuit = parse_qasm(qasm2).unwrap();
+        assert_eq!(circuit.version, "2.0");
+        assert_eq!(
+            circuit.registers,
+            [("q".to_string(), 2), ("anc".to_string(), 1)]
+        );
+        let gates: Vec<_> = circuit
+            .gates
+            .iter()
+            .map(|g| (g.gate.as_str(), g.targets.clone()))
+            .collect();
+        assert_eq!(
+            gates,
+            [("H", vec![0]), ("CNOT", vec![0, 2]), ("RZ", vec![1])]
+        );
+        assert_eq!(circuit.gates[2].angle, Some(Expr::Number(-PI / 4.0)));
+        assert_eq!(circuit.measured, [0, 1, 2]);
+
+        let qasm3 = "OPENQASM 3.0;\ninclude \"stdgates.inc\";\nqubit[2] q;\nbit[2] c;\n\
+                     h q;\nrx(2 * pi / 3) q[1];\nc[1] = measure q[1];";
+        let circuit = parse_qasm(qasm3).unwrap();
+        let gates: Vec<_> = circuit
+            .gates
+            .iter()
+            .map(|g| (g.gate.as_str(), g.targets.clone()))
+            .collect();
+        assert_eq!(gates, [("H", vec![0]), ("H", vec![1]), ("RX", vec![1])]);
+        assert!(
+            matches!(circuit.gates[2].angle, Some(Expr::Number(x)) if (x - 2.0 * PI / 3.0).abs() < 1e-15)
+        );
+        assert_eq!(circuit.measured, [1]);
+
+        // Only part of the register is measured: one projector per outcome
+        let Some(MeasurementSpec::Projective { projectors }) = circuit.measurement() else {
+            panic!("expected projectors");
+        };
+        assert_eq!(projectors.len(), 2);
+        let diagonal: Vec<_> = (0..4).map(|i| projectors[1].rows[i][i].clone()).collect();
+        assert_eq!(diagonal, [0.0, 1.0, 0.0, 1.0].map(Expr::Number));
+    }
+
+    #[test]
+    fn test_imported_circuit_runs() {
+        use crate::executor::{BackendConfig, Executor};
+
+        let bell =
+            "OPENQASM 2.0;\nqreg q[2];\ncreg c[2];\nh q[0];\ncx q[0], q[1];\nmeasure q -> c;";
+        let run = |ast: &Ast| {
+            let typed = crate::typechecker::TypeChecker::new().check(ast).unwrap();
+            let validated = crate::validator::QuantumValidator::new()
+                .validate(&typed)
+                .unwrap();
+            let ir = crate::lowering::Lowerer::new().lower(&validated).unwrap();
+            let result = Executor::new(BackendConfig::default())
+                .execute(&ir)
+                .unwrap();
+            result.experiment_results[0].measurements[0]
+                .probabilities
+                .clone()
+        };
+        let probabilities = run(&crate::parser::import_qasm(bell).unwrap());
+        let expected = [0.5, 0.0, 0.0, 0.5];
+        assert!(
+            probabilities
+                .iter()
+                .zip(expected)
+                .all(|(p, q)| (p - q).abs() < 1e-12),
+            "{:?}",
+            probabilities
+        );
+
+        // The DSL written out is the same program
+        let source = parse_qasm(bell).unwrap().to_dsl();
+        assert!(source.contains("apply CNOT to psi[0, 1];"), "{}", source);
+        assert_eq!(
+            run(&crate::parser::parse_dsl(&source).unwrap()),
+            probabilities
+        );
+    }
+
+    #[test]
+    fn test_parse_qasm_errors() {
+        let err = |source: &str| parse_qasm(source).unwrap_err().to_string();
+        assert!(err("qreg q[1];").contains("OPENQASM"));
+        assert!(err("OPENQASM 4.0;").contains("version 4.0"));
+        let u3 = err("OPENQASM 2.0;\nqreg q[1];\nu3(0, 0, 0) q[0];");
+        assert!(u3.contains("line 3") && u3.contains("'u3'"), "{}", u3);
+        assert!(err("OPENQASM 2.0;\nqreg q[1];\nh q[1];").contains("out of range"));
+        assert!(err("OPENQASM 2.0;\nqreg q[1];\ngate g a { h a; }").contains("gate definition"));
+        assert!(
+            err("OPENQASM 2.0;\nqreg q[1];\ncreg c[1];\nmeasure q -> c;\nh q[0];")
+                .contains("after a measurement")
+        );
+        assert!(err("OPENQASM 3;\nqubit[2] q;\ncx q[0], q[0];").contains("twice"));
+        assert_eq!(
+            evaluate_angle("-(pi/2)^2 + sqrt(4)").unwrap(),
+            -(PI / 2.0).powf(2.0) + 2.0
+        );
+        assert_eq!(evaluate_angle("1.5e-1 ** 2").unwrap(), 0.15f64.powf(2.0));
+        assert!(evaluate_angle("theta").is_err());
+    }
+}
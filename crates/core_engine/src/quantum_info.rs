@@ -0,0 +1,177 @@
+//! Distances between quantum states and processes
+//!
+//! For comparing a simulated state with a target, or two simulations with
+//! each other: the Uhlmann fidelity and trace distance of density matrices,
+//! and the process fidelity of unitaries. For a qubit, [`bloch_vector`]
+//! places a state on (or, when mixed, inside) the Bloch sphere.
+
+use crate::error::{EngineError, Result};
+use crate::kernels_cpu::{dagger, eigh_column_major};
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{Eigh, UPLO};
+use num_complex::Complex64;
+
+/// Uhlmann fidelity `(Tr √(√ρ σ √ρ))²`: 1 for equal states, 0 for states
+/// with orthogonal support, and `|⟨ψ|φ⟩|²` for pure states
+pub fn state_fidelity(rho: &Array2<Complex64>, sigma: &Array2<Complex64>) -> Result<f64> {
+    check_same_shape(rho, sigma)?;
+    let sqrt_rho = psd_sqrt(rho)?;
+    let overlap = sqrt_rho.dot(sigma).dot(&sqrt_rho);
+    let root_trace: f64 = psd_roots(&eigenvalues(&overlap)?).sum();
+    Ok(root_trace * root_trace)
+}
+
+/// Trace distance `½ Tr|ρ - σ|`: the largest difference in probability any
+/// measurement can show between the two states
+pub fn trace_distance(rho: &Array2<Complex64>, sigma: &Array2<Complex64>) -> Result<f64> {
+    check_same_shape(rho, sigma)?;
+    Ok(eigenvalues(&(rho - sigma))?
+        .iter()
+        .map(|lambda| lambda.abs())
+        .sum::<f64>()
+        / 2.0)
+}
+
+/// Process (entanglement) fidelity `|Tr(V†U)|² / d²` of unitary `u` to `v`,
+/// insensitive to global phase
+pub fn process_fidelity(u: &Array2<Complex64>, v: &Array2<Complex64>) -> Result<f64> {
+    check_same_shape(u, v)?;
+    let d = u.nrows() as f64;
+    let overlap: Complex64 = dagger(v).dot(u).diag().sum();
+    Ok(overlap.norm_sqr() / (d * d))
+}
+
+/// Bloch vector `(⟨σx⟩, ⟨σy⟩, ⟨σz⟩)` of a qubit density matrix, of length 1
+/// for pure states and less for mixed ones
+pub fn bloch_vector(rho: &Array2<Complex64>) -> Result<(f64, f64, f64)> {
+    if rho.dim() != (2, 2) {
+        return Err(EngineError::dimension_mismatch(
+            "(2, 2)".to_string(),
+            format!("{:?}", rho.dim()),
+        ));
+    }
+    // ρ = (I + x σx + y σy + z σz) / 2
+    let coherence = rho[[1, 0]];
+    Ok((
+        2.0 * coherence.re,
+        2.0 * coherence.im,
+        (rho[[0, 0]] - rho[[1, 1]]).re,
+    ))
+}
+
+fn check_same_shape(a: &Array2<Complex64>, b: &Array2<Complex64>) -> Result<()> {
+    if !a.is_square() || a.dim() != b.dim() {
+        return Err(EngineError::dimension_mismatch(
+            format!("{:?}", a.dim()),
+            format!("{:?}", b.dim()),
+        ));
+    }
+    Ok(())
+}
+
+fn eigenvalues(mat: &Array2<Complex64>) -> Result<Array1<f64>> {
+    let (values, _) = mat
+        .eigh(UPLO::Lower)
+        .map_err(|e| EngineError::ExecutionError(format!("Diagonalisation failed: {}", e)))?;
+    Ok(values)
+}
+
+/// Square root of a positive semidefinite matrix
+fn psd_sqrt(mat: &Array2<Complex64>) -> Result<Array2<Complex64>> {
+    let (values, vectors) = eigh_column_major(mat)?;
+    let roots: Array1<Complex64> = psd_roots(&values).map(|r| Complex64::new(r, 0.0)).collect();
+    let roots = Array2::from_diag(&roots);
+    Ok(vectors.dot(&roots).dot(&dagger(&vectors)))
+}
+
+/// Square roots of the eigenvalues of a positive semidefinite matrix.
+/// Eigenvalues within rounding of zero, of either sign, are taken as zero:
+/// the root would turn an error of 1e-16 into one of 1e-8
+fn psd_roots(values: &Array1<f64>) -> impl Iterator<Item = f64> + '_ {
+    let largest = values.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+    let cutoff = values.len() as f64 * f64::EPSILON * largest;
+    values
+        .iter()
+        .map(move |&v| if v > cutoff { v.sqrt() } else { 0.0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernels_cpu::ket_to_rho;
+    use approx::assert_relative_eq;
+    use ndarray::{array, Array1};
+
+    fn ket(amplitudes: &[f64]) -> Array2<Complex64> {
+        ket_to_rho(&Array1::from_iter(
+            amplitudes.iter().map(|&a| Complex64::new(a, 0.0)),
+        ))
+    }
+
+    #[test]
+    fn test_state_fidelity_and_trace_distance() {
+        let zero = ket(&[1.0, 0.0]);
+        let one = ket(&[0.0, 1.0]);
+        let plus = ket(&[0.5f64.sqrt(), 0.5f64.sqrt()]);
+        let mixed = Array2::<Complex64>::eye(2).mapv(|x| x * 0.5);
+
+        assert_relative_eq!(state_fidelity(&zero, &zero).unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(state_fidelity(&zero, &one).unwrap(), 0.0, epsilon = 1e-10);
+        assert_relative_eq!(state_fidelity(&zero, &plus).unwrap(), 0.5, epsilon = 1e-10);
+        assert_relative_eq!(state_fidelity(&mixed, &zero).unwrap(), 0.5, epsilon = 1e-10);
+
+        // Complex amplitudes: (|0⟩ + i|1⟩)/√2 against itself and its conjugate
+        let plus_i = ket_to_rho(&ndarray::array![
+            Complex64::new(0.5f64.sqrt(), 0.0),
+            Complex64::new(0.0, 0.5f64.sqrt())
+        ]);
+        let minus_i = plus_i.mapv(|z| z.conj());
+        assert_relative_eq!(state_fidelity(&plus_i, &plus_i).unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(state_fidelity(&plus_i, &minus_i).unwrap(), 0.0, epsilon = 1e-10);
+
+        assert_relative_eq!(trace_distance(&zero, &one).unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(trace_distance(&mixed, &zero).unwrap(), 0.5, epsilon = 1e-10);
+        assert_relative_eq!(
+            trace_distance(&zero, &plus).unwrap(),
+            0.5f64.sqrt(),
+            epsilon = 1e-10
+        );
+
+        assert!(state_fidelity(&zero, &Array2::eye(4)).is_err());
+    }
+
+    #[test]
+    fn test_bloch_vector() {
+        let (x, y, z) = bloch_vector(&ket(&[0.5f64.sqrt(), 0.5f64.sqrt()])).unwrap();
+        assert_relative_eq!(x, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(y, 0.0, epsilon = 1e-12);
+        assert_relative_eq!(z, 0.0, epsilon = 1e-12);
+
+        // (|0⟩ + i|1⟩)/√2 points along +y
+        let s = 0.5f64.sqrt();
+        let plus_i = ket_to_rho(&Array1::from(vec![
+            Complex64::new(s, 0.0),
+            Complex64::new(0.0, s),
+        ]));
+        let (_, y, _) = bloch_vector(&plus_i).unwrap();
+        assert_relative_eq!(y, 1.0, epsilon = 1e-12);
+
+        let mixed =
+            Array2::<Complex64>::eye(2).mapv(|x| x * 0.25) + ket(&[1.0, 0.0]).mapv(|x| x * 0.5);
+        let (x, y, z) = bloch_vector(&mixed).unwrap();
+        assert_relative_eq!((x * x + y * y + z * z).sqrt(), 0.5, epsilon = 1e-12);
+
+        assert!(bloch_vector(&Array2::eye(3)).is_err());
+    }
+
+    #[test]
+    fn test_process_fidelity() {
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let x = array![[c(0.0, 0.0), c(1.0, 0.0)], [c(1.0, 0.0), c(0.0, 0.0)]];
+        let z = array![[c(1.0, 0.0), c(0.0, 0.0)], [c(0.0, 0.0), c(-1.0, 0.0)]];
+        let phased = x.mapv(|e| e * c(0.0, 1.0));
+
+        assert_relative_eq!(process_fidelity(&x, &phased).unwrap(), 1.0, epsilon = 1e-12);
+        assert_relative_eq!(process_fidelity(&x, &z).unwrap(), 0.0, epsilon = 1e-12);
+    }
+}
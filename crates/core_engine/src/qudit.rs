@@ -0,0 +1,218 @@
+//! Operators on qudits
+//!
+//! The Pauli matrices are the qubit case of several families that exist in
+//! every dimension `d`:
+//!
+//! - the generalized Gell-Mann matrices, `d² - 1` traceless Hermitian
+//!   matrices orthogonal under `Tr(A B) = 2 δ`, ordered so that `d = 2`
+//!   gives σx, σy, σz and `d = 3` the usual λ1 … λ8;
+//! - the spin-`j` operators Jx, Jy, Jz on `2j + 1` levels, in the basis
+//!   `m = j, j - 1, …, -j`, so that spin 1/2 gives σ/2;
+//! - the clock and shift operators Z|k⟩ = ωᵏ|k⟩ and X|k⟩ = |k + 1 mod d⟩
+//!   with ω = e^{2πi/d}.
+//!
+//! The DSL builds them with `gellmann(d, k)`, `spin_x(j)`, `spin_y(j)`,
+//! `spin_z(j)`, `clock(d)` and `shift(d)`; see [`crate::builtins`].
+
+use crate::error::{EngineError, Result};
+use ndarray::Array2;
+use num_complex::Complex64;
+
+/// Generalized Gell-Mann matrix `k` of dimension `d`, for `k` from 1 to
+/// `d² - 1`
+///
+/// For each level `l` from 1 to `d - 1` in turn come the symmetric and
+/// antisymmetric matrices coupling each lower level to `l`, then the
+/// diagonal matrix of the levels up to `l`.
+pub fn gell_mann(d: usize, k: usize) -> Result<Array2<Complex64>> {
+    check_dim(d)?;
+    if k == 0 || k >= d * d {
+        return Err(EngineError::validation_error(format!(
+            "Gell-Mann index {} out of range 1..={} for dimension {}",
+            k,
+            d * d - 1,
+            d
+        )));
+    }
+
+    let mut remaining = k - 1;
+    for l in 1..d {
+        // 2l off-diagonal matrices coupling levels below l to l, then one
+        // diagonal matrix
+        if remaining < 2 * l {
+            let (j, antisymmetric) = (remaining / 2, remaining % 2 == 1);
+            let mut m = Array2::zeros((d, d));
+            if antisymmetric {
+                m[[j, l]] = Complex64::new(0.0, -1.0);
+                m[[l, j]] = Complex64::new(0.0, 1.0);
+            } else {
+                m[[j, l]] = Complex64::new(1.0, 0.0);
+                m[[l, j]] = Complex64::new(1.0, 0.0);
+            }
+            return Ok(m);
+        }
+        if remaining == 2 * l {
+            let scale = (2.0 / (l * (l + 1)) as f64).sqrt();
+            let mut m = Array2::zeros((d, d));
+            for n in 0..l {
+                m[[n, n]] = Complex64::new(scale, 0.0);
+            }
+            m[[l, l]] = Complex64::new(-scale * l as f64, 0.0);
+            return Ok(m);
+        }
+        remaining -= 2 * l + 1;
+    }
+    unreachable!("index checked against d² - 1")
+}
+
+/// Number of levels `2j + 1` of spin `j`, which must be a positive multiple
+/// of 1/2
+pub fn spin_dim(j: f64) -> Result<usize> {
+    let twice = 2.0 * j;
+    if !(twice >= 1.0 && twice.fract() == 0.0) {
+        return Err(EngineError::validation_error(format!(
+            "Spin {} is not a positive multiple of 1/2",
+            j
+        )));
+    }
+    Ok(twice as usize + 1)
+}
+
+/// Jx = (J+ + J-) / 2 for spin `j`
+pub fn spin_x(j: f64) -> Result<Array2<Complex64>> {
+    let raise = raising(j)?;
+    Ok((&raise + &lowering(&raise)) * Complex64::new(0.5, 0.0))
+}
+
+/// Jy = (J+ - J-) / 2i for spin `j`
+pub fn spin_y(j: f64) -> Result<Array2<Complex64>> {
+    let raise = raising(j)?;
+    Ok((&raise - &lowering(&raise)) * Complex64::new(0.0, -0.5))
+}
+
+/// Jz = diag(j, j - 1, …, -j) for spin `j`
+pub fn spin_z(j: f64) -> Result<Array2<Complex64>> {
+    let n = spin_dim(j)?;
+    let mut m = Array2::zeros((n, n));
+    for k in 0..n {
+        m[[k, k]] = Complex64::new(j - k as f64, 0.0);
+    }
+    Ok(m)
+}
+
+/// J+ with J+|m⟩ = √(j(j+1) - m(m+1)) |m+1⟩, where level `k` is `m = j - k`
+fn raising(j: f64) -> Result<Array2<Complex64>> {
+    let n = spin_dim(j)?;
+    let mut m = Array2::zeros((n, n));
+    for k in 1..n {
+        let below = j - k as f64;
+        m[[k - 1, k]] = Complex64::new((j * (j + 1.0) - below * (below + 1.0)).sqrt(), 0.0);
+    }
+    Ok(m)
+}
+
+/// J- = (J+)†, which is real
+fn lowering(raise: &Array2<Complex64>) -> Array2<Complex64> {
+    raise.t().to_owned()
+}
+
+/// Clock operator diag(1, ω, …, ω^{d-1}) with ω = e^{2πi/d}
+pub fn clock(d: usize) -> Result<Array2<Complex64>> {
+    check_dim(d)?;
+    let mut m = Array2::zeros((d, d));
+    for k in 0..d {
+        m[[k, k]] = Complex64::from_polar(1.0, 2.0 * std::f64::consts::PI * k as f64 / d as f64);
+    }
+    Ok(m)
+}
+
+/// Shift operator taking |k⟩ to |k + 1 mod d⟩
+pub fn shift(d: usize) -> Result<Array2<Complex64>> {
+    check_dim(d)?;
+    let mut m = Array2::zeros((d, d));
+    for k in 0..d {
+        m[[(k + 1) % d, k]] = Complex64::new(1.0, 0.0);
+    }
+    Ok(m)
+}
+
+fn check_dim(d: usize) -> Result<()> {
+    if d < 2 {
+        return Err(EngineError::validation_error(format!(
+            "Qudit dimension must be at least 2, got {}",
+            d
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Array2<Complex64>, b: &Array2<Complex64>) -> bool {
+        a.dim() == b.dim() && (a - b).iter().all(|z| z.norm() < 1e-12)
+    }
+
+    fn trace_of_product(a: &Array2<Complex64>, b: &Array2<Complex64>) -> Complex64 {
+        a.dot(b).diag().sum()
+    }
+
+    #[test]
+    fn test_gell_mann_matrices() {
+        // The qubit case is the Pauli matrices
+        let c = |re: f64, im: f64| Complex64::new(re, im);
+        let sigma_y = Array2::from_shape_vec(
+            (2, 2),
+            vec![c(0.0, 0.0), c(0.0, -1.0), c(0.0, 1.0), c(0.0, 0.0)],
+        )
+        .unwrap();
+        assert!(close(&gell_mann(2, 2).unwrap(), &sigma_y));
+        // λ8 = diag(1, 1, -2) / √3
+        let lambda8 = gell_mann(3, 8).unwrap();
+        assert!((lambda8[[2, 2]].re + 2.0 / 3f64.sqrt()).abs() < 1e-12);
+
+        for d in 2..=4 {
+            let all: Vec<_> = (1..d * d).map(|k| gell_mann(d, k).unwrap()).collect();
+            for (a, m) in all.iter().enumerate() {
+                assert!(close(m, &m.t().mapv(|z| z.conj())));
+                assert!(m.diag().sum().norm() < 1e-12);
+                for (b, n) in all.iter().enumerate() {
+                    let expected = if a == b { 2.0 } else { 0.0 };
+                    assert!((trace_of_product(m, n) - expected).norm() < 1e-12);
+                }
+            }
+            assert!(gell_mann(d, 0).is_err());
+            assert!(gell_mann(d, d * d).is_err());
+        }
+    }
+
+    #[test]
+    fn test_spin_operators() {
+        for j in [0.5, 1.0, 1.5, 2.0] {
+            let (x, y, z) = (spin_x(j).unwrap(), spin_y(j).unwrap(), spin_z(j).unwrap());
+            let n = spin_dim(j).unwrap();
+            assert_eq!(x.dim(), (n, n));
+            // [Jx, Jy] = i Jz
+            let commutator = x.dot(&y) - y.dot(&x);
+            assert!(close(&commutator, &z.mapv(|v| v * Complex64::i())));
+            // J² = j(j+1)
+            let casimir = x.dot(&x) + y.dot(&y) + z.dot(&z);
+            let expected = Array2::eye(n).mapv(|v: f64| Complex64::new(v * j * (j + 1.0), 0.0));
+            assert!(close(&casimir, &expected));
+        }
+        assert!(spin_dim(0.25).is_err());
+        assert!(spin_dim(0.0).is_err());
+    }
+
+    #[test]
+    fn test_clock_and_shift() {
+        let d = 3;
+        let (z, x) = (clock(d).unwrap(), shift(d).unwrap());
+        // ZX = ω XZ
+        let omega = Complex64::from_polar(1.0, 2.0 * std::f64::consts::PI / d as f64);
+        assert!(close(&z.dot(&x), &x.dot(&z).mapv(|v| v * omega)));
+        assert_eq!(x[[0, 2]], Complex64::new(1.0, 0.0));
+        assert!(shift(1).is_err());
+    }
+}
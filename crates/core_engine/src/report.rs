@@ -0,0 +1,681 @@
+//! Model reports for lab notebooks and supplementary material
+//!
+//! [`ModelReport`] summarizes a parsed program: its Hamiltonians (as LaTeX),
+//! declared constants, parameters and their constraints, operator and
+//! Hilbert-space dimensions, measurements and experiments, and the outcome of
+//! every type and quantum validation check. [`ModelReport::render`] writes it
+//! as Markdown or as a LaTeX fragment ready to `\input`.
+//!
+//! Unlike the pipeline, which stops at the first failure, each Hamiltonian,
+//! measurement and initial state is checked on its own, so one bad
+//! declaration doesn't hide the outcome of the others.
+
+use crate::ast::{Ast, Expr, MeasurementSpec, StateSpec, Statement, SweepValues, TimeGrid};
+use crate::error::{EngineError, Result};
+use crate::typechecker::{Shape, TypeChecker};
+use crate::validator::{GoalStatus, QuantumValidator};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Output format of a [`ModelReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Latex,
+}
+
+impl FromStr for ReportFormat {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "tex" | "latex" => Ok(ReportFormat::Latex),
+            other => Err(EngineError::validation_error(format!(
+                "Unknown report format '{}', expected md or tex",
+                other
+            ))),
+        }
+    }
+}
+
+/// Summary of a model and its validation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelReport {
+    pub title: String,
+    pub hamiltonians: Vec<HamiltonianEntry>,
+    pub parameters: Vec<ParameterEntry>,
+    /// Declared matrices and their shapes
+    pub operators: Vec<(String, String)>,
+    pub measurements: Vec<MeasurementEntry>,
+    pub experiments: Vec<ExperimentEntry>,
+    pub checks: Vec<CheckOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HamiltonianEntry {
+    pub name: String,
+    /// `H(\omega) = \ldots`
+    pub latex: String,
+    pub dim: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterEntry {
+    pub name: String,
+    /// const, param, symbol or sweep
+    pub kind: String,
+    /// Value, range or swept values, as LaTeX
+    pub constraint: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeasurementEntry {
+    pub name: String,
+    pub kind: String,
+    pub outcomes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentEntry {
+    pub name: String,
+    pub hamiltonian: Option<String>,
+    pub dim: Option<usize>,
+    pub time_points: usize,
+    pub dissipators: usize,
+    pub measurement_events: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckOutcome {
+    pub check: String,
+    pub status: CheckStatus,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// Depends on values only known at run time
+    Skipped,
+}
+
+impl ModelReport {
+    /// Report on `ast`, titled `title`
+    ///
+    /// Sweep blocks contribute their parameter to the parameter table and
+    /// are checked at the first swept value.
+    pub fn new(title: impl Into<String>, ast: &Ast) -> Self {
+        let statements = flatten_sweeps(&ast.statements);
+        let flat = Ast::new(statements.clone());
+
+        let mut checks = Vec::new();
+        let shapes = match TypeChecker::new().check(&flat) {
+            Ok(typed) => {
+                checks.push(CheckOutcome::passed("Type check"));
+                typed.shapes
+            }
+            Err(e) => {
+                checks.push(CheckOutcome::failed("Type check", &e));
+                Default::default()
+            }
+        };
+        let dim = |name: &str| match shapes.get(name) {
+            Some(Shape::Matrix(n, _)) => Some(*n),
+            _ => None,
+        };
+
+        let mut report = ModelReport {
+            title: title.into(),
+            hamiltonians: Vec::new(),
+            parameters: Vec::new(),
+            operators: Vec::new(),
+            measurements: Vec::new(),
+            experiments: Vec::new(),
+            checks,
+        };
+        collect_parameters(&ast.statements, &mut report.parameters);
+
+        // Each check runs on the declarations before it, leaving out earlier
+        // checks so their failures aren't attributed to it
+        let mut context: Vec<Statement> = Vec::new();
+        for stmt in &statements {
+            match stmt {
+                Statement::MatrixDecl { name, .. } | Statement::OperatorDecl { name, .. } => {
+                    let shape = match shapes.get(name) {
+                        Some(Shape::Matrix(n, m)) => format!("{}×{}", n, m),
+                        _ => "?".to_string(),
+                    };
+                    report.operators.push((name.clone(), shape));
+                    context.push(stmt.clone());
+                }
+                Statement::HamiltonianDef { name, params, expr } => {
+                    let head = if params.is_empty() {
+                        latex_name(name)
+                    } else {
+                        let params: Vec<String> = params.iter().map(|p| latex_name(p)).collect();
+                        format!("{}\\left({}\\right)", latex_name(name), params.join(", "))
+                    };
+                    report.hamiltonians.push(HamiltonianEntry {
+                        name: name.clone(),
+                        latex: format!("{} = {}", head, expr.to_latex()),
+                        dim: dim(name),
+                    });
+
+                    let check = format!("Hamiltonian {} is Hermitian", name);
+                    let outcome = match validate_with(&context, stmt) {
+                        Ok(results) if results.hermitian_operators.contains_key(name) => {
+                            CheckOutcome::passed(check)
+                        }
+                        Ok(_) => CheckOutcome::skipped(check, "depends on unbound parameters"),
+                        Err(e) => CheckOutcome::failed(check, &e),
+                    };
+                    if outcome.status != CheckStatus::Failed {
+                        context.push(stmt.clone());
+                    }
+                    report.checks.push(outcome);
+                }
+                Statement::MeasurementDef { name, spec } => {
+                    let (kind, outcomes, checked) = match spec {
+                        MeasurementSpec::Projective { projectors } => {
+                            ("projective".to_string(), projectors.len(), true)
+                        }
+                        MeasurementSpec::POVM { effects } => {
+                            ("POVM".to_string(), effects.len(), true)
+                        }
+                        MeasurementSpec::Basis { basis, dim } => {
+                            (format!("{:?} basis", basis), *dim, false)
+                        }
+                    };
+                    report.measurements.push(MeasurementEntry {
+                        name: name.clone(),
+                        kind,
+                        outcomes,
+                    });
+                    // Bases are complete and orthonormal by construction
+                    if checked {
+                        let check = format!("Measurement {} is complete", name);
+                        report.checks.push(match validate_with(&context, stmt) {
+                            Ok(_) => CheckOutcome::passed(check),
+                            Err(e) => CheckOutcome::failed(check, &e),
+                        });
+                    }
+                }
+                Statement::ChannelDef { name, .. } => {
+                    let check = format!("Channel {} is trace preserving", name);
+                    let outcome = match validate_with(&context, stmt) {
+                        Ok(results) if results.cptp_channels.contains_key(name) => {
+                            CheckOutcome::passed(check)
+                        }
+                        Ok(_) => CheckOutcome::skipped(check, "depends on unbound parameters"),
+                        Err(e) => CheckOutcome::failed(check, &e),
+                    };
+                    if outcome.status != CheckStatus::Failed {
+                        context.push(stmt.clone());
+                    }
+                    report.checks.push(outcome);
+                }
+                Statement::Experiment { name, body } => {
+                    let hamiltonian = body.evolution.as_ref().map(|e| e.hamiltonian_name.clone());
+                    report.experiments.push(ExperimentEntry {
+                        name: name.clone(),
+                        dim: hamiltonian.as_deref().and_then(dim),
+                        hamiltonian,
+                        time_points: body.evolution.as_ref().map_or(0, |e| match &e.timegrid {
+                            TimeGrid::Regular { n_steps, .. } => *n_steps,
+                            TimeGrid::Explicit { times } => times.len(),
+                        }),
+                        dissipators: body.evolution.as_ref().map_or(0, |e| e.lindblad_ops.len()),
+                        measurement_events: body
+                            .measurements
+                            .as_ref()
+                            .map_or(0, |m| m.events.len()),
+                    });
+                    if let Some(init) = &body.init {
+                        let check = match init {
+                            StateSpec::Ket(_) => format!("Initial state of {} is normalized", name),
+                            StateSpec::Rho(_) => {
+                                format!("Initial state of {} is a density matrix", name)
+                            }
+                            // Checked with the experiment it comes from
+                            StateSpec::From(_) => continue,
+                            StateSpec::Eigenstate { hamiltonian, level } => {
+                                format!("Initial state of {} is level {} of {}", name, level, hamiltonian)
+                            }
+                        };
+                        report.checks.push(match validate_with(&context, stmt) {
+                            Ok(_) => CheckOutcome::passed(check),
+                            Err(e) => CheckOutcome::failed(check, &e),
+                        });
+                    }
+                }
+                Statement::Prove(goal) => {
+                    let check = format!("Goal {} holds", goal);
+                    report.checks.push(match validate_with(&context, stmt) {
+                        Ok(results) => match results.goals.last().map(|g| &g.status) {
+                            Some(GoalStatus::Undecided { reason }) => CheckOutcome::skipped(check, reason),
+                            _ => CheckOutcome::passed(check),
+                        },
+                        Err(e) => CheckOutcome::failed(check, &e),
+                    });
+                }
+                _ => context.push(stmt.clone()),
+            }
+        }
+
+        report
+    }
+
+    /// Whether every check that ran passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Failed)
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Latex => self.to_latex(),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title);
+
+        if !self.hamiltonians.is_empty() {
+            out.push_str("\n## Hamiltonians\n");
+            for h in &self.hamiltonians {
+                out.push_str(&format!("\n$$\n{}\n$$\n", h.latex));
+                if let Some(dim) = h.dim {
+                    out.push_str(&format!("\nDimension {}.\n", dim));
+                }
+            }
+        }
+
+        if !self.parameters.is_empty() {
+            out.push_str(
+                "\n## Parameters and constraints\n\n| Name | Kind | Constraint |\n|---|---|---|\n",
+            );
+            for p in &self.parameters {
+                out.push_str(&format!(
+                    "| `{}` | {} | ${}$ |\n",
+                    p.name, p.kind, p.constraint
+                ));
+            }
+        }
+
+        if !self.operators.is_empty() {
+            out.push_str("\n## Operators\n\n| Name | Shape |\n|---|---|\n");
+            for (name, shape) in &self.operators {
+                out.push_str(&format!("| `{}` | {} |\n", name, shape));
+            }
+        }
+
+        if !self.measurements.is_empty() {
+            out.push_str("\n## Measurements\n\n| Name | Kind | Outcomes |\n|---|---|---|\n");
+            for m in &self.measurements {
+                out.push_str(&format!("| `{}` | {} | {} |\n", m.name, m.kind, m.outcomes));
+            }
+        }
+
+        if !self.experiments.is_empty() {
+            out.push_str(
+                "\n## Experiments\n\n| Name | Hamiltonian | Dimension | Time points | Dissipators | Measurements |\n|---|---|---|---|---|---|\n",
+            );
+            for e in &self.experiments {
+                out.push_str(&format!(
+                    "| `{}` | {} | {} | {} | {} | {} |\n",
+                    e.name,
+                    e.hamiltonian
+                        .as_deref()
+                        .map_or("–".to_string(), |h| format!("`{}`", h)),
+                    e.dim.map_or("?".to_string(), |d| d.to_string()),
+                    e.time_points,
+                    e.dissipators,
+                    e.measurement_events
+                ));
+            }
+        }
+
+        out.push_str("\n## Validation\n\n| Check | Result |\n|---|---|\n");
+        for c in &self.checks {
+            let result = match c.status {
+                CheckStatus::Passed => "✓ passed",
+                CheckStatus::Failed => "✗ failed",
+                CheckStatus::Skipped => "– skipped",
+            };
+            match &c.detail {
+                Some(detail) => out.push_str(&format!(
+                    "| {} | {}: {} |\n",
+                    c.check,
+                    result,
+                    detail.replace('|', "\\|")
+                )),
+                None => out.push_str(&format!("| {} | {} |\n", c.check, result)),
+            }
+        }
+        out
+    }
+
+    pub fn to_latex(&self) -> String {
+        let mut out = format!("\\section*{{{}}}\n", escape_text(&self.title));
+
+        if !self.hamiltonians.is_empty() {
+            out.push_str("\n\\subsection*{Hamiltonians}\n");
+            for h in &self.hamiltonians {
+                out.push_str(&format!(
+                    "\\begin{{equation*}}\n  {}\n\\end{{equation*}}\n",
+                    h.latex
+                ));
+                if let Some(dim) = h.dim {
+                    out.push_str(&format!("Dimension {}.\n", dim));
+                }
+            }
+        }
+
+        if !self.parameters.is_empty() {
+            out.push_str("\n\\subsection*{Parameters and constraints}\n");
+            let rows: Vec<String> = self
+                .parameters
+                .iter()
+                .map(|p| format!("{} & {} & ${}$", escape_text(&p.name), p.kind, p.constraint))
+                .collect();
+            out.push_str(&latex_table("lll", "Name & Kind & Constraint", &rows));
+        }
+
+        if !self.operators.is_empty() {
+            out.push_str("\n\\subsection*{Operators}\n");
+            let rows: Vec<String> = self
+                .operators
+                .iter()
+                .map(|(name, shape)| {
+                    format!(
+                        "{} & ${}$",
+                        escape_text(name),
+                        shape.replace('×', "\\times ")
+                    )
+                })
+                .collect();
+            out.push_str(&latex_table("ll", "Name & Shape", &rows));
+        }
+
+        if !self.measurements.is_empty() {
+            out.push_str("\n\\subsection*{Measurements}\n");
+            let rows: Vec<String> = self
+                .measurements
+                .iter()
+                .map(|m| format!("{} & {} & {}", escape_text(&m.name), m.kind, m.outcomes))
+                .collect();
+            out.push_str(&latex_table("llr", "Name & Kind & Outcomes", &rows));
+        }
+
+        if !self.experiments.is_empty() {
+            out.push_str("\n\\subsection*{Experiments}\n");
+            let rows: Vec<String> = self
+                .experiments
+                .iter()
+                .map(|e| {
+                    format!(
+                        "{} & {} & {} & {} & {} & {}",
+                        escape_text(&e.name),
+                        e.hamiltonian
+                            .as_deref()
+                            .map_or("--".to_string(), escape_text),
+                        e.dim.map_or("?".to_string(), |d| d.to_string()),
+                        e.time_points,
+                        e.dissipators,
+                        e.measurement_events
+                    )
+                })
+                .collect();
+            out.push_str(&latex_table(
+                "llrrrr",
+                "Name & Hamiltonian & Dimension & Time points & Dissipators & Measurements",
+                &rows,
+            ));
+        }
+
+        out.push_str("\n\\subsection*{Validation}\n");
+        let rows: Vec<String> = self
+            .checks
+            .iter()
+            .map(|c| {
+                let result = match c.status {
+                    CheckStatus::Passed => "passed",
+                    CheckStatus::Failed => "\\textbf{failed}",
+                    CheckStatus::Skipped => "skipped",
+                };
+                match &c.detail {
+                    Some(detail) => format!(
+                        "{} & {}: {}",
+                        escape_text(&c.check),
+                        result,
+                        escape_text(detail)
+                    ),
+                    None => format!("{} & {}", escape_text(&c.check), result),
+                }
+            })
+            .collect();
+        out.push_str(&latex_table("ll", "Check & Result", &rows));
+        out
+    }
+}
+
+impl CheckOutcome {
+    fn passed(check: impl Into<String>) -> Self {
+        CheckOutcome {
+            check: check.into(),
+            status: CheckStatus::Passed,
+            detail: None,
+        }
+    }
+
+    fn failed(check: impl Into<String>, error: &EngineError) -> Self {
+        CheckOutcome {
+            check: check.into(),
+            status: CheckStatus::Failed,
+            detail: Some(error.to_string()),
+        }
+    }
+
+    fn skipped(check: impl Into<String>, reason: &str) -> Self {
+        CheckOutcome {
+            check: check.into(),
+            status: CheckStatus::Skipped,
+            detail: Some(reason.to_string()),
+        }
+    }
+}
+
+/// Type check and validate `stmt` after the declarations in `context`
+fn validate_with(
+    context: &[Statement],
+    stmt: &Statement,
+) -> Result<crate::validator::ValidationResults> {
+    let mut statements = context.to_vec();
+    statements.push(stmt.clone());
+    let typed = TypeChecker::new().check(&Ast::new(statements))?;
+    Ok(QuantumValidator::new().validate(&typed)?.validation_results)
+}
+
+/// `statements` with every sweep block replaced by a `const` at its first
+/// value followed by its body
+fn flatten_sweeps(statements: &[Statement]) -> Vec<Statement> {
+    let mut flat = Vec::with_capacity(statements.len());
+    for stmt in statements {
+        match stmt {
+            Statement::Sweep {
+                param,
+                values,
+                body,
+            } => {
+                let first = match values {
+                    SweepValues::Linspace { start, .. } | SweepValues::Geomspace { start, .. } => {
+                        *start
+                    }
+                    SweepValues::List(values) => values.first().copied().unwrap_or(0.0),
+                };
+                flat.push(Statement::ConstDecl {
+                    name: param.clone(),
+                    value: first,
+                });
+                flat.extend(flatten_sweeps(body));
+            }
+            other => flat.push(other.clone()),
+        }
+    }
+    flat
+}
+
+fn collect_parameters(statements: &[Statement], parameters: &mut Vec<ParameterEntry>) {
+    for stmt in statements {
+        let (name, kind, constraint) = match stmt {
+            Statement::ConstDecl { name, value } => {
+                (name, "const", format!("{} = {}", latex_name(name), value))
+            }
+            Statement::ParamDecl { name, range } => {
+                let constraint = match range {
+                    Some((lo, hi)) => format!("{} \\le {} \\le {}", lo, latex_name(name), hi),
+                    None => format!("{} \\in \\mathbb{{R}}", latex_name(name)),
+                };
+                (name, "param", constraint)
+            }
+            Statement::SymbolDecl { name } => (name, "symbol", latex_name(name)),
+            Statement::Sweep {
+                param,
+                values,
+                body,
+            } => {
+                let constraint = match values {
+                    SweepValues::Linspace { start, end, steps } => {
+                        format!(
+                            "{} \\in \\operatorname{{linspace}}({}, {}, {})",
+                            latex_name(param),
+                            start,
+                            end,
+                            steps
+                        )
+                    }
+                    SweepValues::Geomspace { start, end, steps } => {
+                        format!(
+                            "{} \\in \\operatorname{{geomspace}}({}, {}, {})",
+                            latex_name(param),
+                            start,
+                            end,
+                            steps
+                        )
+                    }
+                    SweepValues::List(values) => {
+                        let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                        format!("{} \\in \\{{{}\\}}", latex_name(param), values.join(", "))
+                    }
+                };
+                parameters.push(ParameterEntry {
+                    name: param.clone(),
+                    kind: "sweep".to_string(),
+                    constraint,
+                });
+                collect_parameters(body, parameters);
+                continue;
+            }
+            _ => continue,
+        };
+        parameters.push(ParameterEntry {
+            name: name.clone(),
+            kind: kind.to_string(),
+            constraint,
+        });
+    }
+}
+
+fn latex_name(name: &str) -> String {
+    Expr::Identifier(name.to_string()).to_latex()
+}
+
+fn latex_table(columns: &str, header: &str, rows: &[String]) -> String {
+    format!(
+        "\\begin{{tabular}}{{{}}}\n\\hline\n{} \\\\\n\\hline\n{}\\hline\n\\end{{tabular}}\n",
+        columns,
+        header,
+        rows.iter()
+            .map(|row| format!("{} \\\\\n", row))
+            .collect::<String>()
+    )
+}
+
+/// Escape LaTeX's special characters in running text
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "
+        param Omega in [0.0, 2.0];
+        const delta = 0.25;
+        matrix sigma_x = [0, 1; 1, 0];
+        matrix sigma_z = [1, 0; 0, -1];
+        Hamiltonian H = delta * sigma_z + Omega * sigma_x;
+        experiment rabi {
+            init: ket(vec(1, 1));
+            evolution: evolve(init, H, timegrid=(0.0, 0.1, 11));
+        }
+    ";
+
+    #[test]
+    fn test_report_checks_each_declaration() {
+        let ast = crate::parser::parse_dsl(MODEL).unwrap();
+        let report = ModelReport::new("Rabi model", &ast);
+
+        assert_eq!(report.hamiltonians[0].dim, Some(2));
+        assert!(report.hamiltonians[0].latex.starts_with("H = "));
+        assert_eq!(report.parameters.len(), 2);
+        assert_eq!(report.parameters[0].constraint, r"0 \le \Omega \le 2");
+        assert_eq!(report.experiments[0].dim, Some(2));
+
+        // H depends on the unbound Omega; the unnormalized ket still fails
+        let status: Vec<CheckStatus> = report.checks.iter().map(|c| c.status).collect();
+        assert_eq!(
+            status,
+            vec![
+                CheckStatus::Passed,
+                CheckStatus::Skipped,
+                CheckStatus::Failed
+            ]
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let ast = crate::parser::parse_dsl(MODEL).unwrap();
+        let report = ModelReport::new("Rabi_model", &ast);
+
+        let md = report.render("md".parse().unwrap());
+        assert!(md.starts_with("# Rabi_model\n"));
+        assert!(md.contains("| `Omega` | param |"));
+        assert!(md.contains("| Type check | ✓ passed |"));
+
+        let tex = report.render("tex".parse().unwrap());
+        assert!(tex.starts_with("\\section*{Rabi\\_model}"));
+        assert!(tex.contains("\\begin{tabular}{ll}"));
+        assert!(tex.contains("sigma\\_z & $2\\times 2$"));
+
+        assert!("pdf".parse::<ReportFormat>().is_err());
+    }
+}
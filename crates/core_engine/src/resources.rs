@@ -0,0 +1,263 @@
+//! Resource estimates and limits for running IR programs
+//!
+//! [`estimate`] works out, from the shapes of an [`IrProgram`]'s nodes and
+//! the time grids of its experiments, the largest Hilbert-space dimension
+//! it will handle, the memory its matrices and trajectories will take and
+//! the number of time steps it will integrate, without evaluating
+//! anything. [`ResourceLimits::check`] compares that against configured
+//! limits, so a program that would exhaust a worker, such as a template
+//! instantiated into a huge tensor product, is rejected before it runs.
+
+use crate::error::{EngineError, Result};
+use crate::ir::{EigenOutput, EvolutionMethod, IrNode, IrProgram, NodeId};
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Matrices of the state's size an evolution holds besides its trajectory:
+/// propagator or integrator stages and dense output
+const WORKING_MATRICES: u64 = 8;
+
+const BYTES_PER_ENTRY: u64 = std::mem::size_of::<Complex64>() as u64;
+
+/// What running a program is expected to take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceEstimate {
+    /// Largest dimension of any operator or state
+    pub dimension: usize,
+    /// Bytes of the program's operators, and of each experiment's
+    /// trajectory and working matrices
+    pub memory_bytes: u64,
+    /// Time steps summed over the experiments
+    pub steps: usize,
+}
+
+impl ResourceEstimate {
+    pub fn memory_mb(&self) -> u64 {
+        self.memory_bytes.div_ceil(1024 * 1024)
+    }
+
+    /// The estimate for running `runs` copies of the program at once, as
+    /// a batch does; the dimension is unchanged
+    pub fn times(self, runs: usize) -> Self {
+        ResourceEstimate {
+            dimension: self.dimension,
+            memory_bytes: self.memory_bytes.saturating_mul(runs as u64),
+            steps: self.steps.saturating_mul(runs),
+        }
+    }
+}
+
+/// Upper bounds on what one run may take; `None` is unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    pub max_dim: Option<usize>,
+    pub max_memory_mb: Option<u64>,
+    pub max_steps: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// Fail with a validation error naming the first limit `estimate`
+    /// exceeds
+    pub fn check(&self, estimate: &ResourceEstimate) -> Result<()> {
+        if let Some(max_dim) = self.max_dim.filter(|&max| estimate.dimension > max) {
+            return Err(EngineError::validation_error(format!(
+                "Hilbert-space dimension {} exceeds the limit of {}",
+                estimate.dimension, max_dim
+            )));
+        }
+        if let Some(max_mb) = self.max_memory_mb.filter(|&max| estimate.memory_mb() > max) {
+            return Err(EngineError::validation_error(format!(
+                "Estimated memory of {} MB (dimension {}) exceeds the limit of {} MB",
+                estimate.memory_mb(),
+                estimate.dimension,
+                max_mb
+            )));
+        }
+        if let Some(max_steps) = self.max_steps.filter(|&max| estimate.steps > max) {
+            return Err(EngineError::validation_error(format!(
+                "{} time steps exceed the limit of {}",
+                estimate.steps, max_steps
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Shape of a node's value
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Scalar,
+    Vector(usize),
+    /// Square matrix of this dimension
+    Matrix(usize),
+}
+
+impl Shape {
+    fn dim(self) -> usize {
+        match self {
+            Shape::Scalar => 1,
+            Shape::Vector(n) | Shape::Matrix(n) => n,
+        }
+    }
+
+    fn bytes(self) -> u64 {
+        let entries = match self {
+            Shape::Scalar => 1,
+            Shape::Vector(n) => n as u64,
+            Shape::Matrix(n) => (n as u64).saturating_mul(n as u64),
+        };
+        entries.saturating_mul(BYTES_PER_ENTRY)
+    }
+}
+
+/// Resources needed to run `ir`, from the shapes of its nodes and the time
+/// grids of its experiments
+pub fn estimate(ir: &IrProgram) -> ResourceEstimate {
+    let mut shapes: HashMap<NodeId, Shape> = HashMap::new();
+    for node in &ir.nodes {
+        let shape = node_shape(node, &shapes);
+        shapes.insert(node.id(), shape);
+    }
+    let dim_of = |id: &NodeId| shapes.get(id).map_or(1, |shape| shape.dim());
+
+    let mut memory_bytes = shapes.values().fold(0u64, |total, shape| total.saturating_add(shape.bytes()));
+    let mut dimension = shapes.values().map(|shape| shape.dim()).max().unwrap_or(1);
+    let mut steps = 0usize;
+    for experiment in &ir.experiments {
+        let (dim, times) = match &experiment.evolution {
+            Some(evolution) => {
+                let hamiltonian = match &evolution.method {
+                    EvolutionMethod::Schrodinger { hamiltonian } => hamiltonian,
+                    EvolutionMethod::Lindblad { hamiltonian, .. } => hamiltonian,
+                };
+                (dim_of(hamiltonian), evolution.times.len())
+            }
+            None => (dim_of(&experiment.initial_state), 1),
+        };
+        dimension = dimension.max(dim);
+        steps = steps.saturating_add(times.saturating_sub(1));
+        // Every state of the trajectory is kept as a density matrix
+        let matrix = Shape::Matrix(dim).bytes();
+        let held = (times as u64).saturating_add(WORKING_MATRICES);
+        memory_bytes = memory_bytes.saturating_add(matrix.saturating_mul(held));
+    }
+
+    ResourceEstimate {
+        dimension,
+        memory_bytes,
+        steps,
+    }
+}
+
+fn node_shape(node: &IrNode, shapes: &HashMap<NodeId, Shape>) -> Shape {
+    let shape = |id: &NodeId| shapes.get(id).copied().unwrap_or(Shape::Scalar);
+    let matrix = |id: &NodeId| Shape::Matrix(shape(id).dim());
+    match node {
+        IrNode::LoadMatrix { shape, .. } => Shape::Matrix(shape.0.max(shape.1)),
+        IrNode::LoadVector { data, .. } => Shape::Vector(data.len()),
+        IrNode::Scalar { .. } | IrNode::Parameter { .. } => Shape::Scalar,
+        IrNode::Trace { .. } | IrNode::MeasureExpectation { .. } => Shape::Scalar,
+        IrNode::MatrixAdd { left, right, .. }
+        | IrNode::MatrixMul { left, right, .. }
+        | IrNode::Commutator { left, right, .. } => Shape::Matrix(shape(left).dim().max(shape(right).dim())),
+        IrNode::TensorProduct { left, right, .. } => {
+            Shape::Matrix(shape(left).dim().saturating_mul(shape(right).dim()))
+        }
+        IrNode::ScalarMul { matrix: input, .. }
+        | IrNode::MatrixExp { input, .. }
+        | IrNode::Dagger { input, .. } => matrix(input),
+        IrNode::PartialTrace { input, dims, keep, .. } => {
+            let kept = match dims {
+                Some(dims) => keep.iter().map(|&k| dims.get(k).copied().unwrap_or(1)).product(),
+                None => 1usize.checked_shl(keep.len() as u32).unwrap_or(usize::MAX),
+            };
+            Shape::Matrix(kept.min(shape(input).dim()))
+        }
+        IrNode::Eigendecompose { input, output, .. } => match output {
+            EigenOutput::Eigenvectors => matrix(input),
+            EigenOutput::Eigenvalues | EigenOutput::Eigenstate(_) => Shape::Vector(shape(input).dim()),
+        },
+        IrNode::UnitaryPropagator { hamiltonian, .. } | IrNode::IntegrateLindblad { hamiltonian, .. } => {
+            matrix(hamiltonian)
+        }
+        IrNode::ApplyUnitaryKet { unitary, .. } => Shape::Vector(shape(unitary).dim()),
+        IrNode::ApplyUnitaryRho { unitary, .. } => matrix(unitary),
+        IrNode::ApplyGate { state, .. } => shape(state),
+        IrNode::ApplyChannel { kraus, .. } => kraus.first().map_or(Shape::Scalar, matrix),
+        IrNode::MeasureProjective { projectors, .. } => Shape::Vector(projectors.len()),
+        // Plugin kernels' outputs are as large as their largest input, as
+        // far as can be told without running them
+        IrNode::Custom { inputs, .. } => inputs
+            .iter()
+            .map(shape)
+            .max_by_key(|shape| shape.dim())
+            .unwrap_or(Shape::Scalar),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{IrEvolution, IrExperiment};
+
+    /// `n` qubits' worth of σz ⊗ … ⊗ σz, evolved over `times` points
+    fn tensor_program(n: usize, times: usize) -> IrProgram {
+        let mut ir = IrProgram::new();
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let z = ir.add_node(IrNode::LoadMatrix {
+            id: ir.nodes.len(),
+            name: "sigma_z".to_string(),
+            data: vec![c(1.0), c(0.0), c(0.0), c(-1.0)],
+            shape: (2, 2),
+        });
+        let mut h = z;
+        for _ in 1..n {
+            let id = ir.nodes.len();
+            h = ir.add_node(IrNode::TensorProduct { id, left: h, right: z });
+        }
+        let psi = ir.add_node(IrNode::LoadVector {
+            id: ir.nodes.len(),
+            name: "psi".to_string(),
+            data: vec![c(0.0); 1 << n],
+        });
+        ir.experiments.push(IrExperiment {
+            name: "e".to_string(),
+            initial_state: psi,
+            evolution: Some(IrEvolution {
+                method: EvolutionMethod::Schrodinger { hamiltonian: h },
+                times: (0..times).map(|i| i as f64).collect(),
+            }),
+            measurements: vec![],
+            initial_from: None,
+            channels: vec![],
+            observables: vec![],
+        });
+        ir
+    }
+
+    #[test]
+    fn test_estimate_follows_tensor_products() {
+        let estimate = estimate(&tensor_program(10, 101));
+        assert_eq!(estimate.dimension, 1024);
+        assert_eq!(estimate.steps, 100);
+        // The trajectory alone holds 101 density matrices of 1024²
+        assert!(estimate.memory_bytes >= 101 * 1024 * 1024 * 16);
+
+        let limits = ResourceLimits {
+            max_dim: Some(256),
+            ..ResourceLimits::default()
+        };
+        let err = limits.check(&estimate).unwrap_err();
+        assert!(err.to_string().contains("dimension 1024 exceeds the limit of 256"), "{}", err);
+
+        let limits = ResourceLimits {
+            max_memory_mb: Some(100),
+            max_steps: Some(1000),
+            ..ResourceLimits::default()
+        };
+        assert!(limits.check(&estimate).unwrap_err().to_string().contains("MB"));
+        assert!(limits.check(&super::estimate(&tensor_program(2, 101))).is_ok());
+        assert!(ResourceLimits::default().check(&estimate.times(1000)).is_ok());
+    }
+}
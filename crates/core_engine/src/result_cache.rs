@@ -0,0 +1,359 @@
+//! Content-addressed cache of execution results
+//!
+//! Running the same program with the same parameters on the same backend
+//! gives the same result, so a [`ResultCache`] keeps each
+//! [`ExecutionResult`] under the SHA-256 of those inputs: the IR, the
+//! parameter values substituted into it, the [`BackendConfig`] and the
+//! engine version. Checkpointing and thread count do not change a result
+//! and are left out of the key. Programs that call plugin kernels
+//! (`IrNode::Custom`) are never cached, as their result depends on code
+//! the key cannot see.
+//!
+//! Entries are JSON files in the cache directory, [`DEFAULT_DIR`] unless
+//! configured, holding the states as well as the serialized result. After
+//! each insertion the [`EvictionPolicy`] removes expired entries and then
+//! the least recently used ones until the cache fits its limits; reading an
+//! entry counts as using it.
+//!
+//! [`Executor::with_result_cache`](crate::executor::Executor::with_result_cache)
+//! consults a cache before every run; `qte simulate --no-cache` skips it.
+
+use crate::error::{EngineError, Result};
+use crate::executor::{BackendConfig, ExecutionResult};
+use crate::ir::{IrNode, IrProgram};
+use crate::logging;
+use ndarray::Array2;
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Default location of the cache, relative to the working directory
+pub const DEFAULT_DIR: &str = ".qte/cache";
+
+/// Bumped whenever the entry format or the key derivation changes
+const FORMAT_VERSION: &str = "1";
+
+/// Limits the cache is trimmed to after each insertion; `None` is unlimited
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvictionPolicy {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<u64>,
+    /// Entries unused for longer are removed
+    pub max_age: Option<Duration>,
+}
+
+impl Default for EvictionPolicy {
+    fn default() -> Self {
+        Self {
+            max_entries: None,
+            max_bytes: Some(1 << 30),
+            max_age: Some(Duration::from_secs(30 * 24 * 3600)),
+        }
+    }
+}
+
+/// Execution results stored on disk by content hash
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+    policy: EvictionPolicy,
+}
+
+/// A cached result with the states its serialized form leaves out
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    result: ExecutionResult,
+    states: Vec<Vec<StoredMatrix>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredMatrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<Complex64>,
+}
+
+impl ResultCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            policy: EvictionPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Key of running `ir` with `params` substituted into it on `config`,
+    /// or `None` if the program cannot be cached
+    pub fn key(
+        ir: &IrProgram,
+        params: &HashMap<String, f64>,
+        config: &BackendConfig,
+    ) -> Result<Option<String>> {
+        if ir
+            .nodes
+            .iter()
+            .any(|node| matches!(node, IrNode::Custom { .. }))
+        {
+            return Ok(None);
+        }
+        let config = BackendConfig {
+            num_threads: None,
+            checkpoint: None,
+            ..config.clone()
+        };
+        let params: BTreeMap<&String, &f64> = params.iter().collect();
+
+        let mut hasher = Sha256::new();
+        for part in [
+            FORMAT_VERSION.as_bytes().to_vec(),
+            env!("CARGO_PKG_VERSION").as_bytes().to_vec(),
+            serde_json::to_vec(ir)?,
+            serde_json::to_vec(&params)?,
+            serde_json::to_vec(&config)?,
+        ] {
+            // Length-prefixed so that parts cannot run into each other
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(&part);
+        }
+        Ok(Some(format!("{:x}", hasher.finalize())))
+    }
+
+    /// Cached result for `key`; a missing or unreadable entry is a miss
+    pub fn get(&self, key: &str) -> Option<ExecutionResult> {
+        let path = self.path(key);
+        let text = fs::read_to_string(&path).ok()?;
+        let entry = match serde_json::from_str::<Entry>(&text) {
+            Ok(entry) => entry,
+            Err(err) => {
+                logging::warn(
+                    "result_cache",
+                    format!("Discarding unreadable entry {}: {}", path.display(), err),
+                );
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+        // Mark the entry as recently used
+        let _ = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+
+        let mut result = entry.result;
+        for (experiment, states) in result.experiment_results.iter_mut().zip(entry.states) {
+            experiment.states = states
+                .into_iter()
+                .map(|m| Array2::from_shape_vec((m.rows, m.cols), m.data).ok())
+                .collect::<Option<_>>()?;
+        }
+        Some(result)
+    }
+
+    /// Store `result` under `key`, then evict down to the policy's limits
+    pub fn insert(&self, key: &str, result: &ExecutionResult) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let states = result
+            .experiment_results
+            .iter()
+            .map(|experiment| {
+                experiment
+                    .states
+                    .iter()
+                    .map(|state| StoredMatrix {
+                        rows: state.nrows(),
+                        cols: state.ncols(),
+                        data: state.iter().copied().collect(),
+                    })
+                    .collect()
+            })
+            .collect();
+        let entry = Entry {
+            result: result.clone(),
+            states,
+        };
+
+        // Written aside and renamed, so readers never see half an entry
+        let path = self.path(key);
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec(&entry)?)?;
+        fs::rename(&partial, &path)?;
+        self.evict()?;
+        Ok(())
+    }
+
+    /// Remove expired entries, then the least recently used until the
+    /// cache is within its limits; returns the number removed
+    pub fn evict(&self) -> Result<usize> {
+        let now = SystemTime::now();
+        let mut entries = self.entries()?;
+        // Most recently used first
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+
+        let (mut kept, mut bytes, mut removed) = (0usize, 0u64, 0);
+        for (path, used, size) in entries {
+            let expired = self
+                .policy
+                .max_age
+                .is_some_and(|age| now.duration_since(used).unwrap_or_default() > age);
+            let over = self.policy.max_entries.is_some_and(|max| kept + 1 > max)
+                || self.policy.max_bytes.is_some_and(|max| bytes + size > max);
+            if expired || over {
+                fs::remove_file(&path)?;
+                removed += 1;
+            } else {
+                kept += 1;
+                bytes += size;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Remove every entry; returns the number removed
+    pub fn clear(&self) -> Result<usize> {
+        let entries = self.entries()?;
+        for (path, _, _) in &entries {
+            fs::remove_file(path)?;
+        }
+        Ok(entries.len())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Path, last use and size of each entry
+    fn entries(&self) -> Result<Vec<(PathBuf, SystemTime, u64)>> {
+        let listing = match fs::read_dir(&self.dir) {
+            Ok(listing) => listing,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(EngineError::from(err)),
+        };
+        let mut entries = Vec::new();
+        for item in listing {
+            let path = item?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            entries.push((path, metadata.modified()?, metadata.len()));
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{ExperimentResult, StateType};
+
+    fn result(value: f64) -> ExecutionResult {
+        ExecutionResult {
+            experiment_results: vec![ExperimentResult {
+                name: "decay".to_string(),
+                times: vec![0.0, 1.0],
+                state_type: StateType::DensityMatrix,
+                measurements: vec![],
+                states: vec![Array2::eye(2), Array2::eye(2) * Complex64::new(value, 0.0)],
+                diagnostics: None,
+                ehrenfest: vec![],
+                observables: vec![],
+            }],
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qte-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_key_depends_on_params_and_config() {
+        let ir = IrProgram::new();
+        let config = BackendConfig::default();
+        let params = HashMap::from([("omega".to_string(), 1.0)]);
+        let key = ResultCache::key(&ir, &params, &config).unwrap().unwrap();
+
+        assert_eq!(
+            ResultCache::key(&ir, &params, &config).unwrap().unwrap(),
+            key
+        );
+        let other = HashMap::from([("omega".to_string(), 2.0)]);
+        assert_ne!(
+            ResultCache::key(&ir, &other, &config).unwrap().unwrap(),
+            key
+        );
+        let threaded = BackendConfig {
+            num_threads: Some(4),
+            ..config.clone()
+        };
+        assert_eq!(
+            ResultCache::key(&ir, &params, &threaded).unwrap().unwrap(),
+            key
+        );
+        let single = BackendConfig {
+            precision: crate::precision::Precision::Single,
+            ..config
+        };
+        assert_ne!(
+            ResultCache::key(&ir, &params, &single).unwrap().unwrap(),
+            key
+        );
+    }
+
+    #[test]
+    fn test_round_trip_keeps_states() {
+        let cache = ResultCache::new(temp_dir("round-trip"));
+        assert!(cache.get("missing").is_none());
+
+        cache.insert("abc", &result(0.5)).unwrap();
+        let cached = cache.get("abc").unwrap();
+        let experiment = &cached.experiment_results[0];
+        assert_eq!(experiment.states.len(), 2);
+        assert_eq!(experiment.states[1][[1, 1]], Complex64::new(0.5, 0.0));
+        assert_eq!(cache.clear().unwrap(), 1);
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let policy = EvictionPolicy {
+            max_entries: Some(2),
+            ..EvictionPolicy::default()
+        };
+        let cache = ResultCache::new(temp_dir("evict")).with_policy(policy);
+        let touch = |key: &str, secs_ago: u64| {
+            let file = fs::File::options()
+                .write(true)
+                .open(cache.path(key))
+                .unwrap();
+            file.set_modified(SystemTime::now() - Duration::from_secs(secs_ago))
+                .unwrap();
+        };
+
+        cache.insert("first", &result(1.0)).unwrap();
+        touch("first", 30);
+        cache.insert("second", &result(2.0)).unwrap();
+        touch("second", 20);
+        // Reading "first" makes "second" the least recently used
+        assert!(cache.get("first").is_some());
+        cache.insert("third", &result(3.0)).unwrap();
+
+        assert!(cache.get("first").is_some());
+        assert!(cache.get("second").is_none());
+        assert!(cache.get("third").is_some());
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+}
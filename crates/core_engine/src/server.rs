@@ -0,0 +1,960 @@
+//! HTTP API for the job queue
+//!
+//! Exposes a [`JobQueue`] over a small REST interface so remote clients can
+//! submit simulations and parameter sweeps:
+//!
+//! | Method   | Path                | Description                        |
+//! |----------|---------------------|------------------------------------|
+//! | `GET`    | `/health`           | Engine health checks               |
+//! | `POST`   | `/jobs`             | Submit a single job                |
+//! | `POST`   | `/sweeps`           | Submit a parameter sweep           |
+//! | `GET`    | `/jobs/{id}/status` | Current [`JobStatus`]              |
+//! | `GET`    | `/jobs/{id}/result` | [`JobResult`] once the job is done |
+//! | `DELETE` | `/jobs/{id}`        | Cancel a queued job                |
+//!
+//! and, for operators (`qte queue`):
+//!
+//! | Method   | Path                | Description                        |
+//! |----------|---------------------|------------------------------------|
+//! | `GET`    | `/jobs`             | [`JobSummary`] list, see [`JobFilter`] |
+//! | `GET`    | `/jobs/{id}`        | [`JobDetails`]: config, params, status |
+//! | `POST`   | `/jobs/{id}/retry`  | Requeue a failed or cancelled job  |
+//! | `GET`    | `/queue`            | [`QueueSummary`]                   |
+//! | `POST`   | `/queue/drain`      | Start no new jobs                  |
+//! | `POST`   | `/queue/resume`     | Start jobs again                   |
+//!
+//! [`router`] serves anyone who can reach it. [`authenticated_router`]
+//! requires an API key from an [`AuthConfig`] on every route but
+//! `/health`, sent as `Authorization: Bearer <key>` or `X-Api-Key: <key>`:
+//!
+//! - a missing or unknown key is `401`;
+//! - each client's `requests_per_minute` is enforced with a token bucket,
+//!   `429` with `Retry-After` once it is spent;
+//! - jobs are owned by the client that submitted them
+//!   ([`JobConfig::owner`]), and the job routes only see the caller's own
+//!   jobs; the queue runs at most `max_concurrent_jobs` of a client's jobs
+//!   at once, see [`AuthConfig::queue_quotas`];
+//! - only admin clients may see, drain and resume the queue, and they have
+//!
+//! | Method   | Path                | Description                        |
+//! |----------|---------------------|------------------------------------|
+//! | `GET`    | `/admin/jobs`       | Every client's jobs, see [`JobFilter::owner`] |
+//! | `DELETE` | `/admin/jobs/{id}`  | Cancel any client's job            |
+
+use crate::error::{EngineError, Result};
+use crate::job_queue::{
+    Job, JobConfig, JobDetails, JobId, JobKind, JobQueue, JobResult, JobStatus, ParameterGrid,
+    Priority,
+};
+use crate::logging::{HealthChecker, HealthStatus};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Body of `POST /jobs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitJobRequest {
+    pub kind: JobKind,
+    #[serde(default = "default_priority")]
+    pub priority: Priority,
+    #[serde(default)]
+    pub params: HashMap<String, f64>,
+    #[serde(default)]
+    pub config: Option<JobConfig>,
+    /// Jobs that must complete first
+    #[serde(default)]
+    pub depends_on: Vec<JobId>,
+}
+
+/// Body of `POST /sweeps`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitSweepRequest {
+    pub base: SubmitJobRequest,
+    pub grid: ParameterGrid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCreated {
+    pub job_id: JobId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepCreated {
+    pub job_ids: Vec<JobId>,
+}
+
+/// Query of `GET /jobs`; each given field must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobFilter {
+    /// [`JobStatus::label`], e.g. `failed`
+    pub status: Option<String>,
+    /// [`JobKind::label`], e.g. `simulate`
+    pub kind: Option<String>,
+    /// `low`, `normal`, `high` or `critical`
+    pub priority: Option<String>,
+    /// Client that submitted the job; only used by `GET /admin/jobs`
+    pub owner: Option<String>,
+}
+
+/// One row of `GET /jobs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub job_id: JobId,
+    pub kind: String,
+    pub priority: Priority,
+    pub status: String,
+    /// Reported progress of a running job
+    pub progress: Option<f64>,
+    /// Error of a failed job
+    pub error: Option<String>,
+    pub retry_count: usize,
+    /// Client that submitted the job, on an authenticated server
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+/// Body of `GET /queue` and the drain/resume responses
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueSummary {
+    pub draining: bool,
+    pub queued: usize,
+    pub running: usize,
+    pub complete: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+impl JobFilter {
+    pub fn matches(&self, details: &JobDetails) -> bool {
+        let matches = |wanted: &Option<String>, actual: &str| {
+            wanted.as_ref().is_none_or(|w| w.eq_ignore_ascii_case(actual))
+        };
+        matches(&self.status, details.status.label())
+            && matches(&self.kind, details.job.kind.label())
+            && matches(&self.priority, &format!("{:?}", details.job.priority))
+            && self.owner.as_ref().is_none_or(|owner| {
+                details.job.config.owner.as_ref() == Some(owner)
+            })
+    }
+}
+
+impl From<&JobDetails> for JobSummary {
+    fn from(details: &JobDetails) -> Self {
+        let (progress, error) = match &details.status {
+            JobStatus::Running { progress, .. } => (Some(*progress), None),
+            JobStatus::Failed { error, .. } => (None, Some(error.clone())),
+            _ => (None, None),
+        };
+        JobSummary {
+            job_id: details.job.id,
+            kind: details.job.kind.label().to_string(),
+            priority: details.job.priority,
+            status: details.status.label().to_string(),
+            progress,
+            error,
+            retry_count: details.retry_count,
+            owner: details.job.config.owner.clone(),
+        }
+    }
+}
+
+/// A client of an authenticated server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiClient {
+    /// Owner of the client's jobs
+    pub name: String,
+    pub key: String,
+    /// May drain and resume the queue and see and cancel every job
+    #[serde(default)]
+    pub admin: bool,
+    /// Sustained request rate, allowed in bursts of up to a minute's worth;
+    /// unlimited if unset
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Most of the client's jobs that run at once; unlimited if unset
+    #[serde(default)]
+    pub max_concurrent_jobs: Option<usize>,
+}
+
+/// Clients allowed to use an authenticated server, read from TOML:
+///
+/// ```toml
+/// [[clients]]
+/// name = "alice"
+/// key = "…"
+/// requests_per_minute = 120
+/// max_concurrent_jobs = 4
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub clients: Vec<ApiClient>,
+}
+
+impl AuthConfig {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = toml::from_str(&text)
+            .map_err(|e| EngineError::SerializationError(format!("Invalid auth config: {}", e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Names and keys must be unique, and keys non-empty
+    pub fn validate(&self) -> Result<()> {
+        let (mut names, mut keys) = (HashSet::new(), HashSet::new());
+        for client in &self.clients {
+            if !names.insert(client.name.as_str()) {
+                return Err(EngineError::validation_error(format!(
+                    "Client '{}' is listed twice",
+                    client.name
+                )));
+            }
+            if client.key.is_empty() || !keys.insert(client.key.as_str()) {
+                return Err(EngineError::validation_error(format!(
+                    "Client '{}' needs a key of its own",
+                    client.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Concurrency quotas for [`QueueOptions::max_running_per_owner`]
+    ///
+    /// [`QueueOptions::max_running_per_owner`]: crate::job_queue::QueueOptions::max_running_per_owner
+    pub fn queue_quotas(&self) -> HashMap<String, usize> {
+        self.clients
+            .iter()
+            .filter_map(|client| Some((client.name.clone(), client.max_concurrent_jobs?)))
+            .collect()
+    }
+
+    /// Client holding `key`, comparing in constant time so response times
+    /// do not reveal how much of a key is right
+    fn client(&self, key: &str) -> Option<&ApiClient> {
+        let equal = |a: &[u8], b: &[u8]| {
+            a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+        };
+        // Every key is compared, not just those up to a match
+        self.clients.iter().fold(None, |found, client| {
+            if equal(client.key.as_bytes(), key.as_bytes()) {
+                Some(client)
+            } else {
+                found
+            }
+        })
+    }
+}
+
+/// Authentication and rate limiting state of a server
+struct Auth {
+    config: AuthConfig,
+    /// Tokens left and time of the last refill, by client name
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+impl Auth {
+    /// Take one request token from `client`'s bucket, or return how long
+    /// until one is available
+    fn take_token(&self, client: &ApiClient, now: Instant) -> std::result::Result<(), Duration> {
+        let Some(per_minute) = client.requests_per_minute else {
+            return Ok(());
+        };
+        let capacity = per_minute as f64;
+        let rate = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap();
+        let (tokens, refilled) = buckets
+            .entry(client.name.clone())
+            .or_insert((capacity, now));
+        *tokens =
+            (*tokens + rate * now.saturating_duration_since(*refilled).as_secs_f64()).min(capacity);
+        *refilled = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else if rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - *tokens) / rate))
+        } else {
+            Err(Duration::from_secs(60))
+        }
+    }
+}
+
+/// The authenticated client making a request
+#[derive(Debug, Clone)]
+struct Caller {
+    name: String,
+    admin: bool,
+}
+
+/// JSON error body returned for every non-2xx response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub error: String,
+}
+
+fn default_priority() -> Priority {
+    Priority::Normal
+}
+
+impl SubmitJobRequest {
+    fn into_job(self) -> Job {
+        Job {
+            id: Uuid::new_v4(),
+            kind: self.kind,
+            priority: self.priority,
+            params: self.params,
+            config: self.config.unwrap_or_default(),
+            depends_on: self.depends_on,
+        }
+    }
+}
+
+impl ApiError {
+    fn new(status: StatusCode, error: impl Into<String>) -> Self {
+        Self {
+            status,
+            error: error.into(),
+        }
+    }
+
+    fn not_found(job_id: JobId) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("Job not found: {}", job_id))
+    }
+
+    fn forbidden() -> Self {
+        Self::new(StatusCode::FORBIDDEN, "Requires an admin API key")
+    }
+}
+
+impl From<EngineError> for ApiError {
+    fn from(err: EngineError) -> Self {
+        let status = match err {
+            EngineError::NotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, err.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Shared state handed to every handler
+#[derive(Clone)]
+struct ServerState {
+    queue: Arc<JobQueue>,
+    /// `None` on an open server
+    auth: Option<Arc<Auth>>,
+}
+
+/// Build the API router backed by `queue`, open to anyone
+pub fn router(queue: Arc<JobQueue>) -> Router {
+    routes(ServerState { queue, auth: None })
+}
+
+/// Build the API router backed by `queue`, for the clients in `auth`
+///
+/// The queue enforces the clients' concurrency quotas only if it was
+/// created with [`AuthConfig::queue_quotas`].
+pub fn authenticated_router(queue: Arc<JobQueue>, auth: AuthConfig) -> Result<Router> {
+    auth.validate()?;
+    Ok(routes(ServerState {
+        queue,
+        auth: Some(Arc::new(Auth {
+            config: auth,
+            buckets: Mutex::new(HashMap::new()),
+        })),
+    }))
+}
+
+fn routes(state: ServerState) -> Router {
+    Router::new()
+        .route("/jobs", post(submit_job).get(list_jobs))
+        .route("/sweeps", post(submit_sweep))
+        .route("/jobs/:id", delete(cancel_job).get(job_details))
+        .route("/jobs/:id/status", get(job_status))
+        .route("/jobs/:id/result", get(job_result))
+        .route("/jobs/:id/retry", post(retry_job))
+        .route("/queue", get(queue_summary))
+        .route("/queue/drain", post(drain_queue))
+        .route("/queue/resume", post(resume_queue))
+        .route("/admin/jobs", get(admin_list_jobs))
+        .route("/admin/jobs/:id", delete(admin_cancel_job))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .route("/health", get(health))
+        .with_state(state)
+}
+
+/// Serve the API on `addr` until `shutdown` resolves
+pub async fn serve<F>(addr: SocketAddr, queue: Arc<JobQueue>, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr).await?;
+    serve_listener(listener, queue, shutdown).await
+}
+
+/// Serve the API on an already-bound listener until `shutdown` resolves
+///
+/// Binding to port 0 first and reading `local_addr()` gives callers (tests,
+/// embedded servers) the ephemeral port before any request is made.
+pub async fn serve_listener<F>(
+    listener: TcpListener,
+    queue: Arc<JobQueue>,
+    shutdown: F,
+) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    serve_router(listener, router(queue), shutdown).await
+}
+
+/// Serve `app`, e.g. from [`authenticated_router`], on an already-bound
+/// listener until `shutdown` resolves
+pub async fn serve_router<F>(listener: TcpListener, app: Router, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    crate::log_info!("server", "Listening on {}", listener.local_addr()?);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await?;
+
+    Ok(())
+}
+
+/// Check the API key and rate limit of a request to an authenticated
+/// server, passing the [`Caller`] on to the handler
+async fn authenticate(
+    State(state): State<ServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(auth) = &state.auth else {
+        return next.run(request).await;
+    };
+    let Some(client) = api_key(request.headers()).and_then(|key| auth.config.client(key)) else {
+        return ApiError::new(StatusCode::UNAUTHORIZED, "Missing or unknown API key")
+            .into_response();
+    };
+    if let Err(wait) = auth.take_token(client, Instant::now()) {
+        let retry_after = wait.as_secs_f64().ceil().max(1.0).to_string();
+        let error = ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "Rate limit of {} requests per minute exceeded",
+                client.requests_per_minute.unwrap_or(0)
+            ),
+        );
+        return ([(header::RETRY_AFTER, retry_after)], error).into_response();
+    }
+
+    request.extensions_mut().insert(Caller {
+        name: client.name.clone(),
+        admin: client.admin,
+    });
+    next.run(request).await
+}
+
+/// Key from `Authorization: Bearer <key>` or `X-Api-Key: <key>`; an
+/// `Authorization` header with another scheme, e.g. `Basic` set by a proxy,
+/// leaves the key to `X-Api-Key`
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok()?.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key")?.to_str().ok())
+}
+
+/// Fail with 404 unless `job_id` belongs to the caller; on an open server
+/// every job does
+async fn check_owner(state: &ServerState, caller: Option<&Caller>, job_id: JobId) -> ApiResult<()> {
+    let Some(caller) = caller else {
+        return Ok(());
+    };
+    match state.queue.details(job_id).await {
+        Some(details) if details.job.config.owner.as_ref() == Some(&caller.name) => Ok(()),
+        _ => Err(ApiError::not_found(job_id)),
+    }
+}
+
+/// Fail with 400 unless every job `job` depends on belongs to the caller
+async fn check_dependencies(state: &ServerState, caller: Option<&Caller>, job: &Job) -> ApiResult<()> {
+    for dependency in &job.depends_on {
+        check_owner(state, caller, *dependency)
+            .await
+            .map_err(|err| ApiError::new(StatusCode::BAD_REQUEST, err.error))?;
+    }
+    Ok(())
+}
+
+/// An unknown dependency is a fault of the request body, so submissions
+/// answer it with 400 rather than 404
+fn submit_error(err: EngineError) -> ApiError {
+    match err {
+        EngineError::NotFound(_) => ApiError::new(StatusCode::BAD_REQUEST, err.to_string()),
+        err => err.into(),
+    }
+}
+
+fn require_admin(caller: Option<&Caller>) -> ApiResult<()> {
+    match caller {
+        Some(caller) if !caller.admin => Err(ApiError::forbidden()),
+        _ => Ok(()),
+    }
+}
+
+async fn health() -> (StatusCode, Json<HealthStatus>) {
+    let status = HealthChecker::default().run_checks();
+    let code = if status.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(status))
+}
+
+async fn submit_job(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<SubmitJobRequest>,
+) -> ApiResult<(StatusCode, Json<JobCreated>)> {
+    let mut job = request.into_job();
+    check_dependencies(&state, caller.as_deref(), &job).await?;
+    if let Some(Extension(caller)) = caller {
+        job.config.owner = Some(caller.name);
+    }
+    let job_id = state.queue.submit(job).await.map_err(submit_error)?;
+    Ok((StatusCode::CREATED, Json(JobCreated { job_id })))
+}
+
+async fn submit_sweep(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<SubmitSweepRequest>,
+) -> ApiResult<(StatusCode, Json<SweepCreated>)> {
+    let mut base = request.base.into_job();
+    check_dependencies(&state, caller.as_deref(), &base).await?;
+    if let Some(Extension(caller)) = caller {
+        base.config.owner = Some(caller.name);
+    }
+    let job_ids = state.queue.submit_sweep(base, request.grid).await.map_err(submit_error)?;
+    Ok((StatusCode::CREATED, Json(SweepCreated { job_ids })))
+}
+
+async fn job_status(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Path(job_id): Path<JobId>,
+) -> ApiResult<Json<JobStatus>> {
+    check_owner(&state, caller.as_deref(), job_id).await?;
+    state
+        .queue
+        .status(job_id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(job_id))
+}
+
+async fn job_result(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Path(job_id): Path<JobId>,
+) -> ApiResult<Json<JobResult>> {
+    check_owner(&state, caller.as_deref(), job_id).await?;
+    if let Some(result) = state.queue.get_result(job_id).await {
+        return Ok(Json(result));
+    }
+
+    match state.queue.status(job_id).await {
+        Some(status) => Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Job {} has no result yet: {:?}", job_id, status),
+        )),
+        None => Err(ApiError::not_found(job_id)),
+    }
+}
+
+async fn cancel_job(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Path(job_id): Path<JobId>,
+) -> ApiResult<StatusCode> {
+    check_owner(&state, caller.as_deref(), job_id).await?;
+    if state.queue.status(job_id).await.is_none() {
+        return Err(ApiError::not_found(job_id));
+    }
+
+    state.queue.cancel(job_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_jobs(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Query(mut filter): Query<JobFilter>,
+) -> Json<Vec<JobSummary>> {
+    // Callers see only their own jobs; admins use /admin/jobs for the rest
+    filter.owner = caller.map(|Extension(caller)| caller.name);
+    let jobs = state.queue.all_details().await;
+    Json(jobs.iter().filter(|d| filter.matches(d)).map(JobSummary::from).collect())
+}
+
+async fn admin_list_jobs(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Query(filter): Query<JobFilter>,
+) -> ApiResult<Json<Vec<JobSummary>>> {
+    require_admin(caller.as_deref())?;
+    let jobs = state.queue.all_details().await;
+    Ok(Json(jobs.iter().filter(|d| filter.matches(d)).map(JobSummary::from).collect()))
+}
+
+async fn admin_cancel_job(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Path(job_id): Path<JobId>,
+) -> ApiResult<StatusCode> {
+    require_admin(caller.as_deref())?;
+    cancel_job(State(state), None, Path(job_id)).await
+}
+
+async fn job_details(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Path(job_id): Path<JobId>,
+) -> ApiResult<Json<JobDetails>> {
+    check_owner(&state, caller.as_deref(), job_id).await?;
+    state
+        .queue
+        .details(job_id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(job_id))
+}
+
+async fn retry_job(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+    Path(job_id): Path<JobId>,
+) -> ApiResult<StatusCode> {
+    check_owner(&state, caller.as_deref(), job_id).await?;
+    state.queue.retry(job_id).await.map_err(|err| match err {
+        EngineError::NotFound(_) => ApiError::not_found(job_id),
+        err => ApiError::new(StatusCode::CONFLICT, err.to_string()),
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn queue_summary(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+) -> ApiResult<Json<QueueSummary>> {
+    require_admin(caller.as_deref())?;
+    Ok(Json(summarize_queue(&state).await))
+}
+
+async fn summarize_queue(state: &ServerState) -> QueueSummary {
+    let mut summary = QueueSummary {
+        draining: state.queue.is_draining(),
+        ..QueueSummary::default()
+    };
+    for details in state.queue.all_details().await {
+        match details.status {
+            JobStatus::Queued { .. } => summary.queued += 1,
+            JobStatus::Running { .. } => summary.running += 1,
+            JobStatus::Complete(_) => summary.complete += 1,
+            JobStatus::Failed { .. } => summary.failed += 1,
+            JobStatus::Cancelled => summary.cancelled += 1,
+        }
+    }
+    summary
+}
+
+async fn drain_queue(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+) -> ApiResult<Json<QueueSummary>> {
+    require_admin(caller.as_deref())?;
+    state.queue.drain().await?;
+    Ok(Json(summarize_queue(&state).await))
+}
+
+async fn resume_queue(
+    State(state): State<ServerState>,
+    caller: Option<Extension<Caller>>,
+) -> ApiResult<Json<QueueSummary>> {
+    require_admin(caller.as_deref())?;
+    state.queue.resume().await?;
+    Ok(Json(summarize_queue(&state).await))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn submit_body() -> Body {
+        let request = SubmitJobRequest {
+            kind: JobKind::Prove {
+                statement: "dagger(dagger(A)) == A".to_string(),
+            },
+            priority: Priority::High,
+            params: HashMap::new(),
+            config: None,
+            depends_on: Vec::new(),
+        };
+        Body::from(serde_json::to_vec(&request).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint() {
+        let app = router(Arc::new(JobQueue::new(1)));
+
+        let response = app
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_submit_then_status() {
+        let app = router(Arc::new(JobQueue::new(1)));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/jobs")
+                    .header("content-type", "application/json")
+                    .body(submit_body())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let created: JobCreated = serde_json::from_slice(&bytes).unwrap();
+
+        let response = app
+            .oneshot(
+                Request::get(format!("/jobs/{}/status", created.job_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_drained_queue_lists_filtered_jobs() {
+        let app = router(Arc::new(JobQueue::new(1)));
+        let send = |request: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, bytes)
+            }
+        };
+
+        let (status, bytes) = send(Request::post("/queue/drain").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(serde_json::from_slice::<QueueSummary>(&bytes).unwrap().draining);
+
+        let submit = Request::post("/jobs")
+            .header("content-type", "application/json")
+            .body(submit_body())
+            .unwrap();
+        let (_, bytes) = send(submit).await;
+        let created: JobCreated = serde_json::from_slice(&bytes).unwrap();
+
+        let (_, bytes) = send(Request::get("/jobs?status=queued&kind=prove").body(Body::empty()).unwrap()).await;
+        let jobs: Vec<JobSummary> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].job_id, created.job_id);
+
+        let (_, bytes) = send(Request::get("/jobs?priority=low").body(Body::empty()).unwrap()).await;
+        assert!(serde_json::from_slice::<Vec<JobSummary>>(&bytes).unwrap().is_empty());
+
+        let (status, bytes) = send(Request::get(format!("/jobs/{}", created.job_id)).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let details: JobDetails = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(details.job.priority, Priority::High);
+
+        // Only failed or cancelled jobs can be retried
+        let retry = format!("/jobs/{}/retry", created.job_id);
+        let (status, _) = send(Request::post(retry).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_clients_see_own_jobs() {
+        let client = |name: &str, admin, requests_per_minute| ApiClient {
+            name: name.to_string(),
+            key: format!("{}-key", name),
+            admin,
+            requests_per_minute,
+            max_concurrent_jobs: Some(1),
+        };
+        let auth = AuthConfig {
+            clients: vec![
+                client("alice", false, Some(2)),
+                client("bob", false, None),
+                client("root", true, None),
+            ],
+        };
+        assert_eq!(auth.queue_quotas()["alice"], 1);
+        let app = authenticated_router(Arc::new(JobQueue::new(1)), auth).unwrap();
+        let send = |request: axum::http::request::Builder, key: Option<&str>, body: Body| {
+            let request = match key {
+                Some(key) => request.header("authorization", format!("Bearer {}", key)),
+                None => request,
+            };
+            let request = request
+                .header("content-type", "application/json")
+                .body(body)
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let health = send(Request::get("/health"), None, Body::empty()).await.unwrap();
+        assert_eq!(health.status(), StatusCode::OK);
+        let anonymous = send(Request::post("/jobs"), None, submit_body()).await.unwrap();
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(Request::post("/jobs"), Some("alice-key"), submit_body()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let JobCreated { job_id } = serde_json::from_slice(&bytes).unwrap();
+
+        // Other clients cannot see the job, nor drain the queue
+        let status = format!("/jobs/{}/status", job_id);
+        let theirs = send(Request::get(&status), Some("bob-key"), Body::empty()).await.unwrap();
+        assert_eq!(theirs.status(), StatusCode::NOT_FOUND);
+        let drain = send(Request::post("/queue/drain"), Some("bob-key"), Body::empty()).await.unwrap();
+        assert_eq!(drain.status(), StatusCode::FORBIDDEN);
+        let admin = send(Request::get("/admin/jobs"), Some("bob-key"), Body::empty()).await.unwrap();
+        assert_eq!(admin.status(), StatusCode::FORBIDDEN);
+        let summary = send(Request::get("/queue"), Some("bob-key"), Body::empty()).await.unwrap();
+        assert_eq!(summary.status(), StatusCode::FORBIDDEN);
+        let summary = send(Request::get("/queue"), Some("root-key"), Body::empty()).await.unwrap();
+        assert_eq!(summary.status(), StatusCode::OK);
+
+        // Nor make their jobs or sweeps wait on it
+        let sweep = serde_json::json!({
+            "base": {
+                "kind": { "Prove": { "statement": "dagger(dagger(A)) == A" } },
+                "depends_on": [job_id],
+            },
+            "grid": { "params": [], "strategy": "FullGrid" },
+        });
+        let sweep = send(Request::post("/sweeps"), Some("bob-key"), Body::from(sweep.to_string())).await.unwrap();
+        assert_eq!(sweep.status(), StatusCode::BAD_REQUEST);
+
+        // alice may make two requests a minute
+        let own = send(Request::get(&status), Some("alice-key"), Body::empty()).await.unwrap();
+        assert_eq!(own.status(), StatusCode::OK);
+        let limited = send(Request::get(&status), Some("alice-key"), Body::empty()).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key(header::RETRY_AFTER));
+
+        let response = send(Request::get("/admin/jobs?owner=alice"), Some("root-key"), Body::empty())
+            .await
+            .unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let jobs: Vec<JobSummary> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].owner.as_deref(), Some("alice"));
+        let cancel = send(Request::delete(format!("/admin/jobs/{}", job_id)), Some("root-key"), Body::empty())
+            .await
+            .unwrap();
+        assert_eq!(cancel.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[test]
+    fn test_api_key_headers() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            pairs
+                .iter()
+                .map(|&(name, value)| (header::HeaderName::from_static(name), value.parse().unwrap()))
+                .collect::<HeaderMap>()
+        };
+
+        assert_eq!(api_key(&headers(&[("authorization", "Bearer alice-key")])), Some("alice-key"));
+        assert_eq!(api_key(&headers(&[("x-api-key", "alice-key")])), Some("alice-key"));
+        let behind_proxy = headers(&[("authorization", "Basic cHJveHk6cHJveHk="), ("x-api-key", "alice-key")]);
+        assert_eq!(api_key(&behind_proxy), Some("alice-key"));
+        assert_eq!(api_key(&headers(&[("authorization", "Basic cHJveHk6cHJveHk=")])), None);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_dependency_is_400() {
+        let app = router(Arc::new(JobQueue::new(1)));
+        let base = serde_json::json!({
+            "kind": { "Prove": { "statement": "dagger(dagger(A)) == A" } },
+            "depends_on": [Uuid::new_v4()],
+        });
+        let sweep = serde_json::json!({
+            "base": base,
+            "grid": { "params": [], "strategy": "FullGrid" },
+        });
+
+        for (uri, body) in [("/jobs", base), ("/sweeps", sweep)] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::post(uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_job_is_404() {
+        let app = router(Arc::new(JobQueue::new(1)));
+
+        let response = app
+            .oneshot(
+                Request::get(format!("/jobs/{}/result", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
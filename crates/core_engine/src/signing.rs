@@ -0,0 +1,207 @@
+//! Ed25519 signatures on proof certificates
+//!
+//! A [`Certificate`]'s `hash` is a SHA-256 of everything it records: the
+//! statement, the assumptions, every replayable step and the engine
+//! version. An engine configured with an [`EngineKey`] (see
+//! [`ProverConfig::signing_key`](crate::prover::ProverConfig::signing_key))
+//! also signs that hash, so anyone holding the engine's public key can
+//! check that a published certificate came from it unaltered;
+//! [`Prover::verify_certificate`](crate::prover::Prover::verify_certificate)
+//! checks the signature along with replaying the steps.
+//!
+//! Keys are stored as the hex encoding of their 32-byte secret seed.
+
+use crate::error::{EngineError, Result};
+use crate::prover::Certificate;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the only signature algorithm, recorded in every signature
+pub const ALGORITHM: &str = "ed25519";
+
+/// The engine's signing key
+#[derive(Clone)]
+pub struct EngineKey {
+    key: SigningKey,
+}
+
+impl std::fmt::Debug for EngineKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret
+        f.debug_struct("EngineKey")
+            .field("public_key", &self.public_key_hex())
+            .finish()
+    }
+}
+
+impl EngineKey {
+    /// A fresh key from the operating system's random source
+    pub fn generate() -> Self {
+        Self {
+            key: SigningKey::generate(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// The key whose secret seed is the 64 hex digits `seed`
+    pub fn from_hex(seed: &str) -> Result<Self> {
+        let bytes: [u8; 32] = decode_hex(seed.trim())?.try_into().map_err(|_| {
+            EngineError::validation_error("An Ed25519 secret key is 32 bytes (64 hex digits)")
+        })?;
+        Ok(Self {
+            key: SigningKey::from_bytes(&bytes),
+        })
+    }
+
+    /// The key stored at `path`, or a new one written there if the file
+    /// does not exist; the flag says whether it was created
+    pub fn load_or_generate(path: &Path) -> Result<(Self, bool)> {
+        if path.exists() {
+            return Ok((Self::from_hex(&std::fs::read_to_string(path)?)?, false));
+        }
+        let key = Self::generate();
+        std::fs::write(path, format!("{}\n", key.secret_hex()))?;
+        Ok((key, true))
+    }
+
+    /// Hex encoding of the secret seed, the format [`from_hex`](Self::from_hex) reads
+    pub fn secret_hex(&self) -> String {
+        encode_hex(&self.key.to_bytes())
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        encode_hex(self.key.verifying_key().as_bytes())
+    }
+
+    /// Signature on the hash of `certificate`
+    pub fn sign(&self, certificate: &Certificate) -> CertificateSignature {
+        CertificateSignature {
+            algorithm: ALGORITHM.to_string(),
+            public_key: self.public_key_hex(),
+            signature: encode_hex(&self.key.sign(certificate.hash.as_bytes()).to_bytes()),
+        }
+    }
+}
+
+/// Signature on a certificate's hash, with the key that made it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateSignature {
+    pub algorithm: String,
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+    /// Hex-encoded signature of the UTF-8 bytes of the certificate's hash
+    pub signature: String,
+}
+
+/// Outcome of checking a certificate's signature
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Unsigned,
+    /// Signed by the key `public_key`; whether that key is trusted is for
+    /// the reader to decide
+    Valid {
+        public_key: String,
+    },
+    Invalid {
+        reason: String,
+    },
+}
+
+/// Check the signature of `certificate`, if any, against its hash
+pub fn verify_signature(certificate: &Certificate) -> SignatureStatus {
+    let Some(signed) = &certificate.signature else {
+        return SignatureStatus::Unsigned;
+    };
+    let invalid = |reason: String| SignatureStatus::Invalid { reason };
+    if signed.algorithm != ALGORITHM {
+        return invalid(format!(
+            "unknown signature algorithm '{}'",
+            signed.algorithm
+        ));
+    }
+    let key = match decode_hex(&signed.public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(|bytes| VerifyingKey::from_bytes(&bytes))
+    {
+        Some(Ok(key)) => key,
+        _ => return invalid("malformed public key".to_string()),
+    };
+    let signature = match decode_hex(&signed.signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+    {
+        Some(bytes) => Signature::from_bytes(&bytes),
+        None => return invalid("malformed signature".to_string()),
+    };
+    match key.verify(certificate.hash.as_bytes(), &signature) {
+        Ok(()) => SignatureStatus::Valid {
+            public_key: signed.public_key.clone(),
+        },
+        Err(_) => invalid("signature does not match the certificate hash".to_string()),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(EngineError::validation_error(format!(
+            "Invalid hex string '{}'",
+            hex
+        )));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| EngineError::validation_error(format!("Invalid hex string '{}'", hex)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate() -> Certificate {
+        Certificate {
+            hash: "00ff".to_string(),
+            timestamp: chrono::Utc::now(),
+            engine_version: crate::VERSION.to_string(),
+            assumptions: vec![],
+            verification_steps: vec![],
+            statement: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = EngineKey::generate();
+        let restored = EngineKey::from_hex(&key.secret_hex()).unwrap();
+        assert_eq!(restored.public_key_hex(), key.public_key_hex());
+        assert!(!format!("{:?}", key).contains(&key.secret_hex()));
+
+        let mut cert = certificate();
+        assert_eq!(verify_signature(&cert), SignatureStatus::Unsigned);
+        cert.signature = Some(key.sign(&cert));
+        assert_eq!(
+            verify_signature(&cert),
+            SignatureStatus::Valid {
+                public_key: key.public_key_hex()
+            }
+        );
+
+        // Any change to the hash breaks the signature
+        cert.hash = "00fe".to_string();
+        assert!(matches!(
+            verify_signature(&cert),
+            SignatureStatus::Invalid { .. }
+        ));
+        assert!(EngineKey::from_hex("abcd").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Sobol low-discrepancy sequences
+//!
+//! Quasi-random points in the unit cube `[0, 1)^d` that fill it far more
+//! evenly than pseudo-random draws: the first `2^k` points put exactly one
+//! point in each of the `2^k` equal slices of every axis. Points are
+//! generated in Gray-code order (Antonov-Saleev) from the Joe-Kuo
+//! `new-joe-kuo-6.21201` direction numbers, unscrambled, so the sequence is
+//! deterministic and starts at the origin.
+
+use crate::error::{EngineError, Result};
+
+/// Bits of precision per coordinate, and so at most `2^BITS` points
+const BITS: usize = 32;
+
+/// Primitive polynomial degree `s`, its coefficients `a` and the initial
+/// direction numbers `m` for dimensions 2, 3, ...
+const DIRECTION_NUMBERS: &[(u32, u32, &[u32])] = &[
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+];
+
+/// Highest dimension [`SobolSequence`] supports
+pub const MAX_DIMENSIONS: usize = DIRECTION_NUMBERS.len() + 1;
+
+/// Iterator over the points of a Sobol sequence
+#[derive(Debug, Clone)]
+pub struct SobolSequence {
+    /// `directions[d][i]` is direction number `i + 1` of dimension `d`,
+    /// scaled to `BITS` bits
+    directions: Vec<[u32; BITS]>,
+    /// Current point as `BITS`-bit fixed-point coordinates
+    point: Vec<u32>,
+    index: u64,
+}
+
+impl SobolSequence {
+    pub fn new(dimensions: usize) -> Result<Self> {
+        if dimensions == 0 || dimensions > MAX_DIMENSIONS {
+            return Err(EngineError::validation_error(format!(
+                "Sobol sequences support 1 to {} dimensions, got {}",
+                MAX_DIMENSIONS, dimensions
+            )));
+        }
+
+        let mut directions = Vec::with_capacity(dimensions);
+        // First dimension: the van der Corput sequence in base 2
+        directions.push(std::array::from_fn(|i| 1u32 << (BITS - 1 - i)));
+        for &(s, a, m) in &DIRECTION_NUMBERS[..dimensions - 1] {
+            let s = s as usize;
+            let mut v = [0u32; BITS];
+            for i in 0..BITS {
+                v[i] = if i < s {
+                    m[i] << (BITS - 1 - i)
+                } else {
+                    let mut value = v[i - s] ^ (v[i - s] >> s);
+                    for k in 1..s {
+                        if (a >> (s - 1 - k)) & 1 == 1 {
+                            value ^= v[i - k];
+                        }
+                    }
+                    value
+                };
+            }
+            directions.push(v);
+        }
+
+        Ok(SobolSequence {
+            directions,
+            point: vec![0; dimensions],
+            index: 0,
+        })
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.point.len()
+    }
+}
+
+impl Iterator for SobolSequence {
+    type Item = Vec<f64>;
+
+    fn next(&mut self) -> Option<Vec<f64>> {
+        if self.index >> BITS != 0 {
+            return None;
+        }
+        if self.index > 0 {
+            // Gray code: flip the direction of the lowest zero bit of index - 1
+            let bit = (!(self.index - 1)).trailing_zeros() as usize;
+            for (x, v) in self.point.iter_mut().zip(&self.directions) {
+                *x ^= v[bit];
+            }
+        }
+        self.index += 1;
+
+        let scale = (1u64 << BITS) as f64;
+        Some(self.point.iter().map(|&x| x as f64 / scale).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_points() {
+        let points: Vec<Vec<f64>> = SobolSequence::new(3).unwrap().take(5).collect();
+        assert_eq!(
+            points,
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![0.5, 0.5, 0.5],
+                vec![0.75, 0.25, 0.25],
+                vec![0.25, 0.75, 0.75],
+                vec![0.375, 0.375, 0.625],
+            ]
+        );
+        assert!(SobolSequence::new(0).is_err());
+        assert!(SobolSequence::new(MAX_DIMENSIONS + 1).is_err());
+    }
+
+    #[test]
+    fn test_points_stratify_every_axis() {
+        let n = 64;
+        let points: Vec<Vec<f64>> = SobolSequence::new(MAX_DIMENSIONS)
+            .unwrap()
+            .take(n)
+            .collect();
+
+        // One point in each of the n slices of every axis
+        for d in 0..MAX_DIMENSIONS {
+            let mut slices: Vec<usize> =
+                points.iter().map(|p| (p[d] * n as f64) as usize).collect();
+            slices.sort_unstable();
+            assert_eq!(slices, (0..n).collect::<Vec<_>>(), "dimension {}", d);
+        }
+
+        // and, for the first two axes, one in each cell of an 8x8 grid
+        let mut cells: Vec<(usize, usize)> = points
+            .iter()
+            .map(|p| ((p[0] * 8.0) as usize, (p[1] * 8.0) as usize))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+        assert_eq!(cells.len(), n);
+    }
+}
@@ -0,0 +1,245 @@
+//! Hamiltonian spectra and level tracking
+//!
+//! [`Spectrum::of`] diagonalises a Hermitian Hamiltonian and groups
+//! degenerate levels. [`LevelScan`] follows the levels across a 1-D
+//! parameter scan: eigenvalues come out of `eigh` sorted, so levels are
+//! matched between neighbouring scan points by eigenvector overlap, and a
+//! change in their energy ordering is reported as a [`LevelCrossing`].
+
+use crate::error::{EngineError, Result};
+use crate::kernels_cpu::eigh_column_major;
+use ndarray::Array2;
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+/// Eigen-decomposition of one Hamiltonian
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spectrum {
+    pub name: String,
+    /// Eigenvalues in ascending order
+    pub eigenvalues: Vec<f64>,
+    /// `eigenvectors[k]` is the normalised eigenvector of `eigenvalues[k]`
+    pub eigenvectors: Vec<Vec<Complex64>>,
+    /// Indices of levels sharing an energy within the degeneracy tolerance
+    pub degeneracies: Vec<Vec<usize>>,
+}
+
+impl Spectrum {
+    /// Diagonalise `hamiltonian`, treating levels closer than `tolerance`
+    /// as degenerate
+    pub fn of(name: &str, hamiltonian: &Array2<Complex64>, tolerance: f64) -> Result<Self> {
+        let (rows, cols) = hamiltonian.dim();
+        if rows != cols {
+            return Err(EngineError::dimension_mismatch(
+                format!("square matrix for Hamiltonian '{}'", name),
+                format!("{}x{}", rows, cols),
+            ));
+        }
+
+        let deviation = hamiltonian
+            .indexed_iter()
+            .map(|((i, j), h)| (h - hamiltonian[[j, i]].conj()).norm())
+            .fold(0.0_f64, f64::max);
+        if deviation > crate::VALIDATION_TOL {
+            return Err(EngineError::NotHermitian { deviation });
+        }
+
+        let (eigenvalues, vectors) = eigh_column_major(hamiltonian)?;
+        let eigenvalues = eigenvalues.to_vec();
+        let eigenvectors = (0..cols).map(|k| vectors.column(k).to_vec()).collect();
+
+        let mut degeneracies: Vec<Vec<usize>> = Vec::new();
+        for (k, &energy) in eigenvalues.iter().enumerate() {
+            match degeneracies.last_mut() {
+                Some(group) if energy - eigenvalues[group[0]] <= tolerance => group.push(k),
+                _ => degeneracies.push(vec![k]),
+            }
+        }
+        degeneracies.retain(|group| group.len() > 1);
+
+        Ok(Spectrum {
+            name: name.to_string(),
+            eigenvalues,
+            eigenvectors,
+            degeneracies,
+        })
+    }
+}
+
+/// Two tracked levels exchanging energy order between two scan points
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelCrossing {
+    /// Tracked levels, labelled by their index at the first scan point
+    pub levels: (usize, usize),
+    /// Parameter value where the energies cross, by linear interpolation
+    pub parameter: f64,
+    pub energy: f64,
+}
+
+/// Spectra of one Hamiltonian across a 1-D parameter scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelScan {
+    pub parameter: String,
+    pub values: Vec<f64>,
+    /// `energies[i][k]` is the energy of tracked level `k` at `values[i]`
+    pub energies: Vec<Vec<f64>>,
+    pub crossings: Vec<LevelCrossing>,
+}
+
+impl LevelScan {
+    /// Track levels through `spectra`, computed at the parameter `values`
+    pub fn track(parameter: &str, values: &[f64], spectra: &[Spectrum]) -> Result<Self> {
+        if values.len() != spectra.len() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{} spectra", values.len()),
+                format!("{}", spectra.len()),
+            ));
+        }
+        let dim = spectra.first().map_or(0, |s| s.eigenvalues.len());
+        if let Some(s) = spectra.iter().find(|s| s.eigenvalues.len() != dim) {
+            return Err(EngineError::dimension_mismatch(
+                format!("{} levels", dim),
+                format!("{} levels in '{}'", s.eigenvalues.len(), s.name),
+            ));
+        }
+
+        // assignment[k] is the eigenvalue index currently followed by level k
+        let mut assignment: Vec<usize> = (0..dim).collect();
+        let mut energies = Vec::with_capacity(spectra.len());
+        let mut crossings = Vec::new();
+
+        for (i, spectrum) in spectra.iter().enumerate() {
+            if i > 0 {
+                let previous = &spectra[i - 1];
+                let next = match_levels(previous, spectrum, &assignment);
+
+                for a in 0..dim {
+                    for b in a + 1..dim {
+                        let before = previous.eigenvalues[assignment[a]]
+                            - previous.eigenvalues[assignment[b]];
+                        let after = spectrum.eigenvalues[next[a]] - spectrum.eigenvalues[next[b]];
+                        if before * after < 0.0 {
+                            let s = before / (before - after);
+                            let energy_a = previous.eigenvalues[assignment[a]]
+                                + s * (spectrum.eigenvalues[next[a]]
+                                    - previous.eigenvalues[assignment[a]]);
+                            crossings.push(LevelCrossing {
+                                levels: (a, b),
+                                parameter: values[i - 1] + s * (values[i] - values[i - 1]),
+                                energy: energy_a,
+                            });
+                        }
+                    }
+                }
+                assignment = next;
+            }
+            energies.push(
+                assignment
+                    .iter()
+                    .map(|&k| spectrum.eigenvalues[k])
+                    .collect(),
+            );
+        }
+
+        Ok(LevelScan {
+            parameter: parameter.to_string(),
+            values: values.to_vec(),
+            energies,
+            crossings,
+        })
+    }
+}
+
+/// Greedily match each tracked level to the unclaimed eigenvector of `next`
+/// with the largest overlap, strongest overlaps first
+fn match_levels(previous: &Spectrum, next: &Spectrum, assignment: &[usize]) -> Vec<usize> {
+    let dim = assignment.len();
+    let mut pairs = Vec::with_capacity(dim * dim);
+    for (level, &k) in assignment.iter().enumerate() {
+        for j in 0..dim {
+            let overlap: Complex64 = previous.eigenvectors[k]
+                .iter()
+                .zip(&next.eigenvectors[j])
+                .map(|(a, b)| a.conj() * b)
+                .sum();
+            pairs.push((overlap.norm_sqr(), level, j));
+        }
+    }
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut matched = vec![usize::MAX; dim];
+    let mut claimed = vec![false; dim];
+    for (_, level, j) in pairs {
+        if matched[level] == usize::MAX && !claimed[j] {
+            matched[level] = j;
+            claimed[j] = true;
+        }
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(re: f64) -> Complex64 {
+        Complex64::new(re, 0.0)
+    }
+
+    /// H = λ σz + Δ σx
+    fn qubit(lambda: f64, delta: f64) -> Array2<Complex64> {
+        Array2::from_shape_vec((2, 2), vec![c(lambda), c(delta), c(delta), c(-lambda)]).unwrap()
+    }
+
+    #[test]
+    fn test_spectrum_and_degeneracies() {
+        let mut h = Array2::zeros((3, 3));
+        h[[0, 0]] = c(1.0);
+        h[[1, 1]] = c(-2.0);
+        h[[2, 2]] = c(1.0);
+
+        let spectrum = Spectrum::of("H", &h, 1e-9).unwrap();
+        assert_eq!(spectrum.eigenvalues.len(), 3);
+        assert!((spectrum.eigenvalues[0] + 2.0).abs() < 1e-12);
+        assert_eq!(spectrum.degeneracies, vec![vec![1, 2]]);
+        assert!((spectrum.eigenvectors[0][1].norm() - 1.0).abs() < 1e-12);
+
+        // σy: H v = λ v for the complex eigenvectors too
+        let sigma_y = ndarray::array![
+            [c(0.0), Complex64::new(0.0, -1.0)],
+            [Complex64::new(0.0, 1.0), c(0.0)]
+        ];
+        let spectrum = Spectrum::of("Y", &sigma_y, 1e-9).unwrap();
+        for (value, vector) in spectrum.eigenvalues.iter().zip(&spectrum.eigenvectors) {
+            let v = ndarray::Array1::from(vector.clone());
+            let residual = sigma_y.dot(&v) - v.mapv(|z| z * value);
+            assert!(residual.iter().all(|z| z.norm() < 1e-12));
+        }
+
+        let mut not_hermitian = qubit(0.0, 1.0);
+        not_hermitian[[0, 1]] = c(2.0);
+        assert!(Spectrum::of("H", &not_hermitian, 1e-9).is_err());
+    }
+
+    #[test]
+    fn test_scan_finds_true_crossing_only() {
+        let values: Vec<f64> = (0..20).map(|i| -0.95 + i as f64 * 0.1).collect();
+        let scan = |delta: f64| {
+            let spectra: Vec<_> = values
+                .iter()
+                .map(|&l| Spectrum::of("H", &qubit(l, delta), 1e-9).unwrap())
+                .collect();
+            LevelScan::track("lambda", &values, &spectra).unwrap()
+        };
+
+        let crossing = scan(0.0);
+        assert_eq!(crossing.crossings.len(), 1);
+        assert!(crossing.crossings[0].parameter.abs() < 1e-9);
+        // Level 0 starts as the ground state and ends as the excited state
+        assert!(crossing.energies[0][0] < crossing.energies[0][1]);
+        assert!(crossing.energies[19][0] > crossing.energies[19][1]);
+
+        let avoided = scan(0.3);
+        assert!(avoided.crossings.is_empty());
+    }
+}
@@ -3,6 +3,12 @@
 use crate::error::{EngineError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub mod mcmc;
+pub mod tomography;
+
+pub use tomography::{process_tomography, process_tomography_with};
 
 /// Measurement data from experiments
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +27,34 @@ pub struct MeasurementEvent {
     pub count: usize,
 }
 
+/// Predicted outcome probabilities by measurement time and outcome index
+///
+/// Times are compared exactly, so look them up with the values the
+/// [`MeasurementEvent`]s carry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PredictedProbs {
+    probs: HashMap<(u64, usize), f64>,
+}
+
+impl PredictedProbs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, time: f64, outcome: usize, prob: f64) {
+        self.probs.insert((time_key(time), outcome), prob);
+    }
+
+    pub fn get(&self, time: f64, outcome: usize) -> Option<f64> {
+        self.probs.get(&(time_key(time), outcome)).copied()
+    }
+}
+
+/// `time` as a hashable key, with -0 and 0 alike
+fn time_key(time: f64) -> u64 {
+    (time + 0.0).to_bits()
+}
+
 /// Parameter fitting result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FitResult {
@@ -30,6 +64,44 @@ pub struct FitResult {
     pub fisher_info: Vec<Vec<f64>>,
     pub converged: bool,
     pub iterations: usize,
+    /// The time budget ran out before convergence; `best_params` is the
+    /// best point seen so far
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl FitResult {
+    /// Parameter covariance, the inverse of the Fisher information
+    ///
+    /// `fisher_info` may hold either the Fisher information or the Hessian
+    /// of the log-likelihood at the optimum (its negative); the sign is
+    /// normalised from the diagonal.
+    pub fn covariance(&self) -> Result<Vec<Vec<f64>>> {
+        use ndarray::Array2;
+        use ndarray_linalg::Inverse;
+
+        let n = self.best_params.len();
+        if self.fisher_info.len() != n || self.fisher_info.iter().any(|row| row.len() != n) {
+            return Err(EngineError::validation_error(format!(
+                "Fisher information must be {}x{}",
+                n, n
+            )));
+        }
+
+        let sign = if (0..n).all(|i| self.fisher_info[i][i] < 0.0) { -1.0 } else { 1.0 };
+        let fisher = Array2::from_shape_fn((n, n), |(i, j)| sign * self.fisher_info[i][j]);
+        let cov = fisher.inv().map_err(|e| {
+            EngineError::validation_error(format!("Fisher information is singular: {}", e))
+        })?;
+
+        if (0..n).any(|i| !(cov[[i, i]].is_finite() && cov[[i, i]] > 0.0)) {
+            return Err(EngineError::validation_error(
+                "Fisher information is not positive definite",
+            ));
+        }
+
+        Ok(cov.outer_iter().map(|row| row.to_vec()).collect())
+    }
 }
 
 /// Theory testing result
@@ -70,16 +142,18 @@ pub fn load_measurements(csv_path: &str) -> Result<MeasurementData> {
         
         let parts: Vec<&str> = line.split(',').collect();
         if parts.len() < 3 {
-            return Err(EngineError::Parse(
-                format!("Line {}: Expected at least 3 columns (observable,value,uncertainty)", idx + 1)
+            return Err(EngineError::parse_error(
+                idx + 1,
+                1,
+                "Expected at least 3 columns (observable,value,uncertainty)",
             ));
         }
         
         let observable = parts[0].trim().to_string();
         let value: f64 = parts[1].trim().parse()
-            .map_err(|e| EngineError::Parse(format!("Line {}: Invalid value: {}", idx + 1, e)))?;
+            .map_err(|e| EngineError::parse_error(idx + 1, 1, format!("Invalid value: {}", e)))?;
         let uncertainty: f64 = parts[2].trim().parse()
-            .map_err(|e| EngineError::Parse(format!("Line {}: Invalid uncertainty: {}", idx + 1, e)))?;
+            .map_err(|e| EngineError::parse_error(idx + 1, 1, format!("Invalid uncertainty: {}", e)))?;
         
         let entry = observables.entry(observable).or_insert_with(|| (Vec::new(), Vec::new()));
         entry.0.push(value);
@@ -102,11 +176,15 @@ pub fn gaussian_log_likelihood(
     predicted_values: &[f64],
 ) -> Result<f64> {
     if observed_values.len() != uncertainties.len() || observed_values.len() != predicted_values.len() {
-        return Err(EngineError::Validation(
+        return Err(EngineError::validation_error(
             "Observed, uncertainties, and predicted must have same length".to_string()
         ));
     }
     
+    check_finite_slice(observed_values, "observed_values")?;
+    check_finite_slice(uncertainties, "uncertainties")?;
+    check_finite_slice(predicted_values, "predicted_values")?;
+
     let mut log_l = 0.0;
     let two_pi = 2.0 * std::f64::consts::PI;
     
@@ -115,7 +193,7 @@ pub fn gaussian_log_likelihood(
         let sigma_sq = uncertainties[i] * uncertainties[i];
         
         if sigma_sq <= 0.0 {
-            return Err(EngineError::Validation(
+            return Err(EngineError::validation_error(
                 format!("Uncertainty must be positive, got {}", uncertainties[i])
             ));
         }
@@ -126,26 +204,44 @@ pub fn gaussian_log_likelihood(
     Ok(log_l)
 }
 
+/// Fail on the first NaN or infinity in likelihood input `name`
+fn check_finite_slice(values: &[f64], name: &str) -> Result<()> {
+    match values.iter().position(|v| !v.is_finite()) {
+        Some(i) => Err(EngineError::non_finite(format!(
+            "{} in {}[{}]",
+            if values[i].is_nan() { "NaN" } else { "Inf" },
+            name,
+            i
+        ))),
+        None => Ok(()),
+    }
+}
+
 /// Compute log-likelihood for discrete (binomial) measurements
 /// L(θ) = Σ_{i,m} n_{i,m} log P(m|θ,t_i)
 pub fn log_likelihood(
     observed: &[MeasurementEvent],
-    predicted_probs: &HashMap<(f64, usize), f64>,
+    predicted_probs: &PredictedProbs,
 ) -> Result<f64> {
     let mut log_l = 0.0;
 
     for event in observed {
-        let key = (event.time, event.outcome);
-        if let Some(&prob) = predicted_probs.get(&key) {
+        if let Some(prob) = predicted_probs.get(event.time, event.outcome) {
+            if !prob.is_finite() {
+                return Err(EngineError::non_finite(format!(
+                    "Predicted probability for time={}, outcome={} is {}",
+                    event.time, event.outcome, prob
+                )));
+            }
             if prob > 0.0 {
                 log_l += (event.count as f64) * prob.ln();
             } else {
-                return Err(EngineError::Validation(
+                return Err(EngineError::validation_error(
                     "Predicted probability is zero for observed event".to_string()
                 ));
             }
         } else {
-            return Err(EngineError::Validation(format!(
+            return Err(EngineError::validation_error(format!(
                 "No prediction for time={}, outcome={}",
                 event.time, event.outcome
             )));
@@ -158,26 +254,26 @@ pub fn log_likelihood(
 /// Compute chi-square statistic: χ² = Σ (observed - expected)² / expected
 pub fn chi_square(
     observed: &[MeasurementEvent],
-    predicted_probs: &HashMap<(f64, usize), f64>,
+    predicted_probs: &PredictedProbs,
 ) -> Result<f64> {
     let mut chi2 = 0.0;
-    let mut grouped: HashMap<(f64, String), Vec<(usize, usize)>> = HashMap::new();
+    let mut grouped: HashMap<(u64, String), Vec<(usize, usize)>> = HashMap::new();
 
     // Group by time and measurement_id
     for event in observed {
-        let key = (event.time, event.measurement_id.clone());
+        let key = (time_key(event.time), event.measurement_id.clone());
         grouped
             .entry(key)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push((event.outcome, event.count));
     }
 
     for ((time, _meas_id), outcomes) in grouped {
+        let time = f64::from_bits(time);
         let total: usize = outcomes.iter().map(|(_, count)| count).sum();
 
         for (outcome, observed_count) in outcomes {
-            let key = (time, outcome);
-            if let Some(&prob) = predicted_probs.get(&key) {
+            if let Some(prob) = predicted_probs.get(time, outcome) {
                 let expected = (total as f64) * prob;
                 if expected > 0.0 {
                     let diff = (observed_count as f64) - expected;
@@ -190,34 +286,107 @@ pub fn chi_square(
     Ok(chi2)
 }
 
+/// Pearson χ² goodness-of-fit test of outcome counts against predicted
+/// probabilities
+///
+/// Each measurement setting (time and measurement id) with `k` predicted
+/// outcomes contributes `k - 1` degrees of freedom, since its counts are
+/// tied to its total; `num_fitted_params` more are lost to parameters fitted
+/// on the same data. The p-value is the probability of a statistic at least
+/// this large if the model is right, and the model is rejected below
+/// `significance`.
+pub fn chi_square_test(
+    observed: &[MeasurementEvent],
+    predicted_probs: &PredictedProbs,
+    num_fitted_params: usize,
+    significance: f64,
+) -> Result<TestResult> {
+    let statistic = chi_square(observed, predicted_probs)?;
+
+    // Cells per setting, counted as in `chi_square`
+    let mut settings: Vec<(f64, &str, usize)> = Vec::new();
+    for event in observed {
+        let predicted = predicted_probs.get(event.time, event.outcome).unwrap_or(0.0);
+        if predicted <= 0.0 {
+            continue;
+        }
+        match settings
+            .iter_mut()
+            .find(|(time, id, _)| *time == event.time && *id == event.measurement_id)
+        {
+            Some((_, _, cells)) => *cells += 1,
+            None => settings.push((event.time, &event.measurement_id, 1)),
+        }
+    }
+    let cells: usize = settings.iter().map(|(_, _, cells)| cells - 1).sum();
+    if cells <= num_fitted_params {
+        return Err(EngineError::validation_error(format!(
+            "χ² test needs more independent cells ({}) than fitted parameters ({})",
+            cells, num_fitted_params
+        )));
+    }
+    let dof = cells - num_fitted_params;
+
+    let p_value = chi_square_sf(statistic, dof);
+    let conclusion = if p_value < significance {
+        format!("Rejected at significance {} (p = {:.3e})", significance, p_value)
+    } else {
+        format!("Consistent at significance {} (p = {:.3})", significance, p_value)
+    };
+    Ok(TestResult {
+        method: "chi_square".to_string(),
+        statistic,
+        p_value: Some(p_value),
+        degrees_of_freedom: Some(dof),
+        conclusion,
+    })
+}
+
+/// CDF of the χ² distribution with `dof` degrees of freedom
+pub fn chi_square_cdf(x: f64, dof: usize) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    1.0 - gamma_q(dof as f64 / 2.0, x / 2.0)
+}
+
+/// Survival function `P(X ≥ x)` of the χ² distribution, accurate in the
+/// far tail where `1 - chi_square_cdf` would round to zero
+pub fn chi_square_sf(x: f64, dof: usize) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    gamma_q(dof as f64 / 2.0, x / 2.0)
+}
+
 /// Compute KL divergence: D_KL(P||Q) = Σ P(x) log(P(x)/Q(x))
 pub fn kl_divergence(
     observed: &[MeasurementEvent],
-    predicted_probs: &HashMap<(f64, usize), f64>,
+    predicted_probs: &PredictedProbs,
 ) -> Result<f64> {
     let mut kl = 0.0;
-    let mut grouped: HashMap<(f64, String), Vec<(usize, usize)>> = HashMap::new();
+    let mut grouped: HashMap<(u64, String), Vec<(usize, usize)>> = HashMap::new();
 
     for event in observed {
-        let key = (event.time, event.measurement_id.clone());
+        let key = (time_key(event.time), event.measurement_id.clone());
         grouped
             .entry(key)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push((event.outcome, event.count));
     }
 
     for ((time, _meas_id), outcomes) in grouped {
+        let time = f64::from_bits(time);
         let total: usize = outcomes.iter().map(|(_, count)| count).sum();
 
         for (outcome, observed_count) in outcomes {
             let p = (observed_count as f64) / (total as f64);
             if p > 0.0 {
-                let key = (time, outcome);
-                if let Some(&q) = predicted_probs.get(&key) {
+                if let Some(q) = predicted_probs.get(time, outcome) {
                     if q > 0.0 {
                         kl += p * (p / q).ln();
                     } else {
-                        return Err(EngineError::Validation(
+                        return Err(EngineError::validation_error(
                             "Predicted probability is zero for observed event".to_string()
                         ));
                     }
@@ -311,6 +480,60 @@ where
     Ok(hessian)
 }
 
+/// Optimizer behind an MLE fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FitMethod {
+    /// Fixed-step gradient ascent
+    #[default]
+    GradientAscent,
+    /// Limited-memory BFGS with a backtracking line search, projected onto
+    /// the bounds
+    Lbfgs,
+    /// Derivative-free downhill simplex
+    NelderMead,
+}
+
+impl std::str::FromStr for FitMethod {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "gradient" | "gradient-ascent" => Ok(FitMethod::GradientAscent),
+            "lbfgs" | "l-bfgs" => Ok(FitMethod::Lbfgs),
+            "nelder-mead" | "simplex" => Ok(FitMethod::NelderMead),
+            _ => Err(EngineError::validation_error(format!(
+                "Unknown fit method '{}' (expected gradient, lbfgs or nelder-mead)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options for [`fit_parameters_mle_with`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FitOptions {
+    pub method: FitMethod,
+    /// `(lower, upper)` box for each parameter
+    pub bounds: Option<Vec<(f64, f64)>>,
+    /// Stop once an iteration improves the log-likelihood by less than this
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    /// Wall-clock budget; see [`fit_parameters_mle_budgeted`]
+    pub budget: Option<Duration>,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        FitOptions {
+            method: FitMethod::GradientAscent,
+            bounds: None,
+            tolerance: 1e-6,
+            max_iterations: 100,
+            budget: None,
+        }
+    }
+}
+
 /// Simple gradient descent optimizer
 pub fn gradient_descent<F>(
     f: F,
@@ -321,22 +544,92 @@ pub fn gradient_descent<F>(
 ) -> Result<FitResult>
 where
     F: Fn(&[f64]) -> Result<f64>,
+{
+    gradient_descent_until(f, initial_params, max_iterations, learning_rate, tolerance, None, None)
+}
+
+/// Gradient descent that stops at `deadline`, returning the best point seen
+fn gradient_descent_until<F>(
+    f: F,
+    initial_params: &[f64],
+    max_iterations: usize,
+    learning_rate: f64,
+    tolerance: f64,
+    deadline: Option<Instant>,
+    bounds: Option<&[(f64, f64)]>,
+) -> Result<FitResult>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+{
+    gradient_ascent(
+        &f,
+        |params: &[f64]| bounded_gradient(&f, params, bounds),
+        initial_params,
+        max_iterations,
+        learning_rate,
+        tolerance,
+        deadline,
+        bounds,
+    )
+}
+
+/// Fixed-step gradient ascent on `f`, with its gradient from `gradient_fn`
+#[allow(clippy::too_many_arguments)]
+fn gradient_ascent<F, G>(
+    f: F,
+    gradient_fn: G,
+    initial_params: &[f64],
+    max_iterations: usize,
+    learning_rate: f64,
+    tolerance: f64,
+    deadline: Option<Instant>,
+    bounds: Option<&[(f64, f64)]>,
+) -> Result<FitResult>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+    G: Fn(&[f64]) -> Result<Vec<f64>>,
 {
     let mut params = initial_params.to_vec();
+    project(&mut params, bounds);
     let mut log_likelihood = f(&params)?;
+    let mut best = (params.clone(), log_likelihood);
     
     for iter in 0..max_iterations {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            let (best_params, best_ll) = best;
+            let n = best_params.len();
+            return Ok(FitResult {
+                best_params,
+                uncertainties: vec![f64::INFINITY; n],
+                log_likelihood: best_ll,
+                fisher_info: vec![vec![0.0; n]; n],
+                converged: false,
+                iterations: iter,
+                truncated: true,
+            });
+        }
+
         // Compute gradient (negative because we maximize log-likelihood)
-        let gradient = compute_gradient(&f, &params, 1e-6)?;
+        let gradient = gradient_fn(&params)?;
+        if gradient.len() != params.len() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{} gradient components", params.len()),
+                format!("{}", gradient.len()),
+            ));
+        }
         
         // Update parameters (gradient ascent for maximization)
-        let mut max_grad = 0.0;
+        let mut max_grad: f64 = 0.0;
         for i in 0..params.len() {
             params[i] += learning_rate * gradient[i];
             max_grad = max_grad.max(gradient[i].abs());
         }
+        project(&mut params, bounds);
         
         let new_log_likelihood = f(&params)?;
+        if new_log_likelihood > best.1 {
+            best = (params.clone(), new_log_likelihood);
+        }
         
         // Check convergence
         if (new_log_likelihood - log_likelihood).abs() < tolerance && max_grad < tolerance {
@@ -355,6 +648,7 @@ where
                 fisher_info: hessian,
                 converged: true,
                 iterations: iter + 1,
+                truncated: false,
             });
         }
         
@@ -362,13 +656,15 @@ where
     }
     
     // Did not converge
+    let n = params.len();
     Ok(FitResult {
         best_params: params,
-        uncertainties: vec![f64::INFINITY; params.len()],
+        uncertainties: vec![f64::INFINITY; n],
         log_likelihood,
-        fisher_info: vec![vec![0.0; params.len()]; params.len()],
+        fisher_info: vec![vec![0.0; n]; n],
         converged: false,
         iterations: max_iterations,
+        truncated: false,
     })
 }
 
@@ -391,6 +687,518 @@ where
     )
 }
 
+/// MLE fit with the gradient of the log-likelihood supplied by
+/// `gradient_fn`, e.g. from
+/// [`Executor::execute_with_sensitivities`](crate::executor::Executor::execute_with_sensitivities),
+/// instead of finite differences
+///
+/// Each step then costs one gradient evaluation rather than two
+/// likelihood evaluations per parameter, and the step direction is free of
+/// differencing error.
+pub fn fit_parameters_mle_with_gradient<F, G>(
+    likelihood_fn: F,
+    gradient_fn: G,
+    initial_guess: &[f64],
+    max_iterations: usize,
+) -> Result<FitResult>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+    G: Fn(&[f64]) -> Result<Vec<f64>>,
+{
+    check_finite_slice(initial_guess, "initial_guess")?;
+    gradient_ascent(likelihood_fn, gradient_fn, initial_guess, max_iterations, 0.01, 1e-6, None, None)
+}
+
+/// Anytime MLE fit: optimize until `budget` of wall-clock time is spent
+///
+/// Returns the best parameters found so far with `truncated = true` if the
+/// budget expires first, so interactive callers and timed jobs get a partial
+/// answer rather than an error.
+pub fn fit_parameters_mle_budgeted<F>(
+    likelihood_fn: F,
+    initial_guess: &[f64],
+    max_iterations: usize,
+    budget: Duration,
+) -> Result<FitResult>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+{
+    gradient_descent_until(
+        likelihood_fn,
+        initial_guess,
+        max_iterations,
+        0.01,
+        1e-6,
+        Some(Instant::now() + budget),
+        None,
+    )
+}
+
+/// Fit parameters using MLE with a chosen optimizer, bounds and tolerance
+///
+/// L-BFGS copes with correlated parameters far better than fixed-step
+/// gradient ascent; Nelder-Mead needs no gradients, for noisy or
+/// non-smooth likelihoods. Uncertainties come from the Hessian at the
+/// optimum.
+pub fn fit_parameters_mle_with<F>(
+    likelihood_fn: F,
+    initial_guess: &[f64],
+    options: &FitOptions,
+) -> Result<FitResult>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+{
+    let bounds = options.bounds.as_deref();
+    if let Some(bounds) = bounds {
+        if bounds.len() != initial_guess.len() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{} bounds", initial_guess.len()),
+                format!("{}", bounds.len()),
+            ));
+        }
+        if let Some((i, (lo, hi))) = bounds.iter().enumerate().find(|(_, (lo, hi))| lo.is_nan() || hi.is_nan() || lo > hi) {
+            return Err(EngineError::validation_error(format!(
+                "Empty bounds [{}, {}] for parameter {}",
+                lo, hi, i
+            )));
+        }
+    }
+    check_finite_slice(initial_guess, "initial_guess")?;
+    let deadline = options.budget.map(|budget| Instant::now() + budget);
+
+    // -∞ is a legitimate log-likelihood (a predicted probability of zero);
+    // NaN and +∞ would steer every optimizer somewhere meaningless
+    let likelihood_fn = |params: &[f64]| -> Result<f64> {
+        let value = likelihood_fn(params)?;
+        if value.is_nan() || value == f64::INFINITY {
+            return Err(EngineError::non_finite(format!(
+                "Log-likelihood is {} at parameters {:?}",
+                value, params
+            )));
+        }
+        Ok(value)
+    };
+
+    let search = match options.method {
+        FitMethod::GradientAscent => {
+            return gradient_descent_until(
+                likelihood_fn,
+                initial_guess,
+                options.max_iterations,
+                0.01,
+                options.tolerance,
+                deadline,
+                bounds,
+            );
+        }
+        FitMethod::Lbfgs => lbfgs(&likelihood_fn, initial_guess, options, bounds, deadline)?,
+        FitMethod::NelderMead => {
+            nelder_mead(&likelihood_fn, initial_guess, options, bounds, deadline)?
+        }
+    };
+
+    let n = search.params.len();
+    if search.truncated {
+        return Ok(FitResult {
+            best_params: search.params,
+            uncertainties: vec![f64::INFINITY; n],
+            log_likelihood: search.log_likelihood,
+            fisher_info: vec![vec![0.0; n]; n],
+            converged: false,
+            iterations: search.iterations,
+            truncated: true,
+        });
+    }
+
+    let hessian = compute_hessian(likelihood_fn, &search.params, 1e-5)?;
+    let mut result = FitResult {
+        best_params: search.params,
+        uncertainties: vec![f64::INFINITY; n],
+        log_likelihood: search.log_likelihood,
+        fisher_info: hessian,
+        converged: search.converged,
+        iterations: search.iterations,
+        truncated: false,
+    };
+    if let Ok(cov) = result.covariance() {
+        result.uncertainties = (0..n).map(|i| cov[i][i].sqrt()).collect();
+    }
+    Ok(result)
+}
+
+/// Fit parameters so the state `state_fn(params)` best matches `target`
+///
+/// Maximizes the fidelity `F(ρ(params), target)`, so `log_likelihood` in
+/// the result holds `-(1 - F)`, the negated infidelity at the optimum.
+pub fn fit_to_target_state<F>(
+    state_fn: F,
+    target: &ndarray::Array2<num_complex::Complex64>,
+    initial_guess: &[f64],
+    options: &FitOptions,
+) -> Result<FitResult>
+where
+    F: Fn(&[f64]) -> Result<ndarray::Array2<num_complex::Complex64>>,
+{
+    let negative_infidelity = |params: &[f64]| -> Result<f64> {
+        Ok(crate::quantum_info::state_fidelity(&state_fn(params)?, target)? - 1.0)
+    };
+    fit_parameters_mle_with(negative_infidelity, initial_guess, options)
+}
+
+/// Outcome of an optimizer run, before uncertainties are computed
+struct Search {
+    params: Vec<f64>,
+    log_likelihood: f64,
+    iterations: usize,
+    converged: bool,
+    truncated: bool,
+}
+
+fn project(params: &mut [f64], bounds: Option<&[(f64, f64)]>) {
+    if let Some(bounds) = bounds {
+        for (x, &(lo, hi)) in params.iter_mut().zip(bounds) {
+            *x = x.clamp(lo, hi);
+        }
+    }
+}
+
+/// Finite-difference gradient that never steps outside the bounds: one-sided
+/// differences are used within `epsilon` of a bound
+fn bounded_gradient<F>(f: &F, params: &[f64], bounds: Option<&[(f64, f64)]>) -> Result<Vec<f64>>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+{
+    let epsilon = 1e-6;
+    let Some(bounds) = bounds else {
+        return compute_gradient(f, params, epsilon);
+    };
+
+    let mut gradient = vec![0.0; params.len()];
+    for i in 0..params.len() {
+        let (lo, hi) = bounds[i];
+        let mut plus = params.to_vec();
+        let mut minus = params.to_vec();
+        plus[i] = (params[i] + epsilon).min(hi);
+        minus[i] = (params[i] - epsilon).max(lo);
+        let h = plus[i] - minus[i];
+        if h > 0.0 {
+            gradient[i] = (f(&plus)? - f(&minus)?) / h;
+        }
+    }
+    Ok(gradient)
+}
+
+/// L-BFGS ascent on the log-likelihood, projected onto the bounds
+fn lbfgs<F>(
+    f: &F,
+    initial: &[f64],
+    options: &FitOptions,
+    bounds: Option<&[(f64, f64)]>,
+    deadline: Option<Instant>,
+) -> Result<Search>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+{
+    const MEMORY: usize = 10;
+    let n = initial.len();
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+
+    // Minimise the negative log-likelihood
+    let mut x = initial.to_vec();
+    project(&mut x, bounds);
+    let mut value = -f(&x)?;
+    let mut gradient: Vec<f64> = bounded_gradient(f, &x, bounds)?
+        .iter()
+        .map(|g| -g)
+        .collect();
+    let mut history: std::collections::VecDeque<(Vec<f64>, Vec<f64>, f64)> =
+        std::collections::VecDeque::with_capacity(MEMORY);
+
+    // Components pinned at a bound with the gradient pushing outwards
+    let pinned = |x: &[f64], g: &[f64], i: usize| {
+        bounds.is_some_and(|b| (x[i] <= b[i].0 && g[i] > 0.0) || (x[i] >= b[i].1 && g[i] < 0.0))
+    };
+
+    for iter in 0..options.max_iterations {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Ok(Search {
+                params: x,
+                log_likelihood: -value,
+                iterations: iter,
+                converged: false,
+                truncated: true,
+            });
+        }
+
+        let free_gradient: Vec<f64> = (0..n)
+            .map(|i| {
+                if pinned(&x, &gradient, i) {
+                    0.0
+                } else {
+                    gradient[i]
+                }
+            })
+            .collect();
+        if free_gradient.iter().all(|g| g.abs() < options.tolerance) {
+            return Ok(Search {
+                params: x,
+                log_likelihood: -value,
+                iterations: iter,
+                converged: true,
+                truncated: false,
+            });
+        }
+
+        // Two-loop recursion: direction = -H·g
+        let mut q = free_gradient.clone();
+        let mut alphas = Vec::with_capacity(history.len());
+        for (s, y, rho) in history.iter().rev() {
+            let alpha = rho * dot(s, &q);
+            q.iter_mut().zip(y).for_each(|(qi, yi)| *qi -= alpha * yi);
+            alphas.push(alpha);
+        }
+        if let Some((s, y, _)) = history.back() {
+            let gamma = dot(s, y) / dot(y, y);
+            q.iter_mut().for_each(|qi| *qi *= gamma);
+        }
+        for ((s, y, rho), alpha) in history.iter().zip(alphas.iter().rev()) {
+            let beta = rho * dot(y, &q);
+            q.iter_mut()
+                .zip(s)
+                .for_each(|(qi, si)| *qi += (alpha - beta) * si);
+        }
+        let mut direction: Vec<f64> = q.iter().map(|qi| -qi).collect();
+        if dot(&direction, &free_gradient) >= 0.0 {
+            // Not a descent direction: fall back to steepest descent
+            history.clear();
+            direction = free_gradient.iter().map(|g| -g).collect();
+        }
+        for (i, d) in direction.iter_mut().enumerate() {
+            if pinned(&x, &gradient, i) {
+                *d = 0.0;
+            }
+        }
+
+        // Backtracking line search (Armijo condition)
+        let mut step = 1.0;
+        let mut accepted = None;
+        for _ in 0..40 {
+            let mut trial: Vec<f64> = x
+                .iter()
+                .zip(&direction)
+                .map(|(xi, di)| xi + step * di)
+                .collect();
+            project(&mut trial, bounds);
+            let moved: Vec<f64> = trial.iter().zip(&x).map(|(t, xi)| t - xi).collect();
+            let trial_value = -f(&trial)?;
+            if trial_value.is_finite() && trial_value <= value + 1e-4 * dot(&gradient, &moved) {
+                accepted = Some((trial, trial_value));
+                break;
+            }
+            step *= 0.5;
+        }
+        let Some((next, next_value)) = accepted else {
+            // No progress along a descent direction: at a (bounded) optimum
+            return Ok(Search {
+                params: x,
+                log_likelihood: -value,
+                iterations: iter + 1,
+                converged: true,
+                truncated: false,
+            });
+        };
+
+        let next_gradient: Vec<f64> = bounded_gradient(f, &next, bounds)?
+            .iter()
+            .map(|g| -g)
+            .collect();
+        let s: Vec<f64> = next.iter().zip(&x).map(|(a, b)| a - b).collect();
+        let y: Vec<f64> = next_gradient
+            .iter()
+            .zip(&gradient)
+            .map(|(a, b)| a - b)
+            .collect();
+        let curvature = dot(&s, &y);
+        if curvature > 1e-12 {
+            if history.len() == MEMORY {
+                history.pop_front();
+            }
+            history.push_back((s, y, 1.0 / curvature));
+        }
+
+        let improvement = value - next_value;
+        x = next;
+        value = next_value;
+        gradient = next_gradient;
+        if improvement < options.tolerance {
+            return Ok(Search {
+                params: x,
+                log_likelihood: -value,
+                iterations: iter + 1,
+                converged: true,
+                truncated: false,
+            });
+        }
+    }
+
+    Ok(Search {
+        params: x,
+        log_likelihood: -value,
+        iterations: options.max_iterations,
+        converged: false,
+        truncated: false,
+    })
+}
+
+/// Nelder-Mead simplex search on the log-likelihood
+///
+/// Bounds are handled by searching over unbounded internal coordinates (see
+/// [`to_external`]); clamping vertices instead collapses the simplex onto
+/// the boundary.
+fn nelder_mead<F>(
+    f: &F,
+    initial: &[f64],
+    options: &FitOptions,
+    bounds: Option<&[(f64, f64)]>,
+    deadline: Option<Instant>,
+) -> Result<Search>
+where
+    F: Fn(&[f64]) -> Result<f64>,
+{
+    let n = initial.len();
+    // Minimise the negative log-likelihood; non-finite values are "worst"
+    let cost = |u: &[f64]| -> Result<f64> {
+        let ll = f(&to_external(u, bounds))?;
+        Ok(if ll.is_finite() { -ll } else { f64::INFINITY })
+    };
+
+    let mut simplex = Vec::with_capacity(n + 1);
+    let u0 = to_internal(initial, bounds);
+    simplex.push((cost(&u0)?, u0.clone()));
+    for i in 0..n {
+        let mut ui = u0.clone();
+        ui[i] += if ui[i] != 0.0 { 0.05 * ui[i] } else { 0.00025 };
+        simplex.push((cost(&ui)?, ui));
+    }
+
+    let combine = |a: &[f64], b: &[f64], t: f64| -> Vec<f64> {
+        a.iter().zip(b).map(|(ai, bi)| ai + t * (bi - ai)).collect()
+    };
+
+    for iter in 0..options.max_iterations {
+        simplex.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (best, worst) = (simplex[0].0, simplex[n].0);
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            let (value, u) = simplex.swap_remove(0);
+            return Ok(Search {
+                params: to_external(&u, bounds),
+                log_likelihood: -value,
+                iterations: iter,
+                converged: false,
+                truncated: true,
+            });
+        }
+        if (worst - best).abs() < options.tolerance {
+            let (value, u) = simplex.swap_remove(0);
+            return Ok(Search {
+                params: to_external(&u, bounds),
+                log_likelihood: -value,
+                iterations: iter,
+                converged: true,
+                truncated: false,
+            });
+        }
+
+        let centroid: Vec<f64> = (0..n)
+            .map(|j| simplex[..n].iter().map(|(_, x)| x[j]).sum::<f64>() / n as f64)
+            .collect();
+        let worst_point = simplex[n].1.clone();
+
+        let reflected = combine(&centroid, &worst_point, -1.0);
+        let reflected_cost = cost(&reflected)?;
+        if reflected_cost < best {
+            let expanded = combine(&centroid, &worst_point, -2.0);
+            let expanded_cost = cost(&expanded)?;
+            simplex[n] = if expanded_cost < reflected_cost {
+                (expanded_cost, expanded)
+            } else {
+                (reflected_cost, reflected)
+            };
+        } else if reflected_cost < simplex[n - 1].0 {
+            simplex[n] = (reflected_cost, reflected);
+        } else {
+            let contracted = if reflected_cost < worst {
+                combine(&centroid, &reflected, 0.5)
+            } else {
+                combine(&centroid, &worst_point, 0.5)
+            };
+            let contracted_cost = cost(&contracted)?;
+            if contracted_cost < worst.min(reflected_cost) {
+                simplex[n] = (contracted_cost, contracted);
+            } else {
+                // Shrink towards the best vertex
+                let best_point = simplex[0].1.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    let shrunk = combine(&best_point, &vertex.1, 0.5);
+                    *vertex = (cost(&shrunk)?, shrunk);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (value, u) = simplex.swap_remove(0);
+    Ok(Search {
+        params: to_external(&u, bounds),
+        log_likelihood: -value,
+        iterations: options.max_iterations,
+        converged: false,
+        truncated: false,
+    })
+}
+
+/// Map unbounded internal coordinates onto the bounds
+///
+/// A finite box uses `lo + (hi - lo)(sin u + 1)/2`, a one-sided bound
+/// `lo - 1 + sqrt(u² + 1)` (or its mirror image), as in MINUIT.
+fn to_external(internal: &[f64], bounds: Option<&[(f64, f64)]>) -> Vec<f64> {
+    let Some(bounds) = bounds else {
+        return internal.to_vec();
+    };
+    internal
+        .iter()
+        .zip(bounds)
+        .map(|(&u, &(lo, hi))| match (lo.is_finite(), hi.is_finite()) {
+            _ if lo == hi => lo,
+            (true, true) => lo + (hi - lo) * (u.sin() + 1.0) / 2.0,
+            (true, false) => lo - 1.0 + (u * u + 1.0).sqrt(),
+            (false, true) => hi + 1.0 - (u * u + 1.0).sqrt(),
+            (false, false) => u,
+        })
+        .collect()
+}
+
+/// Inverse of [`to_external`], clamping `external` into the bounds first
+fn to_internal(external: &[f64], bounds: Option<&[(f64, f64)]>) -> Vec<f64> {
+    let Some(bounds) = bounds else {
+        return external.to_vec();
+    };
+    external
+        .iter()
+        .zip(bounds)
+        .map(|(&x, &(lo, hi))| {
+            let x = x.clamp(lo, hi);
+            match (lo.is_finite(), hi.is_finite()) {
+                _ if lo == hi => 0.0,
+                (true, true) => (2.0 * (x - lo) / (hi - lo) - 1.0).asin(),
+                (true, false) => ((x - lo + 1.0).powi(2) - 1.0).sqrt(),
+                (false, true) => ((hi - x + 1.0).powi(2) - 1.0).sqrt(),
+                (false, false) => x,
+            }
+        })
+        .collect()
+}
+
 /// Compute confidence intervals from Fisher information (Hessian)
 pub fn compute_confidence_intervals(
     fisher_info: &[Vec<f64>],
@@ -401,20 +1209,19 @@ pub fn compute_confidence_intervals(
         0.68 => 1.0,
         0.95 => 1.96,
         0.99 => 2.576,
-        _ => return Err(EngineError::Validation(
+        _ => return Err(EngineError::validation_error(
             format!("Unsupported confidence level: {}", confidence_level)
         )),
     };
     
-    let n = fisher_info.len();
     let mut intervals = Vec::new();
     
-    for i in 0..n {
+    for (i, row) in fisher_info.iter().enumerate() {
         // Fisher information = -Hessian of log-likelihood
         // Variance = (Fisher info)^{-1}_{ii}
         // For now, use diagonal approximation
-        let variance = if fisher_info[i][i].abs() > 1e-10 {
-            1.0 / fisher_info[i][i].abs()
+        let variance = if row[i].abs() > 1e-10 {
+            1.0 / row[i].abs()
         } else {
             f64::INFINITY
         };
@@ -428,61 +1235,342 @@ pub fn compute_confidence_intervals(
     Ok(intervals)
 }
 
+/// How [`bootstrap_confidence_intervals`] turns the bootstrap distribution
+/// into an interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BootstrapInterval {
+    /// Quantiles of the bootstrap distribution
+    Percentile,
+    /// Bias-corrected and accelerated: quantiles shifted for the median bias
+    /// of the replicates and, via a jackknife, for how the estimator's
+    /// spread changes with its value
+    Bca,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapOptions {
+    pub n_resamples: usize,
+    pub confidence_level: f64,
+    pub interval: BootstrapInterval,
+    /// Master seed; replicate `i` resamples with its own generator seeded
+    /// from this and `i`, so results do not depend on thread scheduling
+    pub seed: u64,
+}
+
+impl Default for BootstrapOptions {
+    fn default() -> Self {
+        Self {
+            n_resamples: 1000,
+            confidence_level: 0.95,
+            interval: BootstrapInterval::Percentile,
+            seed: 0,
+        }
+    }
+}
+
+/// Bootstrap distribution of one parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapSummary {
+    /// Fit on the full data
+    pub estimate: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std_error: f64,
+    /// Mean of the replicates minus the full-data estimate
+    pub bias: f64,
+    pub interval: (f64, f64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapResult {
+    pub parameters: Vec<BootstrapSummary>,
+    pub n_resamples: usize,
+    pub interval: BootstrapInterval,
+}
+
+impl BootstrapResult {
+    pub fn intervals(&self) -> Vec<(f64, f64)> {
+        self.parameters.iter().map(|p| p.interval).collect()
+    }
+}
+
 /// Bootstrap resampling for non-parametric confidence intervals
+///
+/// Replicates are fitted in parallel across rayon workers. Each resamples
+/// `data_indices` with replacement from a sub-seed of `options.seed`, so a
+/// given seed always gives the same result.
 pub fn bootstrap_confidence_intervals<F>(
     data_indices: &[usize],
     fit_fn: F,
-    n_bootstrap: usize,
-    confidence_level: f64,
-) -> Result<Vec<(f64, f64)>>
+    options: &BootstrapOptions,
+) -> Result<BootstrapResult>
 where
-    F: Fn(&[usize]) -> Result<Vec<f64>>,
+    F: Fn(&[usize]) -> Result<Vec<f64>> + Sync,
 {
+    use crate::rng;
     use rand::seq::SliceRandom;
-    use rand::thread_rng;
-    
-    let mut rng = thread_rng();
-    let n_data = data_indices.len();
-    
-    // Storage for bootstrap parameter estimates
-    let first_fit = fit_fn(data_indices)?;
-    let n_params = first_fit.len();
-    let mut bootstrap_params = vec![Vec::new(); n_params];
-    
-    for _ in 0..n_bootstrap {
-        // Resample with replacement
-        let mut resampled: Vec<usize> = (0..n_data)
-            .map(|_| *data_indices.choose(&mut rng).unwrap())
-            .collect();
-        
-        // Fit parameters on resampled data
-        let params = fit_fn(&resampled)?;
-        
-        for (i, &param) in params.iter().enumerate() {
-            bootstrap_params[i].push(param);
+    use rayon::prelude::*;
+
+    if data_indices.len() < 2 || options.n_resamples < 2 {
+        return Err(EngineError::validation_error(
+            "Bootstrap needs at least two data points and two resamples",
+        ));
+    }
+    if !(options.confidence_level > 0.0 && options.confidence_level < 1.0) {
+        return Err(EngineError::validation_error(format!(
+            "Confidence level must lie in (0, 1), got {}",
+            options.confidence_level
+        )));
+    }
+
+    let estimate = fit_fn(data_indices)?;
+    let n_params = estimate.len();
+    let checked = |params: Vec<f64>| {
+        if params.len() == n_params {
+            Ok(params)
+        } else {
+            Err(EngineError::dimension_mismatch(
+                format!("{} parameters", n_params),
+                format!("{} parameters", params.len()),
+            ))
+        }
+    };
+
+    let replicates = (0..options.n_resamples)
+        .into_par_iter()
+        .map(|i| {
+            let mut rng = rng::from_seed(rng::sub_seed(options.seed, i as u64));
+            let resampled: Vec<usize> = (0..data_indices.len())
+                .map(|_| *data_indices.choose(&mut rng).unwrap())
+                .collect();
+            checked(fit_fn(&resampled)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Leave-one-out fits for the BCa acceleration
+    let jackknife = match options.interval {
+        BootstrapInterval::Percentile => None,
+        BootstrapInterval::Bca => Some(
+            (0..data_indices.len())
+                .into_par_iter()
+                .map(|i| {
+                    let mut subset = data_indices.to_vec();
+                    subset.remove(i);
+                    checked(fit_fn(&subset)?)
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+    };
+
+    let alpha = (1.0 - options.confidence_level) / 2.0;
+    let parameters = (0..n_params)
+        .map(|k| {
+            let mut samples: Vec<f64> = replicates.iter().map(|r| r[k]).collect();
+            samples.sort_by(f64::total_cmp);
+            let b = samples.len() as f64;
+            let mean = samples.iter().sum::<f64>() / b;
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (b - 1.0);
+
+            let (lower, upper) = match &jackknife {
+                None => (alpha, 1.0 - alpha),
+                Some(jackknife) => {
+                    let below = samples.iter().filter(|&&x| x < estimate[k]).count() as f64;
+                    let z0 = normal_quantile((below / b).clamp(0.5 / b, 1.0 - 0.5 / b));
+                    let loo: Vec<f64> = jackknife.iter().map(|j| j[k]).collect();
+                    let loo_mean = loo.iter().sum::<f64>() / loo.len() as f64;
+                    let (skew, spread) = loo.iter().fold((0.0, 0.0), |(s, v), x| {
+                        let d = loo_mean - x;
+                        (s + d.powi(3), v + d.powi(2))
+                    });
+                    let a = if spread > 0.0 {
+                        skew / (6.0 * spread.powf(1.5))
+                    } else {
+                        0.0
+                    };
+                    let adjust = |p: f64| {
+                        let z = z0 + normal_quantile(p);
+                        normal_cdf(z0 + z / (1.0 - a * z))
+                    };
+                    (adjust(alpha), adjust(1.0 - alpha))
+                }
+            };
+
+            BootstrapSummary {
+                estimate: estimate[k],
+                mean,
+                median: quantile(&samples, 0.5),
+                std_error: variance.sqrt(),
+                bias: mean - estimate[k],
+                interval: (quantile(&samples, lower), quantile(&samples, upper)),
+            }
+        })
+        .collect();
+
+    Ok(BootstrapResult {
+        parameters,
+        n_resamples: options.n_resamples,
+        interval: options.interval,
+    })
+}
+
+/// Linearly interpolated quantile of sorted samples
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let h = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let i = h.floor() as usize;
+    let j = (i + 1).min(sorted.len() - 1);
+    sorted[i] + (h - i as f64) * (sorted[j] - sorted[i])
+}
+
+/// Standard normal CDF (Numerical Recipes `erfcc`, relative error < 1.2e-7)
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |acc, c| acc * t + c);
+    let erfc = t * (-z * z + poly).exp();
+    if x >= 0.0 {
+        1.0 - 0.5 * erfc
+    } else {
+        0.5 * erfc
+    }
+}
+
+/// Natural log of Γ(x) for x > 0 (Lanczos approximation, g = 7, relative
+/// error < 1e-15)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection: Γ(x)Γ(1-x) = π / sin(πx)
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// Regularized upper incomplete gamma function Q(a, x) = Γ(a, x) / Γ(a)
+/// (Numerical Recipes `gammq`: series below x = a + 1, continued fraction
+/// above)
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const MAX_ITER: usize = 500;
+    const EPS: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..MAX_ITER {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPS {
+                break;
+            }
+        }
+        (1.0 - sum * log_prefactor.exp()).max(0.0)
+    } else {
+        // Modified Lentz evaluation of the continued fraction
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..MAX_ITER {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPS {
+                break;
+            }
         }
+        log_prefactor.exp() * h
     }
-    
-    // Compute percentiles for confidence intervals
-    let alpha = 1.0 - confidence_level;
-    let lower_percentile = alpha / 2.0;
-    let upper_percentile = 1.0 - alpha / 2.0;
-    
-    let mut intervals = Vec::new();
-    
-    for param_samples in &mut bootstrap_params {
-        param_samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let lower_idx = (n_bootstrap as f64 * lower_percentile) as usize;
-        let upper_idx = (n_bootstrap as f64 * upper_percentile) as usize;
-        
-        let lower = param_samples[lower_idx];
-        let upper = param_samples[upper_idx];
-        
-        intervals.push((lower, upper));
+}
+
+/// Standard normal quantile (Acklam's rational approximation, relative
+/// error < 1.2e-9)
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    let horner = |coeffs: &[f64], x: f64| coeffs.iter().fold(0.0, |acc, c| acc * x + c);
+    let tail = |q: f64| horner(&C, q) / (horner(&D, q) * q + 1.0);
+
+    const P_LOW: f64 = 0.02425;
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        horner(&A, r) * q / (horner(&B, r) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
     }
-    
-    Ok(intervals)
 }
 
 #[cfg(test)]
@@ -496,7 +1584,13 @@ mod tests {
         let predicted = vec![1.05, 1.95, 3.02];
         
         let log_l = gaussian_log_likelihood(&observed, &uncertainties, &predicted).unwrap();
-        assert!(log_l < 0.0); // Log-likelihood should be negative
+        // A density, not a probability: narrow uncertainties make it positive
+        let chi_sq = (0.05f64.powi(2) * 2.0 + 0.02f64.powi(2)) / 0.01;
+        let expected = -0.5 * (chi_sq + 3.0 * (2.0 * std::f64::consts::PI * 0.01).ln());
+        assert!((log_l - expected).abs() < 1e-12);
+
+        let worse = vec![1.2, 1.8, 3.1];
+        assert!(gaussian_log_likelihood(&observed, &uncertainties, &worse).unwrap() < log_l);
     }
 
     #[test]
@@ -508,8 +1602,8 @@ mod tests {
             count: 100,
         }];
 
-        let mut predicted = HashMap::new();
-        predicted.insert((0.0, 0), 0.9);
+        let mut predicted = PredictedProbs::new();
+        predicted.insert(0.0, 0, 0.9);
 
         let log_l = log_likelihood(&events, &predicted).unwrap();
         assert!(log_l < 0.0);
@@ -532,15 +1626,55 @@ mod tests {
             },
         ];
 
-        let mut predicted = HashMap::new();
-        predicted.insert((0.0, 0), 0.9);
-        predicted.insert((0.0, 1), 0.1);
+        let mut predicted = PredictedProbs::new();
+        predicted.insert(0.0, 0, 0.9);
+        predicted.insert(0.0, 1, 0.1);
 
         let chi2 = chi_square(&events, &predicted).unwrap();
         assert!(chi2 >= 0.0);
         assert!(chi2 < 1.0); // Should be small for good fit
     }
     
+    #[test]
+    fn test_chi_square_distribution() {
+        // Known critical values
+        assert!((chi_square_sf(3.841_459, 1) - 0.05).abs() < 1e-6);
+        assert!((chi_square_sf(18.307_038, 10) - 0.05).abs() < 1e-6);
+        assert!((chi_square_cdf(2.0, 2) - (1.0 - (-1.0f64).exp())).abs() < 1e-12);
+        // Far tail stays representable
+        assert!(chi_square_sf(200.0, 3) > 0.0);
+        assert!(chi_square_sf(200.0, 3) < 1e-40);
+        assert_eq!(chi_square_cdf(0.0, 4), 0.0);
+        assert!((ln_gamma(5.0) - 24.0f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_chi_square_test_counts_dof() {
+        let event = |time: f64, outcome: usize, count: usize| MeasurementEvent {
+            time,
+            measurement_id: "z".to_string(),
+            outcome,
+            count,
+        };
+        // Two settings with two outcomes each: 2 cells, less 1 fitted parameter
+        let events = vec![event(0.0, 0, 60), event(0.0, 1, 40), event(1.0, 0, 30), event(1.0, 1, 70)];
+        let mut predicted = PredictedProbs::new();
+        predicted.insert(0.0, 0, 0.5);
+        predicted.insert(0.0, 1, 0.5);
+        predicted.insert(1.0, 0, 0.5);
+        predicted.insert(1.0, 1, 0.5);
+
+        let result = chi_square_test(&events, &predicted, 1, 0.05).unwrap();
+        assert_eq!(result.degrees_of_freedom, Some(1));
+        // χ² = 4 + 16 = 20 on one degree of freedom
+        assert!((result.statistic - 20.0).abs() < 1e-12);
+        assert!(result.p_value.unwrap() < 1e-4);
+        assert!(result.conclusion.starts_with("Rejected"));
+
+        assert!(chi_square_test(&events, &predicted, 2, 0.05).is_err());
+    }
+
     #[test]
     fn test_gradient_descent() {
         // Minimize f(x) = (x - 2)^2 + (y - 3)^2
@@ -557,6 +1691,136 @@ mod tests {
         assert!(result.converged);
     }
     
+    #[test]
+    fn test_fit_with_analytic_gradient() {
+        let f = |params: &[f64]| -> Result<f64> { Ok(-((params[0] - 2.0).powi(2) + (params[1] + 1.0).powi(2))) };
+        let gradient = |params: &[f64]| -> Result<Vec<f64>> {
+            Ok(vec![-2.0 * (params[0] - 2.0), -2.0 * (params[1] + 1.0)])
+        };
+
+        let result = fit_parameters_mle_with_gradient(f, gradient, &[0.0, 0.0], 2000).unwrap();
+        assert!(result.converged);
+        assert!((result.best_params[0] - 2.0).abs() < 1e-4);
+        assert!((result.best_params[1] + 1.0).abs() < 1e-4);
+        assert!(fit_parameters_mle_with_gradient(f, |_: &[f64]| Ok(vec![0.0]), &[0.0, 0.0], 10).is_err());
+    }
+
+    #[test]
+    fn test_budgeted_fit_returns_best_so_far() {
+        // Slow objective: each evaluation sleeps, so the budget runs out
+        // long before convergence
+        let f = |params: &[f64]| -> Result<f64> {
+            std::thread::sleep(Duration::from_millis(2));
+            Ok(-(params[0] - 5.0).powi(2))
+        };
+
+        let result = fit_parameters_mle_budgeted(f, &[0.0], 100_000, Duration::from_millis(50))
+            .unwrap();
+
+        assert!(result.truncated);
+        assert!(!result.converged);
+        assert!(result.best_params[0] > 0.0);
+        assert!(result.log_likelihood > -25.0);
+    }
+
+    #[test]
+    fn test_covariance_inverts_fisher_information() {
+        let fit = FitResult {
+            best_params: vec![0.0, 0.0],
+            uncertainties: vec![0.0, 0.0],
+            log_likelihood: 0.0,
+            // Hessian of log L (negative definite)
+            fisher_info: vec![vec![-4.0, 0.0], vec![0.0, -25.0]],
+            converged: true,
+            iterations: 1,
+            truncated: false,
+        };
+
+        let cov = fit.covariance().unwrap();
+        assert!((cov[0][0] - 0.25).abs() < 1e-12);
+        assert!((cov[1][1] - 0.04).abs() < 1e-12);
+        assert!(cov[0][1].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lbfgs_and_nelder_mead_fit_correlated_likelihood() {
+        // Strongly correlated Gaussian log-likelihood peaked at (1, -2)
+        let likelihood = |p: &[f64]| -> Result<f64> {
+            let (x, y) = (p[0] - 1.0, p[1] + 2.0);
+            Ok(-(x * x + 1.8 * x * y + y * y) * 50.0)
+        };
+
+        for method in [FitMethod::Lbfgs, FitMethod::NelderMead] {
+            let options = FitOptions {
+                method,
+                tolerance: 1e-10,
+                max_iterations: 2000,
+                ..FitOptions::default()
+            };
+            let fit = fit_parameters_mle_with(likelihood, &[0.0, 0.0], &options).unwrap();
+            assert!(fit.converged, "{:?} did not converge", method);
+            assert!((fit.best_params[0] - 1.0).abs() < 1e-3, "{:?}", method);
+            assert!((fit.best_params[1] + 2.0).abs() < 1e-3, "{:?}", method);
+            assert!(fit.uncertainties.iter().all(|u| u.is_finite() && *u > 0.0));
+        }
+    }
+
+    #[test]
+    fn test_fit_respects_bounds() {
+        // Unconstrained optimum at (3, 0.5) lies outside the box on x
+        let likelihood =
+            |p: &[f64]| -> Result<f64> { Ok(-(p[0] - 3.0).powi(2) - (p[1] - 0.5).powi(2)) };
+
+        for method in [
+            FitMethod::GradientAscent,
+            FitMethod::Lbfgs,
+            FitMethod::NelderMead,
+        ] {
+            let options = FitOptions {
+                method,
+                bounds: Some(vec![(0.0, 2.0), (0.0, 1.0)]),
+                tolerance: 1e-10,
+                max_iterations: 2000,
+                ..FitOptions::default()
+            };
+            let fit = fit_parameters_mle_with(likelihood, &[0.5, 0.9], &options).unwrap();
+            assert!((fit.best_params[0] - 2.0).abs() < 1e-3, "{:?}", method);
+            assert!((fit.best_params[1] - 0.5).abs() < 1e-2, "{:?}", method);
+            assert!(fit.best_params[0] <= 2.0);
+        }
+
+        let mismatched = FitOptions {
+            bounds: Some(vec![(0.0, 1.0)]),
+            ..FitOptions::default()
+        };
+        assert!(fit_parameters_mle_with(likelihood, &[0.5, 0.5], &mismatched).is_err());
+    }
+    #[test]
+    fn test_fit_to_target_state() {
+        use num_complex::Complex64;
+
+        // Ry(theta)|0> approaching |+>, reached at theta = pi/2
+        let ry_state = |p: &[f64]| -> Result<ndarray::Array2<Complex64>> {
+            let ket = ndarray::array![
+                Complex64::new((p[0] / 2.0).cos(), 0.0),
+                Complex64::new((p[0] / 2.0).sin(), 0.0)
+            ];
+            Ok(crate::kernels_cpu::ket_to_rho(&ket))
+        };
+        let target = ry_state(&[std::f64::consts::FRAC_PI_2]).unwrap();
+        // Infidelity is quadratic at the optimum, so the angle is only
+        // resolved to about the square root of the tolerance
+        let options = FitOptions {
+            method: FitMethod::Lbfgs,
+            tolerance: 1e-12,
+            ..FitOptions::default()
+        };
+
+        let result = fit_to_target_state(ry_state, &target, &[0.3], &options).unwrap();
+        assert!((result.best_params[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-2);
+        assert!(result.log_likelihood > -1e-4);
+    }
+
     #[test]
     fn test_compute_gradient() {
         let f = |params: &[f64]| -> Result<f64> {
@@ -569,4 +1833,54 @@ mod tests {
         assert!((grad[0] - 2.0).abs() < 1e-4);
         assert!((grad[1] - 2.0).abs() < 1e-4);
     }
+
+    fn sample_mean(values: &[f64]) -> impl Fn(&[usize]) -> Result<Vec<f64>> + Sync + '_ {
+        move |indices: &[usize]| {
+            Ok(vec![indices.iter().map(|&i| values[i]).sum::<f64>() / indices.len() as f64])
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_reproducible_from_seed() {
+        let values: Vec<f64> = (0..40).map(|i| (i as f64 * 0.7).sin() + i as f64 * 0.05).collect();
+        let indices: Vec<usize> = (0..values.len()).collect();
+        let options = BootstrapOptions { n_resamples: 500, seed: 7, ..BootstrapOptions::default() };
+
+        let first = bootstrap_confidence_intervals(&indices, sample_mean(&values), &options).unwrap();
+        let second = bootstrap_confidence_intervals(&indices, sample_mean(&values), &options).unwrap();
+        assert_eq!(first.intervals(), second.intervals());
+
+        let summary = &first.parameters[0];
+        let (lo, hi) = summary.interval;
+        assert!(lo < summary.estimate && summary.estimate < hi);
+        assert!(summary.bias.abs() < summary.std_error);
+
+        let reseeded = BootstrapOptions { seed: 8, ..options };
+        let other = bootstrap_confidence_intervals(&indices, sample_mean(&values), &reseeded).unwrap();
+        assert_ne!(first.intervals(), other.intervals());
+    }
+
+    #[test]
+    fn test_bca_interval_shifts_with_skew() {
+        // Right-skewed data: BCa stretches the upper end relative to percentile
+        let values: Vec<f64> = (1..=50).map(|i| (i as f64 / 10.0).exp()).collect();
+        let indices: Vec<usize> = (0..values.len()).collect();
+        let percentile = BootstrapOptions { n_resamples: 2000, seed: 1, ..BootstrapOptions::default() };
+        let bca = BootstrapOptions { interval: BootstrapInterval::Bca, ..percentile.clone() };
+
+        let p = bootstrap_confidence_intervals(&indices, sample_mean(&values), &percentile).unwrap();
+        let b = bootstrap_confidence_intervals(&indices, sample_mean(&values), &bca).unwrap();
+        let (p_lo, p_hi) = p.parameters[0].interval;
+        let (b_lo, b_hi) = b.parameters[0].interval;
+        assert!(b_lo > p_lo && b_hi > p_hi);
+    }
+
+    #[test]
+    fn test_normal_cdf_and_quantile() {
+        assert!((normal_quantile(0.975) - 1.959_964).abs() < 1e-6);
+        assert!((normal_cdf(1.959_964) - 0.975).abs() < 1e-6);
+        for &p in &[0.001, 0.02, 0.3, 0.5, 0.9, 0.999] {
+            assert!((normal_cdf(normal_quantile(p)) - p).abs() < 1e-6);
+        }
+    }
 }
@@ -0,0 +1,648 @@
+//! Process tomography
+//!
+//! Reconstructs the channel a device applies from the outcome counts of
+//! measurements on its output, for each of a set of prepared inputs. The
+//! channel `E` is estimated as its Choi matrix
+//! `J = Σ_ij |i⟩⟨j| ⊗ E(|i⟩⟨j|)`, input factor first, in which every outcome
+//! probability is linear: effect `M` on input `ρ` is seen with probability
+//! `Tr[(ρᵀ ⊗ M) J]`. `E` is completely positive when `J ⪰ 0` and trace
+//! preserving when `Tr_out J = I`.
+//!
+//! Two estimators are provided:
+//!
+//! - [`TomographyMethod::LinearInversion`] solves the linear equations for
+//!   the observed frequencies in least squares, then projects the solution
+//!   onto the CPTP maps by Dykstra's alternating projections onto the
+//!   positive semidefinite cone and the trace-preserving plane (Knee et al.,
+//!   PRA 98, 062336, 2018);
+//! - [`TomographyMethod::MaximumLikelihood`] iterates `J ← Λ K J K Λ` from
+//!   the completely depolarizing channel (Ježek, Fiurášek & Hradil, PRA 68,
+//!   012305, 2003), which raises the likelihood and stays CPTP throughout.
+//!
+//! Estimates are reported as the χ matrix in the basis's operator basis,
+//! `E(ρ) = Σ_mn χ_mn P_m ρ P_n†`, and compared with a simulated channel
+//! through [`ProcessEstimate::fidelity`].
+
+use crate::ensemble::StateEnsemble;
+use crate::error::{EngineError, Result};
+use crate::executor::EvolutionState;
+use crate::kernels_cpu::{dagger, eigh_column_major, ket_to_rho, tensor_product};
+use crate::operators::PauliString;
+use crate::quantum_info::state_fidelity;
+use ndarray::{Array1, Array2};
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+/// Preparations and measurements of a tomography experiment, and the
+/// operator basis of the χ matrix
+#[derive(Debug, Clone)]
+pub struct TomographyBasis {
+    /// Density matrices prepared as inputs
+    pub inputs: Vec<Array2<Complex64>>,
+    /// Measurement settings, each given by the effects of its outcomes
+    pub settings: Vec<Vec<Array2<Complex64>>>,
+    /// `d²` operators with `Tr(P_m† P_n) = d δ_mn`, such as the Pauli strings
+    pub operators: Vec<Array2<Complex64>>,
+}
+
+/// Outcome counts of a tomography experiment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessTomographyData {
+    /// `counts[k][s][m]`: how often outcome `m` of setting `s` was seen on
+    /// input `k`
+    pub counts: Vec<Vec<Vec<f64>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TomographyMethod {
+    /// Least squares, projected onto the CPTP maps
+    LinearInversion,
+    /// Iterative maximum likelihood over the CPTP maps
+    MaximumLikelihood,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TomographyOptions {
+    pub method: TomographyMethod,
+    /// Iterations of the CPTP projection or of the likelihood maximisation
+    pub max_iterations: usize,
+    /// Converged once an iteration moves the Choi matrix by less than this
+    /// in Frobenius norm
+    pub tolerance: f64,
+}
+
+impl Default for TomographyOptions {
+    fn default() -> Self {
+        Self {
+            method: TomographyMethod::MaximumLikelihood,
+            max_iterations: 5000,
+            tolerance: 1e-10,
+        }
+    }
+}
+
+/// Reconstructed channel
+#[derive(Debug, Clone)]
+pub struct ProcessEstimate {
+    /// Choi matrix, `d² × d²` with trace `d`
+    pub choi: Array2<Complex64>,
+    /// χ matrix in the basis's operators, with trace 1
+    pub chi: Array2<Complex64>,
+    /// `Σ n log p` of the counts under the estimate
+    pub log_likelihood: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+impl ProcessEstimate {
+    /// Fidelity to the channel with Kraus operators `kraus`: the state
+    /// fidelity of the two Choi matrices normalised to unit trace, 1 for
+    /// the same channel
+    pub fn fidelity(&self, kraus: &[Array2<Complex64>]) -> Result<f64> {
+        let d = Complex64::new((self.choi.nrows() as f64).sqrt(), 0.0);
+        state_fidelity(&(&self.choi / d), &(choi_matrix(kraus)? / d))
+    }
+}
+
+impl TomographyBasis {
+    /// Qubit tomography: the `4ⁿ` product inputs of
+    /// [`StateEnsemble::tomography_inputs`], the `3ⁿ` settings measuring
+    /// each qubit in the X, Y or Z eigenbasis, and the Pauli strings
+    ///
+    /// Settings and operators are ordered as words over `XYZ` and `IXYZ`,
+    /// first qubit first; outcome `m` of a setting has qubit `q` in the `-1`
+    /// eigenstate when bit `n - 1 - q` of `m` is set.
+    pub fn pauli(n_qubits: usize) -> Result<Self> {
+        let inputs = StateEnsemble::tomography_inputs(n_qubits)?
+            .members
+            .into_iter()
+            .map(|member| match member.state {
+                EvolutionState::Ket(ket) => ket_to_rho(&ket),
+                EvolutionState::Rho(rho) => rho,
+            })
+            .collect();
+
+        let mut settings = Vec::new();
+        for word in words("XYZ", n_qubits) {
+            let eigenprojectors = word
+                .chars()
+                .map(|letter| {
+                    let p = letter.to_string().parse::<PauliString>()?.to_dense();
+                    let identity = Array2::<Complex64>::eye(2);
+                    let half = Complex64::new(0.5, 0.0);
+                    Ok([(&identity + &p) * half, (&identity - &p) * half])
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let mut effects = vec![Array2::eye(1)];
+            for pair in &eigenprojectors {
+                effects = effects
+                    .iter()
+                    .flat_map(|effect| {
+                        pair.iter()
+                            .map(move |projector| tensor_product(effect, projector))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+            }
+            settings.push(effects);
+        }
+
+        let operators = words("IXYZ", n_qubits)
+            .iter()
+            .map(|word| Ok(word.parse::<PauliString>()?.to_dense()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            inputs,
+            settings,
+            operators,
+        })
+    }
+
+    /// Dimension `d` the channel acts on
+    pub fn dim(&self) -> usize {
+        self.inputs.first().map_or(0, Array2::nrows)
+    }
+
+    /// Counts expected from `shots` runs of every setting on every input
+    /// for the channel with Kraus operators `kraus`
+    pub fn expected_counts(
+        &self,
+        kraus: &[Array2<Complex64>],
+        shots: f64,
+    ) -> Result<ProcessTomographyData> {
+        let choi = choi_matrix(kraus)?;
+        if choi.nrows() != self.dim() * self.dim() {
+            return Err(EngineError::dimension_mismatch(
+                format!("{}x{} Kraus operators", self.dim(), self.dim()),
+                format!("{}x{}", kraus[0].nrows(), kraus[0].ncols()),
+            ));
+        }
+        let mut counts = Vec::with_capacity(self.inputs.len());
+        for rho in &self.inputs {
+            let rho_t = rho.t().to_owned();
+            let mut per_input = Vec::with_capacity(self.settings.len());
+            for effects in &self.settings {
+                let expected = effects
+                    .iter()
+                    .map(|effect| Ok(shots * probability(&tensor_product(&rho_t, effect)?, &choi)))
+                    .collect::<Result<Vec<_>>>()?;
+                per_input.push(expected);
+            }
+            counts.push(per_input);
+        }
+        Ok(ProcessTomographyData { counts })
+    }
+
+    /// One `(ρᵀ ⊗ M, count)` per outcome, checked against the data's layout
+    fn observations(&self, data: &ProcessTomographyData) -> Result<Vec<(Array2<Complex64>, f64)>> {
+        let d = self.dim();
+        if d == 0 || self.inputs.iter().any(|rho| rho.dim() != (d, d)) {
+            return Err(EngineError::validation_error(
+                "Tomography inputs must be density matrices of one dimension",
+            ));
+        }
+        if self
+            .settings
+            .iter()
+            .flatten()
+            .any(|effect| effect.dim() != (d, d))
+        {
+            return Err(EngineError::dimension_mismatch(
+                format!("{}x{} effects", d, d),
+                "effects of another dimension",
+            ));
+        }
+        if data.counts.len() != self.inputs.len()
+            || data.counts.iter().any(|per_input| {
+                per_input.len() != self.settings.len()
+                    || per_input
+                        .iter()
+                        .zip(&self.settings)
+                        .any(|(counts, effects)| counts.len() != effects.len())
+            })
+        {
+            return Err(EngineError::validation_error(format!(
+                "Counts must be given per input ({}), setting ({}) and outcome",
+                self.inputs.len(),
+                self.settings.len()
+            )));
+        }
+        if data
+            .counts
+            .iter()
+            .flatten()
+            .flatten()
+            .any(|&n| !(n.is_finite() && n >= 0.0))
+        {
+            return Err(EngineError::validation_error(
+                "Counts must be finite and non-negative",
+            ));
+        }
+
+        let mut observations = Vec::new();
+        for (rho, per_input) in self.inputs.iter().zip(&data.counts) {
+            let rho_t = rho.t().to_owned();
+            for (effects, counts) in self.settings.iter().zip(per_input) {
+                for (effect, &n) in effects.iter().zip(counts) {
+                    observations.push((tensor_product(&rho_t, effect)?, n));
+                }
+            }
+        }
+        Ok(observations)
+    }
+}
+
+/// Reconstruct a channel by maximum likelihood with the default options
+pub fn process_tomography(
+    data: &ProcessTomographyData,
+    basis: &TomographyBasis,
+) -> Result<ProcessEstimate> {
+    process_tomography_with(data, basis, &TomographyOptions::default())
+}
+
+/// Reconstruct a channel from tomography counts
+pub fn process_tomography_with(
+    data: &ProcessTomographyData,
+    basis: &TomographyBasis,
+    options: &TomographyOptions,
+) -> Result<ProcessEstimate> {
+    let observations = basis.observations(data)?;
+    let d = basis.dim();
+    let (choi, iterations, converged) = match options.method {
+        TomographyMethod::LinearInversion => {
+            let unconstrained = linear_inversion(&observations, basis, d)?;
+            project_cptp(&unconstrained, d, options)?
+        }
+        TomographyMethod::MaximumLikelihood => maximum_likelihood(&observations, d, options)?,
+    };
+    let log_likelihood = observations
+        .iter()
+        .filter(|(_, n)| *n > 0.0)
+        .map(|(x, n)| n * probability(x, &choi).max(f64::MIN_POSITIVE).ln())
+        .sum::<f64>();
+    Ok(ProcessEstimate {
+        chi: chi_matrix(&choi, &basis.operators)?,
+        choi,
+        log_likelihood,
+        iterations,
+        converged,
+    })
+}
+
+/// Choi matrix `Σ_k |K_k⟩⟩⟨⟨K_k|` of the channel with Kraus operators
+/// `kraus`, where `|K⟩⟩ = Σ_i |i⟩ ⊗ K|i⟩`
+pub fn choi_matrix(kraus: &[Array2<Complex64>]) -> Result<Array2<Complex64>> {
+    let d = kraus.first().map_or(0, Array2::nrows);
+    if d == 0 || kraus.iter().any(|k| k.dim() != (d, d)) {
+        return Err(EngineError::validation_error(
+            "Kraus operators must be square matrices of one dimension",
+        ));
+    }
+    let mut choi = Array2::zeros((d * d, d * d));
+    for k in kraus {
+        let v = vectorize(k);
+        for r in 0..d * d {
+            for c in 0..d * d {
+                choi[[r, c]] += v[r] * v[c].conj();
+            }
+        }
+    }
+    Ok(choi)
+}
+
+/// χ matrix of the channel with Choi matrix `choi` in the basis
+/// `operators`, which must be `d²` operators with `Tr(P_m† P_n) = d δ_mn`
+pub fn chi_matrix(
+    choi: &Array2<Complex64>,
+    operators: &[Array2<Complex64>],
+) -> Result<Array2<Complex64>> {
+    let d = operators.first().map_or(0, Array2::nrows);
+    if operators.len() != d * d || choi.dim() != (d * d, d * d) {
+        return Err(EngineError::dimension_mismatch(
+            format!(
+                "{} operators and a {}x{} Choi matrix",
+                choi.nrows(),
+                choi.nrows(),
+                choi.ncols()
+            ),
+            format!("{} operators of dimension {}", operators.len(), d),
+        ));
+    }
+    // Columns |P_m⟩⟩, with B†B = d I for an orthogonal basis
+    let mut b = Array2::zeros((d * d, d * d));
+    for (m, p) in operators.iter().enumerate() {
+        if p.dim() != (d, d) {
+            return Err(EngineError::validation_error(
+                "Basis operators must all be d×d",
+            ));
+        }
+        b.column_mut(m).assign(&vectorize(p));
+    }
+    let gram = dagger(&b).dot(&b);
+    let expected = Array2::<Complex64>::eye(d * d) * Complex64::new(d as f64, 0.0);
+    if (&gram - &expected).iter().any(|z| z.norm() > 1e-9) {
+        return Err(EngineError::validation_error(
+            "Basis operators must satisfy Tr(P_m† P_n) = d δ_mn",
+        ));
+    }
+    Ok(dagger(&b).dot(choi).dot(&b) / Complex64::new((d * d) as f64, 0.0))
+}
+
+/// `|A⟩⟩ = Σ_i |i⟩ ⊗ A|i⟩`, whose entry `i d + j` is `A[j, i]`
+fn vectorize(a: &Array2<Complex64>) -> Array1<Complex64> {
+    let d = a.nrows();
+    Array1::from_shape_fn(d * d, |r| a[[r % d, r / d]])
+}
+
+/// `Tr(X J)`, real for Hermitian `X` and `J`
+fn probability(x: &Array2<Complex64>, choi: &Array2<Complex64>) -> f64 {
+    x.iter()
+        .zip(choi.t().iter())
+        .map(|(a, b)| a * b)
+        .sum::<Complex64>()
+        .re
+}
+
+/// Least-squares Choi matrix for the observed frequencies of each setting
+fn linear_inversion(
+    observations: &[(Array2<Complex64>, f64)],
+    basis: &TomographyBasis,
+    d: usize,
+) -> Result<Array2<Complex64>> {
+    let n = d * d;
+    // Frequencies within each (input, setting); settings never run are left out
+    let mut frequencies = Vec::with_capacity(observations.len());
+    let mut offset = 0;
+    for _ in &basis.inputs {
+        for effects in &basis.settings {
+            let block = &observations[offset..offset + effects.len()];
+            let total: f64 = block.iter().map(|(_, count)| count).sum();
+            if total > 0.0 {
+                frequencies.extend(block.iter().map(|(x, count)| (x, count / total)));
+            }
+            offset += effects.len();
+        }
+    }
+
+    // Normal equations A†A x = A†f, where row (X, f) of A has X[c, r] at
+    // unknown J[r, c]
+    let mut normal = Array2::<Complex64>::zeros((n * n, n * n));
+    let mut rhs = Array1::<Complex64>::zeros(n * n);
+    for (x, f) in frequencies {
+        let row: Vec<Complex64> = x.t().iter().copied().collect();
+        for (i, a) in row.iter().enumerate() {
+            if a.norm() == 0.0 {
+                continue;
+            }
+            rhs[i] += a.conj() * f;
+            for (j, b) in row.iter().enumerate() {
+                normal[[i, j]] += a.conj() * b;
+            }
+        }
+    }
+    let (values, vectors) = eigh_column_major(&normal)?;
+    let largest = values.iter().cloned().fold(0.0, f64::max);
+    if values.iter().any(|&v| v <= 1e-10 * largest) {
+        return Err(EngineError::validation_error(
+            "Inputs and measurements are not informationally complete",
+        ));
+    }
+    let projected = dagger(&vectors).dot(&rhs) / values.mapv(|v| Complex64::new(v, 0.0));
+    let solution = vectors.dot(&projected);
+    let choi = Array2::from_shape_vec((n, n), solution.to_vec()).expect("n² unknowns");
+    Ok((&choi + &dagger(&choi)) / Complex64::new(2.0, 0.0))
+}
+
+/// Nearest CPTP Choi matrix, by Dykstra's alternating projections
+fn project_cptp(
+    choi: &Array2<Complex64>,
+    d: usize,
+    options: &TomographyOptions,
+) -> Result<(Array2<Complex64>, usize, bool)> {
+    let mut x = choi.clone();
+    let mut p = Array2::zeros(choi.dim());
+    let mut q = Array2::zeros(choi.dim());
+    for iteration in 1..=options.max_iterations {
+        let y = project_tp(&(&x + &p), d);
+        p = &x + &p - &y;
+        let next = project_cp(&(&y + &q))?;
+        q = &y + &q - &next;
+        let step = frobenius(&(&next - &x));
+        x = next;
+        if step < options.tolerance
+            && frobenius(&(partial_trace_output(&x, d) - Array2::<Complex64>::eye(d)))
+                < options.tolerance.sqrt()
+        {
+            return Ok((x, iteration, true));
+        }
+    }
+    Ok((x, options.max_iterations, false))
+}
+
+/// `J - ((Tr_out J - I) ⊗ I) / d`, the nearest trace-preserving Choi matrix
+fn project_tp(choi: &Array2<Complex64>, d: usize) -> Array2<Complex64> {
+    let excess = partial_trace_output(choi, d) - Array2::<Complex64>::eye(d);
+    let mut projected = choi.clone();
+    for i in 0..d {
+        for j in 0..d {
+            for a in 0..d {
+                projected[[i * d + a, j * d + a]] -= excess[[i, j]] / d as f64;
+            }
+        }
+    }
+    projected
+}
+
+/// Nearest positive semidefinite matrix: negative eigenvalues set to zero
+fn project_cp(choi: &Array2<Complex64>) -> Result<Array2<Complex64>> {
+    let (values, vectors) = eigh_column_major(choi)?;
+    let clipped = values.mapv(|v| Complex64::new(v.max(0.0), 0.0));
+    Ok((&vectors * &clipped).dot(&dagger(&vectors)))
+}
+
+/// `J ← Λ K J K Λ` with `K = Σ (n / p) ρᵀ ⊗ M` and `Λ = (Tr_out KJK)^(-1/2) ⊗ I`
+fn maximum_likelihood(
+    observations: &[(Array2<Complex64>, f64)],
+    d: usize,
+    options: &TomographyOptions,
+) -> Result<(Array2<Complex64>, usize, bool)> {
+    let n = d * d;
+    let mut choi = Array2::<Complex64>::eye(n) / Complex64::new(d as f64, 0.0);
+    for iteration in 1..=options.max_iterations {
+        let mut k = Array2::<Complex64>::zeros((n, n));
+        for (x, count) in observations.iter().filter(|(_, count)| *count > 0.0) {
+            let p = probability(x, &choi).max(f64::MIN_POSITIVE);
+            k.scaled_add(Complex64::new(count / p, 0.0), x);
+        }
+        let kjk = k.dot(&choi).dot(&k);
+        let (values, vectors) = eigh_column_major(&partial_trace_output(&kjk, d))?;
+        if values.iter().any(|&v| v <= 0.0) {
+            return Err(EngineError::ExecutionError(
+                "Likelihood iteration became singular; some input was never measured".to_string(),
+            ));
+        }
+        let inverse_root =
+            (&vectors * &values.mapv(|v| Complex64::new(v.powf(-0.5), 0.0))).dot(&dagger(&vectors));
+        let lambda = tensor_product(&inverse_root, &Array2::eye(d))?;
+        let next = lambda.dot(&kjk).dot(&lambda);
+        let next = (&next + &dagger(&next)) / Complex64::new(2.0, 0.0);
+        let step = frobenius(&(&next - &choi));
+        choi = next;
+        if step < options.tolerance {
+            return Ok((choi, iteration, true));
+        }
+    }
+    Ok((choi, options.max_iterations, false))
+}
+
+/// `Tr_out J`, tracing out the second (output) factor
+fn partial_trace_output(choi: &Array2<Complex64>, d: usize) -> Array2<Complex64> {
+    Array2::from_shape_fn((d, d), |(i, j)| {
+        (0..d).map(|a| choi[[i * d + a, j * d + a]]).sum()
+    })
+}
+
+fn frobenius(a: &Array2<Complex64>) -> f64 {
+    a.iter().map(|z| z.norm_sqr()).sum::<f64>().sqrt()
+}
+
+/// All words of length `n` over `letters`, the first letter varying slowest
+fn words(letters: &str, n: usize) -> Vec<String> {
+    (0..n).fold(vec![String::new()], |prefixes, _| {
+        prefixes
+            .iter()
+            .flat_map(|prefix| letters.chars().map(move |c| format!("{}{}", prefix, c)))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use ndarray_linalg::{Eigh, UPLO};
+
+    fn c(re: f64, im: f64) -> Complex64 {
+        Complex64::new(re, im)
+    }
+
+    fn amplitude_damping(gamma: f64) -> Vec<Array2<Complex64>> {
+        vec![
+            Array2::from_shape_vec(
+                (2, 2),
+                vec![
+                    c(1.0, 0.0),
+                    c(0.0, 0.0),
+                    c(0.0, 0.0),
+                    c((1.0 - gamma).sqrt(), 0.0),
+                ],
+            )
+            .unwrap(),
+            Array2::from_shape_vec(
+                (2, 2),
+                vec![c(0.0, 0.0), c(gamma.sqrt(), 0.0), c(0.0, 0.0), c(0.0, 0.0)],
+            )
+            .unwrap(),
+        ]
+    }
+
+    fn hadamard() -> Vec<Array2<Complex64>> {
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        vec![
+            Array2::from_shape_vec((2, 2), vec![c(s, 0.0), c(s, 0.0), c(s, 0.0), c(-s, 0.0)])
+                .unwrap(),
+        ]
+    }
+
+    fn close(a: &Array2<Complex64>, b: &Array2<Complex64>, tol: f64) -> bool {
+        a.dim() == b.dim() && frobenius(&(a - b)) < tol
+    }
+
+    fn assert_cptp(choi: &Array2<Complex64>, d: usize) {
+        let (values, _) = choi.eigh(UPLO::Lower).unwrap();
+        assert!(values.iter().all(|&v| v > -1e-8), "not CP: {:?}", values);
+        assert!(close(&partial_trace_output(choi, d), &Array2::eye(d), 1e-8));
+    }
+
+    #[test]
+    fn test_pauli_basis() {
+        let basis = TomographyBasis::pauli(2).unwrap();
+        assert_eq!(
+            (
+                basis.inputs.len(),
+                basis.settings.len(),
+                basis.operators.len()
+            ),
+            (16, 9, 16)
+        );
+        assert_eq!(basis.dim(), 4);
+        for effects in &basis.settings {
+            let total = effects.iter().fold(Array2::zeros((4, 4)), |acc, e| acc + e);
+            assert!(close(&total, &Array2::eye(4), 1e-12));
+        }
+        // χ of a unitary U = Σ u_m P_m is u uᴴ: H = (X + Z)/√2
+        let chi = chi_matrix(
+            &choi_matrix(&hadamard()).unwrap(),
+            &TomographyBasis::pauli(1).unwrap().operators,
+        )
+        .unwrap();
+        for (m, n) in [(1, 1), (1, 3), (3, 1), (3, 3)] {
+            assert_relative_eq!(chi[[m, n]].re, 0.5, epsilon = 1e-12);
+        }
+        assert_relative_eq!(
+            chi[[0, 0]].norm() + chi[[2, 2]].norm(),
+            0.0,
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_linear_inversion_recovers_channel() {
+        let basis = TomographyBasis::pauli(1).unwrap();
+        let kraus = amplitude_damping(0.3);
+        let data = basis.expected_counts(&kraus, 1000.0).unwrap();
+        let options = TomographyOptions {
+            method: TomographyMethod::LinearInversion,
+            ..TomographyOptions::default()
+        };
+        let estimate = process_tomography_with(&data, &basis, &options).unwrap();
+        assert!(estimate.converged);
+        assert!(close(&estimate.choi, &choi_matrix(&kraus).unwrap(), 1e-6));
+        let chi = chi_matrix(&choi_matrix(&kraus).unwrap(), &basis.operators).unwrap();
+        assert!(close(&estimate.chi, &chi, 1e-6));
+        assert_relative_eq!(estimate.fidelity(&kraus).unwrap(), 1.0, epsilon = 1e-6);
+        assert!(estimate.fidelity(&hadamard()).unwrap() < 0.9);
+    }
+
+    #[test]
+    fn test_estimates_are_cptp_for_noisy_counts() {
+        let basis = TomographyBasis::pauli(1).unwrap();
+        let kraus = hadamard();
+        // Perturbed and rounded counts of 20 shots, which no channel fits
+        // exactly
+        let mut data = basis.expected_counts(&kraus, 20.0).unwrap();
+        for (k, count) in data.counts.iter_mut().flatten().flatten().enumerate() {
+            *count = (*count + if k % 3 == 0 { 1.4 } else { -0.3 })
+                .round()
+                .max(0.0);
+        }
+
+        let ml = process_tomography(&data, &basis).unwrap();
+        assert!(ml.converged);
+        assert_cptp(&ml.choi, 2);
+        assert!(ml.fidelity(&kraus).unwrap() > 0.8);
+        assert_relative_eq!(ml.chi.diag().sum().re, 1.0, epsilon = 1e-8);
+
+        let options = TomographyOptions {
+            method: TomographyMethod::LinearInversion,
+            ..TomographyOptions::default()
+        };
+        let li = process_tomography_with(&data, &basis, &options).unwrap();
+        assert_cptp(&li.choi, 2);
+        // Maximum likelihood is the most likely CPTP map
+        assert!(ml.log_likelihood >= li.log_likelihood - 1e-2);
+
+        data.counts.pop();
+        assert!(process_tomography(&data, &basis).is_err());
+    }
+}
@@ -1,22 +1,158 @@
 //! Streaming data sources for live measurements
 //!
-//! Supports file watching (CSV), WebSocket streaming, and rolling fits.
+//! Supports file watching (CSV), WebSocket streaming, ZeroMQ and MQTT
+//! subscriptions (see [`lab_sources`](crate::lab_sources)), and rolling fits.
+//! Results can also be pushed out: [`StreamingManager::serve_results`]
+//! broadcasts every published [`ResultEvent`] to WebSocket clients as JSON.
 
+use crate::aggregation::Aggregator;
+use crate::changepoint::DriftDetector;
 use crate::error::{EngineError, Result};
-use crate::stats::{MeasurementData, FitResult};
+use crate::executor::{EvolutionCheckpoint, Executor, ExperimentResult, TrajectoryChunk};
+use crate::ir::IrProgram;
+use crate::kernels_cpu;
+#[cfg(feature = "mqtt")]
+use crate::lab_sources::MqttSource;
+#[cfg(feature = "zeromq")]
+use crate::lab_sources::ZmqSource;
+use crate::stats::FitResult;
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{Inverse, Solve};
+use num_complex::Complex64;
 use serde::{Serialize, Deserialize};
-use std::collections::VecDeque;
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, VecDeque};
+use std::net::SocketAddr;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use notify::{Watcher, RecursiveMode, Event};
 
 /// Streaming data source manager
 pub struct StreamingManager {
     sources: Arc<Mutex<Vec<Box<dyn DataSource + Send>>>>,
-    receiver: mpsc::UnboundedReceiver<StreamEvent>,
-    sender: mpsc::UnboundedSender<StreamEvent>,
+    sender: EventSender,
+    results: broadcast::Sender<ResultEvent>,
+    aggregator: Option<Aggregator>,
+    /// Derived events waiting to be returned by `next_event`
+    aggregated: VecDeque<StreamEvent>,
+}
+
+/// Events a result server sends, newest last; slow clients that fall more
+/// than this far behind skip ahead
+const RESULT_BUFFER: usize = 1024;
+
+/// Live simulation output, sent to WebSocket clients as JSON objects
+/// tagged by `"type"`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResultEvent {
+    /// Expectation values of the published observables at one time step
+    TimeStep {
+        experiment: String,
+        step: usize,
+        time: f64,
+        expectations: BTreeMap<String, f64>,
+    },
+    /// All time steps of `experiment` have been sent
+    Finished { experiment: String },
+    FitUpdated {
+        params: Vec<f64>,
+        uncertainties: Vec<f64>,
+        log_likelihood: f64,
+    },
+}
+
+/// Cheap, cloneable handle for publishing results from any thread
+#[derive(Clone)]
+pub struct ResultPublisher {
+    sender: broadcast::Sender<ResultEvent>,
+}
+
+impl ResultPublisher {
+    /// Send an event to every connected client; a no-op when none is
+    pub fn publish(&self, event: ResultEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Publish `⟨A⟩(t)` for each observable at every time step of `result`,
+    /// then `Finished`
+    pub fn publish_experiment(
+        &self,
+        result: &ExperimentResult,
+        observables: &[(String, Array2<Complex64>)],
+    ) -> Result<()> {
+        self.publish_steps(&result.name, 0, &result.times, &result.states, observables)?;
+        self.publish(ResultEvent::Finished {
+            experiment: result.name.clone(),
+        });
+        Ok(())
+    }
+
+    /// Publish `⟨A⟩(t)` for each observable at the time steps of one chunk
+    /// of a chunked execution
+    pub fn publish_chunk(
+        &self,
+        chunk: &TrajectoryChunk,
+        observables: &[(String, Array2<Complex64>)],
+    ) -> Result<()> {
+        self.publish_steps(&chunk.experiment, chunk.start_index, &chunk.times, &chunk.states, observables)
+    }
+
+    fn publish_steps(
+        &self,
+        experiment: &str,
+        first_step: usize,
+        times: &[f64],
+        states: &[Array2<Complex64>],
+        observables: &[(String, Array2<Complex64>)],
+    ) -> Result<()> {
+        for (k, (time, rho)) in times.iter().zip(states).enumerate() {
+            let mut expectations = BTreeMap::new();
+            for (name, observable) in observables {
+                expectations.insert(name.clone(), kernels_cpu::expectation(observable, rho)?.re);
+            }
+            self.publish(ResultEvent::TimeStep {
+                experiment: experiment.to_string(),
+                step: first_step + k,
+                time: *time,
+                expectations,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn publish_fit(&self, fit: &FitResult) {
+        self.publish(ResultEvent::FitUpdated {
+            params: fit.best_params.clone(),
+            uncertainties: fit.uncertainties.clone(),
+            log_likelihood: fit.log_likelihood,
+        });
+    }
+}
+
+/// A running result server; stops when shut down or dropped
+pub struct ResultServer {
+    local_addr: SocketAddr,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl ResultServer {
+    /// Address actually bound, e.g. the port chosen for `127.0.0.1:0`
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn shutdown(self) {
+        self.handle.abort();
+    }
+}
+
+impl Drop for ResultServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +174,18 @@ pub enum StreamEvent {
         uncertainties: Vec<f64>,
         log_likelihood: f64,
     },
+    /// `count` events were shed since the previous notification because
+    /// the buffer was full, see [`OverflowPolicy`]
+    Dropped {
+        count: usize,
+    },
+    /// A fitted parameter left its reference level, by `magnitude` in the
+    /// parameter's units; see [`DriftDetector`](crate::changepoint::DriftDetector)
+    DriftDetected {
+        param: String,
+        magnitude: f64,
+        time: chrono::DateTime<chrono::Utc>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +196,140 @@ pub struct DataPoint {
     pub metadata: serde_json::Value,
 }
 
+/// Which events a full buffer sheds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest buffered event to make room, so a consumer that
+    /// catches up sees the freshest data
+    #[default]
+    DropOldest,
+    /// Drop the incoming event, so a consumer sees everything up to the
+    /// moment the buffer filled
+    DropNewest,
+}
+
+/// Bound on the events waiting between the sources and
+/// [`StreamingManager::next_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferConfig {
+    /// Most events held; at least 1
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// Occupancy of a [`StreamingManager`]'s event buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BufferStats {
+    pub capacity: usize,
+    /// Events currently waiting
+    pub len: usize,
+    /// Most events ever waiting at once
+    pub high_water_mark: usize,
+    /// Events sent by the sources, shed ones included
+    pub received: u64,
+    /// Events shed because the buffer was full
+    pub dropped: u64,
+}
+
+struct EventQueue {
+    events: VecDeque<StreamEvent>,
+    overflow: OverflowPolicy,
+    stats: BufferStats,
+    /// Drops not yet reported by a [`StreamEvent::Dropped`]
+    unreported: usize,
+}
+
+/// Handle sources send their events through, into the bounded buffer of
+/// the [`StreamingManager`] it came from
+///
+/// Sending never blocks: when the buffer is full, the configured
+/// [`OverflowPolicy`] sheds an event, and the consumer's next event is a
+/// [`StreamEvent::Dropped`] counting what was lost.
+#[derive(Clone)]
+pub struct EventSender {
+    queue: Arc<Mutex<EventQueue>>,
+    ready: Arc<tokio::sync::Notify>,
+}
+
+impl EventSender {
+    pub fn new(config: BufferConfig) -> Self {
+        let capacity = config.capacity.max(1);
+        Self {
+            queue: Arc::new(Mutex::new(EventQueue {
+                events: VecDeque::new(),
+                overflow: config.overflow,
+                stats: BufferStats {
+                    capacity,
+                    ..BufferStats::default()
+                },
+                unreported: 0,
+            })),
+            ready: Arc::new(tokio::sync::Notify::new()),
+        }
+    }
+
+    /// Buffer `event`; false if an event had to be shed for it, or it was
+    /// shed itself
+    pub fn send(&self, event: StreamEvent) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        queue.stats.received += 1;
+        let full = queue.events.len() >= queue.stats.capacity;
+        if full {
+            queue.stats.dropped += 1;
+            queue.unreported += 1;
+            match queue.overflow {
+                OverflowPolicy::DropOldest => {
+                    queue.events.pop_front();
+                }
+                OverflowPolicy::DropNewest => {
+                    drop(queue);
+                    self.ready.notify_one();
+                    return false;
+                }
+            }
+        }
+        queue.events.push_back(event);
+        queue.stats.len = queue.events.len();
+        queue.stats.high_water_mark = queue.stats.high_water_mark.max(queue.stats.len);
+        drop(queue);
+        self.ready.notify_one();
+        !full
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        self.queue.lock().unwrap().stats
+    }
+
+    /// Next event, a [`StreamEvent::Dropped`] first if events were shed
+    /// since the last one
+    async fn recv(&self) -> StreamEvent {
+        loop {
+            {
+                let mut queue = self.queue.lock().unwrap();
+                if queue.unreported > 0 {
+                    let count = std::mem::take(&mut queue.unreported);
+                    return StreamEvent::Dropped { count };
+                }
+                if let Some(event) = queue.events.pop_front() {
+                    queue.stats.len = queue.events.len();
+                    return event;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+}
+
 pub trait DataSource {
     fn id(&self) -> &str;
     fn start(&mut self) -> Result<()>;
@@ -55,12 +337,31 @@ pub trait DataSource {
     fn is_active(&self) -> bool;
 }
 
+/// Data point of a line `observable,value,uncertainty`
+pub(crate) fn parse_csv_line(line: &str) -> Result<DataPoint> {
+    let parts: Vec<&str> = line.split(',').collect();
+    if parts.len() < 3 {
+        return Err(EngineError::validation_error(
+            "CSV line must have at least 3 columns: observable,value,uncertainty",
+        ));
+    }
+
+    Ok(DataPoint {
+        observable: parts[0].trim().to_string(),
+        value: parts[1].trim().parse()
+            .map_err(|e| EngineError::validation_error(format!("Invalid value: {}", e)))?,
+        uncertainty: parts[2].trim().parse()
+            .map_err(|e| EngineError::validation_error(format!("Invalid uncertainty: {}", e)))?,
+        metadata: serde_json::json!({}),
+    })
+}
+
 /// CSV file watcher
 pub struct CsvFileWatcher {
     id: String,
     path: PathBuf,
     watcher: Option<Box<dyn Watcher + Send>>,
-    sender: mpsc::UnboundedSender<StreamEvent>,
+    sender: EventSender,
     last_position: Arc<Mutex<u64>>,
 }
 
@@ -68,7 +369,7 @@ impl CsvFileWatcher {
     pub fn new(
         id: String,
         path: PathBuf,
-        sender: mpsc::UnboundedSender<StreamEvent>,
+        sender: EventSender,
     ) -> Self {
         Self {
             id,
@@ -80,21 +381,7 @@ impl CsvFileWatcher {
     }
 
     fn parse_csv_line(&self, line: &str) -> Result<DataPoint> {
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() < 3 {
-            return Err(EngineError::Parse(
-                "CSV line must have at least 3 columns: observable,value,uncertainty".to_string()
-            ));
-        }
-
-        Ok(DataPoint {
-            observable: parts[0].trim().to_string(),
-            value: parts[1].trim().parse()
-                .map_err(|e| EngineError::Parse(format!("Invalid value: {}", e)))?,
-            uncertainty: parts[2].trim().parse()
-                .map_err(|e| EngineError::Parse(format!("Invalid uncertainty: {}", e)))?,
-            metadata: serde_json::json!({}),
-        })
+        parse_csv_line(line)
     }
 
     fn read_new_lines(&self) -> Result<Vec<DataPoint>> {
@@ -156,14 +443,14 @@ impl DataSource for CsvFileWatcher {
                     }
                 }
             }
-        }).map_err(|e| EngineError::Io(
-            format!("Failed to create watcher: {}", e).into()
-        ))?;
+        }).map_err(|e| EngineError::IoError(std::io::Error::other(
+            format!("Failed to create watcher: {}", e)
+        )))?;
 
         watcher.watch(&path, RecursiveMode::NonRecursive)
-            .map_err(|e| EngineError::Io(
-                format!("Failed to watch file: {}", e).into()
-            ))?;
+            .map_err(|e| EngineError::IoError(std::io::Error::other(
+                format!("Failed to watch file: {}", e)
+            )))?;
 
         self.watcher = Some(Box::new(watcher));
 
@@ -204,7 +491,7 @@ impl Clone for CsvFileWatcher {
 pub struct WebSocketSource {
     id: String,
     url: String,
-    sender: mpsc::UnboundedSender<StreamEvent>,
+    sender: EventSender,
     active: Arc<Mutex<bool>>,
     handle: Option<tokio::task::JoinHandle<()>>,
 }
@@ -213,7 +500,7 @@ impl WebSocketSource {
     pub fn new(
         id: String,
         url: String,
-        sender: mpsc::UnboundedSender<StreamEvent>,
+        sender: EventSender,
     ) -> Self {
         Self {
             id,
@@ -311,27 +598,90 @@ impl DataSource for WebSocketSource {
     }
 }
 
+/// Predicted value of a data point for given parameters, e.g. a Rabi curve
+/// evaluated at the time stored in the point's metadata
+pub type ObservationModel = Arc<dyn Fn(&[f64], &DataPoint) -> f64 + Send + Sync>;
+
+/// Most Gauss-Newton iterations in a full refit
+const MAX_REFIT_ITERATIONS: usize = 50;
+
 /// Rolling fit engine - updates parameter estimates as data streams in
+///
+/// With an observation model, every data point updates the estimate at
+/// once through an extended Kalman filter over the parameters: the model is
+/// linearised at the current estimate and the Gaussian posterior updated in
+/// closed form, with the parameters allowed to drift by `process_noise`
+/// between points. [`refit`](Self::refit) re-estimates from the whole
+/// window by Gauss-Newton, removing the filter's linearisation error, and
+/// restarts the filter from the refit's covariance.
 pub struct RollingFitEngine {
     window_size: usize,
     data_buffer: VecDeque<DataPoint>,
     current_params: Vec<f64>,
     last_fit_time: Option<Instant>,
     fit_interval: Duration,
+    model: Option<ObservationModel>,
+    /// Posterior covariance of the parameters
+    covariance: Array2<f64>,
+    /// Variance added to each parameter before every update
+    process_noise: f64,
+    drift_detector: Option<DriftDetector>,
+    /// Alarms raised since the last `take_drift_events`
+    drift_events: Vec<StreamEvent>,
 }
 
 impl RollingFitEngine {
     pub fn new(window_size: usize, initial_params: Vec<f64>) -> Self {
+        let n = initial_params.len();
         Self {
             window_size,
             data_buffer: VecDeque::new(),
             current_params: initial_params,
             last_fit_time: None,
             fit_interval: Duration::from_secs(1),
+            model: None,
+            covariance: Array2::eye(n),
+            process_noise: 0.0,
+            drift_detector: None,
+            drift_events: Vec::new(),
+        }
+    }
+
+    pub fn with_model(mut self, model: ObservationModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Prior standard deviation of each parameter (default 1)
+    pub fn with_prior_std(mut self, std: &[f64]) -> Self {
+        for (i, s) in std.iter().enumerate().take(self.current_params.len()) {
+            self.covariance[[i, i]] = s * s;
         }
+        self
+    }
+
+    /// Let the parameters drift, as a random walk with this variance per
+    /// data point, so the filter tracks slowly changing values
+    pub fn with_process_noise(mut self, variance: f64) -> Self {
+        self.process_noise = variance.max(0.0);
+        self
+    }
+
+    /// Watch the estimate of every refit for drifts; collect the alarms with
+    /// [`take_drift_events`](Self::take_drift_events)
+    pub fn with_drift_detector(mut self, detector: DriftDetector) -> Self {
+        self.drift_detector = Some(detector);
+        self
+    }
+
+    /// [`StreamEvent::DriftDetected`] alarms raised by refits since the
+    /// last call, e.g. to send on a [`StreamingManager::event_sender`]
+    pub fn take_drift_events(&mut self) -> Vec<StreamEvent> {
+        std::mem::take(&mut self.drift_events)
     }
 
     pub fn add_data(&mut self, point: DataPoint) {
+        self.filter_update(&point);
         self.data_buffer.push_back(point);
         
         // Maintain window size
@@ -348,73 +698,219 @@ impl RollingFitEngine {
         }
     }
 
+    /// Gauss-Newton least-squares fit of the model to the buffered window,
+    /// starting from the current estimate
     pub fn refit(&mut self) -> Result<FitResult> {
+        let model = self.model.clone().ok_or_else(|| {
+            EngineError::validation_error("Refitting needs an observation model; see with_model")
+        })?;
         if self.data_buffer.is_empty() {
-            return Err(EngineError::InvalidState(
-                "No data available for fitting".to_string()
-            ));
+            return Err(EngineError::validation_error("No data available for fitting"));
         }
 
-        // Convert buffer to MeasurementData
-        let data = self.buffer_to_measurement_data();
-        
-        // TODO: Implement incremental MLE update
-        // For now, use gradient descent step
-        let step_result = self.gradient_descent_step(&data)?;
-        
-        self.current_params = step_result.best_params.clone();
+        let mut params = self.current_params.clone();
+        let mut chi2 = self.chi_square(&*model, &params);
+        let mut converged = false;
+        let mut iterations = 0;
+        while iterations < MAX_REFIT_ITERATIONS && !converged {
+            iterations += 1;
+            let (fisher, gradient) = self.normal_equations(&*model, &params);
+            let step = fisher.solve(&gradient).map_err(|e| {
+                EngineError::validation_error(format!("Window does not determine the parameters: {}", e))
+            })?;
+
+            // Halve the step until it improves the fit
+            let mut scale = 1.0;
+            loop {
+                let trial: Vec<f64> = params.iter().zip(&step).map(|(p, d)| p + scale * d).collect();
+                let trial_chi2 = self.chi_square(&*model, &trial);
+                if trial_chi2 <= chi2 {
+                    converged = chi2 - trial_chi2 <= 1e-12 * (1.0 + chi2);
+                    (params, chi2) = (trial, trial_chi2);
+                    break;
+                }
+                scale /= 2.0;
+                if scale < 1e-6 {
+                    converged = true;
+                    break;
+                }
+            }
+        }
+
+        let (fisher, _) = self.normal_equations(&*model, &params);
+        let covariance = fisher.inv().map_err(|e| {
+            EngineError::validation_error(format!("Fisher information is singular: {}", e))
+        })?;
+        let normalisation: f64 = self
+            .data_buffer
+            .iter()
+            .map(|p| (sigma(p) * (2.0 * std::f64::consts::PI).sqrt()).ln())
+            .sum();
+
+        self.current_params = params.clone();
+        self.covariance = covariance.clone();
         self.last_fit_time = Some(Instant::now());
-        
-        Ok(step_result)
+
+        let uncertainties: Vec<f64> = covariance.diag().iter().map(|v| v.max(0.0).sqrt()).collect();
+        if let Some(detector) = self.drift_detector.as_mut() {
+            let alarms = detector.update(chrono::Utc::now(), &params, &uncertainties)?;
+            self.drift_events.extend(alarms);
+        }
+
+        Ok(FitResult {
+            best_params: params,
+            uncertainties,
+            log_likelihood: -0.5 * chi2 - normalisation,
+            fisher_info: fisher.outer_iter().map(|row| row.to_vec()).collect(),
+            converged,
+            iterations,
+            truncated: false,
+        })
     }
 
-    fn buffer_to_measurement_data(&self) -> MeasurementData {
-        // Group by observable
-        let mut observables = std::collections::HashMap::new();
-        
-        for point in &self.data_buffer {
-            let entry = observables.entry(point.observable.clone())
-                .or_insert_with(|| (Vec::new(), Vec::new()));
-            entry.0.push(point.value);
-            entry.1.push(point.uncertainty);
+    /// Extended Kalman filter step for one observation
+    fn filter_update(&mut self, point: &DataPoint) {
+        let Some(model) = self.model.clone() else {
+            return;
+        };
+        let n = self.current_params.len();
+
+        // Predict: the parameters follow a random walk
+        for i in 0..n {
+            self.covariance[[i, i]] += self.process_noise;
         }
 
-        MeasurementData {
-            observables,
-            num_shots: self.data_buffer.len(),
-            metadata: serde_json::json!({}),
+        // Update, with the model linearised at the current estimate
+        let h = jacobian_row(&*model, &self.current_params, point);
+        let predicted = model(&self.current_params, point);
+        let r = sigma(point).powi(2);
+        let ph = self.covariance.dot(&h);
+        let s = h.dot(&ph) + r;
+        if !(s.is_finite() && predicted.is_finite()) {
+            return;
+        }
+        let gain = ph / s;
+        let innovation = point.value - predicted;
+        for (p, k) in self.current_params.iter_mut().zip(&gain) {
+            *p += k * innovation;
         }
+
+        // Joseph form keeps the covariance symmetric positive definite
+        let a = Array2::eye(n) - outer(&gain, &h);
+        self.covariance = a.dot(&self.covariance).dot(&a.t()) + outer(&gain, &gain) * r;
     }
 
-    fn gradient_descent_step(&self, _data: &MeasurementData) -> Result<FitResult> {
-        // Stub implementation - would compute gradient and update params
-        Ok(FitResult {
-            best_params: self.current_params.clone(),
-            uncertainties: vec![0.01; self.current_params.len()],
-            log_likelihood: -100.0,
-            fisher_info: vec![vec![1.0; self.current_params.len()]; self.current_params.len()],
-            converged: false,
-            iterations: 1,
-        })
+    /// `JᵀWJ` and `JᵀW(y - f)` over the window
+    fn normal_equations(
+        &self,
+        model: &(dyn Fn(&[f64], &DataPoint) -> f64 + Send + Sync),
+        params: &[f64],
+    ) -> (Array2<f64>, Array1<f64>) {
+        let n = params.len();
+        let mut fisher = Array2::zeros((n, n));
+        let mut gradient = Array1::zeros(n);
+        for point in &self.data_buffer {
+            let j = jacobian_row(model, params, point);
+            let w = sigma(point).powi(-2);
+            fisher = fisher + outer(&j, &j) * w;
+            gradient = gradient + &j * (w * (point.value - model(params, point)));
+        }
+        (fisher, gradient)
+    }
+
+    fn chi_square(&self, model: &(dyn Fn(&[f64], &DataPoint) -> f64 + Send + Sync), params: &[f64]) -> f64 {
+        self.data_buffer
+            .iter()
+            .map(|p| ((p.value - model(params, p)) / sigma(p)).powi(2))
+            .sum()
     }
 
     pub fn current_estimate(&self) -> (&[f64], Option<Duration>) {
         let age = self.last_fit_time.map(|t| t.elapsed());
         (&self.current_params, age)
     }
+
+    /// Standard deviations of the current estimate
+    pub fn current_uncertainties(&self) -> Vec<f64> {
+        self.covariance.diag().iter().map(|v| v.max(0.0).sqrt()).collect()
+    }
+}
+
+/// Measurement error, floored so exact data cannot divide by zero
+fn sigma(point: &DataPoint) -> f64 {
+    point.uncertainty.abs().max(1e-12)
+}
+
+/// Gradient of the model prediction for `point` by central differences
+fn jacobian_row(
+    model: &(dyn Fn(&[f64], &DataPoint) -> f64 + Send + Sync),
+    params: &[f64],
+    point: &DataPoint,
+) -> Array1<f64> {
+    let mut shifted = params.to_vec();
+    Array1::from_shape_fn(params.len(), |i| {
+        let h = 1e-6 * params[i].abs().max(1.0);
+        shifted[i] = params[i] + h;
+        let up = model(&shifted, point);
+        shifted[i] = params[i] - h;
+        let down = model(&shifted, point);
+        shifted[i] = params[i];
+        (up - down) / (2.0 * h)
+    })
+}
+
+fn outer(a: &Array1<f64>, b: &Array1<f64>) -> Array2<f64> {
+    Array2::from_shape_fn((a.len(), b.len()), |(i, j)| a[i] * b[j])
 }
 
 impl StreamingManager {
     pub fn new() -> Self {
-        let (sender, receiver) = mpsc::unbounded_channel();
-        
+        Self::with_buffer(BufferConfig::default())
+    }
+
+    /// Manager whose sources' events wait in a buffer bounded by `config`
+    pub fn with_buffer(config: BufferConfig) -> Self {
+        let (results, _) = broadcast::channel(RESULT_BUFFER);
+
         Self {
             sources: Arc::new(Mutex::new(Vec::new())),
-            receiver,
-            sender,
+            sender: EventSender::new(config),
+            results,
+            aggregator: None,
+            aggregated: VecDeque::new(),
+        }
+    }
+
+    /// Occupancy of the event buffer and how many events it has shed
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.sender.stats()
+    }
+
+    pub fn result_publisher(&self) -> ResultPublisher {
+        ResultPublisher {
+            sender: self.results.clone(),
         }
     }
 
+    /// Listen for WebSocket clients on `addr` and send each of them every
+    /// [`ResultEvent`] published after it connected, as a JSON text message
+    pub async fn serve_results(&self, addr: impl tokio::net::ToSocketAddrs) -> Result<ResultServer> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let results = self.results.clone();
+
+        let handle = tokio::spawn(async move {
+            let mut clients = tokio::task::JoinSet::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                // Subscribe before the handshake so nothing published once
+                // the client is connected is missed
+                clients.spawn(send_results(stream, results.subscribe()));
+            }
+        });
+
+        Ok(ResultServer { local_addr, handle })
+    }
+
     pub fn add_csv_source(&self, id: String, path: PathBuf) -> Result<()> {
         let source = CsvFileWatcher::new(id, path, self.sender.clone());
         self.sources.lock().unwrap().push(Box::new(source));
@@ -427,6 +923,34 @@ impl StreamingManager {
         Ok(())
     }
 
+    /// Subscribe to `topics` of the ZeroMQ PUB socket at `endpoint`,
+    /// decoding JSON payloads
+    #[cfg(feature = "zeromq")]
+    pub fn add_zeromq_source(&self, id: String, endpoint: String, topics: Vec<String>) -> Result<()> {
+        let source = ZmqSource::new(id, endpoint, topics, self.sender.clone());
+        self.add_source(Box::new(source))
+    }
+
+    /// Subscribe to `topics` on the MQTT broker at `host:port`, decoding
+    /// JSON payloads
+    #[cfg(feature = "mqtt")]
+    pub fn add_mqtt_source(&self, id: String, host: String, port: u16, topics: Vec<String>) -> Result<()> {
+        let source = MqttSource::new(id, host, port, topics, self.sender.clone());
+        self.add_source(Box::new(source))
+    }
+
+    /// Add a source built around [`event_sender`](Self::event_sender), such
+    /// as a broker source with its own decoder or reconnect policy
+    pub fn add_source(&self, source: Box<dyn DataSource + Send>) -> Result<()> {
+        self.sources.lock().unwrap().push(source);
+        Ok(())
+    }
+
+    /// Channel sources report their events on
+    pub fn event_sender(&self) -> EventSender {
+        self.sender.clone()
+    }
+
     pub fn start_source(&self, id: &str) -> Result<()> {
         let mut sources = self.sources.lock().unwrap();
         
@@ -447,8 +971,42 @@ impl StreamingManager {
         }
     }
 
+    /// Reduce the data points of every source by `aggregator` before
+    /// [`next_event`](Self::next_event) returns them; the unfinished time
+    /// buckets of a source are returned when it disconnects
+    pub fn set_aggregator(&mut self, aggregator: Aggregator) {
+        self.aggregator = Some(aggregator);
+    }
+
+    /// Wait for the next source event; never `None`, as the manager keeps
+    /// the buffer open
     pub async fn next_event(&mut self) -> Option<StreamEvent> {
-        self.receiver.recv().await
+        loop {
+            if let Some(event) = self.aggregated.pop_front() {
+                return Some(event);
+            }
+            let event = self.sender.recv().await;
+            let Some(aggregator) = self.aggregator.as_mut() else {
+                return Some(event);
+            };
+            let (source_id, derived, disconnected) = match event {
+                StreamEvent::NewData { source_id, timestamp, data } => {
+                    let derived = aggregator.push(&source_id, timestamp, data);
+                    (source_id, derived, None)
+                }
+                StreamEvent::SourceDisconnected { source_id, reason } => {
+                    let flushed = aggregator.flush(&source_id);
+                    (source_id, flushed, Some(reason))
+                }
+                other => return Some(other),
+            };
+            self.aggregated.extend(derived.into_iter().map(|(timestamp, data)| {
+                StreamEvent::NewData { source_id: source_id.clone(), timestamp, data }
+            }));
+            if let Some(reason) = disconnected {
+                self.aggregated.push_back(StreamEvent::SourceDisconnected { source_id, reason });
+            }
+        }
     }
 
     pub fn list_sources(&self) -> Vec<(String, bool)> {
@@ -459,6 +1017,58 @@ impl StreamingManager {
     }
 }
 
+/// Run [`Executor::execute_chunked`] on a blocking thread, receiving the
+/// chunks as they are computed; at most `buffer` chunks wait unconsumed
+///
+/// Dropping the receiver cancels the run before its next chunk. The join
+/// handle yields the checkpoint the run stopped at, or `None` if it
+/// finished.
+pub fn stream_chunks(
+    mut executor: Executor,
+    ir: IrProgram,
+    chunk_steps: usize,
+    resume: Option<EvolutionCheckpoint>,
+    buffer: usize,
+) -> (
+    mpsc::Receiver<TrajectoryChunk>,
+    tokio::task::JoinHandle<Result<Option<EvolutionCheckpoint>>>,
+) {
+    let (sender, receiver) = mpsc::channel(buffer.max(1));
+    let handle = tokio::task::spawn_blocking(move || {
+        executor.execute_chunked(&ir, chunk_steps, resume.as_ref(), |chunk| {
+            match sender.blocking_send(chunk) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(_) => ControlFlow::Break(()),
+            }
+        })
+    });
+    (receiver, handle)
+}
+
+/// Forward events to one WebSocket client until it disconnects
+async fn send_results(stream: tokio::net::TcpStream, mut events: broadcast::Receiver<ResultEvent>) {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let Ok(json) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if ws.send(Message::Text(json)).await.is_err() {
+            break;
+        }
+    }
+    let _ = ws.close(None).await;
+}
+
 impl Default for StreamingManager {
     fn default() -> Self {
         Self::new()
@@ -486,9 +1096,228 @@ mod tests {
         assert!(engine.should_refit());
     }
 
+    #[tokio::test]
+    async fn test_serve_results_broadcasts_time_steps() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let manager = StreamingManager::new();
+        let server = manager.serve_results("127.0.0.1:0").await.unwrap();
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", server.local_addr()))
+            .await
+            .unwrap();
+
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let sz = Array2::from_diag(&ndarray::arr1(&[c(1.0), c(-1.0)]));
+        let excited = Array2::from_diag(&ndarray::arr1(&[c(0.0), c(1.0)]));
+        let result = ExperimentResult {
+            name: "decay".to_string(),
+            times: vec![0.0, 1.0],
+            state_type: crate::executor::StateType::DensityMatrix,
+            measurements: vec![],
+            states: vec![excited.clone(), excited],
+            diagnostics: None,
+            ehrenfest: vec![],
+            observables: vec![],
+        };
+        manager
+            .result_publisher()
+            .publish_experiment(&result, &[("sz".to_string(), sz)])
+            .unwrap();
+
+        let mut events = Vec::new();
+        while events.len() < 3 {
+            if let Some(Ok(Message::Text(text))) = client.next().await {
+                events.push(serde_json::from_str::<ResultEvent>(&text).unwrap());
+            }
+        }
+        assert_eq!(
+            events[1],
+            ResultEvent::TimeStep {
+                experiment: "decay".to_string(),
+                step: 1,
+                time: 1.0,
+                expectations: BTreeMap::from([("sz".to_string(), -1.0)]),
+            }
+        );
+        assert_eq!(
+            events[2],
+            ResultEvent::Finished {
+                experiment: "decay".to_string()
+            }
+        );
+        server.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_dropping_chunk_stream_cancels_run() {
+        use crate::executor::BackendConfig;
+        use crate::ir::*;
+
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let mut ir = IrProgram::new();
+        ir.add_node(IrNode::LoadMatrix {
+            id: 0,
+            name: "H".to_string(),
+            data: vec![c(0.0), c(0.5), c(0.5), c(0.0)],
+            shape: (2, 2),
+        });
+        ir.add_node(IrNode::LoadVector {
+            id: 1,
+            name: "init_ket".to_string(),
+            data: vec![c(1.0), c(0.0)],
+        });
+        ir.experiments.push(IrExperiment {
+            name: "rabi".to_string(),
+            initial_state: 1,
+            evolution: Some(IrEvolution {
+                method: EvolutionMethod::Schrodinger { hamiltonian: 0 },
+                times: (0..1_000).map(|i| i as f64 * 0.01).collect(),
+            }),
+            measurements: Vec::new(),
+            initial_from: None,
+            channels: Vec::new(),
+            observables: Vec::new(),
+        });
+
+        let executor = Executor::new(BackendConfig::default());
+        let (mut chunks, handle) = stream_chunks(executor, ir, 10, None, 1);
+        let first = chunks.recv().await.unwrap();
+        assert_eq!((first.start_index, first.states.len()), (0, 11));
+        drop(chunks);
+
+        let checkpoint = handle.await.unwrap().unwrap().unwrap();
+        assert!(checkpoint.time_index < 999);
+    }
+
+    fn linear_point(x: f64) -> DataPoint {
+        DataPoint {
+            observable: "y".to_string(),
+            value: 2.0 - x,
+            uncertainty: 0.01,
+            metadata: serde_json::json!({ "x": x }),
+        }
+    }
+
+    fn linear_model() -> ObservationModel {
+        Arc::new(|p: &[f64], point: &DataPoint| {
+            p[0] + p[1] * point.metadata["x"].as_f64().unwrap()
+        })
+    }
+
+    #[test]
+    fn test_kalman_updates_between_refits() {
+        let mut engine = RollingFitEngine::new(50, vec![0.0, 0.0])
+            .with_model(linear_model())
+            .with_prior_std(&[10.0, 10.0]);
+
+        engine.add_data(linear_point(0.0));
+        let (after_one, _) = engine.current_estimate();
+        assert!(after_one[0] > 1.9);
+
+        for i in 1..20 {
+            engine.add_data(linear_point(i as f64 * 0.1));
+        }
+        let (estimate, age) = engine.current_estimate();
+        assert!(age.is_none());
+        assert!((estimate[0] - 2.0).abs() < 1e-3);
+        assert!((estimate[1] + 1.0).abs() < 1e-3);
+        assert!(engine.current_uncertainties().iter().all(|&s| s > 0.0 && s < 0.1));
+
+        let fit = engine.refit().unwrap();
+        assert!(fit.converged);
+        assert!((fit.best_params[0] - 2.0).abs() < 1e-8);
+        assert!((fit.best_params[1] + 1.0).abs() < 1e-8);
+        assert!(fit.uncertainties.iter().all(|&s| s > 0.0 && s < 0.1));
+    }
+
+    #[test]
+    fn test_refit_needs_model() {
+        let mut engine = RollingFitEngine::new(10, vec![1.0]);
+        engine.add_data(linear_point(0.0));
+        assert!(engine.refit().is_err());
+        assert_eq!(engine.current_estimate().0, &[1.0]);
+    }
+
+    #[tokio::test]
+    async fn test_full_buffer_sheds_and_reports() {
+        let connected = |i: usize| StreamEvent::SourceConnected { source_id: i.to_string() };
+        let source_id = |event: Option<StreamEvent>| match event {
+            Some(StreamEvent::SourceConnected { source_id }) => source_id,
+            other => panic!("unexpected event {:?}", other),
+        };
+
+        let mut manager = StreamingManager::with_buffer(BufferConfig {
+            capacity: 2,
+            overflow: OverflowPolicy::DropOldest,
+        });
+        let sender = manager.event_sender();
+        assert!(sender.send(connected(0)));
+        assert!(sender.send(connected(1)));
+        assert!(!sender.send(connected(2)));
+        assert!(!sender.send(connected(3)));
+        let stats = manager.buffer_stats();
+        assert_eq!((stats.len, stats.high_water_mark, stats.received, stats.dropped), (2, 2, 4, 2));
+        assert!(matches!(manager.next_event().await, Some(StreamEvent::Dropped { count: 2 })));
+        assert_eq!(source_id(manager.next_event().await), "2");
+        assert_eq!(source_id(manager.next_event().await), "3");
+        assert_eq!(manager.buffer_stats().len, 0);
+
+        let mut manager = StreamingManager::with_buffer(BufferConfig {
+            capacity: 1,
+            overflow: OverflowPolicy::DropNewest,
+        });
+        let sender = manager.event_sender();
+        sender.send(connected(0));
+        sender.send(connected(1));
+        assert!(matches!(manager.next_event().await, Some(StreamEvent::Dropped { count: 1 })));
+        assert_eq!(source_id(manager.next_event().await), "0");
+
+        // A waiting consumer wakes up for the next event
+        let waiting = tokio::spawn(async move { manager.next_event().await });
+        tokio::task::yield_now().await;
+        sender.send(connected(2));
+        assert_eq!(source_id(waiting.await.unwrap()), "2");
+    }
+
+    #[tokio::test]
+    async fn test_aggregator_downsamples_events() {
+        use crate::aggregation::Aggregation;
+
+        let mut manager = StreamingManager::new();
+        manager.set_aggregator(Aggregator::new(Aggregation::Decimate { every: 2 }).unwrap());
+        let sender = manager.event_sender();
+        for i in 0..3 {
+            sender.send(StreamEvent::NewData {
+                source_id: "daq".to_string(),
+                timestamp: chrono::Utc::now(),
+                data: DataPoint {
+                    observable: "z".to_string(),
+                    value: i as f64,
+                    uncertainty: 0.1,
+                    metadata: serde_json::json!({}),
+                },
+            });
+        }
+        sender.send(StreamEvent::SourceDisconnected {
+            source_id: "daq".to_string(),
+            reason: "done".to_string(),
+        });
+
+        let mut values = Vec::new();
+        loop {
+            match manager.next_event().await {
+                Some(StreamEvent::NewData { data, .. }) => values.push(data.value),
+                Some(StreamEvent::SourceDisconnected { .. }) => break,
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert_eq!(values, vec![0.0, 2.0]);
+    }
+
     #[test]
     fn test_csv_line_parsing() {
-        let (sender, _) = mpsc::unbounded_channel();
+        let sender = EventSender::new(BufferConfig::default());
         let watcher = CsvFileWatcher::new(
             "test".to_string(),
             PathBuf::from("test.csv"),
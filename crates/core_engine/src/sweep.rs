@@ -0,0 +1,324 @@
+//! Declarative parameter sweeps
+//!
+//! A `sweep` block repeats its statements for every value of one parameter:
+//!
+//! ```text
+//! sweep omega in linspace(0.5, 2.0, 20) {
+//!     Hamiltonian H = (omega/2) * sigma_z + Omega * sigma_x;
+//!     experiment rabi { ... }
+//! }
+//! ```
+//!
+//! Values are given as `linspace(start, end, steps)`, `geomspace(start, end,
+//! steps)` or a list `[v1, v2, ...]`. Blocks may nest, and sibling blocks over
+//! different parameters multiply: the sweep covers the full grid over every
+//! swept parameter.
+//!
+//! [`expand_sweeps`] turns such a program into that [`ParameterGrid`] and one
+//! sweep-free program per grid point, in which every block is replaced by a
+//! `const` binding its parameter followed by its body. [`lower_sweeps`] also
+//! type checks, validates and lowers each point, giving a batch of IR
+//! programs ready for the executor.
+
+use crate::ast::{Ast, Statement, SweepValues};
+use crate::error::{EngineError, Result};
+use crate::ir::IrProgram;
+use crate::lowering::Lowerer;
+use crate::parameter_grid::{GridStrategy, ParameterGrid, ParameterRange, ParameterScale};
+use crate::typechecker::TypeChecker;
+use crate::validator::QuantumValidator;
+use std::collections::{BTreeMap, HashSet};
+
+/// One point of a sweep: the swept values and the program they bind
+#[derive(Debug, Clone)]
+pub struct SweepPoint {
+    pub params: BTreeMap<String, f64>,
+    pub ast: Ast,
+}
+
+/// Whether `ast` contains any `sweep` block
+pub fn has_sweeps(ast: &Ast) -> bool {
+    ast.statements
+        .iter()
+        .any(|stmt| matches!(stmt, Statement::Sweep { .. }))
+}
+
+/// The grid swept by `ast` and the program at each of its points
+///
+/// Points are ordered like a [`GridStrategy::FullGrid`] over the ranges, with
+/// the first block in the source varying slowest. A program without sweeps
+/// is a single point with no parameters.
+pub fn expand_sweeps(ast: &Ast) -> Result<(ParameterGrid, Vec<SweepPoint>)> {
+    let mut ranges = Vec::new();
+    collect_ranges(&ast.statements, &mut ranges)?;
+
+    let declared = declared_names(&ast.statements);
+    if let Some(range) = ranges.iter().find(|r| declared.contains(r.name.as_str())) {
+        return Err(EngineError::validation_error(format!(
+            "'{}' is both swept and declared",
+            range.name
+        )));
+    }
+
+    let mut points = vec![BTreeMap::new()];
+    for range in &ranges {
+        let values = range.values();
+        points = points
+            .into_iter()
+            .flat_map(|point| {
+                values.iter().map(move |&value| {
+                    let mut point = point.clone();
+                    point.insert(range.name.clone(), value);
+                    point
+                })
+            })
+            .collect();
+    }
+
+    let points = points
+        .into_iter()
+        .map(|params| SweepPoint {
+            ast: Ast::new(substitute(&ast.statements, &params)),
+            params,
+        })
+        .collect();
+    let grid = ParameterGrid {
+        params: ranges,
+        strategy: GridStrategy::FullGrid,
+    };
+    Ok((grid, points))
+}
+
+/// Parameter values of a sweep point and its lowered program
+pub type LoweredPoint = (BTreeMap<String, f64>, IrProgram);
+
+/// [`expand_sweeps`], then lower every point to IR
+pub fn lower_sweeps(ast: &Ast) -> Result<(ParameterGrid, Vec<LoweredPoint>)> {
+    let (grid, points) = expand_sweeps(ast)?;
+    let programs = points
+        .into_iter()
+        .map(|point| {
+            let typed = TypeChecker::new().check(&point.ast)?;
+            let validated = QuantumValidator::new().validate(&typed)?;
+            Ok((point.params, Lowerer::new().lower(&validated)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((grid, programs))
+}
+
+/// Ranges of all blocks in `statements`, depth first in source order
+fn collect_ranges(statements: &[Statement], ranges: &mut Vec<ParameterRange>) -> Result<()> {
+    for stmt in statements {
+        if let Statement::Sweep {
+            param,
+            values,
+            body,
+        } = stmt
+        {
+            if ranges.iter().any(|r| &r.name == param) {
+                return Err(EngineError::validation_error(format!(
+                    "'{}' is swept more than once",
+                    param
+                )));
+            }
+            ranges.push(sweep_range(param, values)?);
+            collect_ranges(body, ranges)?;
+        }
+    }
+    Ok(())
+}
+
+fn sweep_range(param: &str, values: &SweepValues) -> Result<ParameterRange> {
+    let invalid =
+        |reason: &str| EngineError::validation_error(format!("Sweep over '{}': {}", param, reason));
+    let (start, end, steps, scale) = match values {
+        SweepValues::Linspace { start, end, steps } => {
+            (*start, *end, *steps, ParameterScale::Linear)
+        }
+        SweepValues::Geomspace { start, end, steps } => {
+            if !(*start > 0.0 && *end > 0.0) {
+                return Err(invalid("geomspace endpoints must be positive"));
+            }
+            (*start, *end, *steps, ParameterScale::Log)
+        }
+        SweepValues::List(values) => (
+            values.first().copied().unwrap_or(0.0),
+            values.last().copied().unwrap_or(0.0),
+            values.len(),
+            ParameterScale::Custom(values.clone()),
+        ),
+    };
+    if steps == 0 {
+        return Err(invalid("no values"));
+    }
+    let range = ParameterRange {
+        name: param.to_string(),
+        start,
+        end,
+        steps,
+        scale,
+    };
+    if range.values().iter().any(|v| !v.is_finite()) {
+        return Err(invalid("values must be finite"));
+    }
+    Ok(range)
+}
+
+/// Names bound by `const`, `param` and `symbol` declarations, inside sweep
+/// bodies too
+fn declared_names(statements: &[Statement]) -> HashSet<&str> {
+    let mut names = HashSet::new();
+    for stmt in statements {
+        match stmt {
+            Statement::ConstDecl { name, .. }
+            | Statement::ParamDecl { name, .. }
+            | Statement::SymbolDecl { name } => {
+                names.insert(name.as_str());
+            }
+            Statement::Sweep { body, .. } => names.extend(declared_names(body)),
+            _ => {}
+        }
+    }
+    names
+}
+
+/// `statements` with every block flattened into a `const` for its value
+/// followed by its body
+fn substitute(statements: &[Statement], params: &BTreeMap<String, f64>) -> Vec<Statement> {
+    let mut flat = Vec::with_capacity(statements.len());
+    for stmt in statements {
+        match stmt {
+            Statement::Sweep { param, body, .. } => {
+                flat.push(Statement::ConstDecl {
+                    name: param.clone(),
+                    value: params[param],
+                });
+                flat.extend(substitute(body, params));
+            }
+            other => flat.push(other.clone()),
+        }
+    }
+    flat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sweep(param: &str, values: SweepValues, body: Vec<Statement>) -> Statement {
+        Statement::Sweep {
+            param: param.to_string(),
+            values,
+            body,
+        }
+    }
+
+    fn constant(name: &str) -> Statement {
+        Statement::ConstDecl {
+            name: name.to_string(),
+            value: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_expand_nested_sweeps() {
+        let ast = Ast::new(vec![
+            constant("n"),
+            sweep(
+                "omega",
+                SweepValues::Linspace {
+                    start: 0.5,
+                    end: 2.0,
+                    steps: 4,
+                },
+                vec![sweep(
+                    "gamma",
+                    SweepValues::List(vec![0.0, 0.1]),
+                    vec![constant("m")],
+                )],
+            ),
+        ]);
+        assert!(has_sweeps(&ast));
+
+        let (grid, points) = expand_sweeps(&ast).unwrap();
+        assert!(matches!(grid.strategy, GridStrategy::FullGrid));
+        assert_eq!(grid.params.len(), 2);
+        assert_eq!(points.len(), 8);
+
+        // The outer block varies slowest
+        let omegas: Vec<f64> = points.iter().map(|p| p.params["omega"]).collect();
+        assert_eq!(omegas, vec![0.5, 0.5, 1.0, 1.0, 1.5, 1.5, 2.0, 2.0]);
+        assert_eq!(points[1].params["gamma"], 0.1);
+
+        let consts: Vec<(String, f64)> = points[3]
+            .ast
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::ConstDecl { name, value } => Some((name.clone(), *value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            consts,
+            vec![
+                ("n".to_string(), 1.0),
+                ("omega".to_string(), 1.0),
+                ("gamma".to_string(), 0.1),
+                ("m".to_string(), 1.0),
+            ]
+        );
+        assert!(!has_sweeps(&points[3].ast));
+    }
+
+    #[test]
+    fn test_expand_rejects_invalid_sweeps() {
+        let list = || SweepValues::List(vec![1.0]);
+
+        let twice = Ast::new(vec![sweep("x", list(), vec![]), sweep("x", list(), vec![])]);
+        assert!(expand_sweeps(&twice).is_err());
+
+        let declared = Ast::new(vec![sweep("x", list(), vec![constant("x")])]);
+        assert!(expand_sweeps(&declared).is_err());
+
+        let empty = Ast::new(vec![sweep("x", SweepValues::List(vec![]), vec![])]);
+        assert!(expand_sweeps(&empty).is_err());
+
+        let negative = SweepValues::Geomspace {
+            start: -1.0,
+            end: 1.0,
+            steps: 3,
+        };
+        assert!(expand_sweeps(&Ast::new(vec![sweep("x", negative, vec![])])).is_err());
+
+        let (grid, points) = expand_sweeps(&Ast::new(vec![constant("n")])).unwrap();
+        assert!(grid.params.is_empty());
+        assert_eq!(points.len(), 1);
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_lower_sweeps() {
+        let source = "
+            matrix sigma_z = [[1, 0], [0, -1]];
+            sweep omega in [1.0, 2.0, 3.0] {
+                Hamiltonian H = (omega/2) * sigma_z;
+                experiment rabi {
+                    init: ket(vec(1, 0));
+                    evolution: evolve(init, H, timegrid=(0.0, 0.1, 11));
+                }
+            }
+        ";
+        let ast = crate::parser::parse_dsl(source).unwrap();
+        let (grid, programs) = lower_sweeps(&ast).unwrap();
+        assert_eq!(grid.params[0].values(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(programs.len(), 3);
+        for (params, ir) in &programs {
+            assert!(params.contains_key("omega"));
+            assert_eq!(ir.experiments.len(), 1);
+        }
+
+        // Unexpanded sweeps are rejected downstream
+        assert!(TypeChecker::new().check(&ast).is_err());
+    }
+}
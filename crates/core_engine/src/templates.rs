@@ -1,10 +1,41 @@
 //! Template library for common quantum systems
 //!
 //! Provides pre-built, validated DSL programs for standard experiments.
-
+//!
+//! Scalar parameters are substituted into the template text as `{name}`
+//! placeholders. Operator parameters (e.g. the drive or collapse operator)
+//! are free identifiers in the template, replaced in the parsed program by
+//! references to a builtin or template-declared operator of the right
+//! dimension; see [`TemplateRegistry::instantiate_ast`].
+//!
+//! Labs add their own templates as TOML or YAML files holding a serialized
+//! [`Template`], one per file, loaded with [`TemplateRegistry::load_dir`]. A
+//! file whose `id` matches a builtin replaces it.
+
+use crate::ast::{Expr, MatrixLiteral};
+#[cfg(feature = "parser")]
+use crate::ast::{
+    Ast, EvolutionSpec, ExperimentBody, MeasurementSpec, StateSpec, Statement, VectorLiteral,
+};
 use crate::error::{EngineError, Result};
+#[cfg(feature = "parser")]
+use crate::gates;
+use num_complex::Complex64;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Extensions of template definition files
+pub const TEMPLATE_EXTENSIONS: [&str; 3] = ["toml", "yaml", "yml"];
+
+/// Directory user templates are installed to: `$QTE_TEMPLATE_DIR`, else
+/// `~/.qte/templates`
+pub fn default_template_dir() -> Option<PathBuf> {
+    match std::env::var_os("QTE_TEMPLATE_DIR") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => std::env::var_os("HOME").map(|home| Path::new(&home).join(".qte").join("templates")),
+    }
+}
 
 /// Template registry
 pub struct TemplateRegistry {
@@ -14,12 +45,21 @@ pub struct TemplateRegistry {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Template {
     pub id: String,
+    /// Bumped whenever the template's code changes, so provenance records
+    /// pin the exact model a result came from
+    #[serde(default)]
+    pub version: String,
     pub name: String,
     pub description: String,
     pub category: TemplateCategory,
+    #[serde(default)]
     pub parameters: Vec<TemplateParameter>,
+    #[serde(default)]
+    pub operator_parameters: Vec<OperatorParameter>,
     pub code: String,
+    #[serde(default)]
     pub tags: Vec<String>,
+    #[serde(default)]
     pub citations: Vec<Citation>,
 }
 
@@ -36,12 +76,26 @@ pub enum TemplateCategory {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateParameter {
     pub name: String,
+    #[serde(default)]
     pub description: String,
     pub default_value: f64,
+    #[serde(default)]
     pub constraints: ParameterConstraints,
 }
 
+/// Operator-valued parameter, referenced in the template code by its name
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorParameter {
+    pub name: String,
+    pub description: String,
+    /// Dimension of the Hilbert space the operator acts on
+    pub dim: usize,
+    /// Operator used when none is given
+    pub default_operator: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ParameterConstraints {
     pub min: Option<f64>,
     pub max: Option<f64>,
@@ -76,12 +130,73 @@ impl TemplateRegistry {
         self.register(quantum_zeno());
         self.register(grover_search());
         self.register(vqe_h2());
+        self.register(cz_calibration());
+        self.register(driven_qubit());
     }
 
     pub fn register(&mut self, template: Template) {
         self.templates.insert(template.id.clone(), template);
     }
 
+    /// Register every template file (see [`TEMPLATE_EXTENSIONS`]) in `dir`,
+    /// returning their ids in file name order
+    ///
+    /// Templates replace registered ones of the same id, builtins included.
+    /// Each is checked by instantiating it with its defaults; a template that
+    /// fails, or two files in `dir` with one id, is an error naming the file,
+    /// and leaves the registry unchanged.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let dir = dir.as_ref();
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_template = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| TEMPLATE_EXTENSIONS.contains(&e));
+            if path.is_file() && is_template {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut loaded = Self {
+            templates: self.templates.clone(),
+        };
+        let mut sources: HashMap<String, PathBuf> = HashMap::new();
+        let mut ids = Vec::new();
+        for path in paths {
+            let template = Template::from_file(&path)?;
+            if let Some(first) = sources.get(&template.id) {
+                return Err(EngineError::validation_error(format!(
+                    "Template '{}' is defined by both {} and {}",
+                    template.id,
+                    first.display(),
+                    path.display()
+                )));
+            }
+            let id = template.id.clone();
+            loaded.register(template);
+            loaded.check_instantiates(&id).map_err(|e| {
+                EngineError::validation_error(format!("Template file {}: {}", path.display(), e))
+            })?;
+            sources.insert(id.clone(), path);
+            ids.push(id);
+        }
+
+        *self = loaded;
+        Ok(ids)
+    }
+
+    /// Instantiate `id` with its defaults and, with the `parser` feature,
+    /// parse the result
+    fn check_instantiates(&self, id: &str) -> Result<()> {
+        let _code = self.instantiate(id, &HashMap::new())?;
+        #[cfg(feature = "parser")]
+        crate::parser::parse_dsl(&_code)?;
+        Ok(())
+    }
+
     pub fn get(&self, id: &str) -> Option<&Template> {
         self.templates.get(id)
     }
@@ -103,31 +218,115 @@ impl TemplateRegistry {
             .collect()
     }
 
+    /// Template code with every `{name}` placeholder replaced by the value
+    /// of parameter `name`, or its default when absent
+    ///
+    /// A placeholder naming no parameter of the template is an error.
     pub fn instantiate(&self, id: &str, params: &HashMap<String, f64>) -> Result<String> {
         let template = self.get(id)
             .ok_or_else(|| EngineError::NotFound(format!("Template not found: {}", id)))?;
 
         // Validate parameters
         for param in &template.parameters {
-            if let Some(&value) = params.get(&param.name) {
-                self.validate_parameter(param, value)?;
-            }
+            let value = params.get(&param.name).copied().unwrap_or(param.default_value);
+            self.validate_parameter(param, value)?;
         }
 
-        // Substitute parameters in template code
-        let mut code = template.code.clone();
-        for (name, value) in params {
-            let placeholder = format!("{{{}}}", name);
-            code = code.replace(&placeholder, &value.to_string());
+        // Substitute parameters in template code; integer values print
+        // without a fractional part
+        let mut code = String::with_capacity(template.code.len());
+        let mut rest = template.code.as_str();
+        while let Some((start, name)) = next_placeholder(rest) {
+            let param = template.parameters.iter().find(|p| p.name == name).ok_or_else(|| {
+                EngineError::validation_error(format!(
+                    "Template '{}' has a placeholder {{{}}} for no declared parameter",
+                    id, name
+                ))
+            })?;
+            let value = params.get(name).copied().unwrap_or(param.default_value);
+            code.push_str(&rest[..start]);
+            code.push_str(&value.to_string());
+            rest = &rest[start + name.len() + 2..];
         }
+        code.push_str(rest);
 
         Ok(code)
     }
 
+    /// Instantiate, parse and bind operator parameters
+    ///
+    /// `operators` maps operator parameters to a builtin operator
+    /// ([`BUILTIN_OPERATORS`]) or a `matrix` or `operator` declared in the
+    /// template; absent ones take their default. Every reference to the
+    /// parameter (in expressions, `evolve` and `lindblad` operands) is renamed
+    /// to the chosen operator, and builtins gain a `matrix` declaration.
+    #[cfg(feature = "parser")]
+    pub fn instantiate_ast(
+        &self,
+        id: &str,
+        params: &HashMap<String, f64>,
+        operators: &HashMap<String, String>,
+    ) -> Result<Ast> {
+        let template = self.get(id).ok_or_else(|| {
+            EngineError::validation_error(format!("Template not found: {}", id))
+        })?;
+        if let Some(name) = operators
+            .keys()
+            .find(|name| !template.operator_parameters.iter().any(|p| &p.name == *name))
+        {
+            return Err(EngineError::validation_error(format!(
+                "Template '{}' has no operator parameter '{}'",
+                id, name
+            )));
+        }
+
+        let mut ast = crate::parser::parse_dsl(&self.instantiate(id, params)?)?;
+        let mut declarations = Vec::new();
+        for param in &template.operator_parameters {
+            let operator = operators.get(&param.name).unwrap_or(&param.default_operator);
+            let declared = ast.statements.iter().chain(&declarations).find_map(|stmt| match stmt {
+                Statement::MatrixDecl { name, value } if name == operator => {
+                    Some(Ok((value.rows.len(), value.rows.first().map_or(0, Vec::len))))
+                }
+                Statement::OperatorDecl { name, value } if name == operator => {
+                    Some(operator_dim(value).map(|n| (n, n)))
+                }
+                _ => None,
+            });
+            let (rows, cols) = match declared {
+                Some(dim) => dim?,
+                None => {
+                    let value = builtin_operator(operator).ok_or_else(|| {
+                        EngineError::validation_error(format!(
+                            "Unknown operator '{}' for parameter '{}'; use one of {:?} or a matrix or operator declared in the template",
+                            operator, param.name, BUILTIN_OPERATORS
+                        ))
+                    })?;
+                    let dim = (value.rows.len(), value.rows.first().map_or(0, Vec::len));
+                    declarations.push(Statement::MatrixDecl {
+                        name: operator.clone(),
+                        value,
+                    });
+                    dim
+                }
+            };
+            if (rows, cols) != (param.dim, param.dim) {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{}x{} operator for '{}'", param.dim, param.dim, param.name),
+                    format!("{}x{} ('{}')", rows, cols, operator),
+                ));
+            }
+            rename_statements(&mut ast.statements, &param.name, operator);
+        }
+
+        declarations.append(&mut ast.statements);
+        Ok(Ast::new(declarations))
+    }
+
     fn validate_parameter(&self, param: &TemplateParameter, value: f64) -> Result<()> {
         if let Some(min) = param.constraints.min {
             if value < min {
-                return Err(EngineError::Validation(
+                return Err(EngineError::validation_error(
                     format!("Parameter {} = {} is less than minimum {}", param.name, value, min)
                 ));
             }
@@ -135,20 +334,20 @@ impl TemplateRegistry {
 
         if let Some(max) = param.constraints.max {
             if value > max {
-                return Err(EngineError::Validation(
+                return Err(EngineError::validation_error(
                     format!("Parameter {} = {} exceeds maximum {}", param.name, value, max)
                 ));
             }
         }
 
         if param.constraints.must_be_positive && value <= 0.0 {
-            return Err(EngineError::Validation(
+            return Err(EngineError::validation_error(
                 format!("Parameter {} = {} must be positive", param.name, value)
             ));
         }
 
         if param.constraints.must_be_integer && value.fract() != 0.0 {
-            return Err(EngineError::Validation(
+            return Err(EngineError::validation_error(
                 format!("Parameter {} = {} must be an integer", param.name, value)
             ));
         }
@@ -163,11 +362,224 @@ impl Default for TemplateRegistry {
     }
 }
 
+/// Byte offset and name of the first `{name}` placeholder in `code`, where
+/// `name` is an identifier
+fn next_placeholder(code: &str) -> Option<(usize, &str)> {
+    let mut offset = 0;
+    while let Some(open) = code[offset..].find('{') {
+        let start = offset + open;
+        let tail = &code[start + 1..];
+        let len = tail
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(tail.len());
+        let name = &tail[..len];
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+        if is_identifier && tail[len..].starts_with('}') {
+            return Some((start, name));
+        }
+        offset = start + 1;
+    }
+    None
+}
+
+/// Operators an operator parameter can name without the template declaring
+/// them
+pub const BUILTIN_OPERATORS: &[&str] = &[
+    "sigma_x",
+    "sigma_y",
+    "sigma_z",
+    "sigma_plus",
+    "sigma_minus",
+    "identity",
+];
+
+/// Matrix of a builtin single-qubit operator; `sigma_plus` is `|0⟩⟨1|`,
+/// raising in the convention where `sigma_z|0⟩ = |0⟩`
+pub fn builtin_operator(name: &str) -> Option<MatrixLiteral> {
+    let re = |x: f64| Expr::Number(x);
+    let im = |y: f64| Expr::ComplexNumber(Complex64::new(0.0, y));
+    let rows = match name {
+        "sigma_x" => vec![vec![re(0.0), re(1.0)], vec![re(1.0), re(0.0)]],
+        "sigma_y" => vec![vec![re(0.0), im(-1.0)], vec![im(1.0), re(0.0)]],
+        "sigma_z" => vec![vec![re(1.0), re(0.0)], vec![re(0.0), re(-1.0)]],
+        "sigma_plus" => vec![vec![re(0.0), re(1.0)], vec![re(0.0), re(0.0)]],
+        "sigma_minus" => vec![vec![re(0.0), re(0.0)], vec![re(1.0), re(0.0)]],
+        "identity" => vec![vec![re(1.0), re(0.0)], vec![re(0.0), re(1.0)]],
+        _ => return None,
+    };
+    Some(MatrixLiteral { rows })
+}
+
+/// Dimension of the operator an `operator` declaration builds
+#[cfg(feature = "parser")]
+fn operator_dim(value: &Expr) -> Result<usize> {
+    match value {
+        Expr::FuncCall { name, args } => match crate::builtins::lookup(name) {
+            Some(builtin) => Ok(builtin.operator(args)?.nrows()),
+            None => Err(EngineError::validation_error(format!("Unknown operator builtin '{}'", name))),
+        },
+        other => Err(EngineError::validation_error(format!("'{}' is not a builtin operator", other))),
+    }
+}
+
+/// Rename every reference to the operator `from`
+#[cfg(feature = "parser")]
+fn rename_statements(statements: &mut [Statement], from: &str, to: &str) {
+    for stmt in statements {
+        match stmt {
+            Statement::MatrixDecl { value, .. } => rename_matrix(value, from, to),
+            Statement::FunctionDef { body, .. } => rename_expr(body, from, to),
+            Statement::HamiltonianDef { expr, .. } => rename_expr(expr, from, to),
+            Statement::MeasurementDef { spec, .. } => match spec {
+                MeasurementSpec::Projective { projectors: matrices }
+                | MeasurementSpec::POVM { effects: matrices } => {
+                    for matrix in matrices {
+                        rename_matrix(matrix, from, to);
+                    }
+                }
+                MeasurementSpec::Basis { .. } => {}
+            },
+            Statement::ChannelDef { kraus, .. } => {
+                for op in kraus {
+                    rename_expr(op, from, to);
+                }
+            }
+            Statement::Experiment { body, .. } => rename_experiment(body, from, to),
+            Statement::Sweep { body, .. } => rename_statements(body, from, to),
+            Statement::Assume(goal) | Statement::Prove(goal) => {
+                for expr in goal.exprs_mut() {
+                    rename_expr(expr, from, to);
+                }
+            }
+            Statement::ConstDecl { .. }
+            | Statement::SymbolDecl { .. }
+            | Statement::ParamDecl { .. }
+            | Statement::OperatorDecl { .. } => {}
+        }
+    }
+}
+
+#[cfg(feature = "parser")]
+fn rename_experiment(body: &mut ExperimentBody, from: &str, to: &str) {
+    match &mut body.init {
+        Some(StateSpec::Ket(vector)) => rename_vector(vector, from, to),
+        Some(StateSpec::Rho(matrix)) => rename_matrix(matrix, from, to),
+        Some(StateSpec::Eigenstate { hamiltonian, .. }) => {
+            if hamiltonian == from {
+                *hamiltonian = to.to_string();
+            }
+        }
+        Some(StateSpec::From(_)) | None => {}
+    }
+    if let Some(EvolutionSpec {
+        hamiltonian_name,
+        lindblad_ops,
+        ..
+    }) = &mut body.evolution
+    {
+        if hamiltonian_name == from {
+            *hamiltonian_name = to.to_string();
+        }
+        for term in lindblad_ops {
+            if term.operator_name == from {
+                term.operator_name = to.to_string();
+            }
+            rename_expr(&mut term.rate, from, to);
+        }
+    }
+    for observable in &mut body.observables {
+        rename_expr(&mut observable.expr, from, to);
+    }
+    for application in &mut body.gates {
+        // Library gates keep their meaning whatever the template declares
+        if application.gate == from && gates::lookup(from).is_none() {
+            application.gate = to.to_string();
+        }
+        if let Some(angle) = &mut application.angle {
+            rename_expr(angle, from, to);
+        }
+    }
+}
+
+#[cfg(feature = "parser")]
+fn rename_matrix(matrix: &mut MatrixLiteral, from: &str, to: &str) {
+    for element in matrix.rows.iter_mut().flatten() {
+        rename_expr(element, from, to);
+    }
+}
+
+#[cfg(feature = "parser")]
+fn rename_vector(vector: &mut VectorLiteral, from: &str, to: &str) {
+    for element in &mut vector.elements {
+        rename_expr(element, from, to);
+    }
+}
+
+#[cfg(feature = "parser")]
+fn rename_expr(expr: &mut Expr, from: &str, to: &str) {
+    match expr {
+        Expr::Identifier(name) => {
+            if name == from {
+                *name = to.to_string();
+            }
+        }
+        Expr::Number(_) | Expr::ComplexNumber(_) => {}
+        Expr::Matrix(matrix) => rename_matrix(matrix, from, to),
+        Expr::Vector(vector) => rename_vector(vector, from, to),
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Pow(a, b)
+        | Expr::Tensor(a, b)
+        | Expr::Commutator(a, b)
+        | Expr::AntiCommutator(a, b) => {
+            rename_expr(a, from, to);
+            rename_expr(b, from, to);
+        }
+        Expr::Dagger(a)
+        | Expr::Trace(a)
+        | Expr::Expm(a)
+        | Expr::Sqrt(a)
+        | Expr::Sin(a)
+        | Expr::Cos(a)
+        | Expr::Exp(a) => rename_expr(a, from, to),
+        Expr::FuncCall { args, .. } => {
+            for arg in args {
+                rename_expr(arg, from, to);
+            }
+        }
+    }
+}
+
+impl Template {
+    /// Read a template definition from a `.toml`, `.yaml` or `.yml` file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let parsed = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).map_err(|e| e.to_string()),
+            Some("toml") => toml::from_str(&text).map_err(|e| e.to_string()),
+            _ => {
+                return Err(EngineError::validation_error(format!(
+                    "Template file {} must end in one of {:?}",
+                    path.display(),
+                    TEMPLATE_EXTENSIONS
+                )))
+            }
+        };
+        parsed.map_err(|e| {
+            EngineError::SerializationError(format!("Template file {}: {}", path.display(), e))
+        })
+    }
+}
+
 // Built-in templates
 
 fn rabi_oscillations() -> Template {
     Template {
         id: "rabi".to_string(),
+        version: "1.0".to_string(),
         name: "Rabi Oscillations".to_string(),
         description: "Coherent oscillations of a driven two-level system".to_string(),
         category: TemplateCategory::SingleQubit,
@@ -195,6 +607,7 @@ fn rabi_oscillations() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param omega: Real = {omega};  # Rabi frequency
 param T: Real = {T};          # Total time
@@ -221,6 +634,7 @@ measure psi in computational;
 fn ramsey_interferometry() -> Template {
     Template {
         id: "ramsey".to_string(),
+        version: "1.0".to_string(),
         name: "Ramsey Interferometry".to_string(),
         description: "Two π/2 pulses separated by free evolution for precision frequency measurements".to_string(),
         category: TemplateCategory::SingleQubit,
@@ -248,6 +662,7 @@ fn ramsey_interferometry() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param delta: Real = {delta};  # Detuning
 param tau: Real = {tau};      # Free evolution
@@ -270,6 +685,7 @@ measure psi in computational;
 fn bell_state_tomography() -> Template {
     Template {
         id: "bell_tomography".to_string(),
+        version: "1.0".to_string(),
         name: "Bell State Tomography".to_string(),
         description: "Prepare and measure Bell states in multiple bases".to_string(),
         category: TemplateCategory::TwoQubit,
@@ -286,6 +702,7 @@ fn bell_state_tomography() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param shots: Int = {shots};
 
@@ -318,6 +735,7 @@ measure psi in computational with shots;
 fn jaynes_cummings() -> Template {
     Template {
         id: "jaynes_cummings".to_string(),
+        version: "1.0".to_string(),
         name: "Jaynes-Cummings Model".to_string(),
         description: "Atom-cavity coupling with vacuum Rabi oscillations".to_string(),
         category: TemplateCategory::Cavity,
@@ -345,6 +763,7 @@ fn jaynes_cummings() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param g: Real = {g};        # Coupling
 param n_max: Int = {n_max}; # Truncation
@@ -374,6 +793,7 @@ measure cavity in fock;
 fn quantum_zeno() -> Template {
     Template {
         id: "zeno".to_string(),
+        version: "1.0".to_string(),
         name: "Quantum Zeno Effect".to_string(),
         description: "Frequent measurements freeze quantum evolution".to_string(),
         category: TemplateCategory::OpenSystems,
@@ -390,6 +810,7 @@ fn quantum_zeno() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param omega: Real = 1.0;
 param T: Real = 10.0;
@@ -416,6 +837,7 @@ measure psi in computational;
 fn grover_search() -> Template {
     Template {
         id: "grover".to_string(),
+        version: "1.0".to_string(),
         name: "Grover Search Algorithm".to_string(),
         description: "Quantum search with quadratic speedup".to_string(),
         category: TemplateCategory::ManyBody,
@@ -443,6 +865,7 @@ fn grover_search() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param n_qubits: Int = {n_qubits};
 param target: Int = {target};
@@ -477,6 +900,7 @@ measure psi in computational with 1000;
 fn vqe_h2() -> Template {
     Template {
         id: "vqe_h2".to_string(),
+        version: "1.0".to_string(),
         name: "VQE for H₂ Molecule".to_string(),
         description: "Variational quantum eigensolver for hydrogen molecule".to_string(),
         category: TemplateCategory::MetrologyOptimization,
@@ -493,6 +917,7 @@ fn vqe_h2() -> Template {
                 },
             },
         ],
+        operator_parameters: vec![],
         code: r#"
 param R: Real = {bond_length};  # Bond length
 
@@ -528,6 +953,111 @@ fit theta to minimize expectation(H);
     }
 }
 
+fn cz_calibration() -> Template {
+    let parameter = |name: &str, description: &str, default_value: f64, min: Option<f64>| {
+        TemplateParameter {
+            name: name.to_string(),
+            description: description.to_string(),
+            default_value,
+            constraints: ParameterConstraints {
+                min,
+                max: None,
+                must_be_positive: false,
+                must_be_integer: false,
+            },
+        }
+    };
+
+    Template {
+        id: "cz_calibration".to_string(),
+        version: "1.0".to_string(),
+        name: "CZ Gate Calibration".to_string(),
+        description: "Controlled-phase interaction with residual single-qubit phase errors, for fitting CZ/CNOT gate tomography".to_string(),
+        category: TemplateCategory::TwoQubit,
+        parameters: vec![
+            parameter("J", "Exchange coupling on |11⟩ (rad/μs); J·t_gate = π for a CZ", std::f64::consts::PI, Some(0.0)),
+            parameter("delta1", "Phase error rate of qubit 1 (rad/μs)", 0.0, None),
+            parameter("delta2", "Phase error rate of qubit 2 (rad/μs)", 0.0, None),
+            parameter("t_gate", "Gate time (μs)", 1.0, Some(0.0)),
+        ],
+        operator_parameters: vec![],
+        code: r#"
+// H = J |11⟩⟨11| + (δ1/2) Z⊗I + (δ2/2) I⊗Z, applied for t_gate to |++⟩
+const J = {J};
+const delta1 = {delta1};
+const delta2 = {delta2};
+
+matrix P11 = [0, 0, 0, 0; 0, 0, 0, 0; 0, 0, 0, 0; 0, 0, 0, 1];
+matrix Z1 = [0.5, 0, 0, 0; 0, 0.5, 0, 0; 0, 0, -0.5, 0; 0, 0, 0, -0.5];
+matrix Z2 = [0.5, 0, 0, 0; 0, -0.5, 0, 0; 0, 0, 0.5, 0; 0, 0, 0, -0.5];
+
+Hamiltonian H = J * P11 + delta1 * Z1 + delta2 * Z2;
+
+experiment cz_gate {
+  init: ket(vec(0.5, 0.5, 0.5, 0.5));
+  evolution: evolve(init, H, times=[0.0, {t_gate}]);
+}
+"#.to_string(),
+        tags: vec!["cz".to_string(), "cnot".to_string(), "calibration".to_string(), "two-qubit".to_string()],
+        citations: vec![],
+    }
+}
+
+fn driven_qubit() -> Template {
+    let parameter = |name: &str, description: &str, default_value: f64| TemplateParameter {
+        name: name.to_string(),
+        description: description.to_string(),
+        default_value,
+        constraints: ParameterConstraints {
+            min: Some(0.0),
+            max: None,
+            must_be_positive: false,
+            must_be_integer: false,
+        },
+    };
+    let operator = |name: &str, description: &str, default_operator: &str| OperatorParameter {
+        name: name.to_string(),
+        description: description.to_string(),
+        dim: 2,
+        default_operator: default_operator.to_string(),
+    };
+
+    Template {
+        id: "driven_qubit".to_string(),
+        version: "1.0".to_string(),
+        name: "Driven Qubit".to_string(),
+        description: "Qubit under a resonant drive of selectable axis, decaying through a selectable collapse operator".to_string(),
+        category: TemplateCategory::OpenSystems,
+        parameters: vec![
+            parameter("omega", "Qubit frequency (rad/μs)", 1.0),
+            parameter("Omega", "Drive strength (rad/μs)", 1.0),
+            parameter("gamma", "Decay rate (1/μs)", 0.1),
+        ],
+        operator_parameters: vec![
+            operator("drive", "Drive operator", "sigma_x"),
+            operator("collapse", "Collapse operator", "sigma_minus"),
+        ],
+        code: r#"
+// H = (ω/2) σz + (Ω/2) drive, with decay through collapse at rate γ
+const omega = {omega};
+const Omega = {Omega};
+const gamma = {gamma};
+
+matrix sz = [1, 0; 0, -1];
+
+Hamiltonian H = (omega/2) * sz + (Omega/2) * drive;
+
+experiment driven_qubit {
+  init: ket(vec(1, 0));
+  evolution: evolve(init, H, timegrid=(0.0, 0.01, 1001));
+  lindblad: { L: collapse, rate: gamma };
+}
+"#.to_string(),
+        tags: vec!["qubit".to_string(), "driving".to_string(), "decay".to_string(), "lindblad".to_string()],
+        citations: vec![],
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -551,6 +1081,92 @@ mod tests {
         assert!(code.contains("T: Real = 5"));
     }
 
+    #[test]
+    fn test_instantiation_fills_defaults_and_formats_integers() {
+        let registry = TemplateRegistry::new();
+        let code = registry.instantiate("rabi", &HashMap::new()).unwrap();
+        assert!(code.contains("omega: Real = 1;"));
+        assert!(code.contains("T: Real = 10;"));
+
+        let params = HashMap::from([("n_qubits".to_string(), 4.0)]);
+        let code = registry.instantiate("grover", &params).unwrap();
+        assert!(code.contains("n_qubits: Int = 4;"));
+        assert!(code.contains("target: Int = 5;"));
+    }
+
+    #[test]
+    fn test_unknown_placeholder_rejected() {
+        let mut registry = TemplateRegistry::new();
+        let mut template = registry.get("rabi").unwrap().clone();
+        template.id = "broken".to_string();
+        template.code = template.code.replace("{T}", "{duration}");
+        registry.register(template);
+
+        let err = registry.instantiate("broken", &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("{duration}"));
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = std::env::temp_dir().join(format!("qte_templates_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("rabi.toml"),
+            r#"
+id = "rabi"
+version = "lab-1"
+name = "Detuned Rabi"
+description = "Rabi oscillations with a fixed detuning"
+category = "SingleQubit"
+code = """
+const omega = {omega};
+Hamiltonian H = (omega/2) * sigma_x + 0.1 * sigma_z;
+"""
+
+[[parameters]]
+name = "omega"
+default_value = 1.5
+constraints = { min = 0.0 }
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("spin_echo.yaml"),
+            "id: spin_echo\nname: Spin Echo\ndescription: Refocused dephasing\ncategory: OpenSystems\ncode: |\n  const tau = {tau};\nparameters:\n  - name: tau\n    default_value: 2\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a template").unwrap();
+
+        let mut registry = TemplateRegistry::new();
+        let ids = registry.load_dir(&dir).unwrap();
+        assert_eq!(ids, ["rabi", "spin_echo"]);
+        assert_eq!(registry.get("rabi").unwrap().version, "lab-1");
+        assert!(registry.instantiate("rabi", &HashMap::new()).unwrap().contains("const omega = 1.5;"));
+        assert!(registry.get("spin_echo").unwrap().tags.is_empty());
+
+        // Two files with one id are rejected without touching the registry
+        std::fs::write(dir.join("spin_echo_copy.yml"), std::fs::read(dir.join("spin_echo.yaml")).unwrap()).unwrap();
+        let mut fresh = TemplateRegistry::new();
+        let err = fresh.load_dir(&dir).unwrap_err();
+        assert!(err.to_string().contains("spin_echo_copy.yml"));
+        assert_eq!(fresh.get("rabi").unwrap().version, "1.0");
+
+        // So is a file whose code does not parse
+        #[cfg(feature = "parser")]
+        {
+            std::fs::remove_file(dir.join("spin_echo_copy.yml")).unwrap();
+            std::fs::write(
+                dir.join("broken.yaml"),
+                "id: broken\nname: Broken\ndescription: x\ncategory: OpenSystems\ncode: |\n  const tau = ;\n",
+            )
+            .unwrap();
+            let err = TemplateRegistry::new().load_dir(&dir).unwrap_err();
+            assert!(err.to_string().contains("broken.yaml"));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_parameter_validation() {
         let registry = TemplateRegistry::new();
@@ -561,6 +1177,74 @@ mod tests {
         assert!(registry.validate_parameter(param, -1.0).is_err());
     }
 
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_operator_parameters() {
+        let registry = TemplateRegistry::new();
+        let operators = HashMap::from([("drive".to_string(), "sigma_y".to_string())]);
+        let ast = registry
+            .instantiate_ast("driven_qubit", &HashMap::new(), &operators)
+            .unwrap();
+
+        // Builtins are declared up front; references are renamed in place
+        let declared: Vec<&str> = ast
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::MatrixDecl { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(declared, vec!["sigma_y", "sigma_minus", "sz"]);
+        let Some(Statement::HamiltonianDef { expr, .. }) = ast
+            .statements
+            .iter()
+            .find(|stmt| matches!(stmt, Statement::HamiltonianDef { .. }))
+        else {
+            panic!("expected a Hamiltonian");
+        };
+        let Expr::Add(_, drive) = expr.as_ref() else {
+            panic!("expected a sum");
+        };
+        assert!(matches!(drive.as_ref(), Expr::Mul(_, op) if **op == Expr::Identifier("sigma_y".to_string())));
+        let Some(Statement::Experiment { body, .. }) = ast.statements.last() else {
+            panic!("expected an experiment");
+        };
+        assert_eq!(body.evolution.as_ref().unwrap().lindblad_ops[0].operator_name, "sigma_minus");
+
+        let typed = crate::typechecker::TypeChecker::new().check(&ast).unwrap();
+        let validated = crate::validator::QuantumValidator::new().validate(&typed).unwrap();
+        assert!(crate::lowering::Lowerer::new().lower(&validated).is_ok());
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_operator_parameter_validation() {
+        let mut registry = TemplateRegistry::new();
+        let mut template = registry.get("driven_qubit").unwrap().clone();
+        template.id = "driven_qutrit".to_string();
+        template.code.push_str("matrix big = [1, 0, 0; 0, 1, 0; 0, 0, 1];\n");
+        template.code.push_str("operator a: Fock<2> = annihilation;\noperator a3: Fock<3> = annihilation;\n");
+        registry.register(template);
+        let instantiate = |name: &str, operator: &str| {
+            let operators = HashMap::from([(name.to_string(), operator.to_string())]);
+            registry.instantiate_ast("driven_qutrit", &HashMap::new(), &operators)
+        };
+
+        assert!(instantiate("collapse", "sz").is_ok());
+        assert!(matches!(
+            instantiate("drive", "big"),
+            Err(EngineError::DimensionMismatch { .. })
+        ));
+        assert!(instantiate("collapse", "a").is_ok());
+        assert!(matches!(
+            instantiate("collapse", "a3"),
+            Err(EngineError::DimensionMismatch { .. })
+        ));
+        assert!(instantiate("drive", "sigma_w").is_err());
+        assert!(instantiate("detuning", "sigma_z").is_err());
+    }
+
     #[test]
     fn test_category_filtering() {
         let registry = TemplateRegistry::new();
@@ -4,8 +4,17 @@
 //! mathematical operations are well-defined.
 
 use crate::ast::*;
+use crate::bases;
+use crate::builtins::{self, ShapeRule};
 use crate::error::{EngineError, Result};
+use crate::functions::{self, FunctionTable};
+use crate::gates::{self, GateKind};
+use crate::kernels_cpu;
+use crate::operators::PauliString;
+use crate::plugin::KernelRegistry;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Shape information for expressions
 #[derive(Debug, Clone, PartialEq)]
@@ -18,40 +27,102 @@ pub enum Shape {
 /// Type-checked AST with shape annotations
 #[derive(Debug, Clone)]
 pub struct TypedAst {
-    pub ast: Ast,
+    /// Shared by the passes after type checking, which clone it cheaply
+    pub ast: Arc<Ast>,
     pub shapes: HashMap<String, Shape>,
+    /// Subsystem dimensions of the declared operators built on composite
+    /// spaces, with `tensor` or `embed`, which `ptrace` and `name[k]`
+    /// split them into
+    pub subsystems: HashMap<String, Vec<usize>>,
 }
 
 /// Type checker for quantum DSL
 pub struct TypeChecker {
     shapes: HashMap<String, Shape>,
+    subsystems: HashMap<String, Vec<usize>>,
+    /// User-defined functions, whose calls take the shape of their inlined
+    /// bodies
+    functions: FunctionTable,
+    /// Plugin kernels, whose calls take the shape the kernel reports
+    kernels: Arc<KernelRegistry>,
+    /// Innermost expression whose shape could not be inferred, for locating
+    /// the error in the source
+    failed_expr: RefCell<Option<Expr>>,
 }
 
 impl TypeChecker {
     pub fn new() -> Self {
-        let mut shapes = HashMap::new();
+        Self::with_kernels(Arc::new(KernelRegistry::new()))
+    }
 
-        // Predefined Pauli matrices (2x2)
-        shapes.insert("sigma_x".to_string(), Shape::Matrix(2, 2));
-        shapes.insert("sigma_y".to_string(), Shape::Matrix(2, 2));
-        shapes.insert("sigma_z".to_string(), Shape::Matrix(2, 2));
-        shapes.insert("identity".to_string(), Shape::Matrix(2, 2));
+    /// Create a type checker that gives calls to registered plugin kernels
+    /// the shape of their [`Kernel::output_shape`](crate::plugin::Kernel::output_shape)
+    pub fn with_kernels(kernels: Arc<KernelRegistry>) -> Self {
+        let shapes = builtins::builtin_registry()
+            .iter()
+            .filter_map(|builtin| match &builtin.shape {
+                ShapeRule::Fixed(shape) => Some((builtin.name.to_string(), shape.clone())),
+                _ => None,
+            })
+            .collect();
 
-        TypeChecker { shapes }
+        TypeChecker {
+            shapes,
+            subsystems: HashMap::new(),
+            functions: FunctionTable::new(),
+            kernels,
+            failed_expr: RefCell::new(None),
+        }
     }
 
     /// Type check the entire AST
+    ///
+    /// Errors in parsed programs carry the line and column of the
+    /// expression, or else the statement, they arose in.
     pub fn check(&mut self, ast: &Ast) -> Result<TypedAst> {
-        for stmt in &ast.statements {
-            self.check_statement(stmt)?;
+        for (index, stmt) in ast.statements.iter().enumerate() {
+            self.failed_expr.take();
+            self.check_statement(stmt).map_err(|e| {
+                ast.source_map
+                    .locate(index, self.failed_expr.take().as_ref(), e)
+            })?;
         }
 
         Ok(TypedAst {
-            ast: ast.clone(),
+            ast: Arc::new(ast.clone()),
             shapes: self.shapes.clone(),
+            subsystems: self.subsystems.clone(),
         })
     }
 
+    /// Type check every statement of `ast`, carrying on past errors
+    ///
+    /// Returns the typed AST with each error and the position it arose at,
+    /// in statement order. A statement that fails declares nothing, so
+    /// later uses of its name may fail too.
+    pub fn check_all(&mut self, ast: &Ast) -> (TypedAst, Vec<(EngineError, Option<Span>)>) {
+        let mut errors = Vec::new();
+        for (index, stmt) in ast.statements.iter().enumerate() {
+            self.failed_expr.take();
+            if let Err(e) = self.check_statement(stmt) {
+                let span = ast.source_map.span(index, self.failed_expr.take().as_ref());
+                errors.push((e, span));
+            }
+        }
+
+        let typed = TypedAst {
+            ast: Arc::new(ast.clone()),
+            shapes: self.shapes.clone(),
+            subsystems: self.subsystems.clone(),
+        };
+        (typed, errors)
+    }
+
+    /// Shape of `expr` among the declarations checked so far
+    pub fn shape_of(&self, expr: &Expr) -> Result<Shape> {
+        self.infer_shape(expr)
+    }
+
     fn check_statement(&mut self, stmt: &Statement) -> Result<()> {
         match stmt {
             Statement::ConstDecl { name, .. } => {
@@ -62,23 +133,51 @@ impl TypeChecker {
                 self.shapes.insert(name.clone(), Shape::Scalar);
                 Ok(())
             }
+            Statement::ParamDecl { name, range } => {
+                if let Some((lo, hi)) = range {
+                    if lo > hi {
+                        return Err(EngineError::type_error(format!(
+                            "Parameter '{}' has an empty range [{}, {}]",
+                            name, lo, hi
+                        )));
+                    }
+                }
+                self.shapes.insert(name.clone(), Shape::Scalar);
+                Ok(())
+            }
             Statement::MatrixDecl { name, value } => {
                 let shape = self.infer_matrix_shape(value)?;
                 self.shapes.insert(name.clone(), shape);
                 Ok(())
             }
-            Statement::FunctionDef { name, params, body } => {
-                // For now, assume functions return scalars
-                // More sophisticated type inference could be added
-                self.shapes.insert(name.clone(), Shape::Scalar);
-                self.infer_expr_shape(body)?;
+            Statement::OperatorDecl { name, value } => {
+                let shape = self.infer_expr_shape(value)?;
+                self.shapes.insert(name.clone(), shape);
                 Ok(())
             }
+            Statement::FunctionDef { name, params, body } => {
+                // The shape of a call depends on its arguments, so a
+                // definition is checked only for the names it refers to
+                self.functions.define(name, params, body)?;
+                let mut free = Vec::new();
+                functions::free_names(&self.functions.get(name).expect("just defined").body, params, &mut free);
+                match free.into_iter().find(|&free| !self.is_known(free)) {
+                    Some(unknown) => Err(EngineError::type_error(format!(
+                        "Unknown identifier '{}' in function '{}'",
+                        unknown, name
+                    ))),
+                    None => Ok(()),
+                }
+            }
             Statement::HamiltonianDef { name, expr, .. } => {
                 let shape = self.infer_expr_shape(expr)?;
                 match shape {
                     Shape::Matrix(n, m) if n == m => {
                         self.shapes.insert(name.clone(), shape);
+                        match self.subsystems_of(expr) {
+                            Some(dims) if dims.len() > 1 => self.subsystems.insert(name.clone(), dims),
+                            _ => self.subsystems.remove(name),
+                        };
                         Ok(())
                     }
                     _ => Err(EngineError::type_error(format!(
@@ -87,14 +186,79 @@ impl TypeChecker {
                     ))),
                 }
             }
-            Statement::MeasurementDef { name, spec } => {
+            Statement::MeasurementDef { spec, .. } => {
                 self.check_measurement_spec(spec)?;
                 Ok(())
             }
+            Statement::ChannelDef { name, kraus } => {
+                let mut dim = None;
+                for op in kraus {
+                    match self.infer_expr_shape(op)? {
+                        Shape::Matrix(n, m) if n == m && dim.unwrap_or(n) == n => dim = Some(n),
+                        shape => {
+                            return Err(EngineError::type_error(format!(
+                                "Kraus operators of channel '{}' must be square matrices of one dimension, got {:?}",
+                                name, shape
+                            )))
+                        }
+                    }
+                }
+                Ok(())
+            }
             Statement::Experiment { body, .. } => self.check_experiment_body(body),
+            Statement::Sweep { param, .. } => Err(EngineError::type_error(format!(
+                "Sweep over '{}' must be expanded with sweep::expand_sweeps before checking",
+                param
+            ))),
+            // Goals are about abstract operators; the validator hands them
+            // to the prover
+            Statement::Assume(_) | Statement::Prove(_) => Ok(()),
         }
     }
 
+    /// Subsystem dimensions of the composite space the square matrix `expr`
+    /// acts on, when it is built from `tensor` or `embed`, directly or
+    /// through declared operators; `None` when the structure is unknown
+    pub fn subsystems_of(&self, expr: &Expr) -> Option<Vec<usize>> {
+        // A factor of a tensor product is one subsystem unless composite
+        let factors = |expr: &Expr| match self.subsystems_of(expr) {
+            Some(dims) => Some(dims),
+            None => match self.infer_shape(expr).ok()? {
+                Shape::Matrix(n, m) if n == m => Some(vec![n]),
+                _ => None,
+            },
+        };
+        let either = |a: &Expr, b: &Expr| match (self.subsystems_of(a), self.subsystems_of(b)) {
+            (Some(left), Some(right)) if left != right => None,
+            (left, right) => left.or(right),
+        };
+        match expr {
+            Expr::Identifier(name) => self.subsystems.get(name).cloned(),
+            Expr::Tensor(a, b) => {
+                let mut dims = factors(a)?;
+                dims.extend(factors(b)?);
+                Some(dims)
+            }
+            Expr::FuncCall { name, args } if name == "embed" => embedding_args(args).ok().map(|(_, dims)| dims),
+            Expr::FuncCall { name, .. } if self.functions.contains(name) => {
+                self.subsystems_of(&self.functions.inline(expr).ok()?)
+            }
+            Expr::Mul(a, b) => match (self.infer_shape(a).ok()?, self.infer_shape(b).ok()?) {
+                (Shape::Scalar, _) => self.subsystems_of(b),
+                (_, Shape::Scalar) => self.subsystems_of(a),
+                _ => either(a, b),
+            },
+            Expr::Add(a, b) | Expr::Sub(a, b) | Expr::Commutator(a, b) | Expr::AntiCommutator(a, b) => either(a, b),
+            Expr::Div(a, _) | Expr::Pow(a, _) | Expr::Dagger(a) | Expr::Expm(a) => self.subsystems_of(a),
+            _ => None,
+        }
+    }
+
+    /// Whether `name` is declared, built in or a Pauli string
+    fn is_known(&self, name: &str) -> bool {
+        self.shapes.contains_key(name) || name.parse::<PauliString>().is_ok()
+    }
+
     fn infer_matrix_shape(&self, mat: &MatrixLiteral) -> Result<Shape> {
         if mat.rows.is_empty() {
             return Err(EngineError::type_error("Empty matrix"));
@@ -119,121 +283,114 @@ impl TypeChecker {
     }
 
     fn infer_expr_shape(&self, expr: &Expr) -> Result<Shape> {
+        self.infer_shape(expr).inspect_err(|_| {
+            self.failed_expr
+                .borrow_mut()
+                .get_or_insert_with(|| expr.clone());
+        })
+    }
+
+    fn infer_shape(&self, expr: &Expr) -> Result<Shape> {
         match expr {
             Expr::Number(_) | Expr::ComplexNumber(_) => Ok(Shape::Scalar),
-            Expr::Identifier(name) => self
-                .shapes
-                .get(name)
-                .cloned()
-                .ok_or_else(|| EngineError::type_error(format!("Unknown identifier: {}", name))),
+            Expr::Identifier(name) => match (self.shapes.get(name), name.parse::<PauliString>()) {
+                (Some(shape), _) => Ok(shape.clone()),
+                // Undeclared words over I, X, Y, Z are Pauli strings
+                (None, Ok(pauli)) => {
+                    let dim = 1usize << pauli.n_qubits();
+                    Ok(Shape::Matrix(dim, dim))
+                }
+                (None, Err(_)) => Err(EngineError::type_error(format!("Unknown identifier: {}", name))),
+            },
             Expr::Matrix(mat) => self.infer_matrix_shape(mat),
             Expr::Vector(vec) => Ok(Shape::Vector(vec.elements.len())),
-            Expr::Add(left, right) | Expr::Sub(left, right) => {
-                let left_shape = self.infer_expr_shape(left)?;
-                let right_shape = self.infer_expr_shape(right)?;
-                if left_shape != right_shape {
+            Expr::FuncCall { name, args } if name == "ptrace" => {
+                let (subsystem, dims) = partial_trace_args(args)?;
+                let n = match self.infer_expr_shape(&args[0])? {
+                    Shape::Matrix(n, m) if n == m => n,
+                    _ => return Err(EngineError::type_error("ptrace requires a square matrix")),
+                };
+                let dims = match subsystem_dims(&args[0], dims, &self.subsystems) {
+                    Some(dims) => dims,
+                    None => kernels_cpu::qubit_dims(n)?,
+                };
+                let total: usize = dims.iter().product();
+                if total != n {
                     return Err(EngineError::dimension_mismatch(
-                        format!("{:?}", left_shape),
-                        format!("{:?}", right_shape),
+                        format!("{}x{} matrix for subsystem dimensions {:?}", total, total, dims),
+                        format!("{}x{}", n, n),
                     ));
                 }
-                Ok(left_shape)
-            }
-            Expr::Mul(left, right) => {
-                let left_shape = self.infer_expr_shape(left)?;
-                let right_shape = self.infer_expr_shape(right)?;
-
-                match (left_shape, right_shape) {
-                    (Shape::Scalar, s) | (s, Shape::Scalar) => Ok(s),
-                    (Shape::Matrix(n1, m1), Shape::Matrix(n2, m2)) => {
-                        if m1 != n2 {
-                            return Err(EngineError::dimension_mismatch(
-                                format!("{}x{}", n1, m1),
-                                format!("{}x{}", n2, m2),
-                            ));
-                        }
-                        Ok(Shape::Matrix(n1, m2))
-                    }
-                    (Shape::Matrix(n, m), Shape::Vector(v)) => {
-                        if m != v {
-                            return Err(EngineError::dimension_mismatch(
-                                format!("{}x{} matrix", n, m),
-                                format!("vector of length {}", v),
-                            ));
-                        }
-                        Ok(Shape::Vector(n))
-                    }
-                    _ => Err(EngineError::type_error(
-                        "Invalid multiplication of incompatible types",
-                    )),
-                }
-            }
-            Expr::Div(left, right) => {
-                let left_shape = self.infer_expr_shape(left)?;
-                let right_shape = self.infer_expr_shape(right)?;
-                match right_shape {
-                    Shape::Scalar => Ok(left_shape),
-                    _ => Err(EngineError::type_error("Can only divide by scalar")),
+                match dims.get(subsystem) {
+                    Some(&d) => Ok(Shape::Matrix(d, d)),
+                    None => Err(EngineError::type_error(format!(
+                        "ptrace subsystem {} out of range for {} subsystems",
+                        subsystem,
+                        dims.len()
+                    ))),
                 }
             }
-            Expr::Pow(base, _exp) => self.infer_expr_shape(base),
-            Expr::Dagger(inner) => {
-                let shape = self.infer_expr_shape(inner)?;
-                match shape {
-                    Shape::Matrix(n, m) => Ok(Shape::Matrix(m, n)),
-                    Shape::Vector(n) => Ok(Shape::Vector(n)),
-                    Shape::Scalar => Ok(Shape::Scalar),
+            Expr::FuncCall { name, args } if name == "embed" => {
+                let (subsystem, dims) = embedding_args(args)?;
+                let d = dims[subsystem];
+                match self.infer_expr_shape(&args[0])? {
+                    Shape::Matrix(n, m) if n == m && n == d => {
+                        let total = dims.iter().product();
+                        Ok(Shape::Matrix(total, total))
+                    }
+                    // A type error rather than a dimension mismatch, so it
+                    // is located at the placement
+                    Shape::Matrix(n, m) if n == m => Err(EngineError::type_error(format!(
+                        "{}x{} operator placed on subsystem {} of {:?}, which is {}-dimensional",
+                        n, n, subsystem, dims, d
+                    ))),
+                    _ => Err(EngineError::type_error("embed requires a square matrix")),
                 }
             }
-            Expr::Trace(inner) => {
-                let shape = self.infer_expr_shape(inner)?;
-                match shape {
-                    Shape::Matrix(n, m) if n == m => Ok(Shape::Scalar),
-                    _ => Err(EngineError::type_error(
-                        "Trace requires a square matrix",
-                    )),
-                }
+            // Qudit operators, whose dimension the arguments give
+            Expr::FuncCall { name, args } if is_operator(name) => {
+                let builtin = builtins::lookup(name).expect("checked by is_operator");
+                let n = builtin.operator(args)?.nrows();
+                Ok(Shape::Matrix(n, n))
             }
-            Expr::Tensor(left, right) => {
-                let left_shape = self.infer_expr_shape(left)?;
-                let right_shape = self.infer_expr_shape(right)?;
-
-                match (left_shape, right_shape) {
-                    (Shape::Matrix(n1, m1), Shape::Matrix(n2, m2)) => {
-                        Ok(Shape::Matrix(n1 * n2, m1 * m2))
-                    }
-                    (Shape::Vector(n1), Shape::Vector(n2)) => Ok(Shape::Vector(n1 * n2)),
-                    _ => Err(EngineError::type_error(
-                        "Tensor product requires compatible types",
-                    )),
+            Expr::FuncCall { name, args } if name == "eigenstate" => {
+                let level = eigenstate_level(args)?;
+                match self.infer_expr_shape(&args[0])? {
+                    Shape::Matrix(n, m) if n == m && level < n => Ok(Shape::Vector(n)),
+                    Shape::Matrix(n, m) if n == m => Err(EngineError::type_error(format!(
+                        "Eigenstate {} out of range for {} levels",
+                        level, n
+                    ))),
+                    _ => Err(EngineError::type_error("eigenstate requires a square matrix")),
                 }
             }
-            Expr::Commutator(left, right) | Expr::AntiCommutator(left, right) => {
-                let left_shape = self.infer_expr_shape(left)?;
-                let right_shape = self.infer_expr_shape(right)?;
-
-                match (left_shape, right_shape) {
-                    (Shape::Matrix(n1, m1), Shape::Matrix(n2, m2)) if n1 == m1 && n2 == m2 && n1 == n2 => {
-                        Ok(Shape::Matrix(n1, m1))
-                    }
-                    _ => Err(EngineError::type_error(
-                        "Commutator requires square matrices of same dimension",
-                    )),
-                }
+            // The shape of the body with the arguments in place
+            Expr::FuncCall { name, .. } if self.functions.contains(name) => {
+                let inlined = self.functions.inline(expr)?;
+                self.infer_shape(&inlined).inspect_err(|_| {
+                    // Inside the body is nowhere in the source; point at the call
+                    *self.failed_expr.borrow_mut() = Some(expr.clone());
+                })
             }
-            Expr::Expm(inner) | Expr::Sqrt(inner) => {
-                let shape = self.infer_expr_shape(inner)?;
-                match shape {
-                    Shape::Matrix(n, m) if n == m => Ok(shape),
-                    Shape::Scalar => Ok(Shape::Scalar),
-                    _ => Err(EngineError::type_error("Matrix function requires square matrix or scalar")),
-                }
+            // Plugin kernels, unless a builtin of the same name lowers first
+            Expr::FuncCall { name, args } if self.kernels.contains(name) && builtins::builtin_of(expr).is_none() => {
+                let shapes = args
+                    .iter()
+                    .map(|arg| self.infer_expr_shape(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                self.kernels.output_shape(name, &shapes)
             }
-            Expr::Sin(inner) | Expr::Cos(inner) | Expr::Exp(inner) => {
-                self.infer_expr_shape(inner)?;
-                Ok(Shape::Scalar)
+            Expr::FuncCall { .. } if builtins::builtin_of(expr).is_none() => Ok(Shape::Scalar), // Simplified
+            // Operators and the other builtin functions
+            _ => {
+                let (builtin, args) = builtins::builtin_of(expr)
+                    .ok_or_else(|| EngineError::Internal(format!("No builtin applies {:?}", expr)))?;
+                let shapes = args
+                    .into_iter()
+                    .map(|arg| self.infer_expr_shape(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                builtin.shape.apply(&shapes)
             }
-            Expr::FuncCall { .. } => Ok(Shape::Scalar), // Simplified
         }
     }
 
@@ -273,6 +430,7 @@ impl TypeChecker {
                 }
                 Ok(())
             }
+            MeasurementSpec::Basis { basis, dim } => bases::check_basis_dim(*basis, *dim),
         }
     }
 
@@ -294,10 +452,212 @@ impl TypeChecker {
                         }
                     }
                 }
+                // Resolved against the other experiments during lowering
+                StateSpec::From(_) => {}
+                StateSpec::Eigenstate { hamiltonian, level } => match self.shapes.get(hamiltonian) {
+                    Some(Shape::Matrix(n, m)) if n == m && level < n => {}
+                    Some(Shape::Matrix(n, m)) if n == m => {
+                        return Err(EngineError::type_error(format!(
+                            "Eigenstate {} out of range for the {} levels of '{}'",
+                            level, n, hamiltonian
+                        )))
+                    }
+                    Some(shape) => {
+                        return Err(EngineError::type_error(format!(
+                            "Eigenstates need a square matrix, but '{}' is {:?}",
+                            hamiltonian, shape
+                        )))
+                    }
+                    None => {
+                        return Err(EngineError::type_error(format!("Unknown identifier: {}", hamiltonian)))
+                    }
+                },
+            }
+        }
+
+        let dim = body
+            .evolution
+            .as_ref()
+            .and_then(|evolution| match self.shapes.get(&evolution.hamiltonian_name) {
+                Some(Shape::Matrix(n, _)) => Some(*n),
+                _ => None,
+            });
+        let state_dim = match &body.init {
+            Some(StateSpec::Ket(vec)) => Some(vec.elements.len()),
+            Some(StateSpec::Rho(mat)) => Some(mat.rows.len()),
+            Some(StateSpec::Eigenstate { hamiltonian, .. }) => match self.shapes.get(hamiltonian) {
+                Some(Shape::Matrix(n, _)) => Some(*n),
+                _ => None,
+            },
+            _ => None,
+        }
+        .or(dim);
+        for application in &body.gates {
+            self.check_gate(application, state_dim)?;
+        }
+        for observable in &body.observables {
+            match self.infer_expr_shape(&observable.expr)? {
+                Shape::Matrix(n, m) if n == m && dim.is_none_or(|d| d == n) => {}
+                shape => {
+                    return Err(EngineError::type_error(format!(
+                        "Observable <{}> must be a square matrix of the Hamiltonian's dimension, got {:?}",
+                        observable.label, shape
+                    )))
+                }
             }
         }
         Ok(())
     }
+
+    /// The gate of an `apply` statement and its targets, against a register
+    /// of dimension `state_dim` where that is known
+    fn check_gate(&self, application: &GateApplication, state_dim: Option<usize>) -> Result<()> {
+        let name = &application.gate;
+        let gate_dim = match (gates::lookup(name), &application.angle) {
+            (Some(gate), Some(angle)) if gate.kind != GateKind::Fixed => {
+                match self.infer_expr_shape(angle)? {
+                    Shape::Scalar => 1 << gate.qubits,
+                    shape => {
+                        return Err(EngineError::type_error(format!(
+                            "The angle of {} must be a scalar, got {:?}",
+                            name, shape
+                        )))
+                    }
+                }
+            }
+            (Some(gate), None) if gate.kind == GateKind::Fixed => 1 << gate.qubits,
+            (Some(_), Some(_)) => return Err(EngineError::type_error(format!("Gate {} takes no angle", name))),
+            (Some(_), None) => {
+                return Err(EngineError::type_error(format!(
+                    "Gate {} needs an angle, as in {}(theta)",
+                    name, name
+                )))
+            }
+            (None, Some(_)) => {
+                return Err(EngineError::type_error(format!(
+                    "Only rotation gates take an angle, not {}",
+                    name
+                )))
+            }
+            (None, None) => match self.infer_shape(&Expr::Identifier(name.clone())) {
+                Ok(Shape::Matrix(n, m)) if n == m && n >= 2 && n.is_power_of_two() => n,
+                Ok(shape) => {
+                    return Err(EngineError::type_error(format!(
+                        "Gate {} must be a square matrix of dimension 2^k, got {:?}",
+                        name, shape
+                    )))
+                }
+                Err(_) => return Err(EngineError::type_error(format!("Unknown gate: {}", name))),
+            },
+        };
+
+        let qubits = gate_dim.trailing_zeros() as usize;
+        let targets = &application.targets;
+        if !targets.is_empty() && targets.len() != qubits {
+            return Err(EngineError::type_error(format!(
+                "Gate {} acts on {} qubit(s), but is applied to {:?}",
+                name, qubits, targets
+            )));
+        }
+        if let Some(dim) = state_dim {
+            if targets.is_empty() && gate_dim != dim {
+                return Err(EngineError::dimension_mismatch(
+                    format!("{}x{} gate on the whole register", dim, dim),
+                    format!("{}x{}", gate_dim, gate_dim),
+                ));
+            }
+            gates::check_targets(targets, gates::register_qubits(dim)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Kept subsystem and subsystem dimensions of a
+/// `ptrace(rho, subsystem[, vec(d1, d2, ...)])` call; both must be integer
+/// literals, and without dimensions `rho` is split into qubits
+pub(crate) fn partial_trace_args(args: &[Expr]) -> Result<(usize, Option<Vec<usize>>)> {
+    let index = |expr: &Expr, what: &str| integer_literal(expr, "ptrace", what);
+
+    match args {
+        [_, subsystem] => Ok((index(subsystem, "subsystem")?, None)),
+        [_, subsystem, Expr::Vector(dims)] => {
+            let dims = dims
+                .elements
+                .iter()
+                .map(|d| index(d, "dimension"))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((index(subsystem, "subsystem")?, Some(dims)))
+        }
+        _ => Err(EngineError::type_error(
+            "ptrace expects (rho, subsystem) or (rho, subsystem, vec(d1, d2, ...))",
+        )),
+    }
+}
+
+/// Subsystem and subsystem dimensions of an
+/// `embed(A, subsystem, vec(d1, d2, ...))` call, which must be integer
+/// literals naming one of the subsystems
+pub(crate) fn embedding_args(args: &[Expr]) -> Result<(usize, Vec<usize>)> {
+    let [_, subsystem, Expr::Vector(dims)] = args else {
+        return Err(EngineError::type_error("embed expects (A, subsystem, vec(d1, d2, ...))"));
+    };
+    let subsystem = integer_literal(subsystem, "embed", "subsystem")?;
+    let dims = dims
+        .elements
+        .iter()
+        .map(|d| match integer_literal(d, "embed", "dimension")? {
+            0 => Err(EngineError::type_error("embed dimensions must be positive")),
+            d => Ok(d),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if subsystem >= dims.len() {
+        return Err(EngineError::type_error(format!(
+            "embed subsystem {} out of range for {} subsystems",
+            subsystem,
+            dims.len()
+        )));
+    }
+    Ok((subsystem, dims))
+}
+
+/// Dimensions `ptrace` splits `operand` into: those the call gives, else
+/// those of the declared composite `operand` names; `None` leaves it to
+/// be split into qubits
+pub(crate) fn subsystem_dims(
+    operand: &Expr,
+    dims: Option<Vec<usize>>,
+    subsystems: &HashMap<String, Vec<usize>>,
+) -> Option<Vec<usize>> {
+    dims.or_else(|| match operand {
+        Expr::Identifier(name) => subsystems.get(name).cloned(),
+        _ => None,
+    })
+}
+
+fn integer_literal(expr: &Expr, call: &str, what: &str) -> Result<usize> {
+    match expr {
+        Expr::Number(x) if *x >= 0.0 && x.fract() == 0.0 => Ok(*x as usize),
+        _ => Err(EngineError::type_error(format!(
+            "{} {} must be a non-negative integer literal",
+            call, what
+        ))),
+    }
+}
+
+/// Whether `name` is a builtin such as `spin_x` that builds an operator from
+/// literal arguments
+pub(crate) fn is_operator(name: &str) -> bool {
+    builtins::lookup(name).is_some_and(|builtin| builtin.shape == ShapeRule::Operator)
+}
+
+/// Level of an `eigenstate(H, k)` call, which must be an integer literal
+pub(crate) fn eigenstate_level(args: &[Expr]) -> Result<usize> {
+    match args {
+        [_, Expr::Number(k)] if *k >= 0.0 && k.fract() == 0.0 => Ok(*k as usize),
+        _ => Err(EngineError::type_error(
+            "eigenstate level must be a non-negative integer literal",
+        )),
+    }
 }
 
 impl Default for TypeChecker {
@@ -351,4 +711,68 @@ mod tests {
         let shape = checker.infer_expr_shape(&expr).unwrap();
         assert_eq!(shape, Shape::Matrix(1, 1));
     }
+
+    #[test]
+    fn test_partial_trace_shapes() {
+        let mut checker = TypeChecker::new();
+        checker.shapes.insert("rho".to_string(), Shape::Matrix(6, 6));
+        checker.shapes.insert("psi".to_string(), Shape::Matrix(8, 8));
+        let ptrace = |args: Vec<Expr>| Expr::FuncCall {
+            name: "ptrace".to_string(),
+            args,
+        };
+        let rho = || Expr::Identifier("rho".to_string());
+        let dims = |ds: &[f64]| {
+            Expr::Vector(VectorLiteral {
+                elements: ds.iter().map(|&d| Expr::Number(d)).collect(),
+            })
+        };
+
+        let reduced = ptrace(vec![rho(), Expr::Number(1.0), dims(&[2.0, 3.0])]);
+        assert_eq!(checker.infer_expr_shape(&reduced).unwrap(), Shape::Matrix(3, 3));
+        // Without dimensions, subsystems are qubits
+        let qubit = ptrace(vec![Expr::Identifier("psi".to_string()), Expr::Number(2.0)]);
+        assert_eq!(checker.infer_expr_shape(&qubit).unwrap(), Shape::Matrix(2, 2));
+
+        for bad in [
+            ptrace(vec![rho(), Expr::Number(0.0)]),
+            ptrace(vec![rho(), Expr::Number(2.0), dims(&[2.0, 3.0])]),
+            ptrace(vec![rho(), Expr::Number(0.0), dims(&[2.0, 2.0])]),
+            ptrace(vec![rho(), Expr::Number(0.5), dims(&[2.0, 3.0])]),
+        ] {
+            assert!(checker.infer_expr_shape(&bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn test_function_call_shapes_follow_arguments() {
+        let ast = crate::parser::parse_dsl(
+            "matrix A = [1, 0, 0, 0; 0, 1, 0, 0; 0, 0, 1, 0; 0, 0, 0, 1];
+             func rotation(theta) = expm(-i * theta * sigma_x / 2);
+             func conj(U, B) = U * B * dagger(U);
+             Hamiltonian H1 = conj(rotation(0.3), sigma_z);
+             Hamiltonian H2 = conj(tensor(sigma_x, sigma_x), A);",
+        )
+        .unwrap();
+        let typed = TypeChecker::new().check(&ast).unwrap();
+        assert_eq!(typed.shapes["H1"], Shape::Matrix(2, 2));
+        assert_eq!(typed.shapes["H2"], Shape::Matrix(4, 4));
+
+        // Mismatched arguments fail at the call
+        let ast = crate::parser::parse_dsl(
+            "func conj(U, B) = U * B * dagger(U);\nHamiltonian H = sigma_z + conj(sigma_x, vec(1, 0));",
+        )
+        .unwrap();
+        let err = TypeChecker::new().check(&ast).unwrap_err().to_string();
+        assert!(err.contains("line 2, column 27"), "{}", err);
+
+        for bad in [
+            "func f(x) = x * omega;",
+            "func f(x) = x; Hamiltonian H = f(sigma_x, sigma_z);",
+        ] {
+            let ast = crate::parser::parse_dsl(bad).unwrap();
+            assert!(TypeChecker::new().check(&ast).is_err(), "{}", bad);
+        }
+    }
 }
@@ -1,13 +1,22 @@
 //! Quantum validator for enforcing quantum mechanical constraints
 
 use crate::ast::*;
+use crate::builtins;
 use crate::error::{EngineError, Result};
-use crate::typechecker::TypedAst;
+use crate::functions::FunctionTable;
+use crate::gates;
+use crate::kernels_cpu;
+use crate::operators::PauliString;
+use crate::typechecker::{embedding_args, is_operator, partial_trace_args, subsystem_dims, TypedAst};
 use crate::VALIDATION_TOL;
 use ndarray::{Array1, Array2};
 use ndarray_linalg::{Eigh, UPLO};
 use num_complex::Complex64;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
 /// Validated AST with quantum constraints verified
 #[derive(Debug, Clone)]
@@ -22,124 +31,444 @@ pub struct ValidationResults {
     pub hermitian_operators: HashMap<String, bool>,
     pub psd_operators: HashMap<String, bool>,
     pub trace_values: HashMap<String, f64>,
+    /// Channels whose Kraus operators were checked to satisfy Σ K†K = I
+    pub cptp_channels: HashMap<String, bool>,
+    /// Constraints found violated by more than the tolerance, in the order
+    /// they were checked
+    pub violations: Vec<Violation>,
+    /// `prove` goals the prover did not refute, in source order
+    pub goals: Vec<GoalOutcome>,
+}
+
+impl ValidationResults {
+    /// Violations downgraded to warnings by [`ValidatorConfig::warn_only`]
+    pub fn warnings(&self) -> impl Iterator<Item = &Violation> {
+        self.violations.iter().filter(|v| v.warning)
+    }
+}
+
+/// What the prover made of a `prove` statement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoalOutcome {
+    pub goal: String,
+    pub status: GoalStatus,
+}
+
+/// Refuted goals are errors, so a recorded goal is either proven or left
+/// open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum GoalStatus {
+    Proven,
+    Undecided { reason: String },
+}
+
+/// Constraint checked by the validator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Constraint {
+    /// A = A†, of Hamiltonians, projectors and density matrices
+    Hermiticity,
+    /// No negative eigenvalues, of POVM effects and density matrices
+    Positivity,
+    /// Unit trace of density matrices
+    Trace,
+    /// Unit norm of initial kets
+    Normalization,
+    /// P² = P of projectors
+    Idempotence,
+    /// Projectors or POVM effects summing to the identity
+    Completeness,
+    /// Σ K†K = I of channels
+    TracePreservation,
+    /// U†U = I of declared matrices applied as gates
+    Unitarity,
+}
+
+impl Constraint {
+    pub const ALL: [Constraint; 8] = [
+        Constraint::Hermiticity,
+        Constraint::Positivity,
+        Constraint::Trace,
+        Constraint::Normalization,
+        Constraint::Idempotence,
+        Constraint::Completeness,
+        Constraint::TracePreservation,
+        Constraint::Unitarity,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Constraint::Hermiticity => "hermiticity",
+            Constraint::Positivity => "positivity",
+            Constraint::Trace => "trace",
+            Constraint::Normalization => "normalization",
+            Constraint::Idempotence => "idempotence",
+            Constraint::Completeness => "completeness",
+            Constraint::TracePreservation => "trace_preservation",
+            Constraint::Unitarity => "unitarity",
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Constraint {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Constraint::ALL
+            .into_iter()
+            .find(|c| c.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Constraint::ALL.iter().map(|c| c.as_str()).collect();
+                EngineError::validation_error(format!(
+                    "Unknown constraint '{}', expected one of {}",
+                    s,
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// A constraint that failed by more than the tolerance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub constraint: Constraint,
+    /// What was checked, such as `Hamiltonian 'H'`
+    pub subject: String,
+    /// Measured deviation from the constraint
+    pub deviation: f64,
+    pub tolerance: f64,
+    /// Whether the violation was reported as a warning rather than an error
+    pub warning: bool,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} violates {}: deviation {:.2e} exceeds tolerance {:.2e}",
+            self.subject, self.constraint, self.deviation, self.tolerance
+        )
+    }
+}
+
+/// Tolerance and strictness of quantum validation
+///
+/// The default matches [`QuantumValidator::new`]: every constraint must
+/// hold to within [`VALIDATION_TOL`]. Noisy inputs, such as projectors
+/// estimated from experimental data, can be accepted with a larger `tol`
+/// or by listing the constraints they break in `warn_only`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidatorConfig {
+    /// Largest deviation from a constraint that still satisfies it
+    pub tol: f64,
+    /// Report every violation as an error, ignoring `warn_only`
+    pub strict_mode: bool,
+    /// Constraints whose violations are recorded as warnings in
+    /// [`ValidationResults::violations`] instead of failing validation
+    pub warn_only: HashSet<Constraint>,
+}
+
+impl Default for ValidatorConfig {
+    fn default() -> Self {
+        ValidatorConfig {
+            tol: VALIDATION_TOL,
+            strict_mode: false,
+            warn_only: HashSet::new(),
+        }
+    }
+}
+
+impl ValidatorConfig {
+    /// Check that the tolerance is a finite, non-negative number
+    pub fn validate(&self) -> Result<()> {
+        if !self.tol.is_finite() || self.tol < 0.0 {
+            return Err(EngineError::validation_error(format!(
+                "Validation tolerance must be finite and non-negative, got {}",
+                self.tol
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether violations of `constraint` are warnings
+    pub fn warns(&self, constraint: Constraint) -> bool {
+        !self.strict_mode && self.warn_only.contains(&constraint)
+    }
 }
 
 /// Quantum validator
 pub struct QuantumValidator {
     constants: HashMap<String, f64>,
     matrices: HashMap<String, Array2<Complex64>>,
+    /// Subsystem dimensions of composite operators, from the type checker
+    subsystems: HashMap<String, Vec<usize>>,
+    functions: FunctionTable,
+    /// Innermost expression that could not be evaluated, for locating the
+    /// error in the source
+    failed_expr: RefCell<Option<Expr>>,
+    /// Identities stated by `assume`, available to later `prove` goals
+    hypotheses: Vec<Assumption>,
+    config: ValidatorConfig,
 }
 
 impl QuantumValidator {
     pub fn new() -> Self {
-        let mut matrices = HashMap::new();
-
-        // Predefined Pauli matrices
-        matrices.insert(
-            "sigma_x".to_string(),
-            Array2::from_shape_vec((2, 2), vec![
-                Complex64::new(0.0, 0.0),
-                Complex64::new(1.0, 0.0),
-                Complex64::new(1.0, 0.0),
-                Complex64::new(0.0, 0.0),
-            ])
-            .unwrap(),
-        );
-
-        matrices.insert(
-            "sigma_y".to_string(),
-            Array2::from_shape_vec((2, 2), vec![
-                Complex64::new(0.0, 0.0),
-                Complex64::new(0.0, -1.0),
-                Complex64::new(0.0, 1.0),
-                Complex64::new(0.0, 0.0),
-            ])
-            .unwrap(),
-        );
-
-        matrices.insert(
-            "sigma_z".to_string(),
-            Array2::from_shape_vec((2, 2), vec![
-                Complex64::new(1.0, 0.0),
-                Complex64::new(0.0, 0.0),
-                Complex64::new(0.0, 0.0),
-                Complex64::new(-1.0, 0.0),
-            ])
-            .unwrap(),
-        );
-
-        matrices.insert(
-            "identity".to_string(),
-            Array2::from_shape_vec((2, 2), vec![
-                Complex64::new(1.0, 0.0),
-                Complex64::new(0.0, 0.0),
-                Complex64::new(0.0, 0.0),
-                Complex64::new(1.0, 0.0),
-            ])
-            .unwrap(),
-        );
+        Self::with_config(ValidatorConfig::default())
+    }
+
+    /// A validator applying the tolerance and strictness of `config`
+    pub fn with_config(config: ValidatorConfig) -> Self {
+        let matrices = builtins::builtin_registry()
+            .iter()
+            .filter_map(|builtin| Some((builtin.name.to_string(), builtin.value()?)))
+            .collect();
 
         QuantumValidator {
             constants: HashMap::new(),
             matrices,
+            subsystems: HashMap::new(),
+            functions: FunctionTable::new(),
+            failed_expr: RefCell::new(None),
+            hypotheses: Vec::new(),
+            config,
         }
     }
 
+    pub fn config(&self) -> &ValidatorConfig {
+        &self.config
+    }
+
     /// Validate quantum constraints for the typed AST
+    ///
+    /// Errors in parsed programs carry the line and column of the
+    /// expression, or else the statement, they arose in.
     pub fn validate(&mut self, typed_ast: &TypedAst) -> Result<ValidatedAst> {
+        self.config.validate()?;
         let mut results = ValidationResults::default();
+        let ast = &typed_ast.ast;
+        self.subsystems = typed_ast.subsystems.clone();
+        self.hypotheses.clear();
+
+        for (index, stmt) in ast.statements.iter().enumerate() {
+            self.failed_expr.take();
+            self.validate_statement(stmt, &mut results).map_err(|e| {
+                ast.source_map
+                    .locate(index, self.failed_expr.take().as_ref(), e)
+            })?;
+        }
 
-        // Collect constants and matrices from AST
-        for stmt in &typed_ast.ast.statements {
-            match stmt {
-                Statement::ConstDecl { name, value } => {
-                    self.constants.insert(name.clone(), *value);
-                }
-                Statement::MatrixDecl { name, value } => {
-                    if let Ok(mat) = self.evaluate_matrix_literal(value) {
-                        self.matrices.insert(name.clone(), mat);
+        Ok(ValidatedAst {
+            typed_ast: typed_ast.clone(),
+            validation_results: results,
+        })
+    }
+
+    /// Validate every statement of the typed AST, carrying on past errors
+    ///
+    /// Returns the validated AST with each error and the position it arose
+    /// at, in statement order.
+    pub fn validate_all(&mut self, typed_ast: &TypedAst) -> (ValidatedAst, Vec<(EngineError, Option<Span>)>) {
+        let mut results = ValidationResults::default();
+        let mut errors = Vec::new();
+        let ast = &typed_ast.ast;
+        self.subsystems = typed_ast.subsystems.clone();
+        self.hypotheses.clear();
+
+        match self.config.validate() {
+            Ok(()) => {
+                for (index, stmt) in ast.statements.iter().enumerate() {
+                    self.failed_expr.take();
+                    if let Err(e) = self.validate_statement(stmt, &mut results) {
+                        let span = ast.source_map.span(index, self.failed_expr.take().as_ref());
+                        errors.push((e, span));
                     }
                 }
-                Statement::HamiltonianDef { name, expr, .. } => {
-                    if let Ok(mat) = self.evaluate_expr_to_matrix(expr) {
-                        // Check Hermiticity
-                        let is_hermitian = self.check_hermitian(&mat)?;
-                        results.hermitian_operators.insert(name.clone(), is_hermitian);
-                        
-                        if !is_hermitian {
-                            return Err(EngineError::quantum_error(format!(
-                                "Hamiltonian '{}' is not Hermitian",
-                                name
-                            )));
-                        }
-                        
-                        self.matrices.insert(name.clone(), mat);
-                    }
+            }
+            Err(e) => errors.push((e, None)),
+        }
+
+        let validated = ValidatedAst {
+            typed_ast: typed_ast.clone(),
+            validation_results: results,
+        };
+        (validated, errors)
+    }
+
+    /// Collect constants and matrices, and check the constraints of one
+    /// statement
+    fn validate_statement(&mut self, stmt: &Statement, results: &mut ValidationResults) -> Result<()> {
+        match stmt {
+            Statement::ConstDecl { name, value } => {
+                self.constants.insert(name.clone(), *value);
+            }
+            Statement::MatrixDecl { name, value } => {
+                if let Ok(mat) = self.evaluate_matrix_literal(value) {
+                    self.matrices.insert(name.clone(), mat);
                 }
-                Statement::MeasurementDef { name, spec } => {
-                    self.validate_measurement(name, spec, &mut results)?;
+            }
+            Statement::OperatorDecl { name, value } => {
+                let mat = self.evaluate_expr_to_matrix(value)?;
+                self.matrices.insert(name.clone(), mat);
+            }
+            Statement::FunctionDef { name, params, body } => {
+                self.functions.define(name, params, body)?;
+            }
+            Statement::HamiltonianDef { name, expr, .. } => {
+                if let Ok(mat) = self.evaluate_expr_to_matrix(expr) {
+                    // Check Hermiticity
+                    let subject = format!("Hamiltonian '{}'", name);
+                    let is_hermitian = self.check_hermitian(&mat, &subject, results)?;
+                    results.hermitian_operators.insert(name.clone(), is_hermitian);
+                    
+                    if !mat.is_square() {
+                        return Err(EngineError::quantum_error(format!(
+                            "Hamiltonian '{}' is not Hermitian",
+                            name
+                        )));
+                    }
+                    
+                    self.matrices.insert(name.clone(), mat);
                 }
-                Statement::Experiment { body, .. } => {
-                    self.validate_experiment(body, &mut results)?;
+            }
+            Statement::MeasurementDef { name, spec } => {
+                self.validate_measurement(name, spec, results)?;
+            }
+            Statement::ChannelDef { name, kraus } => {
+                // Channels with symbolic Kraus operators are checked once bound
+                let ops: Result<Vec<_>> = kraus.iter().map(|k| self.evaluate_expr_to_matrix(k)).collect();
+                if let Ok(ops) = ops {
+                    let subject = format!("channel '{}'", name);
+                    let preserves_trace = self.check_cptp(&ops, &subject, results).map_err(|e| match e {
+                        EngineError::NotCPTP { reason } => EngineError::NotCPTP {
+                            reason: format!("{}: {}", subject, reason),
+                        },
+                        other => other,
+                    })?;
+                    results.cptp_channels.insert(name.clone(), preserves_trace);
                 }
-                _ => {}
             }
+            Statement::Experiment { name, body } => {
+                self.validate_experiment(name, body, results)?;
+            }
+            Statement::Assume(goal) => {
+                let (lhs, rhs) = goal.identity();
+                self.hypotheses.push(Assumption::equation(lhs, rhs));
+            }
+            Statement::Prove(goal) => self.check_goal(goal, results)?,
+            _ => {}
         }
+        Ok(())
+    }
 
-        Ok(ValidatedAst {
-            typed_ast: typed_ast.clone(),
-            validation_results: results,
-        })
+    /// Hand `goal` to the prover under the assumptions stated so far,
+    /// failing if it is refuted
+    #[cfg(feature = "prover")]
+    fn check_goal(&self, goal: &ProofGoal, results: &mut ValidationResults) -> Result<()> {
+        use crate::prover::{ProofResult, Prover, ProverConfig};
+
+        let mut prover = Prover::new(ProverConfig::default());
+        for hypothesis in &self.hypotheses {
+            prover.add_assumption(hypothesis.clone());
+        }
+        let status = match prover.prove_goal(goal) {
+            ProofResult::Proven(_) => GoalStatus::Proven,
+            ProofResult::Refuted(counterexample) => {
+                // The prover samples every free name as a scalar, which
+                // says nothing about names this file binds to values
+                let mut names: Vec<_> = counterexample.params.iter().collect();
+                names.sort_by(|a, b| a.0.cmp(b.0));
+                if let Some((name, _)) = names
+                    .iter()
+                    .find(|(name, _)| self.constants.contains_key(*name) || self.matrices.contains_key(*name))
+                {
+                    GoalStatus::Undecided {
+                        reason: format!("a counterexample treats '{}' as a free scalar", name),
+                    }
+                } else {
+                    let at = names
+                        .iter()
+                        .map(|(name, value)| format!("{} = {}", name, value))
+                        .collect::<Vec<_>>();
+                    let at = if at.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" at {}", at.join(", "))
+                    };
+                    return Err(EngineError::validation_error(format!(
+                        "Goal '{}' is refuted: its sides differ by {:.3e}{}",
+                        goal, counterexample.difference_norm, at
+                    )));
+                }
+            }
+            ProofResult::Unknown(reason) => GoalStatus::Undecided {
+                reason: format!("{:?}", reason),
+            },
+        };
+        results.goals.push(GoalOutcome {
+            goal: goal.to_string(),
+            status,
+        });
+        Ok(())
+    }
+
+    #[cfg(not(feature = "prover"))]
+    fn check_goal(&self, goal: &ProofGoal, results: &mut ValidationResults) -> Result<()> {
+        results.goals.push(GoalOutcome {
+            goal: goal.to_string(),
+            status: GoalStatus::Undecided {
+                reason: "built without the prover feature".to_string(),
+            },
+        });
+        Ok(())
+    }
+
+    /// Compare the measured `deviation` of `subject` from `constraint` with
+    /// the tolerance, returning whether the constraint holds
+    ///
+    /// A violation is recorded in `results`, and fails with the error built
+    /// by `error` unless the configuration downgrades it to a warning.
+    fn enforce(
+        &self,
+        constraint: Constraint,
+        subject: &str,
+        deviation: f64,
+        results: &mut ValidationResults,
+        error: impl FnOnce(f64) -> EngineError,
+    ) -> Result<bool> {
+        if deviation <= self.config.tol {
+            return Ok(true);
+        }
+        let warning = self.config.warns(constraint);
+        results.violations.push(Violation {
+            constraint,
+            subject: subject.to_string(),
+            deviation,
+            tolerance: self.config.tol,
+            warning,
+        });
+        if warning {
+            Ok(false)
+        } else {
+            Err(error(deviation))
+        }
     }
 
     /// Check if a matrix is Hermitian (A = A†)
-    fn check_hermitian(&self, mat: &Array2<Complex64>) -> Result<bool> {
+    fn check_hermitian(&self, mat: &Array2<Complex64>, subject: &str, results: &mut ValidationResults) -> Result<bool> {
         let (n, m) = mat.dim();
         if n != m {
             return Ok(false);
         }
 
-        let mut max_dev = 0.0;
+        let mut max_dev: f64 = 0.0;
         for i in 0..n {
             for j in 0..m {
                 let diff = (mat[[i, j]] - mat[[j, i]].conj()).norm();
@@ -147,24 +476,23 @@ impl QuantumValidator {
             }
         }
 
-        if max_dev > VALIDATION_TOL {
-            Err(EngineError::NotHermitian { deviation: max_dev })
-        } else {
-            Ok(true)
-        }
+        self.enforce(Constraint::Hermiticity, subject, max_dev, results, |deviation| {
+            EngineError::NotHermitian { deviation }
+        })
     }
 
     /// Check if a matrix is positive semi-definite (all eigenvalues >= 0)
-    fn check_psd(&self, mat: &Array2<Complex64>) -> Result<bool> {
+    ///
+    /// The matrix is checked to be Hermitian first; if that is only a
+    /// warning, the eigenvalues are those of its upper triangle.
+    fn check_psd(&self, mat: &Array2<Complex64>, subject: &str, results: &mut ValidationResults) -> Result<bool> {
         let (n, m) = mat.dim();
         if n != m {
             return Err(EngineError::validation_error("PSD check requires square matrix"));
         }
 
         // First check if Hermitian
-        if !self.check_hermitian(mat).unwrap_or(false) {
-            return Err(EngineError::validation_error("PSD check requires Hermitian matrix"));
-        }
+        self.check_hermitian(mat, subject, results)?;
 
         // Compute eigenvalues
         let eigenvalues = mat.eigh(UPLO::Upper)
@@ -172,15 +500,13 @@ impl QuantumValidator {
         
         let min_eigenvalue = eigenvalues.0.iter().fold(f64::INFINITY, |a, &b| a.min(b));
         
-        if min_eigenvalue < -VALIDATION_TOL {
-            Err(EngineError::NotPSD { min_eigenvalue })
-        } else {
-            Ok(true)
-        }
+        self.enforce(Constraint::Positivity, subject, (-min_eigenvalue).max(0.0), results, |_| {
+            EngineError::NotPSD { min_eigenvalue }
+        })
     }
 
     /// Check trace of a matrix
-    fn check_trace(&self, mat: &Array2<Complex64>, expected: f64) -> Result<()> {
+    fn check_trace(&self, mat: &Array2<Complex64>, expected: f64, subject: &str, results: &mut ValidationResults) -> Result<bool> {
         let (n, m) = mat.dim();
         if n != m {
             return Err(EngineError::validation_error("Trace requires square matrix"));
@@ -189,14 +515,50 @@ impl QuantumValidator {
         let trace: Complex64 = (0..n).map(|i| mat[[i, i]]).sum();
         let trace_real = trace.re;
         
-        if (trace_real - expected).abs() > VALIDATION_TOL {
-            Err(EngineError::TraceError {
+        self.enforce(Constraint::Trace, subject, (trace_real - expected).abs(), results, |_| {
+            EngineError::TraceError {
                 expected,
                 actual: trace_real,
-            })
-        } else {
-            Ok(())
+            }
+        })
+    }
+
+    /// Check that Kraus operators define a trace-preserving map:
+    /// Σ_k K_k† K_k = I (complete positivity holds by construction)
+    fn check_cptp(&self, kraus: &[Array2<Complex64>], subject: &str, results: &mut ValidationResults) -> Result<bool> {
+        let Some(first) = kraus.first() else {
+            return Err(EngineError::NotCPTP {
+                reason: "no Kraus operators".to_string(),
+            });
+        };
+        let dim = first.nrows();
+        let mut sum = Array2::<Complex64>::zeros((dim, dim));
+        for (i, k) in kraus.iter().enumerate() {
+            if k.dim() != (dim, dim) {
+                return Err(EngineError::NotCPTP {
+                    reason: format!("Kraus operator {} is {:?}, expected {}x{}", i, k.dim(), dim, dim),
+                });
+            }
+            sum = sum + kernels_cpu::dagger(k).dot(k);
         }
+
+        let deviation = identity_deviation(sum);
+        self.enforce(Constraint::TracePreservation, subject, deviation, results, |deviation| {
+            EngineError::NotCPTP {
+                reason: format!("Σ K†K deviates from the identity by {:.3e}", deviation),
+            }
+        })
+    }
+
+    /// Check that a matrix applied as a gate is unitary: U†U = I
+    fn check_unitary(&self, mat: &Array2<Complex64>, subject: &str, results: &mut ValidationResults) -> Result<bool> {
+        let deviation = identity_deviation(kernels_cpu::dagger(mat).dot(mat));
+        self.enforce(Constraint::Unitarity, subject, deviation, results, |deviation| {
+            EngineError::quantum_error(format!(
+                "{} is not unitary: U†U deviates from the identity by {:.3e}",
+                subject, deviation
+            ))
+        })
     }
 
     fn validate_measurement(
@@ -205,26 +567,28 @@ impl QuantumValidator {
         spec: &MeasurementSpec,
         results: &mut ValidationResults,
     ) -> Result<()> {
+        let tol = self.config.tol;
         match spec {
             MeasurementSpec::Projective { projectors } => {
                 // Check each projector is Hermitian and P^2 = P
                 for (i, proj_literal) in projectors.iter().enumerate() {
                     let proj = self.evaluate_matrix_literal(proj_literal)?;
+                    let subject = format!("projector {} of measurement '{}'", i, name);
                     
                     // Check Hermiticity
-                    self.check_hermitian(&proj)?;
+                    self.check_hermitian(&proj, &subject, results)?;
                     
                     // Check idempotence: P^2 = P
                     let proj_squared = proj.dot(&proj);
                     let diff = &proj_squared - &proj;
                     let norm = diff.iter().map(|x| x.norm()).fold(0.0, f64::max);
                     
-                    if norm > VALIDATION_TOL {
-                        return Err(EngineError::quantum_error(format!(
-                            "Projector {} in measurement '{}' is not idempotent",
-                            i, name
-                        )));
-                    }
+                    self.enforce(Constraint::Idempotence, &subject, norm, results, |deviation| {
+                        EngineError::quantum_error(format!(
+                            "Projector {} in measurement '{}' is not idempotent: max |P² - P| = {:.2e} exceeds tolerance {:.2e}",
+                            i, name, deviation, tol
+                        ))
+                    })?;
                 }
                 
                 // Check completeness: sum of projectors = identity
@@ -238,26 +602,24 @@ impl QuantumValidator {
                         sum = sum + proj;
                     }
                     
-                    let identity = Array2::from_diag(&Array1::from_elem(dim, Complex64::new(1.0, 0.0)));
-                    let diff = sum - identity;
-                    let norm = diff.iter().map(|x| x.norm()).fold(0.0, f64::max);
-                    
-                    if norm > VALIDATION_TOL {
-                        return Err(EngineError::quantum_error(format!(
-                            "Projectors in measurement '{}' do not sum to identity",
-                            name
-                        )));
-                    }
+                    let subject = format!("measurement '{}'", name);
+                    self.enforce(Constraint::Completeness, &subject, identity_deviation(sum), results, |deviation| {
+                        EngineError::quantum_error(format!(
+                            "Projectors in measurement '{}' do not sum to identity: deviation {:.2e} exceeds tolerance {:.2e}",
+                            name, deviation, tol
+                        ))
+                    })?;
                 }
             }
             MeasurementSpec::POVM { effects } => {
                 // Check each effect is PSD and effects sum to identity
                 for (i, effect_literal) in effects.iter().enumerate() {
                     let effect = self.evaluate_matrix_literal(effect_literal)?;
-                    self.check_psd(&effect).map_err(|_| {
+                    let subject = format!("POVM effect {} of measurement '{}'", i, name);
+                    self.check_psd(&effect, &subject, results).map_err(|e| {
                         EngineError::quantum_error(format!(
-                            "POVM effect {} in measurement '{}' is not PSD",
-                            i, name
+                            "POVM effect {} in measurement '{}' is not PSD: {}",
+                            i, name, e
                         ))
                     })?;
                 }
@@ -273,49 +635,63 @@ impl QuantumValidator {
                         sum = sum + effect;
                     }
                     
-                    let identity = Array2::from_diag(&Array1::from_elem(dim, Complex64::new(1.0, 0.0)));
-                    let diff = sum - identity;
-                    let norm = diff.iter().map(|x| x.norm()).fold(0.0, f64::max);
-                    
-                    if norm > VALIDATION_TOL {
-                        return Err(EngineError::quantum_error(format!(
-                            "POVM effects in measurement '{}' do not sum to identity",
-                            name
-                        )));
-                    }
+                    let subject = format!("measurement '{}'", name);
+                    self.enforce(Constraint::Completeness, &subject, identity_deviation(sum), results, |deviation| {
+                        EngineError::quantum_error(format!(
+                            "POVM effects in measurement '{}' do not sum to identity: deviation {:.2e} exceeds tolerance {:.2e}",
+                            name, deviation, tol
+                        ))
+                    })?;
                 }
             }
+            // Complete and orthonormal by construction
+            MeasurementSpec::Basis { .. } => {}
         }
         Ok(())
     }
 
-    fn validate_experiment(&self, body: &ExperimentBody, results: &mut ValidationResults) -> Result<()> {
+    fn validate_experiment(&self, name: &str, body: &ExperimentBody, results: &mut ValidationResults) -> Result<()> {
         if let Some(init) = &body.init {
+            let subject = format!("initial state of experiment '{}'", name);
             match init {
                 StateSpec::Ket(vec) => {
                     // Check normalization
                     let ket = self.evaluate_vector_literal(vec)?;
                     let norm_sq: f64 = ket.iter().map(|x| x.norm_sqr()).sum();
                     
-                    if (norm_sq - 1.0).abs() > VALIDATION_TOL {
-                        return Err(EngineError::quantum_error(format!(
-                            "Initial ket is not normalized: ||ψ||² = {}",
-                            norm_sq
-                        )));
-                    }
+                    self.enforce(Constraint::Normalization, &subject, (norm_sq - 1.0).abs(), results, |_| {
+                        EngineError::quantum_error(format!(
+                            "Initial ket is not normalized: ||ψ||² = {}, tolerance {:.2e}",
+                            norm_sq, self.config.tol
+                        ))
+                    })?;
                 }
                 StateSpec::Rho(mat) => {
                     let rho = self.evaluate_matrix_literal(mat)?;
                     
-                    // Check Hermitian
-                    self.check_hermitian(&rho)?;
-                    
-                    // Check PSD
-                    self.check_psd(&rho)?;
+                    // Check Hermitian and PSD
+                    self.check_psd(&rho, &subject, results)?;
                     
                     // Check trace = 1
-                    self.check_trace(&rho, 1.0)?;
+                    self.check_trace(&rho, 1.0, &subject, results)?;
                 }
+                // Valid if the experiment it comes from starts valid
+                StateSpec::From(_) => {}
+                // Normalised by construction, from a Hamiltonian checked
+                // where it is declared
+                StateSpec::Eigenstate { .. } => {}
+            }
+        }
+
+        // Library gates are unitary by construction, rotations for real
+        // angles; declared matrices are checked
+        for application in &body.gates {
+            if gates::lookup(&application.gate).is_some() {
+                continue;
+            }
+            if let Some(gate) = self.matrices.get(&application.gate) {
+                let subject = format!("gate '{}' in experiment '{}'", application.gate, name);
+                self.check_unitary(gate, &subject, results)?;
             }
         }
         Ok(())
@@ -347,6 +723,10 @@ impl QuantumValidator {
     }
 
     fn evaluate_expr_to_complex(&self, expr: &Expr) -> Result<Complex64> {
+        self.evaluate_complex(expr).map_err(|e| self.failed_at(expr, e))
+    }
+
+    fn evaluate_complex(&self, expr: &Expr) -> Result<Complex64> {
         match expr {
             Expr::Number(x) => Ok(Complex64::new(*x, 0.0)),
             Expr::ComplexNumber(c) => Ok(*c),
@@ -372,25 +752,56 @@ impl QuantumValidator {
             Expr::Div(a, b) => {
                 Ok(self.evaluate_expr_to_complex(a)? / self.evaluate_expr_to_complex(b)?)
             }
+            Expr::FuncCall { name, .. } if self.functions.contains(name) => {
+                self.evaluate_expr_to_complex(&self.functions.inline(expr)?)
+            }
             _ => Err(EngineError::validation_error(
                 "Expression too complex to evaluate at validation time",
             )),
         }
     }
 
+    /// `expr` as a constant, if it is one; a failed attempt is not taken
+    /// as where evaluation failed
+    fn try_scalar(&self, expr: &Expr) -> Option<Complex64> {
+        let failed = self.failed_expr.borrow().clone();
+        let value = self.evaluate_complex(expr).ok();
+        *self.failed_expr.borrow_mut() = failed;
+        value
+    }
+
     fn evaluate_expr_to_matrix(&self, expr: &Expr) -> Result<Array2<Complex64>> {
+        self.evaluate_matrix(expr).map_err(|e| self.failed_at(expr, e))
+    }
+
+    /// Remember `expr` as where evaluation failed, unless an expression
+    /// inside it already was
+    fn failed_at(&self, expr: &Expr, error: EngineError) -> EngineError {
+        self.failed_expr
+            .borrow_mut()
+            .get_or_insert_with(|| expr.clone());
+        error
+    }
+
+    fn evaluate_matrix(&self, expr: &Expr) -> Result<Array2<Complex64>> {
         match expr {
             Expr::Matrix(mat) => self.evaluate_matrix_literal(mat),
-            Expr::Identifier(name) => {
-                self.matrices.get(name).cloned().ok_or_else(|| {
-                    EngineError::validation_error(format!("Matrix '{}' not found", name))
-                })
-            }
-            Expr::Mul(a, b) => {
-                let left = self.evaluate_expr_to_matrix(a)?;
-                let right = self.evaluate_expr_to_matrix(b)?;
-                Ok(left.dot(&right))
-            }
+            Expr::Identifier(name) => match self.matrices.get(name) {
+                Some(matrix) => Ok(matrix.clone()),
+                None => name
+                    .parse::<PauliString>()
+                    .map(|pauli| pauli.to_dense())
+                    .map_err(|_| EngineError::validation_error(format!("Matrix '{}' not found", name))),
+            },
+            Expr::Mul(a, b) => match (self.try_scalar(a), self.try_scalar(b)) {
+                (Some(c), _) => Ok(self.evaluate_expr_to_matrix(b)?.mapv(|x| c * x)),
+                (_, Some(c)) => Ok(self.evaluate_expr_to_matrix(a)?.mapv(|x| x * c)),
+                _ => {
+                    let left = self.evaluate_expr_to_matrix(a)?;
+                    let right = self.evaluate_expr_to_matrix(b)?;
+                    Ok(left.dot(&right))
+                }
+            },
             Expr::Add(a, b) => {
                 let left = self.evaluate_expr_to_matrix(a)?;
                 let right = self.evaluate_expr_to_matrix(b)?;
@@ -401,6 +812,28 @@ impl QuantumValidator {
                 let right = self.evaluate_expr_to_matrix(b)?;
                 Ok(left - right)
             }
+            Expr::FuncCall { name, args } if name == "ptrace" => {
+                let (subsystem, dims) = partial_trace_args(args)?;
+                let rho = self.evaluate_expr_to_matrix(&args[0])?;
+                let dims = match subsystem_dims(&args[0], dims, &self.subsystems) {
+                    Some(dims) => dims,
+                    None => kernels_cpu::qubit_dims(rho.nrows())?,
+                };
+                kernels_cpu::partial_trace(&rho, &dims, &[subsystem])
+            }
+            Expr::FuncCall { name, args } if name == "embed" => {
+                let (subsystem, dims) = embedding_args(args)?;
+                kernels_cpu::embed_operator(&self.evaluate_expr_to_matrix(&args[0])?, &dims, subsystem)
+            }
+            Expr::FuncCall { name, args } if is_operator(name) => {
+                builtins::lookup(name).expect("checked by is_operator").operator(args)
+            }
+            Expr::FuncCall { name, args } if name == "eig" => {
+                Ok(kernels_cpu::eigendecompose(&self.evaluate_expr_to_matrix(&args[0])?)?.eigenvectors)
+            }
+            Expr::FuncCall { name, .. } if self.functions.contains(name) => {
+                self.evaluate_expr_to_matrix(&self.functions.inline(expr)?)
+            }
             _ => Err(EngineError::Unsupported(
                 "Complex expression evaluation not yet implemented".to_string(),
             )),
@@ -414,28 +847,141 @@ impl Default for QuantumValidator {
     }
 }
 
+/// Largest entry of `sum - I` in magnitude
+fn identity_deviation(sum: Array2<Complex64>) -> f64 {
+    let identity = Array2::from_diag(&Array1::from_elem(sum.nrows(), Complex64::new(1.0, 0.0)));
+    (sum - identity).iter().map(|x| x.norm()).fold(0.0, f64::max)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(all(feature = "parser", feature = "prover"))]
+    #[test]
+    fn test_prove_goals_during_validation() {
+        let validate = |source: &str| {
+            let ast = crate::parser::parse_dsl(source).unwrap();
+            let typed = crate::typechecker::TypeChecker::new().check(&ast).unwrap();
+            QuantumValidator::new().validate(&typed)
+        };
+
+        let validated = validate(
+            r#"
+            assume commutator(A, B) == 0;
+            prove A * B == B * A;
+            prove [H, H] == 0;
+        "#,
+        )
+        .unwrap();
+        let goals = &validated.validation_results.goals;
+        assert_eq!(goals.len(), 2);
+        assert!(goals.iter().all(|g| g.status == GoalStatus::Proven), "{:?}", goals);
+
+        let err = validate("prove sigma_x * sigma_y == sigma_y * sigma_x;").unwrap_err();
+        assert!(err.to_string().contains("refuted"), "{}", err);
+    }
+
     #[test]
     fn test_pauli_hermitian() {
         let validator = QuantumValidator::new();
         let sigma_x = validator.matrices.get("sigma_x").unwrap();
-        assert!(validator.check_hermitian(sigma_x).is_ok());
+        let mut results = ValidationResults::default();
+        assert!(validator.check_hermitian(sigma_x, "sigma_x", &mut results).unwrap());
+        assert!(results.violations.is_empty());
     }
 
     #[test]
     fn test_identity_psd() {
         let validator = QuantumValidator::new();
         let identity = validator.matrices.get("identity").unwrap();
-        assert!(validator.check_psd(identity).is_ok());
+        assert!(validator.check_psd(identity, "identity", &mut ValidationResults::default()).is_ok());
+    }
+
+    #[test]
+    fn test_channel_must_be_trace_preserving() {
+        let validator = QuantumValidator::new();
+        let c = |re: f64| Complex64::new(re, 0.0);
+        let identity = validator.matrices.get("identity").unwrap();
+        let sigma_z = validator.matrices.get("sigma_z").unwrap();
+        let dephasing = [identity.mapv(|x| x * c(0.8)), sigma_z.mapv(|x| x * c(0.6))];
+        let mut results = ValidationResults::default();
+        assert!(validator.check_cptp(&dephasing, "dephasing", &mut results).is_ok());
+
+        let lossy = [identity.mapv(|x| x * c(0.8)), sigma_z.mapv(|x| x * c(0.5))];
+        assert!(matches!(
+            validator.check_cptp(&lossy, "lossy", &mut results),
+            Err(EngineError::NotCPTP { .. })
+        ));
+        assert!(validator.check_cptp(&[], "empty", &mut results).is_err());
     }
 
     #[test]
     fn test_trace_identity() {
         let validator = QuantumValidator::new();
         let identity = validator.matrices.get("identity").unwrap();
-        assert!(validator.check_trace(identity, 2.0).is_ok());
+        assert!(validator.check_trace(identity, 2.0, "identity", &mut ValidationResults::default()).is_ok());
+    }
+
+    /// Projectors onto |0⟩ and |1⟩ whose |0⟩ entry is off by `noise`
+    fn noisy_measurement(noise: f64) -> MeasurementSpec {
+        let literal = |entries: [f64; 4]| MatrixLiteral {
+            rows: vec![
+                vec![Expr::Number(entries[0]), Expr::Number(entries[1])],
+                vec![Expr::Number(entries[2]), Expr::Number(entries[3])],
+            ],
+        };
+        MeasurementSpec::Projective {
+            projectors: vec![literal([1.0 + noise, 0.0, 0.0, 0.0]), literal([0.0, 0.0, 0.0, 1.0])],
+        }
+    }
+
+    #[test]
+    fn test_tolerance_and_warnings_are_configurable() {
+        let spec = noisy_measurement(1e-6);
+        let mut results = ValidationResults::default();
+        let err = QuantumValidator::new()
+            .validate_measurement("M", &spec, &mut results)
+            .unwrap_err();
+        assert!(err.to_string().contains("1.00e-6"), "{}", err);
+
+        // A looser tolerance accepts the noise outright
+        let relaxed = QuantumValidator::with_config(ValidatorConfig {
+            tol: 1e-4,
+            ..ValidatorConfig::default()
+        });
+        let mut results = ValidationResults::default();
+        relaxed.validate_measurement("M", &spec, &mut results).unwrap();
+        assert!(results.violations.is_empty());
+
+        // Downgraded constraints are reported with their deviation
+        let config = ValidatorConfig {
+            warn_only: [Constraint::Idempotence, Constraint::Completeness].into(),
+            ..ValidatorConfig::default()
+        };
+        let mut results = ValidationResults::default();
+        QuantumValidator::with_config(config.clone())
+            .validate_measurement("M", &spec, &mut results)
+            .unwrap();
+        let constraints: Vec<_> = results.warnings().map(|v| v.constraint).collect();
+        assert_eq!(constraints, [Constraint::Idempotence, Constraint::Completeness]);
+        assert!(results.violations.iter().all(|v| (v.deviation - 1e-6).abs() < 1e-9));
+
+        let strict = QuantumValidator::with_config(ValidatorConfig {
+            strict_mode: true,
+            ..config
+        });
+        assert!(strict
+            .validate_measurement("M", &spec, &mut ValidationResults::default())
+            .is_err());
+
+        assert_eq!("trace_preservation".parse::<Constraint>().unwrap(), Constraint::TracePreservation);
+        assert_eq!("unitarity".parse::<Constraint>().unwrap(), Constraint::Unitarity);
+        assert!("orthogonality".parse::<Constraint>().is_err());
+        let negative = ValidatorConfig {
+            tol: -1.0,
+            ..ValidatorConfig::default()
+        };
+        assert!(negative.validate().is_err());
     }
 }
@@ -0,0 +1,232 @@
+//! Parameter values supplied separately from a model
+//!
+//! A `.phys` model can declare parameters without binding them
+//! (`param omega;`, `param gamma in [0.0, 1.0];`) and take their values from
+//! a sidecar file at run time, so one model serves many datasets. Sidecars
+//! are flat tables of numbers, in TOML
+//!
+//! ```toml
+//! omega = 1.5
+//! gamma = 0.02
+//! ```
+//!
+//! or JSON (`{"omega": 1.5, "gamma": 0.02}`).
+
+use crate::ast::{Ast, Statement};
+use crate::error::{EngineError, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Named parameter values, e.g. loaded from `run42.toml`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterValues {
+    values: BTreeMap<String, f64>,
+}
+
+impl ParameterValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a sidecar file; `.json` files are read as JSON, anything else
+    /// as TOML
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json_str(&text),
+            _ => Self::from_toml_str(&text),
+        }
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| EngineError::SerializationError(format!("Parameter file: {}", e)))?;
+        let mut values = Self::new();
+        for (name, value) in table {
+            let number = match value {
+                toml::Value::Float(x) => x,
+                toml::Value::Integer(n) => n as f64,
+                other => return Err(not_a_number(&name, other.type_str())),
+            };
+            values.insert(name, number);
+        }
+        Ok(values)
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(text)?;
+        let mut values = Self::new();
+        for (name, value) in object {
+            let number = value
+                .as_f64()
+                .ok_or_else(|| not_a_number(&name, "non-number"))?;
+            values.insert(name, number);
+        }
+        Ok(values)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: f64) {
+        self.values.insert(name.into(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values.get(name).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Bind these values into `ast`
+    ///
+    /// Every `param` declaration becomes a `const` with its value, which must
+    /// lie in the declared range. Values may also override existing `const`
+    /// declarations. A missing parameter, an out-of-range value or a value
+    /// for a name the model does not declare is an error.
+    pub fn bind(&self, ast: &Ast) -> Result<Ast> {
+        let mut bound = ast.clone();
+        let mut used = HashSet::new();
+
+        for stmt in &mut bound.statements {
+            match stmt {
+                Statement::ParamDecl { name, range } => {
+                    let value = self.get(name).ok_or_else(|| {
+                        EngineError::validation_error(format!(
+                            "Parameter '{}' is declared but has no value",
+                            name
+                        ))
+                    })?;
+                    if let Some((lo, hi)) = *range {
+                        if !(lo..=hi).contains(&value) {
+                            return Err(EngineError::validation_error(format!(
+                                "Parameter '{}' = {} is outside its declared range [{}, {}]",
+                                name, value, lo, hi
+                            )));
+                        }
+                    }
+                    used.insert(name.clone());
+                    *stmt = Statement::ConstDecl {
+                        name: name.clone(),
+                        value,
+                    };
+                }
+                Statement::ConstDecl { name, value } => {
+                    if let Some(v) = self.get(name) {
+                        *value = v;
+                        used.insert(name.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if used.len() < self.values.len() {
+            let declared: Vec<&str> = ast
+                .statements
+                .iter()
+                .filter_map(|stmt| match stmt {
+                    Statement::ParamDecl { name, .. } | Statement::ConstDecl { name, .. } => {
+                        Some(name.as_str())
+                    }
+                    _ => None,
+                })
+                .collect();
+            let unknown: Vec<&str> = self
+                .values
+                .keys()
+                .map(String::as_str)
+                .filter(|name| !declared.contains(name))
+                .collect();
+            return Err(EngineError::validation_error(format!(
+                "Unknown parameter(s) {:?}; the model declares {:?}",
+                unknown, declared
+            )));
+        }
+
+        Ok(bound)
+    }
+}
+
+fn not_a_number(name: &str, kind: &str) -> EngineError {
+    EngineError::SerializationError(format!(
+        "Parameter '{}' must be a number, got {}",
+        name, kind
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model() -> Ast {
+        Ast::new(vec![
+            Statement::ParamDecl {
+                name: "omega".to_string(),
+                range: None,
+            },
+            Statement::ParamDecl {
+                name: "gamma".to_string(),
+                range: Some((0.0, 1.0)),
+            },
+            Statement::ConstDecl {
+                name: "n".to_string(),
+                value: 2.0,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_bind_toml_and_json() {
+        let toml = ParameterValues::from_toml_str("omega = 1.5\ngamma = 0.25\nn = 3").unwrap();
+        let json =
+            ParameterValues::from_json_str(r#"{"omega": 1.5, "gamma": 0.25, "n": 3}"#).unwrap();
+        assert_eq!(toml, json);
+
+        let bound = toml.bind(&model()).unwrap();
+        let consts: Vec<(String, f64)> = bound
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::ConstDecl { name, value } => Some((name.clone(), *value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            consts,
+            vec![
+                ("omega".to_string(), 1.5),
+                ("gamma".to_string(), 0.25),
+                ("n".to_string(), 3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bind_rejects_missing_unknown_and_out_of_range() {
+        let missing = ParameterValues::from_toml_str("omega = 1.0").unwrap();
+        assert!(missing
+            .bind(&model())
+            .unwrap_err()
+            .to_string()
+            .contains("gamma"));
+
+        let out_of_range = ParameterValues::from_toml_str("omega = 1.0\ngamma = 2.0").unwrap();
+        assert!(out_of_range
+            .bind(&model())
+            .unwrap_err()
+            .to_string()
+            .contains("outside"));
+
+        let unknown =
+            ParameterValues::from_toml_str("omega = 1.0\ngamma = 0.5\nomgea = 1.0").unwrap();
+        assert!(unknown
+            .bind(&model())
+            .unwrap_err()
+            .to_string()
+            .contains("omgea"));
+
+        assert!(ParameterValues::from_toml_str("omega = \"fast\"").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Job queue timeouts and retries under virtual time
+//!
+//! Every test runs with tokio's clock paused: the runtime jumps straight to
+//! the next timer whenever all tasks are idle, so minute-long timeouts and
+//! backoffs elapse instantly and in a deterministic order.
+
+use quantum_theory_engine::job_queue::{
+    self, Job, JobConfig, JobKind, JobOutput, JobQueue, JobStatus, Priority, ProofOutput,
+    QueueOptions,
+};
+use quantum_theory_engine::EngineError;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+fn prove_job(timeout: Duration, max_retries: usize) -> Job {
+    Job {
+        id: uuid::Uuid::new_v4(),
+        kind: JobKind::Prove {
+            statement: "dagger(dagger(A)) == A".to_string(),
+        },
+        priority: Priority::Normal,
+        params: HashMap::new(),
+        config: JobConfig {
+            timeout,
+            max_retries,
+            ..JobConfig::default()
+        },
+        depends_on: Vec::new(),
+    }
+}
+
+fn proof_output() -> JobOutput {
+    JobOutput::Proof(ProofOutput {
+        proven: true,
+        steps: 1,
+        certificate_hash: None,
+    })
+}
+
+async fn wait_until_finished(queue: &JobQueue, job: Job) -> JobStatus {
+    let job_id = queue.submit(job).await.unwrap();
+    loop {
+        match queue.status(job_id).await {
+            Some(status @ (JobStatus::Complete(_) | JobStatus::Failed { .. })) => return status,
+            _ => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout_fires_in_virtual_time() {
+    let queue = JobQueue::with_options(
+        1,
+        QueueOptions {
+            runner: Arc::new(|_job: Job| async {
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+                Ok(proof_output())
+            }),
+            ..QueueOptions::default()
+        },
+    );
+
+    let start = Instant::now();
+    let status = wait_until_finished(&queue, prove_job(Duration::from_secs(30), 0)).await;
+
+    match status {
+        JobStatus::Failed { error, retry_count } => {
+            assert!(error.contains("timed out"), "{}", error);
+            assert_eq!(retry_count, 0);
+        }
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(30), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(31), "{:?}", elapsed);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retries_back_off_exponentially() {
+    let attempts = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&attempts);
+    let queue = JobQueue::with_options(
+        1,
+        QueueOptions {
+            retry_backoff: Duration::from_secs(10),
+            runner: Arc::new(move |_job: Job| {
+                let attempts = Arc::clone(&recorded);
+                async move {
+                    let mut attempts = attempts.lock().unwrap();
+                    attempts.push(Instant::now());
+                    if attempts.len() < 3 {
+                        Err(EngineError::ExecutionError("flaky backend".to_string()))
+                    } else {
+                        Ok(proof_output())
+                    }
+                }
+            }),
+            ..QueueOptions::default()
+        },
+    );
+
+    let status = wait_until_finished(&queue, prove_job(Duration::from_secs(60), 3)).await;
+    assert!(matches!(status, JobStatus::Complete(_)), "{:?}", status);
+
+    let attempts = attempts.lock().unwrap();
+    assert_eq!(attempts.len(), 3);
+    // Backoff doubles: 10 s before the first retry, 20 s before the second,
+    // each plus at most one idle poll interval
+    let poll = QueueOptions::default().poll_interval;
+    for (gap, backoff) in attempts.windows(2).zip([10, 20]) {
+        let waited = gap[1] - gap[0];
+        let backoff = Duration::from_secs(backoff);
+        assert!(
+            waited >= backoff && waited <= backoff + poll,
+            "{:?}",
+            waited
+        );
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_failure_reported_after_last_retry() {
+    let queue = JobQueue::with_options(
+        2,
+        QueueOptions {
+            runner: Arc::new(|_job: Job| async {
+                Err(EngineError::ExecutionError("always fails".to_string()))
+            }),
+            ..QueueOptions::default()
+        },
+    );
+
+    let status = wait_until_finished(&queue, prove_job(Duration::from_secs(60), 2)).await;
+
+    match status {
+        JobStatus::Failed { error, retry_count } => {
+            assert!(error.contains("always fails"), "{}", error);
+            assert_eq!(retry_count, 2);
+        }
+        other => panic!("expected a failure, got {:?}", other),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_status_reports_progress_and_eta() {
+    let queue = JobQueue::with_options(
+        1,
+        QueueOptions {
+            runner: Arc::new(|_job: Job| async {
+                for step in 1..=4 {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    job_queue::report_progress(step as f64 / 4.0);
+                }
+                Ok(proof_output())
+            }),
+            ..QueueOptions::default()
+        },
+    );
+    let job_id = queue
+        .submit(prove_job(Duration::from_secs(60), 0))
+        .await
+        .unwrap();
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    match queue.status(job_id).await {
+        Some(JobStatus::Running { progress, eta }) => {
+            assert_eq!(progress, 0.0);
+            assert_eq!(eta, None);
+        }
+        other => panic!("expected a running job, got {:?}", other),
+    }
+
+    // Half done after 25 s: another 25 s at the same rate, less the idle
+    // poll before the job started
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    match queue.status(job_id).await {
+        Some(JobStatus::Running { progress, eta }) => {
+            assert_eq!(progress, 0.5);
+            let eta = eta.unwrap();
+            let poll = QueueOptions::default().poll_interval;
+            assert!(
+                eta <= Duration::from_secs(25) && eta >= Duration::from_secs(25) - poll,
+                "{:?}",
+                eta
+            );
+        }
+        other => panic!("expected a running job, got {:?}", other),
+    }
+}
@@ -0,0 +1,838 @@
+//! Serialization round trips of everything the engine hands to clients
+//!
+//! Jobs, job results, IR programs, execution results, proofs and stream
+//! events are generated at random, written as JSON and as MessagePack, read
+//! back and written again; every round trip must reproduce the original
+//! JSON. A new field or variant whose serde attributes drop or mangle data
+//! fails here rather than in a client parsing engine output.
+//!
+//! Floats are multiples of 1/1024, which print and parse exactly, so any
+//! difference is a real loss rather than decimal rounding.
+
+use chrono::{DateTime, TimeZone, Utc};
+use num_complex::Complex64;
+use proptest::collection::{hash_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+use quantum_theory_engine::ast::{
+    Assumption, AssumptionKind, Expr, MatrixLiteral, Sign, VectorLiteral,
+};
+use quantum_theory_engine::executor::{
+    BackendConfig, BackendType, CheckpointConfig, DiagnosticThresholds, DiagnosticsSummary,
+    EhrenfestCheck, EhrenfestResidual, ExecutionResult, ExperimentResult, MeasurementResult,
+    ObservableSeries, StateType,
+};
+use quantum_theory_engine::ir::{
+    EigenOutput, EvolutionMethod, IrChannelApplication, IrEvolution, IrExperiment, IrMeasurement,
+    IrNode, IrObservable, IrProgram, LindbladOperator, MeasurementType,
+};
+use quantum_theory_engine::job_queue::{
+    ConfidenceEllipsoid, DependencyFailure, EllipsoidSampling, FitOutput, GridStrategy, Job,
+    JobConfig, JobKind, JobOutput, JobResult, ParameterGrid, ParameterRange, ParameterScale,
+    Priority, ProofOutput, SimulationOutput, SweepOutput, SweepSummary, TestOutput,
+};
+use quantum_theory_engine::ode::{AdaptiveConfig, IntegratorKind, StrictMode};
+use quantum_theory_engine::precision::{Precision, PrecisionCheck};
+use quantum_theory_engine::prover::{
+    Certificate, Proof, ProofStep, RewriteRule, Statement, VerificationStep,
+};
+use quantum_theory_engine::streaming::{DataPoint, StreamEvent};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// `value` survives JSON and MessagePack round trips unchanged
+fn assert_round_trips<T: Serialize + DeserializeOwned>(value: &T) -> Result<(), TestCaseError> {
+    let expected = serde_json::to_value(value).unwrap();
+
+    let text = serde_json::to_string(value).unwrap();
+    let from_json: T = serde_json::from_str(&text)
+        .map_err(|e| TestCaseError::fail(format!("JSON: {} in {}", e, text)))?;
+    prop_assert_eq!(&serde_json::to_value(&from_json).unwrap(), &expected);
+
+    let bytes = rmp_serde::to_vec_named(value).unwrap();
+    let from_binary: T = rmp_serde::from_slice(&bytes)
+        .map_err(|e| TestCaseError::fail(format!("MessagePack: {} in {}", e, text)))?;
+    prop_assert_eq!(&serde_json::to_value(&from_binary).unwrap(), &expected);
+    Ok(())
+}
+
+// ==================== Primitives ====================
+
+fn real() -> impl Strategy<Value = f64> {
+    (-1_000_000i64..=1_000_000).prop_map(|n| n as f64 / 1024.0)
+}
+
+fn complex() -> impl Strategy<Value = Complex64> {
+    (real(), real()).prop_map(|(re, im)| Complex64::new(re, im))
+}
+
+fn name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_]{0,8}"
+}
+
+fn text() -> impl Strategy<Value = String> {
+    "[ -~]{0,24}"
+}
+
+fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+fn duration() -> impl Strategy<Value = Duration> {
+    (0u64..10_000_000, 0u32..1_000_000_000).prop_map(|(secs, nanos)| Duration::new(secs, nanos))
+}
+
+fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+    (0i64..4_000_000_000, 0u32..1_000_000_000)
+        .prop_map(|(secs, nanos)| Utc.timestamp_opt(secs, nanos).unwrap())
+}
+
+fn params() -> impl Strategy<Value = HashMap<String, f64>> {
+    hash_map(name(), real(), 0..4)
+}
+
+// ==================== Jobs ====================
+
+fn backend_config() -> impl Strategy<Value = BackendConfig> {
+    let backend_type = prop_oneof![
+        Just(BackendType::CpuDense),
+        Just(BackendType::CpuSparse),
+        Just(BackendType::Gpu),
+    ];
+    let integrator = prop_oneof![
+        Just(IntegratorKind::Rk4),
+        (
+            real(),
+            real(),
+            option::of(real()),
+            real(),
+            option::of(real()),
+            1usize..1_000_000
+        )
+            .prop_map(
+                |(abs_tol, rel_tol, initial_step, min_step, max_step, max_steps)| {
+                    IntegratorKind::DormandPrince(AdaptiveConfig {
+                        abs_tol,
+                        rel_tol,
+                        initial_step,
+                        min_step,
+                        max_step,
+                        max_steps,
+                    })
+                }
+            ),
+    ];
+    let diagnostics = option::of(
+        (real(), real()).prop_map(|(max_trace_drift, min_eigenvalue)| DiagnosticThresholds {
+            max_trace_drift,
+            min_eigenvalue,
+        }),
+    );
+    let strict = option::of(
+        (1usize..100, real()).prop_map(|(interval, tolerance)| StrictMode {
+            interval,
+            tolerance,
+        }),
+    );
+    let ehrenfest = option::of(
+        (vec(name(), 0..3), real()).prop_map(|(observables, tolerance)| EhrenfestCheck {
+            observables,
+            tolerance,
+        }),
+    );
+    let checkpoint =
+        option::of(
+            (name(), 1usize..1000).prop_map(|(path, every_steps)| CheckpointConfig {
+                path: PathBuf::from(format!("{}.json", path)),
+                every_steps,
+            }),
+        );
+    let precision = prop_oneof![Just(Precision::Double), Just(Precision::Single)];
+    let precision_check = option::of(
+        (0usize..100, real())
+            .prop_map(|(sample_every, tolerance)| PrecisionCheck::new(sample_every, tolerance)),
+    );
+
+    (
+        backend_type,
+        option::of(1usize..64),
+        integrator,
+        diagnostics,
+        strict,
+        ehrenfest,
+        1usize..4096,
+        checkpoint,
+        precision,
+        precision_check,
+        0usize..256,
+    )
+        .prop_map(
+            |(
+                backend_type,
+                num_threads,
+                integrator,
+                diagnostics,
+                strict,
+                ehrenfest,
+                gpu_min_dim,
+                checkpoint,
+                precision,
+                precision_check,
+                propagator_cache,
+            )| BackendConfig {
+                backend_type,
+                num_threads,
+                integrator,
+                diagnostics,
+                strict,
+                ehrenfest,
+                gpu_min_dim,
+                checkpoint,
+                precision,
+                precision_check,
+                propagator_cache,
+            },
+        )
+}
+
+fn parameter_grid() -> impl Strategy<Value = ParameterGrid> {
+    let scale = prop_oneof![
+        Just(ParameterScale::Linear),
+        Just(ParameterScale::Log),
+        vec(real(), 0..4).prop_map(ParameterScale::Custom),
+    ];
+    let range = (name(), real(), real(), 0usize..100, scale).prop_map(
+        |(name, start, end, steps, scale)| ParameterRange {
+            name,
+            start,
+            end,
+            steps,
+            scale,
+        },
+    );
+    let sampling = prop_oneof![
+        (0usize..100).prop_map(EllipsoidSampling::Uniform),
+        (0usize..100).prop_map(EllipsoidSampling::PrincipalAxes),
+    ];
+    let ellipsoid = (
+        vec(real(), 0..3),
+        vec(vec(real(), 0..3), 0..3),
+        real(),
+        sampling,
+    )
+        .prop_map(
+            |(center, covariance, n_sigma, sampling)| ConfidenceEllipsoid {
+                center,
+                covariance,
+                n_sigma,
+                sampling,
+            },
+        );
+    let strategy = prop_oneof![
+        Just(GridStrategy::FullGrid),
+        (0usize..1000, option::of(any::<u64>())).prop_map(|(n, seed)| GridStrategy::Random { n, seed }),
+        (0usize..1000).prop_map(GridStrategy::Sobol),
+        ellipsoid.prop_map(GridStrategy::Ellipsoid),
+    ];
+
+    (vec(range, 0..3), strategy).prop_map(|(params, strategy)| ParameterGrid { params, strategy })
+}
+
+fn job() -> impl Strategy<Value = Job> {
+    let leaf = prop_oneof![
+        text().prop_map(|program| JobKind::Simulate { program }),
+        text().prop_map(|statement| JobKind::Prove { statement }),
+        (text(), name()).prop_map(|(program, data_path)| JobKind::Fit { program, data_path }),
+        (text(), name()).prop_map(|(program, data_path)| JobKind::Test { program, data_path }),
+    ];
+    let kind = leaf.prop_recursive(2, 4, 1, |inner| {
+        (job_with(inner), parameter_grid()).prop_map(|(base_job, param_grid)| JobKind::Sweep {
+            base_job: Box::new(base_job),
+            param_grid,
+        })
+    });
+    job_with(kind)
+}
+
+fn job_with(kind: impl Strategy<Value = JobKind>) -> impl Strategy<Value = Job> {
+    let priority = prop_oneof![
+        Just(Priority::Low),
+        Just(Priority::Normal),
+        Just(Priority::High),
+        Just(Priority::Critical),
+    ];
+    let on_dependency_failure = prop_oneof![
+        Just(DependencyFailure::Cancel),
+        Just(DependencyFailure::Skip)
+    ];
+    let config = (
+        duration(),
+        0usize..10,
+        backend_config(),
+        on_dependency_failure,
+    )
+        .prop_map(
+            |(timeout, max_retries, backend, on_dependency_failure)| JobConfig {
+                timeout,
+                max_retries,
+                backend,
+                on_dependency_failure,
+                owner: None,
+                max_dim: None,
+                max_memory_mb: None,
+                max_steps: None,
+            },
+        );
+
+    let depends_on = vec(uuid(), 0..3);
+    (uuid(), kind, priority, params(), config, depends_on).prop_map(
+        |(id, kind, priority, params, config, depends_on)| Job {
+            id,
+            kind,
+            priority,
+            params,
+            config,
+            depends_on,
+        },
+    )
+}
+
+// ==================== Job results ====================
+
+fn diagnostics_summary() -> impl Strategy<Value = DiagnosticsSummary> {
+    (real(), real(), vec(text(), 0..3)).prop_map(|(max_trace_drift, min_eigenvalue, warnings)| {
+        DiagnosticsSummary {
+            max_trace_drift,
+            min_eigenvalue,
+            warnings,
+        }
+    })
+}
+
+fn job_result() -> impl Strategy<Value = JobResult> {
+    let leaf = prop_oneof![
+        (vec(vec(real(), 0..4), 0..3), diagnostics_summary()).prop_map(|(states, diagnostics)| {
+            JobOutput::Simulation(SimulationOutput {
+                states,
+                diagnostics,
+            })
+        }),
+        (any::<bool>(), 0usize..100, option::of("[0-9a-f]{64}")).prop_map(
+            |(proven, steps, certificate_hash)| JobOutput::Proof(ProofOutput {
+                proven,
+                steps,
+                certificate_hash,
+            })
+        ),
+        (vec(real(), 0..4), vec(real(), 0..4), real(), any::<bool>()).prop_map(
+            |(best_params, uncertainties, log_likelihood, truncated)| JobOutput::Fit(FitOutput {
+                best_params,
+                uncertainties,
+                log_likelihood,
+                truncated,
+            })
+        ),
+        (real(), real(), text()).prop_map(|(chi_square, p_value, decision)| {
+            JobOutput::Test(TestOutput {
+                chi_square,
+                p_value,
+                decision,
+            })
+        }),
+    ];
+    let output = leaf.prop_recursive(2, 8, 3, |inner| {
+        let summary = (0usize..100, 0usize..100, 0usize..100, duration()).prop_map(
+            |(total_runs, successful, failed, total_duration)| SweepSummary {
+                total_runs,
+                successful,
+                failed,
+                total_duration,
+            },
+        );
+        (
+            vec((params(), job_result_with(inner)), 0..3),
+            params(),
+            summary,
+        )
+            .prop_map(|(results, best_params, summary)| {
+                JobOutput::Sweep(SweepOutput {
+                    results,
+                    best_params,
+                    summary,
+                })
+            })
+    });
+    job_result_with(output)
+}
+
+fn job_result_with(output: impl Strategy<Value = JobOutput>) -> impl Strategy<Value = JobResult> {
+    (
+        uuid(),
+        (timestamp(), timestamp(), timestamp()),
+        (duration(), duration()),
+        output,
+    )
+        .prop_map(
+            |(job_id, (submitted_at, started_at, completed_at), (wait, duration), output)| {
+                JobResult {
+                    job_id,
+                    submitted_at,
+                    started_at,
+                    completed_at,
+                    wait,
+                    duration,
+                    output,
+                }
+            },
+        )
+}
+
+// ==================== IR ====================
+
+fn ir_node() -> impl Strategy<Value = IrNode> {
+    let id = 0usize..64;
+    let binary = || (0usize..64, 0usize..64, 0usize..64);
+    let lindblad_ops = || {
+        vec(
+            (0usize..64, real()).prop_map(|(operator, rate)| LindbladOperator { operator, rate }),
+            0..3,
+        )
+    };
+
+    prop_oneof![
+        (
+            id.clone(),
+            name(),
+            vec(complex(), 0..9),
+            (0usize..4, 0usize..4)
+        )
+            .prop_map(|(id, name, data, shape)| IrNode::LoadMatrix {
+                id,
+                name,
+                data,
+                shape,
+            }),
+        (id.clone(), name(), vec(complex(), 0..4))
+            .prop_map(|(id, name, data)| IrNode::LoadVector { id, name, data }),
+        (id.clone(), complex()).prop_map(|(id, value)| IrNode::Scalar { id, value }),
+        (id.clone(), name()).prop_map(|(id, name)| IrNode::Parameter { id, name }),
+        binary().prop_map(|(id, left, right)| IrNode::MatrixAdd { id, left, right }),
+        binary().prop_map(|(id, left, right)| IrNode::MatrixMul { id, left, right }),
+        binary().prop_map(|(id, scalar, matrix)| IrNode::ScalarMul { id, scalar, matrix }),
+        (id.clone(), 0usize..64).prop_map(|(id, input)| IrNode::MatrixExp { id, input }),
+        binary().prop_map(|(id, left, right)| IrNode::TensorProduct { id, left, right }),
+        (id.clone(), 0usize..64).prop_map(|(id, input)| IrNode::Dagger { id, input }),
+        (id.clone(), 0usize..64).prop_map(|(id, input)| IrNode::Trace { id, input }),
+        binary().prop_map(|(id, left, right)| IrNode::Commutator { id, left, right }),
+        (
+            id.clone(),
+            0usize..64,
+            option::of(vec(1usize..5, 1..4)),
+            vec(0usize..4, 0..3)
+        )
+            .prop_map(|(id, input, dims, keep)| IrNode::PartialTrace {
+                id,
+                input,
+                dims,
+                keep,
+            }),
+        (
+            id.clone(),
+            0usize..64,
+            prop_oneof![
+                Just(EigenOutput::Eigenvalues),
+                Just(EigenOutput::Eigenvectors),
+                (0usize..8).prop_map(EigenOutput::Eigenstate),
+            ]
+        )
+            .prop_map(|(id, input, output)| IrNode::Eigendecompose { id, input, output }),
+        (id.clone(), 0usize..64, real()).prop_map(|(id, hamiltonian, time)| {
+            IrNode::UnitaryPropagator {
+                id,
+                hamiltonian,
+                time,
+            }
+        }),
+        binary().prop_map(|(id, unitary, ket)| IrNode::ApplyUnitaryKet { id, unitary, ket }),
+        binary().prop_map(|(id, unitary, rho)| IrNode::ApplyUnitaryRho { id, unitary, rho }),
+        (id.clone(), 0usize..64, 0usize..64, vec(0usize..4, 0..3)).prop_map(
+            |(id, gate, state, targets)| IrNode::ApplyGate {
+                id,
+                gate,
+                state,
+                targets,
+            }
+        ),
+        (
+            id.clone(),
+            0usize..64,
+            0usize..64,
+            lindblad_ops(),
+            vec(real(), 0..4)
+        )
+            .prop_map(|(id, hamiltonian, initial_rho, lindblad_ops, times)| {
+                IrNode::IntegrateLindblad {
+                    id,
+                    hamiltonian,
+                    initial_rho,
+                    lindblad_ops,
+                    times,
+                }
+            }),
+        (id.clone(), vec(0usize..64, 0..3))
+            .prop_map(|(id, kraus)| IrNode::ApplyChannel { id, kraus }),
+        binary().prop_map(|(id, observable, state)| IrNode::MeasureExpectation {
+            id,
+            observable,
+            state,
+        }),
+        (id.clone(), vec(0usize..64, 0..3), 0usize..64).prop_map(|(id, projectors, state)| {
+            IrNode::MeasureProjective {
+                id,
+                projectors,
+                state,
+            }
+        }),
+        (id, name(), vec(0usize..64, 0..3)).prop_map(|(id, kernel, inputs)| IrNode::Custom {
+            id,
+            kernel,
+            inputs
+        }),
+    ]
+}
+
+fn ir_experiment() -> impl Strategy<Value = IrExperiment> {
+    let method = prop_oneof![
+        (0usize..64).prop_map(|hamiltonian| EvolutionMethod::Schrodinger { hamiltonian }),
+        (
+            0usize..64,
+            vec(
+                (0usize..64, real())
+                    .prop_map(|(operator, rate)| LindbladOperator { operator, rate }),
+                0..3
+            )
+        )
+            .prop_map(|(hamiltonian, operators)| EvolutionMethod::Lindblad {
+                hamiltonian,
+                operators,
+            }),
+    ];
+    let evolution =
+        (method, vec(real(), 0..5)).prop_map(|(method, times)| IrEvolution { method, times });
+    let measurement_type = prop_oneof![
+        vec(0usize..64, 0..3).prop_map(|projectors| MeasurementType::Projective { projectors }),
+        vec(0usize..64, 0..3).prop_map(|effects| MeasurementType::POVM { effects }),
+        (0usize..64).prop_map(|operator| MeasurementType::Observable { operator }),
+    ];
+    let measurement = (0usize..16, measurement_type, option::of(real())).prop_map(
+        |(time_index, measurement_type, time)| IrMeasurement {
+            time_index,
+            measurement_type,
+            time,
+        },
+    );
+    let channel = (0usize..16, 0usize..64).prop_map(|(time_index, channel)| IrChannelApplication {
+        time_index,
+        channel,
+    });
+    let observable =
+        (name(), 0usize..64).prop_map(|(label, operator)| IrObservable { label, operator });
+
+    (
+        name(),
+        0usize..64,
+        option::of(evolution),
+        vec(measurement, 0..3),
+        option::of(name()),
+        vec(channel, 0..3),
+        vec(observable, 0..3),
+    )
+        .prop_map(
+            |(name, initial_state, evolution, measurements, initial_from, channels, observables)| IrExperiment {
+                name,
+                initial_state,
+                evolution,
+                measurements,
+                initial_from,
+                channels,
+                observables,
+            },
+        )
+}
+
+fn ir_program() -> impl Strategy<Value = IrProgram> {
+    (
+        vec(ir_node(), 0..8),
+        vec(ir_experiment(), 0..3),
+        vec((name(), 0usize..64), 0..3),
+        vec((name(), 0usize..64), 0..3),
+    )
+        .prop_map(|(nodes, experiments, hamiltonians, constants)| IrProgram {
+            nodes,
+            experiments,
+            hamiltonians,
+            constants,
+        })
+}
+
+// ==================== Execution results ====================
+
+fn execution_result() -> impl Strategy<Value = ExecutionResult> {
+    let measurement =
+        (real(), vec(real(), 0..4)).prop_map(|(time, probabilities)| MeasurementResult {
+            time,
+            probabilities,
+        });
+    let residual =
+        (name(), real(), real()).prop_map(|(observable, max_residual, time)| EhrenfestResidual {
+            observable,
+            max_residual,
+            time,
+        });
+    let observable = (name(), vec(complex(), 0..5))
+        .prop_map(|(label, values)| ObservableSeries { label, values });
+    let state_type = prop_oneof![Just(StateType::PureState), Just(StateType::DensityMatrix)];
+    let experiment = (
+        name(),
+        vec(real(), 0..5),
+        state_type,
+        vec(measurement, 0..3),
+        option::of(diagnostics_summary()),
+        vec(residual, 0..2),
+        vec(observable, 0..2),
+    )
+        .prop_map(
+            |(name, times, state_type, measurements, diagnostics, ehrenfest, observables)| {
+                ExperimentResult {
+                    name,
+                    times,
+                    state_type,
+                    measurements,
+                    // Not serialized
+                    states: Vec::new(),
+                    diagnostics,
+                    ehrenfest,
+                    observables,
+                }
+            },
+        );
+
+    vec(experiment, 0..3).prop_map(|experiment_results| ExecutionResult { experiment_results })
+}
+
+// ==================== Proofs ====================
+
+fn expr() -> impl Strategy<Value = Expr> {
+    let leaf = prop_oneof![
+        real().prop_map(Expr::Number),
+        complex().prop_map(Expr::ComplexNumber),
+        name().prop_map(Expr::Identifier),
+    ];
+    leaf.prop_recursive(3, 16, 3, |inner| {
+        let pair = || (inner.clone(), inner.clone()).prop_map(|(a, b)| (Box::new(a), Box::new(b)));
+        let single = || inner.clone().prop_map(Box::new);
+        prop_oneof![
+            vec(vec(inner.clone(), 1..3), 1..3)
+                .prop_map(|rows| Expr::Matrix(MatrixLiteral { rows })),
+            vec(inner.clone(), 1..3).prop_map(|elements| Expr::Vector(VectorLiteral { elements })),
+            pair().prop_map(|(a, b)| Expr::Add(a, b)),
+            pair().prop_map(|(a, b)| Expr::Sub(a, b)),
+            pair().prop_map(|(a, b)| Expr::Mul(a, b)),
+            pair().prop_map(|(a, b)| Expr::Div(a, b)),
+            pair().prop_map(|(a, b)| Expr::Pow(a, b)),
+            single().prop_map(Expr::Dagger),
+            single().prop_map(Expr::Trace),
+            pair().prop_map(|(a, b)| Expr::Tensor(a, b)),
+            pair().prop_map(|(a, b)| Expr::Commutator(a, b)),
+            pair().prop_map(|(a, b)| Expr::AntiCommutator(a, b)),
+            single().prop_map(Expr::Expm),
+            single().prop_map(Expr::Sqrt),
+            single().prop_map(Expr::Sin),
+            single().prop_map(Expr::Cos),
+            single().prop_map(Expr::Exp),
+            (name(), vec(inner.clone(), 0..3))
+                .prop_map(|(name, args)| Expr::FuncCall { name, args }),
+        ]
+    })
+}
+
+fn assumption() -> impl Strategy<Value = Assumption> {
+    let sign = select(vec![
+        Sign::Positive,
+        Sign::Nonnegative,
+        Sign::Negative,
+        Sign::Nonpositive,
+        Sign::Nonzero,
+    ]);
+    let kind = prop_oneof![
+        (option::of(real()), option::of(real()))
+            .prop_map(|(min, max)| AssumptionKind::Range { min, max }),
+        sign.prop_map(AssumptionKind::Sign),
+        Just(AssumptionKind::Integer),
+        (expr(), expr()).prop_map(|(lhs, rhs)| AssumptionKind::Equation { lhs, rhs }),
+    ];
+    (name(), kind).prop_map(|(name, kind)| Assumption { name, kind })
+}
+
+fn proof() -> impl Strategy<Value = Proof> {
+    let rule = prop_oneof![
+        select(vec![
+            RewriteRule::DaggerDagger,
+            RewriteRule::DaggerSum,
+            RewriteRule::DaggerProduct,
+            RewriteRule::DaggerScalar,
+            RewriteRule::TraceCyclic,
+            RewriteRule::TraceLinear,
+            RewriteRule::TraceScalar,
+            RewriteRule::CommutatorSelf,
+            RewriteRule::CommutatorAnti,
+            RewriteRule::CommutatorLinear,
+            RewriteRule::JacobiIdentity,
+            RewriteRule::TensorDistribute,
+            RewriteRule::TensorAssoc,
+            RewriteRule::TensorDagger,
+            RewriteRule::PauliSquare,
+            RewriteRule::PauliCommutator,
+            RewriteRule::PauliAnticommutator,
+            RewriteRule::MultiplyZero,
+            RewriteRule::AddZero,
+            RewriteRule::MultiplyIdentity,
+            RewriteRule::SqrtSquare,
+            RewriteRule::SqrtProduct,
+            RewriteRule::Canonicalize,
+            RewriteRule::HermitianByConstruction,
+        ]),
+        text().prop_map(RewriteRule::Hypothesis),
+    ];
+    let step =
+        (rule, expr(), expr(), text()).prop_map(|(rule, before, after, justification)| ProofStep {
+            rule,
+            before,
+            after,
+            justification,
+        });
+    let verification_step =
+        (0usize..100, text(), any::<bool>()).prop_map(|(step_number, description, verified)| {
+            VerificationStep {
+                step_number,
+                description,
+                verified,
+                step: None,
+            }
+        });
+    let certificate = (
+        "[0-9a-f]{64}",
+        timestamp(),
+        "[0-9]\\.[0-9]\\.[0-9]",
+        vec(assumption(), 0..3),
+        vec(verification_step, 0..3),
+    )
+        .prop_map(
+            |(hash, timestamp, engine_version, assumptions, verification_steps)| Certificate {
+                hash,
+                timestamp,
+                engine_version,
+                assumptions,
+                verification_steps,
+                statement: None,
+                signature: None,
+            },
+        );
+
+    (
+        (expr(), expr()).prop_map(|(lhs, rhs)| Statement::Identity { lhs, rhs }),
+        vec(step, 0..4),
+        vec(assumption(), 0..3),
+        certificate,
+    )
+        .prop_map(|(statement, steps, assumptions_used, certificate)| Proof {
+            statement,
+            steps,
+            assumptions_used,
+            certificate,
+        })
+}
+
+// ==================== Stream events ====================
+
+fn stream_event() -> impl Strategy<Value = StreamEvent> {
+    let metadata = prop_oneof![
+        Just(serde_json::Value::Null),
+        (name(), 0u64..1000, real()).prop_map(|(run, index, value)| {
+            serde_json::json!({ "run": run, "index": index, "tags": [value] })
+        }),
+    ];
+    let data = (name(), real(), real(), metadata).prop_map(
+        |(observable, value, uncertainty, metadata)| DataPoint {
+            observable,
+            value,
+            uncertainty,
+            metadata,
+        },
+    );
+
+    prop_oneof![
+        (name(), timestamp(), data).prop_map(|(source_id, timestamp, data)| {
+            StreamEvent::NewData {
+                source_id,
+                timestamp,
+                data,
+            }
+        }),
+        name().prop_map(|source_id| StreamEvent::SourceConnected { source_id }),
+        (name(), text())
+            .prop_map(|(source_id, reason)| StreamEvent::SourceDisconnected { source_id, reason }),
+        (vec(real(), 0..4), vec(real(), 0..4), real()).prop_map(
+            |(params, uncertainties, log_likelihood)| StreamEvent::FitUpdated {
+                params,
+                uncertainties,
+                log_likelihood,
+            }
+        ),
+        (0usize..1000).prop_map(|count| StreamEvent::Dropped { count }),
+        (name(), real(), timestamp()).prop_map(|(param, magnitude, time)| {
+            StreamEvent::DriftDetected {
+                param,
+                magnitude,
+                time,
+            }
+        }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn job_round_trips(job in job()) {
+        assert_round_trips(&job)?;
+    }
+
+    #[test]
+    fn job_result_round_trips(result in job_result()) {
+        assert_round_trips(&result)?;
+    }
+
+    #[test]
+    fn ir_program_round_trips(program in ir_program()) {
+        assert_round_trips(&program)?;
+    }
+
+    #[test]
+    fn execution_result_round_trips(result in execution_result()) {
+        assert_round_trips(&result)?;
+    }
+
+    #[test]
+    fn proof_round_trips(proof in proof()) {
+        assert_round_trips(&proof)?;
+    }
+
+    #[test]
+    fn stream_event_round_trips(event in stream_event()) {
+        assert_round_trips(&event)?;
+    }
+}
@@ -0,0 +1,209 @@
+//! End-to-end tests for the HTTP job API
+//!
+//! Each test starts a real server on an ephemeral port and talks to it over
+//! TCP with an HTTP client, so routing, JSON encoding and the queue workers
+//! are all exercised together.
+
+use quantum_theory_engine::job_queue::{
+    GridStrategy, JobOutput, JobResult, JobStatus, ParameterGrid, ParameterRange, ParameterScale,
+};
+use quantum_theory_engine::server::{self, JobCreated, SweepCreated};
+use quantum_theory_engine::JobQueue;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Two-level Rabi oscillation at drive strength `omega`
+const RABI: &str = r#"
+    param omega;
+    matrix X = [0, 1; 1, 0];
+    Hamiltonian H = omega * X;
+    experiment rabi {
+        init: ket(vec(1, 0));
+        evolution: evolve(init, H, timegrid=(0.0, 0.1, 10));
+    }
+"#;
+
+struct TestServer {
+    base_url: String,
+    client: reqwest::Client,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+impl TestServer {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let queue = Arc::new(JobQueue::new(2));
+        let (tx, rx) = oneshot::channel::<()>();
+
+        let handle = tokio::spawn(async move {
+            server::serve_listener(listener, queue, async {
+                let _ = rx.await;
+            })
+            .await
+            .unwrap();
+        });
+
+        Self {
+            base_url: format!("http://{}", addr),
+            client: reqwest::Client::new(),
+            shutdown: Some(tx),
+            handle,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn wait_for_result(&self, job_id: uuid::Uuid) -> JobResult {
+        for _ in 0..100 {
+            let status: JobStatus = self
+                .client
+                .get(self.url(&format!("/jobs/{}/status", job_id)))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+
+            match status {
+                JobStatus::Complete(_) => {
+                    let response = self
+                        .client
+                        .get(self.url(&format!("/jobs/{}/result", job_id)))
+                        .send()
+                        .await
+                        .unwrap();
+                    assert_eq!(response.status(), 200);
+                    return response.json().await.unwrap();
+                }
+                JobStatus::Failed { error, .. } => panic!("job {} failed: {}", job_id, error),
+                JobStatus::Cancelled => panic!("job {} was cancelled", job_id),
+                _ => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+
+        panic!("job {} did not complete in time", job_id);
+    }
+
+    async fn stop(mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        tokio::time::timeout(Duration::from_secs(5), self.handle)
+            .await
+            .expect("server did not shut down")
+            .unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_submit_and_fetch_result_over_http() {
+    let server = TestServer::start().await;
+
+    let health = server
+        .client
+        .get(server.url("/health"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(health.status(), 200);
+
+    let response = server
+        .client
+        .post(server.url("/jobs"))
+        .json(&json!({
+            "kind": { "Prove": { "statement": "dagger(dagger(A)) == A" } },
+            "priority": "High"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let JobCreated { job_id } = response.json().await.unwrap();
+
+    let result = server.wait_for_result(job_id).await;
+    assert_eq!(result.job_id, job_id);
+    assert!(matches!(result.output, JobOutput::Proof(_)));
+
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sweep_over_http() {
+    let server = TestServer::start().await;
+
+    let grid = ParameterGrid {
+        params: vec![ParameterRange {
+            name: "omega".to_string(),
+            start: 0.5,
+            end: 1.5,
+            steps: 3,
+            scale: ParameterScale::Linear,
+        }],
+        strategy: GridStrategy::FullGrid,
+    };
+
+    let response = server
+        .client
+        .post(server.url("/sweeps"))
+        .json(&json!({
+            "base": { "kind": { "Simulate": { "program": RABI } } },
+            "grid": grid,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let SweepCreated { job_ids } = response.json().await.unwrap();
+    assert_eq!(job_ids.len(), 3);
+
+    for job_id in job_ids {
+        let result = server.wait_for_result(job_id).await;
+        assert!(matches!(result.output, JobOutput::Simulation(_)));
+    }
+
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_error_responses_over_http() {
+    let server = TestServer::start().await;
+    let missing = uuid::Uuid::new_v4();
+
+    let status = server
+        .client
+        .get(server.url(&format!("/jobs/{}/status", missing)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status.status(), 404);
+    let body: serde_json::Value = status.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("not found"));
+
+    let cancel = server
+        .client
+        .delete(server.url(&format!("/jobs/{}", missing)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cancel.status(), 404);
+
+    let malformed = server
+        .client
+        .post(server.url("/jobs"))
+        .json(&json!({ "kind": "NotAJobKind" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(malformed.status().is_client_error());
+
+    server.stop().await;
+}
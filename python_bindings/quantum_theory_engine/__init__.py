@@ -164,6 +164,96 @@ def run_simulation(
     return engine.run_simulation(model, params)
 
 
+# QuTiP interoperability
+def _import_qutip():
+    try:
+        import qutip
+    except ImportError as e:
+        raise ImportError("QuTiP conversion requires qutip: pip install qutip") from e
+    return qutip
+
+
+def _to_array(obj):
+    """Complex matrix of a Qobj or array-like"""
+    import numpy as np
+
+    return np.asarray(obj.full() if hasattr(obj, 'full') else obj, dtype=complex)
+
+
+def to_qutip(result, dims: Optional[List[int]] = None):
+    """Convert simulation output into QuTiP objects
+    
+    Args:
+        result: A matrix, or the dict returned by `simulate` or `from_qutip`
+        dims: Subsystem dimensions, e.g. [2, 2] for two qubits
+        
+    Returns:
+        A Qobj for a matrix; for a result, a dict with "experiments",
+        mapping each experiment name to its list of density-matrix Qobjs,
+        one per time, and "hamiltonians", mapping names to Qobjs
+    """
+    qutip = _import_qutip()
+    qobj_dims = [list(dims), list(dims)] if dims is not None else None
+
+    def qobj(matrix):
+        return qutip.Qobj(matrix, dims=qobj_dims)
+
+    if not isinstance(result, dict):
+        return qobj(result)
+    return {
+        'experiments': {
+            experiment['name']: [qobj(state) for state in experiment['states']]
+            for experiment in result['experiments']
+        },
+        'hamiltonians': {
+            name: qobj(matrix) for name, matrix in result.get('hamiltonians', {}).items()
+        },
+    }
+
+
+def simulate(dsl_code: str, params: Optional[Dict[str, float]] = None) -> Dict:
+    """Simulate DSL code
+    
+    Args:
+        dsl_code: Program source
+        params: Values of the program's run-time parameters
+        
+    Returns:
+        {"experiments": [{"name", "times", "states"}], "hamiltonians": {name: matrix}},
+        the states being density matrices stacked in an array of shape
+        (times, n, n)
+    """
+    from . import _core
+
+    return _core.simulate(dsl_code, params)
+
+
+def from_qutip(H, state, tlist, c_ops=()) -> Dict:
+    """Simulate a QuTiP problem with the engine, for cross-validation
+    
+    Takes the arguments of `qutip.mesolve(H, state, tlist, c_ops)`: a
+    constant Hamiltonian Qobj, a ket or density matrix, the output times
+    and collapse operators that carry the square roots of their rates.
+    Plain numpy arrays are accepted in place of Qobjs.
+    
+    Returns:
+        The dict of `simulate`, with the single experiment "qutip"; pass it
+        to `to_qutip` to compare states with `mesolve`'s
+    """
+    from . import _core
+
+    state = _to_array(state)
+    if state.ndim == 2 and state.shape[1] == 1:
+        # QuTiP kets are column matrices
+        state = state[:, 0]
+    return _core.simulate_matrices(
+        _to_array(H),
+        [_to_array(c_op) for c_op in c_ops],
+        state,
+        [float(t) for t in tlist],
+    )
+
+
 __all__ = [
     'QuantumTheoryEngine',
     'Model',
@@ -172,4 +262,7 @@ __all__ = [
     'TestResult',
     'load_model',
     'run_simulation',
+    'simulate',
+    'to_qutip',
+    'from_qutip',
 ]
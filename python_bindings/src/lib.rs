@@ -4,12 +4,23 @@
 
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
-use pyo3::exceptions::PyRuntimeError;
-use numpy::{PyArray1, PyArray2, ToPyArray};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray2, PyReadonlyArrayDyn, ToPyArray};
+use numpy::ndarray::Array3;
+use numpy::Complex64;
 use quantum_theory_engine::{
     JobQueue, Job, JobKind, Priority, JobConfig, JobStatus,
     TemplateRegistry, Template,
     LogLevel, Timer, HealthChecker,
+    BackendConfig, EngineError, Executor,
+    parse_dsl, lower, validate_quantum,
+};
+use quantum_theory_engine::executor::ExperimentResult;
+use quantum_theory_engine::ir::{
+    EvolutionMethod, IrEvolution, IrExperiment, IrNode, IrProgram, LindbladOperator,
+};
+use quantum_theory_engine::job_queue::{
+    GridStrategy, ParameterGrid, ParameterRange, ParameterScale, SweepOutput, SweepTable,
 };
 use std::collections::HashMap;
 
@@ -43,6 +54,7 @@ impl PyJob {
                 priority,
                 params: HashMap::new(),
                 config: JobConfig::default(),
+                depends_on: Vec::new(),
             },
         })
     }
@@ -117,6 +129,189 @@ impl PyJobQueue {
             .map(|(id, status)| (id.to_string(), format!("{:?}", status)))
             .collect())
     }
+
+    /// Submit `job` once per point of a grid over `params`, a dict of
+    /// name -> (start, end, steps); `strategy` is "grid" (default),
+    /// "random" or "sobol", the latter two drawing `samples` points;
+    /// `seed` makes the random points repeatable
+    fn submit_sweep(
+        &mut self,
+        job: &PyJob,
+        params: HashMap<String, (f64, f64, usize)>,
+        strategy: Option<&str>,
+        samples: Option<usize>,
+        log_scale: Option<Vec<String>>,
+        seed: Option<u64>,
+    ) -> PyResult<Vec<String>> {
+        let queue = self.queue.as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("Queue is closed"))?;
+
+        let samples = samples.unwrap_or(100);
+        let strategy = match strategy.unwrap_or("grid") {
+            "grid" => GridStrategy::FullGrid,
+            "random" => GridStrategy::Random { n: samples, seed },
+            "sobol" => GridStrategy::Sobol(samples),
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid sweep strategy '{}' (expected grid, random or sobol)",
+                    other
+                )))
+            }
+        };
+        let log_scale = log_scale.unwrap_or_default();
+        let mut ranges: Vec<ParameterRange> = params
+            .into_iter()
+            .map(|(name, (start, end, steps))| ParameterRange {
+                scale: if log_scale.contains(&name) { ParameterScale::Log } else { ParameterScale::Linear },
+                name,
+                start,
+                end,
+                steps,
+            })
+            .collect();
+        ranges.sort_by(|a, b| a.name.cmp(&b.name));
+        let grid = ParameterGrid { params: ranges, strategy };
+
+        let job_ids = self.runtime.block_on(async {
+            queue.submit_sweep(job.inner.clone(), grid).await
+        }).map_err(|e| PyRuntimeError::new_err(format!("{:?}", e)))?;
+
+        Ok(job_ids.into_iter().map(|id| id.to_string()).collect())
+    }
+
+    /// Collect the results of the sweep jobs `job_ids` that have finished
+    fn aggregate_sweep(&self, job_ids: Vec<String>) -> PyResult<PySweep> {
+        let queue = self.queue.as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("Queue is closed"))?;
+
+        let uuids = job_ids
+            .iter()
+            .map(|id| uuid::Uuid::parse_str(id))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PyRuntimeError::new_err(format!("Invalid job ID: {}", e)))?;
+
+        let output = self.runtime.block_on(async {
+            queue.aggregate_sweep(&uuids).await
+        }).map_err(|e| PyRuntimeError::new_err(format!("{:?}", e)))?;
+
+        Ok(PySweep {
+            table: output.table(),
+            output,
+        })
+    }
+}
+
+/// Aggregated sweep: the parameter grid with the objective of each run,
+/// the negated log-likelihood of fits and the χ² of tests (NaN otherwise)
+#[pyclass]
+struct PySweep {
+    output: SweepOutput,
+    table: SweepTable,
+}
+
+impl PySweep {
+    /// Structured array of `rows` with one field per parameter and
+    /// `objective`
+    fn records<'py>(&self, py: Python<'py>, rows: &[usize]) -> PyResult<&'py PyAny> {
+        let mut columns: Vec<PyObject> = (0..self.table.names.len())
+            .map(|k| {
+                let column: Vec<f64> = rows.iter().map(|&row| self.table.points[row][k]).collect();
+                column.to_pyarray(py).to_object(py)
+            })
+            .collect();
+        let objective: Vec<f64> = rows.iter().map(|&row| self.table.objective[row]).collect();
+        columns.push(objective.to_pyarray(py).to_object(py));
+
+        let mut names = self.table.names.clone();
+        names.push("objective".to_string());
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("names", names)?;
+        py.import("numpy")?
+            .getattr("rec")?
+            .call_method("fromarrays", (columns,), Some(kwargs))
+    }
+}
+
+#[pymethods]
+impl PySweep {
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        self.table.names.clone()
+    }
+
+    /// Runs × parameters array of the grid points
+    #[getter]
+    fn points<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray2<f64>> {
+        PyArray2::from_vec2(py, &self.table.points)
+            .map_err(|e| PyRuntimeError::new_err(format!("{}", e)))
+    }
+
+    #[getter]
+    fn objective<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        self.table.objective.to_pyarray(py)
+    }
+
+    /// Parameters of the fit with the highest log-likelihood
+    #[getter]
+    fn best_params(&self) -> HashMap<String, f64> {
+        self.output.best_params.clone()
+    }
+
+    #[getter]
+    fn summary(&self, py: Python) -> PyResult<PyObject> {
+        let summary = &self.output.summary;
+        let dict = PyDict::new(py);
+        dict.set_item("total_runs", summary.total_runs)?;
+        dict.set_item("successful", summary.successful)?;
+        dict.set_item("failed", summary.failed)?;
+        dict.set_item("total_seconds", summary.total_duration.as_secs_f64())?;
+        Ok(dict.into())
+    }
+
+    /// Every run as a structured array with the parameters and `objective`
+    fn table<'py>(&self, py: Python<'py>) -> PyResult<&'py PyAny> {
+        let rows: Vec<usize> = (0..self.table.objective.len()).collect();
+        self.records(py, &rows)
+    }
+
+    /// Run with the smallest objective, as a dict, or None
+    fn argmin(&self) -> Option<HashMap<String, f64>> {
+        let row = self.table.argmin()?;
+        let mut point: HashMap<String, f64> = self
+            .table
+            .names
+            .iter()
+            .cloned()
+            .zip(self.table.points[row].iter().copied())
+            .collect();
+        point.insert("objective".to_string(), self.table.objective[row]);
+        Some(point)
+    }
+
+    /// Local minima of the objective on the grid, best first, as a
+    /// structured array
+    fn minima<'py>(&self, py: Python<'py>) -> PyResult<&'py PyAny> {
+        self.records(py, &self.table.local_minima())
+    }
+
+    /// Profile of the objective over `x`, minimised over the other
+    /// parameters: `(xs, values)`, or with `y` as well
+    /// `(xs, ys, values)` with `values[i, j]` at `(xs[i], ys[j])`
+    fn profile(&self, py: Python, x: &str, y: Option<&str>) -> PyResult<PyObject> {
+        let names: Vec<&str> = std::iter::once(x).chain(y).collect();
+        let profile = self.table
+            .profile(&names)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        let xs = profile.axes[0].to_pyarray(py);
+        match profile.axes.get(1) {
+            None => Ok((xs, profile.values.to_pyarray(py)).to_object(py)),
+            Some(ys) => {
+                let values = profile.values.to_pyarray(py).reshape([profile.axes[0].len(), ys.len()])?;
+                Ok((xs, ys.to_pyarray(py), values).to_object(py))
+            }
+        }
+    }
 }
 
 /// Python wrapper for TemplateRegistry
@@ -291,8 +486,26 @@ fn fit_mle<'py>(
     model_fn: PyObject,
     initial_params: Vec<f64>,
     max_iterations: Option<usize>,
+    budget: Option<f64>,
+    method: Option<&str>,
+    bounds: Option<Vec<(f64, f64)>>,
 ) -> PyResult<&'py PyDict> {
-    use quantum_theory_engine::stats::{gaussian_log_likelihood, fit_parameters_mle};
+    use quantum_theory_engine::stats::{
+        gaussian_log_likelihood, fit_parameters_mle_with, FitMethod, FitOptions,
+    };
+
+    let options = FitOptions {
+        method: match method {
+            Some(name) => name
+                .parse::<FitMethod>()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+            None => FitMethod::default(),
+        },
+        bounds,
+        max_iterations: max_iterations.unwrap_or(100),
+        budget: budget.map(std::time::Duration::from_secs_f64),
+        ..FitOptions::default()
+    };
     
     // Create closure that calls Python model function
     let likelihood_fn = |params: &[f64]| -> quantum_theory_engine::error::Result<f64> {
@@ -308,11 +521,8 @@ fn fit_mle<'py>(
         })
     };
     
-    let result = fit_parameters_mle(
-        likelihood_fn,
-        &initial_params,
-        max_iterations.unwrap_or(100),
-    ).map_err(|e| PyRuntimeError::new_err(format!("{:?}", e)))?;
+    let result = fit_parameters_mle_with(likelihood_fn, &initial_params, &options)
+        .map_err(|e| PyRuntimeError::new_err(format!("{:?}", e)))?;
     
     let dict = PyDict::new(py);
     dict.set_item("best_params", result.best_params.to_pyarray(py))?;
@@ -320,6 +530,7 @@ fn fit_mle<'py>(
     dict.set_item("log_likelihood", result.log_likelihood)?;
     dict.set_item("converged", result.converged)?;
     dict.set_item("iterations", result.iterations)?;
+    dict.set_item("truncated", result.truncated)?;
     
     Ok(dict)
 }
@@ -347,11 +558,166 @@ fn load_measurements(py: Python, csv_path: String) -> PyResult<PyObject> {
     Ok(dict.into())
 }
 
+/// Times and density matrices of one experiment, the latter as a complex
+/// array of shape (times, n, n)
+fn experiment_dict(py: Python, experiment: &ExperimentResult) -> PyResult<PyObject> {
+    let n = experiment.states.first().map_or(0, |state| state.nrows());
+    let mut states = Array3::<Complex64>::zeros((experiment.states.len(), n, n));
+    for (mut slot, state) in states.outer_iter_mut().zip(&experiment.states) {
+        slot.assign(state);
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("name", &experiment.name)?;
+    dict.set_item("times", experiment.times.to_pyarray(py))?;
+    dict.set_item("states", states.to_pyarray(py))?;
+    Ok(dict.into())
+}
+
+fn engine_error(e: EngineError) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// `ir` with its run-time parameters replaced by the constants `params`
+fn bind_parameters(mut ir: IrProgram, params: &HashMap<String, f64>) -> IrProgram {
+    for node in &mut ir.nodes {
+        if let IrNode::Parameter { id, name } = node {
+            if let Some(&value) = params.get(name.as_str()) {
+                *node = IrNode::Scalar { id: *id, value: Complex64::new(value, 0.0) };
+            }
+        }
+    }
+    ir
+}
+
+/// Simulate DSL code with `params` for its run-time parameters, returning
+/// {"experiments": [{name, times, states}], "hamiltonians": {name: matrix}}
+/// with every matrix a complex numpy array; `to_qutip` turns the result
+/// into QuTiP objects
+#[pyfunction]
+fn simulate(py: Python, dsl_code: String, params: Option<HashMap<String, f64>>) -> PyResult<PyObject> {
+    let params = params.unwrap_or_default();
+    let ast = parse_dsl(&dsl_code).map_err(engine_error)?;
+    let validated = validate_quantum(&ast).map_err(engine_error)?;
+    let ir = lower(&validated).map_err(engine_error)?;
+
+    let mut executor = Executor::new(BackendConfig::default());
+    let result = executor.execute_with_params(&ir, &params).map_err(engine_error)?;
+    let hamiltonians = executor
+        .hamiltonians(&bind_parameters(ir, &params))
+        .map_err(engine_error)?;
+
+    let experiments = PyList::empty(py);
+    for experiment in &result.experiment_results {
+        experiments.append(experiment_dict(py, experiment)?)?;
+    }
+    let matrices = PyDict::new(py);
+    for (name, matrix) in hamiltonians {
+        matrices.set_item(name, matrix.to_pyarray(py))?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("experiments", experiments)?;
+    dict.set_item("hamiltonians", matrices)?;
+    Ok(dict.into())
+}
+
+/// Evolve `state`, a ket or density matrix, under the Hamiltonian
+/// `hamiltonian` and the collapse operators `c_ops` over `times`, with
+/// QuTiP's convention that each collapse operator carries the square root
+/// of its rate; `from_qutip` calls this with the matrices of Qobjs. The
+/// matrices are loaded as IR constants, so the run takes the engine's own
+/// integrators, and the result has the shape of `simulate`'s
+#[pyfunction]
+fn simulate_matrices(
+    py: Python,
+    hamiltonian: PyReadonlyArray2<Complex64>,
+    c_ops: Vec<PyReadonlyArray2<Complex64>>,
+    state: PyReadonlyArrayDyn<Complex64>,
+    times: Vec<f64>,
+) -> PyResult<PyObject> {
+    fn load_matrix(ir: &mut IrProgram, name: String, matrix: &PyReadonlyArray2<Complex64>) -> usize {
+        let matrix = matrix.as_array();
+        let id = ir.nodes.len();
+        ir.add_node(IrNode::LoadMatrix {
+            id,
+            name,
+            data: matrix.iter().copied().collect(),
+            shape: matrix.dim(),
+        })
+    }
+
+    let mut ir = IrProgram::new();
+    let h = load_matrix(&mut ir, "H".to_string(), &hamiltonian);
+    ir.hamiltonians.push(("H".to_string(), h));
+    let operators: Vec<LindbladOperator> = c_ops
+        .iter()
+        .enumerate()
+        .map(|(k, c_op)| LindbladOperator {
+            operator: load_matrix(&mut ir, format!("c_ops[{}]", k), c_op),
+            rate: 1.0,
+        })
+        .collect();
+
+    let state = state.as_array();
+    let id = ir.nodes.len();
+    let initial_state = match state.shape() {
+        [_] => ir.add_node(IrNode::LoadVector {
+            id,
+            name: "state".to_string(),
+            data: state.iter().copied().collect(),
+        }),
+        &[rows, cols] => ir.add_node(IrNode::LoadMatrix {
+            id,
+            name: "state".to_string(),
+            data: state.iter().copied().collect(),
+            shape: (rows, cols),
+        }),
+        shape => {
+            return Err(PyValueError::new_err(format!(
+                "state must be a ket or a density matrix, got an array of shape {:?}",
+                shape
+            )))
+        }
+    };
+
+    let method = if operators.is_empty() {
+        EvolutionMethod::Schrodinger { hamiltonian: h }
+    } else {
+        EvolutionMethod::Lindblad { hamiltonian: h, operators }
+    };
+    ir.experiments.push(IrExperiment {
+        name: "qutip".to_string(),
+        initial_state,
+        evolution: Some(IrEvolution { method, times }),
+        measurements: Vec::new(),
+        initial_from: None,
+        channels: Vec::new(),
+        observables: Vec::new(),
+    });
+
+    let mut executor = Executor::new(BackendConfig::default());
+    let result = executor.execute(&ir).map_err(engine_error)?;
+
+    let experiments = PyList::empty(py);
+    for experiment in &result.experiment_results {
+        experiments.append(experiment_dict(py, experiment)?)?;
+    }
+    let matrices = PyDict::new(py);
+    matrices.set_item("H", hamiltonian.as_array().to_pyarray(py))?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("experiments", experiments)?;
+    dict.set_item("hamiltonians", matrices)?;
+    Ok(dict.into())
+}
+
 /// Python module definition
 #[pymodule]
 fn quantum_theory_engine(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyJob>()?;
     m.add_class::<PyJobQueue>()?;
+    m.add_class::<PySweep>()?;
     m.add_class::<PyTemplateRegistry>()?;
     m.add_class::<PyTemplate>()?;
     
@@ -363,6 +729,8 @@ fn quantum_theory_engine(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(health_check, m)?)?;
     m.add_function(wrap_pyfunction!(fit_mle, m)?)?;
     m.add_function(wrap_pyfunction!(load_measurements, m)?)?;
+    m.add_function(wrap_pyfunction!(simulate, m)?)?;
+    m.add_function(wrap_pyfunction!(simulate_matrices, m)?)?;
     
     Ok(())
 }
@@ -381,6 +749,9 @@ mod tests {
             
             assert!(module.hasattr("execute").unwrap());
             assert!(module.hasattr("PyJobQueue").unwrap());
+            assert!(module.hasattr("PySweep").unwrap());
+            assert!(module.hasattr("simulate").unwrap());
+            assert!(module.hasattr("simulate_matrices").unwrap());
         });
     }
 }